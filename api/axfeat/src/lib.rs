@@ -7,6 +7,9 @@
 //!     - `fp_simd`: Enable floating point and SIMD support.
 //! - Interrupts:
 //!     - `irq`: Enable interrupt handling support.
+//! - Clock source
+//!     - `virtual-clock`: Use a virtual clock advanced only by the kernel test API
+//!       (`axtask::time_test::advance`) instead of the hardware timer.
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `alloc-tlsf`: Use the TLSF allocator.
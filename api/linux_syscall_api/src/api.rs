@@ -7,9 +7,10 @@ use axhal::{
     KERNEL_PROCESS_ID,
 };
 use axprocess::{wait_pid, yield_now_task, Process, PID2PC};
-use axruntime::KERNEL_PAGE_TABLE;
+use axruntime::kernel_page_table_root;
 use axtask::{TaskId, EXITED_TASKS};
 
+use axerrno::AxResult;
 use axfs::api::OpenFlags;
 
 /// 在完成一次系统调用之后，恢复全局目录
@@ -40,7 +41,7 @@ pub fn recycle_user_process() {
     }
     TaskId::clear();
     unsafe {
-        write_page_table_root(KERNEL_PAGE_TABLE.root_paddr());
+        write_page_table_root(kernel_page_table_root());
         flush_tlb(None);
     };
     EXITED_TASKS.lock().clear();
@@ -61,19 +62,36 @@ pub fn run_testcase(testcase: &str, envs: Vec<String>) {
         args_vec.push(arg.to_string());
     }
 
-    let user_process = Process::init(args_vec, &envs).unwrap();
-    let now_process_id = user_process.get_process_id() as isize;
-    let mut exit_code = 0;
-    loop {
-        if unsafe { wait_pid(now_process_id, &mut exit_code as *mut i32) }.is_ok() {
-            break;
-        }
-        yield_now_task();
-    }
-    recycle_user_process();
+    let exit_code = run_process(args_vec, envs).unwrap();
     axlog::ax_println!(
         "Testcase {} finished with exit code {}",
         testcase,
         exit_code
     );
 }
+
+/// 以给定的参数与环境变量运行用户程序，等待其退出并回收所有用户进程，返回退出码
+///
+/// 程序无法加载时返回错误，此时不会创建进程
+pub fn run_process(args: Vec<String>, envs: Vec<String>) -> AxResult<i32> {
+    let user_process = match Process::init(args, &envs) {
+        Ok(user_process) => user_process,
+        Err(err) => {
+            // 加载失败时已切换到新建的页表上，需要切换回内核页表
+            unsafe {
+                write_page_table_root(kernel_page_table_root());
+                flush_tlb(None);
+            };
+            return Err(err);
+        }
+    };
+    let now_process_id = user_process.get_process_id() as isize;
+    let exit_code = loop {
+        if let Ok(process) = wait_pid(now_process_id) {
+            break process.get_exit_code();
+        }
+        yield_now_task();
+    };
+    recycle_user_process();
+    Ok(exit_code)
+}
@@ -1,6 +1,7 @@
+use alloc::vec::Vec;
 use axhal::{
     paging::MappingFlags,
-    time::{current_time_nanos, nanos_to_ticks, MICROS_PER_SEC, NANOS_PER_MICROS, NANOS_PER_SEC},
+    time::{current_time_nanos, Duration, MICROS_PER_SEC, NANOS_PER_MICROS, NANOS_PER_SEC},
 };
 use bitflags::*;
 use core::panic;
@@ -32,7 +33,7 @@ pub struct Tms {
 
 /// sys_gettimeofday 中指定的类型
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct TimeVal {
     /// seconds
     pub sec: usize,
@@ -40,13 +41,38 @@ pub struct TimeVal {
     pub usec: usize,
 }
 
+/// sys_wait4 与 sys_getrusage 返回的资源使用情况，与 Linux 的 struct rusage 布局相同
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rusage {
+    /// 用户态执行时间
+    pub ru_utime: TimeVal,
+    /// 内核态执行时间
+    pub ru_stime: TimeVal,
+    /// 驻留内存的峰值，单位为 KB
+    pub ru_maxrss: isize,
+    /// ru_ixrss 至 ru_nsignals，目前均不统计，保持为 0
+    pub ru_unused: [isize; 11],
+    /// 自愿上下文切换的次数，仅在启用 `sched_trace` 时统计
+    pub ru_nvcsw: isize,
+    /// 非自愿上下文切换的次数，仅在启用 `sched_trace` 时统计
+    pub ru_nivcsw: isize,
+}
+
 impl TimeVal {
-    /// turn the TimeVal to nano seconds
+    /// turn the TimeVal to nano seconds, saturating on overflow
     pub fn turn_to_nanos(&self) -> usize {
-        self.sec * NANOS_PER_SEC as usize + self.usec * NANOS_PER_MICROS as usize
+        self.sec
+            .saturating_mul(NANOS_PER_SEC as usize)
+            .saturating_add(self.usec.saturating_mul(NANOS_PER_MICROS as usize))
+    }
+
+    /// turn the TimeVal to a Duration, saturating on overflow
+    pub fn to_duration(&self) -> Duration {
+        Duration::from_secs(self.sec as u64).saturating_add(Duration::from_micros(self.usec as u64))
     }
 
-    /// create a TimeVal from nano seconds
+    /// create a TimeVal from micro seconds
     pub fn from_micro(micro: usize) -> Self {
         TimeVal {
             sec: micro / (MICROS_PER_SEC as usize),
@@ -54,10 +80,12 @@ impl TimeVal {
         }
     }
 
-    /// turn the TimeVal to cpu ticks, which is related to cpu frequency
-    pub fn turn_to_ticks(&self) -> u64 {
-        (self.sec * axconfig::TIMER_FREQUENCY) as u64
-            + nanos_to_ticks((self.usec as u64) * NANOS_PER_MICROS)
+    /// create a TimeVal from a Duration, truncated to micro seconds
+    pub fn from_duration(dur: Duration) -> Self {
+        TimeVal {
+            sec: dur.as_secs() as usize,
+            usec: dur.subsec_micros() as usize,
+        }
     }
 }
 
@@ -87,20 +115,36 @@ pub const UTIME_OMIT: usize = 0x3ffffffe;
 impl TimeSecs {
     /// 根据当前的时间构造一个 TimeSecs
     pub fn now() -> Self {
-        let nano = current_time_nanos() as usize;
-        let tv_sec = nano / NSEC_PER_SEC;
-        let tv_nsec = nano - tv_sec * NSEC_PER_SEC;
-        TimeSecs { tv_sec, tv_nsec }
+        Self::from_nanos(current_time_nanos() as usize)
+    }
+
+    /// 根据以纳秒为单位的时间构造一个 TimeSecs
+    pub fn from_nanos(nanos: usize) -> Self {
+        TimeSecs {
+            tv_sec: nanos / NSEC_PER_SEC,
+            tv_nsec: nanos % NSEC_PER_SEC,
+        }
     }
 
-    /// turn the TimeSecs to nano seconds
+    /// 根据 Duration 构造一个 TimeSecs
+    pub fn from_duration(dur: Duration) -> Self {
+        TimeSecs {
+            tv_sec: dur.as_secs() as usize,
+            tv_nsec: dur.subsec_nanos() as usize,
+        }
+    }
+
+    /// turn the TimeSecs to nano seconds, saturating on overflow
     pub fn turn_to_nanos(&self) -> usize {
-        self.tv_sec * NSEC_PER_SEC + self.tv_nsec
+        self.tv_sec
+            .saturating_mul(NSEC_PER_SEC)
+            .saturating_add(self.tv_nsec)
     }
 
-    /// turn the TimeSecs to cpu ticks, which is related to cpu frequency
-    pub fn get_ticks(&self) -> usize {
-        self.tv_sec * axconfig::TIMER_FREQUENCY + (nanos_to_ticks(self.tv_nsec as u64) as usize)
+    /// turn the TimeSecs to a Duration, saturating on overflow
+    pub fn to_duration(&self) -> Duration {
+        Duration::from_secs(self.tv_sec as u64)
+            .saturating_add(Duration::from_nanos(self.tv_nsec as u64))
     }
 
     /// set the Timesecs to the given time
@@ -134,14 +178,16 @@ bitflags! {
     }
 }
 
+/// PROT_NONE 只得到 USER 标志，这样的区域不可访问
 impl From<MMAPPROT> for MappingFlags {
     fn from(value: MMAPPROT) -> Self {
         let mut flags = MappingFlags::USER;
         if value.contains(MMAPPROT::PROT_READ) {
             flags |= MappingFlags::READ;
         }
+        // 与 Linux 一致，可写的页面总是可读（RISC-V 的页表项也不允许只写）
         if value.contains(MMAPPROT::PROT_WRITE) {
-            flags |= MappingFlags::WRITE;
+            flags |= MappingFlags::READ | MappingFlags::WRITE;
         }
         if value.contains(MMAPPROT::PROT_EXEC) {
             flags |= MappingFlags::EXECUTE;
@@ -207,6 +253,9 @@ impl UtsName {
     }
 }
 
+/// getrandom 的标志位，随机数源尚未初始化时不等待，直接返回 EAGAIN
+pub const GRND_NONBLOCK: usize = 1;
+
 /// specifies the size in bytes of the signal sets in set and oldset, which is equal to sizeof(kernel_sigset_t)
 pub const SIGSET_SIZE_IN_BYTE: usize = 8;
 
@@ -234,6 +283,7 @@ impl SigMaskFlag {
 
 /// sys_prlimit64 使用的数组
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct RLimit {
     /// 软上限
     pub rlim_cur: u64,
@@ -258,6 +308,7 @@ pub struct RobustList {
 
 /// readv/writev使用的结构体
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct IoVec {
     /// base address of the buffer
     pub base: *mut u8,
@@ -272,6 +323,8 @@ pub enum FutexFlags {
     Wake,
     /// 将等待 uaddr 的线程移动到 uaddr2
     Requeue,
+    /// 确认 uaddr 处的值之后再将等待 uaddr 的线程移动到 uaddr2
+    CmpRequeue,
     /// 不支持的操作
     Unsupported,
 }
@@ -283,6 +336,7 @@ impl FutexFlags {
             0 => FutexFlags::Wait,
             1 => FutexFlags::Wake,
             3 => FutexFlags::Requeue,
+            4 => FutexFlags::CmpRequeue,
             _ => FutexFlags::Unsupported,
         }
     }
@@ -304,14 +358,30 @@ numeric_enum_macro::numeric_enum! {
         F_GETFL = 3,
         /// 设置 flags 信息
         F_SETFL = 4,
+        /// 设置接收信号驱动 I/O 信号的进程
+        F_SETOWN = 8,
+        /// 获取接收信号驱动 I/O 信号的进程
+        F_GETOWN = 9,
+        /// 设置信号驱动 I/O 发送的信号
+        F_SETSIG = 10,
+        /// 获取信号驱动 I/O 发送的信号
+        F_GETSIG = 11,
         /// 复制 fd，然后设置 cloexec 信息，即 exec 成功时删除该 fd
         F_DUPFD_CLOEXEC = 1030,
+        /// 修改管道缓冲区的容量
+        F_SETPIPE_SZ = 1031,
+        /// 获取管道缓冲区的容量
+        F_GETPIPE_SZ = 1032,
+        /// 为 memfd 加上封印
+        F_ADD_SEALS = 1033,
+        /// 获取 memfd 的封印
+        F_GET_SEALS = 1034,
     }
 }
 
 /// syscall_info 用到的 结构体
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct SysInfo {
     /// 启动时间(以秒计)
     pub uptime: isize,
@@ -379,12 +449,20 @@ numeric_enum_macro::numeric_enum! {
     #[repr(usize)]
     #[allow(non_camel_case_types)]
     #[derive(PartialEq,Eq)]
-    /// sys_fcntl64 使用的选项
+    /// clock_gettime 等系统调用使用的时钟
     pub enum ClockId {
         /// real-time clock
         CLOCK_REALTIME = 0,
         /// monotonic clock
         CLOCK_MONOTONIC = 1,
+        /// CPU time consumed by all threads of the process
+        CLOCK_PROCESS_CPUTIME_ID = 2,
+        /// CPU time consumed by the calling thread
+        CLOCK_THREAD_CPUTIME_ID = 3,
+        /// monotonic clock that is not subject to frequency adjustments
+        CLOCK_MONOTONIC_RAW = 4,
+        /// monotonic clock that includes time that the system is suspended
+        CLOCK_BOOTTIME = 7,
     }
 }
 
@@ -432,12 +510,22 @@ impl DirEnt {
     pub fn fixed_size() -> usize {
         8 + 8 + 2 + 1
     }
-    /// 设置定长部分
-    pub fn set_fixed_part(&mut self, ino: u64, off: u64, reclen: usize, type_: DirEntType) {
-        self.d_ino = ino;
-        self.d_off = off;
-        self.d_reclen = reclen as u16;
-        self.d_type = type_ as u8;
+    /// 将长度为 `reclen` 的目录项追加到 `buf` 末尾，文件名之后直到目录项结束都填 0
+    pub fn append_to(
+        buf: &mut Vec<u8>,
+        ino: u64,
+        off: u64,
+        reclen: usize,
+        type_: DirEntType,
+        name: &[u8],
+    ) {
+        let start = buf.len();
+        buf.extend_from_slice(&ino.to_ne_bytes());
+        buf.extend_from_slice(&off.to_ne_bytes());
+        buf.extend_from_slice(&(reclen as u16).to_ne_bytes());
+        buf.push(type_ as u8);
+        buf.extend_from_slice(name);
+        buf.resize(start + reclen, 0);
     }
 }
 bitflags! {
@@ -512,6 +600,10 @@ bitflags! {
         const S_IFDIR = 1 << 14;
         /// character device
         const S_IFCHR = 1 << 13;
+        /// FIFO, i.e. pipe
+        const S_IFIFO = 1 << 12;
+        /// socket
+        const S_IFSOCK = (1 << 15) | (1 << 14);
         /// 是否设置 uid/gid/sticky
         //const S_ISUID = 1 << 14;
         //const S_ISGID = 1 << 13;
@@ -565,7 +657,7 @@ numeric_enum_macro::numeric_enum! {
 }
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 /// sys_clone3 中使用的结构体
 pub struct CloneArgs {
     /// 符号位，对应 axprocess 的 CloneFlags
@@ -3,6 +3,8 @@
 #![feature(stmt_expr_attributes)]
 mod ctypes;
 use ctypes::*;
+#[cfg(feature = "selftest")]
+mod selftest;
 mod syscall;
 mod syscall_fs;
 mod syscall_mem;
@@ -11,6 +13,7 @@ mod syscall_task;
 
 pub use axfs::api::{File, OpenFlags};
 pub use axprocess::link::{create_link, FilePath};
+pub use syscall::{register_syscall, SyscallHandler, SyscallTable, MAX_SYSCALL_ID};
 pub use syscall_fs::new_file;
 
 mod api;
@@ -34,9 +37,28 @@ pub type SyscallError = axerrno::LinuxError;
 pub type SyscallResult = Result<isize, SyscallError>;
 
 /// Accept the result of a syscall, and return the isize to the user
+///
+/// 所有系统调用均返回 [`SyscallResult`]，只在此处统一转换为返回给用户的值：
+/// 错误码取负后返回，写入寄存器时即为符号扩展后的负数
 pub(crate) fn deal_result(result: SyscallResult) -> isize {
     match result {
         Ok(x) => x,
         Err(error) => -(error.code() as isize),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{deal_result, SyscallError};
+
+    #[test]
+    fn test_deal_result() {
+        assert_eq!(deal_result(Ok(5)) as usize, 5);
+        // 错误码在寄存器中应为符号扩展后的负数
+        let reg = deal_result(Err(SyscallError::EBADF)) as usize;
+        assert_eq!(reg, usize::MAX - 8);
+        assert_eq!(reg as isize, -9);
+        let reg = deal_result(Err(SyscallError::ENOSYS)) as usize;
+        assert_eq!(reg, (-38isize) as usize);
+    }
+}
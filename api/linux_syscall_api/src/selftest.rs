@@ -0,0 +1,92 @@
+//! 内核自测用的系统调用
+//!
+//! 仅在启用 `selftest` feature 时编译，供 apps/c 下的测例检查无法从用户态直接观察的内核行为。
+//! 调用号 [`SELFTEST_SYSCALL_ID`] 在各架构上均未被 Linux 使用，第一个参数为子命令：
+//!
+//! - [`KERNEL_MAP`]：分配一个内核页并写入 [`KERNEL_MAP_MAGIC`]，通过 [`axmem::map_kernel_region`]
+//!   映射到一个此前未使用的内核顶级页表项中
+//! - [`KERNEL_READ`]：在当前进程的地址空间中经由上述内核地址读出该值并返回
+//!
+//! 两者配合可以检查：在某个进程 fork 之后新增的内核映射，对该进程的系统调用上下文同样可见
+use core::{
+    alloc::Layout,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use axhal::{
+    mem::{virt_to_phys, VirtAddr, PAGE_SIZE_4K},
+    paging::MappingFlags,
+};
+use spinlock::SpinNoIrq;
+
+use crate::{SyscallError, SyscallResult};
+
+/// 自测系统调用的调用号
+pub(crate) const SELFTEST_SYSCALL_ID: usize = 500;
+
+/// 子命令：新增一个内核映射
+const KERNEL_MAP: usize = 0;
+/// 子命令：读出新增的内核映射中的值
+const KERNEL_READ: usize = 1;
+
+/// 写入新增内核映射中的值
+const KERNEL_MAP_MAGIC: u32 = 0x5a5a_c0de;
+
+/// 新增内核映射的地址，位于一个在启动时没有被任何内核映射用到的顶级页表项中
+#[cfg(target_arch = "x86_64")]
+const KERNEL_MAP_VADDR: usize = 0xffff_c000_0000_0000;
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const KERNEL_MAP_VADDR: usize = 0xffff_ffd0_0000_0000;
+#[cfg(target_arch = "aarch64")]
+const KERNEL_MAP_VADDR: usize = 0xffff_0080_0000_0000;
+
+/// 是否已经建立了新增的内核映射
+static KERNEL_MAPPED: AtomicBool = AtomicBool::new(false);
+
+/// 串行化 [`KERNEL_MAP`]，保证只建立一次映射
+static KERNEL_MAP_LOCK: SpinNoIrq<()> = SpinNoIrq::new(());
+
+fn kernel_map() -> SyscallResult {
+    let _guard = KERNEL_MAP_LOCK.lock();
+    if KERNEL_MAPPED.load(Ordering::Acquire) {
+        return Ok(0);
+    }
+    let layout = Layout::from_size_align(PAGE_SIZE_4K, PAGE_SIZE_4K).unwrap();
+    // 映射一直保留，对应的页面也不再释放
+    let page = unsafe { alloc::alloc::alloc_zeroed(layout) };
+    if page.is_null() {
+        return Err(SyscallError::ENOMEM);
+    }
+    unsafe { (page as *mut u32).write_volatile(KERNEL_MAP_MAGIC) };
+    let paddr = virt_to_phys(VirtAddr::from(page as usize));
+    axmem::map_kernel_region(
+        KERNEL_MAP_VADDR.into(),
+        paddr,
+        PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::WRITE,
+    )
+    .map_err(|_| SyscallError::EINVAL)?;
+    KERNEL_MAPPED.store(true, Ordering::Release);
+    Ok(0)
+}
+
+fn kernel_read() -> SyscallResult {
+    if !KERNEL_MAPPED.load(Ordering::Acquire) {
+        return Err(SyscallError::EINVAL);
+    }
+    // 此时使用的是当前进程的页表
+    let value = unsafe { (KERNEL_MAP_VADDR as *const u32).read_volatile() };
+    Ok(value as isize)
+}
+
+/// 自测系统调用的处理函数
+///
+/// # Arguments
+/// * `args[0]` - 子命令
+pub(crate) fn syscall_selftest(args: [usize; 6]) -> SyscallResult {
+    match args[0] {
+        KERNEL_MAP => kernel_map(),
+        KERNEL_READ => kernel_read(),
+        _ => Err(SyscallError::EINVAL),
+    }
+}
@@ -1,54 +1,187 @@
-use crate::{deal_result, SyscallResult};
-use axlog::info;
+//! 系统调用的分发
+//!
+//! 分发表以系统调用号为下标，每一项是处理该调用的函数。表在第一次分发系统调用时根据各模块的
+//! 系统调用表建立，之后的分发只需一次数组访问。新的处理函数可以通过 [`register_syscall`]
+//! 加入，也可以覆盖已有的处理函数。
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-#[no_mangle]
-/// Syscall entry for linux posix api
-pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
-    crate::syscall_task::check_dead_wait();
-    #[allow(unused_mut, unused_assignments)]
-    let mut ans: Option<SyscallResult> = None;
+use crate::{
+    deal_result,
+    syscall_fs::{FsSyscallId, FS_SYSCALLS},
+    syscall_mem::{MemSyscallId, MEM_SYSCALLS},
+    syscall_net::NET_SYSCALLS,
+    syscall_task::{TaskSyscallId, TASK_SYSCALLS},
+    SyscallError, SyscallResult,
+};
+use axlog::{error, info};
 
-    if let Ok(net_syscall_id) = crate::syscall_net::NetSyscallId::try_from(syscall_id) {
-        info!(
-            "[syscall] id = {:#?}, args = {:?}, entry",
-            net_syscall_id, args
-        );
+/// 有意不做任何处理、直接返回 0 的系统调用
+///
+/// 这些调用的效果在当前内核中可以安全地忽略。未在此列出且没有实现的系统调用一律返回 ENOSYS
+const NOOP_SYSCALLS: &[usize] = &[
+    FsSyscallId::SYNC as usize,
+    FsSyscallId::FSYNC as usize,
+    MemSyscallId::MEMBARRIER as usize,
+    MemSyscallId::SHMCTL as usize,
+    TaskSyscallId::SIGTIMEDWAIT as usize,
+    TaskSyscallId::SYSLOG as usize,
+    TaskSyscallId::SCHED_SETAFFINITY as usize,
+    TaskSyscallId::GET_MEMPOLICY as usize,
+];
+
+/// 仅 x86_64 下存在的、有意不做处理的系统调用
+#[cfg(target_arch = "x86_64")]
+const ARCH_NOOP_SYSCALLS: &[usize] = &[
+    TaskSyscallId::ALARM as usize,
+    TaskSyscallId::RSEQ as usize,
+    TaskSyscallId::TIME as usize,
+];
+#[cfg(not(target_arch = "x86_64"))]
+const ARCH_NOOP_SYSCALLS: &[usize] = &[];
+
+/// 系统调用的处理函数，参数为系统调用的六个参数
+pub type SyscallHandler = fn([usize; 6]) -> SyscallResult;
 
-        #[allow(unused_assignments)]
-        ans = Some(crate::syscall_net::net_syscall(net_syscall_id, args));
+/// 分发表的大小，各架构上的系统调用号均小于它
+pub const MAX_SYSCALL_ID: usize = 512;
+
+/// 以系统调用号为下标的处理函数表
+///
+/// 每一项是以 usize 保存的函数指针，0 表示没有处理函数。分发时只需一次原子读，不加锁
+pub struct SyscallTable {
+    handlers: [AtomicUsize; MAX_SYSCALL_ID],
+}
+
+impl SyscallTable {
+    /// 创建所有项均为空的表
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const fn new() -> Self {
+        const EMPTY: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            handlers: [EMPTY; MAX_SYSCALL_ID],
+        }
     }
 
-    if let Ok(mem_syscall_id) = crate::syscall_mem::MemSyscallId::try_from(syscall_id) {
-        info!(
-            "[syscall] id = {:#?}, args = {:?}, entry",
-            mem_syscall_id, args
-        );
-        #[allow(unused_assignments)]
-        ans = Some(crate::syscall_mem::mem_syscall(mem_syscall_id, args));
+    /// 将 `syscall_id` 的处理函数设为 `handler`，覆盖已有的处理函数
+    ///
+    /// 系统调用号超出表的范围时返回 false
+    pub fn register(&self, syscall_id: usize, handler: SyscallHandler) -> bool {
+        match self.handlers.get(syscall_id) {
+            Some(slot) => {
+                slot.store(handler as usize, Ordering::Release);
+                true
+            }
+            None => false,
+        }
     }
 
-    if let Ok(fs_syscall_id) = crate::syscall_fs::FsSyscallId::try_from(syscall_id) {
-        info!(
-            "[syscall] id = {:#?}, args = {:?}, entry",
-            fs_syscall_id, args
-        );
-        #[allow(unused_assignments)]
-        ans = Some(crate::syscall_fs::fs_syscall(fs_syscall_id, args));
+    /// 仅在 `syscall_id` 还没有处理函数时设置，返回是否设置成功
+    fn register_if_empty(&self, syscall_id: usize, handler: SyscallHandler) -> bool {
+        self.handlers.get(syscall_id).is_some_and(|slot| {
+            slot.compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
     }
 
-    if let Ok(task_syscall_id) = crate::syscall_task::TaskSyscallId::try_from(syscall_id) {
-        info!(
-            "[syscall] id = {:#?}, args = {:?}, entry",
-            task_syscall_id, args
+    /// 返回 `syscall_id` 的处理函数
+    pub fn get(&self, syscall_id: usize) -> Option<SyscallHandler> {
+        match self.handlers.get(syscall_id)?.load(Ordering::Acquire) {
+            0 => None,
+            // SAFETY: 非 0 的项只会由 register 与 register_if_empty 写入，均来自 SyscallHandler
+            raw => Some(unsafe { core::mem::transmute::<usize, SyscallHandler>(raw) }),
+        }
+    }
+
+    /// 调用 `syscall_id` 的处理函数，没有处理函数时返回 None
+    pub fn dispatch(&self, syscall_id: usize, args: [usize; 6]) -> Option<SyscallResult> {
+        self.get(syscall_id).map(|handler| handler(args))
+    }
+}
+
+impl Default for SyscallTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 全局的系统调用分发表
+static SYSCALL_TABLE: SyscallTable = SyscallTable::new();
+
+/// 内置的处理函数是否已经填入分发表
+static BUILTIN_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+fn noop_handler(_args: [usize; 6]) -> SyscallResult {
+    Ok(0)
+}
+
+/// 根据各模块的系统调用表填入内置的处理函数
+///
+/// 已经通过 [`register_syscall`] 注册的项不会被覆盖。同一个调用号出现在多个模块中时，
+/// 优先级依次为有意忽略的调用、task、fs、mem、net
+fn register_builtin_syscalls() {
+    for &syscall_id in NOOP_SYSCALLS.iter().chain(ARCH_NOOP_SYSCALLS) {
+        SYSCALL_TABLE.register_if_empty(syscall_id, noop_handler);
+    }
+    let builtin = TASK_SYSCALLS
+        .iter()
+        .map(|&(id, handler)| (id as usize, handler))
+        .chain(
+            FS_SYSCALLS
+                .iter()
+                .map(|&(id, handler)| (id as usize, handler)),
+        )
+        .chain(
+            MEM_SYSCALLS
+                .iter()
+                .map(|&(id, handler)| (id as usize, handler)),
+        )
+        .chain(
+            NET_SYSCALLS
+                .iter()
+                .map(|&(id, handler)| (id as usize, handler)),
         );
-        #[allow(unused_assignments)]
-        ans = Some(crate::syscall_task::task_syscall(task_syscall_id, args));
+    for (syscall_id, handler) in builtin {
+        SYSCALL_TABLE.register_if_empty(syscall_id, handler);
+    }
+    #[cfg(feature = "selftest")]
+    SYSCALL_TABLE.register_if_empty(
+        crate::selftest::SELFTEST_SYSCALL_ID,
+        crate::selftest::syscall_selftest,
+    );
+}
+
+fn ensure_builtin_registered() {
+    if !BUILTIN_REGISTERED.load(Ordering::Acquire) {
+        // 多个 CPU 同时填表是无害的：内置的项只会填入空位
+        register_builtin_syscalls();
+        BUILTIN_REGISTERED.store(true, Ordering::Release);
     }
+}
+
+/// 将 `syscall_id` 的处理函数设为 `handler`，覆盖内置的处理函数
+///
+/// 系统调用号不小于 [`MAX_SYSCALL_ID`] 时返回 false
+pub fn register_syscall(syscall_id: usize, handler: SyscallHandler) -> bool {
+    ensure_builtin_registered();
+    SYSCALL_TABLE.register(syscall_id, handler)
+}
 
-    if ans.is_none() {
-        panic!("unknown syscall id: {}", syscall_id);
+#[no_mangle]
+/// Syscall entry for linux posix api
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
+    ensure_builtin_registered();
+    info!("[syscall] id = {}, args = {:?}, entry", syscall_id, args);
+    let ans = SYSCALL_TABLE
+        .dispatch(syscall_id, args)
+        .unwrap_or(Err(SyscallError::ENOSYS));
+    if ans == Err(SyscallError::ENOSYS) {
+        // 以 error 级别输出，便于移植新程序时发现尚未实现的系统调用
+        error!(
+            "[syscall] unsupported syscall id = {} ({:#x}), args = {:#x?}",
+            syscall_id, syscall_id, args
+        );
     }
-    let ans = deal_result(ans.unwrap());
+    let ans = deal_result(ans);
     if syscall_id != 96 && syscall_id != 98 {
         info!(
             "[syscall] id = {}, args = {:?}, return {}",
@@ -57,3 +190,48 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     }
     ans
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SyscallTable, MAX_SYSCALL_ID, SYSCALL_TABLE};
+    use crate::{SyscallError, SyscallResult};
+
+    fn dummy_handler(args: [usize; 6]) -> SyscallResult {
+        Ok((args[0] * 100 + args[1]) as isize)
+    }
+
+    fn other_handler(_args: [usize; 6]) -> SyscallResult {
+        Err(SyscallError::EPERM)
+    }
+
+    #[test]
+    fn test_syscall_table() {
+        let table = SyscallTable::new();
+        assert!(table.dispatch(7, [0; 6]).is_none());
+        assert!(table.register(7, dummy_handler));
+        // 处理函数收到的是系统调用的参数
+        assert_eq!(table.dispatch(7, [7, 3, 0, 0, 0, 0]), Some(Ok(703)));
+        assert!(table.dispatch(8, [8, 3, 0, 0, 0, 0]).is_none());
+        // 后注册的处理函数覆盖之前的
+        assert!(table.register(7, other_handler));
+        assert_eq!(table.dispatch(7, [0; 6]), Some(Err(SyscallError::EPERM)));
+        // 内置的处理函数不覆盖已有的项
+        assert!(!table.register_if_empty(7, dummy_handler));
+        assert!(table.register_if_empty(8, dummy_handler));
+        assert_eq!(table.dispatch(8, [8, 1, 0, 0, 0, 0]), Some(Ok(801)));
+        // 超出范围的系统调用号
+        assert!(!table.register(MAX_SYSCALL_ID, dummy_handler));
+        assert!(table.dispatch(MAX_SYSCALL_ID, [0; 6]).is_none());
+        assert!(table.dispatch(usize::MAX, [0; 6]).is_none());
+    }
+
+    #[test]
+    fn test_register_syscall() {
+        let id = MAX_SYSCALL_ID - 1;
+        assert!(super::register_syscall(id, dummy_handler));
+        assert_eq!(
+            SYSCALL_TABLE.dispatch(id, [5, 1, 0, 0, 0, 0]),
+            Some(Ok(501))
+        );
+    }
+}
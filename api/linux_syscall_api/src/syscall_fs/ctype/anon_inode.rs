@@ -0,0 +1,230 @@
+//! 匿名 inode，即不对应文件系统中任何对象的文件
+//!
+//! eventfd、epoll 等内核对象通过文件描述符暴露给用户，但并不存在于任何文件系统中。
+//! 这些对象只需实现 [`FileIO`] 中与自身相关的操作，再通过 [`new_fd`] 包装为 [`AnonInode`]
+//! 放入文件描述符表，路径与文件信息由本模块统一提供，其余操作都交给对象自身处理。
+//! 匿名文件不是 `FileDesc`，因此 mmap 会返回 ENODEV。
+extern crate alloc;
+use alloc::{format, string::String, sync::Arc};
+use axerrno::AxResult;
+use axfs::api::{FileIO, FileIOType, Kstat, OpenFlags, SeekFrom};
+use axprocess::current_process;
+
+use crate::{SyscallError, SyscallResult};
+
+/// 所有匿名文件共享同一个 inode
+pub const ANON_INODE_INO: u64 = 1;
+
+/// 匿名 inode 的权限，仅属主可读写，且不带有文件类型位
+const ANON_INODE_MODE: u32 = 0o600;
+
+/// 匿名文件的路径，形如 `anon_inode:[eventfd]`，即 /proc/self/fd 中链接的内容
+pub fn anon_inode_path(name: &str) -> String {
+    format!("anon_inode:[{}]", name)
+}
+
+/// 匿名文件的文件信息
+pub fn anon_inode_stat() -> Kstat {
+    Kstat {
+        st_ino: ANON_INODE_INO,
+        st_mode: ANON_INODE_MODE,
+        st_nlink: 1,
+        st_blksize: 4096,
+        ..Default::default()
+    }
+}
+
+/// 文件描述符表中的匿名文件，包装了实际的内核对象
+///
+/// 路径与文件信息由名称决定，其余操作都转交给内核对象
+pub struct AnonInode {
+    name: &'static str,
+    ops: Arc<dyn FileIO>,
+}
+
+impl AnonInode {
+    /// 以名称 `name` 包装内核对象 `ops`
+    pub fn new(name: &'static str, ops: Arc<dyn FileIO>) -> Self {
+        Self { name, ops }
+    }
+
+    /// 匿名文件的名称，例如 eventfd
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 被包装的内核对象
+    pub fn ops(&self) -> &Arc<dyn FileIO> {
+        &self.ops
+    }
+}
+
+/// 若 `file` 是包装了 `T` 的匿名文件，返回其中的内核对象
+pub fn downcast_anon<T: 'static>(file: &Arc<dyn FileIO>) -> Option<&T> {
+    file.as_any()
+        .downcast_ref::<AnonInode>()?
+        .ops
+        .as_any()
+        .downcast_ref::<T>()
+}
+
+impl FileIO for AnonInode {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.ops.read(buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        self.ops.write(buf)
+    }
+
+    fn flush(&self) -> AxResult<()> {
+        self.ops.flush()
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        self.ops.seek(pos)
+    }
+
+    fn readable(&self) -> bool {
+        self.ops.readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.ops.writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        self.ops.get_type()
+    }
+
+    fn get_path(&self) -> String {
+        anon_inode_path(self.name)
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(anon_inode_stat())
+    }
+
+    fn truncate(&self, len: usize) -> AxResult<()> {
+        self.ops.truncate(len)
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        self.ops.set_status(flags)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.ops.get_status()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.ops.set_close_on_exec(is_set)
+    }
+
+    fn in_exceptional_conditions(&self) -> bool {
+        self.ops.in_exceptional_conditions()
+    }
+
+    fn is_hang_up(&self) -> bool {
+        self.ops.is_hang_up()
+    }
+
+    fn ready_to_read(&self) -> bool {
+        self.ops.ready_to_read()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        self.ops.ready_to_write()
+    }
+
+    fn ioctl(&self, request: usize, arg1: usize) -> AxResult<()> {
+        self.ops.ioctl(request, arg1)
+    }
+}
+
+/// 将文件放入当前进程的文件描述符表，返回分配的文件描述符
+///
+/// 若 `flags` 带有 `CLOEXEC`，则同时设置 close_on_exec 位
+pub fn install_fd(file: Arc<dyn FileIO>, flags: OpenFlags) -> SyscallResult {
+    if flags.contains(OpenFlags::CLOEXEC) {
+        file.set_close_on_exec(true);
+    }
+    let process = current_process();
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
+    if let Ok(fd) = process.alloc_fd(&mut fd_table) {
+        fd_table[fd] = Some(file);
+        Ok(fd as isize)
+    } else {
+        Err(SyscallError::EMFILE)
+    }
+}
+
+/// 以名称 `name` 将内核对象 `ops` 包装为匿名文件，并为其分配一个文件描述符
+pub fn new_fd(name: &'static str, ops: Arc<dyn FileIO>, flags: OpenFlags) -> SyscallResult {
+    install_fd(Arc::new(AnonInode::new(name, ops)), flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        anon_inode_path, anon_inode_stat, downcast_anon, AnonInode, Arc, AxResult, FileIO,
+        FileIOType, String, ANON_INODE_INO,
+    };
+
+    #[test]
+    fn test_anon_inode() {
+        assert_eq!(anon_inode_path("eventfd"), "anon_inode:[eventfd]");
+        let stat = anon_inode_stat();
+        assert_eq!(stat.st_ino, ANON_INODE_INO);
+        // 匿名 inode 不属于任何文件类型
+        assert_eq!(stat.st_mode & 0o170000, 0);
+    }
+
+    struct Counter;
+
+    impl FileIO for Counter {
+        fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+            buf[0] = 7;
+            Ok(1)
+        }
+
+        fn readable(&self) -> bool {
+            true
+        }
+
+        fn writable(&self) -> bool {
+            false
+        }
+
+        fn executable(&self) -> bool {
+            false
+        }
+
+        fn get_type(&self) -> FileIOType {
+            FileIOType::Other
+        }
+
+        fn get_path(&self) -> String {
+            String::from("counter")
+        }
+    }
+
+    #[test]
+    fn test_anon_inode_delegates() {
+        let file: Arc<dyn FileIO> = Arc::new(AnonInode::new("counter", Arc::new(Counter)));
+        // 路径与文件信息来自匿名 inode，读写交给内核对象
+        assert_eq!(file.get_path(), "anon_inode:[counter]");
+        assert_eq!(file.get_stat().unwrap().st_ino, ANON_INODE_INO);
+        let mut buf = [0u8; 1];
+        assert_eq!(file.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 7);
+        assert!(file.readable() && !file.writable());
+        assert!(downcast_anon::<Counter>(&file).is_some());
+        assert!(downcast_anon::<AnonInode>(&file).is_none());
+    }
+}
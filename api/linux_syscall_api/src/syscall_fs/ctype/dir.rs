@@ -1,21 +1,49 @@
 use crate::{normal_file_mode, StMode};
 extern crate alloc;
-use alloc::string::{String, ToString};
+use alloc::{
+    collections::BTreeSet,
+    string::{String, ToString},
+};
 use axerrno::{AxError, AxResult};
 use axfs::api::{self, FileIO, FileIOType, Kstat, OpenFlags, SeekFrom};
 use axlog::debug;
+use axsync::{Mutex, MutexGuard};
+
+use super::{file::inode_number, times::file_times};
 
 /// 目录描述符
 pub struct DirDesc {
     /// 目录
     pub dir_path: String,
+    /// getdents64 读取到的位置
+    cursor: Mutex<DirCursor>,
+}
+
+/// 目录描述符上 getdents64 读取到的位置
+#[derive(Default)]
+pub struct DirCursor {
+    /// 已经读出的最后一个目录项的 d_off，也是 lseek 得到的位置
+    pub offset: u64,
+    /// 从头开始已经读出的目录项的名字
+    ///
+    /// d_off 由目录项依次累加得到，读取期间删除已经读出的目录项（如 `rm -r`）会让后面的 d_off 前移，
+    /// 因此按名字跳过已经读出的目录项。lseek 之后为 `None`，改为按 `offset` 跳过
+    pub returned: Option<BTreeSet<String>>,
 }
 
 /// 目录描述符的实现
 impl DirDesc {
     /// 创建一个新的目录描述符
     pub fn new(path: String) -> Self {
-        Self { dir_path: path }
+        Self {
+            dir_path: path,
+            cursor: Mutex::new(DirCursor::default()),
+        }
+    }
+
+    /// 锁住并返回读取到的位置，getdents64 在整个读取期间持有它
+    pub fn cursor(&self) -> MutexGuard<'_, DirCursor> {
+        self.cursor.lock()
     }
 }
 
@@ -30,8 +58,21 @@ impl FileIO for DirDesc {
     fn flush(&self) -> AxResult {
         Err(AxError::IsADirectory)
     }
-    fn seek(&self, _: SeekFrom) -> AxResult<u64> {
-        Err(AxError::IsADirectory)
+    /// 位置只能设置为 getdents64 返回的 d_off，或者设置为 0 从头读取
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut cursor = self.cursor.lock();
+        let new_offset = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => cursor.offset.checked_add_signed(delta),
+            SeekFrom::End(_) => None,
+        };
+        let new_offset = new_offset.ok_or(AxError::InvalidInput)?;
+        // 只查询位置（如 lseek(fd, 0, SEEK_CUR)）时不影响读过的目录项
+        if new_offset != cursor.offset || new_offset == 0 {
+            cursor.offset = new_offset;
+            cursor.returned = None;
+        }
+        Ok(cursor.offset)
     }
     fn get_type(&self) -> FileIOType {
         FileIOType::DirDesc
@@ -50,9 +91,10 @@ impl FileIO for DirDesc {
     }
 
     fn get_stat(&self) -> AxResult<Kstat> {
+        let times = file_times(&self.dir_path);
         let kstat = Kstat {
             st_dev: 1,
-            st_ino: 0,
+            st_ino: inode_number(&self.dir_path),
             st_mode: normal_file_mode(StMode::S_IFDIR).bits(),
             st_nlink: 1,
             st_uid: 0,
@@ -60,15 +102,15 @@ impl FileIO for DirDesc {
             st_rdev: 0,
             _pad0: 0,
             st_size: 0,
-            st_blksize: 0,
+            st_blksize: axfs::BLOCK_SIZE as u32,
             _pad1: 0,
             st_blocks: 0,
-            st_atime_sec: 0,
-            st_atime_nsec: 0,
-            st_mtime_sec: 0,
-            st_mtime_nsec: 0,
-            st_ctime_sec: 0,
-            st_ctime_nsec: 0,
+            st_atime_sec: times.atime.tv_sec as isize,
+            st_atime_nsec: times.atime.tv_nsec as isize,
+            st_mtime_sec: times.mtime.tv_sec as isize,
+            st_mtime_nsec: times.mtime.tv_nsec as isize,
+            st_ctime_sec: times.ctime.tv_sec as isize,
+            st_ctime_nsec: times.ctime.tv_nsec as isize,
         };
         Ok(kstat)
     }
@@ -1,4 +1,4 @@
-use axhal::time::current_ticks;
+use axhal::time::{current_time, TimeValue};
 use bitflags::bitflags;
 extern crate alloc;
 use alloc::{
@@ -8,7 +8,7 @@ use alloc::{
 };
 use axerrno::{AxError, AxResult};
 
-use axfs::api::{FileIO, FileIOType, SeekFrom};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
 
 use crate::SyscallError;
 use axprocess::{current_process, yield_now_task};
@@ -63,6 +63,8 @@ pub struct EpollFile {
     /// 定义内部可变变量
     /// 由于存在clone，所以要用arc指针包围
     pub inner: Arc<Mutex<EpollFileInner>>,
+    /// 文件状态，目前只记录 close_on_exec 位
+    flags: Mutex<OpenFlags>,
 }
 
 pub struct EpollFileInner {
@@ -80,6 +82,7 @@ impl EpollFile {
                 monitor_list: BTreeMap::new(),
                 _response_list: BTreeSet::new(),
             })),
+            flags: Mutex::new(OpenFlags::empty()),
         }
     }
 
@@ -88,6 +91,7 @@ impl EpollFile {
     pub fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            flags: Mutex::new(*self.flags.lock()),
         }
     }
 
@@ -144,13 +148,14 @@ impl EpollFile {
 
     /// 实现epoll wait，在规定超时时间内收集达到触发条件的事件
     ///
-    /// 实现原理和ppoll很像
-    pub fn epoll_wait(&self, expire_time: usize) -> AxResult<Vec<EpollEvent>> {
+    /// 实现原理和ppoll很像，`deadline` 为超时的时刻
+    pub fn epoll_wait(&self, deadline: TimeValue) -> AxResult<Vec<EpollEvent>> {
         let events = self.get_events();
         let mut ret_events = Vec::new();
         loop {
             let current_process = current_process();
-            let fd_table = current_process.fd_manager.fd_table.lock();
+            let fd_manager = current_process.fd_manager();
+            let fd_table = fd_manager.fd_table.lock();
             for req_event in events.iter() {
                 if let Some(file) = &fd_table[req_event.data as usize] {
                     let mut ret_event_type = EpollEventType::empty();
@@ -189,7 +194,7 @@ impl EpollFile {
                 return Ok(ret_events);
             }
             // 否则直接block
-            if current_ticks() as usize > expire_time {
+            if current_time() > deadline {
                 return Ok(ret_events);
             }
             yield_now_task();
@@ -228,11 +233,19 @@ impl FileIO for EpollFile {
     fn get_type(&self) -> FileIOType {
         FileIOType::FileDesc
     }
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
     fn ready_to_read(&self) -> bool {
         // 如果当前epoll事件确实正在等待事件响应，那么可以认为事件准备好read，尽管无法读到实际内容
         let events = self.get_events();
         let process = current_process();
-        let fd_table = process.fd_manager.fd_table.lock();
+        let fd_manager = process.fd_manager();
+        let fd_table = fd_manager.fd_table.lock();
         for req_event in events.iter() {
             if let Some(file) = fd_table[req_event.data as usize].as_ref() {
                 let mut ret_event_type = EpollEventType::empty();
@@ -18,23 +18,23 @@ bitflags! {
 // https://man7.org/linux/man-pages/man2/eventfd2.2.html
 pub struct EventFd {
     value: Arc<Mutex<u64>>,
-    flags: u32,
+    flags: Mutex<u32>,
 }
 
 impl EventFd {
     pub fn new(initval: u64, flags: u32) -> EventFd {
         EventFd {
             value: Arc::new(Mutex::new(initval)),
-            flags,
+            flags: Mutex::new(flags),
         }
     }
 
     fn should_block(&self) -> bool {
-        self.flags & EventFdFlag::EFD_NONBLOCK.bits() == 0
+        *self.flags.lock() & EventFdFlag::EFD_NONBLOCK.bits() == 0
     }
 
     fn has_semaphore_set(&self) -> bool {
-        self.flags & EventFdFlag::EFD_SEMAPHORE.bits() != 0
+        *self.flags.lock() & EventFdFlag::EFD_SEMAPHORE.bits() != 0
     }
 }
 
@@ -140,21 +140,45 @@ impl FileIO for EventFd {
     }
 
     fn get_status(&self) -> OpenFlags {
+        let flags = *self.flags.lock();
         let mut status = OpenFlags::RDWR;
-        if self.flags & EventFdFlag::EFD_NONBLOCK.bits() != 0 {
-            status &= OpenFlags::NON_BLOCK;
+        if flags & EventFdFlag::EFD_NONBLOCK.bits() != 0 {
+            status |= OpenFlags::NON_BLOCK;
         }
-        if self.flags & EventFdFlag::EFD_CLOEXEC.bits() != 0 {
-            status &= OpenFlags::CLOEXEC;
+        if flags & EventFdFlag::EFD_CLOEXEC.bits() != 0 {
+            status |= OpenFlags::CLOEXEC;
         }
 
         status
     }
+
+    // EFD_NONBLOCK 与 O_NONBLOCK 的取值相同，可以通过 fcntl 修改
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        let mut status = self.flags.lock();
+        if flags.contains(OpenFlags::NON_BLOCK) {
+            *status |= EventFdFlag::EFD_NONBLOCK.bits();
+        } else {
+            *status &= !EventFdFlag::EFD_NONBLOCK.bits();
+        }
+        if flags.contains(OpenFlags::CLOEXEC) {
+            *status |= EventFdFlag::EFD_CLOEXEC.bits();
+        }
+        true
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= EventFdFlag::EFD_CLOEXEC.bits();
+        } else {
+            *self.flags.lock() &= !EventFdFlag::EFD_CLOEXEC.bits();
+        }
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::EventFd;
+    use super::{EventFd, EventFdFlag};
     use axerrno::AxError;
     use axfs::api::FileIO;
 
@@ -176,6 +200,14 @@ mod tests {
         assert_eq!(Err(AxError::InvalidInput), result);
     }
 
+    #[test]
+    fn test_nonblock_read_on_zero() {
+        let event_fd = EventFd::new(0, EventFdFlag::EFD_NONBLOCK.bits());
+        let event_fd_val = 0u64;
+        let result = event_fd.read(&mut event_fd_val.to_ne_bytes());
+        assert_eq!(Err(AxError::WouldBlock), result);
+    }
+
     #[test]
     fn test_write() {
         let event_fd = EventFd::new(42, 0);
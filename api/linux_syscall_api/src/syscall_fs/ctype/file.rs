@@ -1,21 +1,24 @@
 extern crate alloc;
 
-use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
-use axerrno::AxResult;
-use axfs::api::{File, FileIO, FileIOType, Kstat, OpenFlags, Read, Seek, SeekFrom, Write};
+use axerrno::{AxError, AxResult};
+use axfs::api::{
+    lookup, File, FileIO, FileIOType, Kstat, OpenFlags, Read, Seek, SeekFrom, Write, FIONREAD,
+};
 
 use axlog::debug;
+use axmem::invalidate_page_cache;
 
-use crate::{normal_file_mode, StMode, TimeSecs};
+use super::times::{file_accessed, file_modified, file_times};
+
+use crate::{StMode, TimeSecs};
 use axprocess::link::get_link_count;
+use axprocess::uaccess::copy_struct_to_user;
 use axsync::Mutex;
 
-pub static INODE_NAME_MAP: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
-
 /// 文件描述符
 pub struct FileDesc {
     /// 文件路径
@@ -24,12 +27,12 @@ pub struct FileDesc {
     pub file: Arc<Mutex<File>>,
     /// 文件打开的标志位
     pub flags: Mutex<OpenFlags>,
-    /// 文件信息
-    pub stat: Mutex<FileMetaData>,
 }
 
 /// 文件在os中运行时的可变信息
-/// TODO: 暂时全部记为usize
+///
+/// 按 inode 保存，更新规则见 [`super::times`]
+#[derive(Clone, Copy, Debug)]
 pub struct FileMetaData {
     /// 最后一次访问时间
     pub atime: TimeSecs,
@@ -45,7 +48,11 @@ pub struct FileMetaData {
 /// 为FileDesc实现FileIO trait
 impl FileIO for FileDesc {
     fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
-        self.file.lock().read(buf)
+        let size = self.file.lock().read(buf)?;
+        if !self.flags.lock().contains(OpenFlags::NOATIME) {
+            file_accessed(&self.path);
+        }
+        Ok(size)
     }
 
     fn write(&self, buf: &[u8]) -> AxResult<usize> {
@@ -58,7 +65,14 @@ impl FileIO for FileDesc {
             let temp_buf: Vec<u8> = vec![0u8; (old_offset - size) as usize];
             file.write(&temp_buf)?;
         }
-        file.write(buf)
+        let size = file.write(buf)?;
+        let ino = file.ino();
+        drop(file);
+        if size > 0 {
+            invalidate_page_cache(ino);
+            file_modified(&self.path);
+        }
+        Ok(size)
     }
 
     fn flush(&self) -> AxResult {
@@ -87,31 +101,22 @@ impl FileIO for FileDesc {
     }
 
     fn truncate(&self, len: usize) -> AxResult<()> {
-        self.file.lock().truncate(len)
+        let mut file = self.file.lock();
+        file.truncate(len)?;
+        invalidate_page_cache(file.ino());
+        drop(file);
+        file_modified(&self.path);
+        Ok(())
     }
 
     fn get_stat(&self) -> AxResult<Kstat> {
         let file = self.file.lock();
         let attr = file.get_attr()?;
-        let stat = self.stat.lock();
-        let inode_map = INODE_NAME_MAP.lock();
-        let inode_number = if let Some(inode_number) = inode_map.get(&self.path) {
-            *inode_number
-        } else {
-            // return Err(axerrno::AxError::NotFound);
-            // Now the file exists but it wasn't opened
-            drop(inode_map);
-            new_inode(self.path.clone())?;
-            let inode_map = INODE_NAME_MAP.lock();
-            assert!(inode_map.contains_key(&self.path));
-            let number = *(inode_map.get(&self.path).unwrap());
-            drop(inode_map);
-            number
-        };
+        let times = file_times(&self.path);
         let kstat = Kstat {
             st_dev: 1,
-            st_ino: inode_number,
-            st_mode: normal_file_mode(StMode::S_IFREG).bits() | 0o644,
+            st_ino: file.ino(),
+            st_mode: StMode::S_IFREG.bits() | attr.perm().mode(),
             st_nlink: get_link_count(&(self.path.as_str().to_string())) as _,
             st_uid: 0,
             st_gid: 0,
@@ -121,12 +126,12 @@ impl FileIO for FileDesc {
             st_blksize: axfs::BLOCK_SIZE as u32,
             _pad1: 0,
             st_blocks: attr.blocks(),
-            st_atime_sec: stat.atime.tv_sec as isize,
-            st_atime_nsec: stat.atime.tv_nsec as isize,
-            st_mtime_sec: stat.mtime.tv_sec as isize,
-            st_mtime_nsec: stat.mtime.tv_nsec as isize,
-            st_ctime_sec: stat.ctime.tv_sec as isize,
-            st_ctime_nsec: stat.ctime.tv_nsec as isize,
+            st_atime_sec: times.atime.tv_sec as isize,
+            st_atime_nsec: times.atime.tv_nsec as isize,
+            st_mtime_sec: times.mtime.tv_sec as isize,
+            st_mtime_nsec: times.mtime.tv_nsec as isize,
+            st_ctime_sec: times.ctime.tv_sec as isize,
+            st_ctime_nsec: times.ctime.tv_nsec as isize,
         };
         Ok(kstat)
     }
@@ -136,6 +141,20 @@ impl FileIO for FileDesc {
         true
     }
 
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        match request {
+            FIONREAD => {
+                // 从当前偏移到文件末尾的字节数
+                let mut file = self.file.lock();
+                let offset = file.seek(SeekFrom::Current(0))?;
+                let size = file.metadata()?.size();
+                copy_struct_to_user(data, size.saturating_sub(offset) as u32)
+                    .map_err(|_| AxError::BadAddress)
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
+
     fn get_status(&self) -> OpenFlags {
         *self.flags.lock()
     }
@@ -186,11 +205,6 @@ impl FileDesc {
             path: path.to_string(),
             file,
             flags: Mutex::new(flags),
-            stat: Mutex::new(FileMetaData {
-                atime: TimeSecs::default(),
-                mtime: TimeSecs::default(),
-                ctime: TimeSecs::default(),
-            }),
         }
     }
 }
@@ -205,14 +219,14 @@ pub fn new_fd(path: String, flags: OpenFlags) -> AxResult<FileDesc> {
     Ok(fd)
 }
 
-/// 当新建一个文件或者目录节点时，需要为其分配一个新的inode号
-/// 由于我们不涉及删除文件，因此我们可以简单地使用一个全局增的计数器来分配inode号
-pub fn new_inode(path: String) -> AxResult<()> {
-    let mut inode_name_map = INODE_NAME_MAP.lock();
-    if inode_name_map.contains_key(&path) {
-        return Ok(());
-    }
-    let inode_number = inode_name_map.len() as u64 + 1;
-    inode_name_map.insert(path, inode_number);
-    Ok(())
+/// 路径对应的 inode 号，由文件系统中的节点给出
+///
+/// stat 与 getdents64 都从这里取得 inode 号，二者对同一文件给出的结果一致。
+/// 路径不存在时返回 0
+pub fn inode_number(path: &str) -> u64 {
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    };
+    lookup(path).map_or(0, |node| node.ino())
 }
@@ -0,0 +1,328 @@
+//! memfd_create 创建的匿名内存文件
+//!
+//! 文件内容保存在内核内存中，不属于任何文件系统，路径形如 `/memfd:name (deleted)`。
+//! 它可以像普通文件一样读写、截断，也可以通过 execveat 的 AT_EMPTY_PATH 执行。
+//! 创建时带有 MFD_ALLOW_SEALING 的文件可以通过 fcntl 的 F_ADD_SEALS 加上封印，
+//! 禁止之后的写入、增长或缩小，封印本身一旦加上便不能去除。
+extern crate alloc;
+use alloc::{format, string::String, vec::Vec};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, Kstat, OpenFlags, SeekFrom};
+use axsync::Mutex;
+use bitflags::bitflags;
+
+bitflags! {
+    /// memfd_create 的 flags
+    #[derive(Clone, Copy, Debug)]
+    pub struct MemFdFlags: u32 {
+        /// 新的文件描述符带有 close_on_exec 位
+        const MFD_CLOEXEC = 0x1;
+        /// 允许之后为文件加上封印
+        const MFD_ALLOW_SEALING = 0x2;
+        /// 文件不可执行，并加上 F_SEAL_EXEC 使其之后也不能变为可执行，隐含 MFD_ALLOW_SEALING
+        const MFD_NOEXEC_SEAL = 0x8;
+        /// 文件可执行，这也是两个标志都没有给出时的默认行为
+        const MFD_EXEC = 0x10;
+    }
+}
+
+bitflags! {
+    /// fcntl 的 F_ADD_SEALS 与 F_GET_SEALS 使用的封印
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct MemFdSeals: u32 {
+        /// 禁止再加上新的封印
+        const F_SEAL_SEAL = 0x1;
+        /// 禁止缩小文件
+        const F_SEAL_SHRINK = 0x2;
+        /// 禁止增长文件
+        const F_SEAL_GROW = 0x4;
+        /// 禁止写入文件
+        const F_SEAL_WRITE = 0x8;
+        /// 禁止之后的写入，没有可写的共享映射时与 F_SEAL_WRITE 相同
+        const F_SEAL_FUTURE_WRITE = 0x10;
+        /// 禁止修改文件的可执行权限
+        const F_SEAL_EXEC = 0x20;
+    }
+}
+
+/// memfd 名称的最大长度，不包括前缀 `memfd:` 与结尾的 '\0'
+pub const MFD_NAME_MAX_LEN: usize = 249;
+
+/// memfd 文件的最大长度
+///
+/// 内容保存在内核堆中，因此远小于 Linux 的 MAX_LFS_FILESIZE，超过时返回 EFBIG
+pub const MEMFD_MAX_SIZE: usize = 1 << 30;
+
+/// memfd 的 inode 编号从这里开始分配，避开匿名 inode 与常见文件系统使用的编号
+static NEXT_MEMFD_INO: AtomicU64 = AtomicU64::new(0x1_0000_0000);
+
+/// memfd_create 创建的匿名内存文件
+pub struct MemFd {
+    name: String,
+    ino: u64,
+    /// 文件的权限位
+    mode: u32,
+    data: Mutex<Vec<u8>>,
+    offset: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+    seals: Mutex<MemFdSeals>,
+}
+
+impl MemFd {
+    /// 创建名为 `name` 的空文件
+    ///
+    /// `flags` 中的 MFD_ALLOW_SEALING 决定之后能否加上封印，MFD_NOEXEC_SEAL 使文件不可执行
+    pub fn new(name: &str, flags: MemFdFlags) -> Self {
+        let (mode, seals) = if flags.contains(MemFdFlags::MFD_NOEXEC_SEAL) {
+            (0o666, MemFdSeals::F_SEAL_EXEC)
+        } else if flags.contains(MemFdFlags::MFD_ALLOW_SEALING) {
+            (0o777, MemFdSeals::empty())
+        } else {
+            (0o777, MemFdSeals::F_SEAL_SEAL)
+        };
+        Self {
+            name: String::from(name),
+            ino: NEXT_MEMFD_INO.fetch_add(1, Ordering::Relaxed),
+            mode,
+            data: Mutex::new(Vec::new()),
+            offset: Mutex::new(0),
+            flags: Mutex::new(OpenFlags::RDWR),
+            seals: Mutex::new(seals),
+        }
+    }
+
+    /// 当前的封印
+    pub fn seals(&self) -> MemFdSeals {
+        *self.seals.lock()
+    }
+
+    /// 加上新的封印，已经有 F_SEAL_SEAL 时返回 `PermissionDenied`
+    pub fn add_seals(&self, new_seals: MemFdSeals) -> AxResult<()> {
+        let mut seals = self.seals.lock();
+        if seals.contains(MemFdSeals::F_SEAL_SEAL) {
+            return Err(AxError::PermissionDenied);
+        }
+        *seals |= new_seals;
+        Ok(())
+    }
+
+    fn write_sealed(&self) -> bool {
+        self.seals()
+            .intersects(MemFdSeals::F_SEAL_WRITE | MemFdSeals::F_SEAL_FUTURE_WRITE)
+    }
+}
+
+/// 将 `data` 的长度改为 `len`，新增的部分填零
+///
+/// 超过 [`MEMFD_MAX_SIZE`] 时返回 `FileTooLarge`，内存不足时返回 `NoMemory`，此时 `data` 不变
+fn resize_data(data: &mut Vec<u8>, len: usize) -> AxResult<()> {
+    if len > MEMFD_MAX_SIZE {
+        return Err(AxError::FileTooLarge);
+    }
+    if len > data.len() {
+        data.try_reserve(len - data.len())
+            .map_err(|_| AxError::NoMemory)?;
+    }
+    data.resize(len, 0);
+    Ok(())
+}
+
+impl FileIO for MemFd {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let data = self.data.lock();
+        let mut offset = self.offset.lock();
+        let start = (*offset).min(data.len());
+        let len = buf.len().min(data.len() - start);
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        *offset = start + len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        if self.write_sealed() {
+            return Err(AxError::PermissionDenied);
+        }
+        let mut data = self.data.lock();
+        let mut offset = self.offset.lock();
+        let end = offset.checked_add(buf.len()).ok_or(AxError::FileTooLarge)?;
+        if end > data.len() {
+            if self.seals().contains(MemFdSeals::F_SEAL_GROW) {
+                return Err(AxError::PermissionDenied);
+            }
+            resize_data(&mut data, end)?;
+        }
+        data[*offset..end].copy_from_slice(buf);
+        *offset = end;
+        Ok(buf.len())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let len = self.data.lock().len() as i64;
+        let mut offset = self.offset.lock();
+        let new_offset = match pos {
+            SeekFrom::Start(pos) => i64::try_from(pos).ok(),
+            SeekFrom::Current(delta) => (*offset as i64).checked_add(delta),
+            SeekFrom::End(delta) => len.checked_add(delta),
+        };
+        match new_offset {
+            Some(new_offset) if new_offset >= 0 => {
+                *offset = new_offset as usize;
+                Ok(new_offset as u64)
+            }
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+
+    fn truncate(&self, len: usize) -> AxResult<()> {
+        let mut data = self.data.lock();
+        let seals = self.seals();
+        if (len < data.len() && seals.contains(MemFdSeals::F_SEAL_SHRINK))
+            || (len > data.len() && seals.contains(MemFdSeals::F_SEAL_GROW))
+        {
+            return Err(AxError::PermissionDenied);
+        }
+        resize_data(&mut data, len)
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn executable(&self) -> bool {
+        self.mode & 0o111 != 0
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::FileDesc
+    }
+
+    fn get_path(&self) -> String {
+        format!("/memfd:{} (deleted)", self.name)
+    }
+
+    fn get_stat(&self) -> AxResult<Kstat> {
+        let size = self.data.lock().len() as u64;
+        Ok(Kstat {
+            st_ino: self.ino,
+            // 普通文件，权限默认为 0777，与 Linux 相同
+            st_mode: 0o100000 | self.mode,
+            st_nlink: 1,
+            st_size: size,
+            st_blksize: 4096,
+            st_blocks: size.div_ceil(512),
+            ..Default::default()
+        })
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        self.flags
+            .lock()
+            .set(OpenFlags::NON_BLOCK, flags.contains(OpenFlags::NON_BLOCK));
+        true
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemFd, MemFdFlags, MemFdSeals, MEMFD_MAX_SIZE};
+    use axerrno::AxError;
+    use axfs::api::{FileIO, SeekFrom};
+
+    #[test]
+    fn test_memfd_read_write() {
+        let memfd = MemFd::new("test", MemFdFlags::empty());
+        assert_eq!(memfd.write(b"hello world").unwrap(), 11);
+        assert_eq!(memfd.seek(SeekFrom::Start(6)).unwrap(), 6);
+        let mut buf = [0u8; 16];
+        assert_eq!(memfd.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"world");
+        assert_eq!(memfd.read(&mut buf).unwrap(), 0);
+        assert_eq!(memfd.get_path(), "/memfd:test (deleted)");
+        assert_eq!(memfd.get_stat().unwrap().st_size, 11);
+    }
+
+    #[test]
+    fn test_memfd_seals() {
+        // 不带 MFD_ALLOW_SEALING 时不能加上封印
+        let memfd = MemFd::new("unsealable", MemFdFlags::empty());
+        assert_eq!(
+            memfd.add_seals(MemFdSeals::F_SEAL_WRITE),
+            Err(AxError::PermissionDenied)
+        );
+
+        let memfd = MemFd::new("sealable", MemFdFlags::MFD_ALLOW_SEALING);
+        memfd.write(b"data").unwrap();
+        memfd
+            .add_seals(MemFdSeals::F_SEAL_SHRINK | MemFdSeals::F_SEAL_GROW)
+            .unwrap();
+        assert_eq!(memfd.truncate(2), Err(AxError::PermissionDenied));
+        assert_eq!(memfd.truncate(8), Err(AxError::PermissionDenied));
+        assert_eq!(memfd.write(b"more"), Err(AxError::PermissionDenied));
+        // 不改变大小的写入仍然允许
+        memfd.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(memfd.write(b"DATA").unwrap(), 4);
+
+        memfd
+            .add_seals(MemFdSeals::F_SEAL_WRITE | MemFdSeals::F_SEAL_SEAL)
+            .unwrap();
+        memfd.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(memfd.write(b"x"), Err(AxError::PermissionDenied));
+        assert_eq!(
+            memfd.add_seals(MemFdSeals::F_SEAL_GROW),
+            Err(AxError::PermissionDenied)
+        );
+        memfd.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(memfd.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"DATA");
+    }
+
+    #[test]
+    fn test_memfd_size_limit() {
+        let memfd = MemFd::new("big", MemFdFlags::empty());
+        assert_eq!(
+            memfd.truncate(MEMFD_MAX_SIZE + 1),
+            Err(AxError::FileTooLarge)
+        );
+        memfd.seek(SeekFrom::Start(MEMFD_MAX_SIZE as u64)).unwrap();
+        assert_eq!(memfd.write(b"x"), Err(AxError::FileTooLarge));
+        assert_eq!(memfd.get_stat().unwrap().st_size, 0);
+    }
+
+    #[test]
+    fn test_memfd_exec_mode() {
+        let memfd = MemFd::new("exec", MemFdFlags::empty());
+        assert!(memfd.executable());
+        assert_eq!(memfd.get_stat().unwrap().st_mode, 0o100777);
+
+        let memfd = MemFd::new("noexec", MemFdFlags::MFD_NOEXEC_SEAL);
+        assert!(!memfd.executable());
+        assert_eq!(memfd.get_stat().unwrap().st_mode, 0o100666);
+        assert!(memfd.seals().contains(MemFdSeals::F_SEAL_EXEC));
+        // MFD_NOEXEC_SEAL 隐含 MFD_ALLOW_SEALING
+        assert!(memfd.add_seals(MemFdSeals::F_SEAL_GROW).is_ok());
+    }
+}
@@ -1,11 +1,23 @@
+pub mod anon_inode;
+
 pub mod dir;
 
 pub mod file;
 
+pub mod memfd;
+
 pub mod mount;
 
 pub mod pipe;
 
+pub mod proc_mem;
+
+pub mod proc_sys;
+
+pub mod proc_task;
+
+pub mod times;
+
 pub use file::FileDesc;
 
 pub mod epoll;
@@ -7,7 +7,11 @@ use axlog::{debug, info};
 use axprocess::link::FilePath;
 use axsync::Mutex;
 
-use super::{dir::new_dir, file::new_fd};
+use super::{
+    dir::new_dir,
+    file::{inode_number, new_fd},
+    times::{file_times, AtimePolicy},
+};
 
 // use crate::{
 //     dir::new_dir,
@@ -23,10 +27,12 @@ pub struct MountedFs {
     //pub inner: Arc<Mutex<FATFileSystem>>,
     pub device: FilePath,
     pub mnt_dir: FilePath,
+    /// 读取文件时更新 atime 的策略
+    pub atime: AtimePolicy,
 }
 
 impl MountedFs {
-    pub fn new(device: &FilePath, mnt_dir: &FilePath) -> Self {
+    pub fn new(device: &FilePath, mnt_dir: &FilePath, atime: AtimePolicy) -> Self {
         assert!(
             device.is_file() && mnt_dir.is_dir(),
             "device must be a file and mnt_dir must be a dir"
@@ -34,6 +40,7 @@ impl MountedFs {
         Self {
             device: device.clone(),
             mnt_dir: mnt_dir.clone(),
+            atime,
         }
     }
     #[allow(unused)]
@@ -50,13 +57,15 @@ impl MountedFs {
 /// 注意启动时的文件系统不在这个 vec 里，它在 mod.rs 里。
 static MOUNTED: Mutex<Vec<MountedFs>> = Mutex::new(Vec::new());
 
-/// 挂载一个fatfs类型的设备
-pub fn mount_fat_fs(device_path: &FilePath, mount_path: &FilePath) -> bool {
+/// 挂载一个fatfs类型的设备，`atime` 为读取该文件系统中的文件时更新 atime 的策略
+pub fn mount_fat_fs(device_path: &FilePath, mount_path: &FilePath, atime: AtimePolicy) -> bool {
     // // device_path需要链接转换, mount_path不需要, 因为目前目录没有链接  // 暂时只有Open过的文件会加入到链接表，所以这里先不转换
     // debug!("mounting {} to {}", device_path.path(), mount_path.path());
     // if let Some(true_device_path) = real_path(device_path) {
     if path_exists(mount_path.path()) {
-        MOUNTED.lock().push(MountedFs::new(device_path, mount_path));
+        MOUNTED
+            .lock()
+            .push(MountedFs::new(device_path, mount_path, atime));
         info!("mounted {} to {}", device_path.path(), mount_path.path());
         return true;
     }
@@ -97,8 +106,40 @@ pub fn check_mounted(path: &FilePath) -> bool {
     false
 }
 
+/// `path` 所在的文件系统读取文件时更新 atime 的策略
+///
+/// 启动时的文件系统使用默认的 relatime
+pub fn atime_policy(path: &str) -> AtimePolicy {
+    MOUNTED
+        .lock()
+        .iter()
+        .filter(|m| path.starts_with(m.mnt_dir.path()))
+        .max_by_key(|m| m.mnt_dir.path().len())
+        .map_or(AtimePolicy::default(), |m| m.atime)
+}
+
 /// 根据给定的路径获取对应的文件stat
+///
+/// inode 号与 getdents64 给出的一致，时间戳见 [`super::times`]
 pub fn get_stat_in_fs(path: &FilePath) -> Result<Kstat, SyscallError> {
+    let mut stat = stat_in_fs(path)?;
+    stat.st_ino = inode_number(path.path());
+    // devfs 与 ramfs 中的文件没有给出链接数与块大小
+    stat.st_nlink = stat.st_nlink.max(1);
+    if stat.st_blksize == 0 {
+        stat.st_blksize = axfs::BLOCK_SIZE as u32;
+    }
+    let times = file_times(path.path());
+    stat.st_atime_sec = times.atime.tv_sec as isize;
+    stat.st_atime_nsec = times.atime.tv_nsec as isize;
+    stat.st_mtime_sec = times.mtime.tv_sec as isize;
+    stat.st_mtime_nsec = times.mtime.tv_nsec as isize;
+    stat.st_ctime_sec = times.ctime.tv_sec as isize;
+    stat.st_ctime_nsec = times.ctime.tv_nsec as isize;
+    Ok(stat)
+}
+
+fn stat_in_fs(path: &FilePath) -> Result<Kstat, SyscallError> {
     // 根目录算作一个简单的目录文件，不使用特殊的stat
     // 否则在fat32中查找
     let real_path = path.path();
@@ -156,8 +197,9 @@ pub fn get_stat_in_fs(path: &FilePath) -> Result<Kstat, SyscallError> {
                 .downcast_ref::<axfs::axfs_ramfs::FileNode>()
                 .is_some()
             {
-                stat.st_mode = normal_file_mode(StMode::S_IFREG).bits();
-                stat.st_size = node.get_attr().unwrap().size();
+                let attr = node.get_attr().unwrap();
+                stat.st_mode = StMode::S_IFREG.bits() | attr.perm().mode();
+                stat.st_size = attr.size();
                 return Ok(stat);
             }
         }
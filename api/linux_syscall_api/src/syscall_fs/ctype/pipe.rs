@@ -1,11 +1,24 @@
-use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axfs::api::{FileIO, FileIOType, Kstat, OpenFlags, FIONREAD};
 extern crate alloc;
-use alloc::sync::{Arc, Weak};
-use axerrno::AxResult;
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axerrno::{AxError, AxResult};
+use axhal::mem::PAGE_SIZE_4K;
 use axlog::{info, trace};
 
+use axprocess::{
+    fasync::{Fasync, FasyncEvent},
+    uaccess::copy_struct_to_user,
+};
 use axsync::Mutex;
 use axtask::yield_now;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{StMode, SyscallError};
 
 /// IPC pipe
 pub struct Pipe {
@@ -16,6 +29,8 @@ pub struct Pipe {
     buffer: Arc<Mutex<PipeRingBuffer>>,
     #[allow(unused)]
     flags: Mutex<OpenFlags>,
+    /// 信号驱动 I/O 的状态
+    fasync: Arc<Fasync>,
 }
 
 impl Pipe {
@@ -26,6 +41,7 @@ impl Pipe {
             writable: false,
             buffer,
             flags: Mutex::new(flags | OpenFlags::RDONLY),
+            fasync: Arc::default(),
         }
     }
     /// create writable pipe
@@ -35,15 +51,73 @@ impl Pipe {
             writable: true,
             buffer,
             flags: Mutex::new(flags | OpenFlags::WRONLY),
+            fasync: Arc::default(),
         }
     }
     /// is it set non block?
     pub fn is_non_block(&self) -> bool {
         self.flags.lock().contains(OpenFlags::NON_BLOCK)
     }
+
+    /// 信号驱动 I/O 的状态
+    pub fn fasync(&self) -> &Arc<Fasync> {
+        &self.fasync
+    }
+
+    /// 管道缓冲区的容量（F_GETPIPE_SZ）
+    pub fn capacity(&self) -> usize {
+        self.buffer.lock().capacity()
+    }
+
+    /// 修改管道缓冲区的容量（F_SETPIPE_SZ），返回实际的容量
+    ///
+    /// 与 Linux 一样，容量向上取整为 2 的幂个页，最小为一页。超过 [`PIPE_MAX_SIZE`] 时返回 EPERM，
+    /// 放不下缓冲区中已有的数据时返回 EBUSY
+    pub fn set_capacity(&self, size: usize) -> Result<usize, SyscallError> {
+        if size > u32::MAX as usize / 2 + 1 {
+            return Err(SyscallError::EINVAL);
+        }
+        let size = size.max(PAGE_SIZE_4K).next_power_of_two();
+        if size > PIPE_MAX_SIZE {
+            return Err(SyscallError::EPERM);
+        }
+        self.buffer.lock().resize(size)?;
+        // 扩大容量之后写入端可能变为可写
+        self.notify_peer();
+        Ok(size)
+    }
+
+    /// 向管道的另一端发出信号驱动 I/O 的通知
+    fn notify_peer(&self) {
+        let buffer = self.buffer.lock();
+        let (peer, event) = if self.readable {
+            (buffer.write_end.as_ref(), FasyncEvent::Out)
+        } else {
+            (buffer.read_end.as_ref(), FasyncEvent::In)
+        };
+        let peer = peer.and_then(Weak::upgrade);
+        drop(buffer);
+        if let Some(peer) = peer {
+            peer.fasync.notify(event);
+        }
+    }
 }
 
-const RING_BUFFER_SIZE: usize = 0x4000;
+impl Drop for Pipe {
+    /// 与 Linux 一样，一端关闭时另一端变为可读或可写，同样发出通知
+    fn drop(&mut self) {
+        self.notify_peer();
+    }
+}
+
+/// 管道的默认容量，与 Linux 默认的 64K 一致
+const RING_BUFFER_SIZE: usize = 0x10000;
+
+/// F_SETPIPE_SZ 允许的最大容量，与 Linux 的 /proc/sys/fs/pipe-max-size 默认值一致
+pub const PIPE_MAX_SIZE: usize = 0x100000;
+
+/// 不超过 PIPE_BUF 字节的写入是原子的，不会与其他写入者的数据交错
+pub const PIPE_BUF: usize = 4096;
 
 #[derive(Copy, Clone, PartialEq)]
 enum RingBufferStatus {
@@ -53,21 +127,30 @@ enum RingBufferStatus {
 }
 
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Box<[u8]>,
     head: usize,
     tail: usize,
     status: RingBufferStatus,
     write_end: Option<Weak<Pipe>>,
+    read_end: Option<Weak<Pipe>>,
+    /// 管道的 inode 号，读写两端相同
+    ino: u64,
 }
 
+/// 下一个管道的 inode 号
+static NEXT_PIPE_INO: AtomicU64 = AtomicU64::new(1);
+
 impl PipeRingBuffer {
     pub fn new() -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            ino: NEXT_PIPE_INO.fetch_add(1, Ordering::Relaxed),
+            // 缓冲区较大，直接在堆上分配，避免经过内核栈
+            arr: vec![0; RING_BUFFER_SIZE].into_boxed_slice(),
             head: 0,
             tail: 0,
             status: RingBufferStatus::Empty,
             write_end: None,
+            read_end: None,
         }
     }
 
@@ -75,10 +158,45 @@ impl PipeRingBuffer {
         self.write_end = Some(Arc::downgrade(write_end));
     }
 
+    pub fn set_read_end(&mut self, read_end: &Arc<Pipe>) {
+        self.read_end = Some(Arc::downgrade(read_end));
+    }
+
+    /// 缓冲区的容量
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
+    }
+
+    /// 将缓冲区的容量改为 `size`，已有的数据保持不变
+    ///
+    /// 已有的数据超过 `size` 时返回 EBUSY
+    pub fn resize(&mut self, size: usize) -> Result<(), SyscallError> {
+        let len = self.available_read();
+        if len > size {
+            return Err(SyscallError::EBUSY);
+        }
+        let mut arr = Vec::with_capacity(size);
+        for _ in 0..len {
+            arr.push(self.read_byte());
+        }
+        arr.resize(size, 0);
+        self.arr = arr.into_boxed_slice();
+        self.head = 0;
+        self.tail = len % size;
+        self.status = if len == 0 {
+            RingBufferStatus::Empty
+        } else if len == size {
+            RingBufferStatus::Full
+        } else {
+            RingBufferStatus::Normal
+        };
+        Ok(())
+    }
+
     pub fn write_byte(&mut self, byte: u8) {
         self.status = RingBufferStatus::Normal;
         self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        self.tail = (self.tail + 1) % self.capacity();
         if self.tail == self.head {
             self.status = RingBufferStatus::Full;
         }
@@ -86,7 +204,7 @@ impl PipeRingBuffer {
     pub fn read_byte(&mut self) -> u8 {
         self.status = RingBufferStatus::Normal;
         let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.head = (self.head + 1) % self.capacity();
         if self.head == self.tail {
             self.status = RingBufferStatus::Empty;
         }
@@ -98,14 +216,14 @@ impl PipeRingBuffer {
         } else if self.tail > self.head {
             self.tail - self.head
         } else {
-            self.tail + RING_BUFFER_SIZE - self.head
+            self.tail + self.capacity() - self.head
         }
     }
     pub fn available_write(&self) -> usize {
         if self.status == RingBufferStatus::Full {
             0
         } else {
-            RING_BUFFER_SIZE - self.available_read()
+            self.capacity() - self.available_read()
         }
     }
     pub fn all_write_ends_closed(&self) -> bool {
@@ -120,6 +238,7 @@ pub fn make_pipe(flags: OpenFlags) -> (Arc<Pipe>, Arc<Pipe>) {
     let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone(), flags));
     let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone(), flags));
     buffer.lock().set_write_end(&write_end);
+    buffer.lock().set_read_end(&read_end);
     (read_end, write_end)
 }
 
@@ -135,15 +254,16 @@ impl FileIO for Pipe {
             let loop_read = ring_buffer.available_read();
             info!("kernel: Pipe::read: loop_read = {}", loop_read);
             if loop_read == 0 {
-                if axprocess::current_process().have_signals().is_some() {
-                    return Err(axerrno::AxError::Interrupted);
-                }
-                if Arc::strong_count(&self.buffer) < 2
-                    || ring_buffer.all_write_ends_closed()
-                    || self.is_non_block()
-                {
+                if Arc::strong_count(&self.buffer) < 2 || ring_buffer.all_write_ends_closed() {
                     return Ok(already_read);
                 }
+                // 写入端仍然存在但暂无数据，非阻塞模式下直接返回 EAGAIN
+                if self.is_non_block() {
+                    return Err(AxError::WouldBlock);
+                }
+                if axprocess::current_process().have_signals().is_some() {
+                    return Err(AxError::Interrupted);
+                }
                 drop(ring_buffer);
                 yield_now();
                 continue;
@@ -153,51 +273,73 @@ impl FileIO for Pipe {
                     *byte_ref = ring_buffer.read_byte();
                     already_read += 1;
                     if already_read == want_to_read {
-                        return Ok(want_to_read);
+                        break;
                     }
                 } else {
                     break;
                 }
             }
-
+            drop(ring_buffer);
+            if already_read > 0 {
+                self.notify_peer();
+            }
             return Ok(already_read);
         }
     }
 
+    /// 写入管道
+    ///
+    /// 不超过 [`PIPE_BUF`] 字节的写入是原子的：缓冲区放不下全部数据时等待（非阻塞模式下返回 EAGAIN），
+    /// 而不是先写入一部分。更大的写入可能与其他写入者交错：阻塞模式下分多次写完全部数据，
+    /// 非阻塞模式下写入能放下的部分并返回写入的字节数，一个字节也放不下时返回 EAGAIN。
+    /// 已经写入部分数据后被信号打断时返回已写入的字节数
     fn write(&self, buf: &[u8]) -> AxResult<usize> {
         info!("kernel: Pipe::write");
         assert!(self.writable());
         let want_to_write = buf.len();
-        let mut buf_iter = buf.iter();
+        if want_to_write == 0 {
+            return Ok(0);
+        }
+        let atomic = want_to_write <= PIPE_BUF;
         let mut already_write = 0usize;
         loop {
             let mut ring_buffer = self.buffer.lock();
+            let rest = want_to_write - already_write;
             let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
+            if loop_write == 0 || (atomic && loop_write < rest) {
                 drop(ring_buffer);
 
-                if Arc::strong_count(&self.buffer) < 2 || self.is_non_block() {
+                if Arc::strong_count(&self.buffer) < 2 {
                     // 读入端关闭
                     return Ok(already_write);
                 }
+                // 缓冲区已满，非阻塞模式下直接返回 EAGAIN
+                if self.is_non_block() {
+                    return Err(AxError::WouldBlock);
+                }
+                // 与读取一样，等待期间可以被信号打断
+                if axprocess::current_process().have_signals().is_some() {
+                    return if already_write > 0 {
+                        Ok(already_write)
+                    } else {
+                        Err(AxError::Interrupted)
+                    };
+                }
                 yield_now();
                 continue;
             }
 
             // write at most loop_write bytes
-            for _ in 0..loop_write {
-                if let Some(byte_ref) = buf_iter.next() {
-                    ring_buffer.write_byte(*byte_ref);
-                    already_write += 1;
-                    if already_write == want_to_write {
-                        drop(ring_buffer);
-                        return Ok(want_to_write);
-                    }
-                } else {
-                    break;
-                }
+            let loop_write = loop_write.min(rest);
+            for &byte in &buf[already_write..already_write + loop_write] {
+                ring_buffer.write_byte(byte);
+            }
+            already_write += loop_write;
+            drop(ring_buffer);
+            self.notify_peer();
+            if already_write == want_to_write || self.is_non_block() {
+                return Ok(already_write);
             }
-            return Ok(already_write);
         }
     }
 
@@ -215,6 +357,16 @@ impl FileIO for Pipe {
         FileIOType::Pipe
     }
 
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_ino: self.buffer.lock().ino,
+            st_mode: (StMode::S_IFIFO | StMode::S_IRUSR | StMode::S_IWUSR).bits(),
+            st_nlink: 1,
+            st_blksize: 4096,
+            ..Kstat::default()
+        })
+    }
+
     fn is_hang_up(&self) -> bool {
         if self.readable {
             if self.buffer.lock().available_read() == 0
@@ -235,18 +387,32 @@ impl FileIO for Pipe {
         self.readable && self.buffer.lock().available_read() != 0
     }
 
+    /// 与 Linux 一样，至少能原子地写入 PIPE_BUF 字节时才可写
     fn ready_to_write(&self) -> bool {
-        self.writable && self.buffer.lock().available_write() != 0
+        self.writable && self.buffer.lock().available_write() >= PIPE_BUF
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        match request {
+            FIONREAD => {
+                // 管道缓冲区中已有的字节数
+                let available = self.buffer.lock().available_read() as u32;
+                copy_struct_to_user(data, available).map_err(|_| AxError::BadAddress)
+            }
+            _ => Err(AxError::Unsupported),
+        }
     }
 
     /// 设置文件状态
+    ///
+    /// 访问模式不会被修改
     fn set_status(&self, flags: OpenFlags) -> bool {
+        let mut status = self.flags.lock();
+        status.set(OpenFlags::NON_BLOCK, flags.contains(OpenFlags::NON_BLOCK));
         if flags.contains(OpenFlags::CLOEXEC) {
-            *self.flags.lock() = flags;
-            true
-        } else {
-            false
+            status.insert(OpenFlags::CLOEXEC);
         }
+        true
     }
 
     /// 获取文件状态
@@ -0,0 +1,358 @@
+//! 进程的 /proc/<pid>/maps、/proc/<pid>/smaps 与 /proc/<pid>/statm
+//!
+//! 这些文件并不存在于 procfs 中，而是在打开时遍历进程地址空间中的映射区域生成内容，
+//! 各区域的统计见 [`MappingUsage`]。格式与 Linux 相同，以便现有的解析程序直接使用：
+//! - 设备号与 inode 总是为 0；
+//! - 共享文件映射与共享内存不记录路径，路径一栏为空，堆所在的区域显示为 `[heap]`。
+extern crate alloc;
+use alloc::{format, string::String, sync::Arc};
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
+use axhal::{mem::PAGE_SIZE_4K, paging::MappingFlags};
+use axmem::{accountable, MappingUsage};
+use axprocess::{current_process, PID2PC};
+use axsync::Mutex;
+
+/// 由本模块生成的文件
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProcMemFileKind {
+    /// /proc/<pid>/maps
+    Maps,
+    /// /proc/<pid>/smaps
+    Smaps,
+    /// /proc/<pid>/statm
+    Statm,
+}
+
+impl ProcMemFileKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Maps => "maps",
+            Self::Smaps => "smaps",
+            Self::Statm => "statm",
+        }
+    }
+}
+
+/// 解析 /proc/<pid>/maps、smaps 与 statm，`self` 被解析为 `self_pid`
+///
+/// 不属于本模块的路径返回 `None`
+pub fn parse_proc_mem_path(path: &str, self_pid: u64) -> Option<(u64, ProcMemFileKind)> {
+    let (pid, name) = path.strip_prefix("/proc/")?.split_once('/')?;
+    let pid = match pid {
+        "self" => self_pid,
+        pid => pid.parse().ok()?,
+    };
+    let kind = match name {
+        "maps" => ProcMemFileKind::Maps,
+        "smaps" => ProcMemFileKind::Smaps,
+        "statm" => ProcMemFileKind::Statm,
+        _ => return None,
+    };
+    Some((pid, kind))
+}
+
+/// maps 与 smaps 中描述区域的一行
+fn map_line(usage: &MappingUsage, name: Option<&str>) -> String {
+    let perm = |flag, c| if usage.flags.contains(flag) { c } else { '-' };
+    let mut line = format!(
+        "{:08x}-{:08x} {}{}{}{} {:08x} 00:00 0 ",
+        usage.start,
+        usage.end,
+        perm(MappingFlags::READ, 'r'),
+        perm(MappingFlags::WRITE, 'w'),
+        perm(MappingFlags::EXECUTE, 'x'),
+        if usage.shared { 's' } else { 'p' },
+        usage.offset
+    );
+    // 与 Linux 一样，名称从第 73 列开始
+    if let Some(name) = name {
+        while line.len() < 72 {
+            line.push(' ');
+        }
+        line.push(' ');
+        line.push_str(name);
+    }
+    line.push('\n');
+    line
+}
+
+/// smaps 中以 kB 为单位的一项
+fn kb_field(name: &str, bytes: usize) -> String {
+    format!("{:<16}{:>8} kB\n", name, bytes / 1024)
+}
+
+/// 区域对应的 VmFlags
+fn vm_flags(usage: &MappingUsage) -> String {
+    let mut flags = String::new();
+    for (set, name) in [
+        (usage.flags.contains(MappingFlags::READ), "rd"),
+        (usage.flags.contains(MappingFlags::WRITE), "wr"),
+        (usage.flags.contains(MappingFlags::EXECUTE), "ex"),
+        (usage.shared, "sh"),
+        (true, "mr"),
+        (true, "mw"),
+        (true, "me"),
+        (usage.shared, "ms"),
+        (accountable(usage.flags, usage.shared), "ac"),
+        (usage.hugepage, "hg"),
+    ] {
+        if set {
+            flags.push_str(name);
+            flags.push(' ');
+        }
+    }
+    flags
+}
+
+/// smaps 中的一项
+fn smaps_entry(usage: &MappingUsage, name: Option<&str>) -> String {
+    let mut entry = map_line(usage, name);
+    for (field, bytes) in [
+        ("Size:", usage.size()),
+        ("KernelPageSize:", PAGE_SIZE_4K),
+        ("MMUPageSize:", PAGE_SIZE_4K),
+        ("Rss:", usage.rss),
+        ("Pss:", usage.pss),
+        ("Pss_Dirty:", usage.pss_dirty),
+        ("Shared_Clean:", usage.shared_clean),
+        ("Shared_Dirty:", usage.shared_dirty),
+        ("Private_Clean:", usage.private_clean),
+        ("Private_Dirty:", usage.private_dirty),
+        ("Referenced:", usage.rss),
+        ("Anonymous:", usage.anonymous),
+        ("KSM:", 0),
+        ("LazyFree:", usage.lazy_free),
+        ("AnonHugePages:", usage.anon_huge),
+        ("ShmemPmdMapped:", 0),
+        ("FilePmdMapped:", 0),
+        ("Shared_Hugetlb:", 0),
+        ("Private_Hugetlb:", 0),
+        ("Swap:", 0),
+        ("SwapPss:", 0),
+        ("Locked:", 0),
+    ] {
+        entry.push_str(&kb_field(field, bytes));
+    }
+    entry.push_str(&format!("THPeligible:    {}\n", usage.hugepage as u8));
+    entry.push_str(&format!("VmFlags: {}\n", vm_flags(usage)));
+    entry
+}
+
+/// statm 的内容，依次为总大小、常驻、共享、代码、库（总是 0）、数据与脏页（总是 0），以页为单位
+fn statm(usages: &[MappingUsage]) -> String {
+    let pages = |bytes: usize| bytes / PAGE_SIZE_4K;
+    let size: usize = usages.iter().map(|usage| usage.size()).sum();
+    let resident: usize = usages.iter().map(|usage| usage.rss).sum();
+    let shared: usize = usages.iter().map(|usage| usage.rss - usage.anonymous).sum();
+    let text: usize = usages
+        .iter()
+        .filter(|usage| usage.flags.contains(MappingFlags::EXECUTE) && usage.path.is_some())
+        .map(|usage| usage.size())
+        .sum();
+    let data: usize = usages
+        .iter()
+        .filter(|usage| accountable(usage.flags, usage.shared))
+        .map(|usage| usage.size())
+        .sum();
+    format!(
+        "{} {} {} {} 0 {} 0\n",
+        pages(size),
+        pages(resident),
+        pages(shared),
+        pages(text),
+        pages(data)
+    )
+}
+
+/// 生成进程 `pid` 的文件内容，进程不存在时返回 `NotFound`
+fn proc_mem_content(pid: u64, kind: ProcMemFileKind) -> AxResult<String> {
+    let process = PID2PC.lock().get(&pid).cloned().ok_or(AxError::NotFound)?;
+    let usages = process.memory_set.lock().lock().mapping_usage();
+    let heap_bottom = process.get_heap_bottom() as usize;
+    let name = |usage: &MappingUsage| match &usage.path {
+        Some(path) => Some(String::from(path)),
+        None if usage.start == heap_bottom => Some(String::from("[heap]")),
+        None => None,
+    };
+    Ok(match kind {
+        ProcMemFileKind::Maps => usages
+            .iter()
+            .map(|usage| map_line(usage, name(usage).as_deref()))
+            .collect(),
+        ProcMemFileKind::Smaps => usages
+            .iter()
+            .map(|usage| smaps_entry(usage, name(usage).as_deref()))
+            .collect(),
+        ProcMemFileKind::Statm => statm(&usages),
+    })
+}
+
+/// /proc/<pid>/maps、smaps 或 statm，内容在打开时生成
+pub struct ProcMemFile {
+    path: String,
+    content: String,
+    offset: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl ProcMemFile {
+    /// 打开进程 `pid` 的文件，进程不存在时返回 `NotFound`
+    pub fn open(pid: u64, kind: ProcMemFileKind, flags: OpenFlags) -> AxResult<Self> {
+        Ok(Self {
+            path: format!("/proc/{}/{}", pid, kind.name()),
+            content: proc_mem_content(pid, kind)?,
+            offset: Mutex::new(0),
+            flags: Mutex::new(flags),
+        })
+    }
+}
+
+impl FileIO for ProcMemFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut offset = self.offset.lock();
+        let content = self.content.as_bytes();
+        let start = (*offset).min(content.len());
+        let len = buf.len().min(content.len() - start);
+        buf[..len].copy_from_slice(&content[start..start + len]);
+        *offset = start + len;
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> AxResult<usize> {
+        Err(AxError::PermissionDenied)
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut offset = self.offset.lock();
+        let new_offset = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::Current(pos) => *offset as i64 + pos,
+            SeekFrom::End(pos) => self.content.len() as i64 + pos,
+        };
+        if new_offset < 0 {
+            return Err(AxError::InvalidInput);
+        }
+        *offset = new_offset as usize;
+        Ok(new_offset as u64)
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        self.path.clone()
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
+
+/// 若 `path` 是 /proc/<pid>/maps、smaps 或 statm，则打开对应的文件
+///
+/// 返回 `None` 表示该路径不由本模块处理
+pub fn open_proc_mem(path: &str, flags: OpenFlags) -> Option<AxResult<Arc<dyn FileIO>>> {
+    let (pid, kind) = parse_proc_mem_path(path, current_process().pid())?;
+    Some(ProcMemFile::open(pid, kind, flags).map(|file| Arc::new(file) as Arc<dyn FileIO>))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{map_line, parse_proc_mem_path, smaps_entry, statm, ProcMemFileKind};
+    use axhal::{mem::PAGE_SIZE_4K, paging::MappingFlags};
+    use axmem::MappingUsage;
+
+    #[test]
+    fn test_parse_proc_mem_path() {
+        assert_eq!(
+            parse_proc_mem_path("/proc/self/maps", 3),
+            Some((3, ProcMemFileKind::Maps))
+        );
+        assert_eq!(
+            parse_proc_mem_path("/proc/12/smaps", 3),
+            Some((12, ProcMemFileKind::Smaps))
+        );
+        assert_eq!(
+            parse_proc_mem_path("/proc/self/statm", 5),
+            Some((5, ProcMemFileKind::Statm))
+        );
+        assert_eq!(parse_proc_mem_path("/proc/self/smaps/x", 3), None);
+        assert_eq!(parse_proc_mem_path("/proc/self/status", 3), None);
+        assert_eq!(parse_proc_mem_path("/proc/abc/maps", 3), None);
+    }
+
+    fn anon_usage() -> MappingUsage {
+        let flags = MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER;
+        let mut usage = MappingUsage::new(0x10000, 0x20000, flags, false);
+        for _ in 0..3 {
+            usage.add_page(1, true);
+            usage.anonymous += PAGE_SIZE_4K;
+        }
+        usage
+    }
+
+    #[test]
+    fn test_map_line() {
+        let usage = anon_usage();
+        assert_eq!(
+            map_line(&usage, None),
+            "00010000-00020000 rw-p 00000000 00:00 0 \n"
+        );
+        let line = map_line(&usage, Some("[heap]"));
+        assert_eq!(line.find("[heap]"), Some(73));
+    }
+
+    #[test]
+    fn test_smaps_entry() {
+        let entry = smaps_entry(&anon_usage(), None);
+        let mut lines = entry.lines();
+        assert_eq!(
+            lines.next(),
+            Some("00010000-00020000 rw-p 00000000 00:00 0 ")
+        );
+        assert_eq!(lines.next(), Some("Size:                 64 kB"));
+        assert!(entry.contains("\nRss:                  12 kB\n"));
+        assert!(entry.contains("\nPss:                  12 kB\n"));
+        assert!(entry.contains("\nPrivate_Dirty:        12 kB\n"));
+        assert!(entry.contains("\nShared_Dirty:          0 kB\n"));
+        assert!(entry.contains("\nAnonymous:            12 kB\n"));
+        assert!(entry.ends_with("\nVmFlags: rd wr mr mw me ac \n"));
+    }
+
+    #[test]
+    fn test_statm() {
+        let shared = MappingUsage::new(0x30000, 0x32000, MappingFlags::READ, true);
+        assert_eq!(statm(&[anon_usage(), shared]), "18 3 0 0 0 16 0\n");
+    }
+}
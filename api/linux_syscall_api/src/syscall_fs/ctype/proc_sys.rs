@@ -0,0 +1,205 @@
+//! /proc/sys/vm/overcommit_memory 与 /proc/vmstat
+//!
+//! procfs 中的同名文件只是占位，打开时由这里接管：
+//! - overcommit_memory 读出的是当前的 overcommit 策略，写入 0、1、2 即切换为对应的策略，
+//!   其余内容返回 EINVAL。
+//! - vmstat 只读，报告页缓存的页数以及页面回收与透明大页的累计统计。
+extern crate alloc;
+use alloc::{format, string::String};
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
+use axmem::{
+    overcommit_policy, page_cache_pages, reclaim_stats, set_overcommit_policy, thp_stats,
+    OvercommitPolicy,
+};
+use axsync::Mutex;
+
+/// overcommit_memory 在 procfs 中的路径
+pub const OVERCOMMIT_MEMORY_PATH: &str = "/proc/sys/vm/overcommit_memory";
+
+/// vmstat 在 procfs 中的路径
+pub const VMSTAT_PATH: &str = "/proc/vmstat";
+
+/// 由本模块接管的文件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcVmFileKind {
+    /// /proc/sys/vm/overcommit_memory
+    OvercommitMemory,
+    /// /proc/vmstat
+    Vmstat,
+}
+
+impl ProcVmFileKind {
+    /// 根据路径判断文件种类，不由本模块处理的路径返回 None
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            OVERCOMMIT_MEMORY_PATH => Some(Self::OvercommitMemory),
+            VMSTAT_PATH => Some(Self::Vmstat),
+            _ => None,
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Self::OvercommitMemory => OVERCOMMIT_MEMORY_PATH,
+            Self::Vmstat => VMSTAT_PATH,
+        }
+    }
+}
+
+/// 由本模块接管的文件，每次读取时生成内容
+pub struct ProcVmFile {
+    kind: ProcVmFileKind,
+    offset: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl ProcVmFile {
+    /// 以 `flags` 打开 `kind` 对应的文件
+    pub fn new(kind: ProcVmFileKind, flags: OpenFlags) -> Self {
+        Self {
+            kind,
+            offset: Mutex::new(0),
+            flags: Mutex::new(flags),
+        }
+    }
+
+    fn content(&self) -> String {
+        match self.kind {
+            ProcVmFileKind::OvercommitMemory => format!("{}\n", overcommit_policy() as usize),
+            ProcVmFileKind::Vmstat => {
+                let stats = reclaim_stats();
+                let thp = thp_stats();
+                format!(
+                    concat!(
+                        "nr_file_pages {}\n",
+                        "pgscan_direct {}\n",
+                        "pgsteal_direct {}\n",
+                        "thp_fault_alloc {}\n",
+                        "thp_collapse_alloc {}\n",
+                    ),
+                    page_cache_pages(),
+                    stats.scanned,
+                    stats.reclaimed,
+                    thp.fault_alloc,
+                    thp.collapse_alloc
+                )
+            }
+        }
+    }
+}
+
+/// 解析写入的策略，允许结尾的空白
+fn parse_policy(buf: &[u8]) -> AxResult<OvercommitPolicy> {
+    let text = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+    let mode = text
+        .trim_end()
+        .parse::<usize>()
+        .map_err(|_| AxError::InvalidInput)?;
+    OvercommitPolicy::try_from(mode)
+}
+
+impl FileIO for ProcVmFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut offset = self.offset.lock();
+        let content = self.content();
+        let content = content.as_bytes();
+        let start = (*offset).min(content.len());
+        let len = buf.len().min(content.len() - start);
+        buf[..len].copy_from_slice(&content[start..start + len]);
+        *offset = start + len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        if self.kind != ProcVmFileKind::OvercommitMemory {
+            return Err(AxError::PermissionDenied);
+        }
+        set_overcommit_policy(parse_policy(buf)?);
+        Ok(buf.len())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut offset = self.offset.lock();
+        let new_offset = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::Current(pos) => *offset as i64 + pos,
+            SeekFrom::End(pos) => self.content().len() as i64 + pos,
+        };
+        if new_offset < 0 {
+            return Err(AxError::InvalidInput);
+        }
+        *offset = new_offset as usize;
+        Ok(new_offset as u64)
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        self.kind == ProcVmFileKind::OvercommitMemory
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        String::from(self.kind.path())
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_policy, ProcVmFileKind};
+    use axmem::OvercommitPolicy;
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(parse_policy(b"2\n"), Ok(OvercommitPolicy::Never));
+        assert_eq!(parse_policy(b"1"), Ok(OvercommitPolicy::Always));
+        assert_eq!(parse_policy(b"0 \n"), Ok(OvercommitPolicy::Guess));
+        assert!(parse_policy(b"3\n").is_err());
+        assert!(parse_policy(b"never").is_err());
+        assert!(parse_policy(b"").is_err());
+    }
+
+    #[test]
+    fn test_file_kind() {
+        assert_eq!(
+            ProcVmFileKind::from_path("/proc/sys/vm/overcommit_memory"),
+            Some(ProcVmFileKind::OvercommitMemory)
+        );
+        assert_eq!(
+            ProcVmFileKind::from_path("/proc/vmstat"),
+            Some(ProcVmFileKind::Vmstat)
+        );
+        assert_eq!(ProcVmFileKind::from_path("/proc/meminfo"), None);
+    }
+}
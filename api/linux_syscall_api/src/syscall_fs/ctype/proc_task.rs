@@ -0,0 +1,675 @@
+//! /proc/<pid>/task 目录及其下各线程的 stat、status、comm、schedstat、sched、oom_score_adj 文件，
+//! 以及进程的 /proc/<pid>/status、schedstat、sched 与 oom_score_adj
+//!
+//! 这些文件并不存在于 procfs 中，而是在打开时根据线程的调度信息生成内容。
+//! 启用 `sched_trace` 时 status 中还包括上下文切换次数，schedstat 与 sched 中还包括在就绪队列中
+//! 等待的时间（调度延迟），否则这些项为 0。进程的文件报告的是所有线程之和，最长等待时间取各线程的最大值。
+//! oom_score_adj 可写，用于调整进程被 OOM killer 选中的优先级，同一进程的所有线程共享这一值。
+//! 进程的符号链接 /proc/<pid>/exe 与 /proc/<pid>/cwd 由 readlinkat 通过 [`proc_link_target`] 读取。
+extern crate alloc;
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use axerrno::{AxError, AxResult, LinuxError};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
+use axhal::time::{time_to_nanos, Duration};
+use axprocess::{
+    current_process,
+    oom::{OOM_SCORE_ADJ_MAX, OOM_SCORE_ADJ_MIN},
+    uaccess::copy_to_user,
+    Process, PID2PC,
+};
+use axsync::Mutex;
+use axtask::AxTaskRef;
+
+use crate::{DirEnt, DirEntType};
+
+/// comm 中线程名称的最大长度，与 prctl 的 PR_SET_NAME 一致
+const TASK_COMM_LEN: usize = 15;
+
+/// 线程目录下的文件
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskFileKind {
+    /// /proc/<pid>/task/<tid>/stat
+    Stat,
+    /// /proc/<pid>/task/<tid>/status
+    Status,
+    /// /proc/<pid>/task/<tid>/comm
+    Comm,
+    /// /proc/<pid>/task/<tid>/schedstat，依次为运行时间、等待时间（纳秒）与被调度运行的次数
+    Schedstat,
+    /// /proc/<pid>/task/<tid>/sched，以毫秒为单位的运行时间、调度延迟与切换次数
+    Sched,
+    /// /proc/<pid>/task/<tid>/oom_score_adj，范围为 [-1000, 1000]
+    OomScoreAdj,
+}
+
+impl TaskFileKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Stat => "stat",
+            Self::Status => "status",
+            Self::Comm => "comm",
+            Self::Schedstat => "schedstat",
+            Self::Sched => "sched",
+            Self::OomScoreAdj => "oom_score_adj",
+        }
+    }
+}
+
+/// 解析后的 /proc/<pid>/task 路径
+#[derive(PartialEq, Eq, Debug)]
+pub enum ProcTaskPath {
+    /// /proc/<pid>/task
+    TaskDir(u64),
+    /// /proc/<pid>/task/<tid>/<file>
+    TaskFile(u64, u64, TaskFileKind),
+    /// /proc/<pid>/status、/proc/<pid>/schedstat、/proc/<pid>/sched 与 /proc/<pid>/oom_score_adj
+    ProcessFile(u64, TaskFileKind),
+}
+
+/// 解析 /proc/<pid>/task 下的路径与进程的 status、schedstat、sched、oom_score_adj，`self` 被解析为 `self_pid`
+///
+/// 不属于该目录的路径返回 `None`
+pub fn parse_proc_task_path(path: &str, self_pid: u64) -> Option<ProcTaskPath> {
+    let mut parts = path
+        .strip_prefix("/proc/")?
+        .split('/')
+        .filter(|part| !part.is_empty());
+    let pid = match parts.next()? {
+        "self" => self_pid,
+        pid => pid.parse().ok()?,
+    };
+    let kind = match parts.next()? {
+        "task" => None,
+        "status" => Some(TaskFileKind::Status),
+        "schedstat" => Some(TaskFileKind::Schedstat),
+        "sched" => Some(TaskFileKind::Sched),
+        "oom_score_adj" => Some(TaskFileKind::OomScoreAdj),
+        _ => return None,
+    };
+    if let Some(kind) = kind {
+        return parts
+            .next()
+            .is_none()
+            .then_some(ProcTaskPath::ProcessFile(pid, kind));
+    }
+    let tid = match parts.next() {
+        Some(tid) => tid.parse().ok()?,
+        None => return Some(ProcTaskPath::TaskDir(pid)),
+    };
+    let kind = match parts.next()? {
+        "stat" => TaskFileKind::Stat,
+        "status" => TaskFileKind::Status,
+        "comm" => TaskFileKind::Comm,
+        "schedstat" => TaskFileKind::Schedstat,
+        "sched" => TaskFileKind::Sched,
+        "oom_score_adj" => TaskFileKind::OomScoreAdj,
+        _ => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(ProcTaskPath::TaskFile(pid, tid, kind))
+}
+
+/// /proc/<pid> 下的符号链接
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProcLink {
+    /// /proc/<pid>/exe，进程的可执行文件
+    Exe(u64),
+    /// /proc/<pid>/cwd，进程的工作目录
+    Cwd(u64),
+}
+
+/// 解析 /proc/<pid>/exe 与 /proc/<pid>/cwd，`self` 被解析为 `self_pid`
+pub fn parse_proc_link(path: &str, self_pid: u64) -> Option<ProcLink> {
+    let (pid, name) = path.strip_prefix("/proc/")?.split_once('/')?;
+    let pid = match pid {
+        "self" => self_pid,
+        pid => pid.parse().ok()?,
+    };
+    match name {
+        "exe" => Some(ProcLink::Exe(pid)),
+        "cwd" => Some(ProcLink::Cwd(pid)),
+        _ => None,
+    }
+}
+
+/// 若 `path` 是 /proc/<pid> 下的符号链接，返回其指向的路径，进程不存在时返回 `NotFound`
+///
+/// 返回 `None` 表示该路径不由本模块处理
+pub fn proc_link_target(path: &str) -> Option<AxResult<String>> {
+    let link = parse_proc_link(path, current_process().pid())?;
+    let (ProcLink::Exe(pid) | ProcLink::Cwd(pid)) = link;
+    let Some(process) = find_process(pid) else {
+        return Some(Err(AxError::NotFound));
+    };
+    Some(Ok(match link {
+        ProcLink::Exe(_) => process.get_file_path(),
+        ProcLink::Cwd(_) => process.fs_context.canonical_cwd(),
+    }))
+}
+
+fn find_process(pid: u64) -> Option<Arc<Process>> {
+    PID2PC.lock().get(&pid).cloned()
+}
+
+fn find_task(process: &Process, tid: u64) -> Option<AxTaskRef> {
+    process
+        .tasks
+        .lock()
+        .iter()
+        .find(|task| task.id().as_u64() == tid)
+        .cloned()
+}
+
+/// status 末尾的上下文切换次数，为 `tasks` 中各线程之和
+#[cfg(feature = "sched_trace")]
+fn ctxt_switches(tasks: &[AxTaskRef]) -> String {
+    let (voluntary, involuntary) = switch_counts(tasks);
+    format!(
+        "voluntary_ctxt_switches:\t{}\nnonvoluntary_ctxt_switches:\t{}\n",
+        voluntary, involuntary
+    )
+}
+
+#[cfg(not(feature = "sched_trace"))]
+fn ctxt_switches(_tasks: &[AxTaskRef]) -> String {
+    String::new()
+}
+
+/// `tasks` 中各线程自愿与非自愿上下文切换次数之和
+#[cfg(feature = "sched_trace")]
+fn switch_counts(tasks: &[AxTaskRef]) -> (usize, usize) {
+    tasks.iter().fold((0, 0), |(voluntary, involuntary), task| {
+        let stat = task.sched_stat();
+        (
+            voluntary + stat.voluntary_switches(),
+            involuntary + stat.involuntary_switches(),
+        )
+    })
+}
+
+#[cfg(not(feature = "sched_trace"))]
+fn switch_counts(_tasks: &[AxTaskRef]) -> (usize, usize) {
+    (0, 0)
+}
+
+/// `tasks` 在就绪队列中等待的统计：总等待时间、单次最长等待时间与被调度运行的次数
+#[cfg(feature = "sched_trace")]
+fn wait_stats(tasks: &[AxTaskRef]) -> (Duration, Duration, usize) {
+    tasks.iter().fold(
+        (Duration::ZERO, Duration::ZERO, 0),
+        |(sum, max, count), task| {
+            let stat = task.sched_stat();
+            (
+                sum + stat.wait_time(),
+                max.max(stat.max_wait()),
+                count + stat.run_count(),
+            )
+        },
+    )
+}
+
+#[cfg(not(feature = "sched_trace"))]
+fn wait_stats(_tasks: &[AxTaskRef]) -> (Duration, Duration, usize) {
+    (Duration::ZERO, Duration::ZERO, 0)
+}
+
+/// `tasks` 在用户态与内核态运行的总时间
+fn exec_runtime(tasks: &[AxTaskRef]) -> Duration {
+    tasks
+        .iter()
+        .map(|task| {
+            let (utime, stime) = task.time_stat_output();
+            utime + stime
+        })
+        .sum()
+}
+
+/// sched 中以毫秒为单位、保留 6 位小数的一项，格式与 Linux 相同
+fn sched_ms_line(name: &str, time: Duration) -> String {
+    let ns = time_to_nanos(time);
+    format!(
+        "{:<45}:{:>21}.{:06}\n",
+        name,
+        ns / 1_000_000,
+        ns % 1_000_000
+    )
+}
+
+/// sched 中的计数项
+fn sched_count_line(name: &str, count: usize) -> String {
+    format!("{:<45}:{:>21}\n", name, count)
+}
+
+/// sched 的内容，`tasks` 为统计的线程
+fn sched_content(tid: u64, comm: &str, tasks: &[AxTaskRef]) -> String {
+    let (wait_sum, wait_max, wait_count) = wait_stats(tasks);
+    let wait_avg = match wait_count {
+        0 => Duration::ZERO,
+        count => wait_sum / count as u32,
+    };
+    let (voluntary, involuntary) = switch_counts(tasks);
+    let mut content = format!(
+        "{} ({}, #threads: {})\n{}\n",
+        comm,
+        tid,
+        tasks.len(),
+        "-".repeat(67)
+    );
+    content.push_str(&sched_ms_line("se.sum_exec_runtime", exec_runtime(tasks)));
+    content.push_str(&sched_ms_line("wait_max", wait_max));
+    content.push_str(&sched_ms_line("wait_sum", wait_sum));
+    content.push_str(&sched_ms_line("wait_avg", wait_avg));
+    content.push_str(&sched_count_line("wait_count", wait_count));
+    content.push_str(&sched_count_line("nr_switches", voluntary + involuntary));
+    content.push_str(&sched_count_line("nr_voluntary_switches", voluntary));
+    content.push_str(&sched_count_line("nr_involuntary_switches", involuntary));
+    content
+}
+
+/// 生成线程目录下文件的内容，status、schedstat 与 sched 中的统计取自 `counted` 中的线程
+fn task_file_content(
+    process: &Process,
+    task: &AxTaskRef,
+    kind: TaskFileKind,
+    counted: &[AxTaskRef],
+) -> String {
+    let tid = task.id().as_u64();
+    let comm = task.name();
+    let state = task.state_letter();
+    match kind {
+        // 仅提供 pid、comm、state、ppid、pgrp、session 等前几项
+        TaskFileKind::Stat => format!(
+            "{} ({}) {} {} {} {}\n",
+            tid,
+            comm,
+            state,
+            process.get_parent(),
+            process.pgid(),
+            process.pid()
+        ),
+        TaskFileKind::Status => format!(
+            "Name:\t{}\nState:\t{}\nTgid:\t{}\nPid:\t{}\nPPid:\t{}\n{}",
+            comm,
+            state,
+            process.pid(),
+            tid,
+            process.get_parent(),
+            ctxt_switches(counted)
+        ),
+        TaskFileKind::Comm => format!("{}\n", comm),
+        TaskFileKind::Schedstat => {
+            let (wait_sum, _, wait_count) = wait_stats(counted);
+            format!(
+                "{} {} {}\n",
+                time_to_nanos(exec_runtime(counted)),
+                time_to_nanos(wait_sum),
+                wait_count
+            )
+        }
+        TaskFileKind::Sched => sched_content(tid, &comm, counted),
+        TaskFileKind::OomScoreAdj => format!("{}\n", process.get_oom_score_adj()),
+    }
+}
+
+/// /proc/<pid>/task/<tid> 下的文件，内容在打开时生成
+pub struct ProcTaskFile {
+    process: Arc<Process>,
+    task: AxTaskRef,
+    kind: TaskFileKind,
+    path: String,
+    content: String,
+    offset: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl ProcTaskFile {
+    /// 打开指定线程的文件，线程不存在时返回 `NotFound`
+    pub fn open(pid: u64, tid: u64, kind: TaskFileKind, flags: OpenFlags) -> AxResult<Self> {
+        let process = find_process(pid).ok_or(AxError::NotFound)?;
+        let task = find_task(&process, tid).ok_or(AxError::NotFound)?;
+        let content = task_file_content(&process, &task, kind, core::slice::from_ref(&task));
+        Ok(Self {
+            process,
+            task,
+            kind,
+            path: format!("/proc/{}/task/{}/{}", pid, tid, kind.name()),
+            content,
+            offset: Mutex::new(0),
+            flags: Mutex::new(flags),
+        })
+    }
+
+    /// 打开进程的 status、schedstat、sched 或 oom_score_adj，内容以主线程为准，统计为所有线程之和
+    pub fn open_process_file(pid: u64, kind: TaskFileKind, flags: OpenFlags) -> AxResult<Self> {
+        let process = find_process(pid).ok_or(AxError::NotFound)?;
+        let tasks = process.tasks.lock().clone();
+        let task = tasks
+            .iter()
+            .find(|task| task.is_leader())
+            .or(tasks.first())
+            .cloned()
+            .ok_or(AxError::NotFound)?;
+        let content = task_file_content(&process, &task, kind, &tasks);
+        Ok(Self {
+            process,
+            task,
+            kind,
+            path: format!("/proc/{}/{}", pid, kind.name()),
+            content,
+            offset: Mutex::new(0),
+            flags: Mutex::new(flags),
+        })
+    }
+}
+
+impl FileIO for ProcTaskFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut offset = self.offset.lock();
+        let content = self.content.as_bytes();
+        let start = (*offset).min(content.len());
+        let len = buf.len().min(content.len() - start);
+        buf[..len].copy_from_slice(&content[start..start + len]);
+        *offset = start + len;
+        Ok(len)
+    }
+
+    /// 只有 comm 与 oom_score_adj 可写，写入 comm 的内容即为新的线程名
+    ///
+    /// 写入 oom_score_adj 的值不是整数或超出 [-1000, 1000] 时返回 EINVAL
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        if self.kind == TaskFileKind::OomScoreAdj {
+            let adj: i32 = core::str::from_utf8(buf)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .ok_or(AxError::InvalidInput)?;
+            if !(OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&adj) {
+                return Err(AxError::InvalidInput);
+            }
+            self.process.set_oom_score_adj(adj);
+            return Ok(buf.len());
+        }
+        if self.kind != TaskFileKind::Comm {
+            return Err(AxError::PermissionDenied);
+        }
+        let name = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+        let name = name.trim_end_matches('\n');
+        let end = name
+            .char_indices()
+            .map(|(idx, c)| idx + c.len_utf8())
+            .take_while(|&end| end <= TASK_COMM_LEN)
+            .last()
+            .unwrap_or(0);
+        self.task.set_name(&name[..end]);
+        Ok(buf.len())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        let mut offset = self.offset.lock();
+        let new_offset = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::Current(pos) => *offset as i64 + pos,
+            SeekFrom::End(pos) => self.content.len() as i64 + pos,
+        };
+        if new_offset < 0 {
+            return Err(AxError::InvalidInput);
+        }
+        *offset = new_offset as usize;
+        Ok(new_offset as u64)
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        matches!(self.kind, TaskFileKind::Comm | TaskFileKind::OomScoreAdj)
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        self.path.clone()
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        matches!(self.kind, TaskFileKind::Comm | TaskFileKind::OomScoreAdj)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
+
+/// /proc/<pid>/task 目录
+///
+/// 打开时即记录下当前所有线程的 tid，之后的 getdents64 均基于这份快照，
+/// 因此即使期间有线程创建或退出，遍历结果也是一致的
+pub struct ProcTaskDir {
+    pid: u64,
+    tids: Vec<u64>,
+    /// 下一个要返回的目录项序号
+    index: Mutex<usize>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl ProcTaskDir {
+    /// 打开指定进程的 task 目录，进程不存在时返回 `NotFound`
+    pub fn open(pid: u64, flags: OpenFlags) -> AxResult<Self> {
+        let process = find_process(pid).ok_or(AxError::NotFound)?;
+        let tids = process
+            .tasks
+            .lock()
+            .iter()
+            .map(|task| task.id().as_u64())
+            .collect();
+        Ok(Self {
+            pid,
+            tids,
+            index: Mutex::new(0),
+            flags: Mutex::new(flags),
+        })
+    }
+
+    /// 将目录项以 linux_dirent64 的格式写入用户地址 `buf`，最多写入 `len` 字节，返回写入的字节数
+    ///
+    /// 写入用户内存失败时返回 EFAULT，且不推进读取位置
+    pub fn read_dirents(&self, buf: usize, len: usize) -> Result<usize, LinuxError> {
+        let mut index = self.index.lock();
+        let mut next = *index;
+        let mut dirents = Vec::new();
+        let total = self.tids.len() + 2;
+        while next < total {
+            let (name, ino, type_) = match next {
+                0 => (String::from("."), self.pid, DirEntType::Dir),
+                1 => (String::from(".."), 1, DirEntType::Dir),
+                i => (
+                    format!("{}", self.tids[i - 2]),
+                    self.tids[i - 2],
+                    DirEntType::Dir,
+                ),
+            };
+            let name = name.as_bytes();
+            // 文件名以 '\0' 结尾，且按 8 字节对齐
+            let entry_size = (DirEnt::fixed_size() + name.len() + 1 + 7) & !7;
+            if dirents.len() + entry_size > len {
+                break;
+            }
+            DirEnt::append_to(
+                &mut dirents,
+                ino,
+                (next + 1) as u64,
+                entry_size,
+                type_,
+                name,
+            );
+            next += 1;
+        }
+        copy_to_user(buf, &dirents)?;
+        *index = next;
+        Ok(dirents.len())
+    }
+}
+
+impl FileIO for ProcTaskDir {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::DirDesc
+    }
+
+    fn get_path(&self) -> String {
+        format!("/proc/{}/task", self.pid)
+    }
+
+    fn seek(&self, pos: SeekFrom) -> AxResult<u64> {
+        // 只支持回到目录开头
+        match pos {
+            SeekFrom::Start(0) => {
+                *self.index.lock() = 0;
+                Ok(0)
+            }
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
+
+/// 若 `path` 位于 /proc/<pid>/task 下，则打开对应的文件
+///
+/// 返回 `None` 表示该路径不由本模块处理
+pub fn open_proc_task(path: &str, flags: OpenFlags) -> Option<AxResult<Arc<dyn FileIO>>> {
+    let self_pid = current_process().pid();
+    Some(match parse_proc_task_path(path, self_pid)? {
+        ProcTaskPath::TaskDir(pid) => {
+            ProcTaskDir::open(pid, flags).map(|dir| Arc::new(dir) as Arc<dyn FileIO>)
+        }
+        ProcTaskPath::TaskFile(pid, tid, kind) => {
+            ProcTaskFile::open(pid, tid, kind, flags).map(|file| Arc::new(file) as Arc<dyn FileIO>)
+        }
+        ProcTaskPath::ProcessFile(pid, kind) => ProcTaskFile::open_process_file(pid, kind, flags)
+            .map(|file| Arc::new(file) as Arc<dyn FileIO>),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_proc_link, parse_proc_task_path, sched_count_line, sched_ms_line, Duration, ProcLink,
+        ProcTaskPath, TaskFileKind,
+    };
+
+    #[test]
+    fn test_parse_proc_task_path() {
+        assert_eq!(
+            parse_proc_task_path("/proc/3/task", 1),
+            Some(ProcTaskPath::TaskDir(3))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/self/task/", 7),
+            Some(ProcTaskPath::TaskDir(7))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/3/task/9/comm", 1),
+            Some(ProcTaskPath::TaskFile(3, 9, TaskFileKind::Comm))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/self/task/9/stat", 5),
+            Some(ProcTaskPath::TaskFile(5, 9, TaskFileKind::Stat))
+        );
+        assert_eq!(parse_proc_task_path("/proc/3/task/9", 1), None);
+        assert_eq!(parse_proc_task_path("/proc/3/task/9/maps", 1), None);
+        assert_eq!(parse_proc_task_path("/proc/self/stat", 1), None);
+        assert_eq!(
+            parse_proc_task_path("/proc/self/status", 4),
+            Some(ProcTaskPath::ProcessFile(4, TaskFileKind::Status))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/12/status", 1),
+            Some(ProcTaskPath::ProcessFile(12, TaskFileKind::Status))
+        );
+        assert_eq!(parse_proc_task_path("/proc/12/status/x", 1), None);
+        assert_eq!(
+            parse_proc_task_path("/proc/self/schedstat", 4),
+            Some(ProcTaskPath::ProcessFile(4, TaskFileKind::Schedstat))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/3/task/9/sched", 1),
+            Some(ProcTaskPath::TaskFile(3, 9, TaskFileKind::Sched))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/self/oom_score_adj", 4),
+            Some(ProcTaskPath::ProcessFile(4, TaskFileKind::OomScoreAdj))
+        );
+        assert_eq!(
+            parse_proc_task_path("/proc/3/task/9/oom_score_adj", 1),
+            Some(ProcTaskPath::TaskFile(3, 9, TaskFileKind::OomScoreAdj))
+        );
+        assert_eq!(parse_proc_task_path("/dev/tty", 1), None);
+    }
+
+    #[test]
+    fn test_parse_proc_link() {
+        assert_eq!(parse_proc_link("/proc/self/exe", 3), Some(ProcLink::Exe(3)));
+        assert_eq!(parse_proc_link("/proc/12/exe", 3), Some(ProcLink::Exe(12)));
+        assert_eq!(parse_proc_link("/proc/self/cwd", 5), Some(ProcLink::Cwd(5)));
+        assert_eq!(parse_proc_link("/proc/self/exe/x", 3), None);
+        assert_eq!(parse_proc_link("/proc/self/status", 3), None);
+        assert_eq!(parse_proc_link("/proc/abc/exe", 3), None);
+        assert_eq!(parse_proc_link("/tmp/exe", 3), None);
+    }
+
+    #[test]
+    fn test_sched_lines() {
+        assert_eq!(
+            sched_ms_line("wait_max", Duration::from_nanos(12_345_678)),
+            "wait_max                                     :                   12.345678\n"
+        );
+        assert_eq!(
+            sched_count_line("wait_count", 5),
+            "wait_count                                   :                    5\n"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! 文件的时间戳
+//!
+//! 时间戳按 inode 号记录，同一文件的所有文件描述符与按路径进行的 stat 看到的是同一份时间戳。
+//! 各个系统调用都通过这里的函数更新时间戳，规则与 Linux 一致：
+//! - 写入与截断修改了内容，更新 mtime 与 ctime；
+//! - chmod、创建链接等只修改元数据的操作只更新 ctime；
+//! - 读取按文件所在挂载点的 [`AtimePolicy`] 更新 atime，以 O_NOATIME 打开的文件读取时不更新；
+//! - utimensat 设置 atime 与 mtime，同时把 ctime 更新为当前时间。
+//!
+//! 时间戳使用墙上时间。文件的时间戳第一次被用到时，三个时间戳都取当前时间
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use axhal::time::wall_time_nanos;
+use axsync::Mutex;
+
+use super::{
+    file::{inode_number, FileMetaData},
+    mount::atime_policy,
+};
+use crate::{TimeSecs, UTIME_NOW, UTIME_OMIT};
+
+/// relatime 策略下 atime 至少每隔这么久更新一次，单位为秒
+const RELATIME_INTERVAL_SECS: usize = 24 * 60 * 60;
+
+/// mount 的 flags：读取时不更新 atime
+const MS_NOATIME: usize = 1 << 10;
+/// mount 的 flags：每次读取都更新 atime
+const MS_STRICTATIME: usize = 1 << 24;
+
+/// 读取文件时更新 atime 的策略，由挂载时的选项决定
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AtimePolicy {
+    /// 每次读取都更新，对应 MS_STRICTATIME
+    Strict,
+    /// 只有 atime 不晚于 mtime 或 ctime，或者距今已超过一天时才更新，对应 MS_RELATIME，是默认的策略
+    #[default]
+    Relative,
+    /// 从不更新，对应 MS_NOATIME
+    Never,
+}
+
+impl AtimePolicy {
+    /// mount 的 flags 对应的策略
+    ///
+    /// 与 Linux 一样，MS_STRICTATIME 优先于 MS_NOATIME，两者都没有时使用 relatime
+    pub fn from_mount_flags(flags: usize) -> Self {
+        if flags & MS_STRICTATIME != 0 {
+            Self::Strict
+        } else if flags & MS_NOATIME != 0 {
+            Self::Never
+        } else {
+            Self::Relative
+        }
+    }
+
+    /// 在 `now` 读取时间戳为 `times` 的文件时是否需要更新 atime
+    fn should_update(self, times: &FileMetaData, now: &TimeSecs) -> bool {
+        match self {
+            Self::Strict => true,
+            Self::Never => false,
+            Self::Relative => {
+                let atime = times.atime.turn_to_nanos();
+                atime <= times.mtime.turn_to_nanos()
+                    || atime <= times.ctime.turn_to_nanos()
+                    || now.tv_sec >= times.atime.tv_sec + RELATIME_INTERVAL_SECS
+            }
+        }
+    }
+}
+
+/// 各个 inode 的时间戳
+static INODE_TIMES: Mutex<BTreeMap<u64, FileMetaData>> = Mutex::new(BTreeMap::new());
+
+/// 当前的墙上时间
+fn now() -> TimeSecs {
+    TimeSecs::from_nanos(wall_time_nanos() as usize)
+}
+
+/// 对 `path` 对应的文件的时间戳执行 `f`
+fn with_times<R>(path: &str, f: impl FnOnce(&mut FileMetaData) -> R) -> R {
+    let ino = inode_number(path);
+    let mut inode_times = INODE_TIMES.lock();
+    let times = inode_times.entry(ino).or_insert_with(|| {
+        let now = now();
+        FileMetaData {
+            atime: now,
+            mtime: now,
+            ctime: now,
+        }
+    });
+    f(times)
+}
+
+/// 文件当前的时间戳
+pub fn file_times(path: &str) -> FileMetaData {
+    with_times(path, |times| *times)
+}
+
+/// 文件刚被创建，三个时间戳都取当前时间
+///
+/// inode 号按路径分配，同一路径上之前的文件留下的时间戳在这里被覆盖
+pub fn file_created(path: &str) {
+    let now = now();
+    with_times(path, |times| {
+        *times = FileMetaData {
+            atime: now,
+            mtime: now,
+            ctime: now,
+        }
+    });
+}
+
+/// 文件的内容被修改
+pub fn file_modified(path: &str) {
+    let now = now();
+    with_times(path, |times| {
+        times.mtime = now;
+        times.ctime = now;
+    });
+}
+
+/// 文件的元数据被修改
+pub fn file_changed(path: &str) {
+    let now = now();
+    with_times(path, |times| times.ctime = now);
+}
+
+/// 文件被读取，按文件所在挂载点的策略更新 atime
+pub fn file_accessed(path: &str) {
+    let policy = atime_policy(path);
+    if policy == AtimePolicy::Never {
+        return;
+    }
+    let now = now();
+    with_times(path, |times| {
+        if policy.should_update(times, &now) {
+            times.atime = now;
+        }
+    });
+}
+
+/// 由 utimensat 设置 atime 与 mtime，时间的 tv_nsec 可以为 UTIME_NOW 或 UTIME_OMIT
+///
+/// 两者都为 UTIME_OMIT 时不修改任何时间戳，否则 ctime 也会更新
+pub fn set_file_times(path: &str, atime: &TimeSecs, mtime: &TimeSecs) {
+    if atime.tv_nsec == UTIME_OMIT && mtime.tv_nsec == UTIME_OMIT {
+        return;
+    }
+    let now = now();
+    let set = |time: &mut TimeSecs, new: &TimeSecs| match new.tv_nsec {
+        UTIME_OMIT => {}
+        UTIME_NOW => *time = now,
+        _ => *time = *new,
+    };
+    with_times(path, |times| {
+        set(&mut times.atime, atime);
+        set(&mut times.mtime, mtime);
+        times.ctime = now;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(tv_sec: usize) -> TimeSecs {
+        TimeSecs { tv_sec, tv_nsec: 0 }
+    }
+
+    fn times(atime: usize, mtime: usize, ctime: usize) -> FileMetaData {
+        FileMetaData {
+            atime: secs(atime),
+            mtime: secs(mtime),
+            ctime: secs(ctime),
+        }
+    }
+
+    #[test]
+    fn test_mount_flags() {
+        assert_eq!(AtimePolicy::from_mount_flags(0), AtimePolicy::Relative);
+        assert_eq!(
+            AtimePolicy::from_mount_flags(1 << 21),
+            AtimePolicy::Relative
+        );
+        assert_eq!(
+            AtimePolicy::from_mount_flags(MS_NOATIME),
+            AtimePolicy::Never
+        );
+        assert_eq!(
+            AtimePolicy::from_mount_flags(MS_NOATIME | MS_STRICTATIME),
+            AtimePolicy::Strict
+        );
+    }
+
+    #[test]
+    fn test_relatime() {
+        let policy = AtimePolicy::Relative;
+        // 修改之后的第一次读取更新 atime，之后的读取不再更新
+        assert!(policy.should_update(&times(100, 200, 200), &secs(300)));
+        assert!(policy.should_update(&times(100, 50, 200), &secs(300)));
+        assert!(!policy.should_update(&times(300, 200, 200), &secs(400)));
+        // atime 超过一天没有更新时仍然更新
+        let day = RELATIME_INTERVAL_SECS;
+        assert!(!policy.should_update(&times(300, 200, 200), &secs(300 + day - 1)));
+        assert!(policy.should_update(&times(300, 200, 200), &secs(300 + day)));
+        assert!(AtimePolicy::Strict.should_update(&times(300, 200, 200), &secs(400)));
+        assert!(!AtimePolicy::Never.should_update(&times(100, 200, 200), &secs(400)));
+    }
+}
@@ -18,13 +18,14 @@ pub enum FsSyscallId {
     EVENT_FD = 19,
     EPOLL_CREATE = 20,
     EPOLL_CTL = 21,
-    EPOLL_WAIT = 22,
+    EPOLL_PWAIT = 22,
     DUP = 23,
     DUP3 = 24,
     FCNTL64 = 25,
     IOCTL = 29,
     MKDIRAT = 34,
     UNLINKAT = 35,
+    SYMLINKAT = 36,
     LINKAT = 37,
     RENAMEAT = 38,
     UNMOUNT = 39,
@@ -33,6 +34,7 @@ pub enum FsSyscallId {
     FTRUNCATE64 = 46,
     FACCESSAT = 48,
     CHDIR = 49,
+    CHROOT = 51,
     FCHMODAT = 53,
     OPENAT = 56,
     CLOSE = 57,
@@ -55,7 +57,9 @@ pub enum FsSyscallId {
     FSYNC = 82,
     UTIMENSAT = 88,
     RENAMEAT2 = 276,
+    MEMFD_CREATE = 279,
     COPYFILERANGE = 285,
+    FACCESSAT2 = 439,
 }
 }
 
@@ -75,6 +79,7 @@ numeric_enum_macro::numeric_enum! {
         EPOLL_CREATE = 213,
         EPOLL_CTL = 233,
         EPOLL_WAIT = 232,
+        EPOLL_PWAIT = 281,
         DUP = 32,
         DUP2 = 33,
         DUP3 = 292,
@@ -86,6 +91,8 @@ numeric_enum_macro::numeric_enum! {
         RMDIR = 84,
         UNLINKAT = 263,
         LINKAT = 265,
+        SYMLINKAT = 266,
+        SYMLINK = 88,
         UNMOUNT = 166,
         MOUNT = 165,
         STATFS = 137,
@@ -93,6 +100,7 @@ numeric_enum_macro::numeric_enum! {
         FACCESSAT = 269,
         ACCESS = 21,
         CHDIR = 80,
+        CHROOT = 161,
         FCHMODAT = 268,
         OPENAT = 257,
         CLOSE = 3,
@@ -122,6 +130,8 @@ numeric_enum_macro::numeric_enum! {
         UTIMENSAT = 280,
         RENAMEAT = 264,
         RENAMEAT2 = 316,
+        MEMFD_CREATE = 319,
         COPYFILERANGE = 326,
+        FACCESSAT2 = 439,
     }
 }
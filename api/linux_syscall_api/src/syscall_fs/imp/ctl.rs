@@ -1,20 +1,42 @@
 //! 对文件系统的管理,包括目录项的创建、文件权限设置等内容
-use axfs::api::{remove_dir, remove_file, rename, OpenFlags, Permissions};
-use axlog::{debug, error, info};
-use core::ptr::copy_nonoverlapping;
+use axerrno::AxError;
+use axfs::api::{
+    remove_dir, remove_file, rename, FileIO, FileType, OpenFlags, Permissions, FIONBIO,
+};
+use axlog::{debug, error, info, warn};
+use axmem::invalidate_page_cache;
 
+use super::io::dup_fd_from;
 use crate::{
-    syscall_fs::ctype::{file::new_fd, FileDesc},
-    DirEnt, DirEntType, Fcntl64Cmd, RenameFlags, SyscallError, SyscallResult, TimeSecs,
+    syscall_fs::ctype::{
+        dir::DirDesc,
+        file::inode_number,
+        memfd::{MemFd, MemFdSeals},
+        pipe::Pipe,
+        proc_task::ProcTaskDir,
+        times::{file_changed, file_created, file_modified, set_file_times},
+        FileDesc,
+    },
+    syscall_net::Socket,
+    DirEnt, DirEntType, Fcntl64Cmd, RenameFlags, SyscallError, SyscallResult, TimeSecs, UTIME_NOW,
 };
-use axhal::mem::VirtAddr;
 use axprocess::{
-    current_process,
-    link::{deal_with_path, FilePath, AT_FDCWD},
+    console_fasync, current_process,
+    fasync::{Fasync, ReadyWatch},
+    link::{deal_with_path_str, resolve_path_at, FilePath, AT_FDCWD},
+    uaccess::{copy_struct_from_user, copy_to_user, user_path},
+    CONSOLE_WATCH,
 };
 
 extern crate alloc;
-use alloc::string::ToString;
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use axsync::Mutex;
 
 /// 功能:获取当前工作目录；
 /// # Arguments
@@ -24,39 +46,22 @@ use alloc::string::ToString;
 /// 成功执行,则返回当前工作目录的字符串的指针。失败,则返回NULL。
 /// 暂时:成功执行,则返回当前工作目录的字符串的指针 as isize。失败,返回0。
 ///
-/// TODO: 当前写法存在问题,cwd应当是各个进程独立的,而这里修改的是整个fs的目录
+/// 工作目录保存在进程的文件系统上下文中，相对于进程的根目录给出，
+/// 是不含 "."、".." 与链接的规范路径
 pub fn syscall_getcwd(args: [usize; 6]) -> SyscallResult {
-    let buf = args[0] as *mut u8;
+    let buf = args[0];
     let len = args[1];
-    debug!("Into syscall_getcwd. buf: {}, len: {}", buf as usize, len);
-    let cwd = axfs::api::current_dir().unwrap();
+    debug!("Into syscall_getcwd. buf: {}, len: {}", buf, len);
+    let mut cwd = current_process().fs_context.canonical_cwd();
+    cwd.push('\0');
 
     // todo: 如果buf为NULL,则系统分配缓存区
-    // let process = current_process();
-    // let process_inner = process.inner.lock();
-    // if buf.is_null() {
-    //     buf = allocate_buffer(cwd.len());   // 分配缓存区 allocate_buffer
-    // }
-
-    let cwd = cwd.as_bytes();
-
-    if len >= cwd.len() {
-        let process = current_process();
-        let start: VirtAddr = (buf as usize).into();
-        let end = start + len;
-        if process.manual_alloc_range_for_lazy(start, end).is_ok() {
-            unsafe {
-                core::ptr::copy_nonoverlapping(cwd.as_ptr(), buf, cwd.len());
-            }
-            Ok(buf as isize)
-        } else {
-            // ErrorNo::EINVAL as isize
-            Err(SyscallError::EINVAL)
-        }
-    } else {
+    if len < cwd.len() {
         debug!("getcwd: buf size is too small");
-        Err(SyscallError::ERANGE)
+        return Err(SyscallError::ERANGE);
     }
+    copy_to_user(buf, cwd.as_bytes())?;
+    Ok(buf as isize)
 }
 
 /// 功能:创建目录；
@@ -70,11 +75,7 @@ pub fn syscall_mkdirat(args: [usize; 6]) -> SyscallResult {
     let path = args[1] as *const u8;
     let mode = args[2] as u32;
     // info!("signal module: {:?}", process_inner.signal_module.keys());
-    let path = if let Some(path) = deal_with_path(dir_fd, Some(path), true) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
+    let path = resolve_path_at(dir_fd, user_path(path as usize)?, true)?;
     debug!(
         "Into syscall_mkdirat. dirfd: {}, path: {:?}, mode: {}",
         dir_fd,
@@ -88,6 +89,13 @@ pub fn syscall_mkdirat(args: [usize; 6]) -> SyscallResult {
     let _ = axfs::api::create_dir(path.path());
     // 只要文件夹存在就返回0
     if axfs::api::path_exists(path.path()) {
+        // 新建目录的权限需要经过进程 umask 的屏蔽
+        let mode = current_process().fs_context.apply_umask(mode);
+        let _ = set_permissions(path.path(), mode as usize);
+        file_created(path.path());
+        if let Ok(dir) = path.dir() {
+            file_modified(dir);
+        }
         Ok(0)
     } else {
         Err(SyscallError::EPERM)
@@ -116,15 +124,35 @@ pub fn syscall_mkdir(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_chdir(args: [usize; 6]) -> SyscallResult {
     let path = args[0] as *const u8;
     // 从path中读取字符串
-    let path = if let Some(path) = deal_with_path(AT_FDCWD, Some(path), true) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
+    let path = resolve_path_at(AT_FDCWD, user_path(path as usize)?, true)?;
     debug!("Into syscall_chdir. path: {:?}", path.path());
-    match axfs::api::set_current_dir(path.path()) {
-        Ok(_) => Ok(0),
-        Err(_) => Err(SyscallError::EINVAL),
+    check_dir(path.path())?;
+    // resolve_path_at 得到的路径已经过规范化与链接解析，保存下来的工作目录即为规范路径
+    // 工作目录属于进程的文件系统上下文，以 CLONE_FS 共享的进程会同时看到变化
+    current_process().fs_context.set_cwd(path.path());
+    Ok(0)
+}
+
+/// 功能:切换根目录；
+/// # Arguments
+/// * `path``: *const u8, 新的根目录。
+/// # Return
+/// 成功执行:返回0。之后的工作目录为新的根目录。
+pub fn syscall_chroot(args: [usize; 6]) -> SyscallResult {
+    let path = args[0] as *const u8;
+    let path = resolve_path_at(AT_FDCWD, user_path(path as usize)?, true)?;
+    debug!("Into syscall_chroot. path: {:?}", path.path());
+    check_dir(path.path())?;
+    current_process().fs_context.set_root(path.path());
+    Ok(0)
+}
+
+/// 确认 `path` 是一个存在的目录
+fn check_dir(path: &str) -> Result<(), SyscallError> {
+    match axfs::api::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(SyscallError::ENOTDIR),
+        Err(_) => Err(SyscallError::ENOENT),
     }
 }
 
@@ -139,95 +167,104 @@ pub fn syscall_chdir(args: [usize; 6]) -> SyscallResult {
 /// * On error, -1 is returned.
 pub fn syscall_getdents64(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let buf = args[1] as *mut u8;
+    let buf = args[1];
     let len = args[2];
-    let path = if let Some(path) = deal_with_path(fd, None, true) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
-
     let process = current_process();
-    // 注意是否分配地址
-    let start: VirtAddr = (buf as usize).into();
-    let end = start + len;
-    if process.manual_alloc_range_for_lazy(start, end).is_err() {
-        return Err(SyscallError::EFAULT);
+    let file = match process.fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    // /proc/<pid>/task 目录的内容来自打开时的线程快照
+    if let Some(dir) = file.as_any().downcast_ref::<ProcTaskDir>() {
+        return Ok(dir.read_dirents(buf, len)? as isize);
     }
+    let Some(dir) = file.as_any().downcast_ref::<DirDesc>() else {
+        return Err(SyscallError::ENOTDIR);
+    };
+
     if len < DirEnt::fixed_size() {
         return Err(SyscallError::EINVAL);
     }
-    // let entry_id_from = unsafe { (*(buf as *const DirEnt)).d_off };
-    // error!("entry_id_from: {}", entry_id_from);
-    // 先获取buffer里面最后一个长度
-    let mut all_offset = 0; // 记录上一次调用时进行到的目录项距离文件夹开始时的偏移量
-    let mut buf_offset = 0; // 记录当前buf里面的目录项的指针偏移量
-    loop {
-        if buf_offset + DirEnt::fixed_size() >= len {
-            break;
-        }
-        let dir_ent = unsafe { *(buf.add(buf_offset) as *const DirEnt) };
-        if dir_ent.d_reclen == 0 {
-            break;
+    // 上一次读到的位置，lseek 可以修改它
+    let mut cursor = dir.cursor();
+    // lseek 之后按偏移跳过读过的目录项，否则按名字跳过
+    let by_offset = cursor.returned.is_none();
+    let mut returned = cursor.returned.take().unwrap_or_default();
+    let all_offset = cursor.offset;
+    // 目录项先写入内核缓冲区，最后一次性复制给用户
+    let mut dirents = Vec::new();
+    let dir_iter = axfs::api::read_dir(&dir.dir_path).map_err(SyscallError::from)?;
+    let mut offset: u64 = 0; // 当前目录项在文件夹中的偏移
+    let mut read_offset = all_offset; // 本次读到的最后一个目录项的偏移
+    let mut too_small = false; // 是否有目录项因为 buf 不够大而没有写入
+
+    // 文件系统不返回 "." 与 ".."，与 Linux 一样把它们放在最前面
+    let dots = [".", ".."].map(|name| Ok((String::from(name), FileType::Dir)));
+    let entries = dir_iter.map(|entry| entry.map(|entry| (entry.file_name(), entry.file_type())));
+    for entry in dots.into_iter().chain(entries) {
+        let (file_name, file_type) = entry.map_err(SyscallError::from)?;
+        let name = file_name.as_bytes();
+        // 文件名以 '\0' 结尾，且整个目录项按 8 字节对齐
+        let entry_size = (DirEnt::fixed_size() + name.len() + 1 + 7) & !7;
+        offset += entry_size as u64;
+        if by_offset && offset <= all_offset {
+            returned.insert(file_name);
+            continue;
         }
-        buf_offset += dir_ent.d_reclen as usize;
-        // all_offset = dir_ent.d_off; // 记录最新的 offset
-        if all_offset < dir_ent.d_off {
-            all_offset = dir_ent.d_off;
-        } else {
-            break;
+        if returned.contains(&file_name) {
+            continue;
         }
-    }
-
-    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
-    let dir_iter = axfs::api::read_dir(path.path()).unwrap();
-    let mut count = 0; // buf中已经写入的字节数
-    let mut offset: u64 = 0; // 当前目录项在文件夹中的偏移
-    for entry in dir_iter {
-        let entry = entry.unwrap();
-        let mut name = entry.file_name();
-        name.push('\0');
-        let name = name.as_bytes();
-        let name_len = name.len();
-        let file_type = entry.file_type();
-        let entry_size = DirEnt::fixed_size() + name_len + 1;
-
         // buf不够大，写不下新的entry
-        if count + entry_size + DirEnt::fixed_size() + 1 > len {
+        if dirents.len() + entry_size > len {
             debug!("buf not big enough");
+            too_small = true;
             break;
         }
-        offset += entry_size as u64;
-        if offset <= all_offset {
-            continue;
-        }
-        // 转换为DirEnt
-        let dirent: &mut DirEnt = unsafe { &mut *(buf.as_mut_ptr().add(count) as *mut DirEnt) };
-        // 设置定长部分
-        if file_type.is_dir() {
-            dirent.set_fixed_part(1, offset, entry_size, DirEntType::Dir);
-        } else if file_type.is_file() {
-            dirent.set_fixed_part(1, offset, entry_size, DirEntType::Reg);
-        } else {
-            dirent.set_fixed_part(1, offset, entry_size, DirEntType::Unknown);
-        }
-
-        // 写入文件名
-        unsafe { copy_nonoverlapping(name.as_ptr(), dirent.d_name.as_mut_ptr(), name_len) };
+        let ino = dirent_ino(&dir.dir_path, &file_name);
+        DirEnt::append_to(
+            &mut dirents,
+            ino,
+            offset,
+            entry_size,
+            dirent_type(file_type),
+            name,
+        );
+        read_offset = offset;
+        returned.insert(file_name);
+    }
+    // 一个目录项也放不下时返回 EINVAL
+    if dirents.is_empty() && too_small {
+        cursor.returned = Some(returned);
+        return Err(SyscallError::EINVAL);
+    }
+    // 复制失败时 cursor 保持原来的偏移，下次仍从这里开始读
+    copy_to_user(buf, &dirents)?;
+    cursor.returned = Some(returned);
+    cursor.offset = read_offset;
+    Ok(dirents.len() as isize)
+}
 
-        count += entry_size;
+/// 文件类型对应的 d_type
+fn dirent_type(file_type: FileType) -> DirEntType {
+    match file_type {
+        FileType::Fifo => DirEntType::Fifo,
+        FileType::CharDevice => DirEntType::Chr,
+        FileType::Dir => DirEntType::Dir,
+        FileType::BlockDevice => DirEntType::Blk,
+        FileType::File => DirEntType::Reg,
+        FileType::SymLink => DirEntType::Lnk,
+        FileType::Socket => DirEntType::Socket,
     }
+}
 
-    // 为了保证下一次访问的时候边界是存在的，因此需要手动写入一个空的目录项
-    if count != 0 && count + DirEnt::fixed_size() <= len {
-        // 转换为DirEnt
-        let dirent: &mut DirEnt = unsafe { &mut *(buf.as_mut_ptr().add(count) as *mut DirEnt) };
-        // 设置定长部分
-        dirent.set_fixed_part(1, offset, DirEnt::fixed_size(), DirEntType::Reg);
-        count += DirEnt::fixed_size();
-        return Ok((count - DirEnt::fixed_size()) as isize);
+/// 目录 `dir` 下名为 `name` 的目录项的 inode 号，与对该文件 stat 得到的一致
+fn dirent_ino(dir: &str, name: &str) -> u64 {
+    let dir = dir.trim_end_matches('/');
+    match name {
+        "." => inode_number(dir),
+        ".." => inode_number(dir.rsplit_once('/').map_or("/", |(parent, _)| parent)),
+        name => inode_number(&format!("{}/{}", dir, name)),
     }
-    Ok(count as isize)
 }
 
 /// 276
@@ -257,8 +294,8 @@ pub fn syscall_renameat2(args: [usize; 6]) -> SyscallResult {
     let new_dirfd = args[2];
     let _new_path = args[3] as *const u8;
     let flags = args[4];
-    let old_path = deal_with_path(old_dirfd, Some(_old_path), false).unwrap();
-    let new_path = deal_with_path(new_dirfd, Some(_new_path), false).unwrap();
+    let old_path = resolve_path_at(old_dirfd, user_path(_old_path as usize)?, false)?;
+    let new_path = resolve_path_at(new_dirfd, user_path(_new_path as usize)?, false)?;
 
     let proc_path = FilePath::new("/proc").unwrap();
     if old_path.start_with(&proc_path) || new_path.start_with(&proc_path) {
@@ -306,6 +343,9 @@ pub fn syscall_renameat2(args: [usize; 6]) -> SyscallResult {
         // 相同文件不用改
         return Ok(0);
     }
+    // 两个路径上文件的 inode 号都可能因重命名而改变或被复用，丢弃它们的页缓存
+    let old_ino = inode_number(old_path.path());
+    let new_ino = inode_number(new_path.path());
     if !flags.contains(RenameFlags::EXCHANGE) {
         // 当新文件存在，先删掉新文件
         // 此时若存在新文件，默认是没有 NOREPLACE 的
@@ -327,6 +367,8 @@ pub fn syscall_renameat2(args: [usize; 6]) -> SyscallResult {
             error!("error: {:?}", err);
             return Err(SyscallError::EPERM);
         }
+        invalidate_page_cache(old_ino);
+        invalidate_page_cache(new_ino);
     } else {
         // 当前不支持交换
         axlog::warn!("renameat2 exchange not implemented");
@@ -348,16 +390,32 @@ pub fn syscall_rename(args: [usize; 6]) -> SyscallResult {
     syscall_renameat2(temp_args)
 }
 
+/// 支持信号驱动 I/O 的文件的状态，以及启用 O_ASYNC 后需要登记到的 [`ReadyWatch`]
+///
+/// 管道在读写时自行通知，套接字与控制台在就绪状态变化的路径上检查登记的文件
+fn fasync_of(file: &dyn FileIO) -> Option<(&Arc<Fasync>, Option<&'static ReadyWatch>)> {
+    if let Some(pipe) = file.as_any().downcast_ref::<Pipe>() {
+        return Some((pipe.fasync(), None));
+    }
+    if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        return Some((socket.fasync(), Some(Socket::async_watch())));
+    }
+    console_fasync(file).map(|fasync| (fasync, Some(&CONSOLE_WATCH)))
+}
+
 /// # Arguments
 /// * `fd`: usize
 /// * `cmd`: usize
 /// * `arg`: usize
+///
+/// 只有管道、套接字与控制台支持 O_ASYNC，其余文件忽略该标志，F_SETOWN 与 F_SETSIG 也不生效
 pub fn syscall_fcntl64(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let cmd = args[1];
     let arg = args[2];
     let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
 
     if fd >= fd_table.len() {
         debug!("fd {} is out of range", fd);
@@ -369,62 +427,107 @@ pub fn syscall_fcntl64(args: [usize; 6]) -> SyscallResult {
     }
     let file = fd_table[fd].clone().unwrap();
     info!("fd: {}, cmd: {}", fd, cmd);
-    match Fcntl64Cmd::try_from(cmd) {
-        Ok(Fcntl64Cmd::F_DUPFD) => {
-            let new_fd = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-                fd
-            } else {
-                // 文件描述符达到上限了
-                return Err(SyscallError::EMFILE);
-            };
-            fd_table[new_fd] = fd_table[fd].clone();
-            Ok(new_fd as isize)
-        }
-        Ok(Fcntl64Cmd::F_GETFD) => {
-            if file.get_status().contains(OpenFlags::CLOEXEC) {
-                Ok(1)
-            } else {
-                Ok(0)
-            }
+    // 复制到不小于 arg 的最小空闲文件描述符上，其余命令不需要持有文件描述符表的锁
+    if cmd == Fcntl64Cmd::F_DUPFD as usize || cmd == Fcntl64Cmd::F_DUPFD_CLOEXEC as usize {
+        if arg >= process.fd_manager().get_limit() as usize {
+            return Err(SyscallError::EINVAL);
         }
+        let cloexec = cmd == Fcntl64Cmd::F_DUPFD_CLOEXEC as usize;
+        return dup_fd_from(&process, &mut fd_table, fd, arg, cloexec);
+    }
+    drop(fd_table);
+    match Fcntl64Cmd::try_from(cmd) {
+        // close_on_exec 位属于文件描述符，而不是共享的文件
+        Ok(Fcntl64Cmd::F_GETFD) => Ok(process.fd_manager().is_close_on_exec(fd, &file) as isize),
         Ok(Fcntl64Cmd::F_SETFD) => {
-            if file.set_close_on_exec((arg & 1) != 0) {
-                Ok(0)
-            } else {
-                Err(SyscallError::EINVAL)
-            }
+            process.fd_manager().set_close_on_exec(fd, (arg & 1) != 0);
+            Ok(0)
+        }
+        Ok(Fcntl64Cmd::F_GETFL) => {
+            let mut status = file.get_status();
+            status.set(
+                OpenFlags::ASYNC,
+                fasync_of(file.as_ref()).is_some_and(|(fasync, _)| fasync.is_async()),
+            );
+            Ok(status.bits() as isize)
         }
-        Ok(Fcntl64Cmd::F_GETFL) => Ok(file.get_status().bits() as isize),
         Ok(Fcntl64Cmd::F_SETFL) => {
-            if let Some(flags) = OpenFlags::from_bits(arg as u32) {
-                if file.set_status(flags) {
-                    return Ok(0);
+            // 忽略不认识的标志位，如 O_LARGEFILE，由各文件自行决定可以修改哪些状态
+            let flags = OpenFlags::from_bits_truncate(arg as u32);
+            if !file.set_status(flags - OpenFlags::ASYNC) {
+                return Err(SyscallError::EINVAL);
+            }
+            if let Some((fasync, watch)) = fasync_of(file.as_ref()) {
+                let enabled = flags.contains(OpenFlags::ASYNC);
+                fasync.set_async(enabled, fd);
+                if let Some(watch) = watch.filter(|_| enabled) {
+                    watch.add(&file, fasync);
                 }
             }
-            Err(SyscallError::EINVAL)
+            Ok(0)
+        }
+        Ok(Fcntl64Cmd::F_GETOWN) => {
+            Ok(fasync_of(file.as_ref()).map_or(0, |(fasync, _)| fasync.owner()) as isize)
         }
-        Ok(Fcntl64Cmd::F_DUPFD_CLOEXEC) => {
-            let new_fd = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-                fd
-            } else {
-                // 文件描述符达到上限了
-                return Err(SyscallError::EMFILE);
-            };
-
-            if file.set_close_on_exec((arg & 1) != 0) {
-                fd_table[new_fd] = fd_table[fd].clone();
-                Ok(new_fd as isize)
-            } else {
-                Err(SyscallError::EINVAL)
+        Ok(Fcntl64Cmd::F_SETOWN) => {
+            if let Some((fasync, _)) = fasync_of(file.as_ref()) {
+                fasync.set_owner(arg as i32)?;
             }
+            Ok(0)
+        }
+        Ok(Fcntl64Cmd::F_GETSIG) => {
+            Ok(fasync_of(file.as_ref()).map_or(0, |(fasync, _)| fasync.signal()) as isize)
+        }
+        Ok(Fcntl64Cmd::F_SETSIG) => {
+            if let Some((fasync, _)) = fasync_of(file.as_ref()) {
+                fasync.set_signal(arg)?;
+            }
+            Ok(0)
+        }
+        // 只有管道可以修改容量，其余文件返回 EBADF
+        Ok(Fcntl64Cmd::F_SETPIPE_SZ) => {
+            let pipe = file
+                .as_any()
+                .downcast_ref::<Pipe>()
+                .ok_or(SyscallError::EBADF)?;
+            Ok(pipe.set_capacity(arg as u32 as usize)? as isize)
+        }
+        Ok(Fcntl64Cmd::F_GETPIPE_SZ) => {
+            let pipe = file
+                .as_any()
+                .downcast_ref::<Pipe>()
+                .ok_or(SyscallError::EBADF)?;
+            Ok(pipe.capacity() as isize)
+        }
+        // 只有 memfd 支持封印
+        Ok(Fcntl64Cmd::F_ADD_SEALS) => {
+            let memfd = file
+                .as_any()
+                .downcast_ref::<MemFd>()
+                .ok_or(SyscallError::EINVAL)?;
+            let seals = MemFdSeals::from_bits(arg as u32).ok_or(SyscallError::EINVAL)?;
+            memfd.add_seals(seals).map_err(|_| SyscallError::EPERM)?;
+            Ok(0)
+        }
+        Ok(Fcntl64Cmd::F_GET_SEALS) => {
+            let memfd = file
+                .as_any()
+                .downcast_ref::<MemFd>()
+                .ok_or(SyscallError::EINVAL)?;
+            Ok(memfd.seals().bits() as isize)
         }
         _ => Err(SyscallError::EINVAL),
     }
 }
 
+/// 已经打印过警告的未知 ioctl 请求，每个请求只打印一次
+static UNKNOWN_IOCTLS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
 /// 29
 /// 执行各种设备相关的控制功能
-/// todo: 未实现
+///
+/// FIONBIO 对所有文件通用，与 fcntl 设置 O_NONBLOCK 等价；其余请求交由各类文件自行处理，
+/// 不支持的请求返回 ENOTTY
 /// # Arguments
 /// * `fd`: usize, 文件描述符
 /// * `request`: usize, 控制命令
@@ -434,7 +537,8 @@ pub fn syscall_ioctl(args: [usize; 6]) -> SyscallResult {
     let request = args[1];
     let argp = args[2];
     let process = current_process();
-    let fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let fd_table = fd_manager.fd_table.lock();
     info!("fd: {}, request: {}, argp: {}", fd, request, argp);
     if fd >= fd_table.len() {
         debug!("fd {} is out of range", fd);
@@ -444,13 +548,29 @@ pub fn syscall_ioctl(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is none", fd);
         return Err(SyscallError::EBADF);
     }
-    if process.manual_alloc_for_lazy(argp.into()).is_err() {
-        return Err(SyscallError::EFAULT); // 地址不合法
-    }
-
+    // argp 由各个请求自行通过 uaccess 访问，不使用 argp 的请求不检查它是否合法
     let file = fd_table[fd].clone().unwrap();
-    let _ = file.ioctl(request, argp);
-    Ok(0)
+    drop(fd_table);
+    if request == FIONBIO {
+        let non_block = copy_struct_from_user::<i32>(argp)? != 0;
+        let mut status = file.get_status();
+        status.set(OpenFlags::NON_BLOCK, non_block);
+        return if file.set_status(status) {
+            Ok(0)
+        } else {
+            Err(SyscallError::EINVAL)
+        };
+    }
+    match file.ioctl(request, argp) {
+        Ok(()) => Ok(0),
+        Err(AxError::Unsupported) => {
+            if UNKNOWN_IOCTLS.lock().insert(request) {
+                warn!("unsupported ioctl request {:#x} on fd {}", request, fd);
+            }
+            Err(SyscallError::ENOTTY)
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
 /// 53
@@ -469,18 +589,44 @@ pub fn syscall_fchmodat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let mode = args[2];
-    let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
-    axfs::api::metadata(file_path.path())
-        .map(|mut metadata| {
-            metadata.set_permissions(Permissions::from_bits_truncate(mode as u16));
-            Ok(0)
-        })
-        .unwrap_or_else(|_| Err(SyscallError::ENOENT))
+    let file_path = resolve_path_at(dir_fd, user_path(path as usize)?, false)?;
+    if !axfs::api::path_exists(file_path.path()) {
+        return Err(SyscallError::ENOENT);
+    }
+    set_permissions(file_path.path(), mode)?;
+    file_changed(file_path.path());
+    Ok(0)
+}
+
+/// 将 `path` 的权限修改为 `mode` 的低 9 位
+///
+/// FAT 等文件系统不保存权限，此时忽略修改
+pub(crate) fn set_permissions(path: &str, mode: usize) -> Result<(), SyscallError> {
+    let perm = Permissions::from_bits_truncate((mode & 0o777) as u16);
+    match axfs::api::set_permissions(path, perm) {
+        Ok(()) | Err(AxError::Unsupported) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
 }
 
+/// faccessat 的 mode：检查文件是否存在
+const F_OK: usize = 0;
+/// faccessat 的 mode：检查是否可执行
+const X_OK: usize = 1;
+/// faccessat 的 mode：检查是否可写
+const W_OK: usize = 2;
+/// faccessat 的 mode：检查是否可读
+const R_OK: usize = 4;
+
+/// faccessat2 的 flags：以有效用户而不是实际用户的身份检查
+const AT_EACCESS: usize = 0x200;
+/// faccessat2 的 flags：不跟随路径末尾的符号链接
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+/// faccessat2 的 flags：path 为空字符串时检查 dir_fd 本身
+const AT_EMPTY_PATH: usize = 0x1000;
+
 /// 48
-/// 获取文件权限
-/// 类似上面的fchmodat
+/// 检查当前进程能否以 mode 的方式访问文件，等价于 flags 为 0 的 faccessat2
 ///        The mode specifies the accessibility check(s) to be performed,
 ///        and is either the value F_OK, or a mask consisting of the bitwise
 ///        OR of one or more of R_OK, W_OK, and X_OK.  F_OK tests for the
@@ -491,43 +637,48 @@ pub fn syscall_fchmodat(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * `dir_fd`: usize, 目录的文件描述符
 /// * `path`: *const u8, 文件的路径
-/// * `mode`: usize, 文件的权限
+/// * `mode`: usize, 要检查的权限
 pub fn syscall_faccessat(args: [usize; 6]) -> SyscallResult {
+    syscall_faccessat2([args[0], args[1], args[2], 0, 0, 0])
+}
+
+/// 439
+/// 检查当前进程能否以 mode 的方式访问文件
+///
+/// 所有进程都以 root 身份运行，因此读写总是允许的；X_OK 要求目标是目录，
+/// 或者至少带有一个可执行位。shell 在 PATH 中查找命令时依赖这一点跳过不可执行的文件。
+///
+/// 路径中某一级不存在时返回 ENOENT，某一级不是目录时返回 ENOTDIR，没有执行权限时返回 EACCES。
+/// 路径末尾的符号链接不会被跟随，因此 AT_SYMLINK_NOFOLLOW 不影响结果；实际用户与有效用户相同，
+/// 因此 AT_EACCESS 也不影响结果。
+/// # Arguments
+/// * `dir_fd`: usize, 目录的文件描述符
+/// * `path`: *const u8, 文件的路径
+/// * `mode`: usize, 要检查的权限
+/// * `flags`: usize, 可以包含 AT_EACCESS、AT_SYMLINK_NOFOLLOW 与 AT_EMPTY_PATH
+pub fn syscall_faccessat2(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
-    let path = args[1] as *const u8;
+    let path = args[1];
     let mode = args[2];
-    // todo: 有问题,实际上需要考虑当前进程对应的用户UID和文件拥有者之间的关系
-    // 现在一律当作root用户处理
-    let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
-    axfs::api::metadata(file_path.path())
-        .map(|metadata| {
-            if mode == 0 {
-                //F_OK
-                // 文件存在返回0,不存在返回-1
-                if axfs::api::path_exists(file_path.path()) {
-                    Ok(0)
-                } else {
-                    Err(SyscallError::ENOENT)
-                }
-            } else {
-                // 逐位对比
-                let mut ret = true;
-                if mode & 1 != 0 {
-                    // X_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_EXEC)
-                }
-                if mode & 2 != 0 {
-                    // W_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_WRITE)
-                }
-                if mode & 4 != 0 {
-                    // R_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_READ)
-                }
-                Ok(ret as isize - 1)
-            }
-        })
-        .unwrap_or_else(|_| Err(SyscallError::ENOENT))
+    let flags = args[3];
+    if mode & !(R_OK | W_OK | X_OK) != 0
+        || flags & !(AT_EACCESS | AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0
+    {
+        return Err(SyscallError::EINVAL);
+    }
+    let path = user_path(path)?;
+    if path.is_empty() && flags & AT_EMPTY_PATH == 0 {
+        return Err(SyscallError::ENOENT);
+    }
+    let file_path = deal_with_path_str(dir_fd, path, false).ok_or(SyscallError::ENOENT)?;
+    let attr = axfs::api::lookup(file_path.path())?.get_attr()?;
+    if mode == F_OK {
+        return Ok(0);
+    }
+    if mode & X_OK != 0 && !attr.is_dir() && attr.perm().mode() & 0o111 == 0 {
+        return Err(SyscallError::EACCES);
+    }
+    Ok(0)
 }
 
 /// 48
@@ -562,77 +713,50 @@ pub fn syscall_rmdir(args: [usize; 6]) -> SyscallResult {
 
 /// 88
 /// 用于修改文件或目录的时间戳(timestamp)
-/// 如果 fir_fd < 0,它和 path 共同决定要找的文件；
-/// 如果 fir_fd >=0,它就是文件对应的 fd
+/// path 为空指针时修改 dir_fd 对应的文件，否则与 openat 一样相对于 dir_fd 解析 path
+///
+/// times 为空指针时两个时间戳都设置为当前时间，tv_nsec 为 UTIME_NOW 或 UTIME_OMIT 时
+/// 对应的时间戳设置为当前时间或保持不变。修改之后文件的 ctime 更新为当前时间
 /// # Arguments
 /// * `dir_fd`: usize, 目录的文件描述符
 /// * `path`: *const u8, 文件的路径
-/// * `times`: *const TimeSecs, 时间戳
+/// * `times`: *const [TimeSecs; 2], 依次为 atime 与 mtime
 /// * `flags`: usize, 选项
 pub fn syscall_utimensat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
-    let times = args[2] as *const TimeSecs;
+    let times = args[2];
     let _flags = args[3];
     let process = current_process();
-    // info!("dir_fd: {}, path: {}", dir_fd as usize, path as usize);
-    if dir_fd != AT_FDCWD && (dir_fd as isize) < 0 {
-        return Err(SyscallError::EBADF); // 错误的文件描述符
-    }
-
-    if dir_fd == AT_FDCWD
-        && process
-            .manual_alloc_for_lazy((path as usize).into())
-            .is_err()
-    {
-        return Err(SyscallError::EFAULT); // 地址不合法
-    }
     // 需要设置的时间
-    let (new_atime, new_mtime) = if times.is_null() {
-        (TimeSecs::now(), TimeSecs::now())
+    let [new_atime, new_mtime] = if times == 0 {
+        let now = TimeSecs {
+            tv_sec: 0,
+            tv_nsec: UTIME_NOW,
+        };
+        [now, now]
     } else {
-        if process.manual_alloc_type_for_lazy(times).is_err() {
-            return Err(SyscallError::EFAULT);
-        }
-        unsafe { (*times, *(times.add(1))) } //  注意传入的TimeVal中 sec和nsec都是usize, 但TimeValue中nsec是u32
+        copy_struct_from_user::<[TimeSecs; 2]>(times)?
     };
-    // 感觉以下仿照maturin的实现不太合理,并没有真的把时间写给文件,只是写给了一个新建的临时的fd
-    if (dir_fd as isize) > 0 {
-        // let file = process_inner.fd_manager.fd_table[dir_fd].clone();
-        // if !file.unwrap().lock().set_time(new_atime, new_mtime) {
-        //     error!("Set time failed: unknown reason.");
-        //     return ErrorNo::EPERM as isize;
-        // }
-        let fd_table = process.fd_manager.fd_table.lock();
-        if dir_fd > fd_table.len() || fd_table[dir_fd].is_none() {
-            return Err(SyscallError::EBADF);
-        }
-        if let Some(file) = fd_table[dir_fd].as_ref() {
-            if let Some(fat_file) = file.as_any().downcast_ref::<FileDesc>() {
-                // if !fat_file.set_time(new_atime, new_mtime) {
-                //     error!("Set time failed: unknown reason.");
-                //     return ErrorNo::EPERM as isize;
-                // }
-                fat_file.stat.lock().atime.set_as_utime(&new_atime);
-                fat_file.stat.lock().mtime.set_as_utime(&new_mtime);
-            } else {
-                return Err(SyscallError::EPERM);
-            }
+    let file_path = if path.is_null() {
+        let fd_manager = process.fd_manager();
+        let fd_table = fd_manager.fd_table.lock();
+        let file = fd_table
+            .get(dir_fd)
+            .and_then(|file| file.as_ref())
+            .ok_or(SyscallError::EBADF)?;
+        let any = file.as_any();
+        if !any.is::<FileDesc>() && !any.is::<DirDesc>() {
+            return Err(SyscallError::EPERM);
         }
-        Ok(0)
+        file.get_path()
     } else {
-        let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
+        let file_path = resolve_path_at(dir_fd, user_path(path as usize)?, false)?;
         if !axfs::api::path_exists(file_path.path()) {
-            error!("Set time failed: file {} doesn't exist!", file_path.path());
-            if !axfs::api::path_exists(file_path.dir().unwrap()) {
-                return Err(SyscallError::ENOTDIR);
-            } else {
-                return Err(SyscallError::ENOENT);
-            }
+            return Err(SyscallError::ENOENT);
         }
-        let file = new_fd(file_path.path().to_string(), 0.into()).unwrap();
-        file.stat.lock().atime.set_as_utime(&new_atime);
-        file.stat.lock().mtime.set_as_utime(&new_mtime);
-        Ok(0)
-    }
+        file_path.path().to_string()
+    };
+    set_file_times(&file_path, &new_atime, &new_mtime);
+    Ok(0)
 }
@@ -4,10 +4,19 @@
 extern crate alloc;
 use crate::{SyscallError, SyscallResult};
 use alloc::sync::Arc;
-use axhal::{mem::VirtAddr, time::current_ticks};
-use axprocess::current_process;
+use axfs::api::OpenFlags;
+use axhal::time::{current_time, Duration, TimeValue};
+use axprocess::{
+    current_process,
+    signal::wait_with_sigmask,
+    uaccess::{copy_struct_from_user, copy_struct_to_user},
+};
 
-use crate::syscall_fs::ctype::epoll::{EpollCtl, EpollEvent, EpollFile};
+use super::poll::read_user_sigmask;
+use crate::syscall_fs::ctype::{
+    anon_inode,
+    epoll::{EpollCtl, EpollEvent, EpollFile},
+};
 
 /// For epoll_create, Since Linux 2.6.8, the size argument is ignored, but must be greater than zero;
 ///
@@ -19,17 +28,12 @@ use crate::syscall_fs::ctype::epoll::{EpollCtl, EpollEvent, EpollFile};
 /// # Arguments
 /// * `flag` - usize
 pub fn syscall_epoll_create1(args: [usize; 6]) -> SyscallResult {
-    let _flag = args[0];
-    let file = EpollFile::new();
-    let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
-    if let Ok(num) = process.alloc_fd(&mut fd_table) {
-        fd_table[num] = Some(Arc::new(file));
-        Ok(num as isize)
-    } else {
-        // ErrorNo::EMFILE as isize
-        Err(SyscallError::EMFILE)
-    }
+    let flag = OpenFlags::from_bits_truncate(args[0] as u32);
+    anon_inode::new_fd(
+        "eventpoll",
+        Arc::new(EpollFile::new()),
+        flag & OpenFlags::CLOEXEC,
+    )
 }
 
 /// 执行syscall_epoll_ctl，修改文件对应的响应事件
@@ -45,13 +49,10 @@ pub fn syscall_epoll_ctl(args: [usize; 6]) -> SyscallResult {
     let epfd = args[0] as i32;
     let op = args[1] as i32;
     let fd = args[2] as i32;
-    let event = args[3] as *const EpollEvent;
+    let event: EpollEvent = copy_struct_from_user(args[3])?;
     let process = current_process();
-    if process.manual_alloc_type_for_lazy(event).is_err() {
-        return Err(SyscallError::EFAULT);
-    }
-    let fd_table = process.fd_manager.fd_table.lock();
-    let event = unsafe { *event };
+    let fd_manager = process.fd_manager();
+    let fd_table = fd_manager.fd_table.lock();
     if fd_table[fd as usize].is_none() {
         return Err(SyscallError::EBADF);
     }
@@ -61,7 +62,7 @@ pub fn syscall_epoll_ctl(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EINVAL);
     };
     if let Some(file) = fd_table[epfd as usize].as_ref() {
-        if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
+        if let Some(epoll_file) = anon_inode::downcast_anon::<EpollFile>(file) {
             epoll_file.epoll_ctl(op, fd, event)
         } else {
             Err(SyscallError::EBADF)
@@ -77,28 +78,44 @@ pub fn syscall_epoll_ctl(args: [usize; 6]) -> SyscallResult {
 /// * `epfd`: i32, epoll文件的fd
 /// * `event`: *mut EpollEvent, 接受事件的数组
 /// * `max_event`: i32, 最大的响应事件数量,必须大于0
-/// * `timeout`: i32, 超时时间，是一段相对时间，需要手动转化为绝对时间
+/// * `timeout`: i32, 超时时间，单位为毫秒，是一段相对时间；负数表示无限等待，0 表示立即返回
+///
+/// ret: 实际写入的响应事件数目
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_epoll_wait(mut args: [usize; 6]) -> SyscallResult {
+    args[4] = 0;
+    syscall_epoll_pwait(args)
+}
+
+/// 执行syscall_epoll_pwait系统调用
+///
+/// 与 epoll_wait 相同，但等待期间会将信号掩码替换为 `sigmask`
+///
+/// # Arguments
+/// * `epfd`: i32, epoll文件的fd
+/// * `event`: *mut EpollEvent, 接受事件的数组
+/// * `max_event`: i32, 最大的响应事件数量,必须大于0
+/// * `timeout`: i32, 超时时间，单位为毫秒，是一段相对时间；负数表示无限等待，0 表示立即返回
+/// * `sigmask`: *const usize, 等待期间使用的信号掩码，为空则不替换
+/// * `sigsetsize`: usize, 信号掩码的大小
 ///
 /// ret: 实际写入的响应事件数目
-pub fn syscall_epoll_wait(args: [usize; 6]) -> SyscallResult {
+pub fn syscall_epoll_pwait(args: [usize; 6]) -> SyscallResult {
     let epfd = args[0] as i32;
-    let event = args[1] as *mut EpollEvent;
+    let event = args[1];
     let max_event = args[2] as i32;
     let timeout = args[3] as i32;
+    let mask = read_user_sigmask(args[4], args[5])?;
     if max_event <= 0 {
         return Err(SyscallError::EINVAL);
     }
     let max_event = max_event as usize;
     let process = current_process();
-    let start: VirtAddr = (event as usize).into();
-    let end = start + max_event * core::mem::size_of::<EpollEvent>();
-    if process.manual_alloc_range_for_lazy(start, end).is_err() {
-        return Err(SyscallError::EFAULT);
-    }
 
-    let fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let fd_table = fd_manager.fd_table.lock();
     let epoll_file = if let Some(file) = fd_table[epfd as usize].as_ref() {
-        if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
+        if let Some(epoll_file) = anon_inode::downcast_anon::<EpollFile>(file) {
             epoll_file.clone()
         } else {
             return Err(SyscallError::EBADF);
@@ -107,21 +124,20 @@ pub fn syscall_epoll_wait(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EBADF);
     };
 
-    let timeout = if timeout > 0 {
-        current_ticks() as usize + timeout as usize
+    let deadline = if timeout < 0 {
+        TimeValue::MAX
     } else {
-        usize::MAX
+        current_time().saturating_add(Duration::from_millis(timeout as u64))
     };
-    let ret_events = epoll_file.epoll_wait(timeout);
+    drop(fd_table);
+    let ret_events = wait_with_sigmask(mask, || epoll_file.epoll_wait(deadline));
     if ret_events.is_err() {
         return Err(SyscallError::EINTR);
     }
     let ret_events = ret_events.unwrap();
     let real_len = ret_events.len().min(max_event);
     for (i, e) in ret_events.iter().enumerate().take(real_len) {
-        unsafe {
-            *(event.add(i)) = *e;
-        }
+        copy_struct_to_user(event + i * core::mem::size_of::<EpollEvent>(), *e)?;
     }
     Ok(real_len as isize)
 }
@@ -1,22 +1,24 @@
 use alloc::sync::Arc;
-use axprocess::current_process;
+use axfs::api::OpenFlags;
 
-use crate::syscall_fs::ctype::eventfd::EventFd;
-use crate::{SyscallError, SyscallResult};
+use crate::syscall_fs::ctype::{
+    anon_inode,
+    eventfd::{EventFd, EventFdFlag},
+};
+use crate::SyscallResult;
 
 pub fn syscall_eventfd(args: [usize; 6]) -> SyscallResult {
     let initval = args[0] as u64;
     let flags = args[1] as u32;
 
-    let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
-    let fd_num = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-        fd
+    let open_flags = if flags & EventFdFlag::EFD_CLOEXEC.bits() != 0 {
+        OpenFlags::CLOEXEC
     } else {
-        return Err(SyscallError::EPERM);
+        OpenFlags::empty()
     };
-
-    fd_table[fd_num] = Some(Arc::new(EventFd::new(initval, flags)));
-
-    Ok(fd_num as isize)
+    anon_inode::new_fd(
+        "eventfd",
+        Arc::new(EventFd::new(initval, flags)),
+        open_flags,
+    )
 }
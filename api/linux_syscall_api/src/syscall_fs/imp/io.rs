@@ -2,21 +2,36 @@
 extern crate alloc;
 use crate::syscall_net::Socket;
 use crate::{IoVec, SyscallError, SyscallResult};
+use alloc::format;
 use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec;
-use axerrno::AxError;
-use axfs::api::{FileIOType, OpenFlags, SeekFrom};
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags, SeekFrom};
+use axhal::mem::PAGE_SIZE_4K;
 
 use axlog::{debug, info};
-use axprocess::current_process;
-use axprocess::link::{create_link, deal_with_path, real_path};
+use axprocess::link::{
+    create_link, deal_with_path_str, read_symlink, real_path, resolve_path_at, FilePath,
+};
+use axprocess::uaccess::{
+    copy_from_user, copy_struct_from_user, copy_struct_to_user, copy_to_user, user_path,
+};
+use axprocess::{current_process, Process, Tty};
+use axsync::Mutex;
 
 use crate::syscall_fs::ctype::{
     dir::new_dir,
-    file::{new_fd, new_inode},
+    file::new_fd,
     pipe::make_pipe,
+    proc_mem::open_proc_mem,
+    proc_sys::{ProcVmFile, ProcVmFileKind},
+    proc_task::{open_proc_task, proc_link_target},
+    times::{file_created, file_modified},
 };
+
+use super::ctl::set_permissions;
 /// 功能:从一个文件描述符中读取；
 /// # Arguments
 /// * `fd`: usize, 要读取文件的文件描述符。
@@ -29,27 +44,24 @@ pub fn syscall_read(args: [usize; 6]) -> SyscallResult {
     let count = args[2];
     info!("[read()] fd: {fd}, buf: {buf:?}, len: {count}",);
 
-    if buf.is_null() {
-        return Err(SyscallError::EFAULT);
-    }
-
     let process = current_process();
 
-    // TODO: 左闭右开
-    let buf = match process.manual_alloc_range_for_lazy(
-        (buf as usize).into(),
-        (unsafe { buf.add(count) as usize } - 1).into(),
-    ) {
-        Ok(_) => unsafe { core::slice::from_raw_parts_mut(buf, count) },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
-
-    let file = match process.fd_manager.fd_table.lock().get(fd) {
+    let file = match process.fd_manager().fd_table.lock().get(fd) {
         Some(Some(f)) => f.clone(),
         _ => return Err(SyscallError::EBADF),
     };
 
-    if file.get_type() == FileIOType::DirDesc {
+    // 读取 0 字节时不访问缓冲区
+    if count == 0 {
+        return Ok(0);
+    }
+
+    let file_type = file.get_type();
+    if file_type == FileIOType::FileDesc {
+        return read_file_by_page(file.as_ref(), buf as usize, count);
+    }
+
+    if file_type == FileIOType::DirDesc {
         axlog::error!("fd is a dir");
         return Err(SyscallError::EISDIR);
     }
@@ -79,14 +91,68 @@ pub fn syscall_read(args: [usize; 6]) -> SyscallResult {
     //   this will return Ok(0)
     // - ready to accept new connections
 
-    match file.read(buf) {
-        Ok(len) => Ok(len as isize),
-        Err(AxError::WouldBlock) => Err(SyscallError::EAGAIN),
-        Err(AxError::InvalidInput) => Err(SyscallError::EINVAL),
-        Err(_) => Err(SyscallError::EPERM),
+    // 读取可能阻塞，先读入内核缓冲区，之后再复制到用户空间
+    let mut chunk = vec![0u8; STREAM_CHUNK_SIZE.min(count)];
+    let len = file.read(&mut chunk).map_err(read_error)?;
+    copy_to_user(buf as usize, &chunk[..len])?;
+    Ok(len as isize)
+}
+
+fn read_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        AxError::Interrupted => SyscallError::EINTR,
+        AxError::InvalidInput => SyscallError::EINVAL,
+        _ => SyscallError::EPERM,
     }
 }
 
+/// 管道、socket 等其他文件每次读写的块大小
+///
+/// 一次读取最多返回这么多字节；写入时按块依次写入，块不小于 PIPE_BUF，因此不会拆开管道的原子写入
+const STREAM_CHUNK_SIZE: usize = 16 * PAGE_SIZE_4K;
+
+/// 读写普通文件时，从用户地址 `uaddr` 开始的下一块的长度
+///
+/// 每一块都在用户缓冲区的页边界处结束，缓冲区后半部分失效时，前面的页面仍然会被完整地读写
+fn file_chunk_len(uaddr: usize, remaining: usize) -> usize {
+    (PAGE_SIZE_4K - uaddr % PAGE_SIZE_4K).min(remaining)
+}
+
+/// 按页读取普通文件，先读入内核缓冲区再复制到用户空间
+///
+/// 读到文件末尾时返回实际读取的字节数。用户缓冲区在中途失效时，将文件偏移退回到未复制的数据之前，
+/// 若已经读取了部分内容则返回已读取的字节数，否则返回 EFAULT
+fn read_file_by_page(file: &dyn FileIO, buf: usize, count: usize) -> SyscallResult {
+    if !file.readable() {
+        return Err(SyscallError::EBADF);
+    }
+    let mut chunk = vec![0u8; PAGE_SIZE_4K.min(count)];
+    let mut read_len = 0;
+    while read_len < count {
+        let want = file_chunk_len(buf + read_len, count - read_len);
+        let len = match file.read(&mut chunk[..want]) {
+            Ok(len) => len,
+            Err(_) if read_len > 0 => break,
+            Err(err) => return Err(read_error(err)),
+        };
+        if let Err(err) = copy_to_user(buf + read_len, &chunk[..len]) {
+            // 这一块没有交给用户，下次读取应当重新读到它
+            file.seek(SeekFrom::Current(-(len as i64)))
+                .map_err(|_| SyscallError::EFAULT)?;
+            if read_len > 0 {
+                break;
+            }
+            return Err(err);
+        }
+        read_len += len;
+        if len < want {
+            break;
+        }
+    }
+    Ok(read_len as isize)
+}
+
 /// 功能:从一个文件描述符中写入；
 /// # Arguments:
 /// * `fd`: usize, 要写入文件的文件描述符。
@@ -99,26 +165,23 @@ pub fn syscall_write(args: [usize; 6]) -> SyscallResult {
     let count = args[2];
 
     info!("[write()] fd: {}, buf: {buf:?}, len: {count}", fd as i32);
-    if buf.is_null() {
-        return Err(SyscallError::EFAULT);
-    }
 
     let process = current_process();
 
-    // TODO: 左闭右开
-    let buf = match process.manual_alloc_range_for_lazy(
-        (buf as usize).into(),
-        (unsafe { buf.add(count) as usize } - 1).into(),
-    ) {
-        Ok(_) => unsafe { core::slice::from_raw_parts(buf, count) },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
-
-    let file = match process.fd_manager.fd_table.lock().get(fd) {
+    let file = match process.fd_manager().fd_table.lock().get(fd) {
         Some(Some(f)) => f.clone(),
         _ => return Err(SyscallError::EBADF),
     };
 
+    // 写入 0 字节时不访问缓冲区
+    if count == 0 {
+        return Ok(0);
+    }
+
+    if file.get_type() == FileIOType::FileDesc {
+        return write_file_by_page(file.as_ref(), buf as usize, count);
+    }
+
     if file.get_type() == FileIOType::DirDesc {
         debug!("fd is a dir");
         return Err(SyscallError::EBADF);
@@ -149,17 +212,71 @@ pub fn syscall_write(args: [usize; 6]) -> SyscallResult {
     // - sent FIN packet, local send half is closed (this will return 0 immediately)
     //   this will return Err(ConnectionReset)
 
-    match file.write(buf) {
-        Ok(len) => Ok(len as isize),
+    write_by_chunk(
+        file.as_ref(),
+        buf as usize,
+        count,
+        STREAM_CHUNK_SIZE,
+        |_, remaining| remaining.min(STREAM_CHUNK_SIZE),
+    )
+}
+
+fn write_error(err: AxError) -> SyscallError {
+    match err {
         // socket with send half closed
         // TODO: send a SIGPIPE signal to the process
-        Err(axerrno::AxError::ConnectionReset) => Err(SyscallError::EPIPE),
-        Err(AxError::WouldBlock) => Err(SyscallError::EAGAIN),
-        Err(AxError::InvalidInput) => Err(SyscallError::EINVAL),
-        Err(_) => Err(SyscallError::EPERM),
+        AxError::ConnectionReset => SyscallError::EPIPE,
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        AxError::InvalidInput => SyscallError::EINVAL,
+        AxError::FileTooLarge => SyscallError::EFBIG,
+        AxError::NoMemory => SyscallError::ENOMEM,
+        _ => SyscallError::EPERM,
     }
 }
 
+/// 按页写入普通文件，先将用户缓冲区复制到内核缓冲区再写入
+///
+/// 文件写满时返回实际写入的字节数。用户缓冲区在中途失效时，若已经写入了部分内容则返回已写入的
+/// 字节数，否则返回 EFAULT
+fn write_file_by_page(file: &dyn FileIO, buf: usize, count: usize) -> SyscallResult {
+    if !file.writable() {
+        return Err(SyscallError::EBADF);
+    }
+    write_by_chunk(file, buf, count, PAGE_SIZE_4K, file_chunk_len)
+}
+
+/// 将用户缓冲区分块复制到内核中依次写入 `file`，某一块没有写完时停止
+///
+/// `chunk_len` 根据下一块的起始用户地址与剩余长度给出该块的长度，不超过 `chunk_size`
+fn write_by_chunk(
+    file: &dyn FileIO,
+    buf: usize,
+    count: usize,
+    chunk_size: usize,
+    chunk_len: fn(usize, usize) -> usize,
+) -> SyscallResult {
+    let mut chunk = vec![0u8; chunk_size.min(count)];
+    let mut write_len = 0;
+    while write_len < count {
+        let want = chunk_len(buf + write_len, count - write_len);
+        match copy_from_user(&mut chunk[..want], buf + write_len) {
+            Ok(()) => {}
+            Err(_) if write_len > 0 => break,
+            Err(err) => return Err(err),
+        }
+        let len = match file.write(&chunk[..want]) {
+            Ok(len) => len,
+            Err(_) if write_len > 0 => break,
+            Err(err) => return Err(write_error(err)),
+        };
+        write_len += len;
+        if len < want {
+            break;
+        }
+    }
+    Ok(write_len as isize)
+}
+
 /// 从同一个文件描述符读取多个字符串
 /// # Arguments
 /// * `fd`: usize, 要读取文件的文件描述符。
@@ -170,23 +287,58 @@ pub fn syscall_readv(args: [usize; 6]) -> SyscallResult {
     let iov = args[1] as *mut IoVec;
     let iov_cnt = args[2];
     let mut read_len = 0;
-    // 似乎要判断iov是否分配,但是懒了,反正能过测例
-    for i in 0..iov_cnt {
-        let io: &IoVec = unsafe { &*iov.add(i) };
+    for io in user_iovecs(iov, iov_cnt)? {
         if io.base.is_null() || io.len == 0 {
             continue;
         }
         let temp_args = [fd, io.base as usize, io.len, 0, 0, 0];
         match syscall_read(temp_args) {
-            len if len.is_ok() => read_len += len.unwrap(),
-
+            Ok(len) => {
+                read_len += len;
+                // 没有读满当前的 iovec 时不再继续，否则可能阻塞在下一次读取上
+                if len < io.len {
+                    break;
+                }
+            }
+            // 已经读取了部分内容时返回已读取的字节数
+            Err(_) if read_len > 0 => break,
             err => return err,
         }
     }
     Ok(read_len)
 }
 
+/// readv/writev 一次最多处理的 iovec 数量
+const IOV_MAX: usize = 1024;
+
+/// 从用户地址空间复制 iovec 数组
+///
+/// 数组过长或总长度溢出时返回 EINVAL；数组地址不合法，或某一项的 base 为空而 len 不为 0 时返回 EFAULT。
+/// 这些检查在读写任何数据之前完成，出错时不会写入部分数据
+fn user_iovecs(iov: *const IoVec, iov_cnt: usize) -> Result<Vec<IoVec>, SyscallError> {
+    if iov_cnt > IOV_MAX {
+        return Err(SyscallError::EINVAL);
+    }
+    let mut iovecs = Vec::with_capacity(iov_cnt);
+    let mut total_len: usize = 0;
+    for i in 0..iov_cnt {
+        let io: IoVec = copy_struct_from_user(iov as usize + i * core::mem::size_of::<IoVec>())?;
+        if io.base.is_null() && io.len != 0 {
+            return Err(SyscallError::EFAULT);
+        }
+        total_len = total_len
+            .checked_add(io.len)
+            .filter(|&len| len <= isize::MAX as usize)
+            .ok_or(SyscallError::EINVAL)?;
+        iovecs.push(io);
+    }
+    Ok(iovecs)
+}
+
 /// 从同一个文件描述符写入多个字符串
+///
+/// 依次写入每个 iovec 中的原始字节，返回写入的总字节数。某一项只写入了一部分时停止，
+/// 返回值即实际写入的字节数
 /// # Arguments
 /// * `fd`: usize, 要写入文件的文件描述符。
 /// * `iov`: *mut IoVec, 一个缓存区,用于存放要写入的内容。
@@ -196,16 +348,21 @@ pub fn syscall_writev(args: [usize; 6]) -> SyscallResult {
     let iov = args[1] as *mut IoVec;
     let iov_cnt = args[2];
     let mut write_len = 0;
-    // 似乎要判断iov是否分配,但是懒了,反正能过测例
-    for i in 0..iov_cnt {
-        let io: &IoVec = unsafe { &(*iov.add(i)) };
+    for io in user_iovecs(iov, iov_cnt)? {
         if io.base.is_null() || io.len == 0 {
             continue;
         }
         let temp_args = [fd, io.base as usize, io.len, 0, 0, 0];
         match syscall_write(temp_args) {
-            len if len.is_ok() => write_len += len.unwrap(),
-
+            Ok(len) => {
+                write_len += len;
+                // 短写时不再继续，否则后面 iovec 的内容会写到错误的位置
+                if len < io.len {
+                    break;
+                }
+            }
+            // 已经写入了部分内容时返回已写入的字节数
+            Err(_) if write_len > 0 => break,
             err => return err,
         }
     }
@@ -215,36 +372,40 @@ pub fn syscall_writev(args: [usize; 6]) -> SyscallResult {
 /// 功能:创建管道；
 /// # Arguments
 /// * `fd[2]`: *mut u32, 用于保存2个文件描述符。其中,`fd[0]`为管道的读出端,`fd[1]`为管道的写入端。
-/// * `flags`: usize, 用于指定管道的属性。
+/// * `flags`: usize, 用于指定管道的属性，只接受 O_CLOEXEC 与 O_NONBLOCK。
 /// 返回值:成功执行,返回0。失败,返回-1。
 ///
 /// 注意:`fd[2]`是32位数组,所以这里的 fd 是 u32 类型的指针,而不是 usize 类型的指针。
+///
+/// 文件描述符不足时返回 EMFILE，`fd` 不可写时返回 EFAULT，两种情况下都不会留下打开的文件描述符
 pub fn syscall_pipe2(args: [usize; 6]) -> SyscallResult {
-    let fd = args[0] as *mut u32;
+    let fd = args[0];
     let flags = args[1] as u32;
-    axlog::info!("Into syscall_pipe2. fd: {} flags: {}", fd as usize, flags);
+    axlog::info!("Into syscall_pipe2. fd: {} flags: {}", fd, flags);
+    let flags = OpenFlags::from_bits(flags)
+        .filter(|flags| (OpenFlags::CLOEXEC | OpenFlags::NON_BLOCK).contains(*flags))
+        .ok_or(SyscallError::EINVAL)?;
+    let cloexec = flags.is_close_on_exec();
     let process = current_process();
-    if process.manual_alloc_for_lazy((fd as usize).into()).is_err() {
-        return Err(SyscallError::EINVAL);
-    }
-    let (read, write) = make_pipe(OpenFlags::from_bits_truncate(flags));
-    let mut fd_table = process.fd_manager.fd_table.lock();
-    let fd_num = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-        fd
-    } else {
-        return Err(SyscallError::EPERM);
-    };
+    let (read, write) = make_pipe(flags);
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
+    let fd_num = process
+        .alloc_fd(&mut fd_table)
+        .map_err(|_| SyscallError::EMFILE)?;
     fd_table[fd_num] = Some(read);
-    let fd_num2 = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-        fd
-    } else {
-        return Err(SyscallError::EPERM);
+    let Ok(fd_num2) = process.alloc_fd(&mut fd_table) else {
+        fd_table[fd_num] = None;
+        return Err(SyscallError::EMFILE);
     };
     fd_table[fd_num2] = Some(write);
+    process.fd_manager().set_close_on_exec(fd_num, cloexec);
+    process.fd_manager().set_close_on_exec(fd_num2, cloexec);
     info!("read end: {} write: end: {}", fd_num, fd_num2);
-    unsafe {
-        core::ptr::write(fd, fd_num as u32);
-        core::ptr::write(fd.offset(1), fd_num2 as u32);
+    if let Err(err) = copy_struct_to_user(fd, [fd_num as u32, fd_num2 as u32]) {
+        fd_table[fd_num] = None;
+        fd_table[fd_num2] = None;
+        return Err(err);
     }
     Ok(0)
 }
@@ -268,7 +429,8 @@ pub fn syscall_pipe(mut args: [usize; 6]) -> SyscallResult {
 pub fn syscall_dup(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
     if fd >= fd_table.len() {
         debug!("fd {} is out of range", fd);
         return Err(SyscallError::EBADF);
@@ -278,14 +440,30 @@ pub fn syscall_dup(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EBADF);
     }
 
-    let new_fd = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
-        fd
-    } else {
-        // 文件描述符达到上限了
-        return Err(SyscallError::EMFILE);
-    };
-    fd_table[new_fd] = fd_table[fd].clone();
+    // 新的文件描述符不带 close_on_exec 位
+    dup_fd_from(&process, &mut fd_table, fd, 0, false)
+}
 
+/// 将已经打开的 `fd` 复制到不小于 `floor` 的最小空闲文件描述符上，返回新的文件描述符
+///
+/// dup 与 fcntl 的 F_DUPFD、F_DUPFD_CLOEXEC 共用，`cloexec` 只设置在新的文件描述符上。
+/// 没有空闲的文件描述符时返回 EMFILE
+pub(crate) fn dup_fd_from(
+    process: &Process,
+    fd_table: &mut Vec<Option<Arc<dyn FileIO>>>,
+    fd: usize,
+    floor: usize,
+    cloexec: bool,
+) -> SyscallResult {
+    let file = fd_table[fd].clone().ok_or(SyscallError::EBADF)?;
+    let new_fd = process
+        .fd_manager()
+        .alloc_fd_from(fd_table, floor)
+        .map_err(|_| SyscallError::EMFILE)?;
+    process
+        .fd_manager()
+        .insert_at(fd_table, new_fd, file, cloexec)
+        .map_err(|_| SyscallError::EMFILE)?;
     Ok(new_fd as isize)
 }
 
@@ -295,7 +473,20 @@ pub fn syscall_dup(args: [usize; 6]) -> SyscallResult {
 /// * new_fd: usize, 新的文件描述符
 /// 返回值:成功执行,返回新的文件描述符。失败,返回-1。
 #[cfg(target_arch = "x86_64")]
-pub fn syscall_dup2(args: [usize; 6]) -> SyscallResult {
+pub fn syscall_dup2(mut args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    if fd == args[1] {
+        // dup2 在两个文件描述符相同时仅检查其是否有效
+        let process = current_process();
+        let fd_manager = process.fd_manager();
+        let fd_table = fd_manager.fd_table.lock();
+        return if fd < fd_table.len() && fd_table[fd].is_some() {
+            Ok(fd as isize)
+        } else {
+            Err(SyscallError::EBADF)
+        };
+    }
+    args[2] = 0;
     syscall_dup3(args)
 }
 
@@ -303,36 +494,48 @@ pub fn syscall_dup2(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * fd: usize, 原文件所在的文件描述符
 /// * new_fd: usize, 新的文件描述符
+/// * flags: usize, 仅支持 O_CLOEXEC
 /// 返回值:成功执行,返回新的文件描述符。失败,返回-1。
+///
+/// 常用于 shell 在子进程中将文件重定向到标准输入输出上。整个替换过程都持有文件描述符表的锁，
+/// 因此 new_fd 上原有的文件（如控制台）会被原子地替换。替换后两个文件描述符各自持有文件的引用，
+/// 关闭原文件描述符不会影响 new_fd
 pub fn syscall_dup3(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let new_fd = args[1];
+    let flags = OpenFlags::from_bits_truncate(args[2] as u32);
+    if fd == new_fd || !(flags - OpenFlags::CLOEXEC).is_empty() {
+        return Err(SyscallError::EINVAL);
+    }
     let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
     if fd >= fd_table.len() {
         debug!("fd {} is out of range", fd);
-        return Err(SyscallError::EPERM);
+        return Err(SyscallError::EBADF);
     }
     if fd_table[fd].is_none() {
         debug!("fd {} is not opened", fd);
-        return Err(SyscallError::EPERM);
-    }
-    if new_fd >= fd_table.len() {
-        if new_fd >= (process.fd_manager.get_limit() as usize) {
-            // 超出了资源限制
-            return Err(SyscallError::EBADF);
-        }
-        for _i in fd_table.len()..new_fd + 1 {
-            fd_table.push(None);
-        }
+        return Err(SyscallError::EBADF);
     }
-    // if process_inner.fd_manager.fd_table[new_fd].is_some() {
-    //     debug!("new_fd {} is already opened", new_fd);
-    //     return ErrorNo::EINVAL as isize;
-    // }
     info!("dup3 fd {} to new fd {}", fd, new_fd);
-    // 就算new_fd已经被打开了,也可以被重新替代掉
-    fd_table[new_fd] = fd_table[fd].clone();
+    // 就算new_fd已经被打开了,也可以被重新替代掉；close_on_exec 位只属于 new_fd，不影响 fd
+    let file = fd_table[fd].clone().unwrap();
+    let old_file = process
+        .fd_manager()
+        .insert_at(
+            &mut fd_table,
+            new_fd,
+            file,
+            flags.contains(OpenFlags::CLOEXEC),
+        )
+        // 超出了资源限制
+        .map_err(|_| SyscallError::EBADF)?;
+    // new_fd 上原来的文件在释放文件描述符表的锁之后再关闭，与 Linux 一样忽略关闭时的错误
+    drop(fd_table);
+    if let Some(old_file) = old_file {
+        let _ = flush_on_close(old_file.as_ref());
+    }
     Ok(new_fd as isize)
 }
 
@@ -346,31 +549,82 @@ pub fn syscall_dup3(args: [usize; 6]) -> SyscallResult {
 ///
 /// 说明:如果打开的是一个目录,那么返回的文件描述符指向的是该目录的描述符。(后面会用到针对目录的文件描述符)
 /// flags: O_RDONLY: 0, O_WRONLY: 1, O_RDWR: 2, O_CREAT: 64, O_DIRECTORY: 65536
+///
+/// O_DIRECTORY 打开普通文件返回 ENOTDIR，以可写方式打开目录返回 EISDIR。
+/// 相对路径需要 fd 时，fd 未打开返回 EBADF，fd 不是目录返回 ENOTDIR
 pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let path = args[1] as *const u8;
     let flags = args[2];
-    let _mode = args[3] as u8;
+    let mode = args[3] as u32;
     let force_dir = OpenFlags::from(flags).is_dir();
-    let path = if let Some(path) = deal_with_path(fd, Some(path), force_dir) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
     let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
+    // 先读出路径，以便区分 EFAULT 与 ENAMETOOLONG
+    let path = user_path(args[1])?;
+    let mut path = resolve_path_at(fd, path, force_dir)?;
+    // 没有 O_DIRECTORY 时打开的也可能是目录，同样需要得到目录的文件描述符，
+    // 之后才能作为 *at 系统调用的 dirfd
+    if !path.is_dir() && axfs::api::metadata(path.path()).is_ok_and(|meta| meta.is_dir()) {
+        path = FilePath::new(&format!("{}/", path.path()))?;
+    }
+    // /dev/tty 总是指向进程的控制终端，不受 0/1/2 重定向的影响
+    if path.path() == "/dev/tty" && !process.has_ctty() {
+        return Err(SyscallError::ENXIO);
+    }
+    let fd_manager = process.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
     let fd_num: usize = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
         fd
     } else {
         return Err(SyscallError::EMFILE);
     };
     debug!("allocated fd_num: {}", fd_num);
-    // 分配 inode
-    new_inode(path.path().to_string()).unwrap();
+    // close_on_exec 位记录在文件描述符上，目录等不保存打开标志的文件也能保留它
+    process
+        .fd_manager()
+        .set_close_on_exec(fd_num, OpenFlags::from(flags).is_close_on_exec());
+    if path.path() == "/dev/tty" {
+        fd_table[fd_num] = Some(Arc::new(Tty {
+            flags: Mutex::new(flags.into()),
+            fasync: Arc::default(),
+        }));
+        return Ok(fd_num as isize);
+    }
+    // /proc/<pid>/task 下的文件根据线程信息动态生成
+    if let Some(file) = open_proc_task(path.path(), flags.into()) {
+        return match file {
+            Ok(file) => {
+                fd_table[fd_num] = Some(file);
+                Ok(fd_num as isize)
+            }
+            Err(_) => Err(SyscallError::ENOENT),
+        };
+    }
+    // /proc/<pid>/maps、smaps 与 statm 根据进程的地址空间生成
+    if let Some(file) = open_proc_mem(path.path(), flags.into()) {
+        return match file {
+            Ok(file) => {
+                fd_table[fd_num] = Some(file);
+                Ok(fd_num as isize)
+            }
+            Err(_) => Err(SyscallError::ENOENT),
+        };
+    }
+    // overcommit_memory 的读写对应内核当前的 overcommit 策略，vmstat 报告页面回收的统计
+    if let Some(kind) = ProcVmFileKind::from_path(path.path()) {
+        fd_table[fd_num] = Some(Arc::new(ProcVmFile::new(kind, flags.into())));
+        return Ok(fd_num as isize);
+    }
     // 如果是DIR
     info!("path: {:?}", path.path());
     if path.is_dir() {
         debug!("open dir");
+        // O_DIRECTORY 只能打开目录，目录不能以可写的方式打开
+        if let Err(AxError::NotADirectory) = axfs::api::metadata(path.path()) {
+            return Err(SyscallError::ENOTDIR);
+        }
+        if OpenFlags::from(flags).writable() {
+            return Err(SyscallError::EISDIR);
+        }
         if let Ok(dir) = new_dir(path.path().to_string(), flags.into()) {
             debug!("new dir_desc successfully allocated: {}", path.path());
             fd_table[fd_num] = Some(Arc::new(dir));
@@ -383,14 +637,32 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
     // 如果是FILE,注意若创建了新文件,需要添加链接
     else {
         debug!("open file");
-        if let Ok(file) = new_fd(path.path().to_string(), flags.into()) {
-            debug!("new file_desc successfully allocated");
-            fd_table[fd_num] = Some(Arc::new(file));
-            let _ = create_link(&path, &path); // 不需要检查是否成功,因为如果成功,说明是新建的文件,如果失败,说明已经存在了
-            Ok(fd_num as isize)
-        } else {
-            debug!("open file failed");
-            Err(SyscallError::ENOENT)
+        let created = OpenFlags::from(flags).creatable() && !axfs::api::path_exists(path.path());
+        match new_fd(path.path().to_string(), flags.into()) {
+            Ok(file) => {
+                debug!("new file_desc successfully allocated");
+                if created {
+                    // 新建文件的权限需要经过进程 umask 的屏蔽
+                    let mode = process.fs_context.apply_umask(mode);
+                    let _ = set_permissions(path.path(), mode as usize);
+                    // 新建文件修改了所在目录的内容
+                    file_created(path.path());
+                    if let Ok(dir) = path.dir() {
+                        file_modified(dir);
+                    }
+                } else if OpenFlags::from(flags).is_truncate() && OpenFlags::from(flags).writable()
+                {
+                    file_modified(path.path());
+                }
+                fd_table[fd_num] = Some(Arc::new(file));
+                let _ = create_link(&path, &path); // 不需要检查是否成功,因为如果成功,说明是新建的文件,如果失败,说明已经存在了
+                Ok(fd_num as isize)
+            }
+            // 如 O_CREAT | O_EXCL 时文件已存在返回 EEXIST，不存在且没有 O_CREAT 时返回 ENOENT
+            Err(err) => {
+                debug!("open file failed: {:?}", err);
+                Err(SyscallError::from(err))
+            }
         }
     }
 }
@@ -415,36 +687,43 @@ pub fn syscall_open(args: [usize; 6]) -> SyscallResult {
 /// 功能:关闭一个文件描述符；
 /// # Arguments
 /// * `fd`: usize, 要关闭的文件描述符。
-/// 返回值:成功执行,返回0。失败,返回-1。
+/// 返回值:成功执行,返回0。fd 超出文件描述符表或没有打开时返回 EBADF，负数的 fd 同样如此。
 pub fn syscall_close(args: [usize; 6]) -> SyscallResult {
+    // 按 usize 检查整个参数，不截断为 i32，否则高位不为 0 的值会关闭另一个文件描述符
     let fd = args[0];
     info!("Into syscall_close. fd: {}", fd);
 
     let process = current_process();
-    let mut fd_table = process.fd_manager.fd_table.lock();
-    if fd >= fd_table.len() {
-        debug!("fd {} is out of range", fd);
-        return Err(SyscallError::EPERM);
+    match process.fd_manager().remove(fd) {
+        // 文件在此处被丢弃，此时已经不再持有文件描述符表的锁
+        Some(file) => {
+            let flushed = flush_on_close(file.as_ref());
+            drop(file);
+            // 与 Linux 一样，即使写回失败，文件描述符也已经被关闭
+            flushed.map_err(SyscallError::from)?;
+        }
+        None => {
+            debug!("fd {} is none", fd);
+            return Err(SyscallError::EBADF);
+        }
     }
-    // if fd == 3 {
-    //     debug!("fd {} is reserved for cwd", fd);
-    //     return -1;
-    // }
-    if fd_table[fd].is_none() {
-        debug!("fd {} is none", fd);
-        return Err(SyscallError::EPERM);
-    }
-    // let file = process_inner.fd_manager.fd_table[fd].unwrap();
-    fd_table[fd] = None;
-    // for i in 0..process_inner.fd_table.len() {
-    //     if let Some(file) = process_inner.fd_table[i].as_ref() {
-    //         debug!("fd: {} has file", i);
-    //     }
-    // }
 
     Ok(0)
 }
 
+/// 关闭可写的普通文件前写回文件系统中缓存的数据，写回的错误作为 close 的返回值
+///
+/// 底层文件系统不需要写回时 flush 返回 InvalidInput，此时视为成功
+fn flush_on_close(file: &dyn FileIO) -> AxResult<()> {
+    if file.get_type() != FileIOType::FileDesc || !file.writable() {
+        return Ok(());
+    }
+    match file.flush() {
+        Err(AxError::InvalidInput) => Ok(()),
+        result => result,
+    }
+}
+
 /// 67
 /// pread64
 /// 从文件的指定位置读取数据,并且不改变文件的读写指针
@@ -455,20 +734,22 @@ pub fn syscall_close(args: [usize; 6]) -> SyscallResult {
 /// * `offset`: usize
 pub fn syscall_pread64(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let buf = args[1] as *mut u8;
+    let buf = args[1];
     let count = args[2];
     let offset = args[3];
     let process = current_process();
-    // todo: 把check fd整合到fd_manager中
-    let file = process.fd_manager.fd_table.lock()[fd].clone().unwrap();
-
-    let old_offset = file.seek(SeekFrom::Current(0)).unwrap();
-    let ret = file
-        .seek(SeekFrom::Start(offset as u64))
-        .and_then(|_| file.read(unsafe { core::slice::from_raw_parts_mut(buf, count) }));
-    file.seek(SeekFrom::Start(old_offset)).unwrap();
-    ret.map(|size| Ok(size as isize))
-        .unwrap_or_else(|_| Err(SyscallError::EINVAL))
+    let file = fd_file(&process, fd)?;
+
+    // 管道等不能定位的文件返回 ESPIPE
+    let old_offset = file
+        .seek(SeekFrom::Current(0))
+        .map_err(|_| SyscallError::ESPIPE)?;
+    let ret = match file.seek(SeekFrom::Start(offset as u64)) {
+        Ok(_) => read_file_by_page(file.as_ref(), buf, count),
+        Err(_) => Err(SyscallError::EINVAL),
+    };
+    let _ = file.seek(SeekFrom::Start(old_offset));
+    ret
 }
 
 /// 68
@@ -481,25 +762,29 @@ pub fn syscall_pread64(args: [usize; 6]) -> SyscallResult {
 /// * `offset`: usize
 pub fn syscall_pwrite64(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let buf = args[1] as *const u8;
+    let buf = args[1];
     let count = args[2];
     let offset = args[3];
     let process = current_process();
+    let file = fd_file(&process, fd)?;
+
+    let old_offset = file
+        .seek(SeekFrom::Current(0))
+        .map_err(|_| SyscallError::ESPIPE)?;
+    let ret = match file.seek(SeekFrom::Start(offset as u64)) {
+        Ok(_) => write_file_by_page(file.as_ref(), buf, count),
+        Err(_) => Err(SyscallError::EINVAL),
+    };
+    let _ = file.seek(SeekFrom::Start(old_offset));
+    ret
+}
 
-    let file = process.fd_manager.fd_table.lock()[fd].clone().unwrap();
-
-    let old_offset = file.seek(SeekFrom::Current(0)).unwrap();
-
-    let ret = file.seek(SeekFrom::Start(offset as u64)).and_then(|_| {
-        let res = file.write(unsafe { core::slice::from_raw_parts(buf, count) });
-        res
-    });
-
-    file.seek(SeekFrom::Start(old_offset)).unwrap();
-    drop(file);
-
-    ret.map(|size| Ok(size as isize))
-        .unwrap_or_else(|_| Err(SyscallError::EINVAL))
+/// 取出文件描述符 `fd` 对应的文件，`fd` 未打开时返回 EBADF
+fn fd_file(process: &Process, fd: usize) -> Result<Arc<dyn FileIO>, SyscallError> {
+    match process.fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => Ok(Arc::clone(file)),
+        _ => Err(SyscallError::EBADF),
+    }
 }
 
 /// 71
@@ -507,6 +792,8 @@ pub fn syscall_pwrite64(args: [usize; 6]) -> SyscallResult {
 /// 将一个文件的内容发送到另一个文件中
 /// 如果offset为NULL,则从当前读写指针开始读取,读取完毕后会更新读写指针
 /// 如果offset不为NULL,则从offset指定的位置开始读取,读取完毕后不会更新读写指针,但是会更新offset的值
+///
+/// 数据按块在内核中转发，读到文件末尾或某一块没有写完时停止，返回实际写入的字节数
 /// # Arguments
 /// * `out_fd`: usize
 /// * `in_fd`: usize
@@ -515,41 +802,72 @@ pub fn syscall_pwrite64(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_sendfile64(args: [usize; 6]) -> SyscallResult {
     let out_fd = args[0];
     let in_fd = args[1];
-    let offset = args[2] as *mut usize;
+    let offset = args[2];
     let count = args[3];
     info!("send from {} to {}, count: {}", in_fd, out_fd, count);
     let process = current_process();
-    let out_file = process.fd_manager.fd_table.lock()[out_fd].clone().unwrap();
-    let in_file = process.fd_manager.fd_table.lock()[in_fd].clone().unwrap();
-    let old_in_offset = in_file.seek(SeekFrom::Current(0)).unwrap();
+    let out_file = fd_file(&process, out_fd)?;
+    let in_file = fd_file(&process, in_fd)?;
+    if !in_file.readable() || !out_file.writable() {
+        return Err(SyscallError::EBADF);
+    }
 
-    let mut buf = vec![0u8; count];
-    if !offset.is_null() {
-        // 如果offset不为NULL,则从offset指定的位置开始读取
-        let in_offset = unsafe { *offset };
-        in_file.seek(SeekFrom::Start(in_offset as u64)).unwrap();
-        let ret = in_file.read(buf.as_mut_slice());
-        unsafe { *offset = in_offset + ret.unwrap() };
-        in_file.seek(SeekFrom::Start(old_in_offset)).unwrap();
-        let buf = buf[..ret.unwrap()].to_vec();
-        Ok(out_file.write(buf.as_slice()).unwrap() as isize)
+    // 如果offset不为NULL,则从offset指定的位置开始读取，结束后恢复原来的读写指针
+    let saved = if offset != 0 {
+        let in_offset: usize = copy_struct_from_user(offset)?;
+        let old_in_offset = in_file
+            .seek(SeekFrom::Current(0))
+            .map_err(|_| SyscallError::ESPIPE)?;
+        in_file
+            .seek(SeekFrom::Start(in_offset as u64))
+            .map_err(|_| SyscallError::EINVAL)?;
+        Some((in_offset, old_in_offset))
     } else {
-        // 如果offset为NULL,则从当前读写指针开始读取
-        let ret = in_file.read(buf.as_mut_slice());
-        info!("in fd: {}, count: {}", in_fd, count);
-        let buf = buf[..ret.unwrap()].to_vec();
-        info!("read len: {}", buf.len());
-        info!("write len: {}", buf.as_slice().len());
-        Ok(out_file.write(buf.as_slice()).unwrap() as isize)
+        None
+    };
+
+    let mut buf = vec![0u8; count.min(STREAM_CHUNK_SIZE)];
+    let mut sent = 0;
+    let mut ret = Ok(());
+    while sent < count {
+        let want = (count - sent).min(STREAM_CHUNK_SIZE);
+        let read_len = match in_file.read(&mut buf[..want]) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) => {
+                ret = Err(read_error(err));
+                break;
+            }
+        };
+        let write_len = match out_file.write(&buf[..read_len]) {
+            Ok(len) => len,
+            Err(err) => {
+                ret = Err(write_error(err));
+                0
+            }
+        };
+        sent += write_len;
+        if write_len < read_len {
+            // 没有写出去的数据应当在下次读取时重新读到
+            let _ = in_file.seek(SeekFrom::Current(write_len as i64 - read_len as i64));
+            break;
+        }
+    }
+
+    if let Some((in_offset, old_in_offset)) = saved {
+        let _ = in_file.seek(SeekFrom::Start(old_in_offset));
+        copy_struct_to_user(offset, in_offset + sent)?;
+    }
+    match ret {
+        Err(err) if sent == 0 => Err(err),
+        _ => Ok(sent as isize),
     }
 }
 
 /// 78
 /// readlinkat
-/// 读取符号链接文件的内容
-/// * 如果buf为NULL,则返回符号链接文件的长度
-/// * 如果buf不为NULL,则将符号链接文件的内容写入buf中
-/// 如果写入的内容超出了buf_size则直接截断
+/// 读取符号链接文件的内容，写入 buf 中，超出 bufsiz 的部分被截断，结尾不添加 '\0'
+/// 返回写入的字节数；路径不是符号链接时返回 EINVAL，bufsiz 为 0 时返回 EINVAL
 /// # Arguments
 /// * `dir_fd`: usize
 /// * `path`: *const u8
@@ -557,57 +875,35 @@ pub fn syscall_sendfile64(args: [usize; 6]) -> SyscallResult {
 /// * `bufsiz`: usize
 pub fn syscall_readlinkat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
-    let path = args[1] as *const u8;
-    let buf = args[2] as *mut u8;
-    let bufsiz = args[3];
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy((path as usize).into())
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-    if !buf.is_null()
-        && process
-            .manual_alloc_for_lazy((buf as usize).into())
-            .is_err()
-    {
-        return Err(SyscallError::EFAULT);
+    let buf = args[2];
+    let bufsiz = args[3] as isize;
+    if bufsiz <= 0 {
+        return Err(SyscallError::EINVAL);
     }
+    let path = user_path(args[1])?;
+    let path = deal_with_path_str(dir_fd, path, false).ok_or(SyscallError::ENOENT)?;
+    // 将链接的内容写入 buf，超出 bufsiz 的部分被截断
+    let write_target = |target: &str| -> SyscallResult {
+        let len = (bufsiz as usize).min(target.len());
+        copy_to_user(buf, &target.as_bytes()[..len])?;
+        Ok(len as isize)
+    };
 
-    let path = deal_with_path(dir_fd, Some(path), false);
-
-    if path.is_none() {
-        return Err(SyscallError::ENOENT);
-    }
-    let path = path.unwrap();
-    if path.path() == "proc/self/exe" {
-        // 针对lmbench_all特判
-        let name = "/lmbench_all";
-        let len = bufsiz.min(name.len());
-        let slice = unsafe { core::slice::from_raw_parts_mut(buf, bufsiz) };
-        slice.copy_from_slice(&name.as_bytes()[..len]);
-        return Ok(len as isize);
+    // /proc/<pid>/exe 与 /proc/<pid>/cwd，可执行文件的路径在 exec 时记录
+    if let Some(target) = proc_link_target(path.path()) {
+        return write_target(&target.map_err(|_| SyscallError::ENOENT)?);
     }
 
-    // 获取进程自身的符号链接信息
-    if path.path() == "/proc/self/exe" {
-        // 获取该进程符号链接对应的真正地址
-        let file_real_path = process.get_file_path();
-        let len = bufsiz.min(file_real_path.len());
-        let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
-        slice.copy_from_slice(&file_real_path.as_bytes()[..len]);
-
-        return Ok(file_real_path.len() as isize);
+    if let Some(target) = read_symlink(path.path()) {
+        return write_target(&target);
     }
-
-    if *path.path() != real_path(&(path.path().to_string())) {
+    let target = real_path(&(path.path().to_string()));
+    if *path.path() != target {
         // 说明链接存在
-        let path = path.path();
-        let len = bufsiz.min(path.len());
-        let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
-        slice.copy_from_slice(&path.as_bytes()[..len]);
-        return Ok(path.len() as isize);
+        return write_target(&target);
+    }
+    if !axfs::api::path_exists(path.path()) {
+        return Err(SyscallError::ENOENT);
     }
     Err(SyscallError::EINVAL)
 }
@@ -635,42 +931,36 @@ pub fn syscall_readlink(args: [usize; 6]) -> SyscallResult {
 /// * `fd`: usize
 /// * `offset`: isize
 /// * `whence`: usize
+///
+/// 目录的读写指针是 getdents64 读取的位置，可以设置为 0 或之前读到的目录项的 d_off
 pub fn syscall_lseek(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let offset = args[1] as isize;
     let whence = args[2];
     let process = current_process();
     info!("fd: {} offset: {} whence: {}", fd, offset, whence);
-    if fd >= process.fd_manager.fd_table.lock().len() || fd < 3 {
-        debug!("fd {} is out of range", fd);
-        return Err(SyscallError::EBADF);
-    }
-    let fd_table = process.fd_manager.fd_table.lock();
-    if let Some(file) = fd_table[fd].as_ref() {
-        if file.get_type() == FileIOType::DirDesc {
-            debug!("fd is a dir");
-            return Err(SyscallError::EISDIR);
-        }
-        let ans = if whence == 0 {
-            // 即SEEK_SET
-            file.seek(SeekFrom::Start(offset as u64))
-        } else if whence == 1 {
-            // 即SEEK_CUR
-            file.seek(SeekFrom::Current(offset as i64))
-        } else if whence == 2 {
-            // 即SEEK_END
-            file.seek(SeekFrom::End(offset as i64))
-        } else {
-            return Err(SyscallError::EINVAL);
-        };
-        if let Ok(now_offset) = ans {
-            Ok(now_offset as isize)
-        } else {
-            Err(SyscallError::EINVAL)
+    let file = match process.fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => {
+            debug!("fd {} is none", fd);
+            return Err(SyscallError::EBADF);
         }
-    } else {
-        debug!("fd {} is none", fd);
-        Err(SyscallError::EBADF)
+    };
+    let pos = match whence {
+        // 即SEEK_SET
+        0 if offset < 0 => return Err(SyscallError::EINVAL),
+        0 => SeekFrom::Start(offset as u64),
+        // 即SEEK_CUR
+        1 => SeekFrom::Current(offset as i64),
+        // 即SEEK_END
+        2 => SeekFrom::End(offset as i64),
+        _ => return Err(SyscallError::EINVAL),
+    };
+    // 管道、套接字与终端等不支持 seek 的文件返回 ESPIPE，结果为负的偏移量返回 EINVAL
+    match file.seek(pos) {
+        Ok(now_offset) => Ok(now_offset as isize),
+        Err(AxError::Unsupported) => Err(SyscallError::ESPIPE),
+        Err(_) => Err(SyscallError::EINVAL),
     }
 }
 
@@ -682,11 +972,12 @@ pub fn syscall_lseek(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let process = current_process();
-    if fd >= process.fd_manager.fd_table.lock().len() || fd < 3 {
+    if fd >= process.fd_manager().fd_table.lock().len() || fd < 3 {
         debug!("fd {} is out of range", fd);
         return Err(SyscallError::EBADF);
     }
-    let fd_table = process.fd_manager.fd_table.lock();
+    let fd_manager = process.fd_manager();
+    let fd_table = fd_manager.fd_table.lock();
     if let Some(file) = fd_table[fd].clone() {
         // if file.flush().is_err() {}
         Ok(0)
@@ -712,20 +1003,20 @@ pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
 /// * `flags`: usize
 pub fn syscall_copyfilerange(args: [usize; 6]) -> SyscallResult {
     let fd_in = args[0];
-    let off_in = args[1] as *mut usize;
+    let off_in = args[1];
     let fd_out = args[2];
-    let off_out = args[3] as *mut usize;
+    let off_out = args[3];
     let len = args[4];
     let flags = args[5];
-    let in_offset = if off_in.is_null() {
+    let in_offset = if off_in == 0 {
         -1
     } else {
-        unsafe { *off_in as isize }
+        copy_struct_from_user::<usize>(off_in)? as isize
     };
-    let out_offset = if off_out.is_null() {
+    let out_offset = if off_out == 0 {
         -1
     } else {
-        unsafe { *off_out as isize }
+        copy_struct_from_user::<usize>(off_out)? as isize
     };
     if len == 0 {
         return Ok(0);
@@ -735,9 +1026,14 @@ pub fn syscall_copyfilerange(args: [usize; 6]) -> SyscallResult {
         fd_in, fd_out, in_offset, out_offset, len, flags
     );
     let process = current_process();
-    let fd_table = process.fd_manager.fd_table.lock();
-    let out_file = fd_table[fd_out].clone().unwrap();
-    let in_file = fd_table[fd_in].clone().unwrap();
+    let (in_file, out_file) = {
+        let fd_manager = process.fd_manager();
+        let fd_table = fd_manager.fd_table.lock();
+        match (fd_table.get(fd_in), fd_table.get(fd_out)) {
+            (Some(Some(in_file)), Some(Some(out_file))) => (in_file.clone(), out_file.clone()),
+            _ => return Err(SyscallError::EBADF),
+        }
+    };
     let old_in_offset = in_file.seek(SeekFrom::Current(0)).unwrap();
     let old_out_offset = out_file.seek(SeekFrom::Current(0)).unwrap();
 
@@ -746,11 +1042,11 @@ pub fn syscall_copyfilerange(args: [usize; 6]) -> SyscallResult {
     // }
 
     // set offset
-    if !off_in.is_null() {
+    if off_in != 0 {
         in_file.seek(SeekFrom::Start(in_offset as u64)).unwrap();
     }
 
-    if !off_out.is_null() {
+    if off_out != 0 {
         out_file.seek(SeekFrom::Start(out_offset as u64)).unwrap();
     }
 
@@ -763,17 +1059,13 @@ pub fn syscall_copyfilerange(args: [usize; 6]) -> SyscallResult {
     // assert_eq!(read_len, write_len);    // tmp
 
     // set offset | modify off_in & off_out
-    if !off_in.is_null() {
+    if off_in != 0 {
         in_file.seek(SeekFrom::Start(old_in_offset)).unwrap();
-        unsafe {
-            *off_in += read_len;
-        }
+        copy_struct_to_user(off_in, in_offset as usize + read_len)?;
     }
-    if !off_out.is_null() {
+    if off_out != 0 {
         out_file.seek(SeekFrom::Start(old_out_offset)).unwrap();
-        unsafe {
-            *off_out += write_len;
-        }
+        copy_struct_to_user(off_out, out_offset as usize + write_len)?;
     }
 
     Ok(write_len as isize)
@@ -787,18 +1079,14 @@ pub fn syscall_ftruncate64(args: [usize; 6]) -> SyscallResult {
     let len = args[1];
     let process = current_process();
     info!("fd: {}, len: {}", fd, len);
-    let fd_table = process.fd_manager.fd_table.lock();
-    if fd >= fd_table.len() {
-        return Err(SyscallError::EINVAL);
-    }
-    if fd_table[fd].is_none() {
-        return Err(SyscallError::EINVAL);
-    }
-
-    if let Some(file) = fd_table[fd].as_ref() {
-        if file.truncate(len).is_err() {
-            return Err(SyscallError::EINVAL);
-        }
-    }
+    let file = match process.fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EINVAL),
+    };
+    file.truncate(len).map_err(|err| match err {
+        AxError::FileTooLarge => SyscallError::EFBIG,
+        AxError::NoMemory => SyscallError::ENOMEM,
+        _ => SyscallError::EINVAL,
+    })?;
     Ok(0)
 }
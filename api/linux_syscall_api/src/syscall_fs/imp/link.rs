@@ -3,9 +3,19 @@
 // const STDERR: usize = 2;
 extern crate alloc;
 
-use crate::{SyscallError, SyscallResult};
+use crate::{
+    syscall_fs::ctype::{
+        file::inode_number,
+        times::{file_changed, file_modified},
+    },
+    SyscallError, SyscallResult,
+};
 use axlog::debug;
-use axprocess::link::{create_link, deal_with_path, remove_link, FilePath};
+use axmem::invalidate_page_cache;
+use axprocess::link::{
+    create_link, create_symlink, remove_link, remove_symlink, resolve_path_at, FilePath,
+};
+use axprocess::uaccess::user_path;
 
 /// Special value used to indicate openat should use the current working directory.
 pub const AT_REMOVEDIR: usize = 0x200; // Remove directory instead of unlinking file.
@@ -27,23 +37,52 @@ pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
     let new_path = args[3] as *const u8;
     let _flags = args[4];
 
-    let old_path = if let Some(path) = deal_with_path(old_dir_fd, Some(old_path), false) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
-    let new_path = if let Some(path) = deal_with_path(new_dir_fd, Some(new_path), false) {
-        path
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
+    let old_path = resolve_path_at(old_dir_fd, user_path(old_path as usize)?, false)?;
+    let new_path = resolve_path_at(new_dir_fd, user_path(new_path as usize)?, false)?;
     if create_link(&old_path, &new_path) {
+        // 链接数是 inode 的元数据，新的目录项修改了所在目录
+        file_changed(old_path.path());
+        if let Ok(dir) = new_path.dir() {
+            file_modified(dir);
+        }
         Ok(0)
     } else {
         Err(SyscallError::EINVAL)
     }
 }
 
+/// 功能:创建符号链接；
+/// # Arguments
+/// * `target`: *const u8, 链接的内容。创建时不检查它指向的文件是否存在。
+/// * `link_path`: *const u8, 链接的名字。
+/// # Return
+/// 成功执行,返回0。链接已经存在时返回 EEXIST。
+#[cfg(target_arch = "x86_64")]
+pub fn syscall_symlink(args: [usize; 6]) -> SyscallResult {
+    let temp_args = [args[0], axprocess::link::AT_FDCWD, args[1], 0, 0, 0];
+    syscall_symlinkat(temp_args)
+}
+
+/// 功能:创建符号链接；
+/// # Arguments
+/// * `target`: *const u8, 链接的内容。创建时不检查它指向的文件是否存在。
+/// * `new_dir_fd`: usize, 链接所在目录的文件描述符。
+/// * `link_path`: *const u8, 链接的名字。如果link_path是相对路径,则它是相对于new_dir_fd目录而言的。如果link_path是相对路径,且new_dir_fd的值为AT_FDCWD,则它是相对于当前路径而言的。
+/// # Return
+/// 成功执行,返回0。链接已经存在时返回 EEXIST。
+pub fn syscall_symlinkat(args: [usize; 6]) -> SyscallResult {
+    let target = user_path(args[0])?;
+    if target.is_empty() {
+        return Err(SyscallError::ENOENT);
+    }
+    let link_path = resolve_path_at(args[1], user_path(args[2])?, false)?;
+    create_symlink(&target, &link_path)?;
+    if let Ok(dir) = link_path.dir() {
+        file_modified(dir);
+    }
+    Ok(0)
+}
+
 /// 功能:移除指定文件的链接
 /// # Arguments
 /// * `path`: *const u8, 要删除的链接的名字。
@@ -67,17 +106,22 @@ pub fn syscall_unlinkat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let flags = args[2];
-    let path = deal_with_path(dir_fd, Some(path), false).unwrap();
+    let path = resolve_path_at(dir_fd, user_path(path as usize)?, false)?;
 
     if path.start_with(&FilePath::new("/proc").unwrap()) {
-        return Ok(-1);
+        return Err(SyscallError::EPERM);
     }
 
-    // unlink file
+    // unlink file, 符号链接只删除链接本身，而不是它指向的文件
     if flags == 0 {
-        if remove_link(&path).is_none() {
-            debug!("unlink file error");
-            return Err(SyscallError::EINVAL);
+        if !remove_symlink(path.path()) {
+            // 之后在同一路径上新建的文件可能得到相同的 inode 号，因此丢弃页缓存
+            let ino = inode_number(path.path());
+            if remove_link(&path).is_none() {
+                debug!("unlink file error");
+                return Err(SyscallError::EINVAL);
+            }
+            invalidate_page_cache(ino);
         }
     }
     // remove dir
@@ -92,5 +136,8 @@ pub fn syscall_unlinkat(args: [usize; 6]) -> SyscallResult {
         debug!("flags error");
         return Err(SyscallError::EINVAL);
     }
+    if let Ok(dir) = path.dir() {
+        file_modified(dir);
+    }
     Ok(0)
 }
@@ -0,0 +1,35 @@
+use alloc::{string::String, sync::Arc};
+use axfs::api::OpenFlags;
+use axprocess::uaccess::strncpy_from_user;
+
+use crate::syscall_fs::ctype::{
+    anon_inode,
+    memfd::{MemFd, MemFdFlags, MFD_NAME_MAX_LEN},
+};
+use crate::{SyscallError, SyscallResult};
+
+/// 创建一个匿名内存文件
+///
+/// 名称只用于 /proc/self/fd 中显示的路径，可以重复，超过 [`MFD_NAME_MAX_LEN`] 时返回 EINVAL
+/// # Arguments
+/// * `name`: *const u8
+/// * `flags`: u32, MFD_CLOEXEC 与 MFD_ALLOW_SEALING 的组合
+pub fn syscall_memfd_create(args: [usize; 6]) -> SyscallResult {
+    let name = args[0];
+    let flags = MemFdFlags::from_bits(args[1] as u32).ok_or(SyscallError::EINVAL)?;
+    if flags.contains(MemFdFlags::MFD_EXEC | MemFdFlags::MFD_NOEXEC_SEAL) {
+        return Err(SyscallError::EINVAL);
+    }
+    let name = strncpy_from_user(name, MFD_NAME_MAX_LEN + 1).map_err(|err| match err {
+        SyscallError::ENAMETOOLONG => SyscallError::EINVAL,
+        err => err,
+    })?;
+    let name = String::from_utf8_lossy(&name);
+
+    let open_flags = if flags.contains(MemFdFlags::MFD_CLOEXEC) {
+        OpenFlags::CLOEXEC
+    } else {
+        OpenFlags::empty()
+    };
+    anon_inode::install_fd(Arc::new(MemFd::new(&name, flags)), open_flags)
+}
@@ -6,6 +6,7 @@ mod epoll;
 mod eventfd;
 mod io;
 mod link;
+mod memfd;
 mod mount;
 mod poll;
 mod stat;
@@ -14,6 +15,7 @@ pub use epoll::*;
 pub use eventfd::*;
 pub use io::*;
 pub use link::*;
+pub use memfd::*;
 pub use mount::*;
 pub use poll::*;
 pub use stat::*;
@@ -1,51 +1,44 @@
 use crate::{SyscallError, SyscallResult};
+use axhal::mem::PAGE_SIZE_4K;
 use axprocess::{
-    current_process,
-    link::{deal_with_path, raw_ptr_to_ref_str, AT_FDCWD},
+    link::{deal_with_path_str, AT_FDCWD},
+    uaccess::{strncpy_from_user, user_path},
 };
 
 // use super::{deal_with_path, AT_FDCWD};
-use crate::syscall_fs::ctype::mount::{check_mounted, mount_fat_fs, umount_fat_fs};
+use crate::syscall_fs::ctype::{
+    mount::{check_mounted, mount_fat_fs, umount_fat_fs},
+    times::AtimePolicy,
+};
 extern crate alloc;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use axlog::debug;
 /// 功能:挂载文件系统；
 /// # Arguments
 /// * `special`: *const u8, 挂载设备
 /// * `dir`: *const u8, 挂载点
 /// * `fs_type`: *const u8, 挂载的文件系统类型
-/// * `flags`: usize, 挂载参数
+/// * `flags`: usize, 挂载参数，目前只使用其中的 atime 策略（MS_NOATIME、MS_RELATIME、MS_STRICTATIME）
 /// * `data`: *const u8, 传递给文件系统的字符串参数,可为NULL
 /// 返回值:成功返回0,失败返回-1
 pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
     let special = args[0] as *const u8;
     let dir = args[1] as *const u8;
     let fs_type = args[2] as *const u8;
-    let _flags = args[3];
+    let flags = args[3];
     let _data = args[4] as *const u8;
-    let device_path = deal_with_path(AT_FDCWD, Some(special), false).unwrap();
+    let device_path = deal_with_path_str(AT_FDCWD, user_path(special as usize)?, false)
+        .ok_or(SyscallError::ENOENT)?;
     // 这里dir必须以"/"结尾,但在shell中输入时,不需要以"/"结尾
-    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).unwrap();
-
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy((fs_type as usize).into())
-        .is_err()
-    {
-        return Err(SyscallError::EINVAL);
-    }
+    let mount_path =
+        deal_with_path_str(AT_FDCWD, user_path(dir as usize)?, true).ok_or(SyscallError::ENOENT)?;
 
-    let fs_type = unsafe { raw_ptr_to_ref_str(fs_type).to_string() };
+    let fs_type = user_path(fs_type as usize)?;
     let mut _data_str = "".to_string();
     if !_data.is_null() {
-        if process
-            .manual_alloc_for_lazy((_data as usize).into())
-            .is_err()
-        {
-            return Err(SyscallError::EINVAL);
-        }
-        // data可以为NULL, 必须判断, 否则会panic, 发生LoadPageFault
-        _data_str = unsafe { raw_ptr_to_ref_str(_data) }.to_string();
+        // data可以为NULL, 必须判断, 否则会返回 EFAULT
+        let data = strncpy_from_user(_data as usize, PAGE_SIZE_4K)?;
+        _data_str = String::from_utf8(data).map_err(|_| SyscallError::EINVAL)?;
     }
     if device_path.is_dir() {
         debug!("device_path should not be a dir");
@@ -79,7 +72,11 @@ pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EPERM);
     }
     // 挂载
-    if !mount_fat_fs(&device_path, &mount_path) {
+    if !mount_fat_fs(
+        &device_path,
+        &mount_path,
+        AtimePolicy::from_mount_flags(flags),
+    ) {
         debug!("mount error");
         return Err(SyscallError::EPERM);
     }
@@ -96,7 +93,8 @@ pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_umount(args: [usize; 6]) -> SyscallResult {
     let dir = args[0] as *const u8;
     let flags = args[1];
-    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).unwrap();
+    let mount_path =
+        deal_with_path_str(AT_FDCWD, user_path(dir as usize)?, true).ok_or(SyscallError::ENOENT)?;
 
     if flags != 0 {
         debug!("flags unimplemented");
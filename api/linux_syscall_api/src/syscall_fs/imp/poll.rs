@@ -1,9 +1,14 @@
 use axfs::api::FileIO;
-use axhal::{mem::VirtAddr, time::current_ticks};
-use axprocess::{current_process, yield_now_task};
+use axhal::time::{current_time, TimeValue};
+use axprocess::{
+    current_process,
+    signal::wait_with_sigmask,
+    uaccess::{copy_struct_from_user, copy_struct_to_user},
+    yield_now_task,
+};
 use bitflags::bitflags;
 extern crate alloc;
-use crate::{SyscallError, SyscallResult, TimeSecs};
+use crate::{SyscallError, SyscallResult, TimeSecs, SIGSET_SIZE_IN_BYTE};
 use alloc::{sync::Arc, vec::Vec};
 bitflags! {
     /// 在文件上等待或者发生过的事件
@@ -43,26 +48,30 @@ pub struct PollFd {
 }
 
 /// 定义一个bitset,用于查找掩码
+///
+/// 用户空间中的 bitset 在创建时复制到内核中，修改之后由 [`ShadowBitset::write_back`] 写回
+#[derive(Default)]
 struct ShadowBitset {
     /// start address of the bitset which is in user space
-    addr: *mut usize,
+    addr: usize,
     /// 是包含的bit数目,而不是字节数目
     len: usize,
-}
-
-impl Default for ShadowBitset {
-    fn default() -> Self {
-        Self {
-            addr: core::ptr::null_mut(),
-            len: 0,
-        }
-    }
+    /// bitset 在内核中的副本
+    bits: Vec<usize>,
 }
 
 impl ShadowBitset {
-    /// create a new bitset
-    pub fn new(addr: *mut usize, len: usize) -> Self {
-        Self { addr, len }
+    /// 从用户地址 `addr` 读取一个包含 `len` 位的 bitset，`addr` 为 0 时得到无效的 bitset
+    pub fn new(addr: usize, len: usize) -> Result<Self, SyscallError> {
+        let mut bits = Vec::new();
+        if addr != 0 {
+            for i in 0..(len + 63) / 64 {
+                bits.push(copy_struct_from_user(
+                    addr + i * core::mem::size_of::<usize>(),
+                )?);
+            }
+        }
+        Ok(Self { addr, len, bits })
     }
 
     /// check if the index is set
@@ -70,10 +79,10 @@ impl ShadowBitset {
         if index >= self.len {
             return false;
         }
-        // 因为一次add会移动八个字节,所以这里需要除以64,即8个字节,每一个字节8位
-        let byte_index = index / 64;
+        // 每个 usize 包含 64 位
+        let word_index = index / 64;
         let bit_index = index & 0x3f;
-        unsafe { *self.addr.add(byte_index) & (1 << bit_index) != 0 }
+        self.bits[word_index] & (1 << bit_index) != 0
     }
 
     /// set the index in the bitset
@@ -81,43 +90,75 @@ impl ShadowBitset {
         if index >= self.len {
             return;
         }
-        let byte_index = index / 64;
+        let word_index = index / 64;
         let bit_index = index & 0x3f;
-        unsafe {
-            *self.addr.add(byte_index) |= 1 << bit_index;
-        }
+        self.bits[word_index] |= 1 << bit_index;
     }
 
     // 清空自己
-    pub fn clear(&self) {
-        for i in 0..=(self.len - 1) / 64 {
-            unsafe {
-                *(self.addr.add(i)) = 0;
-            }
-        }
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
     }
 
     /// check if the bitset is valid
     ///
     /// if the addr is null, it is invalid
     pub fn valid(&self) -> bool {
-        self.addr as usize != 0
+        self.addr != 0
+    }
+
+    /// 将内核中的副本写回用户空间
+    pub fn write_back(&self) -> Result<(), SyscallError> {
+        for (i, word) in self.bits.iter().enumerate() {
+            copy_struct_to_user(self.addr + i * core::mem::size_of::<usize>(), *word)?;
+        }
+        Ok(())
+    }
+}
+
+/// 从用户地址 `ufds` 读取 `nfds` 个 [`PollFd`]
+fn read_poll_fds(ufds: usize, nfds: usize) -> Result<Vec<PollFd>, SyscallError> {
+    if nfds > current_process().fd_manager().get_limit() as usize {
+        return Err(SyscallError::EINVAL);
     }
+    (0..nfds)
+        .map(|i| copy_struct_from_user(ufds + i * core::mem::size_of::<PollFd>()))
+        .collect()
+}
+
+/// 将 [`PollFd`] 列表写回用户地址 `ufds`
+fn write_poll_fds(ufds: usize, fds: &[PollFd]) -> Result<(), SyscallError> {
+    for (i, fd) in fds.iter().enumerate() {
+        copy_struct_to_user(ufds + i * core::mem::size_of::<PollFd>(), *fd)?;
+    }
+    Ok(())
+}
+
+/// 从用户地址 `timeout` 读取相对的超时时间，返回超时的时刻，`timeout` 为 0 时永不超时
+fn read_deadline(timeout: usize) -> Result<TimeValue, SyscallError> {
+    if timeout == 0 {
+        return Ok(TimeValue::MAX);
+    }
+    let timeout: TimeSecs = copy_struct_from_user(timeout)?;
+    Ok(current_time().saturating_add(timeout.to_duration()))
 }
 
 /// 实现ppoll系统调用
 ///
 /// fds：一个PollFd列表
-/// expire_time：时间戳,用来记录是否超时
+/// deadline：超时的时刻
 ///
 /// 返回值：(usize, Vec<PollFd>) 第一个参数遵守 ppoll 系统调用的返回值约定,第二个参数为返回的 `PollFd` 列表
-fn ppoll(mut fds: Vec<PollFd>, expire_time: usize) -> (isize, Vec<PollFd>) {
+///
+/// 若等待期间被信号打断，则返回 `EINTR`
+fn ppoll(mut fds: Vec<PollFd>, deadline: TimeValue) -> Result<(isize, Vec<PollFd>), SyscallError> {
     loop {
         // 满足事件要求而被触发的事件描述符数量
         let mut set: isize = 0;
         let process = current_process();
         for poll_fd in &mut fds {
-            let fd_table = process.fd_manager.fd_table.lock();
+            let fd_manager = process.fd_manager();
+            let fd_table = fd_manager.fd_table.lock();
             if let Some(file) = fd_table[poll_fd.fd as usize].as_ref() {
                 poll_fd.revents = PollEvents::empty();
                 // let file = file.lock();
@@ -144,21 +185,37 @@ fn ppoll(mut fds: Vec<PollFd>, expire_time: usize) -> (isize, Vec<PollFd>) {
             }
         }
         if set > 0 {
-            return (set, fds);
+            return Ok((set, fds));
         }
-        if current_ticks() as usize > expire_time {
+        if current_time() > deadline {
             // 过期了,直接返回
-            return (0, fds);
+            return Ok((0, fds));
         }
         yield_now_task();
 
         if process.have_signals().is_some() {
             // 有信号,此时停止处理,直接返回
-            return (0, fds);
+            return Err(SyscallError::EINTR);
         }
     }
 }
 
+/// 读取 ppoll、pselect6 与 epoll_pwait 传入的临时信号掩码
+///
+/// 若 `mask` 为空指针，则等待期间不替换掩码
+pub(crate) fn read_user_sigmask(
+    mask: usize,
+    sigsetsize: usize,
+) -> Result<Option<usize>, SyscallError> {
+    if mask == 0 {
+        return Ok(None);
+    }
+    if sigsetsize != SIGSET_SIZE_IN_BYTE {
+        return Err(SyscallError::EINVAL);
+    }
+    copy_struct_from_user(mask).map(Some)
+}
+
 /// 实现ppoll系统调用
 ///
 /// 其中timeout是一段相对时间,需要计算出相对于当前时间戳的绝对时间戳
@@ -167,44 +224,20 @@ fn ppoll(mut fds: Vec<PollFd>, expire_time: usize) -> (isize, Vec<PollFd>) {
 /// * `ufds` - *mut PollFd
 /// * `nfds` - usize
 /// * `timeout` - *const TimeSecs
-/// * `mask` - usize
+/// * `mask` - *const usize
+/// * `sigsetsize` - usize
 pub fn syscall_ppoll(args: [usize; 6]) -> SyscallResult {
-    let ufds = args[0] as *mut PollFd;
+    let ufds = args[0];
     let nfds = args[1];
-    let timeout = args[2] as *const TimeSecs;
-    let _mask = args[3];
-    let process = current_process();
-
-    let start: VirtAddr = (ufds as usize).into();
-    let end = start + nfds * core::mem::size_of::<PollFd>();
-    if process.manual_alloc_range_for_lazy(start, end).is_err() {
-        return Err(SyscallError::EFAULT);
-    }
-
-    let mut fds: Vec<PollFd> = Vec::new();
-
-    for i in 0..nfds {
-        unsafe {
-            fds.push(*(ufds.add(i)));
-        }
-    }
+    let timeout = args[2];
+    let mask = read_user_sigmask(args[3], args[4])?;
 
-    let expire_time = if timeout as usize != 0 {
-        if process.manual_alloc_type_for_lazy(timeout).is_err() {
-            return Err(SyscallError::EFAULT);
-        }
-        current_ticks() as usize + unsafe { (*timeout).get_ticks() }
-    } else {
-        usize::MAX
-    };
+    let fds = read_poll_fds(ufds, nfds)?;
+    let deadline = read_deadline(timeout)?;
 
-    let (set, ret_fds) = ppoll(fds, expire_time);
+    let (set, ret_fds) = wait_with_sigmask(mask, || ppoll(fds, deadline))?;
     // 将得到的fd存储到原先的指针中
-    for (i, fd) in ret_fds.iter().enumerate() {
-        unsafe {
-            *(ufds.add(i)) = *fd;
-        }
-    }
+    write_poll_fds(ufds, &ret_fds)?;
     Ok(set)
 }
 
@@ -212,74 +245,57 @@ pub fn syscall_ppoll(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * `ufds` - *mut PollFd
 /// * `nfds` - usize
-/// * `timeout_msecs` - usize
+/// * `timeout_msecs` - i32，单位为毫秒，负数表示无限等待
 #[cfg(target_arch = "x86_64")]
 pub fn syscall_poll(args: [usize; 6]) -> SyscallResult {
-    use crate::TimeVal;
+    use axhal::time::Duration;
 
-    let ufds = args[0] as *mut PollFd;
+    let ufds = args[0];
     let nfds = args[1];
-    let timeout_msecs = args[2];
-    let process = current_process();
-
-    let start: VirtAddr = (ufds as usize).into();
-    let end = start + nfds * core::mem::size_of::<PollFd>();
-    if process.manual_alloc_range_for_lazy(start, end).is_err() {
-        return Err(SyscallError::EFAULT);
-    }
+    let timeout_msecs = args[2] as i32;
 
-    let mut fds: Vec<PollFd> = Vec::new();
-
-    for i in 0..nfds {
-        unsafe {
-            fds.push(*(ufds.add(i)));
-        }
-    }
-    let expire_time =
-        current_ticks() as usize + TimeVal::from_micro(timeout_msecs).turn_to_ticks() as usize;
+    let fds = read_poll_fds(ufds, nfds)?;
+    let deadline = if timeout_msecs < 0 {
+        TimeValue::MAX
+    } else {
+        current_time().saturating_add(Duration::from_millis(timeout_msecs as u64))
+    };
 
-    let (set, ret_fds) = ppoll(fds, expire_time);
+    let (set, ret_fds) = ppoll(fds, deadline)?;
     // 将得到的fd存储到原先的指针中
-    for (i, fd) in ret_fds.iter().enumerate() {
-        unsafe {
-            *(ufds.add(i)) = *fd;
-        }
-    }
+    write_poll_fds(ufds, &ret_fds)?;
     Ok(set)
 }
 
 /// 根据给定的地址和长度新建一个fd set,包括文件描述符指针数组,文件描述符数值数组,以及一个bitset
-fn init_fd_set(addr: *mut usize, len: usize) -> Result<PpollFdSet, SyscallError> {
+fn init_fd_set(addr: usize, len: usize) -> Result<PpollFdSet, SyscallError> {
     let process = current_process();
-    if len >= process.fd_manager.get_limit() as usize {
+    if len >= process.fd_manager().get_limit() as usize {
         axlog::error!(
             "[pselect6()] len {len} >= limit {}",
-            process.fd_manager.get_limit()
+            process.fd_manager().get_limit()
         );
         return Err(SyscallError::EINVAL);
     }
 
-    let shadow_bitset = ShadowBitset::new(addr, len);
-    if addr.is_null() {
+    let mut shadow_bitset = ShadowBitset::new(addr, len).map_err(|err| {
+        axlog::error!("[pselect6()] addr {addr:#x} invalid");
+        err
+    })?;
+    if addr == 0 {
         return Ok(PpollFdSet {
             shadow_bitset,
             ..Default::default()
         });
     }
 
-    let start: VirtAddr = (addr as usize).into();
-    let end = start + (len + 7) / 8;
-    if process.manual_alloc_range_for_lazy(start, end).is_err() {
-        axlog::error!("[pselect6()] addr {addr:?} invalid");
-        return Err(SyscallError::EFAULT);
-    }
-
     let mut fds = Vec::new();
     let mut files = Vec::new();
     for fd in 0..len {
         if shadow_bitset.check(fd) {
-            let fd_table = process.fd_manager.fd_table.lock();
-            if let Some(file) = fd_table[fd].as_ref() {
+            let fd_manager = process.fd_manager();
+            let fd_table = fd_manager.fd_table.lock();
+            if let Some(Some(file)) = fd_table.get(fd) {
                 files.push(Arc::clone(file));
                 fds.push(fd);
             } else {
@@ -316,14 +332,20 @@ pub fn syscall_select(mut args: [usize; 6]) -> SyscallResult {
 /// * `writefds` - *mut usize
 /// * `exceptfds` - *mut usize
 /// * `timeout` - *const TimeSecs
-/// * `mask` - usize
+/// * `sigmask` - *const [usize; 2], 依次为信号掩码的地址与长度
 pub fn syscall_pselect6(args: [usize; 6]) -> SyscallResult {
     let nfds = args[0];
-    let readfds = args[1] as *mut usize;
-    let writefds = args[2] as *mut usize;
-    let exceptfds = args[3] as *mut usize;
-    let timeout = args[4] as *const TimeSecs;
-    let _mask = args[5];
+    let readfds = args[1];
+    let writefds = args[2];
+    let exceptfds = args[3];
+    let timeout = args[4];
+    let sigmask = args[5];
+    let mask = if sigmask == 0 {
+        None
+    } else {
+        let [mask, sigsetsize] = copy_struct_from_user::<[usize; 2]>(sigmask)?;
+        read_user_sigmask(mask, sigsetsize)?
+    };
     let (rfiles, rfds, mut rset) = match init_fd_set(readfds, nfds) {
         Ok(ans) => (ans.files, ans.fds, ans.shadow_bitset),
         Err(e) => return Err(e),
@@ -338,70 +360,68 @@ pub fn syscall_pselect6(args: [usize; 6]) -> SyscallResult {
     };
     let process = current_process();
 
-    let expire_time = if !timeout.is_null() {
-        if process
-            .memory_set
-            .lock()
-            .lock()
-            .manual_alloc_type_for_lazy(timeout)
-            .is_err()
-        {
-            axlog::error!("[pselect6()] timeout addr {timeout:?} invalid");
-            return Err(SyscallError::EFAULT);
-        }
-        current_ticks() as usize + unsafe { (*timeout).get_ticks() }
-    } else {
-        usize::MAX
-    };
+    let deadline = read_deadline(timeout).map_err(|err| {
+        axlog::error!("[pselect6()] timeout addr {timeout:#x} invalid");
+        err
+    })?;
 
     axlog::debug!("[pselect6()]: r: {rfds:?}, w: {wfds:?}, e: {efds:?}");
 
-    loop {
-        // Why yield first?
-        //
-        // 当用户程序中出现如下结构：
-        // while (true) { select(); }
-        // 如果存在 ready 的 fd,select() 立即返回,
-        // 但并不完全满足用户程序的要求,可能出现死循环。
-        //
-        // 因此先 yield 避免其他进程 starvation。
-        //
-        // 可见 iperf 测例。
-        yield_now_task();
-
-        let mut set = 0;
-        if rset.valid() {
-            for i in 0..rfds.len() {
-                if rfiles[i].ready_to_read() {
-                    rset.set(rfds[i]);
-                    set += 1;
+    let set = wait_with_sigmask(mask, || {
+        loop {
+            // Why yield first?
+            //
+            // 当用户程序中出现如下结构：
+            // while (true) { select(); }
+            // 如果存在 ready 的 fd,select() 立即返回,
+            // 但并不完全满足用户程序的要求,可能出现死循环。
+            //
+            // 因此先 yield 避免其他进程 starvation。
+            //
+            // 可见 iperf 测例。
+            yield_now_task();
+
+            let mut set = 0;
+            if rset.valid() {
+                for i in 0..rfds.len() {
+                    if rfiles[i].ready_to_read() {
+                        rset.set(rfds[i]);
+                        set += 1;
+                    }
                 }
             }
-        }
-        if wset.valid() {
-            for i in 0..wfds.len() {
-                if wfiles[i].ready_to_write() {
-                    wset.set(wfds[i]);
-                    set += 1;
+            if wset.valid() {
+                for i in 0..wfds.len() {
+                    if wfiles[i].ready_to_write() {
+                        wset.set(wfds[i]);
+                        set += 1;
+                    }
                 }
             }
-        }
-        if eset.valid() {
-            for i in 0..efds.len() {
-                if efiles[i].in_exceptional_conditions() {
-                    eset.set(efds[i]);
-                    set += 1;
+            if eset.valid() {
+                for i in 0..efds.len() {
+                    if efiles[i].in_exceptional_conditions() {
+                        eset.set(efds[i]);
+                        set += 1;
+                    }
                 }
             }
+            if set > 0 {
+                return Ok(set as isize);
+            }
+            if current_time() > deadline {
+                return Ok(0);
+            }
+            if process.have_signals().is_some() {
+                return Err(SyscallError::EINTR);
+            }
         }
-        if set > 0 {
-            return Ok(set as isize);
-        }
-        if current_ticks() as usize > expire_time {
-            return Ok(0);
-        }
-        if process.have_signals().is_some() {
-            return Err(SyscallError::EINTR);
+    })?;
+    // 就绪的 fd 在内核中的副本里标记，返回前写回用户空间
+    for bitset in [&rset, &wset, &eset] {
+        if bitset.valid() {
+            bitset.write_back()?;
         }
     }
+    Ok(set)
 }
@@ -1,51 +1,62 @@
 //! 获取文件系统状态信息
 //!
 
-use crate::{get_fs_stat, FsStat, SyscallError, SyscallResult};
+use crate::{get_fs_stat, SyscallError, SyscallResult};
 use axfs::api::{FileIOType, Kstat};
 use axlog::{debug, error, info};
 use axprocess::{
     current_process,
-    link::{deal_with_path, raw_ptr_to_ref_str, FilePath, AT_FDCWD},
+    link::{resolve_path_at, FilePath, AT_FDCWD},
+    uaccess::{copy_struct_to_user, user_path},
+    Tty,
 };
 
 use crate::syscall_fs::ctype::mount::get_stat_in_fs;
 
 /// 实现 stat 系列系统调用
+///
+/// 标准输入输出同样可以获取状态，见 [`fd_stat`]
 /// # Arguments
 /// * `fd` - usize
 /// * `kst` - *mut Kstat
 pub fn syscall_fstat(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let kst = args[1] as *mut Kstat;
-    let process = current_process();
-    let fd_table = process.fd_manager.fd_table.lock();
+    let kst = args[1];
+    let stat = fd_stat(fd)?;
+    copy_struct_to_user(kst, stat)?;
+    Ok(0)
+}
 
-    if fd >= fd_table.len() || fd < 3 {
-        debug!("fd {} is out of range", fd);
-        return Err(SyscallError::EPERM);
-    }
-    if fd_table[fd].is_none() {
-        debug!("fd {} is none", fd);
-        return Err(SyscallError::EPERM);
-    }
-    let file = fd_table[fd].clone().unwrap();
-    if file.get_type() != FileIOType::FileDesc {
-        debug!("fd {} is not a file", fd);
-        return Err(SyscallError::EPERM);
-    }
+/// fstatat 的 flags：path 为空字符串时获取 dir_fd 本身的状态
+const AT_EMPTY_PATH: usize = 0x1000;
 
-    match file.get_stat() {
-        Ok(stat) => {
-            unsafe {
-                *kst = stat;
-            }
-            Ok(0)
-        }
-        Err(e) => {
+/// 获取 fd 对应的文件的状态
+///
+/// 标准输入输出与 /dev/tty 视为终端字符设备
+fn fd_stat(fd: usize) -> Result<Kstat, SyscallError> {
+    let file = match current_process().fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    if file.as_any().is::<Tty>() {
+        return Ok(Kstat {
+            st_mode: 0o20000 | 0o666,
+            st_ino: 1,
+            st_nlink: 1,
+            ..Kstat::default()
+        });
+    }
+    match file.get_type() {
+        FileIOType::Stdin | FileIOType::Stdout | FileIOType::Stderr => Ok(Kstat {
+            st_mode: 0o20000 | 0o620,
+            st_ino: 1,
+            st_nlink: 1,
+            ..Kstat::default()
+        }),
+        _ => file.get_stat().map_err(|e| {
             debug!("get stat error: {:?}", e);
-            Err(SyscallError::EPERM)
-        }
+            SyscallError::EBADF
+        }),
     }
 }
 
@@ -54,41 +65,38 @@ pub fn syscall_fstat(args: [usize; 6]) -> SyscallResult {
 /// * `dir_fd` - usize
 /// * `path` - *const u8
 /// * `kst` - *mut Kstat
+/// * `flags` - usize, 带有 AT_EMPTY_PATH 且 path 为空时获取 dir_fd 本身的状态
 pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
-    let path = args[1] as *const u8;
-    let kst = args[2] as *mut Kstat;
-    let file_path = if let Some(file_path) = deal_with_path(dir_fd, Some(path), false) {
-        // error!("test {:?}", file_path);
-        file_path
+    let path = user_path(args[1])?;
+    let kst = args[2];
+    let flags = args[3];
+    let process = current_process();
+
+    let stat = if path.is_empty() {
+        if flags & AT_EMPTY_PATH == 0 {
+            return Err(SyscallError::ENOENT);
+        }
+        if dir_fd == AT_FDCWD {
+            let cwd = process.fs_context.resolve(&process.get_cwd());
+            let cwd = FilePath::new(&cwd).map_err(|_| SyscallError::ENOENT)?;
+            get_stat_in_fs(&cwd)?
+        } else {
+            fd_stat(dir_fd)?
+        }
     } else {
-        // x86 下应用会调用 newfstatat(1, "", {st_mode=S_IFCHR|0620, st_rdev=makedev(0x88, 0xe), ...}, AT_EMPTY_PATH) = 0
-        // 去尝试检查 STDOUT 的属性。这里暂时先特判，以后再改成真正的 stdout 的属性
-        let path = unsafe { raw_ptr_to_ref_str(path) };
-        if path.is_empty() && dir_fd == 1 {
-            unsafe {
-                (*kst).st_mode = 0o20000 | 0o220u32;
-                (*kst).st_ino = 1;
-                (*kst).st_nlink = 1;
-            }
-            return Ok(0);
+        let file_path = resolve_path_at(dir_fd, path, false)?;
+        info!("path : {}", file_path.path());
+        if !axfs::api::path_exists(file_path.path()) {
+            return Err(SyscallError::ENOENT);
         }
-        panic!("Wrong path at syscall_fstatat: {}(dir_fd={})", path, dir_fd);
-    };
-    info!("path : {}", file_path.path());
-    if !axfs::api::path_exists(file_path.path()) {
-        return Err(SyscallError::ENOENT);
-    }
-    match get_stat_in_fs(&file_path) {
-        Ok(stat) => unsafe {
-            *kst = stat;
-            Ok(0)
-        },
-        Err(error_no) => {
+        get_stat_in_fs(&file_path).map_err(|error_no| {
             debug!("get stat error: {:?}", error_no);
-            Err(error_no)
-        }
-    }
+            error_no
+        })?
+    };
+    copy_struct_to_user(kst, stat)?;
+    Ok(0)
 }
 
 /// 获取文件状态信息
@@ -120,14 +128,12 @@ pub fn syscall_stat(args: [usize; 6]) -> SyscallResult {
 /// * `path` - *const u8
 /// * `stat` - *mut FsStat
 pub fn syscall_statfs(args: [usize; 6]) -> SyscallResult {
-    let path = args[0] as *const u8;
-    let stat = args[1] as *mut FsStat;
-    let file_path = deal_with_path(AT_FDCWD, Some(path), false).unwrap();
+    let path = user_path(args[0])?;
+    let stat = args[1];
+    let file_path = deal_with_path_str(AT_FDCWD, path, false).ok_or(SyscallError::ENOENT)?;
     if file_path.equal_to(&FilePath::new("/").unwrap()) {
         // 目前只支持访问根目录文件系统的信息
-        unsafe {
-            *stat = get_fs_stat();
-        }
+        copy_struct_to_user(stat, get_fs_stat())?;
 
         Ok(0)
     } else {
@@ -3,7 +3,7 @@ extern crate alloc;
 mod ctype;
 pub mod imp;
 
-use crate::SyscallResult;
+use crate::SyscallHandler;
 use axerrno::AxResult;
 use axfs::api::{File, OpenFlags};
 pub use ctype::FileDesc;
@@ -14,89 +14,100 @@ use imp::*;
 /// 若使用多次new file打开同名文件，那么不同new file之间读写指针不共享，但是修改的内容是共享的
 pub fn new_file(path: &str, flags: &OpenFlags) -> AxResult<File> {
     let mut file = File::options();
+    // 与 Linux 一样，O_TRUNC 与 O_APPEND 只对可写的打开方式生效，O_EXCL 只与 O_CREAT 一起生效
+    let writable = flags.writable();
+    let truncate = writable && flags.is_truncate();
+    let append = writable && flags.is_append();
     file.read(flags.readable());
-    file.write(flags.writable());
+    file.write(writable);
+    file.append(append);
+    // 追加模式下 OpenOptions 不接受截断，因此在打开后单独截断
+    file.truncate(truncate && !append);
     file.create(flags.creatable());
-    file.create_new(flags.new_creatable());
-    file.open(path)
-}
-
-/// 文件系统相关系统调用
-pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> SyscallResult {
-    match syscall_id {
-        EVENT_FD => syscall_eventfd(args),
-        OPENAT => syscall_openat(args),
-        CLOSE => syscall_close(args),
-        READ => syscall_read(args),
-        WRITE => syscall_write(args),
-        GETCWD => syscall_getcwd(args),
-        PIPE2 => syscall_pipe2(args),
-        DUP => syscall_dup(args),
-        DUP3 => syscall_dup3(args),
-        MKDIRAT => syscall_mkdirat(args),
-        CHDIR => syscall_chdir(args),
-        GETDENTS64 => syscall_getdents64(args),
-        MOUNT => syscall_mount(args),
-        UNMOUNT => syscall_umount(args),
-        FSTAT => syscall_fstat(args),
-        RENAMEAT | RENAMEAT2 => syscall_renameat2(args),
-        READV => syscall_readv(args),
-        WRITEV => syscall_writev(args),
-        FCNTL64 => syscall_fcntl64(args),
-        FSTATAT => syscall_fstatat(args),
-        STATFS => syscall_statfs(args),
-        FCHMODAT => syscall_fchmodat(args),
-        FACCESSAT => syscall_faccessat(args),
-        LSEEK => syscall_lseek(args),
-        PREAD64 => syscall_pread64(args),
-        PREADLINKAT => syscall_readlinkat(args),
-        PWRITE64 => syscall_pwrite64(args),
-        SENDFILE64 => syscall_sendfile64(args),
-        FSYNC => Ok(0),
-        FTRUNCATE64 => {
-            syscall_ftruncate64(args)
-            // 0
-        }
-        IOCTL => syscall_ioctl(args),
-        // 不做处理即可
-        SYNC => Ok(0),
-        COPYFILERANGE => syscall_copyfilerange(args),
-        LINKAT => sys_linkat(args),
-        UNLINKAT => syscall_unlinkat(args),
-        UTIMENSAT => syscall_utimensat(args),
-        EPOLL_CREATE => syscall_epoll_create1(args),
-        EPOLL_CTL => syscall_epoll_ctl(args),
-        EPOLL_WAIT => syscall_epoll_wait(args),
-        PPOLL => syscall_ppoll(args),
-        PSELECT6 => syscall_pselect6(args),
-
-        #[cfg(target_arch = "x86_64")]
-        DUP2 => syscall_dup2(args),
-        #[cfg(target_arch = "x86_64")]
-        LSTAT => syscall_lstat(args),
-        #[cfg(target_arch = "x86_64")]
-        OPEN => syscall_open(args),
-        #[cfg(target_arch = "x86_64")]
-        PIPE => syscall_pipe(args),
-        #[cfg(target_arch = "x86_64")]
-        POLL => syscall_poll(args),
-        #[cfg(target_arch = "x86_64")]
-        STAT => syscall_stat(args),
-        #[cfg(target_arch = "x86_64")]
-        UNLINK => syscall_unlink(args),
-        #[cfg(target_arch = "x86_64")]
-        ACCESS => syscall_access(args),
-        #[cfg(target_arch = "x86_64")]
-        MKDIR => syscall_mkdir(args),
-        #[cfg(target_arch = "x86_64")]
-        RENAME => syscall_rename(args),
-        #[cfg(target_arch = "x86_64")]
-        RMDIR => syscall_rmdir(args),
-        #[cfg(target_arch = "x86_64")]
-        SELECT => syscall_select(args),
-        #[cfg(target_arch = "x86_64")]
-        READLINK => syscall_readlink(args),
-        #[cfg(target_arch = "x86_64")]
-        CREAT => Err(axerrno::LinuxError::EPERM),
+    file.create_new(flags.creatable() && flags.new_creatable());
+    let mut file = file.open(path)?;
+    if truncate && append {
+        file.truncate(0)?;
     }
+    Ok(file)
 }
+
+/// 文件系统相关的系统调用及其处理函数
+pub(crate) const FS_SYSCALLS: &[(FsSyscallId, SyscallHandler)] = &[
+    (EVENT_FD, syscall_eventfd),
+    (OPENAT, syscall_openat),
+    (CLOSE, syscall_close),
+    (READ, syscall_read),
+    (WRITE, syscall_write),
+    (GETCWD, syscall_getcwd),
+    (PIPE2, syscall_pipe2),
+    (DUP, syscall_dup),
+    (DUP3, syscall_dup3),
+    (MKDIRAT, syscall_mkdirat),
+    (CHDIR, syscall_chdir),
+    (CHROOT, syscall_chroot),
+    (GETDENTS64, syscall_getdents64),
+    (MOUNT, syscall_mount),
+    (UNMOUNT, syscall_umount),
+    (FSTAT, syscall_fstat),
+    (RENAMEAT, syscall_renameat2),
+    (RENAMEAT2, syscall_renameat2),
+    (READV, syscall_readv),
+    (WRITEV, syscall_writev),
+    (FCNTL64, syscall_fcntl64),
+    (FSTATAT, syscall_fstatat),
+    (STATFS, syscall_statfs),
+    (FCHMODAT, syscall_fchmodat),
+    (FACCESSAT, syscall_faccessat),
+    (FACCESSAT2, syscall_faccessat2),
+    (LSEEK, syscall_lseek),
+    (PREAD64, syscall_pread64),
+    (PREADLINKAT, syscall_readlinkat),
+    (PWRITE64, syscall_pwrite64),
+    (SENDFILE64, syscall_sendfile64),
+    (FTRUNCATE64, syscall_ftruncate64),
+    (IOCTL, syscall_ioctl),
+    (COPYFILERANGE, syscall_copyfilerange),
+    (MEMFD_CREATE, syscall_memfd_create),
+    (LINKAT, sys_linkat),
+    (UNLINKAT, syscall_unlinkat),
+    (SYMLINKAT, syscall_symlinkat),
+    (UTIMENSAT, syscall_utimensat),
+    (EPOLL_CREATE, syscall_epoll_create1),
+    (EPOLL_CTL, syscall_epoll_ctl),
+    (EPOLL_PWAIT, syscall_epoll_pwait),
+    (PPOLL, syscall_ppoll),
+    (PSELECT6, syscall_pselect6),
+    #[cfg(target_arch = "x86_64")]
+    (EPOLL_WAIT, syscall_epoll_wait),
+    #[cfg(target_arch = "x86_64")]
+    (DUP2, syscall_dup2),
+    #[cfg(target_arch = "x86_64")]
+    (LSTAT, syscall_lstat),
+    #[cfg(target_arch = "x86_64")]
+    (OPEN, syscall_open),
+    #[cfg(target_arch = "x86_64")]
+    (PIPE, syscall_pipe),
+    #[cfg(target_arch = "x86_64")]
+    (POLL, syscall_poll),
+    #[cfg(target_arch = "x86_64")]
+    (STAT, syscall_stat),
+    #[cfg(target_arch = "x86_64")]
+    (UNLINK, syscall_unlink),
+    #[cfg(target_arch = "x86_64")]
+    (SYMLINK, syscall_symlink),
+    #[cfg(target_arch = "x86_64")]
+    (ACCESS, syscall_access),
+    #[cfg(target_arch = "x86_64")]
+    (MKDIR, syscall_mkdir),
+    #[cfg(target_arch = "x86_64")]
+    (RENAME, syscall_rename),
+    #[cfg(target_arch = "x86_64")]
+    (RMDIR, syscall_rmdir),
+    #[cfg(target_arch = "x86_64")]
+    (SELECT, syscall_select),
+    #[cfg(target_arch = "x86_64")]
+    (READLINK, syscall_readlink),
+    #[cfg(target_arch = "x86_64")]
+    (CREAT, |_| Err(axerrno::LinuxError::EPERM)),
+];
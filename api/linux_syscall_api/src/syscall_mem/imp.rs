@@ -1,30 +1,57 @@
 use crate::{syscall_fs::FileDesc, MMAPFlags, SyscallError, SyscallResult, MMAPPROT};
 extern crate alloc;
 
-use axhal::{arch::flush_tlb, mem::VirtAddr, paging::MappingFlags};
-use axmem::MemorySet;
-
-use axprocess::current_process;
+use axconfig::{MAX_USER_HEAP_SIZE, TASK_SIZE};
+use axerrno::AxError;
+use axfs::axfs_devfs::ZeroDev;
+use axhal::{
+    arch::flush_tlb,
+    mem::{VirtAddr, PAGE_SIZE_4K},
+    paging::MappingFlags,
+};
+use axmem::{MemBackend, MemorySet};
+
+use axprocess::{current_process, oom::retry_on_oom, uaccess::clear_user, Process};
 use bitflags::bitflags;
 
-const MAX_HEAP_SIZE: usize = 0x20000;
+/// fd 是否指向 /dev/zero，映射 /dev/zero 等价于匿名映射
+///
+/// 根据打开文件背后的设备节点判断，而不是比较路径，/dev/zero 的链接等也能被识别
+fn is_dev_zero(process: &Process, fd: i32) -> bool {
+    if fd < 0 {
+        return false;
+    }
+    match process.fd_manager().fd_table.lock().get(fd as usize) {
+        Some(Some(file)) => file
+            .as_any()
+            .downcast_ref::<FileDesc>()
+            .is_some_and(|file_desc| file_desc.file.lock().node_is::<ZeroDev>()),
+        _ => false,
+    }
+}
 /// 修改用户堆大小，
 ///
 /// - 如输入 brk 为 0 ，则返回堆顶地址
 /// - 重新设置堆顶地址，如成功则返回设置后的堆顶地址，否则保持不变，并返回之前的堆顶地址。
+///   堆顶可以增长、收缩或保持不变，与当前堆顶相同的请求直接返回当前堆顶
+/// - 收缩时清零释放的部分，之后再次增长得到的内存与新分配的一样全为 0，
+///   glibc 的 calloc 依赖这一点
 ///
 /// # Arguments
 /// * `brk` - usize
 pub fn syscall_brk(args: [usize; 6]) -> SyscallResult {
     let brk = args[0];
     let curr_process = current_process();
-    let mut return_val: isize = curr_process.get_heap_top() as isize;
+    let heap_top = curr_process.get_heap_top() as usize;
     let heap_bottom = curr_process.get_heap_bottom() as usize;
-    if brk != 0 && brk >= heap_bottom && brk <= heap_bottom + MAX_HEAP_SIZE {
-        curr_process.set_heap_top(brk as u64);
-        return_val = brk as isize;
+    if brk == 0 || brk == heap_top || brk < heap_bottom || brk > heap_bottom + MAX_USER_HEAP_SIZE {
+        return Ok(heap_top as isize);
     }
-    Ok(return_val)
+    if brk < heap_top && clear_user(brk, heap_top - brk).is_err() {
+        return Ok(heap_top as isize);
+    }
+    curr_process.set_heap_top(brk as u64);
+    Ok(brk as isize)
 }
 
 /// 将文件内容映射到内存中
@@ -47,68 +74,148 @@ pub fn syscall_mmap(args: [usize; 6]) -> SyscallResult {
     let fd = args[4] as i32;
     let offset = args[5];
     use axlog::debug;
-    use axmem::MemBackend;
 
     let fixed = flags.contains(MMAPFlags::MAP_FIXED);
     // try to map to NULL
     if fixed && start == 0 {
         return Err(SyscallError::EINVAL);
     }
+    // 长度为 0、向上对齐到页后溢出，或超出用户地址空间的映射均不合法
+    let len = match len.checked_add(PAGE_SIZE_4K - 1) {
+        Some(len) if len >= PAGE_SIZE_4K => len & !(PAGE_SIZE_4K - 1),
+        _ => return Err(SyscallError::EINVAL),
+    };
+    match start.checked_add(len) {
+        Some(end) if len <= TASK_SIZE && (!fixed || end <= TASK_SIZE) => {}
+        _ => return Err(SyscallError::EINVAL),
+    }
+    // 文件偏移必须按页对齐，映射的第一页对应文件中 offset 处的一页
+    if offset % PAGE_SIZE_4K != 0 {
+        return Err(SyscallError::EINVAL);
+    }
 
     let process = current_process();
 
-    let addr = if flags.contains(MMAPFlags::MAP_ANONYMOUS) {
+    // 不可访问的映射只用于匿名的保护区域
+    if prot.is_empty() && !flags.contains(MMAPFlags::MAP_ANONYMOUS) {
+        return Err(SyscallError::EINVAL);
+    }
+
+    // 映射 /dev/zero 得到的是零填充的匿名内存，而不是从设备中读取内容
+    let dev_zero = !flags.contains(MMAPFlags::MAP_ANONYMOUS) && is_dev_zero(&process, fd);
+    let addr = if flags.contains(MMAPFlags::MAP_ANONYMOUS) || dev_zero {
         // no file
-        if !(fd == -1 && offset == 0) {
+        if !dev_zero && !(fd == -1 && offset == 0) {
             return Err(SyscallError::EINVAL);
         }
-        process
-            .memory_set
-            .lock()
-            .lock()
-            .mmap(start.into(), len, prot.into(), fixed, None)
+        if flags.contains(MMAPFlags::MAP_SHARED) {
+            // 共享匿名映射在 fork 后由父子进程共享，而不是写时复制
+            let prot: MappingFlags = prot.into();
+            let addr = retry_on_oom("mmap", || {
+                process.memory_set.lock().lock().mmap_shared_anonymous(
+                    start.into(),
+                    len,
+                    prot,
+                    fixed,
+                )
+            })
+            .map_err(SyscallError::from)?;
+            flush_tlb(None);
+            return Ok(addr.as_usize() as isize);
+        }
+        mmap_retry_on_oom(&process, start, len, prot.into(), fixed, None)
     } else {
         // file backend
         debug!("[mmap] fd: {}, offset: 0x{:x}", fd, offset);
-        if fd >= process.fd_manager.fd_table.lock().len() as i32 || fd < 0 {
+        if fd >= process.fd_manager().fd_table.lock().len() as i32 || fd < 0 {
             return Err(SyscallError::EINVAL);
         }
-        let file = match &process.fd_manager.fd_table.lock()[fd as usize] {
+        let (file, path) = match &process.fd_manager().fd_table.lock()[fd as usize] {
             // 文件描述符表里面存的是文件描述符，这很合理罢
-            Some(file) => alloc::boxed::Box::new(
-                file.as_any()
-                    .downcast_ref::<FileDesc>()
-                    .expect("Try to mmap with a non-file backend")
-                    .file
-                    .lock()
-                    .clone(),
-            ),
+            Some(file) => {
+                // 管道、匿名 inode 等不支持映射
+                let file_desc = match file.as_any().downcast_ref::<FileDesc>() {
+                    Some(file_desc) => file_desc,
+                    None => return Err(SyscallError::ENODEV),
+                };
+                (
+                    alloc::boxed::Box::new(file_desc.file.lock().clone()),
+                    file_desc.path.clone(),
+                )
+            }
             // fd not found
             None => return Err(SyscallError::EINVAL),
         };
 
-        let backend = MemBackend::new(file, offset as u64);
-        process
-            .memory_set
-            .lock()
-            .lock()
-            .mmap(start.into(), len, prot.into(), fixed, Some(backend))
+        let mut backend = MemBackend::new(file, offset as u64);
+        if flags.contains(MMAPFlags::MAP_PRIVATE) {
+            // 私有映射的干净页面可以在进程间共享，写入时再复制
+            backend = backend.with_path(path);
+        }
+        mmap_retry_on_oom(&process, start, len, prot.into(), fixed, Some(backend))
     };
 
     flush_tlb(None);
     debug!("mmap: 0x{:x}", addr);
     // info!("val: {}", unsafe { *(addr as *const usize) });
+    if addr < 0 {
+        // 找不到足够大的空闲区域
+        return Err(SyscallError::ENOMEM);
+    }
     Ok(addr)
 }
 
+/// 建立私有映射或文件映射，页表页分配失败时回收内存后重试
+///
+/// 返回映射的起始地址，失败时返回 -1
+fn mmap_retry_on_oom(
+    process: &Process,
+    start: usize,
+    len: usize,
+    flags: MappingFlags,
+    fixed: bool,
+    backend: Option<MemBackend>,
+) -> isize {
+    retry_on_oom("mmap", || {
+        let addr =
+            process
+                .memory_set
+                .lock()
+                .lock()
+                .mmap(start.into(), len, flags, fixed, backend.clone());
+        if addr < 0 {
+            Err(AxError::NoMemory)
+        } else {
+            Ok(addr)
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// 解除 [start, start + len) 中的映射，部分覆盖的映射区域会被拆分，共享文件映射会先写回文件
+///
+/// start 未按页对齐、len 为 0 或范围溢出时返回 EINVAL
 /// # Arguments
 /// * `start` - usize
 /// * `len` - usize
 pub fn syscall_munmap(args: [usize; 6]) -> SyscallResult {
     let start = args[0];
     let len = args[1];
-    let process = current_process();
-    process.memory_set.lock().lock().munmap(start.into(), len);
+    if start % PAGE_SIZE_4K != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let len = match len.checked_add(PAGE_SIZE_4K - 1) {
+        Some(len) if len >= PAGE_SIZE_4K => len & !(PAGE_SIZE_4K - 1),
+        _ => return Err(SyscallError::EINVAL),
+    };
+    if start.checked_add(len).is_none() {
+        return Err(SyscallError::EINVAL);
+    }
+    current_process()
+        .memory_set
+        .lock()
+        .lock()
+        .munmap(start.into(), len);
     flush_tlb(None);
     Ok(0)
 }
@@ -125,6 +232,11 @@ pub fn syscall_msync(args: [usize; 6]) -> SyscallResult {
     Ok(0)
 }
 
+/// 修改 [start, start + len) 中页面的访问权限，部分覆盖的映射区域会被拆分
+///
+/// - start 未按页对齐或 prot 含有未知的位时返回 EINVAL
+/// - 范围溢出、含有未映射的部分，或拆分后映射区域过多时返回 ENOMEM
+/// - 为以只读方式打开的共享文件映射加上写权限时返回 EACCES
 /// # Arguments
 /// * `start` - usize
 /// * `len` - usize
@@ -132,15 +244,86 @@ pub fn syscall_msync(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_mprotect(args: [usize; 6]) -> SyscallResult {
     let start = args[0];
     let len = args[1];
-    let prot = MMAPPROT::from_bits_truncate(args[2] as u32);
+    let prot = MMAPPROT::from_bits(args[2] as u32).ok_or(SyscallError::EINVAL)?;
+    if start % PAGE_SIZE_4K != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let len = match len.checked_add(PAGE_SIZE_4K - 1) {
+        Some(len) => len & !(PAGE_SIZE_4K - 1),
+        None => return Err(SyscallError::ENOMEM),
+    };
+    if start.checked_add(len).is_none() {
+        return Err(SyscallError::ENOMEM);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
     let process = current_process();
 
     process
         .memory_set
         .lock()
         .lock()
-        .mprotect(VirtAddr::from(start), len, prot.into());
+        .mprotect(VirtAddr::from(start), len, prot.into())
+        .map_err(|err| match err {
+            AxError::PermissionDenied => SyscallError::EACCES,
+            _ => SyscallError::ENOMEM,
+        })?;
+
+    flush_tlb(None);
+    Ok(0)
+}
 
+const MADV_DONTNEED: usize = 4;
+const MADV_FREE: usize = 8;
+const MADV_HUGEPAGE: usize = 14;
+const MADV_NOHUGEPAGE: usize = 15;
+const MADV_COLLAPSE: usize = 25;
+/// Linux 目前定义的最大的 advice（MADV_GUARD_REMOVE）
+const MADV_MAX: usize = 103;
+
+/// # Arguments
+/// * `start` - usize
+/// * `len` - usize
+/// * `advice` - usize
+///
+/// 实现了 MADV_DONTNEED、MADV_FREE、控制透明大页的 MADV_HUGEPAGE 与 MADV_NOHUGEPAGE，
+/// 以及立即合并大页的 MADV_COLLAPSE，其余的 advice 只是提示，直接忽略
+pub fn syscall_madvise(args: [usize; 6]) -> SyscallResult {
+    let start = args[0];
+    let len = args[1];
+    let advice = args[2];
+    if start % PAGE_SIZE_4K != 0 || advice > MADV_MAX {
+        return Err(SyscallError::EINVAL);
+    }
+    let len = (len + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K * PAGE_SIZE_4K;
+    if start.checked_add(len).is_none() {
+        return Err(SyscallError::EINVAL);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let process = current_process();
+    let memory_set = process.memory_set.lock();
+    match advice {
+        MADV_DONTNEED => memory_set.lock().discard_pages(start.into(), len),
+        MADV_FREE => memory_set
+            .lock()
+            .lazy_free_pages(start.into(), len)
+            .map_err(|_| SyscallError::EINVAL)?,
+        MADV_HUGEPAGE => memory_set.lock().set_hugepage(start.into(), len, true),
+        MADV_NOHUGEPAGE => memory_set.lock().set_hugepage(start.into(), len, false),
+        MADV_COLLAPSE => {
+            memory_set
+                .lock()
+                .collapse(start.into(), len)
+                .map_err(|err| match err {
+                    AxError::NoMemory => SyscallError::ENOMEM,
+                    _ => SyscallError::EINVAL,
+                })?
+        }
+        _ => return Ok(0),
+    }
     flush_tlb(None);
     Ok(0)
 }
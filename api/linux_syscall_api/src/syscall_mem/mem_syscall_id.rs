@@ -13,6 +13,10 @@ numeric_enum_macro::numeric_enum! {
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum MemSyscallId {
     // mem
+    MSGGET = 186,
+    MSGCTL = 187,
+    MSGRCV = 188,
+    MSGSND = 189,
     SHMGET = 194,
     SHMCTL = 195,
     SHMAT = 196,
@@ -21,6 +25,7 @@ pub enum MemSyscallId {
     MMAP = 222,
     MSYNC = 227,
     MPROTECT = 226,
+    MADVISE = 233,
     MEMBARRIER = 283,
 }
 }
@@ -36,11 +41,16 @@ numeric_enum_macro::numeric_enum! {
         SHMGET = 29,
         SHMCTL = 31,
         SHMAT = 30,
+        MSGGET = 68,
+        MSGSND = 69,
+        MSGRCV = 70,
+        MSGCTL = 71,
         BRK = 12,
         MUNMAP = 11,
         MMAP = 9,
         MSYNC = 26,
         MPROTECT = 10,
+        MADVISE = 28,
         MEMBARRIER = 324,
     }
 }
@@ -1,28 +1,27 @@
 //! 与内存相关的系统调用
 
-use crate::SyscallResult;
+use crate::SyscallHandler;
 
 mod imp;
+mod msg;
 
 mod mem_syscall_id;
 pub use mem_syscall_id::MemSyscallId::{self, *};
 
 use imp::*;
-/// 与内存相关的系统调用
-pub fn mem_syscall(syscall_id: mem_syscall_id::MemSyscallId, args: [usize; 6]) -> SyscallResult {
-    match syscall_id {
-        BRK => syscall_brk(args),
-        MUNMAP => syscall_munmap(args),
-        MMAP => syscall_mmap(args),
-        MSYNC => syscall_msync(args),
-        MPROTECT => syscall_mprotect(args),
-        MEMBARRIER => Ok(0),
-        SHMGET => syscall_shmget(args),
-        SHMCTL => Ok(0),
-        SHMAT => syscall_shmat(args),
-        #[allow(unused)]
-        _ => {
-            panic!("Invalid Syscall Id: {:?}!", syscall_id);
-        }
-    }
-}
+use msg::*;
+/// 与内存相关的系统调用及其处理函数
+pub(crate) const MEM_SYSCALLS: &[(MemSyscallId, SyscallHandler)] = &[
+    (BRK, syscall_brk),
+    (MUNMAP, syscall_munmap),
+    (MMAP, syscall_mmap),
+    (MSYNC, syscall_msync),
+    (MPROTECT, syscall_mprotect),
+    (MADVISE, syscall_madvise),
+    (SHMGET, syscall_shmget),
+    (SHMAT, syscall_shmat),
+    (MSGGET, syscall_msgget),
+    (MSGCTL, syscall_msgctl),
+    (MSGRCV, syscall_msgrcv),
+    (MSGSND, syscall_msgsnd),
+];
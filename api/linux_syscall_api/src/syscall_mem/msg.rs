@@ -0,0 +1,453 @@
+//! System V 消息队列
+//!
+//! 消息队列以 key 为索引全局共享，每个队列中消息的总字节数不超过 `msg_qbytes`。
+//! 发送方在队列已满、接收方在没有符合条件的消息时阻塞，可被信号打断。
+extern crate alloc;
+use alloc::{collections::BTreeMap, collections::VecDeque, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axhal::time::current_time;
+use axmem::IpcPerm;
+use axprocess::{
+    current_process, current_task,
+    uaccess::{
+        check_user_writable, copy_from_user, copy_struct_from_user, copy_struct_to_user,
+        copy_to_user,
+    },
+};
+use axtask::WaitQueue;
+use spinlock::SpinNoIrq;
+
+use crate::{SyscallError, SyscallResult};
+
+/// 单条消息的最大长度
+const MSGMAX: usize = 8192;
+/// 单个队列默认的最大总字节数
+const MSGMNB: usize = 16384;
+/// 系统中消息队列的最大数量
+const MSGMNI: usize = 32000;
+
+const IPC_PRIVATE: i32 = 0;
+const IPC_CREAT: i32 = 0o1000;
+const IPC_EXCL: i32 = 0o2000;
+const IPC_NOWAIT: i32 = 0o4000;
+
+const IPC_RMID: usize = 0;
+const IPC_SET: usize = 1;
+const IPC_STAT: usize = 2;
+/// 部分 libc 会在 cmd 中带上该标志，表示使用 64 位的结构体
+const IPC_64: usize = 0x100;
+
+const MSG_NOERROR: i32 = 0o10000;
+const MSG_EXCEPT: i32 = 0o20000;
+
+/// 读权限
+const IPC_READ: u32 = 0o4;
+/// 写权限
+const IPC_WRITE: u32 = 0o2;
+
+/// 当前用户的 uid 与 gid，在实现多用户权限前与 getuid 一致默认为超级用户
+fn current_cred() -> (u32, u32) {
+    (0, 0)
+}
+
+/// 消息队列的状态，对应 Linux 的 `struct msqid64_ds`
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Clone, Copy, Default)]
+pub struct MsqidDs {
+    msg_perm: IpcPerm,
+    msg_stime: i64,
+    msg_rtime: i64,
+    msg_ctime: i64,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: i32,
+    msg_lrpid: i32,
+    __unused4: u64,
+    __unused5: u64,
+}
+
+struct Message {
+    mtype: isize,
+    data: Vec<u8>,
+}
+
+struct MsgQueueInner {
+    ds: MsqidDs,
+    messages: VecDeque<Message>,
+    /// 队列已被 IPC_RMID 删除，等待者应返回 EIDRM
+    removed: bool,
+}
+
+impl MsgQueueInner {
+    /// 按照 msgrcv 的 msgtyp 语义查找消息的下标
+    ///
+    /// - 0：队列中的第一条消息
+    /// - 正数：第一条类型为 msgtyp 的消息，若有 MSG_EXCEPT 则为第一条类型不为 msgtyp 的消息
+    /// - 负数：类型不超过 |msgtyp| 的消息中类型最小的第一条
+    fn find(&self, msgtyp: isize, except: bool) -> Option<usize> {
+        if msgtyp == 0 {
+            return if self.messages.is_empty() {
+                None
+            } else {
+                Some(0)
+            };
+        }
+        if msgtyp > 0 {
+            return self
+                .messages
+                .iter()
+                .position(|msg| (msg.mtype == msgtyp) != except);
+        }
+        let limit = msgtyp.unsigned_abs() as isize;
+        let mut found: Option<usize> = None;
+        for (idx, msg) in self.messages.iter().enumerate() {
+            if msg.mtype <= limit
+                && found.map_or(true, |best| msg.mtype < self.messages[best].mtype)
+            {
+                found = Some(idx);
+            }
+        }
+        found
+    }
+}
+
+/// 一个消息队列
+pub struct MsgQueue {
+    inner: SpinNoIrq<MsgQueueInner>,
+    /// 每次队列内容或状态变化时递增，等待者据此判断是否需要重新检查
+    generation: AtomicUsize,
+    wait: WaitQueue,
+}
+
+impl MsgQueue {
+    fn new(key: i32, mode: u32) -> Self {
+        let (uid, gid) = current_cred();
+        let ds = MsqidDs {
+            msg_perm: IpcPerm::new(key, uid, gid, mode),
+            msg_ctime: current_time().as_secs() as i64,
+            msg_qbytes: MSGMNB as u64,
+            ..Default::default()
+        };
+        Self {
+            inner: SpinNoIrq::new(MsgQueueInner {
+                ds,
+                messages: VecDeque::new(),
+                removed: false,
+            }),
+            generation: AtomicUsize::new(0),
+            wait: WaitQueue::new(),
+        }
+    }
+
+    /// 队列发生变化，唤醒所有等待者重新检查
+    fn notify(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.wait.notify_all(true);
+    }
+
+    /// 等待队列在 `generation` 之后发生变化，期间收到信号则返回 EINTR
+    fn wait_change(&self, generation: usize) -> Result<(), SyscallError> {
+        let process = current_process();
+        if process.have_signals().is_some() {
+            return Err(SyscallError::EINTR);
+        }
+        let curr = current_task();
+        self.wait.wait_until(|| {
+            self.generation.load(Ordering::Acquire) != generation || curr.take_woken_by_signal()
+        });
+        if process.have_signals().is_some() {
+            return Err(SyscallError::EINTR);
+        }
+        Ok(())
+    }
+}
+
+struct MsgQueues {
+    queues: BTreeMap<i32, Arc<MsgQueue>>,
+    keys: BTreeMap<i32, i32>,
+    next_id: i32,
+}
+
+static MSG_QUEUES: SpinNoIrq<MsgQueues> = SpinNoIrq::new(MsgQueues {
+    queues: BTreeMap::new(),
+    keys: BTreeMap::new(),
+    next_id: 0,
+});
+
+fn get_queue(msqid: i32) -> Result<Arc<MsgQueue>, SyscallError> {
+    if msqid < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    MSG_QUEUES
+        .lock()
+        .queues
+        .get(&msqid)
+        .cloned()
+        .ok_or(SyscallError::EINVAL)
+}
+
+fn check_perm(ds: &MsqidDs, access: u32) -> Result<(), SyscallError> {
+    let (uid, gid) = current_cred();
+    if ds.msg_perm.permits(uid, gid, access) {
+        Ok(())
+    } else {
+        Err(SyscallError::EACCES)
+    }
+}
+
+/// 获取或创建消息队列
+/// # Arguments
+/// * `key` - i32
+/// * `msgflg` - i32
+pub fn syscall_msgget(args: [usize; 6]) -> SyscallResult {
+    let key = args[0] as i32;
+    let msgflg = args[1] as i32;
+    let mode = (msgflg & 0o777) as u32;
+
+    let mut msg_queues = MSG_QUEUES.lock();
+    if key != IPC_PRIVATE {
+        if let Some(&msqid) = msg_queues.keys.get(&key) {
+            if msgflg & IPC_CREAT != 0 && msgflg & IPC_EXCL != 0 {
+                return Err(SyscallError::EEXIST);
+            }
+            // msgflg 中要求的权限位
+            let access = (mode >> 6) | (mode >> 3) | mode;
+            check_perm(&msg_queues.queues[&msqid].inner.lock().ds, access)?;
+            return Ok(msqid as isize);
+        }
+        if msgflg & IPC_CREAT == 0 {
+            return Err(SyscallError::ENOENT);
+        }
+    }
+    if msg_queues.queues.len() >= MSGMNI {
+        return Err(SyscallError::ENOSPC);
+    }
+    let mut msqid = msg_queues.next_id;
+    while msg_queues.queues.contains_key(&msqid) {
+        msqid = msqid.wrapping_add(1) & i32::MAX;
+    }
+    msg_queues.next_id = msqid.wrapping_add(1) & i32::MAX;
+    msg_queues
+        .queues
+        .insert(msqid, Arc::new(MsgQueue::new(key, mode)));
+    if key != IPC_PRIVATE {
+        msg_queues.keys.insert(key, msqid);
+    }
+    Ok(msqid as isize)
+}
+
+/// 向消息队列发送消息，队列已满时阻塞
+/// # Arguments
+/// * `msqid` - i32
+/// * `msgp` - *const msgbuf，开头为 long 类型的 mtype，之后为消息正文
+/// * `msgsz` - usize，消息正文的长度
+/// * `msgflg` - i32
+pub fn syscall_msgsnd(args: [usize; 6]) -> SyscallResult {
+    let msqid = args[0] as i32;
+    let msgp = args[1];
+    let msgsz = args[2];
+    let msgflg = args[3] as i32;
+    if msgsz > MSGMAX {
+        return Err(SyscallError::EINVAL);
+    }
+    let queue = get_queue(msqid)?;
+
+    let process = current_process();
+    let mtype: isize = copy_struct_from_user(msgp)?;
+    if mtype <= 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let mut data = vec![0u8; msgsz];
+    copy_from_user(&mut data, msgp + core::mem::size_of::<isize>())?;
+
+    loop {
+        let generation = {
+            let mut inner = queue.inner.lock();
+            if inner.removed {
+                return Err(SyscallError::EIDRM);
+            }
+            check_perm(&inner.ds, IPC_WRITE)?;
+            if inner.ds.msg_cbytes as usize + msgsz <= inner.ds.msg_qbytes as usize {
+                inner.ds.msg_cbytes += msgsz as u64;
+                inner.ds.msg_qnum += 1;
+                inner.ds.msg_lspid = process.pid() as i32;
+                inner.ds.msg_stime = current_time().as_secs() as i64;
+                inner.messages.push_back(Message { mtype, data });
+                drop(inner);
+                queue.notify();
+                return Ok(0);
+            }
+            if msgflg & IPC_NOWAIT != 0 {
+                return Err(SyscallError::EAGAIN);
+            }
+            queue.generation.load(Ordering::Acquire)
+        };
+        queue.wait_change(generation)?;
+    }
+}
+
+/// 从消息队列中接收消息，没有符合条件的消息时阻塞
+/// # Arguments
+/// * `msqid` - i32
+/// * `msgp` - *mut msgbuf
+/// * `msgsz` - usize，用户缓冲区中正文部分的长度
+/// * `msgtyp` - isize
+/// * `msgflg` - i32
+pub fn syscall_msgrcv(args: [usize; 6]) -> SyscallResult {
+    let msqid = args[0] as i32;
+    let msgp = args[1];
+    let msgsz = args[2] as isize;
+    let msgtyp = args[3] as isize;
+    let msgflg = args[4] as i32;
+    if msgsz < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let msgsz = msgsz as usize;
+    let queue = get_queue(msqid)?;
+
+    let process = current_process();
+    // 先检查缓冲区，避免取出消息之后才发现无法写入
+    check_user_writable(msgp, core::mem::size_of::<isize>() + msgsz)?;
+
+    let msg = loop {
+        let generation = {
+            let mut inner = queue.inner.lock();
+            if inner.removed {
+                return Err(SyscallError::EIDRM);
+            }
+            check_perm(&inner.ds, IPC_READ)?;
+            if let Some(idx) = inner.find(msgtyp, msgflg & MSG_EXCEPT != 0) {
+                if inner.messages[idx].data.len() > msgsz && msgflg & MSG_NOERROR == 0 {
+                    // 消息过长且不允许截断，消息保留在队列中
+                    return Err(SyscallError::E2BIG);
+                }
+                let msg = inner.messages.remove(idx).unwrap();
+                inner.ds.msg_cbytes -= msg.data.len() as u64;
+                inner.ds.msg_qnum -= 1;
+                inner.ds.msg_lrpid = process.pid() as i32;
+                inner.ds.msg_rtime = current_time().as_secs() as i64;
+                drop(inner);
+                queue.notify();
+                break msg;
+            }
+            if msgflg & IPC_NOWAIT != 0 {
+                return Err(SyscallError::ENOMSG);
+            }
+            queue.generation.load(Ordering::Acquire)
+        };
+        queue.wait_change(generation)?;
+    };
+
+    // 设置了 MSG_NOERROR 时超出部分被丢弃
+    let len = msg.data.len().min(msgsz);
+    copy_struct_to_user(msgp, msg.mtype)?;
+    copy_to_user(msgp + core::mem::size_of::<isize>(), &msg.data[..len])?;
+    Ok(len as isize)
+}
+
+/// 控制消息队列
+/// # Arguments
+/// * `msqid` - i32
+/// * `cmd` - usize，支持 IPC_STAT、IPC_SET 与 IPC_RMID
+/// * `buf` - *mut MsqidDs
+pub fn syscall_msgctl(args: [usize; 6]) -> SyscallResult {
+    let msqid = args[0] as i32;
+    let cmd = args[1] & !IPC_64;
+    let buf = args[2];
+
+    match cmd {
+        IPC_STAT => {
+            let queue = get_queue(msqid)?;
+            let inner = queue.inner.lock();
+            check_perm(&inner.ds, IPC_READ)?;
+            let ds = inner.ds;
+            drop(inner);
+            copy_struct_to_user(buf, ds)?;
+            Ok(0)
+        }
+        IPC_SET => {
+            let queue = get_queue(msqid)?;
+            let new_ds: MsqidDs = copy_struct_from_user(buf)?;
+            let mut inner = queue.inner.lock();
+            let (uid, _) = current_cred();
+            if uid != 0 && uid != inner.ds.msg_perm.uid && uid != inner.ds.msg_perm.cuid {
+                return Err(SyscallError::EPERM);
+            }
+            if new_ds.msg_qbytes as usize > MSGMNB && uid != 0 {
+                return Err(SyscallError::EPERM);
+            }
+            inner.ds.msg_perm.uid = new_ds.msg_perm.uid;
+            inner.ds.msg_perm.gid = new_ds.msg_perm.gid;
+            inner.ds.msg_perm.mode = new_ds.msg_perm.mode & 0o777;
+            inner.ds.msg_qbytes = new_ds.msg_qbytes;
+            inner.ds.msg_ctime = current_time().as_secs() as i64;
+            drop(inner);
+            // 队列容量可能变大，唤醒等待的发送方
+            queue.notify();
+            Ok(0)
+        }
+        IPC_RMID => {
+            let mut msg_queues = MSG_QUEUES.lock();
+            let queue = msg_queues
+                .queues
+                .get(&msqid)
+                .cloned()
+                .ok_or(SyscallError::EINVAL)?;
+            let mut inner = queue.inner.lock();
+            let (uid, _) = current_cred();
+            if uid != 0 && uid != inner.ds.msg_perm.uid && uid != inner.ds.msg_perm.cuid {
+                return Err(SyscallError::EPERM);
+            }
+            inner.removed = true;
+            inner.messages.clear();
+            let key = inner.ds.msg_perm.key;
+            drop(inner);
+            msg_queues.queues.remove(&msqid);
+            if key != IPC_PRIVATE {
+                msg_queues.keys.remove(&key);
+            }
+            drop(msg_queues);
+            queue.notify();
+            Ok(0)
+        }
+        _ => Err(SyscallError::EINVAL),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Message, MsgQueueInner, MsqidDs};
+    use alloc::{collections::VecDeque, vec::Vec};
+
+    fn queue_of(types: &[isize]) -> MsgQueueInner {
+        MsgQueueInner {
+            ds: MsqidDs::default(),
+            messages: types
+                .iter()
+                .map(|&mtype| Message {
+                    mtype,
+                    data: Vec::new(),
+                })
+                .collect::<VecDeque<_>>(),
+            removed: false,
+        }
+    }
+
+    #[test]
+    fn test_find_by_type() {
+        let queue = queue_of(&[3, 1, 2, 1]);
+        assert_eq!(queue.find(0, false), Some(0));
+        assert_eq!(queue.find(1, false), Some(1));
+        assert_eq!(queue.find(4, false), None);
+        assert_eq!(queue.find(3, true), Some(1));
+        // 类型不超过 2 的消息中类型最小的第一条
+        assert_eq!(queue.find(-2, false), Some(1));
+        assert_eq!(queue.find(-3, false), Some(1));
+        assert_eq!(queue_of(&[3, 2]).find(-2, false), Some(1));
+        assert_eq!(queue_of(&[3]).find(-2, false), None);
+        assert_eq!(queue_of(&[]).find(0, false), None);
+    }
+}
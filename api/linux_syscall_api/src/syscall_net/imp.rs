@@ -1,15 +1,17 @@
 //! 相关系统调用的具体实现
 extern crate alloc;
 use super::socket::*;
-use core::slice::{from_raw_parts, from_raw_parts_mut};
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec};
 
 use crate::{SyscallError, SyscallResult};
 use axerrno::AxError;
 use axlog::{debug, error, info, warn};
 use axnet::{into_core_sockaddr, IpAddr, SocketAddr};
-use axprocess::current_process;
+use axprocess::{
+    current_process,
+    uaccess::{copy_from_user, copy_struct_from_user, copy_to_user},
+};
 use num_enum::TryFromPrimitive;
 
 pub const SOCKET_TYPE_MASK: usize = 0xFF;
@@ -39,7 +41,8 @@ pub fn syscall_socket(args: [usize; 6]) -> SyscallResult {
         socket.close_exec = true;
     }
     let curr = current_process();
-    let mut fd_table = curr.fd_manager.fd_table.lock();
+    let fd_manager = curr.fd_manager();
+    let mut fd_table = fd_manager.fd_table.lock();
     let Ok(fd) = curr.alloc_fd(&mut fd_table) else {
         return Err(SyscallError::EMFILE);
     };
@@ -57,16 +60,16 @@ pub fn syscall_socket(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - usize
 pub fn syscall_bind(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let addr = args[1] as *const u8;
+    let addr = args[1];
     let _addr_len = args[2];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
 
-    let addr = unsafe { socket_address_from(addr) };
+    let addr = socket_address_from(addr)?;
 
     let Some(socket) = file.as_any().downcast_ref::<Socket>() else {
         return Err(SyscallError::ENOTSOCK);
@@ -86,7 +89,7 @@ pub fn syscall_listen(args: [usize; 6]) -> SyscallResult {
     let _backlog = args[1];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -105,12 +108,12 @@ pub fn syscall_listen(args: [usize; 6]) -> SyscallResult {
 /// * `flags` - usize
 pub fn syscall_accept4(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let addr_buf = args[1] as *mut u8;
-    let addr_len = args[2] as *mut u32;
+    let addr_buf = args[1];
+    let addr_len = args[2];
     let flags = args[3];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -125,9 +128,12 @@ pub fn syscall_accept4(args: [usize; 6]) -> SyscallResult {
 
     match socket.accept() {
         Ok((mut s, addr)) => {
-            let _ = unsafe { socket_address_to(addr, addr_buf, addr_len) };
+            if addr_buf != 0 {
+                socket_address_to(addr, addr_buf, addr_len)?;
+            }
 
-            let mut fd_table = curr.fd_manager.fd_table.lock();
+            let fd_manager = curr.fd_manager();
+            let mut fd_table = fd_manager.fd_table.lock();
             let Ok(new_fd) = curr.alloc_fd(&mut fd_table) else {
                 return Err(SyscallError::ENFILE); // Maybe ENFILE
             };
@@ -158,11 +164,11 @@ pub fn syscall_accept4(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - usize
 pub fn syscall_connect(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let addr_buf = args[1] as *const u8;
+    let addr_buf = args[1];
     let _addr_len = args[2];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -171,7 +177,7 @@ pub fn syscall_connect(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::ENOTSOCK);
     };
 
-    let addr = unsafe { socket_address_from(addr_buf) };
+    let addr = socket_address_from(addr_buf)?;
 
     debug!("[connect()] socket {fd} connecting to {addr:?}");
 
@@ -191,11 +197,11 @@ pub fn syscall_connect(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - *mut u32
 pub fn syscall_get_sock_name(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let addr = args[1] as *mut u8;
-    let addr_len = args[2] as *mut u32;
+    let addr = args[1];
+    let addr_len = args[2];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -212,7 +218,8 @@ pub fn syscall_get_sock_name(args: [usize; 6]) -> SyscallResult {
 
     info!("[getsockname()] socket {fd} name: {:?}", name);
 
-    Ok(unsafe { socket_address_to(name, addr, addr_len) }.map_or(-1, |_| 0))
+    socket_address_to(name, addr, addr_len)?;
+    Ok(0)
 }
 
 #[allow(unused)]
@@ -222,40 +229,30 @@ pub fn syscall_get_sock_name(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - *mut u32
 pub fn syscall_getpeername(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let addr_buf = args[1] as *mut u8;
-    let addr_len = args[2] as *mut u32;
+    let addr_buf = args[1];
+    let addr_len = args[2];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
 
-    let len = match curr.manual_alloc_type_for_lazy(addr_len as *const u32) {
-        Ok(_) => unsafe { *addr_len },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
+    let len = copy_struct_from_user::<u32>(addr_len)?;
     // It seems it could be negative according to Linux man page.
     if (len as i32) < 0 {
         return Err(SyscallError::EINVAL);
     }
 
-    if curr
-        .manual_alloc_range_for_lazy(
-            (addr_buf as usize).into(),
-            unsafe { addr_buf.add(len as usize) as usize }.into(),
-        )
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-
     let Some(socket) = file.as_any().downcast_ref::<Socket>() else {
         return Err(SyscallError::ENOTSOCK);
     };
 
     match socket.peer_name() {
-        Ok(name) => Ok(unsafe { socket_address_to(name, addr_buf, addr_len) }.map_or(-1, |_| 0)),
+        Ok(name) => {
+            socket_address_to(name, addr_buf, addr_len)?;
+            Ok(0)
+        }
         Err(AxError::NotConnected) => Err(SyscallError::ENOTCONN),
         Err(_) => unreachable!(),
     }
@@ -272,14 +269,14 @@ pub fn syscall_getpeername(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - usize
 pub fn syscall_sendto(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let buf = args[1] as *const u8;
+    let buf = args[1];
     let len = args[2];
     let _flags = args[3];
-    let addr = args[4] as *const u8;
+    let addr = args[4];
     let addr_len = args[5];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -288,31 +285,18 @@ pub fn syscall_sendto(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::ENOTSOCK);
     };
 
-    if buf.is_null() {
+    if buf == 0 {
         return Err(SyscallError::EFAULT);
     }
-    let Ok(buf) = curr
-        .manual_alloc_range_for_lazy(
-            (buf as usize).into(),
-            unsafe { buf.add(len) as usize }.into(),
-        )
-        .map(|_| unsafe { from_raw_parts(buf, len) })
-    else {
-        error!("[sendto()] buf address {buf:?} invalid");
+    let mut data = vec![0u8; len];
+    if copy_from_user(&mut data, buf).is_err() {
+        error!("[sendto()] buf address {buf:#x} invalid");
         return Err(SyscallError::EFAULT);
-    };
+    }
+    let buf = &data[..];
 
-    let addr = if !addr.is_null() && addr_len != 0 {
-        match curr.manual_alloc_range_for_lazy(
-            (addr as usize).into(),
-            unsafe { addr.add(addr_len) as usize }.into(),
-        ) {
-            Ok(_) => Some(unsafe { socket_address_from(addr) }),
-            Err(_) => {
-                error!("[sendto()] addr address {addr:?} invalid");
-                return Err(SyscallError::EFAULT);
-            }
-        }
+    let addr = if addr != 0 && addr_len != 0 {
+        Some(socket_address_from(addr)?)
     } else {
         None
     };
@@ -370,14 +354,14 @@ pub fn syscall_sendto(args: [usize; 6]) -> SyscallResult {
 /// * `addr_len` - *mut u32
 pub fn syscall_recvfrom(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
-    let buf = args[1] as *mut u8;
+    let buf = args[1];
     let len = args[2];
     let _flags = args[3];
-    let addr_buf = args[4] as *mut u8;
-    let addr_len = args[5] as *mut u32;
+    let addr_buf = args[4];
+    let addr_len = args[5];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -385,40 +369,17 @@ pub fn syscall_recvfrom(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::ENOTSOCK);
     };
 
-    if !addr_len.is_null()
-        && curr
-            .manual_alloc_for_lazy((addr_len as usize).into())
-            .is_err()
-    {
-        error!("[recvfrom()] addr_len address {addr_len:?} invalid");
-        return Err(SyscallError::EFAULT);
-    }
-    if !addr_buf.is_null()
-        && !addr_len.is_null()
-        && curr
-            .manual_alloc_range_for_lazy(
-                (addr_buf as usize).into(),
-                unsafe { addr_buf.add(*addr_len as usize) as usize }.into(),
-            )
-            .is_err()
-    {
-        error!(
-            "[recvfrom()] addr_buf address {addr_buf:?}, len: {} invalid",
-            unsafe { *addr_len }
-        );
-        return Err(SyscallError::EFAULT);
-    }
-    let buf = unsafe { from_raw_parts_mut(buf, len) };
+    // 数据先收到内核缓冲区中，阻塞期间不访问用户内存
+    let mut data = vec![0u8; len];
     info!("recv addr: {:?}", socket.name().unwrap());
-    match socket.recv_from(buf) {
+    match socket.recv_from(&mut data) {
         Ok((len, addr)) => {
             info!("socket {fd} recv {len} bytes from {addr:?}");
-            if !addr_buf.is_null() && !addr_len.is_null() {
-                Ok(unsafe { socket_address_to(addr, addr_buf, addr_len) }
-                    .map_or(-1, |_| len as isize))
-            } else {
-                Ok(len as isize)
+            copy_to_user(buf, &data[..len])?;
+            if addr_buf != 0 && addr_len != 0 {
+                socket_address_to(addr, addr_buf, addr_len)?;
             }
+            Ok(len as isize)
         }
         Err(AxError::ConnectionRefused) => Ok(0),
         Err(AxError::Interrupted) => Err(SyscallError::EINTR),
@@ -438,7 +399,7 @@ pub fn syscall_set_sock_opt(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let level = args[1];
     let opt_name = args[2];
-    let opt_value = args[3] as *const u8;
+    let opt_value = args[3];
     let opt_len = args[4] as u32;
     let Ok(level) = SocketOptionLevel::try_from(level) else {
         error!("[setsockopt()] level {level} not supported");
@@ -447,7 +408,7 @@ pub fn syscall_set_sock_opt(args: [usize; 6]) -> SyscallResult {
 
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -456,7 +417,9 @@ pub fn syscall_set_sock_opt(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::ENOTSOCK);
     };
 
-    let opt = unsafe { from_raw_parts(opt_value, opt_len as usize) };
+    let mut opt = vec![0u8; opt_len as usize];
+    copy_from_user(&mut opt, opt_value)?;
+    let opt = &opt[..];
 
     match level {
         SocketOptionLevel::IP => Ok(0),
@@ -475,7 +438,7 @@ pub fn syscall_set_sock_opt(args: [usize; 6]) -> SyscallResult {
                 return Ok(0);
             };
 
-            option.set(socket, opt);
+            option.set(socket, opt).map_err(SyscallError::from)?;
             Ok(0)
         }
     }
@@ -491,20 +454,20 @@ pub fn syscall_get_sock_opt(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let level = args[1];
     let opt_name = args[2];
-    let opt_value = args[3] as *mut u8;
-    let opt_len = args[4] as *mut u32;
+    let opt_value = args[3];
+    let opt_len = args[4];
     let Ok(level) = SocketOptionLevel::try_from(level) else {
         error!("[setsockopt()] level {level} not supported");
         unimplemented!();
     };
 
-    if opt_value.is_null() || opt_len.is_null() {
+    if opt_value == 0 || opt_len == 0 {
         return Err(SyscallError::EFAULT);
     }
 
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
@@ -513,27 +476,6 @@ pub fn syscall_get_sock_opt(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::ENOTSOCK);
     };
 
-    if curr
-        .manual_alloc_type_for_lazy(opt_len as *const u32)
-        .is_err()
-    {
-        error!("[getsockopt()] opt_len address {opt_len:?} invalid");
-        return Err(SyscallError::EFAULT);
-    }
-    if curr
-        .manual_alloc_range_for_lazy(
-            (opt_value as usize).into(),
-            (unsafe { opt_value.add(*opt_len as usize) } as usize).into(),
-        )
-        .is_err()
-    {
-        error!(
-            "[getsockopt()] opt_value {opt_value:?}, len {} invalid",
-            unsafe { *opt_len }
-        );
-        return Err(SyscallError::EFAULT);
-    }
-
     match level {
         SocketOptionLevel::IP => {}
         SocketOptionLevel::Socket => {
@@ -541,7 +483,7 @@ pub fn syscall_get_sock_opt(args: [usize; 6]) -> SyscallResult {
                 panic!("[setsockopt()] option {opt_name} not supported in socket level");
             };
 
-            option.get(socket, opt_value, opt_len);
+            option.get(socket, opt_value, opt_len)?;
         }
         SocketOptionLevel::Tcp => {
             let Ok(option) = TcpSocketOption::try_from(opt_name) else {
@@ -552,7 +494,7 @@ pub fn syscall_get_sock_opt(args: [usize; 6]) -> SyscallResult {
                 return Err(SyscallError::ENOPROTOOPT);
             }
 
-            option.get(socket, opt_value, opt_len);
+            option.get(socket, opt_value, opt_len)?;
         }
     }
 
@@ -575,7 +517,7 @@ pub fn syscall_shutdown(args: [usize; 6]) -> SyscallResult {
     let how = args[1];
     let curr = current_process();
 
-    let file = match curr.fd_manager.fd_table.lock().get(fd) {
+    let file = match curr.fd_manager().fd_table.lock().get(fd) {
         Some(Some(file)) => file.clone(),
         _ => return Err(SyscallError::EBADF),
     };
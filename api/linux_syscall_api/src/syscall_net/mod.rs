@@ -1,6 +1,6 @@
 //! 提供与 net work 相关的 syscall
 
-use crate::SyscallResult;
+use crate::SyscallHandler;
 mod imp;
 
 #[allow(unused)]
@@ -10,30 +10,20 @@ pub use socket::Socket;
 mod net_syscall_id;
 pub use net_syscall_id::NetSyscallId::{self, *};
 
-/// 进行 syscall 的分发
-pub fn net_syscall(syscall_id: net_syscall_id::NetSyscallId, args: [usize; 6]) -> SyscallResult {
-    match syscall_id {
-        SOCKET => syscall_socket(args),
-        BIND => syscall_bind(args),
-        LISTEN => syscall_listen(args),
-        ACCEPT => syscall_accept4(args),
-        CONNECT => syscall_connect(args),
-        GETSOCKNAME => syscall_get_sock_name(args),
-        GETPEERNAME => syscall_getpeername(args),
-        // GETPEERNAME => 0,
-        SENDTO => syscall_sendto(args),
-        RECVFROM => syscall_recvfrom(args),
-        SETSOCKOPT => syscall_set_sock_opt(args),
-        // SETSOCKOPT => 0,
-        GETSOCKOPT => syscall_get_sock_opt(args),
-        SOCKETPAIR => syscall_socketpair(),
-        ACCEPT4 => syscall_accept4(args),
-        SHUTDOWN => syscall_shutdown(args),
-        #[allow(unused)]
-        _ => {
-            panic!("Invalid Syscall Id: {:?}!", syscall_id);
-            // return -1;
-            // exit(-1)
-        }
-    }
-}
+/// 与网络相关的系统调用及其处理函数
+pub(crate) const NET_SYSCALLS: &[(NetSyscallId, SyscallHandler)] = &[
+    (SOCKET, syscall_socket),
+    (BIND, syscall_bind),
+    (LISTEN, syscall_listen),
+    (ACCEPT, syscall_accept4),
+    (CONNECT, syscall_connect),
+    (GETSOCKNAME, syscall_get_sock_name),
+    (GETPEERNAME, syscall_getpeername),
+    (SENDTO, syscall_sendto),
+    (RECVFROM, syscall_recvfrom),
+    (SETSOCKOPT, syscall_set_sock_opt),
+    (GETSOCKOPT, syscall_get_sock_opt),
+    (SOCKETPAIR, |_| syscall_socketpair()),
+    (ACCEPT4, syscall_accept4),
+    (SHUTDOWN, syscall_shutdown),
+];
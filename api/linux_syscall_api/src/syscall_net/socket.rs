@@ -1,24 +1,26 @@
 extern crate alloc;
-use alloc::vec::Vec;
 use core::{
     mem::size_of,
-    ptr::copy_nonoverlapping,
     sync::atomic::{AtomicBool, AtomicU64},
 };
 
-use alloc::string::String;
-use axerrno::{AxError, AxResult};
-use axfs::api::{FileIO, FileIOType, OpenFlags, Read, Write};
+use alloc::{string::String, sync::Arc};
+use axerrno::{AxError, AxResult, LinuxError};
+use axfs::api::{FileIO, FileIOType, Kstat, OpenFlags, Read, Write, FIONREAD};
 
 use axlog::warn;
 use axnet::{
-    from_core_sockaddr, into_core_sockaddr, poll_interfaces, IpAddr, SocketAddr, TcpSocket,
-    UdpSocket,
+    from_core_sockaddr, into_core_sockaddr, poll_interfaces, set_poll_hook, IpAddr, SocketAddr,
+    TcpSocket, UdpSocket,
+};
+use axprocess::{
+    fasync::{Fasync, ReadyWatch},
+    uaccess::{copy_struct_from_user, copy_struct_to_user, copy_to_user},
 };
 use axsync::Mutex;
 use num_enum::TryFromPrimitive;
 
-use crate::TimeVal;
+use crate::{StMode, TimeVal};
 
 pub const SOCKET_TYPE_MASK: usize = 0xFF;
 
@@ -168,7 +170,7 @@ impl SocketOption {
                     panic!("can't read a timeval from socket opt value");
                 }
 
-                let timeout = unsafe { *(opt.as_ptr() as *const TimeVal) };
+                let timeout = unsafe { (opt.as_ptr() as *const TimeVal).read_unaligned() };
                 socket.set_recv_timeout(if timeout.sec == 0 && timeout.usec == 0 {
                     None
                 } else {
@@ -181,8 +183,9 @@ impl SocketOption {
         }
     }
 
-    pub fn get(&self, socket: &Socket, opt_value: *mut u8, opt_len: *mut u32) {
-        let buf_len = unsafe { *opt_len } as usize;
+    /// 将选项的值写入用户地址 `opt_value`，并将其长度写入 `opt_len`
+    pub fn get(&self, socket: &Socket, opt_value: usize, opt_len: usize) -> Result<(), LinuxError> {
+        let buf_len = copy_struct_from_user::<u32>(opt_len)? as usize;
 
         match self {
             SocketOption::SO_REUSEADDR => {
@@ -192,10 +195,7 @@ impl SocketOption {
                     panic!("can't write a int to socket opt value");
                 }
 
-                unsafe {
-                    copy_nonoverlapping(&value.to_ne_bytes() as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &value.to_ne_bytes())?;
             }
             SocketOption::SO_DONTROUTE => {
                 if buf_len < 4 {
@@ -204,10 +204,7 @@ impl SocketOption {
 
                 let size: i32 = if socket.dont_route { 1 } else { 0 };
 
-                unsafe {
-                    copy_nonoverlapping(&size.to_ne_bytes() as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &size.to_ne_bytes())?;
             }
             SocketOption::SO_SNDBUF => {
                 if buf_len < 4 {
@@ -216,10 +213,7 @@ impl SocketOption {
 
                 let size: i32 = socket.get_send_buf_size() as i32;
 
-                unsafe {
-                    copy_nonoverlapping(&size.to_ne_bytes() as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &size.to_ne_bytes())?;
             }
             SocketOption::SO_RCVBUF => {
                 if buf_len < 4 {
@@ -228,10 +222,7 @@ impl SocketOption {
 
                 let size: i32 = socket.get_recv_buf_size() as i32;
 
-                unsafe {
-                    copy_nonoverlapping(&size.to_ne_bytes() as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &size.to_ne_bytes())?;
             }
             SocketOption::SO_KEEPALIVE => {
                 if buf_len < 4 {
@@ -254,40 +245,28 @@ impl SocketOption {
                 };
                 drop(inner);
 
-                unsafe {
-                    copy_nonoverlapping(&keep_alive.to_ne_bytes() as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &keep_alive.to_ne_bytes())?;
             }
             SocketOption::SO_RCVTIMEO => {
                 if buf_len < size_of::<TimeVal>() {
                     panic!("can't write a timeval to socket opt value");
                 }
 
-                unsafe {
-                    match socket.get_recv_timeout() {
-                        Some(time) => copy_nonoverlapping(
-                            (&time) as *const TimeVal,
-                            opt_value as *mut TimeVal,
-                            1,
-                        ),
-                        None => {
-                            copy_nonoverlapping(&0u8 as *const u8, opt_value, size_of::<TimeVal>())
-                        }
-                    }
-
-                    *opt_len = size_of::<TimeVal>() as u32;
-                }
+                let time = socket.get_recv_timeout().unwrap_or_default();
+                copy_struct_to_user(opt_value, time)?;
+                copy_struct_to_user(opt_len, size_of::<TimeVal>() as u32)?;
             }
             SocketOption::SO_ERROR => {
                 // 当前没有存储错误列表，因此不做处理
             }
         }
+        Ok(())
     }
 }
 
 impl TcpSocketOption {
-    pub fn set(&self, raw_socket: &Socket, opt: &[u8]) {
+    /// 设置选项，TCP_CONGESTION 的算法名不是合法的 UTF-8 时返回 InvalidInput
+    pub fn set(&self, raw_socket: &Socket, opt: &[u8]) -> AxResult<()> {
         let mut inner = raw_socket.inner.lock();
         let socket = match &mut *inner {
             SocketInner::Tcp(ref mut s) => s,
@@ -306,22 +285,32 @@ impl TcpSocketOption {
             }
             TcpSocketOption::TCP_INFO => panic!("[setsockopt()] try to set TCP_INFO"),
             TcpSocketOption::TCP_CONGESTION => {
-                raw_socket.set_congestion(String::from_utf8(Vec::from(opt)).unwrap())
+                // 算法名可以带有结尾的 '\0'
+                let len = opt.iter().position(|&byte| byte == 0).unwrap_or(opt.len());
+                let name = core::str::from_utf8(&opt[..len]).map_err(|_| AxError::InvalidInput)?;
+                raw_socket.set_congestion(String::from(name))
             }
             _ => {
                 unimplemented!()
             }
         }
+        Ok(())
     }
 
-    pub fn get(&self, raw_socket: &Socket, opt_value: *mut u8, opt_len: *mut u32) {
+    /// 将选项的值写入用户地址 `opt_value`，并将其长度写入 `opt_len`
+    pub fn get(
+        &self,
+        raw_socket: &Socket,
+        opt_value: usize,
+        opt_len: usize,
+    ) -> Result<(), LinuxError> {
         let inner = raw_socket.inner.lock();
         let socket = match &*inner {
             SocketInner::Tcp(ref s) => s,
             _ => panic!("calling tcp option on a wrong type of socket"),
         };
 
-        let buf_len = unsafe { *opt_len };
+        let buf_len = copy_struct_from_user::<u32>(opt_len)?;
 
         match self {
             TcpSocketOption::TCP_NODELAY => {
@@ -331,37 +320,30 @@ impl TcpSocketOption {
 
                 let value: i32 = if socket.nagle_enabled() { 0 } else { 1 };
 
-                let value = value.to_ne_bytes();
-
-                unsafe {
-                    copy_nonoverlapping(&value as *const u8, opt_value, 4);
-                    *opt_len = 4;
-                }
+                write_sock_opt(opt_value, opt_len, &value.to_ne_bytes())?;
             }
             TcpSocketOption::TCP_MAXSEG => {
-                let len = size_of::<usize>();
-
                 let value: usize = 1500;
 
-                unsafe {
-                    copy_nonoverlapping(&value as *const usize as *const u8, opt_value, len);
-                    *opt_len = len as u32;
-                };
+                write_sock_opt(opt_value, opt_len, &value.to_ne_bytes())?;
             }
             TcpSocketOption::TCP_INFO => {}
             TcpSocketOption::TCP_CONGESTION => {
                 let bytes = raw_socket.get_congestion();
-                let bytes = bytes.as_bytes();
 
-                unsafe {
-                    copy_nonoverlapping(bytes.as_ptr(), opt_value, bytes.len());
-                    *opt_len = bytes.len() as u32;
-                };
+                write_sock_opt(opt_value, opt_len, bytes.as_bytes())?;
             }
         }
+        Ok(())
     }
 }
 
+/// 将选项的值 `value` 写入用户地址 `opt_value`，并将其长度写入 `opt_len`
+fn write_sock_opt(opt_value: usize, opt_len: usize, value: &[u8]) -> Result<(), LinuxError> {
+    copy_to_user(opt_value, value)?;
+    copy_struct_to_user(opt_len, value.len() as u32)
+}
+
 /// 包装内部的不同协议 Socket
 /// 类似 FileDesc，impl FileIO 后加入fd_list
 #[allow(dead_code)]
@@ -381,6 +363,8 @@ pub struct Socket {
     send_buf_size: AtomicU64,
     recv_buf_size: AtomicU64,
     congestion: Mutex<String>,
+    /// 信号驱动 I/O 的状态
+    fasync: Arc<Fasync>,
 }
 
 /// The transport protocol used by the socket
@@ -436,6 +420,18 @@ impl Socket {
         *self.congestion.lock() = congestion;
     }
 
+    /// 信号驱动 I/O 的状态
+    pub fn fasync(&self) -> &Arc<Fasync> {
+        &self.fasync
+    }
+
+    /// 启用了 O_ASYNC 的套接字，协议栈每次处理收发之后检查
+    pub fn async_watch() -> &'static ReadyWatch {
+        static ASYNC_SOCKETS: ReadyWatch = ReadyWatch::new();
+        set_poll_hook(|| ASYNC_SOCKETS.check());
+        &ASYNC_SOCKETS
+    }
+
     /// Create a new socket with the given domain and socket type.
     pub fn new(domain: Domain, socket_type: SocketType) -> Self {
         let inner = match socket_type {
@@ -456,6 +452,7 @@ impl Socket {
             send_buf_size: AtomicU64::new(64 * 1024),
             recv_buf_size: AtomicU64::new(64 * 1024),
             congestion: Mutex::new(String::from("reno")),
+            fasync: Arc::default(),
         }
     }
 
@@ -560,6 +557,7 @@ impl Socket {
                 send_buf_size: AtomicU64::new(64 * 1024),
                 recv_buf_size: AtomicU64::new(64 * 1024),
                 congestion: Mutex::new(String::from("reno")),
+                fasync: Arc::default(),
             },
             from_core_sockaddr(addr),
         ))
@@ -601,14 +599,14 @@ impl Socket {
                 let addr = s.peer_addr()?;
 
                 match self.get_recv_timeout() {
-                    Some(time) => s.recv_timeout(buf, time.turn_to_ticks()),
+                    Some(time) => s.recv_timeout(buf, time.to_duration()),
                     None => s.recv(buf),
                 }
                 .map(|len| (len, from_core_sockaddr(addr)))
             }
             SocketInner::Udp(s) => match self.get_recv_timeout() {
                 Some(time) => s
-                    .recv_from_timeout(buf, time.turn_to_ticks())
+                    .recv_from_timeout(buf, time.to_duration())
                     .map(|(val, addr)| (val, from_core_sockaddr(addr))),
                 None => s
                     .recv_from(buf)
@@ -691,6 +689,15 @@ impl FileIO for Socket {
         FileIOType::Socket
     }
 
+    fn get_stat(&self) -> AxResult<Kstat> {
+        Ok(Kstat {
+            st_mode: StMode::S_IFSOCK.bits() | 0o777,
+            st_nlink: 1,
+            st_blksize: 4096,
+            ..Kstat::default()
+        })
+    }
+
     fn get_status(&self) -> OpenFlags {
         let mut flags = OpenFlags::default();
 
@@ -718,25 +725,39 @@ impl FileIO for Socket {
     fn ready_to_write(&self) -> bool {
         self.writable()
     }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        match request {
+            FIONREAD => {
+                // 接收缓冲区中待读取的字节数，UDP 为下一个数据报的长度
+                poll_interfaces();
+                let pending = match &*self.inner.lock() {
+                    SocketInner::Tcp(s) => s.recv_queue(),
+                    SocketInner::Udp(s) => s.recv_queue(),
+                };
+                copy_struct_to_user(data, pending as u32).map_err(|_| AxError::BadAddress)
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
 }
 
-/// Turn a socket address buffer into a SocketAddr
+/// 从用户地址 `addr` 读取 socket 地址
 ///
-/// Only support INET (ipv4)
-pub unsafe fn socket_address_from(addr: *const u8) -> SocketAddr {
-    let addr = addr as *const u16;
-    let domain = Domain::try_from(*addr as usize).expect("Unsupported Domain (Address Family)");
-    match domain {
-        Domain::AF_UNIX => unimplemented!(),
-        Domain::AF_INET => {
-            let port = u16::from_be(*addr.add(1));
-            let a = (*(addr.add(2) as *const u32)).to_le_bytes();
-
-            let addr = IpAddr::v4(a[0], a[1], a[2], a[3]);
-            SocketAddr { addr, port }
+/// Only support INET (ipv4)，其他地址族返回 EAFNOSUPPORT，地址不可读时返回 EFAULT
+pub fn socket_address_from(addr: usize) -> Result<SocketAddr, LinuxError> {
+    let family: u16 = copy_struct_from_user(addr)?;
+    match Domain::try_from(family as usize) {
+        Ok(Domain::AF_INET) => {
+            let raw: [u8; 8] = copy_struct_from_user(addr)?;
+            let port = u16::from_be_bytes([raw[2], raw[3]]);
+            let addr = IpAddr::v4(raw[4], raw[5], raw[6], raw[7]);
+            Ok(SocketAddr { addr, port })
         }
+        _ => Err(LinuxError::EAFNOSUPPORT),
     }
 }
+
 /// Only support INET (ipv4)
 ///
 /// ipv4 socket address buffer:
@@ -744,39 +765,15 @@ pub unsafe fn socket_address_from(addr: *const u8) -> SocketAddr {
 /// port u16 (big endian)
 /// addr u32 (big endian)
 ///
-/// TODO: Returns error if buf or buf_len is in invalid memory
-pub unsafe fn socket_address_to(addr: SocketAddr, buf: *mut u8, buf_len: *mut u32) -> AxResult {
-    let mut tot_len = *buf_len as usize;
-
-    *buf_len = 8;
-
-    // 写入 AF_INET
-    if tot_len == 0 {
-        return Ok(());
-    }
-    let domain = (Domain::AF_INET as u16).to_ne_bytes();
-    let write_len = tot_len.min(2);
-    copy_nonoverlapping(domain.as_ptr(), buf, write_len);
-    let buf = buf.add(write_len);
-    tot_len -= write_len;
-
-    // 写入 port
-    if tot_len == 0 {
-        return Ok(());
-    }
-    let port = &addr.port.to_be_bytes();
-    let write_len = tot_len.min(2);
-    copy_nonoverlapping(port.as_ptr(), buf, write_len);
-    let buf = buf.add(write_len);
-    tot_len -= write_len;
-
-    // 写入 address
-    if tot_len == 0 {
-        return Ok(());
-    }
-    let address = &addr.addr.as_bytes();
-    let write_len = tot_len.min(4);
-    copy_nonoverlapping(address.as_ptr(), buf, write_len);
-
-    Ok(())
+/// 地址按 `buf_len` 指向的长度截断写入 `buf`，之后将完整的长度写回 `buf_len`，
+/// 用户地址不可访问时返回 EFAULT
+pub fn socket_address_to(addr: SocketAddr, buf: usize, buf_len: usize) -> Result<(), LinuxError> {
+    let tot_len = copy_struct_from_user::<u32>(buf_len)? as usize;
+
+    let mut raw = [0u8; 8];
+    raw[..2].copy_from_slice(&(Domain::AF_INET as u16).to_ne_bytes());
+    raw[2..4].copy_from_slice(&addr.port.to_be_bytes());
+    raw[4..].copy_from_slice(&addr.addr.as_bytes()[..4]);
+    copy_to_user(buf, &raw[..tot_len.min(raw.len())])?;
+    copy_struct_to_user(buf_len, raw.len() as u32)
 }
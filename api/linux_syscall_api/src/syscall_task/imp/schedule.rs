@@ -2,8 +2,11 @@
 extern crate alloc;
 use alloc::sync::Arc;
 use axconfig::SMP;
-use axhal::mem::VirtAddr;
-use axprocess::{current_process, current_task, PID2PC, TID2TASK};
+use axprocess::{
+    current_task,
+    uaccess::{copy_struct_from_user, copy_struct_to_user},
+    PID2PC, TID2TASK,
+};
 
 use axtask::{SchedPolicy, SchedStatus};
 
@@ -24,13 +27,13 @@ use crate::{SchedParam, SyscallError, SyscallResult};
 pub fn syscall_sched_getaffinity(args: [usize; 6]) -> SyscallResult {
     let pid = args[0];
     let cpu_set_size = args[1];
-    let mask = args[2] as *mut usize;
+    let mask = args[2];
     // let task: LazyInit<AxTaskRef> = LazyInit::new();
     let tid2task = TID2TASK.lock();
     let pid2task = PID2PC.lock();
     let pid = pid as u64;
-    let task = if tid2task.contains_key(&pid) {
-        Arc::clone(tid2task.get(&pid).unwrap())
+    let task = if let Some(task) = tid2task.get(&pid).and_then(|task| task.upgrade()) {
+        task
     } else if pid2task.contains_key(&pid) {
         let process = pid2task.get(&pid).unwrap();
 
@@ -51,21 +54,12 @@ pub fn syscall_sched_getaffinity(args: [usize; 6]) -> SyscallResult {
     drop(pid2task);
     drop(tid2task);
 
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy(VirtAddr::from(mask as usize))
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
     let cpu_set = task.get_cpu_set();
-    let mut prev_mask = unsafe { *mask };
+    let mut prev_mask: usize = copy_struct_from_user(mask)?;
     let len = SMP.min(cpu_set_size * 4);
     prev_mask &= !((1 << len) - 1);
     prev_mask &= cpu_set & ((1 << len) - 1);
-    unsafe {
-        *mask = prev_mask;
-    }
+    copy_struct_to_user(mask, prev_mask)?;
     // 返回成功填充的缓冲区的长度
     Ok(SMP as isize)
 }
@@ -78,12 +72,12 @@ pub fn syscall_sched_getaffinity(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_sched_setaffinity(args: [usize; 6]) -> SyscallResult {
     let pid = args[0];
     let cpu_set_size = args[1];
-    let mask = args[2] as *const usize;
+    let mask = args[2];
     let tid2task = TID2TASK.lock();
     let pid2task = PID2PC.lock();
     let pid = pid as u64;
-    let task = if tid2task.contains_key(&pid) {
-        Arc::clone(tid2task.get(&pid).unwrap())
+    let task = if let Some(task) = tid2task.get(&pid).and_then(|task| task.upgrade()) {
+        task
     } else if pid2task.contains_key(&pid) {
         let process = pid2task.get(&pid).unwrap();
 
@@ -104,15 +98,7 @@ pub fn syscall_sched_setaffinity(args: [usize; 6]) -> SyscallResult {
     drop(pid2task);
     drop(tid2task);
 
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy(VirtAddr::from(mask as usize))
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-
-    let mask = unsafe { *mask };
+    let mask: usize = copy_struct_from_user(mask)?;
 
     task.set_cpu_set(mask, cpu_set_size, axconfig::SMP);
 
@@ -126,7 +112,7 @@ pub fn syscall_sched_setaffinity(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_sched_setscheduler(args: [usize; 6]) -> SyscallResult {
     let pid = args[0];
     let policy = args[1];
-    let param = args[2] as *const SchedParam;
+    let param = args[2];
     if (pid as isize) < 0 || param.is_null() {
         return Err(SyscallError::EINVAL);
     }
@@ -134,8 +120,8 @@ pub fn syscall_sched_setscheduler(args: [usize; 6]) -> SyscallResult {
     let tid2task = TID2TASK.lock();
     let pid2task = PID2PC.lock();
     let pid = pid as u64;
-    let task = if tid2task.contains_key(&pid) {
-        Arc::clone(tid2task.get(&pid).unwrap())
+    let task = if let Some(task) = tid2task.get(&pid).and_then(|task| task.upgrade()) {
+        task
     } else if pid2task.contains_key(&pid) {
         let process = pid2task.get(&pid).unwrap();
 
@@ -156,15 +142,7 @@ pub fn syscall_sched_setscheduler(args: [usize; 6]) -> SyscallResult {
     drop(pid2task);
     drop(tid2task);
 
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy(VirtAddr::from(param as usize))
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-
-    let param = unsafe { *param };
+    let param: SchedParam = copy_struct_from_user(param)?;
     let policy = SchedPolicy::from(policy);
     if policy == SchedPolicy::SCHED_UNKNOWN {
         return Err(SyscallError::EINVAL);
@@ -199,8 +177,8 @@ pub fn syscall_sched_getscheduler(args: [usize; 6]) -> SyscallResult {
     let tid2task = TID2TASK.lock();
     let pid2task = PID2PC.lock();
     let pid = pid as u64;
-    let task = if tid2task.contains_key(&pid) {
-        Arc::clone(tid2task.get(&pid).unwrap())
+    let task = if let Some(task) = tid2task.get(&pid).and_then(|task| task.upgrade()) {
+        task
     } else if pid2task.contains_key(&pid) {
         let process = pid2task.get(&pid).unwrap();
 
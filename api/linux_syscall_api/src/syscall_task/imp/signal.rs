@@ -3,7 +3,11 @@
 
 use axhal::cpu::this_cpu_id;
 use axlog::{debug, info};
-use axprocess::{current_process, current_task, yield_now_task};
+use axprocess::{
+    current_process, current_task,
+    uaccess::{copy_struct_from_user, copy_struct_to_user},
+    yield_now_task,
+};
 use axsignal::action::SigAction;
 use axsignal::signal_no::SignalNo;
 
@@ -15,11 +19,11 @@ use crate::{SigMaskFlag, SyscallError, SyscallResult, SIGSET_SIZE_IN_BYTE};
 /// * `old_action` - *mut SigAction
 pub fn syscall_sigaction(args: [usize; 6]) -> SyscallResult {
     let signum = args[0];
-    let action = args[1] as *const SigAction;
-    let old_action = args[2] as *mut SigAction;
+    let action = args[1];
+    let old_action = args[2];
     info!(
         "signum: {}, action: {:X}, old_action: {:X}",
-        signum, action as usize, old_action as usize
+        signum, action, old_action
     );
     if signum == SignalNo::SIGKILL as usize || signum == SignalNo::SIGSTOP as usize {
         // 特殊参数不能被覆盖
@@ -32,36 +36,23 @@ pub fn syscall_sigaction(args: [usize; 6]) -> SyscallResult {
         .get_mut(&current_task().id().as_u64())
         .unwrap();
     let mut signal_handler = signal_module.signal_handler.lock();
-    let old_address = old_action as usize;
-
-    if old_address != 0 {
-        // old_address非零说明要求写入到这个地址
-        // 此时要检查old_address是否在某一个段中
-        if current_process
-            .manual_alloc_for_lazy(old_address.into())
-            .is_err()
-        {
-            // 无法分配
-            return Err(SyscallError::EPERM);
-        }
+    // 先读出新的 action，地址不合法时不修改原有的 action
+    let new_action = if action != 0 {
+        Some(copy_struct_from_user::<SigAction>(action)?)
+    } else {
+        None
+    };
+
+    if old_action != 0 {
+        // old_action非零说明要求写入到这个地址
         if let Some(action) = signal_handler.get_action(signum) {
-            // 将原有的action存储到old_address
-            unsafe {
-                *old_action = *action;
-            }
+            // 将原有的action存储到old_action
+            copy_struct_to_user(old_action, *action)?;
         }
     }
 
-    let new_address = action as usize;
-    if new_address != 0 {
-        if current_process
-            .manual_alloc_for_lazy(new_address.into())
-            .is_err()
-        {
-            // 无法分配
-            return Err(SyscallError::EPERM);
-        }
-        unsafe { signal_handler.set_action(signum, action) };
+    if let Some(action) = new_action {
+        signal_handler.set_action(signum, action);
     }
     Ok(0)
 }
@@ -71,14 +62,8 @@ pub fn syscall_sigaction(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * `mask` - *const usize
 pub fn syscall_sigsuspend(args: [usize; 6]) -> SyscallResult {
-    let mask = args[0] as *const usize;
+    let mask: usize = copy_struct_from_user(args[0])?;
     let process = current_process();
-    if process
-        .manual_alloc_for_lazy((mask as usize).into())
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
     let mut signal_modules = process.signal_modules.lock();
 
     let signal_module = signal_modules
@@ -89,7 +74,7 @@ pub fn syscall_sigsuspend(args: [usize; 6]) -> SyscallResult {
         // 信号嵌套的情况下触发这个调用
         return Err(SyscallError::EINTR);
     }
-    signal_module.signal_set.mask = unsafe { *mask };
+    signal_module.signal_set.mask = mask;
     drop(signal_modules);
     loop {
         let mut signal_modules = process.signal_modules.lock();
@@ -124,8 +109,8 @@ pub fn syscall_sigreturn() -> SyscallResult {
 /// * `sigsetsize` - usize, specifies the size in bytes of the signal sets in set and oldset, which is equal to sizeof(kernel_sigset_t)
 pub fn syscall_sigprocmask(args: [usize; 6]) -> SyscallResult {
     let flag = SigMaskFlag::from(args[0]);
-    let new_mask = args[1] as *const usize;
-    let old_mask = args[2] as *mut usize;
+    let new_mask = args[1];
+    let old_mask = args[2];
     let sigsetsize = args[3];
     if sigsetsize != SIGSET_SIZE_IN_BYTE {
         // 若sigsetsize不是正确的大小，则返回错误
@@ -133,33 +118,21 @@ pub fn syscall_sigprocmask(args: [usize; 6]) -> SyscallResult {
     }
 
     let current_process = current_process();
-    if old_mask as usize != 0
-        && current_process
-            .manual_alloc_for_lazy((old_mask as usize).into())
-            .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-    if new_mask as usize != 0
-        && current_process
-            .manual_alloc_for_lazy((new_mask as usize).into())
-            .is_err()
-    {
-        return Err(SyscallError::EPERM);
-    }
+    let now_mask = if new_mask != 0 {
+        Some(copy_struct_from_user::<usize>(new_mask)?)
+    } else {
+        None
+    };
 
     let mut signal_modules = current_process.signal_modules.lock();
     let signal_module = signal_modules
         .get_mut(&current_task().id().as_u64())
         .unwrap();
-    if old_mask as usize != 0 {
-        unsafe {
-            *old_mask = signal_module.signal_set.mask;
-        }
+    if old_mask != 0 {
+        copy_struct_to_user(old_mask, signal_module.signal_set.mask)?;
     }
 
-    if new_mask as usize != 0 {
-        let now_mask = unsafe { *new_mask };
+    if let Some(now_mask) = now_mask {
         match flag {
             SigMaskFlag::Block => {
                 signal_module.signal_set.mask |= now_mask;
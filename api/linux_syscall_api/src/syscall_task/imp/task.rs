@@ -1,30 +1,45 @@
+//! 处理与任务（线程）有关的系统调用
 use core::mem::size_of;
-/// 处理与任务（线程）有关的系统调用
-use core::time::Duration;
 
-use axconfig::TASK_STACK_SIZE;
+use axconfig::{MAX_USER_STACK_SIZE, TASK_STACK_SIZE};
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, SeekFrom};
+use axhal::mem::PAGE_SIZE_4K;
 use axhal::time::current_time;
 use axprocess::{
-    current_process, current_task, exit_current_task,
+    check_app, check_elf, check_user_tls, current_process, current_task, exit_current_task,
     flags::{CloneFlags, WaitStatus},
     futex::clear_wait,
-    link::{deal_with_path, raw_ptr_to_ref_str, AT_FDCWD},
-    set_child_tid, sleep_now_task, wait_pid, yield_now_task, Process, PID2PC,
+    link::{
+        deal_with_path_str, follow_symlinks, read_symlink, resolve_path_at, FilePath, AT_FDCWD,
+    },
+    set_child_tid,
+    uaccess::{
+        copy_struct_from_user, copy_struct_to_user, copy_to_user, user_path, user_string_array,
+    },
+    wait_pid, yield_now_task, Process, PID2PC,
 };
-use axsync::Mutex;
+use axsync::SpinWaitNoIrq;
 // use axtask::{
 //     monolithic_task::task::{SchedPolicy, SchedStatus},
 //     AxTaskRef,
 // };
+use super::utils::{sleep_duration, sleep_until_deadline};
 use crate::{
-    CloneArgs, RLimit, SyscallError, SyscallResult, TimeSecs, WaitFlags, RLIMIT_AS, RLIMIT_NOFILE,
-    RLIMIT_STACK,
+    CloneArgs, RLimit, Rusage, SyscallError, SyscallResult, TimeVal, WaitFlags, RLIMIT_AS,
+    RLIMIT_NOFILE, RLIMIT_STACK,
 };
 use axlog::{info, warn};
 use axtask::TaskId;
 extern crate alloc;
 
-use alloc::{string::ToString, sync::Arc, vec::Vec};
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
 
 use axsignal::signal_no::SignalNo;
 
@@ -80,62 +95,38 @@ pub fn syscall_exit(args: [usize; 6]) -> ! {
 //     true
 // }
 
-/// # Arguments
-/// * `path` - *const u8
-/// * `argv` - *const usize
-/// * `envp` - *const usize
-pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
-    let path = args[0] as *const u8;
-    let mut argv = args[1] as *const usize;
-    let mut envp = args[2] as *const usize;
-    let path = deal_with_path(AT_FDCWD, Some(path), false);
-    if path.is_none() {
-        return Err(SyscallError::EINVAL);
-    }
-    let path = path.unwrap();
-    if path.is_dir() {
-        return Err(SyscallError::EISDIR);
-    }
-    let path = path.path().to_string();
+/// execveat 的 flags 中允许路径为空，此时执行 dir_fd 本身
+const AT_EMPTY_PATH: usize = 0x1000;
+/// execveat 的 flags 中不跟随路径最后一级的符号链接
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
 
-    let mut args_vec = Vec::new();
-    // args相当于argv，指向了参数所在的地址
-    loop {
-        let args_str_ptr = unsafe { *argv };
-        if args_str_ptr == 0 {
-            break;
-        }
-        args_vec.push(unsafe { raw_ptr_to_ref_str(args_str_ptr as *const u8) }.to_string());
-        unsafe {
-            argv = argv.add(1);
-        }
-    }
-    let mut envs_vec = Vec::new();
-    if envp as usize != 0 {
-        loop {
-            let envp_str_ptr = unsafe { *envp };
-            if envp_str_ptr == 0 {
-                break;
-            }
-            envs_vec.push(unsafe { raw_ptr_to_ref_str(envp_str_ptr as *const u8) }.to_string());
-            unsafe {
-                envp = envp.add(1);
-            }
-        }
+/// 执行 `path` 处的程序，替换当前进程的地址空间
+///
+/// `elf_data` 不为空时直接从中加载程序，此时 `path` 只用作任务名与 /proc/self/exe 的内容；
+/// 为空时 `path` 是由 busybox sh 执行的脚本。
+/// 在释放原有地址空间之前读取参数与环境变量并检查程序能否加载，
+/// 因此参数地址不合法或程序无法加载时 exec 返回错误，调用者继续执行。
+/// 之后加载失败时进程已经无法返回，只能退出
+fn execve_common(
+    path: String,
+    elf_data: Option<Vec<u8>>,
+    argv: usize,
+    envp: usize,
+) -> SyscallResult {
+    // 参数与环境变量最终放在用户栈上，各自最多占用栈的四分之一
+    let arg_max = MAX_USER_STACK_SIZE / 4;
+    let args_vec = user_string_array(argv, arg_max)?;
+    let envs_vec = user_string_array(envp, arg_max)?;
+
+    match elf_data.as_deref() {
+        Some(elf_data) => check_elf(elf_data),
+        None => check_app(&path),
     }
-    // let testcase = if args_vec[0] == "./busybox".to_string()
-    //     || args_vec[0] == "busybox".to_string()
-    //     || args_vec[0] == "entry-static.exe".to_string()
-    //     || args_vec[0] == "entry-dynamic.exe".to_string()
-    //     || args_vec[0] == "lmbench_all".to_string()
-    // {
-    //     args_vec[1].clone()
-    // } else {
-    //     args_vec[0].clone()
-    // };
-    // if filter(testcase) == false {
-    //     return -1;
-    // }
+    .map_err(|err| match err {
+        AxError::InvalidData => SyscallError::ENOEXEC,
+        err => SyscallError::from(err),
+    })?;
+
     let curr_process = current_process();
 
     // 设置 file_path
@@ -144,12 +135,114 @@ pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
     // 清空futex信号列表
     clear_wait(curr_process.pid(), true);
     let argc = args_vec.len();
-    if curr_process.exec(path, args_vec, &envs_vec).is_err() {
+    if curr_process
+        .exec_image(path, elf_data.as_deref(), args_vec, &envs_vec)
+        .is_err()
+    {
         exit_current_task(0);
     }
     Ok(argc as isize)
 }
 
+/// 执行路径对应的程序
+///
+/// 与 Linux 一样跟随路径最后一级的符号链接，`nofollow` 为真且最后一级是符号链接时返回 ELOOP。
+/// 程序只读入内存一次，之后的检查与加载都使用这份数据；脚本交给 busybox sh 执行，不需要读入
+fn execve_path(path: FilePath, nofollow: bool, argv: usize, envp: usize) -> SyscallResult {
+    let path = if read_symlink(path.path()).is_some() {
+        if nofollow {
+            return Err(SyscallError::ELOOP);
+        }
+        FilePath::new(&follow_symlinks(path.path(), true)?).map_err(|_| SyscallError::ENOENT)?
+    } else {
+        path
+    };
+    if path.is_dir() {
+        return Err(SyscallError::EACCES);
+    }
+    let path = path.path().to_string();
+    let elf_data = if path.ends_with(".sh") {
+        None
+    } else {
+        Some(axfs::api::read(&path).map_err(|err| match err {
+            AxError::IsADirectory => SyscallError::EACCES,
+            err => SyscallError::from(err),
+        })?)
+    };
+    execve_common(path, elf_data, argv, envp)
+}
+
+/// 执行文件描述符 `fd` 本身，即 fexecve
+///
+/// 文件必须是可执行的普通文件，如 memfd 或打开的程序文件。程序在 exec 之前被完整读入内存，
+/// 因此带有 CLOEXEC 的文件描述符也可以执行。与 Linux 一样，新程序的路径记为 `/dev/fd/N`
+fn execve_fd(fd: usize, argv: usize, envp: usize) -> SyscallResult {
+    let file = match current_process().fd_manager().fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    if file.get_type() != FileIOType::FileDesc || !file.executable() {
+        return Err(SyscallError::EACCES);
+    }
+    let elf_data = read_whole_file(file.as_ref()).map_err(|_| SyscallError::EIO)?;
+    execve_common(format!("/dev/fd/{}", fd), Some(elf_data), argv, envp)
+}
+
+/// 读取文件的全部内容，完成后恢复原来的读写位置
+fn read_whole_file(file: &dyn FileIO) -> AxResult<Vec<u8>> {
+    let old_offset = file.seek(SeekFrom::Current(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut data = Vec::new();
+    let mut buf = vec![0u8; 4096];
+    let result = loop {
+        match file.read(&mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(len) => data.extend_from_slice(&buf[..len]),
+            Err(err) => break Err(err),
+        }
+    };
+    file.seek(SeekFrom::Start(old_offset))?;
+    result.map(|_| data)
+}
+
+/// # Arguments
+/// * `path` - *const u8
+/// * `argv` - *const usize
+/// * `envp` - *const usize
+pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
+    let path = user_path(args[0])?;
+    let path = deal_with_path_str(AT_FDCWD, path, false).ok_or(SyscallError::ENOENT)?;
+    execve_path(path, false, args[1], args[2])
+}
+
+/// 与 execve 相同，但相对路径从 `dir_fd` 开始解析
+///
+/// 带有 AT_EMPTY_PATH 且路径为空时执行 `dir_fd` 本身，见 [`execve_fd`]。
+/// 带有 AT_SYMLINK_NOFOLLOW 且路径最后一级是符号链接时返回 ELOOP
+///
+/// # Arguments
+/// * `dir_fd` - usize
+/// * `path` - *const u8
+/// * `argv` - *const usize
+/// * `envp` - *const usize
+/// * `flags` - usize, 可以包含 AT_EMPTY_PATH 与 AT_SYMLINK_NOFOLLOW
+pub fn syscall_execveat(args: [usize; 6]) -> SyscallResult {
+    let dir_fd = args[0];
+    let flags = args[4];
+    if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let path = user_path(args[1])?;
+    if path.is_empty() {
+        if flags & AT_EMPTY_PATH == 0 {
+            return Err(SyscallError::ENOENT);
+        }
+        return execve_fd(dir_fd, args[2], args[3]);
+    }
+    let path = deal_with_path_str(dir_fd, path, false).ok_or(SyscallError::ENOENT)?;
+    execve_path(path, flags & AT_SYMLINK_NOFOLLOW != 0, args[2], args[3])
+}
+
 /// # Arguments for riscv
 /// * `flags` - usize
 /// * `user_stack` - usize
@@ -180,6 +273,7 @@ pub fn syscall_clone(args: [usize; 6]) -> SyscallResult {
         ctid = args[4];
     }
     let clone_flags = CloneFlags::from_bits((flags & !0x3f) as u32).unwrap();
+    check_clone_flags(clone_flags, tls)?;
 
     let stack = if user_stack == 0 {
         None
@@ -198,23 +292,38 @@ pub fn syscall_clone(args: [usize; 6]) -> SyscallResult {
     }
 }
 
+/// 检查 clone 标志的组合以及 CLONE_SETTLS 给出的线程指针是否合法
+///
+/// 同一进程的线程必须共享信号处理函数，而共享信号处理函数又要求共享地址空间，
+/// 否则处理函数的地址在另一方中没有意义
+fn check_clone_flags(flags: CloneFlags, tls: usize) -> Result<(), SyscallError> {
+    if flags.contains(CloneFlags::CLONE_SETTLS) && check_user_tls(tls).is_err() {
+        return Err(SyscallError::EINVAL);
+    }
+    if flags.contains(CloneFlags::CLONE_THREAD) && !flags.contains(CloneFlags::CLONE_SIGHAND) {
+        return Err(SyscallError::EINVAL);
+    }
+    if flags.contains(CloneFlags::CLONE_SIGHAND) && !flags.contains(CloneFlags::CLONE_VM) {
+        return Err(SyscallError::EINVAL);
+    }
+    Ok(())
+}
+
 /// 创建子进程的新函数，所有信息保存在 CloneArgs
 /// # Arguments
 /// * `clone_args` - *const CloneArgs
 /// * `size` - usize
 pub fn syscall_clone3(args: [usize; 6]) -> SyscallResult {
     let size = args[1];
-    let clone_args = args[0] as *const CloneArgs;
+    let clone_args = args[0];
     assert!(size >= size_of::<CloneArgs>());
 
     let curr_process = current_process();
 
-    let args = match curr_process.manual_alloc_type_for_lazy(clone_args) {
-        Ok(_) => unsafe { &*clone_args },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
+    let args: CloneArgs = copy_struct_from_user(clone_args)?;
 
     let clone_flags = CloneFlags::from_bits(args.flags as u32).unwrap();
+    check_clone_flags(clone_flags, args.tls as usize)?;
 
     let stack = if args.stack == 0 {
         None
@@ -242,56 +351,61 @@ pub fn syscall_clone3(args: [usize; 6]) -> SyscallResult {
 /// 创建一个子进程，挂起父进程，直到子进程exec或者exit，父进程才继续执行
 #[cfg(target_arch = "x86_64")]
 pub fn syscall_vfork() -> SyscallResult {
+    // CLONE_VFORK | SIGCHLD，父进程在 clone 中挂起
     let args: [usize; 6] = [0x4011, 0, 0, 0, 0, 0];
-    // TODO: check the correctness
-    syscall_clone(args).map(|new_task_id| {
-        let task_ref = axprocess::get_task_ref(new_task_id as u64).unwrap();
-        axtask::vfork_suspend(&task_ref);
-        new_task_id
-    })
+    syscall_clone(args)
 }
 
-/// 等待子进程完成任务，若子进程没有完成，则自身yield
+/// 等待子进程退出，若子进程尚未退出，则在子进程退出前睡眠
 /// 当前仅支持WNOHANG选项，即若未完成时则不予等待，直接返回0
+///
+/// `pid` 为 0 或小于 -1 时按进程组匹配子进程，见 [`wait_pid`]
+///
+/// 退出状态按 Linux 的 W* 宏编码写入 `wstatus`，`rusage` 非空时写入子进程的资源使用情况
 /// # Arguments
 /// * `pid` - isize
-/// * `exit_code_ptr` - *mut i32
+/// * `wstatus` - *mut i32
 /// * `option` - WaitFlags
+/// * `rusage` - *mut Rusage
 pub fn syscall_wait4(args: [usize; 6]) -> SyscallResult {
     let pid = args[0] as isize;
-    let exit_code_ptr = args[1] as *mut i32;
-    let option = WaitFlags::from_bits(args[2] as u32).unwrap();
+    let wstatus = args[1];
+    let option = WaitFlags::from_bits_truncate(args[2] as u32);
+    let rusage = args[3];
+    let process = current_process();
     loop {
-        let answer = unsafe { wait_pid(pid, exit_code_ptr) };
-        match answer {
-            Ok(pid) => {
-                return Ok(pid as isize);
+        // 先记录退出次数再检查子进程，避免错过检查之后、睡眠之前退出的子进程
+        let seen = process.child_exits();
+        match wait_pid(pid) {
+            Ok(child) => {
+                if wstatus != 0 {
+                    copy_struct_to_user(wstatus, child.wait_status())?;
+                }
+                if rusage != 0 {
+                    let (utime, stime) = child.exit_times();
+                    let usage = Rusage {
+                        ru_utime: TimeVal::from_duration(utime),
+                        ru_stime: TimeVal::from_duration(stime),
+                        ru_maxrss: (child.peak_rss_pages() * PAGE_SIZE_4K / 1024) as isize,
+                        ..Default::default()
+                    };
+                    copy_struct_to_user(rusage, usage)?;
+                }
+                return Ok(child.pid() as isize);
             }
-            Err(status) => {
-                match status {
-                    WaitStatus::NotExist => {
-                        return Err(SyscallError::EPERM);
-                    }
-                    WaitStatus::Running => {
-                        if option.contains(WaitFlags::WNOHANG) {
-                            // 不予等待，直接返回0
-                            return Ok(0);
-                        } else {
-                            // wait回来之后，如果还需要wait，先检查是否有信号未处理
-
-                            if current_process().have_signals().is_some() {
-                                return Err(SyscallError::EINTR);
-                            }
-                            // 执行yield操作，切换任务
-                            yield_now_task();
-                        }
-                    }
-                    _ => {
-                        panic!("Shouldn't reach here!");
-                    }
+            Err(WaitStatus::Running) => {
+                if option.contains(WaitFlags::WNOHANG) {
+                    // 不予等待，直接返回0
+                    return Ok(0);
                 }
+                // wait回来之后，如果还需要wait，先检查是否有信号未处理
+                if process.have_signals().is_some() {
+                    return Err(SyscallError::EINTR);
+                }
+                process.wait_child_exit(seen);
             }
-        };
+            Err(_) => return Err(SyscallError::ECHILD),
+        }
     }
 }
 
@@ -302,43 +416,13 @@ pub fn syscall_yield() -> SyscallResult {
 }
 
 /// 当前任务进入睡眠，req指定了睡眠的时间
-/// rem存储当睡眠完成时，真实睡眠时间和预期睡眠时间之间的差值
+/// 睡眠被信号打断时返回 EINTR，rem存储尚未睡眠的剩余时间
 /// # Arguments
 /// * `req` - *const TimeSecs
 /// * `rem` - *mut TimeSecs
 pub fn syscall_sleep(args: [usize; 6]) -> SyscallResult {
-    let req = args[0] as *const TimeSecs;
-    let rem = args[1] as *mut TimeSecs;
-    let req_time = unsafe { *req };
-    let start_to_sleep = current_time();
-    // info!("sleep: req_time = {:?}", req_time);
-    let dur = Duration::new(req_time.tv_sec as u64, req_time.tv_nsec as u32);
-    sleep_now_task(dur);
-    // 若被唤醒时时间小于请求时间，则将剩余时间写入rem
-    let sleep_time = current_time() - start_to_sleep;
-    if rem as usize != 0 {
-        if sleep_time < dur {
-            let delta = (dur - sleep_time).as_nanos() as usize;
-            unsafe {
-                *rem = TimeSecs {
-                    tv_sec: delta / 1_000_000_000,
-                    tv_nsec: delta % 1_000_000_000,
-                }
-            };
-        } else {
-            unsafe {
-                *rem = TimeSecs {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                }
-            };
-        }
-    }
-
-    if current_process().have_signals().is_some() {
-        return Err(SyscallError::EINTR);
-    }
-    Ok(0)
+    let dur = sleep_duration(args[0])?;
+    sleep_until_deadline(current_time() + dur, args[1])
 }
 
 /// 设置tid对应的指针
@@ -363,47 +447,50 @@ pub fn syscall_set_tid_address(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
     let pid = args[0];
     let resource = args[1] as i32;
-    let new_limit = args[2] as *const RLimit;
-    let old_limit = args[3] as *mut RLimit;
+    let new_limit = args[2];
+    let old_limit = args[3];
     // 当pid不为0，其实没有权利去修改其他的进程的资源限制
     let curr_process = current_process();
     if pid == 0 || pid == curr_process.pid() as usize {
         match resource {
             RLIMIT_STACK => {
-                if old_limit as usize != 0 {
-                    unsafe {
-                        *old_limit = RLimit {
+                if old_limit != 0 {
+                    copy_struct_to_user(
+                        old_limit,
+                        RLimit {
                             rlim_cur: TASK_STACK_SIZE as u64,
                             rlim_max: TASK_STACK_SIZE as u64,
-                        };
-                    }
+                        },
+                    )?;
                 }
             }
             RLIMIT_NOFILE => {
                 // 仅支持修改最大文件数
-                if old_limit as usize != 0 {
-                    let limit = curr_process.fd_manager.get_limit();
-                    unsafe {
-                        *old_limit = RLimit {
+                if old_limit != 0 {
+                    let limit = curr_process.fd_manager().get_limit();
+                    copy_struct_to_user(
+                        old_limit,
+                        RLimit {
                             rlim_cur: limit as u64,
                             rlim_max: limit as u64,
-                        };
-                    }
+                        },
+                    )?;
                 }
-                if new_limit as usize != 0 {
-                    let new_limit = unsafe { (*new_limit).rlim_cur };
-                    curr_process.fd_manager.set_limit(new_limit);
+                if new_limit != 0 {
+                    let new_limit = copy_struct_from_user::<RLimit>(new_limit)?.rlim_cur;
+                    curr_process.fd_manager().set_limit(new_limit);
                 }
             }
             RLIMIT_AS => {
                 const USER_MEMORY_LIMIT: usize = 0xffff_ffff;
-                if old_limit as usize != 0 {
-                    unsafe {
-                        *old_limit = RLimit {
+                if old_limit != 0 {
+                    copy_struct_to_user(
+                        old_limit,
+                        RLimit {
                             rlim_cur: USER_MEMORY_LIMIT as u64,
                             rlim_max: USER_MEMORY_LIMIT as u64,
-                        };
-                    }
+                        },
+                    )?;
                 }
             }
             _ => {}
@@ -412,16 +499,43 @@ pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
     Ok(0)
 }
 
-/// not support
-pub fn syscall_getpgid() -> SyscallResult {
-    Ok(0)
+/// 获取进程组号
+/// # Arguments
+/// * `pid`: usize, 为 0 时表示当前进程
+pub fn syscall_getpgid(args: [usize; 6]) -> SyscallResult {
+    let pid = args[0] as u64;
+    if pid == 0 {
+        return Ok(current_process().pgid() as isize);
+    }
+    PID2PC
+        .lock()
+        .get(&pid)
+        .map(|process| process.pgid() as isize)
+        .ok_or(SyscallError::ESRCH)
 }
 
+/// 设置进程组号，只能设置当前进程或其子进程
 /// # Arguments
-/// * `pgid`: usize
+/// * `pid`: usize, 为 0 时表示当前进程
+/// * `pgid`: usize, 为 0 时表示以 `pid` 作为进程组号
 pub fn syscall_setpgid(args: [usize; 6]) -> SyscallResult {
-    let pgid = args[0];
-    info!("not support setpgid, try to set {}", pgid);
+    let (pid, pgid) = (args[0] as i32, args[1] as i32);
+    if pid < 0 || pgid < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let curr = current_process();
+    let target = if pid == 0 || pid as u64 == curr.pid() {
+        curr
+    } else {
+        curr.children
+            .lock()
+            .iter()
+            .find(|child| child.pid() == pid as u64)
+            .cloned()
+            .ok_or(SyscallError::ESRCH)?
+    };
+    let pgid = if pgid == 0 { target.pid() } else { pgid as u64 };
+    target.set_pgid(pgid);
     Ok(0)
 }
 
@@ -439,7 +553,29 @@ pub fn syscall_getppid() -> SyscallResult {
 /// * `new_mask` - i32
 pub fn syscall_umask(args: [usize; 6]) -> SyscallResult {
     let new_mask = args[0] as i32;
-    Ok(current_process().fd_manager.set_mask(new_mask) as isize)
+    Ok(current_process().fs_context.set_umask(new_mask) as isize)
+}
+
+/// 开启或关闭进程记账
+/// # Arguments
+/// * `path` - *const u8, 记账文件的路径，为空指针时关闭记账
+///
+/// 开启后每个进程退出时都会向该文件追加一条 acct_v3 格式的记录
+pub fn syscall_acct(args: [usize; 6]) -> SyscallResult {
+    let path = args[0] as *const u8;
+    if path.is_null() {
+        axprocess::acct::disable();
+        return Ok(0);
+    }
+    let path = resolve_path_at(AT_FDCWD, user_path(path as usize)?, false)?;
+    if path.is_dir() {
+        return Err(SyscallError::EISDIR);
+    }
+    match axprocess::acct::enable(path.path()) {
+        Ok(()) => Ok(0),
+        Err(AxError::NotFound) => Err(SyscallError::ENOENT),
+        Err(_) => Err(SyscallError::EACCES),
+    }
 }
 
 /// 获取用户 id。在实现多用户权限前默认为最高权限
@@ -484,13 +620,15 @@ pub fn syscall_setsid() -> SyscallResult {
     // 从当前 process 的 thread group 中移除 calling thread
     process.tasks.lock().retain(|t| t.id().as_u64() != task_id);
 
-    // 新建 process group 并加入
+    // 新建 process group 并加入，新会话没有控制终端；文件描述符表与文件系统上下文各复制一份，
+    // 不再与原来的进程共享
     let new_process = Process::new(
         TaskId::new().as_u64(),
         process.get_parent(),
-        Mutex::new(process.memory_set.lock().clone()),
+        SpinWaitNoIrq::new(process.memory_set.lock().clone()),
         process.get_heap_bottom(),
-        process.fd_manager.fd_table.lock().clone(),
+        Arc::new(process.fd_manager().deep_copy()),
+        Arc::new(process.fs_context.deep_copy()),
     );
 
     new_process
@@ -523,21 +661,20 @@ pub fn syscall_arch_prctl(args: [usize; 6]) -> SyscallResult {
     #define ARCH_GET_GS			0x1004
     */
     let code = args[0];
-    let addr = args[1] as *mut usize;
+    let addr = args[1];
     match code {
         0x1002 => {
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                axhal::arch::write_thread_pointer(*addr);
-                // *(read_thread_pointer() as *mut usize) = addr;
-            }
+            // 参数本身就是新的 fs_base
+            let current_task = current_task();
+            let kstack_top = current_task.get_kernel_stack_top().unwrap();
+            let mut trap_frame = axhal::arch::read_trapframe_from_kstack(kstack_top);
+            axprocess::set_user_tls(current_task.as_task_ref(), &mut trap_frame, addr)
+                .map_err(|_| SyscallError::EPERM)?;
             Ok(0)
         }
         0x1003 => {
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                *addr = *(axhal::arch::read_thread_pointer() as *mut usize);
-            }
+            let fs_base: usize = copy_struct_from_user(axhal::arch::read_thread_pointer())?;
+            copy_struct_to_user(addr, fs_base)?;
             Ok(0)
         }
         0x1001 | 0x1004 => todo!(),
@@ -560,12 +697,10 @@ pub fn syscall_fork() -> SyscallResult {
 /// * `arg2` - *mut u8
 #[cfg(target_arch = "x86_64")]
 pub fn syscall_prctl(args: [usize; 6]) -> SyscallResult {
-    use core::ptr::slice_from_raw_parts_mut;
-
     use crate::{PrctlOption, PR_NAME_SIZE};
 
     let option = args[0];
-    let arg2 = args[1] as *mut u8;
+    let arg2 = args[1];
     match PrctlOption::try_from(option) {
         Ok(PrctlOption::PR_GET_NAME) => {
             // 获取进程名称。
@@ -573,22 +708,25 @@ pub fn syscall_prctl(args: [usize; 6]) -> SyscallResult {
             process_name += "\0";
             // [syscall 定义](https://man7.org/linux/man-pages/man2/prctl.2.html)要求 NAME 应该不超过 16 Byte
             process_name.truncate(PR_NAME_SIZE);
-            // 把 arg2 转换成可写的 buffer
-            if current_process()
-                .manual_alloc_for_lazy((arg2 as usize).into())
-                .is_ok()
-            // 直接访问前需要确保地址已经被分配
-            {
-                unsafe {
-                    let name = &mut *slice_from_raw_parts_mut(arg2, PR_NAME_SIZE);
-                    name[..process_name.len()].copy_from_slice(process_name.as_bytes());
+            copy_to_user(arg2, process_name.as_bytes())?;
+            Ok(0)
+        }
+        Ok(PrctlOption::PR_SET_NAME) => {
+            // 设置线程名称，与 /proc/<pid>/task/<tid>/comm 中的内容一致
+            let name: [u8; PR_NAME_SIZE] = copy_struct_from_user(arg2)?;
+            // 名称最长为 PR_NAME_SIZE - 1 字节，超出部分被截断
+            let len = name[..PR_NAME_SIZE - 1]
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(PR_NAME_SIZE - 1);
+            match core::str::from_utf8(&name[..len]) {
+                Ok(name) => {
+                    current_task().set_name(name);
+                    Ok(0)
                 }
-                Ok(0)
-            } else {
-                Err(SyscallError::EINVAL)
+                Err(_) => Err(SyscallError::EINVAL),
             }
         }
-        Ok(PrctlOption::PR_SET_NAME) => Ok(0),
         _ => Ok(0),
     }
 }
@@ -1,29 +1,34 @@
-use core::{slice::from_raw_parts_mut, time::Duration};
+use core::time::Duration;
 
-use axhal::time::{current_time, current_time_nanos, nanos_to_ticks, NANOS_PER_SEC};
+use axhal::mem::PAGE_SIZE_4K;
+use axhal::time::{
+    current_time, current_time_nanos, epoch_offset_nanos, nanos_to_ticks, wall_time, NANOS_PER_SEC,
+};
 
-use axprocess::{current_process, current_task, time_stat_output};
-use rand::{rngs::SmallRng, Fill, SeedableRng};
+use axprocess::uaccess::{copy_struct_from_user, copy_struct_to_user, copy_to_user};
+use axprocess::{current_process, current_task, time_stat_output, PID2PC};
 
 use crate::{
-    ClockId, ITimerVal, RusageFlags, SysInfo, SyscallError, SyscallResult, TimeSecs, TimeVal, Tms,
-    UtsName,
+    ClockId, ITimerVal, Rusage, RusageFlags, SysInfo, SyscallError, SyscallResult, TimeSecs,
+    TimeVal, Tms, UtsName, GRND_NONBLOCK, NSEC_PER_SEC,
 };
 
 /// 返回值为当前经过的时钟中断数
 /// # Arguments
 /// * `tms` - *mut Tms
 pub fn syscall_time(args: [usize; 6]) -> SyscallResult {
-    let tms = args[0] as *mut Tms;
-    let (_, utime_us, _, stime_us) = time_stat_output();
-    unsafe {
-        *tms = Tms {
+    let tms = args[0];
+    let (utime, stime) = time_stat_output();
+    let (utime_us, stime_us) = (utime.as_micros() as usize, stime.as_micros() as usize);
+    copy_struct_to_user(
+        tms,
+        Tms {
             tms_utime: utime_us,
             tms_stime: stime_us,
             tms_cutime: utime_us,
             tms_cstime: stime_us,
-        }
-    }
+        },
+    )?;
     Ok(nanos_to_ticks(current_time_nanos()) as isize)
 }
 
@@ -31,27 +36,44 @@ pub fn syscall_time(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * `ts` - *mut TimeVal
 pub fn syscall_get_time_of_day(args: [usize; 6]) -> SyscallResult {
-    let ts = args[0] as *mut TimeVal;
-    let current_us = current_time_nanos() as usize / 1000;
-    unsafe {
-        *ts = TimeVal {
-            sec: current_us / 1_000_000,
-            usec: current_us % 1_000_000,
+    let ts = args[0];
+    copy_struct_to_user(ts, TimeVal::from_duration(wall_time()))?;
+    Ok(0)
+}
+
+/// 读取 `id` 对应时钟的当前时间
+///
+/// CLOCK_REALTIME 为自 Unix 纪元以来的墙上时间，单调时钟均为自启动以来的系统时钟，
+/// CPU 时间时钟为用户态与内核态时间之和
+fn clock_time(id: ClockId) -> Duration {
+    match id {
+        ClockId::CLOCK_REALTIME => wall_time(),
+        ClockId::CLOCK_MONOTONIC | ClockId::CLOCK_MONOTONIC_RAW | ClockId::CLOCK_BOOTTIME => {
+            current_time()
+        }
+        ClockId::CLOCK_PROCESS_CPUTIME_ID => current_process()
+            .tasks
+            .lock()
+            .iter()
+            .map(|task| {
+                let (utime, stime) = task.time_stat_output();
+                utime + stime
+            })
+            .sum(),
+        ClockId::CLOCK_THREAD_CPUTIME_ID => {
+            let (utime, stime) = current_task().time_stat_output();
+            utime + stime
         }
     }
-    Ok(0)
 }
 
 /// 用于获取当前系统时间并且存储在对应的结构体中
 /// # Arguments
-/// * `clock_id` - usize
+/// * `clock_id` - usize, 时钟种类，对应结构体为ClockId，不支持的时钟返回 EINVAL
 /// * `ts` - *mut TimeSecs
 pub fn syscall_clock_get_time(args: [usize; 6]) -> SyscallResult {
-    let _clock_id = args[0];
-    let ts = args[1] as *mut TimeSecs;
-    unsafe {
-        (*ts) = TimeSecs::now();
-    }
+    let id = ClockId::try_from(args[0]).map_err(|_| SyscallError::EINVAL)?;
+    copy_struct_to_user(args[1], TimeSecs::from_duration(clock_time(id)))?;
     Ok(0)
 }
 
@@ -59,30 +81,25 @@ pub fn syscall_clock_get_time(args: [usize; 6]) -> SyscallResult {
 /// # Arguments
 /// * `uts` - *mut UtsName
 pub fn syscall_uname(args: [usize; 6]) -> SyscallResult {
-    let uts = args[0] as *mut UtsName;
-    unsafe {
-        *uts = UtsName::default();
-    }
+    copy_struct_to_user(args[0], UtsName::default())?;
     Ok(0)
 }
 
-/// 获取系统的启动时间和内存信息，当前仅支持启动时间
+/// 获取系统的启动时间和内存信息
+///
+/// 内存大小以字节为单位，freeram 为页分配器中空闲的物理页，没有 swap 与高端内存
 /// # Arguments
 /// * `info` - *mut SysInfo
 pub fn syscall_sysinfo(args: [usize; 6]) -> SyscallResult {
-    let info = args[0] as *mut SysInfo;
-    let process = current_process();
-    if process
-        .manual_alloc_type_for_lazy(info as *const SysInfo)
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-
-    unsafe {
-        // 获取以秒为单位的时间
-        (*info).uptime = (current_time_nanos() / NANOS_PER_SEC) as isize;
-    }
+    let info = SysInfo {
+        uptime: (current_time_nanos() / NANOS_PER_SEC) as isize,
+        totalram: axmem::total_pages() * PAGE_SIZE_4K,
+        freeram: axmem::free_pages() * PAGE_SIZE_4K,
+        procs: PID2PC.lock().len() as u16,
+        mem_unit: 1,
+        ..Default::default()
+    };
+    copy_struct_to_user(args[0], info)?;
     Ok(0)
 }
 
@@ -92,35 +109,30 @@ pub fn syscall_sysinfo(args: [usize; 6]) -> SyscallResult {
 /// * `old_value` - *mut ITimerVal
 pub fn syscall_settimer(args: [usize; 6]) -> SyscallResult {
     let which = args[0];
-    let new_value = args[1] as *const ITimerVal;
-    let old_value = args[2] as *mut ITimerVal;
-    let process = current_process();
+    let new_value = args[1];
+    let old_value = args[2];
 
-    if new_value.is_null() {
+    if new_value == 0 {
         return Err(SyscallError::EFAULT);
     }
 
-    let new_value = match process.manual_alloc_type_for_lazy(new_value) {
-        Ok(_) => unsafe { &*new_value },
-        Err(_) => return Err(SyscallError::EFAULT),
-    };
-
-    if !old_value.is_null() {
-        if process.manual_alloc_type_for_lazy(old_value).is_err() {
-            return Err(SyscallError::EFAULT);
-        }
-
-        let (time_interval_us, time_remained_us) = current_task().timer_output();
-        unsafe {
-            (*old_value).it_interval = TimeVal::from_micro(time_interval_us);
-            (*old_value).it_value = TimeVal::from_micro(time_remained_us);
-        }
+    let new_value: ITimerVal = copy_struct_from_user(new_value)?;
+
+    if old_value != 0 {
+        let (time_interval, time_remained) = current_task().timer_output();
+        copy_struct_to_user(
+            old_value,
+            ITimerVal {
+                it_interval: TimeVal::from_duration(time_interval),
+                it_value: TimeVal::from_duration(time_remained),
+            },
+        )?;
     }
-    let (time_interval_ns, time_remained_ns) = (
-        new_value.it_interval.turn_to_nanos(),
-        new_value.it_value.turn_to_nanos(),
+    let (time_interval, time_remained) = (
+        new_value.it_interval.to_duration(),
+        new_value.it_value.to_duration(),
     );
-    if current_task().set_timer(time_interval_ns, time_remained_ns, which) {
+    if current_task().set_timer(time_interval, time_remained, which) {
         Ok(0)
     } else {
         // 说明which参数错误
@@ -133,45 +145,53 @@ pub fn syscall_settimer(args: [usize; 6]) -> SyscallResult {
 /// * `value` - *mut ITimerVal
 pub fn syscall_gettimer(args: [usize; 6]) -> SyscallResult {
     let _which = args[0];
-    let value = args[1] as *mut ITimerVal;
-    let process = current_process();
-    if process
-        .manual_alloc_type_for_lazy(value as *const ITimerVal)
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
-    }
-    let (time_interval_us, time_remained_us) = current_task().timer_output();
-    unsafe {
-        (*value).it_interval = TimeVal::from_micro(time_interval_us);
-        (*value).it_value = TimeVal::from_micro(time_remained_us);
-    }
+    let value = args[1];
+    let (time_interval, time_remained) = current_task().timer_output();
+    copy_struct_to_user(
+        value,
+        ITimerVal {
+            it_interval: TimeVal::from_duration(time_interval),
+            it_value: TimeVal::from_duration(time_remained),
+        },
+    )?;
     Ok(0)
 }
 
+/// 获取资源使用情况
+///
+/// 目前 RUSAGE_CHILDREN 返回的也是当前任务的时间；启用 `sched_trace` 时，
+/// RUSAGE_SELF 与 RUSAGE_THREAD 还会报告整个进程或当前线程的上下文切换次数
 /// # Arguments
 /// * `who` - i32
-/// * `utime` - *mut TimeVal
+/// * `usage` - *mut Rusage
 pub fn syscall_getrusage(args: [usize; 6]) -> SyscallResult {
-    let who = args[0] as i32;
-    let utime = args[1] as *mut TimeVal;
-    let stime: *mut TimeVal = unsafe { utime.add(1) };
-    let process = current_process();
-    if process.manual_alloc_type_for_lazy(utime).is_err()
-        || process.manual_alloc_type_for_lazy(stime).is_err()
-    {
-        return Err(SyscallError::EFAULT);
+    let who = RusageFlags::from(args[0] as i32).ok_or(SyscallError::EINVAL)?;
+    let usage = args[1];
+    let (utime, stime) = time_stat_output();
+    let mut rusage = Rusage {
+        ru_utime: TimeVal::from_duration(utime),
+        ru_stime: TimeVal::from_duration(stime),
+        ..Default::default()
+    };
+    if !matches!(who, RusageFlags::RUSAGE_CHILDREN) {
+        let process = current_process();
+        process.update_peak_rss();
+        rusage.ru_maxrss = (process.peak_rss_pages() * PAGE_SIZE_4K / 1024) as isize;
     }
-    if RusageFlags::from(who).is_some() {
-        let (_, utime_us, _, stime_us) = time_stat_output();
-        unsafe {
-            *utime = TimeVal::from_micro(utime_us);
-            *stime = TimeVal::from_micro(stime_us);
+    #[cfg(feature = "sched_trace")]
+    {
+        let tasks = match who {
+            RusageFlags::RUSAGE_SELF => current_process().tasks.lock().clone(),
+            RusageFlags::RUSAGE_THREAD => alloc::vec![current_task().as_task_ref().clone()],
+            RusageFlags::RUSAGE_CHILDREN => alloc::vec::Vec::new(),
+        };
+        for task in tasks {
+            rusage.ru_nvcsw += task.sched_stat().voluntary_switches() as isize;
+            rusage.ru_nivcsw += task.sched_stat().involuntary_switches() as isize;
         }
-        Ok(0)
-    } else {
-        Err(SyscallError::EINVAL)
     }
+    copy_struct_to_user(usage, rusage)?;
+    Ok(0)
 }
 
 /// # Arguments
@@ -179,64 +199,81 @@ pub fn syscall_getrusage(args: [usize; 6]) -> SyscallResult {
 /// * `len` - usize
 /// * `flags` - usize
 pub fn syscall_getrandom(args: [usize; 6]) -> SyscallResult {
-    let buf = args[0] as *mut u8;
+    let buf = args[0];
     let len = args[1];
-    let _flags = args[2];
-    let process = current_process();
+    let flags = args[2];
 
-    if process
-        .manual_alloc_range_for_lazy(
-            (buf as usize).into(),
-            unsafe { buf.add(len) as usize }.into(),
-        )
-        .is_err()
-    {
-        return Err(SyscallError::EFAULT);
+    // GRND_RANDOM 不区分随机数源，统一使用内核随机数生成器
+    if !axrandom::is_seeded() {
+        // 随机数生成器尚未初始化
+        if flags & GRND_NONBLOCK != 0 {
+            return Err(SyscallError::EAGAIN);
+        }
+        axrandom::wait_for_seed();
     }
 
-    let buf = unsafe { from_raw_parts_mut(buf, len) };
-
-    // TODO: flags
-    // - GRND_RANDOM: use /dev/random or /dev/urandom
-    // - GRND_NONBLOCK: EAGAIN when block
-    let mut rng = SmallRng::from_seed([0; 32]);
-    buf.try_fill(&mut rng).unwrap();
-
-    Ok(buf.len() as isize)
+    // 按块生成随机数并复制到用户空间，中途地址失效时返回已经写入的字节数
+    let mut chunk = [0u8; 256];
+    let mut done = 0;
+    while done < len {
+        let chunk_len = (len - done).min(chunk.len());
+        axrandom::fill_bytes(&mut chunk[..chunk_len]);
+        match copy_to_user(buf + done, &chunk[..chunk_len]) {
+            Ok(()) => done += chunk_len,
+            Err(_) if done > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(done as isize)
 }
 
 /// # 获取时钟精度
 ///
 /// # Arguments
-/// * `id` - usize, 时钟种类,当前仅支持CLOCK_MONOTONIC
-/// * `res` - *mut TimeSecs, 存储时钟精度的结构体的地址
+/// * `id` - usize, 时钟种类，对应结构体为ClockId
+/// * `res` - *mut TimeSecs, 存储时钟精度的结构体的地址，为空时只检查时钟是否合法
 pub fn syscall_clock_getres(args: [usize; 6]) -> SyscallResult {
-    let id = args[0];
-    let res = args[1] as *mut TimeSecs;
-    let id = if let Ok(opt) = ClockId::try_from(id) {
-        opt
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
-
-    if id != ClockId::CLOCK_MONOTONIC {
-        // 暂时不支持其他类型
-        return Err(SyscallError::EINVAL);
+    ClockId::try_from(args[0]).map_err(|_| SyscallError::EINVAL)?;
+    let res = args[1];
+    if res != 0 {
+        copy_struct_to_user(
+            res,
+            TimeSecs {
+                tv_nsec: 1,
+                tv_sec: 0,
+            },
+        )?;
     }
+    Ok(0)
+}
 
-    let process = current_process();
-    if process.manual_alloc_type_for_lazy(res).is_err() {
-        return Err(SyscallError::EFAULT);
+/// 读取用户传入的睡眠时间，tv_sec 为负或 tv_nsec 超出范围时返回 EINVAL
+pub(crate) fn sleep_duration(addr: usize) -> Result<Duration, SyscallError> {
+    let time: TimeSecs = copy_struct_from_user(addr)?;
+    if (time.tv_sec as isize) < 0 || time.tv_nsec >= NSEC_PER_SEC {
+        return Err(SyscallError::EINVAL);
     }
+    Ok(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
 
-    unsafe {
-        (*res) = TimeSecs {
-            tv_nsec: 1,
-            tv_sec: 0,
-        };
+/// 睡眠到 `deadline`，期间让出 CPU 给其他任务
+///
+/// 被信号打断时返回 EINTR，若 `rem` 不为 0，则将剩余的睡眠时间写入 `rem`
+pub(crate) fn sleep_until_deadline(deadline: Duration, rem: usize) -> SyscallResult {
+    loop {
+        axtask::sleep_until(deadline);
+        let now = current_time();
+        if now >= deadline {
+            return Ok(0);
+        }
+        // 提前醒来且有待处理的信号，说明睡眠被信号打断
+        if current_process().have_signals().is_some() {
+            if rem != 0 {
+                copy_struct_to_user(rem, TimeSecs::from_duration(deadline - now))?;
+            }
+            return Err(SyscallError::EINTR);
+        }
     }
-
-    Ok(0)
 }
 
 /// # 指定任务进行睡眠
@@ -251,55 +288,30 @@ pub fn syscall_clock_getres(args: [usize; 6]) -> SyscallResult {
 /// * remain: *mut TimeSecs存储剩余睡眠时间。当任务提前醒来时,如果flags不为绝对时间,且remain不为空,则将剩余存储时间存进remain所指向地址。
 ///
 /// 若睡眠被信号处理打断或者遇到未知错误，则返回对应错误码
+///
+/// 各个时钟都以系统时钟计时，CLOCK_REALTIME 的绝对时间会先换算为系统时钟的时间
 pub fn syscall_clock_nanosleep(args: [usize; 6]) -> SyscallResult {
     let id = args[0];
     let flags = args[1];
-    let request = args[2] as *const TimeSecs;
-    let remain = args[3] as *mut TimeSecs;
+    let request = args[2];
+    let remain = args[3];
     const TIMER_ABSTIME: usize = 1;
-    let id = if let Ok(opt) = ClockId::try_from(id) {
-        opt
-    } else {
-        return Err(SyscallError::EINVAL);
-    };
-
-    if id != ClockId::CLOCK_MONOTONIC {
-        // 暂时不支持其他类型
-        return Err(SyscallError::EINVAL);
-    }
-
-    let process = current_process();
-
-    if process.manual_alloc_type_for_lazy(request).is_err() {
-        return Err(SyscallError::EFAULT);
-    }
-    let request_time = unsafe { *request };
-    let request_time = Duration::new(request_time.tv_sec as u64, request_time.tv_nsec as u32);
-    let deadline = if flags != TIMER_ABSTIME {
-        current_time() + request_time
-    } else {
-        if request_time < current_time() {
-            return Ok(0);
+    let id = match ClockId::try_from(id) {
+        Ok(ClockId::CLOCK_PROCESS_CPUTIME_ID) | Ok(ClockId::CLOCK_THREAD_CPUTIME_ID) | Err(_) => {
+            // CPU 时间等其他时钟暂不支持
+            return Err(SyscallError::EINVAL);
         }
-        request_time
+        Ok(id) => id,
     };
-
-    axtask::sleep_until(deadline);
-
-    let current_time = current_time();
-    if current_time < deadline && !remain.is_null() {
-        if process.manual_alloc_type_for_lazy(remain).is_err() {
-            return Err(SyscallError::EFAULT);
+    let request_time = sleep_duration(request)?;
+    if flags & TIMER_ABSTIME != 0 {
+        let deadline = if id == ClockId::CLOCK_REALTIME {
+            request_time.saturating_sub(Duration::from_nanos(epoch_offset_nanos()))
         } else {
-            let delta = (deadline - current_time).as_nanos() as usize;
-            unsafe {
-                *remain = TimeSecs {
-                    tv_sec: delta / 1_000_000_000,
-                    tv_nsec: delta % 1_000_000_000,
-                }
-            };
-            return Err(SyscallError::EINTR);
-        }
+            request_time
+        };
+        // 绝对时间不需要写回剩余时间
+        return sleep_until_deadline(deadline, 0);
     }
-    Ok(0)
+    sleep_until_deadline(current_time() + request_time, remain)
 }
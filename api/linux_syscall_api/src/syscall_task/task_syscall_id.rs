@@ -12,6 +12,7 @@ numeric_enum_macro::numeric_enum! {
 #[allow(missing_docs)]
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum TaskSyscallId {
+    ACCT = 89,
     EXIT = 93,
     EXIT_GROUP = 94,
     SET_TID_ADDRESS = 96,
@@ -46,7 +47,7 @@ pub enum TaskSyscallId {
     CLONE = 220,
     CLONE3 = 435,
     EXECVE = 221,
-    MADVICE = 233,
+    EXECVEAT = 281,
     WAIT4 = 260,
     GETRANDOM = 278,
     SCHED_YIELD = 124,
@@ -96,6 +97,7 @@ numeric_enum_macro::numeric_enum! {
         SETSID = 112,
         GETRUSAGE = 98,
         UMASK = 95,
+        ACCT = 163,
         PRCTL = 157,
         GETPID = 39,
         GETPPID = 110,
@@ -110,7 +112,7 @@ numeric_enum_macro::numeric_enum! {
         CLONE = 56,
         CLONE3 = 435,
         EXECVE = 59,
-        MADVICE = 28,
+        EXECVEAT = 322,
         WAIT4 = 61,
         GETRANDOM = 318,
         SCHED_YIELD = 24,
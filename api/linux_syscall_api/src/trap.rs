@@ -37,11 +37,16 @@ pub fn handle_irq(irq_num: usize, from_user: bool) {
 /// * `syscall_id` - The id of the syscall
 ///
 /// * `args` - The arguments of the syscall
-pub fn handle_syscall(syscall_id: usize, args: [usize; 6]) -> isize {
+///
+/// # Return
+///
+/// The value to be written into the return register of the trap frame. An error is
+/// returned as the sign-extended negative errno.
+pub fn handle_syscall(syscall_id: usize, args: [usize; 6]) -> usize {
     time_stat_from_user_to_kernel();
     let ans = syscall(syscall_id, args);
     time_stat_from_kernel_to_user();
-    ans
+    ans as usize
 }
 
 /// Handle the page fault exception
@@ -0,0 +1,100 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#ifndef SYS_faccessat2
+#define SYS_faccessat2 439
+#endif
+
+#define ROOT "/tmp/access_test"
+
+static void make_file(const char *path, mode_t mode)
+{
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "create file");
+    if (fd >= 0)
+        close(fd);
+    check(chmod(path, mode) == 0, "chmod");
+}
+
+// 与 shell 相同的方式在 PATH 中查找命令：可执行的普通文件
+static int search_path(const char *path, const char *cmd, char *found)
+{
+    char entries[PATH_MAX];
+    strcpy(entries, path);
+    for (char *dir = strtok(entries, ":"); dir; dir = strtok(NULL, ":")) {
+        struct stat st;
+        snprintf(found, PATH_MAX, "%s/%s", dir, cmd);
+        if (access(found, X_OK) == 0 && stat(found, &st) == 0 && S_ISREG(st.st_mode))
+            return 0;
+    }
+    return -1;
+}
+
+int main(void)
+{
+    mkdir(ROOT, 0755);
+    // a：同名目录，b：不可执行的文件，c：不存在，d 与 e：可执行的文件
+    mkdir(ROOT "/a", 0755);
+    mkdir(ROOT "/a/cmd", 0755);
+    mkdir(ROOT "/b", 0755);
+    make_file(ROOT "/b/cmd", 0644);
+    mkdir(ROOT "/d", 0755);
+    make_file(ROOT "/d/cmd", 0755);
+    mkdir(ROOT "/e", 0755);
+    make_file(ROOT "/e/cmd", 0700);
+
+    struct stat st;
+    check(stat(ROOT "/d/cmd", &st) == 0 && (st.st_mode & 0777) == 0755, "chmod is persistent");
+    check(stat(ROOT "/b/cmd", &st) == 0 && (st.st_mode & 0111) == 0, "non-executable mode");
+
+    errno = 0;
+    check(access(ROOT "/c/cmd", X_OK) == -1 && errno == ENOENT, "missing entry is ENOENT");
+    errno = 0;
+    check(access(ROOT "/b/cmd", X_OK) == -1 && errno == EACCES, "non-executable is EACCES");
+    check(access(ROOT "/b/cmd", F_OK) == 0, "non-executable exists");
+    check(access(ROOT "/b/cmd", R_OK | W_OK) == 0, "non-executable is readable and writable");
+    check(access(ROOT "/a/cmd", X_OK) == 0, "directory is searchable");
+    check(access(ROOT "/d/cmd", X_OK) == 0, "executable passes X_OK");
+    errno = 0;
+    check(access(ROOT "/b/cmd/x", F_OK) == -1 && errno == ENOTDIR, "file as directory is ENOTDIR");
+    errno = 0;
+    check(access(ROOT "/d/cmd", 8) == -1 && errno == EINVAL, "invalid mode is EINVAL");
+
+    // faccessat2 支持 AT_EACCESS，拒绝未知的 flags
+    check(syscall(SYS_faccessat2, AT_FDCWD, ROOT "/d/cmd", X_OK, AT_EACCESS) == 0,
+          "faccessat2 with AT_EACCESS");
+    errno = 0;
+    check(syscall(SYS_faccessat2, AT_FDCWD, ROOT "/b/cmd", X_OK, AT_EACCESS) == -1 &&
+              errno == EACCES,
+          "faccessat2 non-executable is EACCES");
+    errno = 0;
+    check(syscall(SYS_faccessat2, AT_FDCWD, ROOT "/d/cmd", X_OK, 0x4) == -1 && errno == EINVAL,
+          "faccessat2 unknown flag is EINVAL");
+    int dir = open(ROOT "/d", O_RDONLY | O_DIRECTORY);
+    check(dir >= 0, "open PATH entry");
+    check(faccessat(dir, "cmd", X_OK, 0) == 0, "faccessat relative to dir_fd");
+    close(dir);
+
+    // 跳过目录、不可执行的文件与不存在的目录，找到第一个可执行文件
+    char found[PATH_MAX];
+    check(search_path(ROOT "/a:" ROOT "/b:" ROOT "/c:" ROOT "/d:" ROOT "/e", "cmd", found) == 0 &&
+              strcmp(found, ROOT "/d/cmd") == 0,
+          "PATH search finds the first executable");
+    check(search_path(ROOT "/a:" ROOT "/b:" ROOT "/c", "cmd", found) == -1,
+          "PATH search without executables fails");
+    check(chmod(ROOT "/b/cmd", 0755) == 0, "chmod +x");
+    check(search_path(ROOT "/a:" ROOT "/b:" ROOT "/d", "cmd", found) == 0 &&
+              strcmp(found, ROOT "/b/cmd") == 0,
+          "PATH search sees the new executable");
+
+    puts(failed ? "access test failed" : "access test passed");
+    return failed;
+}
@@ -0,0 +1,141 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/acct.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CHILDREN 3
+#define ACCT_FILE "acct.log"
+// 被记录峰值的子进程使用的内存大小
+#define PEAK_SIZE (4 << 20)
+
+// comp_t 的低 13 位为尾数，高 3 位为以 8 为底的指数
+static unsigned long decode_comp_t(comp_t value)
+{
+    return (unsigned long)(value & 0x1fff) << (3 * (value >> 13));
+}
+
+int main(void)
+{
+    int failed = 0;
+    pid_t pids[CHILDREN];
+    pid_t killed, peak;
+
+    // 记账文件需要事先存在
+    int fd = open(ACCT_FILE, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0) {
+        puts("create acct file failed");
+        return 1;
+    }
+    close(fd);
+
+    if (acct(ACCT_FILE) != 0) {
+        puts("acct enable failed");
+        return 1;
+    }
+    for (int i = 0; i < CHILDREN; i++) {
+        pids[i] = fork();
+        if (pids[i] == 0) {
+            _exit(i + 1);
+        }
+        waitpid(pids[i], NULL, 0);
+    }
+    // 被信号杀死的进程，退出状态与 wait 得到的相同
+    killed = fork();
+    if (killed == 0) {
+        for (;;)
+            pause();
+    }
+    kill(killed, SIGKILL);
+    waitpid(killed, NULL, 0);
+    // 用过的内存在退出前被 MADV_DONTNEED 释放，峰值仍然计入记录
+    peak = fork();
+    if (peak == 0) {
+        char *buf = mmap(NULL, PEAK_SIZE, PROT_READ | PROT_WRITE,
+                         MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+        if (buf == MAP_FAILED)
+            _exit(1);
+        memset(buf, 1, PEAK_SIZE);
+        madvise(buf, PEAK_SIZE, MADV_DONTNEED);
+        _exit(0);
+    }
+    waitpid(peak, NULL, 0);
+    // 关闭时会写完已经生成的记录
+    if (acct(NULL) != 0) {
+        puts("acct disable failed");
+        return 1;
+    }
+
+    fd = open(ACCT_FILE, O_RDONLY);
+    struct acct_v3 record;
+    int found[CHILDREN] = {0};
+    int found_killed = 0, found_peak = 0;
+    while (read(fd, &record, sizeof(record)) == sizeof(record)) {
+        if (record.ac_version != 3) {
+            printf("bad record version %d\n", record.ac_version);
+            failed = 1;
+            break;
+        }
+        if (record.ac_pid == (unsigned)killed) {
+            found_killed = 1;
+            if (!WIFSIGNALED(record.ac_exitcode) || WTERMSIG(record.ac_exitcode) != SIGKILL) {
+                printf("killed child: exit status %u\n", record.ac_exitcode);
+                failed = 1;
+            }
+        }
+        if (record.ac_pid == (unsigned)peak) {
+            found_peak = 1;
+            if (decode_comp_t(record.ac_mem) < PEAK_SIZE / 1024) {
+                printf("peak child: peak rss %lu KB\n", decode_comp_t(record.ac_mem));
+                failed = 1;
+            }
+        }
+        for (int i = 0; i < CHILDREN; i++) {
+            if (record.ac_pid != (unsigned)pids[i]) {
+                continue;
+            }
+            found[i] = 1;
+            if (record.ac_ppid != (unsigned)getpid() ||
+                WEXITSTATUS(record.ac_exitcode) != i + 1) {
+                printf("child %d: ppid %u, exit status %u\n", i, record.ac_ppid,
+                       record.ac_exitcode);
+                failed = 1;
+            }
+        }
+    }
+    close(fd);
+    for (int i = 0; i < CHILDREN; i++) {
+        if (!found[i]) {
+            printf("no record for child %d\n", i);
+            failed = 1;
+        }
+    }
+    if (!found_killed || !found_peak) {
+        puts("no record for the killed or peak child");
+        failed = 1;
+    }
+
+    // 关闭后退出的进程不再产生记录
+    fd = open(ACCT_FILE, O_RDONLY);
+    off_t size_before = lseek(fd, 0, SEEK_END);
+    close(fd);
+    pid_t child = fork();
+    if (child == 0) {
+        _exit(0);
+    }
+    waitpid(child, NULL, 0);
+    fd = open(ACCT_FILE, O_RDONLY);
+    if (lseek(fd, 0, SEEK_END) != size_before) {
+        puts("record written after acct disabled");
+        failed = 1;
+    }
+    close(fd);
+
+    unlink(ACCT_FILE);
+    puts(failed ? "acct test failed" : "acct test passed");
+    return failed;
+}
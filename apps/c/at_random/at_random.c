@@ -0,0 +1,34 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <sys/random.h>
+
+int main(void)
+{
+    const unsigned char *at_random = (const unsigned char *)getauxval(AT_RANDOM);
+    if (at_random == NULL) {
+        puts("AT_RANDOM is missing");
+        return 1;
+    }
+
+    // AT_RANDOM 与 getrandom 取自同一随机数源，但两次得到的字节不同
+    unsigned char buf[16];
+    if (getrandom(buf, sizeof(buf), 0) != sizeof(buf)) {
+        puts("getrandom failed");
+        return 1;
+    }
+    if (memcmp(buf, at_random, sizeof(buf)) == 0) {
+        puts("getrandom returned the AT_RANDOM bytes");
+        return 1;
+    }
+
+    // 不应再是固定的常量
+    static const unsigned long long fixed[2] = {3703830112808742751ULL, 7081108068768079778ULL};
+    if (memcmp(at_random, fixed, sizeof(fixed)) == 0) {
+        puts("AT_RANDOM is a fixed constant");
+        return 1;
+    }
+
+    puts("at_random test passed");
+    return 0;
+}
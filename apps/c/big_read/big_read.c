@@ -0,0 +1,62 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define SIZE (64 * 1024)
+
+static char data[SIZE], buf[SIZE];
+
+int main(void)
+{
+    int failed = 0;
+    for (int i = 0; i < SIZE; i++) {
+        data[i] = (char)(i * 7);
+    }
+
+    int fd = open("big_read.bin", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, data, SIZE) != SIZE) {
+        puts("prepare file failed");
+        return 1;
+    }
+
+    // 一次读取远大于 1024 字节的内容
+    lseek(fd, 0, SEEK_SET);
+    if (read(fd, buf, SIZE) != SIZE || memcmp(buf, data, SIZE) != 0) {
+        puts("64KiB read returned wrong data");
+        failed = 1;
+    }
+
+    // 读到文件末尾时返回实际读到的字节数
+    lseek(fd, SIZE - 100, SEEK_SET);
+    if (read(fd, buf, 4096) != 100) {
+        puts("partial read at EOF has the wrong length");
+        failed = 1;
+    }
+
+    // 用户缓冲区的后半部分没有映射时，返回已经读取的字节数
+    char *page = mmap(NULL, 8192, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    munmap(page + 4096, 4096);
+    lseek(fd, 0, SEEK_SET);
+    if (read(fd, page, 8192) != 4096 || memcmp(page, data, 4096) != 0) {
+        puts("read into a partly unmapped buffer should stop at the hole");
+        failed = 1;
+    }
+    munmap(page, 4096);
+
+    // 读取 0 字节直接返回 0，无效的 fd 返回 EBADF
+    if (read(fd, NULL, 0) != 0) {
+        puts("zero-length read should return 0");
+        failed = 1;
+    }
+    close(fd);
+    if (read(fd, buf, 1) != -1) {
+        puts("read on a closed fd should fail");
+        failed = 1;
+    }
+    unlink("big_read.bin");
+
+    puts(failed ? "big_read test failed" : "big_read test passed");
+    return failed;
+}
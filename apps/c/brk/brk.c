@@ -0,0 +1,60 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static uintptr_t sys_brk(uintptr_t addr)
+{
+    return (uintptr_t)syscall(SYS_brk, addr);
+}
+
+int main(void)
+{
+    // 与 glibc 的 malloc 一样，先以 0 查询当前堆顶
+    uintptr_t base = sys_brk(0);
+    check(base != 0, "query initial brk");
+    check(sys_brk(base) == base, "brk(current) returns current");
+    check(sys_brk(base) == base, "repeated brk(current)");
+
+    // 增长一个 arena 的大小，再以相同的值重复调用
+    uintptr_t grow = 0x21000;
+    check(sys_brk(base + grow) == base + grow, "grow");
+    check(sys_brk(0) == base + grow, "query after grow");
+    check(sys_brk(base + grow) == base + grow, "repeated grow to same value");
+
+    char *heap = (char *)base;
+    memset(heap, 0x5a, grow);
+
+    // current + delta，delta 为负数
+    uintptr_t cur = sys_brk(0);
+    intptr_t delta = -(intptr_t)(grow / 2);
+    check(sys_brk(cur + delta) == cur + delta, "shrink with negative delta");
+    check(sys_brk(0) == cur + delta, "query after shrink");
+    check(heap[0] == 0x5a, "memory below the break kept");
+
+    // 重新增长后，收缩掉的部分应当全为 0
+    check(sys_brk(cur) == cur, "regrow");
+    int zero = 1;
+    for (uintptr_t i = grow / 2; i < grow; i++) {
+        if (heap[i] != 0) {
+            zero = 0;
+            break;
+        }
+    }
+    check(zero, "regrown memory is zeroed");
+
+    // 非法的请求不改变堆顶，返回当前堆顶
+    check(sys_brk(base - 0x1000) == cur, "brk below heap bottom");
+    check(sys_brk(base + ((uintptr_t)1 << 40)) == cur, "brk beyond heap limit");
+    check(sys_brk(0) == cur, "break unchanged after invalid requests");
+
+    check(sys_brk(base) == base, "shrink back to base");
+    check(sys_brk(0) == base, "query after shrink to base");
+
+    puts(failed ? "brk test failed" : "brk test passed");
+    return failed;
+}
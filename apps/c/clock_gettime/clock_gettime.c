@@ -0,0 +1,59 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static long long nanos(const struct timespec *ts)
+{
+    return (long long)ts->tv_sec * 1000000000LL + ts->tv_nsec;
+}
+
+int main(void)
+{
+    const clockid_t clocks[] = {CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_MONOTONIC_RAW,
+                                CLOCK_PROCESS_CPUTIME_ID, CLOCK_THREAD_CPUTIME_ID};
+    for (unsigned i = 0; i < sizeof(clocks) / sizeof(clocks[0]); i++) {
+        struct timespec a, b, res;
+        check(clock_gettime(clocks[i], &a) == 0, "clock_gettime");
+        check(a.tv_nsec >= 0 && a.tv_nsec < 1000000000L, "tv_nsec in range");
+        check(clock_getres(clocks[i], &res) == 0, "clock_getres");
+        check(clock_getres(clocks[i], NULL) == 0, "clock_getres with a null pointer");
+        // 忙等一段时间，CPU 时间时钟也应当前进
+        for (volatile int j = 0; j < 10000000; j++)
+            ;
+        check(clock_gettime(clocks[i], &b) == 0, "clock_gettime again");
+        check(nanos(&b) >= nanos(&a), "clock does not go backwards");
+    }
+
+    // 单调时钟在睡眠期间前进
+    struct timespec start, end;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    usleep(20000);
+    clock_gettime(CLOCK_MONOTONIC, &end);
+    check(nanos(&end) - nanos(&start) >= 20000000LL, "monotonic clock advances during a sleep");
+
+    // 墙上时间与 gettimeofday 一致
+    struct timespec rt;
+    struct timeval tv;
+    clock_gettime(CLOCK_REALTIME, &rt);
+    gettimeofday(&tv, NULL);
+    check(tv.tv_sec - rt.tv_sec <= 1 && tv.tv_sec >= rt.tv_sec, "gettimeofday matches CLOCK_REALTIME");
+
+    // 不存在的时钟与非法的地址
+    struct timespec ts;
+    errno = 0;
+    check(syscall(SYS_clock_gettime, 100, &ts) == -1 && errno == EINVAL, "unknown clock: EINVAL");
+    errno = 0;
+    check(syscall(SYS_clock_getres, 100, &ts) == -1 && errno == EINVAL,
+          "clock_getres of an unknown clock: EINVAL");
+    errno = 0;
+    check(syscall(SYS_clock_gettime, CLOCK_MONOTONIC, (void *)1) == -1 && errno == EFAULT,
+          "bad address: EFAULT");
+
+    puts(failed ? "clock_gettime test failed" : "clock_gettime test passed");
+    return failed;
+}
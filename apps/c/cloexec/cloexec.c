@@ -0,0 +1,93 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static int is_closed(int fd)
+{
+    errno = 0;
+    return fcntl(fd, F_GETFD) == -1 && errno == EBADF;
+}
+
+// exec 之后的新映像：带有 CLOEXEC 的文件已被关闭，其余文件仍然打开
+static int after_exec(char *argv[])
+{
+    int efd = atoi(argv[2]), pipe_r = atoi(argv[3]), pipe_w = atoi(argv[4]);
+    int epfd = atoi(argv[5]), keep = atoi(argv[6]);
+    int dup_cloexec = atoi(argv[7]), dup_plain = atoi(argv[8]);
+    check(is_closed(efd), "eventfd with EFD_CLOEXEC closed on exec");
+    check(is_closed(pipe_r) && is_closed(pipe_w), "pipe2 with O_CLOEXEC closed on exec");
+    check(is_closed(epfd), "epoll with EPOLL_CLOEXEC closed on exec");
+    check(!is_closed(keep), "eventfd without EFD_CLOEXEC kept across exec");
+    check(eventfd_write(keep, 1) == 0, "kept eventfd still usable");
+    // close_on_exec 位属于文件描述符，复制出的文件描述符各自决定
+    check(is_closed(dup_cloexec), "dup3 O_CLOEXEC copy closed on exec");
+    check(!is_closed(dup_plain), "dup of a CLOEXEC fd kept across exec");
+    puts(failed ? "cloexec test failed" : "cloexec test passed");
+    return failed;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc == 9 && strcmp(argv[1], "exec") == 0)
+        return after_exec(argv);
+
+    int efd = eventfd(0, EFD_CLOEXEC);
+    check(efd >= 0 && fcntl(efd, F_GETFD) == FD_CLOEXEC, "eventfd EFD_CLOEXEC sets FD_CLOEXEC");
+    int pipefd[2];
+    check(pipe2(pipefd, O_CLOEXEC) == 0, "pipe2 O_CLOEXEC");
+    check(fcntl(pipefd[0], F_GETFD) == FD_CLOEXEC && fcntl(pipefd[1], F_GETFD) == FD_CLOEXEC,
+          "pipe2 O_CLOEXEC sets FD_CLOEXEC on both ends");
+    int epfd = epoll_create1(EPOLL_CLOEXEC);
+    check(epfd >= 0 && fcntl(epfd, F_GETFD) == FD_CLOEXEC, "epoll_create1 EPOLL_CLOEXEC sets FD_CLOEXEC");
+    int keep = eventfd(0, 0);
+    check(keep >= 0 && fcntl(keep, F_GETFD) == 0, "eventfd without EFD_CLOEXEC");
+
+    // dup3 的 O_CLOEXEC 只作用于新的文件描述符
+    int dup_cloexec = dup3(keep, 40, O_CLOEXEC);
+    check(dup_cloexec == 40, "dup3 to fd 40");
+    check(fcntl(dup_cloexec, F_GETFD) == FD_CLOEXEC, "dup3 O_CLOEXEC sets FD_CLOEXEC");
+    check(fcntl(keep, F_GETFD) == 0, "dup3 O_CLOEXEC leaves old fd alone");
+    errno = 0;
+    check(dup3(keep, keep, 0) == -1 && errno == EINVAL, "dup3 with equal fds is EINVAL");
+    // dup 得到的文件描述符不带 FD_CLOEXEC
+    int dup_plain = dup(efd);
+    check(dup_plain >= 0 && fcntl(dup_plain, F_GETFD) == 0, "dup clears FD_CLOEXEC");
+    check(fcntl(efd, F_GETFD) == FD_CLOEXEC, "dup leaves old fd alone");
+    // F_DUPFD 分配不小于参数的最小空闲文件描述符
+    int floor = fcntl(keep, F_DUPFD, 50);
+    check(floor == 50, "F_DUPFD honours the lower bound");
+    check(fcntl(keep, F_DUPFD, 50) == 51, "F_DUPFD skips used fds");
+    int floor_cloexec = fcntl(keep, F_DUPFD_CLOEXEC, 50);
+    check(floor_cloexec == 52 && fcntl(floor_cloexec, F_GETFD) == FD_CLOEXEC,
+          "F_DUPFD_CLOEXEC sets FD_CLOEXEC on the new fd");
+    check(fcntl(keep, F_GETFD) == 0, "F_DUPFD_CLOEXEC leaves old fd alone");
+    // F_SETFD 只修改一个文件描述符
+    check(fcntl(floor, F_SETFD, FD_CLOEXEC) == 0 && fcntl(keep, F_GETFD) == 0,
+          "F_SETFD is per descriptor");
+    close(floor);
+    close(51);
+    close(floor_cloexec);
+    if (failed) {
+        puts("cloexec test failed");
+        return failed;
+    }
+
+    char args[7][16];
+    int fds[7] = {efd, pipefd[0], pipefd[1], epfd, keep, dup_cloexec, dup_plain};
+    for (int i = 0; i < 7; i++)
+        snprintf(args[i], sizeof(args[i]), "%d", fds[i]);
+    char *child_argv[] = {argv[0], "exec", args[0], args[1], args[2], args[3],
+                          args[4], args[5], args[6], NULL};
+    execv(argv[0], child_argv);
+    check(0, "execv");
+    puts("cloexec test failed");
+    return 1;
+}
@@ -0,0 +1,90 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/eventfd.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define STACK_SIZE 0x4000
+
+static volatile int child_fd = -1;
+
+static int open_in_child(void *arg)
+{
+    (void)arg;
+    child_fd = open("clone_files_test.txt", O_RDWR | O_CREAT, 0644);
+    return child_fd < 0;
+}
+
+static char *self_path;
+
+static int exec_in_child(void *arg)
+{
+    (void)arg;
+    char *child_argv[] = {self_path, "exec", NULL};
+    execv(self_path, child_argv);
+    return 1;
+}
+
+static int spawn(int flags)
+{
+    char *stack = malloc(STACK_SIZE);
+    int status = -1;
+    child_fd = -1;
+    pid_t pid = clone(open_in_child, stack + STACK_SIZE, flags | SIGCHLD, NULL);
+    if (pid > 0)
+        waitpid(pid, &status, 0);
+    free(stack);
+    return pid > 0 && WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+
+int main(int argc, char *argv[])
+{
+    // exec 之后的新映像，直接退出
+    if (argc == 2 && strcmp(argv[1], "exec") == 0)
+        return 0;
+    self_path = argv[0];
+
+    // 带有 CLONE_FILES 时子进程打开的文件在父进程中可见
+    check(spawn(CLONE_VM | CLONE_FILES), "clone with CLONE_FILES");
+    check(child_fd >= 0 && fcntl(child_fd, F_GETFD) != -1, "fd opened by child is shared");
+    if (child_fd >= 0)
+        close(child_fd);
+
+    // 不带 CLONE_FILES 时子进程使用复制的表，父进程看不到新的文件
+    check(spawn(CLONE_VM), "clone without CLONE_FILES");
+    errno = 0;
+    check(child_fd >= 0 && fcntl(child_fd, F_GETFD) == -1 && errno == EBADF,
+          "fd opened by child is private");
+
+    // 共享文件描述符表的子进程 exec 时先复制一份再关闭 CLOEXEC 的文件，父进程的文件不受影响
+    int efd = eventfd(0, EFD_CLOEXEC);
+    check(efd >= 0, "eventfd with EFD_CLOEXEC");
+    char *stack = malloc(STACK_SIZE);
+    int exec_status = -1;
+    pid_t exec_pid = clone(exec_in_child, stack + STACK_SIZE, CLONE_FILES | SIGCHLD, NULL);
+    check(exec_pid > 0 && waitpid(exec_pid, &exec_status, 0) == exec_pid, "clone and exec");
+    check(WIFEXITED(exec_status) && WEXITSTATUS(exec_status) == 0, "exec in a CLONE_FILES child");
+    free(stack);
+    check(fcntl(efd, F_GETFD) == FD_CLOEXEC, "exec in a CLONE_FILES child keeps the parent's fd");
+    close(efd);
+
+    // fork 在子进程中返回 0，在父进程中返回子进程的 pid
+    int status = -1;
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(getppid() > 0 ? 7 : 1);
+    check(pid > 0 && waitpid(pid, &status, 0) == pid, "fork and wait");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 7, "fork child exit status");
+
+    unlink("clone_files_test.txt");
+    puts(failed ? "clone_files test failed" : "clone_files test passed");
+    return failed;
+}
@@ -0,0 +1,95 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static int pipe_fds[2];
+static char thread_buf[8];
+static ssize_t thread_read_len = -1;
+
+// 在另一个线程关闭读端之前开始读取，关闭不影响已经开始的读取
+static void *reader(void *arg)
+{
+    (void)arg;
+    thread_read_len = read(pipe_fds[0], thread_buf, sizeof(thread_buf));
+    return NULL;
+}
+
+int main(void)
+{
+    int failed = 0;
+
+    // 关闭之后文件描述符被释放，再次打开时复用同一个编号
+    int fd = open("close_fd_test.txt", O_RDWR | O_CREAT, 0644);
+    if (fd < 0 || close(fd) != 0) {
+        puts("open/close failed");
+        return 1;
+    }
+    int again = open("close_fd_test.txt", O_RDWR);
+    if (again != fd) {
+        printf("fd %d was not reused, got %d\n", fd, again);
+        failed = 1;
+    }
+    close(again);
+
+    // 关闭时写入的内容被刷回文件，重新打开后可以读到
+    const char msg[] = "written before close";
+    fd = open("close_fd_test.txt", O_WRONLY | O_TRUNC);
+    if (fd < 0 || write(fd, msg, sizeof(msg)) != sizeof(msg) || close(fd) != 0) {
+        puts("write/close failed");
+        failed = 1;
+    }
+    char buf[sizeof(msg)] = {0};
+    fd = open("close_fd_test.txt", O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf)) != sizeof(msg) || memcmp(buf, msg, sizeof(msg)) != 0) {
+        puts("data written before close was lost");
+        failed = 1;
+    }
+    close(fd);
+    unlink("close_fd_test.txt");
+
+    // 重复关闭或关闭负数返回 EBADF
+    if (close(again) != -1 || errno != EBADF) {
+        puts("double close should fail with EBADF");
+        failed = 1;
+    }
+    if (close(-1) != -1 || errno != EBADF) {
+        puts("close(-1) should fail with EBADF");
+        failed = 1;
+    }
+
+    // 关闭另一个线程正在使用的文件描述符时，该线程持有的文件仍然有效
+    pthread_t thread;
+    if (pipe(pipe_fds) != 0 || pthread_create(&thread, NULL, reader, NULL) != 0) {
+        puts("pipe/pthread_create failed");
+        failed = 1;
+    } else {
+        usleep(100000);
+        if (close(pipe_fds[0]) != 0 || write(pipe_fds[1], "pipe", 4) != 4) {
+            puts("close/write while another thread reads failed");
+            failed = 1;
+        }
+        pthread_join(thread, NULL);
+        if (thread_read_len != 4 || memcmp(thread_buf, "pipe", 4) != 0) {
+            printf("read in another thread got %zd bytes after close\n", thread_read_len);
+            failed = 1;
+        }
+        close(pipe_fds[1]);
+    }
+
+    // 可以关闭标准输入，再次关闭返回 EBADF
+    if (close(0) != 0) {
+        puts("close(0) failed");
+        failed = 1;
+    }
+    errno = 0;
+    if (close(0) != -1 || errno != EBADF) {
+        puts("closing stdin twice should fail with EBADF");
+        failed = 1;
+    }
+
+    puts(failed ? "close_fd test failed" : "close_fd test passed");
+    return failed;
+}
@@ -0,0 +1,71 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_FILE "close_flush_test.txt"
+#define CHUNK 1000
+#define CHUNKS 50
+
+// 重新打开文件，检查其内容是否为 CHUNKS 个以块号填充的块
+static int verify(void)
+{
+    char buf[CHUNK];
+    int fd = open(TEST_FILE, O_RDONLY);
+    if (fd < 0)
+        return 0;
+    int ok = 1;
+    for (int i = 0; i < CHUNKS && ok; i++) {
+        ssize_t total = 0, ret;
+        while (total < CHUNK && (ret = read(fd, buf + total, CHUNK - total)) > 0)
+            total += ret;
+        ok = total == CHUNK;
+        for (int j = 0; j < CHUNK && ok; j++)
+            ok = buf[j] == (char)('a' + i % 26);
+    }
+    ok = ok && read(fd, buf, 1) == 0;
+    close(fd);
+    return ok;
+}
+
+int main(void)
+{
+    char chunk[CHUNK];
+    struct stat st;
+
+    // 通过 stdio 的缓冲写入，fclose 时写入剩余的数据并关闭文件
+    FILE *fp = fopen(TEST_FILE, "w");
+    check(fp != NULL, "fopen");
+    for (int i = 0; i < CHUNKS; i++) {
+        memset(chunk, 'a' + i % 26, CHUNK);
+        check(fwrite(chunk, 1, CHUNK, fp) == CHUNK, "fwrite");
+    }
+    check(fclose(fp) == 0, "fclose succeeds");
+    check(stat(TEST_FILE, &st) == 0 && st.st_size == CHUNK * CHUNKS, "size after fclose");
+    check(verify(), "all bytes written through stdio are persisted");
+
+    // 直接 write 后 close，关闭后文件的大小与内容都已经写回
+    int fd = open(TEST_FILE, O_WRONLY | O_TRUNC);
+    check(fd >= 0, "open for rewrite");
+    for (int i = 0; i < CHUNKS; i++) {
+        memset(chunk, 'a' + i % 26, CHUNK);
+        check(write(fd, chunk, CHUNK) == CHUNK, "write");
+    }
+    check(close(fd) == 0, "close succeeds");
+    check(stat(TEST_FILE, &st) == 0 && st.st_size == CHUNK * CHUNKS, "size after close");
+    check(verify(), "all bytes written with write are persisted");
+
+    // 只读的文件与特殊文件关闭时没有需要写回的数据
+    fd = open(TEST_FILE, O_RDONLY);
+    check(fd >= 0 && close(fd) == 0, "close a read-only file");
+    fd = open("/dev/null", O_WRONLY);
+    check(fd >= 0 && write(fd, chunk, CHUNK) == CHUNK && close(fd) == 0, "close /dev/null");
+    unlink(TEST_FILE);
+
+    puts(failed ? "close_flush test failed" : "close_flush test passed");
+    return failed;
+}
@@ -0,0 +1,43 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 两个进程以 MAP_PRIVATE 映射同一个文件，干净页面共享同一物理页帧，
+// 其中一个进程写入后只修改自己的副本
+int main()
+{
+    const char *path = "./cow_mmap.txt";
+    const char *content = "page cache shared content";
+    int fd = open(path, O_CREAT | O_RDWR, 0644);
+    write(fd, content, strlen(content) + 1);
+
+    pid_t pid = fork();
+    char *p = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if (p == MAP_FAILED) {
+        printf("cow_mmap: mmap failed\n");
+        return 1;
+    }
+    if (strcmp(p, content) != 0) {
+        printf("cow_mmap: unexpected content %s\n", p);
+        return 1;
+    }
+    if (pid == 0) {
+        // 写入触发复制
+        p[0] = 'P';
+        _exit(p[0] == 'P' ? 0 : 1);
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0 || p[0] != 'p') {
+        printf("cow_mmap: private write leaked to other process\n");
+        return 1;
+    }
+    munmap(p, 4096);
+    close(fd);
+    unlink(path);
+    printf("cow_mmap: test passed\n");
+    return 0;
+}
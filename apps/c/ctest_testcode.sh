@@ -0,0 +1,14 @@
+#!/bin/busybox sh
+# 依次运行 testcase_list 中的 C 测例，全部通过时退出码为 0
+
+failed=0
+for name in $(busybox grep -v '^#' ./testcase_list); do
+	echo "========== START ctest $name =========="
+	if ./$name; then
+		echo "========== END ctest $name: passed =========="
+	else
+		echo "========== END ctest $name: failed =========="
+		failed=1
+	fi
+done
+exit $failed
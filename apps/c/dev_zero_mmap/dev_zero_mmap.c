@@ -0,0 +1,58 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define LEN (2 * 4096)
+
+int main(void)
+{
+    int failed = 0;
+    int fd = open("/dev/zero", O_RDWR);
+    if (fd < 0) {
+        puts("open /dev/zero failed");
+        return 1;
+    }
+
+    // 私有映射读出全零，并且写入互不影响
+    char *a = mmap(NULL, LEN, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    char *b = mmap(NULL, LEN, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if (a == MAP_FAILED || b == MAP_FAILED) {
+        puts("mmap /dev/zero failed");
+        return 1;
+    }
+    for (int i = 0; i < LEN; i++) {
+        if (a[i] != 0 || b[i] != 0) {
+            puts("mapping is not zero-filled");
+            failed = 1;
+            break;
+        }
+    }
+    a[0] = 'a';
+    a[LEN - 1] = 'a';
+    if (b[0] != 0 || b[LEN - 1] != 0) {
+        puts("private mappings are not independent");
+        failed = 1;
+    }
+
+    // 共享映射与匿名共享内存一样在 fork 后共享
+    char *s = mmap(NULL, LEN, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (s == MAP_FAILED || s[0] != 0) {
+        puts("shared mmap of /dev/zero failed");
+        return 1;
+    }
+    if (fork() == 0) {
+        s[0] = 's';
+        _exit(0);
+    }
+    wait(NULL);
+    if (s[0] != 's') {
+        puts("shared mapping is not shared with the child");
+        failed = 1;
+    }
+
+    close(fd);
+    puts(failed ? "dev_zero_mmap test failed" : "dev_zero_mmap test passed");
+    return failed;
+}
@@ -0,0 +1,87 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_FILE "dup_test.txt"
+
+int main(void)
+{
+    char buf[16];
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "open");
+
+    // dup 得到的文件描述符与原来的共享同一个打开的文件，包括偏移量
+    int copy = dup(fd);
+    check(copy >= 0 && copy != fd, "dup");
+    check(write(fd, "abc", 3) == 3, "write through the old fd");
+    check(write(copy, "def", 3) == 3, "write through the copy");
+    check(lseek(copy, 0, SEEK_CUR) == 6 && lseek(fd, 0, SEEK_CUR) == 6, "dup shares the offset");
+    close(fd);
+    check(pread(copy, buf, 6, 0) == 6 && memcmp(buf, "abcdef", 6) == 0,
+          "copy still works after closing the old fd");
+
+    // dup 使用最小的空闲文件描述符，新的文件描述符不继承 close_on_exec
+    int cloexec = open(TEST_FILE, O_RDONLY | O_CLOEXEC);
+    int low = dup(copy);
+    int high = dup(copy);
+    check(cloexec >= 0 && low >= 0 && high > low, "dup twice");
+    close(low);
+    int reused = dup(cloexec);
+    check(reused == low, "dup reuses the lowest free fd");
+    check(fcntl(reused, F_GETFD) == 0, "dup clears close_on_exec");
+    close(reused);
+    close(high);
+    close(cloexec);
+
+    // dup2 到已经打开的文件描述符上时先悄悄关闭它
+    int pipe_fds[2];
+    check(pipe(pipe_fds) == 0, "pipe");
+    check(dup2(copy, pipe_fds[1]) == pipe_fds[1], "dup2 onto an open fd");
+    check(read(pipe_fds[0], buf, sizeof(buf)) == 0, "replaced pipe write end is closed");
+    check(pwrite(pipe_fds[1], "X", 1, 0) == 1, "dup2 target refers to the file");
+    check(pread(copy, buf, 1, 0) == 1 && buf[0] == 'X', "write through the dup2 target");
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+
+    // dup2 的两个文件描述符相同时只检查其有效性，dup3 则返回 EINVAL
+    check(dup2(copy, copy) == copy, "dup2 onto itself");
+    errno = 0;
+    check(dup3(copy, copy, 0) == -1 && errno == EINVAL, "dup3 onto itself sets EINVAL");
+    errno = 0;
+    check(dup3(copy, 50, O_NONBLOCK) == -1 && errno == EINVAL, "dup3 with bad flags sets EINVAL");
+    check(dup3(copy, 50, O_CLOEXEC) == 50 && fcntl(50, F_GETFD) == FD_CLOEXEC,
+          "dup3 O_CLOEXEC");
+    close(50);
+
+    // 原文件描述符无效或目标超出限制时返回 EBADF
+    errno = 0;
+    check(dup(1000) == -1 && errno == EBADF, "dup of a closed fd sets EBADF");
+    errno = 0;
+    check(dup2(1000, 50) == -1 && errno == EBADF, "dup2 of a closed fd sets EBADF");
+    errno = 0;
+    check(dup2(1000, 1000) == -1 && errno == EBADF, "dup2 of a closed fd onto itself sets EBADF");
+    struct rlimit lim;
+    check(getrlimit(RLIMIT_NOFILE, &lim) == 0, "getrlimit");
+    errno = 0;
+    check(dup2(copy, lim.rlim_cur) == -1 && errno == EBADF, "dup2 past RLIMIT_NOFILE sets EBADF");
+
+    // 没有不超过 RLIMIT_NOFILE 的空闲文件描述符时 dup 返回 EMFILE
+    int next = dup(copy);
+    close(next);
+    struct rlimit small = {next, lim.rlim_max};
+    check(setrlimit(RLIMIT_NOFILE, &small) == 0, "lower RLIMIT_NOFILE");
+    errno = 0;
+    check(dup(copy) == -1 && errno == EMFILE, "dup with no free fd sets EMFILE");
+    check(setrlimit(RLIMIT_NOFILE, &lim) == 0, "restore RLIMIT_NOFILE");
+    close(copy);
+    unlink(TEST_FILE);
+
+    puts(failed ? "dup test failed" : "dup test passed");
+    return failed;
+}
@@ -0,0 +1,55 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PATH "/dup_redirect.txt"
+#define MSG "redirected\n"
+
+// 子进程将文件 dup3 到标准输出上并关闭原文件描述符，之后写入标准输出的内容应写入该文件
+int main()
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        int fd = open(PATH, O_CREAT | O_RDWR, 0644);
+        if (fd < 0)
+            _exit(1);
+        if (dup3(fd, 1, 0) != 1)
+            _exit(2);
+        close(fd);
+        if (write(1, MSG, strlen(MSG)) != (ssize_t)strlen(MSG))
+            _exit(3);
+        _exit(0);
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("dup_redirect: child failed with status %d\n", status);
+        return 1;
+    }
+
+    char buf[32] = {0};
+    int fd = open(PATH, O_RDONLY);
+    int len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    unlink(PATH);
+    if (len != (int)strlen(MSG) || strcmp(buf, MSG) != 0) {
+        printf("dup_redirect: file contains %d bytes: %s\n", len, buf);
+        return 1;
+    }
+    // dup3 的两个文件描述符相同时应返回 EINVAL
+    if (dup3(1, 1, 0) != -1) {
+        printf("dup_redirect: dup3 onto itself succeeded\n");
+        return 1;
+    }
+    // O_CLOEXEC 只设置在 new_fd 上，不影响原文件描述符
+    if (dup3(1, 10, O_CLOEXEC) != 10 || fcntl(10, F_GETFD) != FD_CLOEXEC || fcntl(1, F_GETFD) != 0) {
+        printf("dup_redirect: dup3 O_CLOEXEC leaked to the old fd\n");
+        return 1;
+    }
+    close(10);
+    printf("dup_redirect: test passed\n");
+    return 0;
+}
@@ -0,0 +1,49 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main(void)
+{
+    int failed = 0;
+
+    // 未实现的系统调用返回 ENOSYS，而不是让内核崩溃或假装成功
+    errno = 0;
+    if (syscall(4000, 1, 2, 3, 4, 5, 6) != -1 || errno != ENOSYS) {
+        printf("unknown syscall: errno %d\n", errno);
+        failed = 1;
+    }
+
+    // 有意忽略的系统调用直接成功
+    sync();
+    if (syscall(SYS_fsync, 1) != 0) {
+        puts("fsync failed");
+        failed = 1;
+    }
+
+    // readlink 返回写入的字节数，超出缓冲区的部分被截断
+    char full[256] = {0};
+    ssize_t len = readlink("/proc/self/exe", full, sizeof(full) - 1);
+    if (len <= 0) {
+        puts("readlink /proc/self/exe failed");
+        failed = 1;
+    } else {
+        char part[4];
+        ssize_t n = readlink("/proc/self/exe", part, sizeof(part));
+        if (n != (len < 4 ? len : 4) || memcmp(part, full, n) != 0) {
+            printf("truncated readlink returned %zd\n", n);
+            failed = 1;
+        }
+    }
+
+    // 不是链接时返回 EINVAL
+    if (readlink("/", full, sizeof(full)) != -1 || errno != EINVAL) {
+        puts("readlink on a directory should fail with EINVAL");
+        failed = 1;
+    }
+
+    puts(failed ? "enosys test failed" : "enosys test passed");
+    return failed;
+}
@@ -0,0 +1,118 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#ifndef AT_EMPTY_PATH
+#define AT_EMPTY_PATH 0x1000
+#endif
+
+static long sys_execveat(int dirfd, const char *path, char *const argv[], char *const envp[], int flags)
+{
+    return syscall(SYS_execveat, dirfd, path, argv, envp, flags);
+}
+
+// exec 之后的新映像：检查参数、环境变量与辅助向量
+static int after_exec(char *argv[])
+{
+    check(strcmp(argv[2], "arg") == 0, "argv passed through exec");
+    const char *env = getenv("EXECVEAT_TEST");
+    check(env != NULL && strcmp(env, "1") == 0, "envp passed through exec");
+    check(getauxval(AT_ENTRY) != 0, "AT_ENTRY present");
+    check(getauxval(AT_PHDR) != 0, "AT_PHDR present");
+    check(getauxval(AT_PHNUM) != 0, "AT_PHNUM present");
+    check(getauxval(AT_PHENT) != 0, "AT_PHENT present");
+    check(getauxval(AT_PAGESZ) == 4096, "AT_PAGESZ is 4096");
+    check(getauxval(AT_RANDOM) != 0, "AT_RANDOM present");
+    return failed;
+}
+
+// 在子进程中执行 exec，返回子进程的退出码
+static int run_child(int dirfd, const char *path, int flags, const char *self)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        char *child_argv[] = {(char *)self, "exec", "arg", NULL};
+        char *child_envp[] = {"EXECVEAT_TEST=1", NULL};
+        sys_execveat(dirfd, path, child_argv, child_envp, flags);
+        _exit(100);
+    }
+    int status = 0;
+    waitpid(pid, &status, 0);
+    return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc == 3 && strcmp(argv[1], "exec") == 0)
+        return after_exec(argv);
+
+    char *args[] = {argv[0], NULL};
+    char *envs[] = {NULL};
+
+    // 失败的 exec 返回错误，调用者继续执行
+    errno = 0;
+    check(execve("/no/such/program", args, envs) == -1 && errno == ENOENT, "execve missing file: ENOENT");
+    errno = 0;
+    check(execve("/", args, envs) == -1 && errno == EACCES, "execve directory: EACCES");
+
+    int fd = open("execveat_not_elf", O_RDWR | O_CREAT | O_TRUNC, 0755);
+    check(fd >= 0 && write(fd, "not an elf file\n", 16) == 16, "create non-ELF file");
+    close(fd);
+    errno = 0;
+    check(execve("execveat_not_elf", args, envs) == -1 && errno == ENOEXEC, "execve non-ELF: ENOEXEC");
+    unlink("execveat_not_elf");
+
+    errno = 0;
+    check(syscall(SYS_execve, argv[0], 1, envs) == -1 && errno == EFAULT, "execve bad argv: EFAULT");
+    errno = 0;
+    check(sys_execveat(AT_FDCWD, argv[0], args, envs, 0x1) == -1 && errno == EINVAL,
+          "execveat unknown flags: EINVAL");
+    errno = 0;
+    check(sys_execveat(AT_FDCWD, "", args, envs, 0) == -1 && errno == ENOENT,
+          "execveat empty path without AT_EMPTY_PATH: ENOENT");
+
+    // 相对于 AT_FDCWD 与 dir_fd 的路径，以及以 AT_EMPTY_PATH 执行 fd 本身
+    check(run_child(AT_FDCWD, argv[0], 0, argv[0]) == 0, "execveat relative to AT_FDCWD");
+
+    char dir[256], *name;
+    strncpy(dir, argv[0], sizeof(dir) - 1);
+    dir[sizeof(dir) - 1] = '\0';
+    name = strrchr(dir, '/');
+    int dirfd;
+    if (name != NULL) {
+        *name++ = '\0';
+        dirfd = open(dir[0] ? dir : "/", O_RDONLY | O_DIRECTORY);
+    } else {
+        name = dir;
+        dirfd = open(".", O_RDONLY | O_DIRECTORY);
+    }
+    check(dirfd >= 0, "open program directory");
+    check(run_child(dirfd, name, 0, argv[0]) == 0, "execveat relative to dir_fd");
+    close(dirfd);
+
+    int self = open(argv[0], O_RDONLY);
+    check(self >= 0, "open program");
+    check(run_child(self, "", AT_EMPTY_PATH, argv[0]) == 0, "execveat with AT_EMPTY_PATH");
+    close(self);
+
+    // 跟随路径最后一级的符号链接，带有 AT_SYMLINK_NOFOLLOW 时返回 ELOOP
+    check(symlink(argv[0], "execveat_link") == 0, "create symlink to the program");
+    check(run_child(AT_FDCWD, "execveat_link", 0, argv[0]) == 0, "execveat through a symlink");
+    errno = 0;
+    check(sys_execveat(AT_FDCWD, "execveat_link", args, envs, AT_SYMLINK_NOFOLLOW) == -1 && errno == ELOOP,
+          "execveat AT_SYMLINK_NOFOLLOW on a symlink: ELOOP");
+    unlink("execveat_link");
+
+    puts(failed ? "execveat test failed" : "execveat test passed");
+    return failed;
+}
@@ -0,0 +1,100 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#ifndef AT_EMPTY_PATH
+#define AT_EMPTY_PATH 0x1000
+#endif
+
+static long sys_execveat(int dirfd, const char *path, char *const argv[], char *const envp[], int flags)
+{
+    return syscall(SYS_execveat, dirfd, path, argv, envp, flags);
+}
+
+// 从 memfd 执行的新映像：/proc/self/exe 指向 /dev/fd/N
+static int after_exec(void)
+{
+    char exe[64] = {0};
+    check(readlink("/proc/self/exe", exe, sizeof(exe) - 1) > 0, "readlink /proc/self/exe");
+    check(strncmp(exe, "/dev/fd/", 8) == 0, "/proc/self/exe is /dev/fd/N");
+    puts("hello world from memfd");
+    return failed;
+}
+
+// 在子进程中执行 fd 本身，返回子进程的退出码
+static int run_child(int fd)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        char *child_argv[] = {"fexecve", "child", NULL};
+        char *child_envp[] = {NULL};
+        sys_execveat(fd, "", child_argv, child_envp, AT_EMPTY_PATH);
+        _exit(100 + errno);
+    }
+    int status = 0;
+    waitpid(pid, &status, 0);
+    return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc == 2 && strcmp(argv[1], "child") == 0)
+        return after_exec();
+
+    // 把程序本身复制到 memfd 中
+    int memfd = memfd_create("fexecve", MFD_ALLOW_SEALING);
+    check(memfd >= 0, "memfd_create");
+    int self = open(argv[0], O_RDONLY);
+    check(self >= 0, "open program");
+    char buf[4096];
+    ssize_t len;
+    while ((len = read(self, buf, sizeof(buf))) > 0)
+        check(write(memfd, buf, len) == len, "copy program into memfd");
+
+    // 加上封印之后不能再修改
+    check(fcntl(memfd, F_ADD_SEALS, F_SEAL_WRITE | F_SEAL_GROW | F_SEAL_SHRINK | F_SEAL_SEAL) == 0,
+          "F_ADD_SEALS");
+    check(fcntl(memfd, F_GET_SEALS) == (F_SEAL_WRITE | F_SEAL_GROW | F_SEAL_SHRINK | F_SEAL_SEAL),
+          "F_GET_SEALS");
+    errno = 0;
+    check(write(memfd, "x", 1) == -1 && errno == EPERM, "write to a sealed memfd: EPERM");
+    errno = 0;
+    check(ftruncate(memfd, 0) == -1, "truncate a sealed memfd fails");
+    errno = 0;
+    check(fcntl(memfd, F_ADD_SEALS, F_SEAL_GROW) == -1 && errno == EPERM, "F_SEAL_SEAL forbids new seals");
+
+    // 不带 MFD_ALLOW_SEALING 的 memfd 不能加上封印，普通文件不支持封印
+    int plain = memfd_create("plain", MFD_CLOEXEC);
+    check(plain >= 0 && fcntl(plain, F_GETFD) == FD_CLOEXEC, "memfd_create MFD_CLOEXEC");
+    errno = 0;
+    check(fcntl(plain, F_ADD_SEALS, F_SEAL_WRITE) == -1 && errno == EPERM, "seal without MFD_ALLOW_SEALING: EPERM");
+    errno = 0;
+    check(fcntl(self, F_GET_SEALS) == -1 && errno == EINVAL, "F_GET_SEALS on a regular file: EINVAL");
+    close(plain);
+    close(self);
+
+    // 执行 memfd，执行之后 lseek 的位置不变
+    off_t offset = lseek(memfd, 0, SEEK_CUR);
+    check(run_child(memfd) == 0, "execveat memfd with AT_EMPTY_PATH");
+    check(lseek(memfd, 0, SEEK_CUR) == offset, "memfd offset unchanged");
+
+    // 管道与已关闭的文件描述符不能执行
+    int pipefd[2];
+    check(pipe(pipefd) == 0, "pipe");
+    check(run_child(pipefd[0]) == 100 + EACCES, "execveat a pipe: EACCES");
+    close(pipefd[0]);
+    close(pipefd[1]);
+    check(run_child(pipefd[0]) == 100 + EBADF, "execveat a closed fd: EBADF");
+    close(memfd);
+
+    puts(failed ? "fexecve test failed" : "fexecve test passed");
+    return failed;
+}
@@ -0,0 +1,75 @@
+#include <stdlib.h>
+#include <string.h>
+#include <sys/sysinfo.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define ROUNDS 200
+#define WARMUP_ROUNDS 20
+#define PAGE 4096
+// 内核堆扩展后不会归还页分配器，预热之后仍允许少量页的误差
+#define SLACK_PAGES 32
+
+static char data[16 * PAGE];
+
+static unsigned long free_bytes(void)
+{
+    struct sysinfo info;
+    if (sysinfo(&info) != 0)
+        return 0;
+    return info.freeram * info.mem_unit;
+}
+
+// fork 出子进程，子进程写入数据页与新分配的内存触发写时复制后退出
+static int fork_and_exit(int rounds)
+{
+    for (int i = 0; i < rounds; i++) {
+        pid_t pid = fork();
+        if (pid < 0)
+            return -1;
+        if (pid == 0) {
+            memset(data, i, sizeof(data));
+            char *buf = malloc(4 * PAGE);
+            if (buf)
+                memset(buf, i, 4 * PAGE);
+            _exit(i & 0x7f);
+        }
+        int status;
+        if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != (i & 0x7f))
+            return -1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    struct sysinfo info;
+    check(sysinfo(&info) == 0, "sysinfo");
+    check(info.totalram > 0 && info.freeram > 0 && info.freeram <= info.totalram,
+          "sysinfo reports total and free memory");
+
+    check(fork_and_exit(WARMUP_ROUNDS) == 0, "warm-up fork/exit");
+    unsigned long base = free_bytes();
+    check(base > 0, "read baseline free memory");
+
+    check(fork_and_exit(ROUNDS) == 0, "fork/exit stress loop");
+
+    // 已退出的任务由内核异步回收，等待空闲内存回到基线
+    unsigned long now = 0;
+    struct timespec tick = {.tv_sec = 0, .tv_nsec = 10 * 1000 * 1000};
+    for (int i = 0; i < 200; i++) {
+        now = free_bytes();
+        if (now + SLACK_PAGES * PAGE >= base)
+            break;
+        nanosleep(&tick, NULL);
+    }
+    if (now + SLACK_PAGES * PAGE < base)
+        printf("free memory: baseline %lu, after %lu\n", base, now);
+    check(now + SLACK_PAGES * PAGE >= base, "free frames return to the baseline after fork/exit");
+
+    puts(failed ? "fork_leak test failed" : "fork_leak test passed");
+    return failed;
+}
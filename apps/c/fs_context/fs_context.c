@@ -0,0 +1,48 @@
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static void *chdir_thread(void *arg)
+{
+    (void)arg;
+    check(chdir("fs_context_dir") == 0, "chdir in thread");
+    return NULL;
+}
+
+int main(void)
+{
+    char before[256], cwd[256];
+    mkdir("fs_context_dir", 0755);
+    check(getcwd(before, sizeof(before)) != NULL, "getcwd");
+
+    // 不带 CLONE_FS 的子进程拥有独立的工作目录
+    pid_t pid = fork();
+    if (pid == 0) {
+        _exit(chdir("fs_context_dir") == 0 ? 0 : 1);
+    }
+    int status = 0;
+    waitpid(pid, &status, 0);
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 0, "chdir in child");
+    check(getcwd(cwd, sizeof(cwd)) != NULL && strcmp(cwd, before) == 0,
+          "chdir in forked child is not seen by the parent");
+
+    // 同一进程的线程共享工作目录
+    pthread_t thread;
+    pthread_create(&thread, NULL, chdir_thread, NULL);
+    pthread_join(thread, NULL);
+    check(getcwd(cwd, sizeof(cwd)) != NULL && strstr(cwd, "fs_context_dir") != NULL,
+          "chdir in a thread is seen by its siblings");
+
+    // umask 同样保存在文件系统上下文中，设置时返回旧值
+    chdir(before);
+    umask(077);
+    check(umask(022) == 077, "umask returns the previous mask");
+
+    puts(failed ? "fs_context test failed" : "fs_context test passed");
+    return failed;
+}
@@ -0,0 +1,123 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define TEST_FILE "fstatat_test.txt"
+
+int main(void)
+{
+    int failed = 0;
+    struct stat st;
+
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+
+    // 通过路径获取普通文件的大小与类型
+    if (fstatat(AT_FDCWD, TEST_FILE, &st, 0) != 0 || !S_ISREG(st.st_mode) || st.st_size != 5) {
+        printf("fstatat file: mode %o, size %ld\n", st.st_mode, (long)st.st_size);
+        failed = 1;
+    }
+
+    // AT_EMPTY_PATH 获取 fd 本身的状态
+    if (fstatat(fd, "", &st, AT_EMPTY_PATH) != 0 || !S_ISREG(st.st_mode) || st.st_size != 5) {
+        puts("fstatat with AT_EMPTY_PATH on a file failed");
+        failed = 1;
+    }
+    if (fstatat(1, "", &st, AT_EMPTY_PATH) != 0 || !S_ISCHR(st.st_mode)) {
+        puts("stdout should be a character device");
+        failed = 1;
+    }
+    // 没有 AT_EMPTY_PATH 时空路径不存在
+    if (fstatat(fd, "", &st, 0) != -1 || errno != ENOENT) {
+        puts("empty path without AT_EMPTY_PATH should fail with ENOENT");
+        failed = 1;
+    }
+
+    // fstat 与 fstatat 的结果一致，标准输入输出同样可以获取状态
+    if (fstat(fd, &st) != 0 || !S_ISREG(st.st_mode) || st.st_size != 5 || st.st_blksize <= 0) {
+        puts("fstat on a file failed");
+        failed = 1;
+    }
+    if (fstat(1, &st) != 0 || !S_ISCHR(st.st_mode)) {
+        puts("fstat on stdout should report a character device");
+        failed = 1;
+    }
+    if (syscall(SYS_fstat, fd, NULL) != -1 || errno != EFAULT) {
+        puts("fstat with a NULL buffer should fail with EFAULT");
+        failed = 1;
+    }
+    close(fd);
+    if (fstat(fd, &st) != -1 || errno != EBADF) {
+        puts("fstat on a closed fd should fail with EBADF");
+        failed = 1;
+    }
+
+    // 目录
+    if (fstatat(AT_FDCWD, "/", &st, 0) != 0 || !S_ISDIR(st.st_mode)) {
+        puts("fstatat on / should report a directory");
+        failed = 1;
+    }
+
+    if (stat("/", &st) != 0 || st.st_blksize <= 0 || st.st_nlink < 1) {
+        puts("a directory should report st_blksize and st_nlink");
+        failed = 1;
+    }
+
+    // 普通文件的块数按 512 字节计
+    fd = open(TEST_FILE, O_RDONLY);
+    if (fstat(fd, &st) != 0 || st.st_nlink != 1 || st.st_blocks * 512 < st.st_size) {
+        printf("fstat file: nlink %ld, blocks %ld\n", (long)st.st_nlink, (long)st.st_blocks);
+        failed = 1;
+    }
+
+    // 相对路径的 dir_fd 无效时返回 EBADF，不是目录时返回 ENOTDIR
+    if (fstatat(1000, TEST_FILE, &st, 0) != -1 || errno != EBADF) {
+        puts("a closed dir_fd should fail with EBADF");
+        failed = 1;
+    }
+    if (fstatat(fd, TEST_FILE, &st, 0) != -1 || errno != ENOTDIR) {
+        puts("a dir_fd that is not a directory should fail with ENOTDIR");
+        failed = 1;
+    }
+    close(fd);
+
+    // 管道的两端是同一个 FIFO
+    int fds[2];
+    struct stat st_write;
+    if (pipe(fds) != 0 || fstat(fds[0], &st) != 0 || fstat(fds[1], &st_write) != 0 ||
+        !S_ISFIFO(st.st_mode) || !S_ISFIFO(st_write.st_mode) || st.st_ino != st_write.st_ino) {
+        puts("both ends of a pipe should report the same FIFO");
+        failed = 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // 套接字
+    int sock = socket(AF_INET, SOCK_STREAM, 0);
+    if (sock < 0 || fstat(sock, &st) != 0 || !S_ISSOCK(st.st_mode)) {
+        puts("fstat on a socket should report a socket");
+        failed = 1;
+    }
+    close(sock);
+
+    // 不存在的文件与非法的缓冲区
+    if (fstatat(AT_FDCWD, "no_such_file", &st, 0) != -1 || errno != ENOENT) {
+        puts("missing file should fail with ENOENT");
+        failed = 1;
+    }
+#ifdef SYS_newfstatat
+    if (syscall(SYS_newfstatat, AT_FDCWD, TEST_FILE, NULL, 0) != -1 || errno != EFAULT) {
+        puts("NULL stat buffer should fail with EFAULT");
+        failed = 1;
+    }
+#endif
+    unlink(TEST_FILE);
+
+    puts(failed ? "fstatat test failed" : "fstatat test passed");
+    return failed;
+}
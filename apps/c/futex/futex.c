@@ -1,15 +1,152 @@
-#include <unistd.h>
-#include <sys/syscall.h>
+#include <errno.h>
+#include <limits.h>
 #include <linux/futex.h>
+#include <pthread.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define NR_WAITERS 3
+
+static long futex(int *uaddr, int op, int val, long val2, int *uaddr2, int val3)
+{
+    return syscall(SYS_futex, uaddr, op, val, val2, uaddr2, val3);
+}
+
+static int word = 0;
+static int word2 = 0;
+static atomic_int ready = 0;
+static atomic_int woken = 0;
+
+static void *waiter(void *arg)
+{
+    int *uaddr = arg;
+    atomic_fetch_add(&ready, 1);
+    while (futex(uaddr, FUTEX_WAIT_PRIVATE, 0, 0, NULL, 0) != 0 && errno == EINTR)
+        ;
+    atomic_fetch_add(&woken, 1);
+    return NULL;
+}
+
+// 等待所有线程进入 futex 等待
+static void wait_ready(int n)
+{
+    while (atomic_load(&ready) < n)
+        sched_yield();
+    usleep(100000);
+}
+
+static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t cond = PTHREAD_COND_INITIALIZER;
+static int produced = 0;
+
+static void *consumer(void *arg)
+{
+    int *consumed = arg;
+    for (int i = 0; i < 100; i++) {
+        pthread_mutex_lock(&lock);
+        while (produced == 0)
+            pthread_cond_wait(&cond, &lock);
+        produced--;
+        (*consumed)++;
+        pthread_mutex_unlock(&lock);
+    }
+    return NULL;
+}
+
+int main(void)
+{
+    pthread_t threads[NR_WAITERS];
+
+    // 值不相等时立即返回 EAGAIN
+    word = 1;
+    errno = 0;
+    check(futex(&word, FUTEX_WAIT_PRIVATE, 0, 0, NULL, 0) == -1 && errno == EAGAIN,
+          "FUTEX_WAIT with changed value is EAGAIN");
+
+    // 超时返回 ETIMEDOUT
+    struct timespec ts = {0, 10000000};
+    errno = 0;
+    check(futex(&word, FUTEX_WAIT_PRIVATE, 1, (long)&ts, NULL, 0) == -1 && errno == ETIMEDOUT,
+          "FUTEX_WAIT timeout is ETIMEDOUT");
+    ts.tv_nsec = 1000000000;
+    errno = 0;
+    check(futex(&word, FUTEX_WAIT_PRIVATE, 1, (long)&ts, NULL, 0) == -1 && errno == EINVAL,
+          "FUTEX_WAIT invalid timeout is EINVAL");
+
+    // 没有等待者时唤醒 0 个
+    check(futex(&word, FUTEX_WAKE_PRIVATE, 1, 0, NULL, 0) == 0, "FUTEX_WAKE without waiters");
+
+    // FUTEX_WAKE 至多唤醒 val 个等待者
+    word = 0;
+    for (int i = 0; i < NR_WAITERS; i++)
+        pthread_create(&threads[i], NULL, waiter, &word);
+    wait_ready(NR_WAITERS);
+    check(futex(&word, FUTEX_WAKE_PRIVATE, 1, 0, NULL, 0) == 1, "FUTEX_WAKE wakes one");
+    usleep(100000);
+    check(atomic_load(&woken) == 1, "only one waiter woken");
+    check(futex(&word, FUTEX_WAKE_PRIVATE, INT_MAX, 0, NULL, 0) == NR_WAITERS - 1,
+          "FUTEX_WAKE wakes the rest");
+    for (int i = 0; i < NR_WAITERS; i++)
+        pthread_join(threads[i], NULL);
+    check(atomic_load(&woken) == NR_WAITERS, "all waiters woken");
 
-int main() {
-    int futex_var = 0;
+    // FUTEX_REQUEUE 将等待者移动到另一个地址
+    atomic_store(&ready, 0);
+    atomic_store(&woken, 0);
+    for (int i = 0; i < 2; i++)
+        pthread_create(&threads[i], NULL, waiter, &word);
+    wait_ready(2);
+    errno = 0;
+    check(futex(&word, FUTEX_CMP_REQUEUE_PRIVATE, 0, 2, &word2, 1) == -1 && errno == EAGAIN,
+          "FUTEX_CMP_REQUEUE with changed value is EAGAIN");
+    // 返回值为唤醒与移动的数目之和
+    check(futex(&word, FUTEX_REQUEUE_PRIVATE, 0, 2, &word2, 0) == 2, "FUTEX_REQUEUE moves two");
+    check(futex(&word, FUTEX_WAKE_PRIVATE, INT_MAX, 0, NULL, 0) == 0, "requeued waiters moved");
+    check(futex(&word2, FUTEX_WAKE_PRIVATE, INT_MAX, 0, NULL, 0) == 2,
+          "requeued waiters woken at new address");
+    for (int i = 0; i < 2; i++)
+        pthread_join(threads[i], NULL);
 
-    // 创建一个 futex，初始值为 0
-    int *futex_ptr = &futex_var;
+    // 共享内存中的 futex 可以跨进程唤醒
+    int *shared = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    check(shared != MAP_FAILED, "mmap shared");
+    shared[0] = 0;
+    shared[1] = 0;
+    pid_t pid = fork();
+    if (pid == 0) {
+        __atomic_store_n(&shared[1], 1, __ATOMIC_SEQ_CST);
+        while (__atomic_load_n(&shared[0], __ATOMIC_SEQ_CST) == 0)
+            futex(&shared[0], FUTEX_WAIT, 0, 0, NULL, 0);
+        _exit(0);
+    }
+    while (__atomic_load_n(&shared[1], __ATOMIC_SEQ_CST) == 0)
+        sched_yield();
+    usleep(100000);
+    __atomic_store_n(&shared[0], 1, __ATOMIC_SEQ_CST);
+    check(futex(&shared[0], FUTEX_WAKE, 1, 0, NULL, 0) == 1, "shared futex wakes other process");
+    int status = 0;
+    check(waitpid(pid, &status, 0) == pid && WIFEXITED(status), "shared futex child exits");
 
-    // 唤醒等待在 futex 上的线程（私有唤醒）
-    syscall(SYS_futex, futex_ptr, FUTEX_WAKE_PRIVATE, 1, NULL, NULL, 0);
+    // pthread 的互斥锁与条件变量建立在 futex 之上
+    int consumed = 0;
+    pthread_t cons;
+    pthread_create(&cons, NULL, consumer, &consumed);
+    for (int i = 0; i < 100; i++) {
+        pthread_mutex_lock(&lock);
+        produced++;
+        pthread_cond_signal(&cond);
+        pthread_mutex_unlock(&lock);
+    }
+    pthread_join(cons, NULL);
+    check(consumed == 100 && produced == 0, "mutex and condition variable");
 
-    return 0;
+    puts(failed ? "futex test failed" : "futex test passed");
+    return failed;
 }
@@ -0,0 +1,45 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "check.h"
+
+int main(void)
+{
+    char base[256], expected[300], cwd[300];
+    check(getcwd(base, sizeof(base)) != NULL, "getcwd");
+    mkdir("getcwd_dir", 0755);
+    mkdir("getcwd_dir/sub", 0755);
+    snprintf(expected, sizeof(expected), "%s%sgetcwd_dir/sub", base,
+             strcmp(base, "/") == 0 ? "" : "/");
+
+    // 路径中的 "."、".." 与多余的 '/' 不会出现在 getcwd 的结果中
+    check(chdir("getcwd_dir//./sub/../sub/") == 0, "chdir with dot segments");
+    check(getcwd(cwd, sizeof(cwd)) != NULL && strcmp(cwd, expected) == 0,
+          "getcwd returns the canonical path");
+
+    // 缓冲区不足以容纳结尾的 '\0' 时返回 ERANGE
+    check(getcwd(cwd, strlen(expected)) == NULL && errno == ERANGE, "getcwd with a short buffer");
+
+    // 经过指向目录的符号链接 chdir 后，getcwd 返回链接指向的目录
+    chdir(base);
+    check(symlink("getcwd_dir", "getcwd_link") == 0, "symlink to a directory");
+    check(chdir("getcwd_link/sub") == 0, "chdir through a symlink");
+    check(getcwd(cwd, sizeof(cwd)) != NULL && strcmp(cwd, expected) == 0,
+          "getcwd resolves the symlink");
+    chdir(base);
+    check(symlink("getcwd_link/sub", "getcwd_link2") == 0, "symlink through a symlink");
+    check(chdir("getcwd_link2") == 0, "chdir to a symlink");
+    check(getcwd(cwd, sizeof(cwd)) != NULL && strcmp(cwd, expected) == 0,
+          "getcwd resolves nested symlinks");
+    chdir(base);
+    unlink("getcwd_link2");
+    unlink("getcwd_link");
+
+    rmdir("getcwd_dir/sub");
+    rmdir("getcwd_dir");
+    puts(failed ? "getcwd test failed" : "getcwd test passed");
+    return failed;
+}
@@ -0,0 +1,132 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_DIR "getdents_dir"
+#define FILES 20
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+static void cleanup(void)
+{
+    unlink(TEST_DIR "/file");
+    unlink(TEST_DIR "/a_much_longer_file_name");
+    rmdir(TEST_DIR "/sub");
+    rmdir(TEST_DIR);
+}
+
+int main(void)
+{
+    char buf[1024] __attribute__((aligned(8)));
+    cleanup();
+    check(mkdir(TEST_DIR, 0755) == 0, "mkdir");
+    check(mkdir(TEST_DIR "/sub", 0755) == 0, "mkdir sub");
+    close(open(TEST_DIR "/file", O_WRONLY | O_CREAT, 0644));
+    close(open(TEST_DIR "/a_much_longer_file_name", O_WRONLY | O_CREAT, 0644));
+
+    // 每个目录项按 8 字节对齐，d_type 与文件类型一致
+    int fd = open(TEST_DIR, O_RDONLY | O_DIRECTORY);
+    check(fd >= 0, "open dir");
+    long n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    check(n > 0 && n % 8 == 0, "getdents64 returns whole records");
+    int seen = 0;
+    for (long pos = 0; pos < n;) {
+        struct linux_dirent64 *d = (struct linux_dirent64 *)(buf + pos);
+        check(d->d_reclen % 8 == 0, "d_reclen is 8-byte aligned");
+        check(strlen(d->d_name) + 19 < d->d_reclen, "d_name is NUL terminated");
+        if (strcmp(d->d_name, "sub") == 0) {
+            check(d->d_type == DT_DIR, "d_type of a directory");
+            seen++;
+        } else if (strcmp(d->d_name, "file") == 0 ||
+                   strcmp(d->d_name, "a_much_longer_file_name") == 0) {
+            check(d->d_type == DT_REG, "d_type of a regular file");
+            seen++;
+        }
+        pos += d->d_reclen;
+    }
+    check(seen == 3, "all entries are listed");
+    check(syscall(SYS_getdents64, fd, buf, sizeof(buf)) == 0, "0 at end of directory");
+
+    // 缓冲区很小时分多次读出，最终返回 0
+    check(lseek(fd, 0, SEEK_SET) == 0, "rewind");
+    long total = 0, part;
+    int calls = 0;
+    while ((part = syscall(SYS_getdents64, fd, buf, 64)) > 0) {
+        total += part;
+        calls++;
+    }
+    check(part == 0 && total == n && calls > 1, "small buffer reads every entry");
+
+    // 一个目录项也放不下时返回 EINVAL
+    check(lseek(fd, 0, SEEK_SET) == 0, "rewind again");
+    errno = 0;
+    check(syscall(SYS_getdents64, fd, buf, 8) == -1 && errno == EINVAL, "tiny buffer sets EINVAL");
+    close(fd);
+
+    // fd 无效时返回 EBADF，不是目录时返回 ENOTDIR
+    errno = 0;
+    check(syscall(SYS_getdents64, 12345, buf, sizeof(buf)) == -1 && errno == EBADF,
+          "closed fd sets EBADF");
+    fd = open(TEST_DIR "/file", O_RDONLY);
+    errno = 0;
+    check(syscall(SYS_getdents64, fd, buf, sizeof(buf)) == -1 && errno == ENOTDIR,
+          "regular file sets ENOTDIR");
+    close(fd);
+
+    // readdir 建立在 getdents64 之上
+    DIR *dir = opendir(TEST_DIR);
+    int entries = 0;
+    struct dirent *ent;
+    while (dir && (ent = readdir(dir)) != NULL)
+        entries++;
+    check(dir != NULL && entries == 5, "readdir lists . .. and three entries");
+    if (dir)
+        closedir(dir);
+
+    // 像 rm -r 一样边读边删除读到的目录项，剩下的目录项不会被跳过
+    char name[64];
+    for (int i = 0; i < FILES; i++) {
+        snprintf(name, sizeof(name), TEST_DIR "/f%02d", i);
+        close(open(name, O_WRONLY | O_CREAT, 0644));
+    }
+    fd = open(TEST_DIR, O_RDONLY | O_DIRECTORY);
+    int removed = 0;
+    while ((n = syscall(SYS_getdents64, fd, buf, 96)) > 0) {
+        for (long pos = 0; pos < n;) {
+            struct linux_dirent64 *d = (struct linux_dirent64 *)(buf + pos);
+            if (d->d_name[0] == 'f' && d->d_name[1] != 'i') {
+                snprintf(name, sizeof(name), TEST_DIR "/%s", d->d_name);
+                removed += unlink(name) == 0;
+            }
+            pos += d->d_reclen;
+        }
+    }
+    check(n == 0 && removed == FILES, "deleting while reading visits every entry");
+    // lseek 回到开头之后重新读出全部目录项
+    check(lseek(fd, 0, SEEK_SET) == 0, "rewind after deleting");
+    n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    entries = 0;
+    for (long pos = 0; pos < n; pos += ((struct linux_dirent64 *)(buf + pos))->d_reclen)
+        entries++;
+    check(entries == 5, "rewind lists the remaining entries again");
+    close(fd);
+
+    cleanup();
+
+    puts(failed ? "getdents test failed" : "getdents test passed");
+    return failed;
+}
@@ -0,0 +1,76 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_DIR "getdents_ino_dir"
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+// getdents64 读出 TEST_DIR 中名为 name 的目录项的 d_ino，找不到时返回 0
+static unsigned long long dirent_ino(const char *name)
+{
+    char buf[4096];
+    unsigned long long ino = 0;
+    int fd = open(TEST_DIR, O_RDONLY | O_DIRECTORY);
+    if (fd < 0)
+        return 0;
+    long n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    for (long pos = 0; pos < n;) {
+        struct linux_dirent64 *d = (struct linux_dirent64 *)(buf + pos);
+        if (strcmp(d->d_name, name) == 0)
+            ino = d->d_ino;
+        pos += d->d_reclen;
+    }
+    close(fd);
+    return ino;
+}
+
+static unsigned long long stat_ino(const char *path)
+{
+    struct stat st;
+    return stat(path, &st) == 0 ? st.st_ino : 0;
+}
+
+int main(void)
+{
+    unlink(TEST_DIR "/file");
+    rmdir(TEST_DIR "/sub");
+    rmdir(TEST_DIR);
+    check(mkdir(TEST_DIR, 0755) == 0, "mkdir");
+    check(mkdir(TEST_DIR "/sub", 0755) == 0, "mkdir sub");
+    int fd = open(TEST_DIR "/file", O_WRONLY | O_CREAT, 0644);
+    check(fd >= 0, "create file");
+    close(fd);
+
+    // 目录项中的 inode 号与 stat 得到的一致
+    unsigned long long file_ino = dirent_ino("file");
+    check(file_ino != 0, "file has a d_ino");
+    check(file_ino == stat_ino(TEST_DIR "/file"), "d_ino of a file equals st_ino");
+    struct stat st;
+    fd = open(TEST_DIR "/file", O_RDONLY);
+    check(fd >= 0 && fstat(fd, &st) == 0 && st.st_ino == file_ino, "d_ino equals fstat st_ino");
+    close(fd);
+    check(dirent_ino("sub") == stat_ino(TEST_DIR "/sub"), "d_ino of a directory equals st_ino");
+    check(dirent_ino("sub") != file_ino, "different files have different inode numbers");
+
+    unlink(TEST_DIR "/file");
+    rmdir(TEST_DIR "/sub");
+    rmdir(TEST_DIR);
+
+    puts(failed ? "getdents_ino test failed" : "getdents_ino test passed");
+    return failed;
+}
@@ -0,0 +1,33 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/random.h>
+
+#include "check.h"
+
+int main(void)
+{
+    unsigned char a[64], b[64];
+
+    // GRND_RANDOM 与默认随机数源相同，一次可以读满请求的长度
+    memset(a, 0, sizeof(a));
+    memset(b, 0, sizeof(b));
+    check(getrandom(a, sizeof(a), GRND_RANDOM) == sizeof(a), "GRND_RANDOM fills the buffer");
+    check(getrandom(b, sizeof(b), GRND_RANDOM) == sizeof(b), "second GRND_RANDOM fills the buffer");
+    check(memcmp(a, b, sizeof(a)) != 0, "successive GRND_RANDOM reads differ");
+
+    // 随机数生成器在内核初始化时已完成初始化，GRND_NONBLOCK 不会返回 EAGAIN
+    errno = 0;
+    check(getrandom(a, sizeof(a), GRND_NONBLOCK) == sizeof(a), "GRND_NONBLOCK does not block after boot");
+    check(errno != EAGAIN, "GRND_NONBLOCK does not return EAGAIN after boot");
+    errno = 0;
+    check(getrandom(a, sizeof(a), GRND_RANDOM | GRND_NONBLOCK) == sizeof(a),
+          "GRND_RANDOM | GRND_NONBLOCK fills the buffer");
+
+    // 大于内核分块大小的请求也能一次读满
+    static unsigned char big[4096];
+    check(getrandom(big, sizeof(big), 0) == sizeof(big), "large request is filled");
+
+    puts(failed ? "getrandom test failed" : "getrandom test passed");
+    return failed;
+}
@@ -0,0 +1,53 @@
+#define _GNU_SOURCE
+#include <pthread.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static pid_t thread_pid, thread_tid;
+
+static void *thread_fn(void *arg)
+{
+    (void)arg;
+    thread_pid = getpid();
+    thread_tid = syscall(SYS_gettid);
+    return NULL;
+}
+
+int main(void)
+{
+    int failed = 0;
+    pid_t pid = getpid();
+    pid_t tid = syscall(SYS_gettid);
+
+    // 主线程的 tid 与 pid 相同
+    if (pid <= 0 || tid != pid) {
+        printf("main thread: pid %d, tid %d\n", pid, tid);
+        failed = 1;
+    }
+
+    // 其他线程的 pid 与主线程相同，tid 不同
+    pthread_t thread;
+    pthread_create(&thread, NULL, thread_fn, NULL);
+    pthread_join(thread, NULL);
+    if (thread_pid != pid || thread_tid == pid || thread_tid <= 0) {
+        printf("thread: pid %d, tid %d\n", thread_pid, thread_tid);
+        failed = 1;
+    }
+
+    // 子进程拥有新的 pid，其父进程为当前进程
+    pid_t child = fork();
+    if (child == 0) {
+        _exit(getpid() != pid && getppid() == pid ? 0 : 1);
+    }
+    int status = 0;
+    waitpid(child, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        puts("child pid/ppid mismatch");
+        failed = 1;
+    }
+
+    puts(failed ? "gettid test failed" : "gettid test passed");
+    return failed;
+}
@@ -0,0 +1,57 @@
+#include <errno.h>
+#include <linux/futex.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+// 时钟 tick 为 10ms，短于一个 tick 的睡眠不应被延长到下一个 tick
+#define ROUNDS 50
+#define SHORT_NS 1000000L
+
+static long elapsed_ns(const struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000000000L + (now.tv_nsec - start->tv_nsec);
+}
+
+int main(void)
+{
+    struct timespec start;
+    struct timespec req = {0, SHORT_NS};
+
+    // 睡眠不会提前返回，且总时间远小于 ROUNDS 个 tick
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    for (int i = 0; i < ROUNDS; i++)
+        check(nanosleep(&req, NULL) == 0, "nanosleep");
+    long total = elapsed_ns(&start);
+    check(total >= ROUNDS * SHORT_NS, "nanosleep does not return early");
+    check(total < ROUNDS * SHORT_NS * 5, "short sleeps are not rounded up to ticks");
+
+    // futex 的超时同样由定时器队列驱动
+    int word = 0;
+    struct timespec timeout = {0, SHORT_NS};
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    for (int i = 0; i < ROUNDS; i++) {
+        errno = 0;
+        check(syscall(SYS_futex, &word, FUTEX_WAIT_PRIVATE, 0, &timeout, NULL, 0) == -1 &&
+                  errno == ETIMEDOUT,
+              "futex timeout");
+    }
+    total = elapsed_ns(&start);
+    check(total >= ROUNDS * SHORT_NS, "futex timeout does not expire early");
+    check(total < ROUNDS * SHORT_NS * 5, "futex timeouts are not rounded up to ticks");
+
+    // 跨越多个 tick 的睡眠在截止时间附近唤醒
+    struct timespec longer = {0, 30 * SHORT_NS};
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(nanosleep(&longer, NULL) == 0, "long nanosleep");
+    total = elapsed_ns(&start);
+    check(total >= 30 * SHORT_NS && total < 60 * SHORT_NS, "long sleep is accurate");
+
+    puts(failed ? "hrtimer test failed" : "hrtimer test passed");
+    return failed;
+}
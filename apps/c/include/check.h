@@ -0,0 +1,19 @@
+#ifndef STARRY_CHECK_H
+#define STARRY_CHECK_H
+
+#include <errno.h>
+#include <stdio.h>
+
+// apps/c 下测例共用的检查函数：条件不成立时打印信息并记录失败，main 最后返回 failed
+static int failed = 0;
+
+static void check(int cond, const char *msg)
+{
+    if (!cond) {
+        int err = errno;
+        printf("FAIL: %s (errno %d)\n", msg, err);
+        failed = 1;
+    }
+}
+
+#endif
@@ -0,0 +1,38 @@
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "check.h"
+
+// 作为 init 运行，检查内核交给第一个进程的参数与环境变量，内核命令行为
+//     init=/init_env INIT_ENV_TEST=hello PATH=/bin -- first
+static int env_is(const char *name, const char *value)
+{
+    const char *env = getenv(name);
+    return env != NULL && strcmp(env, value) == 0;
+}
+
+int main(int argc, char *argv[], char *envp[])
+{
+    // argv[0] 是 init 程序的路径，-- 之后的内容作为参数
+    const char *name = strrchr(argv[0], '/');
+    check(strcmp(name ? name + 1 : argv[0], "init_env") == 0, "argv[0] is the init program");
+    check(argc == 2 && strcmp(argv[1], "first") == 0, "arguments after -- reach init");
+    check(argv[argc] == NULL, "argv is NULL terminated");
+
+    // 默认的环境变量与 Linux 交给 init 的一致
+    check(env_is("HOME", "/"), "HOME is /");
+    check(env_is("TERM", "linux"), "TERM is linux");
+
+    // 命令行中的 KEY=value 成为环境变量，并覆盖默认值
+    check(env_is("INIT_ENV_TEST", "hello"), "KEY=value from the command line");
+    check(env_is("PATH", "/bin"), "command line overrides the default PATH");
+    int paths = 0;
+    for (char **env = envp; *env; env++)
+        paths += strncmp(*env, "PATH=", 5) == 0;
+    check(paths == 1, "PATH appears once");
+
+    puts(failed ? "init_env test failed" : "init_env test passed");
+    return failed;
+}
@@ -0,0 +1,72 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main(void)
+{
+    int failed = 0;
+    int fds[2], avail = -1;
+
+    // 管道中可读的字节数在写入前后的变化
+    if (pipe(fds) != 0) {
+        puts("pipe failed");
+        return 1;
+    }
+    if (ioctl(fds[0], FIONREAD, &avail) != 0 || avail != 0) {
+        printf("FIONREAD on empty pipe: %d\n", avail);
+        failed = 1;
+    }
+    write(fds[1], "hello", 5);
+    if (ioctl(fds[0], FIONREAD, &avail) != 0 || avail != 5) {
+        printf("FIONREAD after write: %d\n", avail);
+        failed = 1;
+    }
+    // 结果写不到用户内存时返回 EFAULT
+    errno = 0;
+    if (ioctl(fds[0], FIONREAD, (int *)1) != -1 || errno != EFAULT) {
+        puts("FIONREAD to a bad pointer should fail with EFAULT");
+        failed = 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // FIONBIO 与 fcntl 设置 O_NONBLOCK 等价
+    int sock = socket(AF_INET, SOCK_STREAM, 0);
+    if (sock < 0) {
+        puts("socket failed");
+        return 1;
+    }
+    int on = 1;
+    if (ioctl(sock, FIONBIO, &on) != 0 || !(fcntl(sock, F_GETFL) & O_NONBLOCK)) {
+        puts("FIONBIO did not set O_NONBLOCK");
+        failed = 1;
+    }
+    int off = 0;
+    if (ioctl(sock, FIONBIO, &off) != 0 || (fcntl(sock, F_GETFL) & O_NONBLOCK)) {
+        puts("FIONBIO did not clear O_NONBLOCK");
+        failed = 1;
+    }
+
+    // 不支持的请求返回 ENOTTY
+    if (ioctl(sock, 0x7fff, &on) != -1 || errno != ENOTTY) {
+        puts("unknown ioctl should fail with ENOTTY");
+        failed = 1;
+    }
+    close(sock);
+
+    // 普通文件不是终端，即使 argp 为空也返回 ENOTTY
+    int file = open("ioctl_fion.txt", O_CREAT | O_RDWR, 0644);
+    errno = 0;
+    if (file < 0 || ioctl(file, TIOCGWINSZ, NULL) != -1 || errno != ENOTTY) {
+        puts("TIOCGWINSZ on a regular file should fail with ENOTTY");
+        failed = 1;
+    }
+    close(file);
+    unlink("ioctl_fion.txt");
+
+    puts(failed ? "ioctl_fion test failed" : "ioctl_fion test passed");
+    return failed;
+}
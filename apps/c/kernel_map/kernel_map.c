@@ -0,0 +1,48 @@
+#define _GNU_SOURCE
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+// 内核以 selftest feature 编译时提供的自测系统调用，见 linux_syscall_api 的 selftest 模块
+#define SYS_SELFTEST 500
+#define KERNEL_MAP 0
+#define KERNEL_READ 1
+#define KERNEL_MAP_MAGIC 0x5a5ac0de
+
+// 子进程 fork 之后，父进程在一个新的内核顶级页表项中新增映射，
+// 子进程在自己的系统调用上下文中应当能够经由该映射读出内核写入的值
+int main()
+{
+    errno = 0;
+    if (syscall(SYS_SELFTEST, KERNEL_READ) == -1 && errno == ENOSYS) {
+        puts("kernel_map test skipped: kernel built without selftest");
+        return 0;
+    }
+
+    int pipefd[2];
+    check(pipe(pipefd) == 0, "pipe");
+    pid_t pid = fork();
+    if (pid == 0) {
+        char c;
+        close(pipefd[1]);
+        // 等待父进程建立映射
+        if (read(pipefd[0], &c, 1) != 1)
+            _exit(2);
+        _exit(syscall(SYS_SELFTEST, KERNEL_READ) == KERNEL_MAP_MAGIC ? 0 : 1);
+    }
+    check(pid > 0, "fork");
+    close(pipefd[0]);
+    check(syscall(SYS_SELFTEST, KERNEL_MAP) == 0, "map kernel region");
+    check(syscall(SYS_SELFTEST, KERNEL_READ) == KERNEL_MAP_MAGIC, "read in parent");
+    check(write(pipefd[1], "x", 1) == 1, "notify child");
+
+    int status;
+    check(waitpid(pid, &status, 0) == pid, "waitpid");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 0,
+          "kernel mapping added after fork not visible in child");
+
+    puts(failed ? "kernel_map test failed" : "kernel_map test passed");
+    return failed;
+}
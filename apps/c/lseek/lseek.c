@@ -0,0 +1,117 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_FILE "lseek_test.txt"
+#define TEST_DIR "lseek_dir"
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+// 目录的偏移量是 getdents64 读取的位置，可以回到开头或之前的 d_off
+static void check_dir(void)
+{
+    char buf[1024];
+    unlink(TEST_DIR "/a");
+    unlink(TEST_DIR "/b");
+    rmdir(TEST_DIR);
+    check(mkdir(TEST_DIR, 0755) == 0, "mkdir");
+    close(open(TEST_DIR "/a", O_WRONLY | O_CREAT, 0644));
+    close(open(TEST_DIR "/b", O_WRONLY | O_CREAT, 0644));
+
+    int fd = open(TEST_DIR, O_RDONLY | O_DIRECTORY);
+    check(fd >= 0, "open dir");
+    long first = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    check(first > 0, "getdents64");
+    check(syscall(SYS_getdents64, fd, buf, sizeof(buf)) == 0, "getdents64 at end of dir");
+    check(lseek(fd, 0, SEEK_SET) == 0, "rewind dir");
+    long again = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    check(again == first, "getdents64 after rewind returns everything again");
+
+    // 回到第一个目录项之后，读出的是剩下的目录项
+    struct linux_dirent64 *d = (struct linux_dirent64 *)buf;
+    check(lseek(fd, d->d_off, SEEK_SET) == d->d_off, "seek dir to d_off");
+    check(syscall(SYS_getdents64, fd, buf, sizeof(buf)) == first - d->d_reclen,
+          "getdents64 after seeking to d_off");
+    close(fd);
+
+    unlink(TEST_DIR "/a");
+    unlink(TEST_DIR "/b");
+    rmdir(TEST_DIR);
+}
+
+int main(void)
+{
+    char buf[16];
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "open");
+    check(write(fd, "0123456789", 10) == 10, "write");
+
+    // 写入之后偏移量位于文件末尾
+    check(lseek(fd, 0, SEEK_CUR) == 10, "offset advances with write");
+
+    // read 与 lseek 交替进行
+    check(lseek(fd, 2, SEEK_SET) == 2, "SEEK_SET");
+    check(read(fd, buf, 3) == 3 && memcmp(buf, "234", 3) == 0, "read after SEEK_SET");
+    check(lseek(fd, 0, SEEK_CUR) == 5, "read advances offset");
+    check(lseek(fd, -2, SEEK_CUR) == 3, "SEEK_CUR backwards");
+    check(read(fd, buf, 2) == 2 && memcmp(buf, "34", 2) == 0, "read after SEEK_CUR");
+    check(lseek(fd, -1, SEEK_END) == 9, "SEEK_END");
+    check(read(fd, buf, sizeof(buf)) == 1 && buf[0] == '9', "read after SEEK_END");
+    check(read(fd, buf, sizeof(buf)) == 0, "read at end of file");
+
+    // 结果为负的偏移量与非法的 whence
+    errno = 0;
+    check(lseek(fd, -1, SEEK_SET) == -1 && errno == EINVAL, "negative SEEK_SET sets EINVAL");
+    errno = 0;
+    check(lseek(fd, -11, SEEK_END) == -1 && errno == EINVAL, "negative SEEK_END sets EINVAL");
+    errno = 0;
+    check(lseek(fd, 0, 42) == -1 && errno == EINVAL, "bad whence sets EINVAL");
+    errno = 0;
+    check(lseek(fd, LLONG_MAX, SEEK_CUR) == -1 && errno == EINVAL, "offset overflowing off_t sets EINVAL");
+    check(lseek(fd, 0, SEEK_CUR) == 10, "failed lseek keeps offset");
+
+    // 越过文件末尾写入，中间的空洞读出为 0
+    check(lseek(fd, 12, SEEK_SET) == 12, "seek past end");
+    check(write(fd, "x", 1) == 1, "write past end");
+    check(lseek(fd, 10, SEEK_SET) == 10, "seek into the hole");
+    check(read(fd, buf, 3) == 3 && buf[0] == 0 && buf[1] == 0 && buf[2] == 'x', "hole reads as zero");
+
+    // 两次 open 得到的偏移量相互独立，dup 得到的则共享
+    int other = open(TEST_FILE, O_RDONLY);
+    check(other >= 0 && lseek(other, 0, SEEK_CUR) == 0, "new open starts at 0");
+    int dup_fd = dup(fd);
+    check(lseek(fd, 4, SEEK_SET) == 4 && lseek(dup_fd, 0, SEEK_CUR) == 4, "dup shares offset");
+    close(dup_fd);
+    close(other);
+    close(fd);
+
+    // 管道不能 seek，已关闭的 fd 返回 EBADF
+    int pipe_fds[2];
+    check(pipe(pipe_fds) == 0, "pipe");
+    errno = 0;
+    check(lseek(pipe_fds[0], 0, SEEK_CUR) == -1 && errno == ESPIPE, "pipe sets ESPIPE");
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    errno = 0;
+    check(lseek(fd, 0, SEEK_SET) == -1 && errno == EBADF, "closed fd sets EBADF");
+    unlink(TEST_FILE);
+
+    check_dir();
+
+    puts(failed ? "lseek test failed" : "lseek test passed");
+    return failed;
+}
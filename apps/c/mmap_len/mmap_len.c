@@ -0,0 +1,39 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/mman.h>
+
+static int expect_einval(const char *name, void *addr, size_t len, int flags)
+{
+    void *ptr = mmap(addr, len, PROT_READ | PROT_WRITE, flags, -1, 0);
+    if (ptr != MAP_FAILED || errno != EINVAL) {
+        printf("%s: expected EINVAL, got %p (errno %d)\n", name, ptr, errno);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    int failed = 0;
+    int flags = MAP_PRIVATE | MAP_ANONYMOUS;
+
+    failed |= expect_einval("zero length", NULL, 0, flags);
+    // 向上对齐到页时溢出
+    failed |= expect_einval("overflowing length", NULL, SIZE_MAX - 1, flags);
+    // 起始地址加长度溢出
+    failed |= expect_einval("overflowing end", (void *)0x10000, SIZE_MAX - 0xffff, flags | MAP_FIXED);
+
+    // 正常长度依然可以映射，且按页对齐
+    char *ptr = mmap(NULL, 100, PROT_READ | PROT_WRITE, flags, -1, 0);
+    if (ptr == MAP_FAILED) {
+        puts("mmap of 100 bytes failed");
+        failed = 1;
+    } else {
+        ptr[4095] = 1;
+        munmap(ptr, 100);
+    }
+
+    puts(failed ? "mmap_len test failed" : "mmap_len test passed");
+    return failed;
+}
@@ -0,0 +1,57 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+
+int main(void)
+{
+    // 文件共两页半：第 i 页的内容全为 'a' + i
+    int fd = open("mmap_offset_test.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "open");
+    char buf[PAGE];
+    for (int i = 0; i < 3; i++) {
+        memset(buf, 'a' + i, sizeof(buf));
+        check(write(fd, buf, i < 2 ? PAGE : PAGE / 2) > 0, "write");
+    }
+
+    // 从第二页开始映射，读到的是文件中 offset 处的内容
+    char *p = mmap(NULL, 2 * PAGE, PROT_READ, MAP_PRIVATE, fd, PAGE);
+    check(p != MAP_FAILED, "mmap with offset");
+    if (p != MAP_FAILED) {
+        check(p[0] == 'b' && p[PAGE - 1] == 'b', "first page comes from the offset");
+        check(p[PAGE] == 'c' && p[PAGE + PAGE / 2 - 1] == 'c', "second page follows");
+        check(p[PAGE + PAGE / 2] == 0 && p[2 * PAGE - 1] == 0, "past EOF is zero-filled");
+        munmap(p, 2 * PAGE);
+    }
+
+    // 先写入再读取的页面走另一条缺页路径，同样要按 offset 读取并填零
+    p = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 2 * PAGE);
+    check(p != MAP_FAILED, "writable private mmap with offset");
+    if (p != MAP_FAILED) {
+        p[0] = 'X';
+        check(p[1] == 'c' && p[PAGE / 2] == 0 && p[PAGE - 1] == 0, "write fault reads at offset");
+        munmap(p, PAGE);
+    }
+
+    // 共享映射同样从 offset 开始
+    p = mmap(NULL, PAGE, PROT_READ, MAP_SHARED, fd, PAGE);
+    check(p != MAP_FAILED && p[0] == 'b', "shared mmap with offset");
+    if (p != MAP_FAILED)
+        munmap(p, PAGE);
+
+    // offset 没有按页对齐
+    errno = 0;
+    p = mmap(NULL, PAGE, PROT_READ, MAP_PRIVATE, fd, 100);
+    check(p == MAP_FAILED && errno == EINVAL, "unaligned offset gives EINVAL");
+
+    close(fd);
+    unlink("mmap_offset_test.txt");
+    puts(failed ? "mmap_offset test failed" : "mmap_offset test passed");
+    return failed;
+}
@@ -0,0 +1,65 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+// 在子进程中访问 addr，返回子进程是否因 SIGSEGV 退出
+static int access_faults(volatile char *addr, int write)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        if (write) {
+            *addr = 1;
+        } else {
+            (void)*addr;
+        }
+        _exit(0);
+    }
+    int status = 0;
+    waitpid(pid, &status, 0);
+    return WIFSIGNALED(status) && WTERMSIG(status) == SIGSEGV;
+}
+
+int main(void)
+{
+    long page = sysconf(_SC_PAGESIZE);
+
+    // 只读映射：读取正常，写入触发缺页并收到 SIGSEGV
+    char *ro = mmap(NULL, page, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(ro != MAP_FAILED, "mmap PROT_READ");
+    check(!access_faults(ro, 0), "read from a PROT_READ mapping");
+    check(access_faults(ro, 1), "write to a PROT_READ mapping faults");
+
+    // 内核代替进程写入只读映射时返回 EFAULT
+    int fds[2];
+    pipe(fds);
+    write(fds[1], "x", 1);
+    errno = 0;
+    check(read(fds[0], ro, 1) == -1 && errno == EFAULT, "read into a PROT_READ buffer");
+
+    // PROT_NONE 的匿名映射可以作为保护区域，任何访问都会触发缺页
+    char *guard = mmap(NULL, page, PROT_NONE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(guard != MAP_FAILED, "mmap PROT_NONE anonymous");
+    check(access_faults(guard, 0), "read from a PROT_NONE mapping faults");
+
+    // 可写映射正常读写
+    char *rw = mmap(NULL, page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(rw != MAP_FAILED && !access_faults(rw, 1), "write to a PROT_WRITE mapping");
+
+    // 文件的 PROT_NONE 映射被拒绝
+    int fd = open("mmap_prot.txt", O_RDWR | O_CREAT, 0644);
+    write(fd, "data", 4);
+    errno = 0;
+    check(mmap(NULL, page, PROT_NONE, MAP_PRIVATE, fd, 0) == MAP_FAILED && errno == EINVAL,
+          "PROT_NONE file mapping is rejected");
+    close(fd);
+    unlink("mmap_prot.txt");
+
+    puts(failed ? "mmap_prot test failed" : "mmap_prot test passed");
+    return failed;
+}
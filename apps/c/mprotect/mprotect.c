@@ -0,0 +1,83 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+// 在子进程中写入 addr，返回子进程是否因 SIGSEGV 退出
+static int write_faults(volatile char *addr)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        *addr = 1;
+        _exit(0);
+    }
+    int status = 0;
+    waitpid(pid, &status, 0);
+    return WIFSIGNALED(status) && WTERMSIG(status) == SIGSEGV;
+}
+
+int main(void)
+{
+    long page = sysconf(_SC_PAGESIZE);
+
+    // 改为只读后写入触发 SIGSEGV，改回可写后数据不变
+    char *p = mmap(NULL, 3 * page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(p != MAP_FAILED, "mmap");
+    memset(p, 0x5a, 3 * page);
+    check(mprotect(p, 3 * page, PROT_READ) == 0, "mprotect PROT_READ");
+    check(write_faults(p), "write to a read-only page faults");
+    check(mprotect(p, 3 * page, PROT_READ | PROT_WRITE) == 0, "mprotect back to PROT_WRITE");
+    check(!write_faults(p) && p[0] == 0x5a, "writable again with the old data");
+
+    // 只修改中间的一页，映射区域被拆分
+    check(mprotect(p + page, page, PROT_READ) == 0, "mprotect the middle page");
+    check(!write_faults(p) && write_faults(p + page) && !write_faults(p + 2 * page),
+          "only the middle page is read-only");
+    check(mprotect(p, 3 * page, PROT_READ | PROT_WRITE) == 0, "mprotect across the split areas");
+    check(!write_faults(p + page), "split areas are writable again");
+
+    // 参数检查
+    errno = 0;
+    check(mprotect(p + 1, page, PROT_READ) == -1 && errno == EINVAL, "unaligned address: EINVAL");
+    errno = 0;
+    check(mprotect(p, page, 0x100) == -1 && errno == EINVAL, "unknown prot bit: EINVAL");
+    check(mprotect(p, 0, PROT_READ) == 0, "zero length");
+
+    // 范围中有未映射的部分
+    check(munmap(p + page, page) == 0, "munmap the middle page");
+    errno = 0;
+    check(mprotect(p, 3 * page, PROT_READ) == -1 && errno == ENOMEM, "range with a hole: ENOMEM");
+    munmap(p, 3 * page);
+    errno = 0;
+    check(mprotect(p, page, PROT_READ) == -1 && errno == ENOMEM, "unmapped range: ENOMEM");
+
+    // 以只读方式打开的文件：共享映射不能改为可写，私有映射可以
+    int fd = open("mprotect_test.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0 && write(fd, "data", 4) == 4, "create file");
+    close(fd);
+    fd = open("mprotect_test.txt", O_RDONLY);
+    char *shared = mmap(NULL, page, PROT_READ, MAP_SHARED, fd, 0);
+    check(shared != MAP_FAILED, "mmap shared read-only");
+    errno = 0;
+    check(mprotect(shared, page, PROT_READ | PROT_WRITE) == -1 && errno == EACCES,
+          "PROT_WRITE on a read-only shared file mapping: EACCES");
+    char *private = mmap(NULL, page, PROT_READ, MAP_PRIVATE, fd, 0);
+    check(private != MAP_FAILED, "mmap private read-only");
+    check(mprotect(private, page, PROT_READ | PROT_WRITE) == 0,
+          "PROT_WRITE on a private file mapping");
+    private[0] = 'D';
+    check(shared[0] == 'd', "private write does not reach the file");
+    munmap(shared, page);
+    munmap(private, page);
+    close(fd);
+    unlink("mprotect_test.txt");
+
+    puts(failed ? "mprotect test failed" : "mprotect test passed");
+    return failed;
+}
@@ -0,0 +1,96 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ipc.h>
+#include <sys/msg.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+struct message {
+    long mtype;
+    char mtext[32];
+};
+
+static int send(int msqid, long mtype, const char *text)
+{
+    struct message msg = {.mtype = mtype};
+    strcpy(msg.mtext, text);
+    return msgsnd(msqid, &msg, strlen(text) + 1, 0);
+}
+
+static int expect(int msqid, long msgtyp, int flags, const char *text)
+{
+    struct message msg;
+    ssize_t len = msgrcv(msqid, &msg, sizeof(msg.mtext), msgtyp, flags);
+    if (len < 0 || strcmp(msg.mtext, text) != 0) {
+        printf("msgrcv(type %ld): expected \"%s\", got %zd\n", msgtyp, text, len);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    int failed = 0;
+    int msqid = msgget(IPC_PRIVATE, IPC_CREAT | 0600);
+    if (msqid < 0) {
+        puts("msgget failed");
+        return 1;
+    }
+
+    // 按类型选择性接收
+    send(msqid, 3, "three");
+    send(msqid, 1, "one");
+    send(msqid, 2, "two");
+    send(msqid, 1, "another one");
+    failed |= expect(msqid, 2, 0, "two");
+    failed |= expect(msqid, -2, 0, "one");
+    failed |= expect(msqid, 1, MSG_EXCEPT, "three");
+    failed |= expect(msqid, 0, 0, "another one");
+
+    struct message msg;
+    if (msgrcv(msqid, &msg, sizeof(msg.mtext), 0, IPC_NOWAIT) != -1 || errno != ENOMSG) {
+        puts("empty queue with IPC_NOWAIT should fail with ENOMSG");
+        failed = 1;
+    }
+
+    // 消息过长时，只有设置了 MSG_NOERROR 才会截断
+    send(msqid, 5, "truncated message");
+    if (msgrcv(msqid, &msg, 4, 5, 0) != -1 || errno != E2BIG) {
+        puts("long message without MSG_NOERROR should fail with E2BIG");
+        failed = 1;
+    }
+    if (msgrcv(msqid, &msg, 4, 5, MSG_NOERROR) != 4 || strncmp(msg.mtext, "trun", 4) != 0) {
+        puts("MSG_NOERROR should truncate the message");
+        failed = 1;
+    }
+
+    // 阻塞的接收方被发送方唤醒
+    pid_t pid = fork();
+    if (pid == 0) {
+        int ret = expect(msqid, 7, 0, "wake up");
+        _exit(ret);
+    }
+    usleep(100000);
+    send(msqid, 7, "wake up");
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        puts("blocked receiver was not woken by the sender");
+        failed = 1;
+    }
+
+    struct msqid_ds ds;
+    if (msgctl(msqid, IPC_STAT, &ds) != 0 || ds.msg_qnum != 0 || ds.msg_lspid != getpid()) {
+        puts("IPC_STAT returned unexpected state");
+        failed = 1;
+    }
+    if (msgctl(msqid, IPC_RMID, NULL) != 0 || msgsnd(msqid, &msg, 1, 0) != -1) {
+        puts("queue should be gone after IPC_RMID");
+        failed = 1;
+    }
+
+    puts(failed ? "msg_queue test failed" : "msg_queue test passed");
+    return failed;
+}
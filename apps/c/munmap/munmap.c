@@ -0,0 +1,59 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+
+int main(void)
+{
+    // 参数检查
+    char *p = mmap(NULL, 3 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(p != MAP_FAILED, "mmap anonymous");
+    check(munmap(p + 1, PAGE) == -1 && errno == EINVAL, "unaligned address is EINVAL");
+    check(munmap(p, 0) == -1 && errno == EINVAL, "zero length is EINVAL");
+
+    // 解除中间一页，映射被拆分为两段
+    memset(p, 'a', 3 * PAGE);
+    check(munmap(p + PAGE, PAGE) == 0, "unmap middle page");
+    check(p[0] == 'a' && p[3 * PAGE - 1] == 'a', "pages around the hole survive");
+    // 再次映射到空洞处，应当是全新的零页
+    char *hole = mmap(p + PAGE, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1, 0);
+    check(hole == p + PAGE && hole[0] == 0, "hole is remapped with a zero page");
+
+    // 一次解除跨越多个映射的范围
+    check(munmap(p, 3 * PAGE) == 0, "unmap across several mappings");
+
+    // 拆分后尚未访问过的两侧页面仍能正常按需分配
+    char *lazy = mmap(NULL, 3 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(lazy != MAP_FAILED, "mmap lazy pages");
+    check(munmap(lazy + PAGE, PAGE) == 0, "unmap untouched middle page");
+    lazy[0] = 'x';
+    lazy[2 * PAGE] = 'y';
+    check(lazy[0] == 'x' && lazy[2 * PAGE] == 'y', "outer pages fault in after split");
+    check(munmap(lazy, 3 * PAGE) == 0, "unmap split mapping");
+
+    // 解除从未映射过的地址不是错误
+    check(munmap(lazy, PAGE) == 0, "unmap never-mapped range returns 0");
+
+    // 共享文件映射在解除时写回文件
+    int fd = open("munmap_test.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "open file");
+    ftruncate(fd, PAGE);
+    char *f = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    check(f != MAP_FAILED, "mmap file");
+    memcpy(f, "written", 7);
+    check(munmap(f, PAGE) == 0, "unmap file mapping");
+    char buf[8] = {0};
+    pread(fd, buf, 7, 0);
+    check(strcmp(buf, "written") == 0, "dirty page is written back");
+    close(fd);
+    unlink("munmap_test.txt");
+
+    puts(failed ? "munmap test failed" : "munmap test passed");
+    return failed;
+}
@@ -0,0 +1,116 @@
+#include <errno.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define MS 1000000L
+
+static long elapsed_ms(const struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / MS;
+}
+
+static atomic_int counter = 0;
+static atomic_int stop = 0;
+
+static void *spin(void *arg)
+{
+    (void)arg;
+    while (!atomic_load(&stop)) {
+        atomic_fetch_add(&counter, 1);
+        sched_yield();
+    }
+    return NULL;
+}
+
+static void on_signal(int sig)
+{
+    (void)sig;
+}
+
+int main(void)
+{
+    struct timespec start;
+    struct timespec req = {0, 50 * MS};
+    struct timespec rem = {0, 0};
+
+    // 睡眠期间其他任务继续运行
+    pthread_t thread;
+    pthread_create(&thread, NULL, spin, NULL);
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(nanosleep(&req, &rem) == 0, "nanosleep");
+    check(elapsed_ms(&start) >= 50, "nanosleep sleeps long enough");
+    check(atomic_load(&counter) > 0, "other tasks run during the sleep");
+    atomic_store(&stop, 1);
+    pthread_join(thread, NULL);
+
+    // 参数检查
+    struct timespec bad = {0, 1000000000};
+    errno = 0;
+    check(nanosleep(&bad, NULL) == -1 && errno == EINVAL, "tv_nsec out of range is EINVAL");
+    bad.tv_sec = -1;
+    bad.tv_nsec = 0;
+    errno = 0;
+    check(nanosleep(&bad, NULL) == -1 && errno == EINVAL, "negative tv_sec is EINVAL");
+    errno = 0;
+    check(syscall(SYS_nanosleep, (void *)1, NULL) == -1 && errno == EFAULT, "bad request is EFAULT");
+
+    // clock_nanosleep 支持相对时间与绝对时间
+    clockid_t clocks[] = {CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_BOOTTIME};
+    for (int i = 0; i < 3; i++) {
+        req.tv_nsec = 20 * MS;
+        clock_gettime(CLOCK_MONOTONIC, &start);
+        check(clock_nanosleep(clocks[i], 0, &req, NULL) == 0, "relative clock_nanosleep");
+        check(elapsed_ms(&start) >= 20, "relative clock_nanosleep sleeps long enough");
+    }
+    struct timespec deadline;
+    clock_gettime(CLOCK_MONOTONIC, &deadline);
+    start = deadline;
+    deadline.tv_nsec += 30 * MS;
+    if (deadline.tv_nsec >= 1000000000) {
+        deadline.tv_sec++;
+        deadline.tv_nsec -= 1000000000;
+    }
+    check(clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &deadline, NULL) == 0,
+          "absolute clock_nanosleep");
+    check(elapsed_ms(&start) >= 30, "absolute clock_nanosleep sleeps until the deadline");
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &deadline, NULL) == 0 &&
+              elapsed_ms(&start) < 10,
+          "past deadline returns immediately");
+    check(clock_nanosleep(CLOCK_THREAD_CPUTIME_ID, 0, &req, NULL) == EINVAL,
+          "unsupported clock is EINVAL");
+
+    // 被信号打断时返回 EINTR，并写回剩余时间
+    struct sigaction sa = {0};
+    sa.sa_handler = on_signal;
+    sigaction(SIGUSR1, &sa, NULL);
+    pid_t parent = getpid();
+    pid_t child = fork();
+    if (child == 0) {
+        usleep(100000);
+        kill(parent, SIGUSR1);
+        _exit(0);
+    }
+    req.tv_sec = 3;
+    req.tv_nsec = 0;
+    rem.tv_sec = rem.tv_nsec = 0;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    errno = 0;
+    check(nanosleep(&req, &rem) == -1 && errno == EINTR, "interrupted nanosleep is EINTR");
+    check(elapsed_ms(&start) < 2000, "signal wakes the sleeper early");
+    check(rem.tv_sec >= 1 && rem.tv_sec <= 3, "remaining time is written back");
+    waitpid(child, NULL, 0);
+
+    puts(failed ? "nanosleep test failed" : "nanosleep test passed");
+    return failed;
+}
@@ -0,0 +1,39 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 非阻塞读空管道应返回 EAGAIN，阻塞读则应等待写入端写入数据
+int main()
+{
+    int fds[2];
+    char buf[8];
+    if (pipe2(fds, O_NONBLOCK) < 0) {
+        perror("pipe2");
+        return 1;
+    }
+    int ret = read(fds[0], buf, sizeof(buf));
+    if (ret != -1 || errno != EAGAIN) {
+        printf("nonblock: read returned %d, errno %d\n", ret, errno);
+        return 1;
+    }
+
+    // 切换回阻塞模式
+    int flags = fcntl(fds[0], F_GETFL);
+    fcntl(fds[0], F_SETFL, flags & ~O_NONBLOCK);
+    pid_t pid = fork();
+    if (pid == 0) {
+        sleep(1);
+        write(fds[1], "x", 1);
+        _exit(0);
+    }
+    ret = read(fds[0], buf, sizeof(buf));
+    if (ret != 1 || buf[0] != 'x') {
+        printf("nonblock: blocking read returned %d\n", ret);
+        return 1;
+    }
+    waitpid(pid, NULL, 0);
+    printf("nonblock: test passed\n");
+    return 0;
+}
@@ -0,0 +1,106 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CHUNK (4 << 20)
+
+// 向 /proc/self/oom_score_adj 写入 value，返回 write 的结果
+static int write_adj(const char *value)
+{
+    int fd = open("/proc/self/oom_score_adj", O_WRONLY);
+    if (fd < 0)
+        return -1;
+    int ret = write(fd, value, strlen(value));
+    close(fd);
+    return ret;
+}
+
+// 读取 /proc/self/oom_score_adj，失败时返回 -10000
+static int read_adj(void)
+{
+    char buf[16] = {0};
+    int fd = open("/proc/self/oom_score_adj", O_RDONLY);
+    if (fd < 0)
+        return -10000;
+    int n = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    return n > 0 ? atoi(buf) : -10000;
+}
+
+// oom_score_adj 的读写与范围检查
+static int test_oom_score_adj(void)
+{
+    if (read_adj() != 0) {
+        printf("oom: default oom_score_adj is not 0\n");
+        return 1;
+    }
+    if (write_adj("500\n") != 4 || read_adj() != 500) {
+        printf("oom: failed to set oom_score_adj to 500\n");
+        return 1;
+    }
+    errno = 0;
+    if (write_adj("1001") != -1 || errno != EINVAL) {
+        printf("oom: out of range oom_score_adj is not rejected with EINVAL\n");
+        return 1;
+    }
+    errno = 0;
+    if (write_adj("abc") != -1 || errno != EINVAL) {
+        printf("oom: non-numeric oom_score_adj is not rejected with EINVAL\n");
+        return 1;
+    }
+    if (write_adj("0") != 1 || read_adj() != 0) {
+        printf("oom: failed to restore oom_score_adj\n");
+        return 1;
+    }
+    return 0;
+}
+
+// 一个正常进程与一个不断申请匿名内存的进程，OOM 时只有后者应被杀死
+// 正常进程也占用一些内存，但通过 oom_score_adj 使自己不会被选中
+int main()
+{
+    if (test_oom_score_adj())
+        return 1;
+
+    pid_t good = fork();
+    if (good == 0) {
+        if (write_adj("-1000") != 5)
+            exit(2);
+        char *p = malloc(CHUNK);
+        if (p == NULL)
+            exit(3);
+        memset(p, 1, CHUNK);
+        sleep(1);
+        exit(0);
+    }
+
+    pid_t hog = fork();
+    if (hog == 0) {
+        for (;;) {
+            char *p = mmap(NULL, CHUNK, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+            if (p == MAP_FAILED)
+                continue;
+            memset(p, 1, CHUNK);
+        }
+    }
+
+    int status;
+    waitpid(hog, &status, 0);
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL) {
+        printf("oom: hog was not killed by SIGKILL, status %x\n", status);
+        return 1;
+    }
+    waitpid(good, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("oom: well-behaved task did not exit normally, status %x\n", status);
+        return 1;
+    }
+    printf("oom: test passed\n");
+    return 0;
+}
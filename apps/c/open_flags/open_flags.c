@@ -0,0 +1,110 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_FILE "open_flags_test.txt"
+
+static off_t file_size(const char *path)
+{
+    struct stat st;
+    return stat(path, &st) == 0 ? st.st_size : -1;
+}
+
+int main(void)
+{
+    char buf[32];
+    unlink(TEST_FILE);
+
+    // 没有 O_CREAT 时不会创建文件
+    errno = 0;
+    check(open(TEST_FILE, O_RDWR) == -1 && errno == ENOENT, "open without O_CREAT sets ENOENT");
+
+    // O_CREAT | O_EXCL 创建新文件，文件已存在时返回 EEXIST
+    int fd = open(TEST_FILE, O_WRONLY | O_CREAT | O_EXCL, 0644);
+    check(fd >= 0, "O_CREAT | O_EXCL creates a new file");
+    check(write(fd, "0123456789", 10) == 10, "write to the new file");
+    errno = 0;
+    check(read(fd, buf, 1) == -1 && errno == EBADF, "O_WRONLY file is not readable");
+    close(fd);
+    check(file_size(TEST_FILE) == 10, "new file has the written size");
+    errno = 0;
+    check(open(TEST_FILE, O_WRONLY | O_CREAT | O_EXCL, 0644) == -1 && errno == EEXIST,
+          "O_CREAT | O_EXCL on an existing file sets EEXIST");
+
+    // 不带 O_TRUNC 打开不改变内容，O_RDONLY 打开不可写
+    fd = open(TEST_FILE, O_RDONLY);
+    check(fd >= 0, "open O_RDONLY");
+    errno = 0;
+    check(write(fd, "x", 1) == -1 && errno == EBADF, "O_RDONLY file is not writable");
+    check(read(fd, buf, sizeof(buf)) == 10 && memcmp(buf, "0123456789", 10) == 0, "content is kept");
+    close(fd);
+
+    // O_APPEND 的写入总是位于文件末尾
+    fd = open(TEST_FILE, O_WRONLY | O_APPEND);
+    check(fd >= 0, "open O_APPEND");
+    check(lseek(fd, 0, SEEK_SET) == 0, "seek to start");
+    check(write(fd, "ab", 2) == 2, "append write");
+    check(lseek(fd, 0, SEEK_CUR) == 12, "offset is at the end after append");
+    close(fd);
+    fd = open(TEST_FILE, O_RDONLY);
+    check(read(fd, buf, sizeof(buf)) == 12 && memcmp(buf, "0123456789ab", 12) == 0,
+          "append does not overwrite");
+    close(fd);
+
+    // O_TRUNC 截断已存在的文件
+    fd = open(TEST_FILE, O_RDWR | O_TRUNC);
+    check(fd >= 0, "open O_TRUNC");
+    check(file_size(TEST_FILE) == 0, "O_TRUNC truncates the existing file");
+    check(write(fd, "xyz", 3) == 3 && lseek(fd, 0, SEEK_SET) == 0, "write after truncate");
+    check(read(fd, buf, sizeof(buf)) == 3 && memcmp(buf, "xyz", 3) == 0, "read after truncate");
+    close(fd);
+
+    // O_TRUNC 与 O_APPEND 可以同时使用
+    fd = open(TEST_FILE, O_WRONLY | O_TRUNC | O_APPEND);
+    check(fd >= 0 && file_size(TEST_FILE) == 0, "O_TRUNC | O_APPEND truncates");
+    check(write(fd, "q", 1) == 1 && file_size(TEST_FILE) == 1, "append after truncate");
+    close(fd);
+
+    // O_RDONLY | O_CREAT 可以创建文件
+    unlink(TEST_FILE);
+    fd = open(TEST_FILE, O_RDONLY | O_CREAT, 0644);
+    check(fd >= 0 && file_size(TEST_FILE) == 0, "O_RDONLY | O_CREAT creates a file");
+    close(fd);
+    unlink(TEST_FILE);
+
+    // 新建文件的权限来自 mode，访问模式可以由 F_GETFL 读出
+    mode_t old_umask = umask(0);
+    fd = open(TEST_FILE, O_RDWR | O_CREAT | O_EXCL, 0640);
+    struct stat st;
+    check(fd >= 0 && fstat(fd, &st) == 0 && (st.st_mode & 0777) == 0640, "mode of the new file");
+    check((fcntl(fd, F_GETFL) & O_ACCMODE) == O_RDWR, "F_GETFL reports the access mode");
+    close(fd);
+    umask(old_umask);
+
+    // O_DIRECTORY 只能打开目录，目录不能以可写方式打开
+    errno = 0;
+    check(open(TEST_FILE, O_RDONLY | O_DIRECTORY) == -1 && errno == ENOTDIR,
+          "O_DIRECTORY on a regular file sets ENOTDIR");
+    unlink(TEST_FILE);
+    errno = 0;
+    check(open(".", O_WRONLY) == -1 && errno == EISDIR, "writing a directory sets EISDIR");
+    errno = 0;
+    check(open(".", O_RDWR | O_DIRECTORY) == -1 && errno == EISDIR,
+          "writing a directory with O_DIRECTORY sets EISDIR");
+
+    // O_CLOEXEC 记录在文件描述符上，目录也不例外
+    fd = open(".", O_RDONLY | O_DIRECTORY | O_CLOEXEC);
+    check(fd >= 0 && fcntl(fd, F_GETFD) == FD_CLOEXEC, "O_CLOEXEC on a directory");
+    close(fd);
+    fd = open(".", O_RDONLY | O_DIRECTORY);
+    check(fd >= 0 && fcntl(fd, F_GETFD) == 0, "no FD_CLOEXEC without O_CLOEXEC");
+    close(fd);
+
+    puts(failed ? "open_flags test failed" : "open_flags test passed");
+    return failed;
+}
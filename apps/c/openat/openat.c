@@ -0,0 +1,105 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_DIR "openat_dir"
+
+// 读出 fd 中的内容并与 expected 比较
+static int has_content(int fd, const char *expected)
+{
+    char buf[32] = {0};
+    ssize_t n = read(fd, buf, sizeof(buf) - 1);
+    return n == (ssize_t)strlen(expected) && strcmp(buf, expected) == 0;
+}
+
+int main(void)
+{
+    unlink(TEST_DIR "/inner");
+    rmdir(TEST_DIR);
+    check(mkdir(TEST_DIR, 0755) == 0, "mkdir");
+    int fd = open(TEST_DIR "/inner", O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0 && write(fd, "inner", 5) == 5, "create the file");
+    close(fd);
+
+    // 相对路径相对于 dirfd 解析
+    int dirfd = open(TEST_DIR, O_RDONLY | O_DIRECTORY);
+    check(dirfd >= 0, "open the directory");
+    fd = openat(dirfd, "inner", O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "openat relative to dirfd");
+    close(fd);
+
+    // AT_FDCWD 使用当前工作目录，chdir 之后随之改变
+    fd = openat(AT_FDCWD, TEST_DIR "/inner", O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "openat relative to AT_FDCWD");
+    close(fd);
+    char cwd[PATH_MAX];
+    check(getcwd(cwd, sizeof(cwd)) != NULL, "getcwd");
+    check(chdir(TEST_DIR) == 0, "chdir");
+    fd = openat(AT_FDCWD, "inner", O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "AT_FDCWD follows chdir");
+    close(fd);
+    check(chdir(cwd) == 0, "chdir back");
+
+    // "."、".." 与多余的 '/' 按目录解析
+    fd = openat(dirfd, "./inner", O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "openat with ./");
+    close(fd);
+    fd = openat(dirfd, "../" TEST_DIR "//inner", O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "openat with ../ and //");
+    close(fd);
+    struct stat st;
+    fd = openat(dirfd, ".", O_RDONLY);
+    check(fd >= 0 && fstat(fd, &st) == 0 && S_ISDIR(st.st_mode), "openat . opens the directory");
+    close(fd);
+    fd = openat(dirfd, "..", O_RDONLY);
+    check(fd >= 0 && fstat(fd, &st) == 0 && S_ISDIR(st.st_mode), "openat .. opens the parent");
+    int inner = fd >= 0 ? openat(fd, TEST_DIR "/inner", O_RDONLY) : -1;
+    check(inner >= 0 && has_content(inner, "inner"), "openat relative to the parent");
+    close(inner);
+    close(fd);
+
+    // 以 '/' 结尾的路径必须是目录
+    fd = openat(AT_FDCWD, TEST_DIR "/", O_RDONLY);
+    check(fd >= 0 && fstat(fd, &st) == 0 && S_ISDIR(st.st_mode), "trailing slash on a directory");
+    close(fd);
+    errno = 0;
+    check(openat(dirfd, "inner/", O_RDONLY) == -1 && errno == ENOTDIR,
+          "trailing slash on a file sets ENOTDIR");
+
+    // 不带 O_DIRECTORY 打开的目录同样可以作为 dirfd
+    int plain = open(TEST_DIR, O_RDONLY);
+    fd = plain >= 0 ? openat(plain, "inner", O_RDONLY) : -1;
+    check(fd >= 0 && has_content(fd, "inner"), "dirfd opened without O_DIRECTORY");
+    close(fd);
+    close(plain);
+
+    // 绝对路径忽略 dirfd
+    char abs[PATH_MAX + 32];
+    snprintf(abs, sizeof(abs), "%s/%s/inner", cwd, TEST_DIR);
+    fd = openat(12345, abs, O_RDONLY);
+    check(fd >= 0 && has_content(fd, "inner"), "absolute path ignores dirfd");
+    close(fd);
+
+    // dirfd 未打开时返回 EBADF，不是目录时返回 ENOTDIR
+    errno = 0;
+    check(openat(12345, "inner", O_RDONLY) == -1 && errno == EBADF, "closed dirfd sets EBADF");
+    int filefd = openat(dirfd, "inner", O_RDONLY);
+    errno = 0;
+    check(openat(filefd, "inner", O_RDONLY) == -1 && errno == ENOTDIR,
+          "dirfd of a regular file sets ENOTDIR");
+    close(filefd);
+    close(dirfd);
+
+    unlink(TEST_DIR "/inner");
+    rmdir(TEST_DIR);
+
+    puts(failed ? "openat test failed" : "openat test passed");
+    return failed;
+}
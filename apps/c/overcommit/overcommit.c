@@ -0,0 +1,105 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define OVERCOMMIT "/proc/sys/vm/overcommit_memory"
+// 远大于物理内存的映射
+#define HUGE_LEN (1UL << 36)
+#define CHUNK (4 << 20)
+
+static int set_mode(const char *mode)
+{
+    int fd = open(OVERCOMMIT, O_WRONLY);
+    if (fd < 0)
+        return -1;
+    int ret = write(fd, mode, strlen(mode));
+    close(fd);
+    return ret < 0 ? -1 : 0;
+}
+
+static int get_mode(void)
+{
+    char buf[8] = {0};
+    int fd = open(OVERCOMMIT, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    int ret = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    return ret > 0 ? atoi(buf) : -1;
+}
+
+static void *map(size_t len, int prot)
+{
+    return mmap(NULL, len, prot, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+}
+
+int main(void)
+{
+    int old_mode = get_mode();
+    check(old_mode >= 0, "read overcommit_memory");
+
+    // NEVER：超过提交上限的可写私有映射被拒绝
+    check(set_mode("2\n") == 0 && get_mode() == 2, "set NEVER");
+    errno = 0;
+    check(map(HUGE_LEN, PROT_READ | PROT_WRITE) == MAP_FAILED && errno == ENOMEM,
+          "NEVER refuses huge writable mapping");
+    // 只读映射不需要物理页，不计入提交量
+    void *p = map(HUGE_LEN, PROT_READ);
+    check(p != MAP_FAILED, "NEVER allows read-only mapping");
+    if (p != MAP_FAILED) {
+        // 加上写权限后需要物理页，同样计入提交量
+        errno = 0;
+        check(mprotect(p, HUGE_LEN, PROT_READ | PROT_WRITE) < 0 && errno == ENOMEM,
+              "NEVER refuses making a huge mapping writable");
+        check(mprotect(p, 4096, PROT_READ | PROT_WRITE) == 0,
+              "NEVER allows making a small part writable");
+        munmap(p, HUGE_LEN);
+    }
+    p = map(4096, PROT_READ | PROT_WRITE);
+    check(p != MAP_FAILED, "NEVER allows small mapping");
+    if (p != MAP_FAILED) {
+        memset(p, 1, 4096);
+        munmap(p, 4096);
+    }
+
+    // 非法的策略
+    errno = 0;
+    check(set_mode("3\n") < 0 && errno == EINVAL, "invalid mode is EINVAL");
+    check(get_mode() == 2, "invalid mode keeps policy");
+
+    // 启发式：单次超过物理内存的映射被拒绝
+    check(set_mode("0\n") == 0 && get_mode() == 0, "set heuristic");
+    errno = 0;
+    check(map(HUGE_LEN, PROT_READ | PROT_WRITE) == MAP_FAILED && errno == ENOMEM,
+          "heuristic refuses mapping larger than RAM");
+
+    // 启发式：缺页时内存耗尽，杀死占用内存最多的进程而不是让内核崩溃
+    pid_t hog = fork();
+    if (hog == 0) {
+        for (;;) {
+            char *chunk = map(CHUNK, PROT_READ | PROT_WRITE);
+            if (chunk != MAP_FAILED)
+                memset(chunk, 1, CHUNK);
+        }
+    }
+    int status = 0;
+    check(waitpid(hog, &status, 0) == hog, "waitpid hog");
+    check(WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL, "hog killed by SIGKILL");
+
+    if (old_mode >= 0) {
+        char buf[16];
+        snprintf(buf, sizeof(buf), "%d\n", old_mode);
+        set_mode(buf);
+    }
+
+    puts(failed ? "overcommit test failed" : "overcommit test passed");
+    return failed;
+}
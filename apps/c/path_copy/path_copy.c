@@ -0,0 +1,65 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PATH_MAX_LEN 4096
+
+int main(void)
+{
+    static char long_path[PATH_MAX_LEN + 16];
+    long page = sysconf(_SC_PAGESIZE);
+
+    // 超过 PATH_MAX 仍没有遇到 '\0' 时返回 ENAMETOOLONG
+    memset(long_path, 'a', sizeof(long_path) - 1);
+    long_path[sizeof(long_path) - 1] = '\0';
+    errno = 0;
+    check(open(long_path, O_RDONLY) == -1 && errno == ENAMETOOLONG, "overlong path");
+
+    // 路径一直延伸到未映射的页面而没有 '\0' 时返回 EFAULT，而不是使内核崩溃
+    char *pages = mmap(NULL, page * 2, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(pages != MAP_FAILED, "mmap");
+    munmap(pages + page, page);
+    memset(pages, 'b', page);
+    errno = 0;
+    check(open(pages + page - 16, O_RDONLY) == -1 && errno == EFAULT, "unterminated path");
+
+    // 整整一页都没有 '\0'，紧接着是未映射的页面：读满 PATH_MAX 字节后返回 ENAMETOOLONG，
+    // 不会扫描到之后的页面
+    char *unterminated = mmap(NULL, page * 2, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(unterminated != MAP_FAILED, "mmap");
+    munmap(unterminated + PATH_MAX_LEN, page * 2 - PATH_MAX_LEN);
+    memset(unterminated, 'c', PATH_MAX_LEN);
+    struct stat st;
+    struct statfs sfs;
+    errno = 0;
+    check(open(unterminated, O_RDONLY) == -1 && errno == ENAMETOOLONG, "open with an unterminated buffer");
+    errno = 0;
+    check(stat(unterminated, &st) == -1 && errno == ENAMETOOLONG, "stat with an unterminated buffer");
+    errno = 0;
+    check(statfs(unterminated, &sfs) == -1 && errno == ENAMETOOLONG,
+          "statfs with an unterminated buffer");
+    errno = 0;
+    check(syscall(SYS_newfstatat, AT_FDCWD, NULL, &st, 0) == -1 && errno == EFAULT, "stat with a NULL path");
+    munmap(unterminated, PATH_MAX_LEN);
+
+    // 以 '\0' 结尾的路径不受影响
+    memcpy(pages + page - 16, "path_copy.txt", 14);
+    int fd = open(pages + page - 16, O_RDWR | O_CREAT, 0644);
+    check(fd >= 0, "terminated path at the end of a page");
+    close(fd);
+    unlink("path_copy.txt");
+
+    errno = 0;
+    check(syscall(SYS_openat, AT_FDCWD, NULL, O_RDONLY) == -1 && errno == EFAULT, "NULL path");
+
+    puts(failed ? "path_copy test failed" : "path_copy test passed");
+    return failed;
+}
@@ -0,0 +1,93 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PIPE_CAPACITY 65536
+
+static char buf[PIPE_CAPACITY];
+// 不可写的地址，用 volatile 避免编译器检查数组长度
+static int *volatile bad_fds = (int *)1;
+
+int main(void)
+{
+    int fds[2];
+
+    // 只接受 O_CLOEXEC 与 O_NONBLOCK，fds 不可写时返回 EFAULT
+    errno = 0;
+    check(pipe2(fds, O_APPEND) == -1 && errno == EINVAL, "pipe2 rejects unknown flags");
+    errno = 0;
+    check(pipe2(bad_fds, 0) == -1 && errno == EFAULT, "pipe2 with a bad pointer sets EFAULT");
+
+    // 标志分别落在文件描述符与打开的文件上
+    check(pipe2(fds, O_CLOEXEC | O_NONBLOCK) == 0, "pipe2 O_CLOEXEC | O_NONBLOCK");
+    check(fcntl(fds[0], F_GETFD) == FD_CLOEXEC && fcntl(fds[1], F_GETFD) == FD_CLOEXEC,
+          "both ends are close-on-exec");
+    check((fcntl(fds[0], F_GETFL) & O_NONBLOCK) && (fcntl(fds[1], F_GETFL) & O_NONBLOCK),
+          "both ends are non-blocking");
+    check((fcntl(fds[0], F_GETFL) & O_ACCMODE) == O_RDONLY, "read end is read-only");
+    check((fcntl(fds[1], F_GETFL) & O_ACCMODE) == O_WRONLY, "write end is write-only");
+    errno = 0;
+    check(read(fds[0], buf, 1) == -1 && errno == EAGAIN, "empty non-blocking read sets EAGAIN");
+
+    // 容量与 Linux 默认的 64K 一致
+    size_t total = 0;
+    ssize_t ret;
+    memset(buf, 'p', sizeof(buf));
+    while ((ret = write(fds[1], buf, 4096)) > 0)
+        total += ret;
+    check(total == PIPE_CAPACITY, "pipe holds 64K");
+    check(ret == -1 && errno == EAGAIN, "full non-blocking write sets EAGAIN");
+    close(fds[0]);
+    close(fds[1]);
+
+    // 阻塞的读取在对端写入后返回，写入端关闭后读到文件末尾
+    check(pipe(fds) == 0, "pipe");
+    check(fcntl(fds[0], F_GETFD) == 0, "pipe is not close-on-exec");
+    pid_t child = fork();
+    if (child == 0) {
+        close(fds[0]);
+        usleep(50000);
+        write(fds[1], "hello", 5);
+        _exit(0);
+    }
+    close(fds[1]);
+    check(read(fds[0], buf, sizeof(buf)) == 5 && memcmp(buf, "hello", 5) == 0,
+          "blocking read waits for the writer");
+    check(read(fds[0], buf, sizeof(buf)) == 0, "EOF after the writer exits");
+    int status;
+    check(waitpid(child, &status, 0) == child, "waitpid");
+    close(fds[0]);
+
+    // 阻塞的写入在对端读出数据后继续
+    check(pipe(fds) == 0, "pipe");
+    child = fork();
+    if (child == 0) {
+        close(fds[0]);
+        size_t written = 0;
+        while (written < 2 * PIPE_CAPACITY) {
+            ssize_t n = write(fds[1], buf, sizeof(buf));
+            if (n <= 0)
+                _exit(1);
+            written += n;
+        }
+        _exit(0);
+    }
+    close(fds[1]);
+    usleep(50000);
+    total = 0;
+    while ((ret = read(fds[0], buf, sizeof(buf))) > 0)
+        total += ret;
+    check(total == 2 * PIPE_CAPACITY, "reader gets everything the blocked writer wrote");
+    check(waitpid(child, &status, 0) == child && WIFEXITED(status) && WEXITSTATUS(status) == 0,
+          "writer finishes");
+    close(fds[0]);
+
+    puts(failed ? "pipe2 test failed" : "pipe2 test passed");
+    return failed;
+}
@@ -0,0 +1,109 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define RECORDS 64
+
+// 子进程写入 RECORDS 条 PIPE_BUF 字节的记录，每条记录的内容全部为 tag
+static void writer(int fd, char tag)
+{
+    char record[PIPE_BUF];
+    memset(record, tag, sizeof(record));
+    for (int i = 0; i < RECORDS; i++) {
+        if (write(fd, record, sizeof(record)) != sizeof(record))
+            _exit(1);
+    }
+    _exit(0);
+}
+
+// 读满 len 字节
+static int read_full(int fd, char *buf, int len)
+{
+    int done = 0;
+    while (done < len) {
+        int n = read(fd, buf + done, len - done);
+        if (n <= 0)
+            return done;
+        done += n;
+    }
+    return done;
+}
+
+int main(void)
+{
+    int fds[2];
+    check(pipe(fds) == 0, "pipe");
+
+    // 默认容量为 64K，修改时向上取整为 2 的幂个页
+    check(fcntl(fds[0], F_GETPIPE_SZ) == 65536, "default capacity is 64K");
+    check(fcntl(fds[1], F_SETPIPE_SZ, 5000) == 8192, "capacity rounds up to a power of two");
+    check(fcntl(fds[0], F_GETPIPE_SZ) == 8192, "both ends see the new capacity");
+    check(fcntl(fds[1], F_SETPIPE_SZ, 0) == 4096, "capacity is at least a page");
+
+    // 缩小到放不下已有数据时返回 EBUSY，数据保持不变
+    char buf[PIPE_BUF * 2];
+    memset(buf, 'd', sizeof(buf));
+    check(fcntl(fds[1], F_SETPIPE_SZ, 16384) == 16384, "grow");
+    check(write(fds[1], buf, 6000) == 6000, "write 6000 bytes");
+    errno = 0;
+    check(fcntl(fds[1], F_SETPIPE_SZ, 4096) == -1 && errno == EBUSY, "shrink below the data sets EBUSY");
+    check(fcntl(fds[1], F_SETPIPE_SZ, 8192) == 8192, "shrink while keeping the data");
+    check(read_full(fds[0], buf, 6000) == 6000 && buf[0] == 'd' && buf[5999] == 'd',
+          "data survives resizing");
+    errno = 0;
+    check(fcntl(fds[1], F_SETPIPE_SZ, (1U << 31) + 1) == -1 && errno == EINVAL,
+          "huge capacity sets EINVAL");
+    errno = 0;
+    check(fcntl(0, F_GETPIPE_SZ) == -1 && errno == EBADF, "F_GETPIPE_SZ on a non-pipe sets EBADF");
+
+    // 非阻塞模式下，放不下的 PIPE_BUF 以内的写入返回 EAGAIN，更大的写入只写入能放下的部分
+    fcntl(fds[1], F_SETFL, O_NONBLOCK);
+    check(write(fds[1], buf, 6000) == 6000, "fill most of the pipe");
+    errno = 0;
+    check(write(fds[1], buf, PIPE_BUF) == -1 && errno == EAGAIN, "atomic write does not split");
+    errno = 0;
+    check(write(fds[1], buf, 3000) == -1 && errno == EAGAIN, "short atomic write does not split");
+    check(read_full(fds[0], buf, 6000) == 6000, "drain");
+    char big[3 * PIPE_BUF];
+    memset(big, 'e', sizeof(big));
+    check(write(fds[1], big, sizeof(big)) == 8192, "large write is partial");
+    check(read_full(fds[0], big, 8192) == 8192, "drain again");
+    fcntl(fds[1], F_SETFL, 0);
+
+    // 两个写入者并发写入 PIPE_BUF 字节的记录，读出的每条记录都不会交错
+    check(fcntl(fds[1], F_SETPIPE_SZ, 3 * PIPE_BUF) == 4 * PIPE_BUF, "capacity for the race");
+    pid_t a = fork();
+    if (a == 0)
+        writer(fds[1], 'a');
+    pid_t b = fork();
+    if (b == 0)
+        writer(fds[1], 'b');
+    close(fds[1]);
+    int records = 0, torn = 0;
+    char record[PIPE_BUF];
+    while (read_full(fds[0], record, sizeof(record)) == sizeof(record)) {
+        for (int i = 1; i < PIPE_BUF; i++) {
+            if (record[i] != record[0]) {
+                torn++;
+                break;
+            }
+        }
+        records++;
+    }
+    check(records == 2 * RECORDS, "every record arrives");
+    check(torn == 0, "records from concurrent writers do not interleave");
+    int status;
+    check(waitpid(a, &status, 0) == a && status == 0, "first writer");
+    check(waitpid(b, &status, 0) == b && status == 0, "second writer");
+    close(fds[0]);
+
+    puts(failed ? "pipe_size test failed" : "pipe_size test passed");
+    return failed;
+}
@@ -0,0 +1,62 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <poll.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define MS 1000000L
+
+static long elapsed_ms(const struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / MS;
+}
+
+int main(void)
+{
+    struct timespec start;
+    int fds[2];
+    check(pipe(fds) == 0, "pipe");
+    struct pollfd pfd = {.fd = fds[0], .events = POLLIN};
+
+    // poll 的超时以毫秒为单位，超时返回 0 且等待时间不短于要求
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(poll(&pfd, 1, 50) == 0, "poll times out");
+    long ms = elapsed_ms(&start);
+    check(ms >= 50 && ms < 1000, "poll waits the timeout in milliseconds");
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(poll(&pfd, 1, 0) == 0 && elapsed_ms(&start) < 10, "poll with 0 returns at once");
+
+    // ppoll 的超时很大时不会因为溢出而立即返回
+    struct timespec huge = {0x7fffffffffffffffL, 0};
+    check(write(fds[1], "x", 1) == 1, "write");
+    check(ppoll(&pfd, 1, &huge, NULL) == 1, "ppoll with a huge timeout sees the data");
+    char c;
+    check(read(fds[0], &c, 1) == 1, "read");
+    struct timespec ts = {0, 30 * MS};
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(ppoll(&pfd, 1, &ts, NULL) == 0 && elapsed_ms(&start) >= 30, "ppoll waits the timeout");
+
+    // epoll_wait 的超时同样以毫秒为单位，0 表示立即返回
+    int ep = epoll_create1(0);
+    struct epoll_event ev = {.events = EPOLLIN, .data.fd = fds[0]};
+    check(ep >= 0 && epoll_ctl(ep, EPOLL_CTL_ADD, fds[0], &ev) == 0, "epoll_ctl");
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(epoll_wait(ep, &ev, 1, 0) == 0 && elapsed_ms(&start) < 10,
+          "epoll_wait with 0 returns at once");
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    check(epoll_wait(ep, &ev, 1, 30) == 0, "epoll_wait times out");
+    ms = elapsed_ms(&start);
+    check(ms >= 30 && ms < 1000, "epoll_wait waits the timeout in milliseconds");
+    close(ep);
+    close(fds[0]);
+    close(fds[1]);
+
+    puts(failed ? "poll_timeout test failed" : "poll_timeout test passed");
+    return failed;
+}
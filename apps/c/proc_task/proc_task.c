@@ -0,0 +1,123 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdlib.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define NR_THREADS 4
+
+static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t cond = PTHREAD_COND_INITIALIZER;
+static int started = 0;
+static int done = 0;
+static pid_t tids[NR_THREADS];
+
+static void *worker(void *arg)
+{
+    long idx = (long)arg;
+    pthread_mutex_lock(&lock);
+    tids[idx] = syscall(SYS_gettid);
+    started++;
+    pthread_cond_broadcast(&cond);
+    while (!done)
+        pthread_cond_wait(&cond, &lock);
+    pthread_mutex_unlock(&lock);
+    return NULL;
+}
+
+static int read_file(const char *path, char *buf, size_t size)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    ssize_t len = read(fd, buf, size - 1);
+    close(fd);
+    if (len < 0)
+        return -1;
+    buf[len] = '\0';
+    return 0;
+}
+
+int main(void)
+{
+    pthread_t threads[NR_THREADS];
+    char path[64], buf[256];
+    int failed = 0;
+
+    for (long i = 0; i < NR_THREADS; i++)
+        pthread_create(&threads[i], NULL, worker, (void *)i);
+    pthread_mutex_lock(&lock);
+    while (started < NR_THREADS)
+        pthread_cond_wait(&cond, &lock);
+    pthread_mutex_unlock(&lock);
+
+    // 主线程与 4 个子线程都应出现在 task 目录中
+    DIR *dir = opendir("/proc/self/task");
+    if (!dir) {
+        puts("opendir /proc/self/task failed");
+        return 1;
+    }
+    int count = 0, found = 0;
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (entry->d_name[0] == '.')
+            continue;
+        count++;
+        for (int i = 0; i < NR_THREADS; i++)
+            if (atoi(entry->d_name) == tids[i])
+                found++;
+    }
+    closedir(dir);
+    if (count != NR_THREADS + 1 || found != NR_THREADS) {
+        printf("task dir: %d entries, %d worker tids found\n", count, found);
+        failed = 1;
+    }
+
+    // 通过 comm 重命名其中一个线程
+    snprintf(path, sizeof(path), "/proc/self/task/%d/comm", tids[1]);
+    int fd = open(path, O_WRONLY);
+    if (fd < 0 || write(fd, "renamed\n", 8) != 8) {
+        printf("write %s failed\n", path);
+        failed = 1;
+    }
+    if (fd >= 0)
+        close(fd);
+
+    if (read_file(path, buf, sizeof(buf)) < 0 || strcmp(buf, "renamed\n") != 0) {
+        printf("comm mismatch: %s", buf);
+        failed = 1;
+    }
+    snprintf(path, sizeof(path), "/proc/self/task/%d/status", tids[1]);
+    if (read_file(path, buf, sizeof(buf)) < 0 || strncmp(buf, "Name:\trenamed\n", 14) != 0) {
+        printf("status mismatch: %s", buf);
+        failed = 1;
+    }
+    snprintf(path, sizeof(path), "/proc/self/task/%d/stat", tids[1]);
+    if (read_file(path, buf, sizeof(buf)) < 0 || strstr(buf, "(renamed)") == NULL) {
+        printf("stat mismatch: %s", buf);
+        failed = 1;
+    }
+
+    // prctl 设置的名称同样应反映在 comm 中
+    prctl(PR_SET_NAME, "main-thread");
+    snprintf(path, sizeof(path), "/proc/self/task/%d/comm", getpid());
+    if (read_file(path, buf, sizeof(buf)) < 0 || strcmp(buf, "main-thread\n") != 0) {
+        printf("prctl name mismatch: %s", buf);
+        failed = 1;
+    }
+
+    pthread_mutex_lock(&lock);
+    done = 1;
+    pthread_cond_broadcast(&cond);
+    pthread_mutex_unlock(&lock);
+    for (int i = 0; i < NR_THREADS; i++)
+        pthread_join(threads[i], NULL);
+
+    puts(failed ? "proc_task test failed" : "proc_task test passed");
+    return failed;
+}
@@ -0,0 +1,55 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "check.h"
+
+int main(int argc, char *argv[])
+{
+    (void)argc;
+    char buf[256];
+
+    // /proc/self/exe 指向当前运行的可执行文件，结果不以 '\0' 结尾
+    memset(buf, 'x', sizeof(buf));
+    ssize_t len = readlink("/proc/self/exe", buf, sizeof(buf));
+    check(len > 0 && buf[len] == 'x', "readlink /proc/self/exe");
+    const char *name = strrchr(argv[0], '/') ? strrchr(argv[0], '/') + 1 : argv[0];
+    check(len > 0 && len >= (ssize_t)strlen(name) &&
+              memcmp(buf + len - strlen(name), name, strlen(name)) == 0,
+          "/proc/self/exe names the running program");
+
+    // 缓冲区不足时截断，返回写入的字节数
+    check(len <= 2 || readlink("/proc/self/exe", buf, 2) == 2, "truncated to bufsiz");
+
+    // /proc/<pid>/exe 与 /proc/self/exe 相同
+    char path[64], buf2[256];
+    snprintf(path, sizeof(path), "/proc/%d/exe", getpid());
+    ssize_t len2 = readlink(path, buf2, sizeof(buf2));
+    check(len2 == len && memcmp(buf, buf2, len) == 0, "/proc/<pid>/exe");
+
+    // /proc/self/cwd 指向当前工作目录
+    char cwd[256];
+    check(getcwd(cwd, sizeof(cwd)) != NULL, "getcwd");
+    memset(buf2, 0, sizeof(buf2));
+    len2 = readlink("/proc/self/cwd", buf2, sizeof(buf2) - 1);
+    check(len2 == (ssize_t)strlen(cwd) && strcmp(buf2, cwd) == 0, "/proc/self/cwd");
+
+    // 普通文件不是符号链接
+    int fd = open("readlink_test.txt", O_RDWR | O_CREAT, 0644);
+    close(fd);
+    errno = 0;
+    check(readlink("readlink_test.txt", buf, sizeof(buf)) == -1 && errno == EINVAL,
+          "regular file gives EINVAL");
+    unlink("readlink_test.txt");
+
+    errno = 0;
+    check(readlink("no_such_file", buf, sizeof(buf)) == -1 && errno == ENOENT,
+          "missing file gives ENOENT");
+    errno = 0;
+    check(readlink("/proc/self/exe", buf, 0) == -1 && errno == EINVAL, "bufsiz 0 gives EINVAL");
+
+    puts(failed ? "readlink test failed" : "readlink test passed");
+    return failed;
+}
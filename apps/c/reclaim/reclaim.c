@@ -0,0 +1,171 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+// 可回收的页面多于一次分配的页面，回收之后本次分配不会再次耗尽内存
+#define LAZY_LEN (16 << 20)
+#define CHUNK (4 << 20)
+// 读入页缓存的文件大小
+#define FILE_LEN (16 << 20)
+
+static void *map_anon(size_t len)
+{
+    return mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+}
+
+// 读出 /proc/vmstat 中名为 name 的一项，失败时返回 -1
+static long vmstat(const char *name)
+{
+    char buf[512] = {0};
+    char key[64];
+    int fd = open("/proc/vmstat", O_RDONLY);
+    if (fd < 0)
+        return -1;
+    int ret = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (ret <= 0)
+        return -1;
+    snprintf(key, sizeof(key), "%s ", name);
+    char *line = strstr(buf, key);
+    return line ? atol(line + strlen(key)) : -1;
+}
+
+static long pgsteal(void)
+{
+    return vmstat("pgsteal_direct");
+}
+
+// 文件第 i 页的内容
+static char file_byte(size_t i)
+{
+    return (char)(i * 7 + 1);
+}
+
+// 通过只读映射读完整个文件，使其页面进入页缓存，返回各页首字节是否正确
+static int read_file(int fd)
+{
+    char *f = mmap(NULL, FILE_LEN, PROT_READ, MAP_PRIVATE, fd, 0);
+    if (f == MAP_FAILED)
+        return 0;
+    int ok = 1;
+    for (size_t i = 0; i < FILE_LEN / PAGE; i++)
+        ok &= f[i * PAGE] == file_byte(i);
+    munmap(f, FILE_LEN);
+    return ok;
+}
+
+static int all_bytes(const char *p, size_t len, char byte)
+{
+    for (size_t i = 0; i < len; i++)
+        if (p[i] != byte)
+            return 0;
+    return 1;
+}
+
+int main(void)
+{
+    // MADV_DONTNEED 之后私有匿名页面读出 0
+    char *p = map_anon(4 * PAGE);
+    check(p != MAP_FAILED, "mmap anonymous");
+    memset(p, 0x5a, 4 * PAGE);
+    check(madvise(p + PAGE, 2 * PAGE, MADV_DONTNEED) == 0, "MADV_DONTNEED");
+    check(p[0] == 0x5a && p[3 * PAGE] == 0x5a, "pages outside the range are kept");
+    check(all_bytes(p + PAGE, 2 * PAGE, 0), "discarded pages read as zero");
+
+    // MADV_FREE 之后再写入，页面不再被回收
+    memset(p, 0x5a, 4 * PAGE);
+    check(madvise(p, 4 * PAGE, MADV_FREE) == 0, "MADV_FREE");
+    p[0] = 0x33;
+    check(p[0] == 0x33 && all_bytes(p + 1, PAGE - 1, 0x5a), "written page keeps its data");
+    check(p[PAGE] == 0x5a || p[PAGE] == 0, "lazily freed page reads old data or zero");
+    munmap(p, 4 * PAGE);
+
+    // 参数检查
+    errno = 0;
+    check(madvise((char *)PAGE + 1, PAGE, MADV_DONTNEED) == -1 && errno == EINVAL,
+          "unaligned start is EINVAL");
+    errno = 0;
+    check(madvise(NULL, PAGE, 1000) == -1 && errno == EINVAL, "unknown advice is EINVAL");
+    check(madvise(NULL, 0, MADV_WILLNEED) == 0, "hint advice is accepted");
+
+    // MADV_FREE 只适用于私有匿名映射
+    char tmp[] = "reclaim_test_file";
+    int file = open(tmp, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(file >= 0 && ftruncate(file, PAGE) == 0, "create file");
+    char *f = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE, file, 0);
+    check(f != MAP_FAILED, "mmap file");
+    errno = 0;
+    check(madvise(f, PAGE, MADV_FREE) == -1 && errno == EINVAL, "MADV_FREE on file mapping is EINVAL");
+    munmap(f, PAGE);
+    close(file);
+    unlink(tmp);
+
+    // 内存紧张时先回收 MADV_FREE 页面，而不是杀死进程
+    long before = pgsteal();
+    check(before >= 0, "read pgsteal_direct");
+    pid_t child = fork();
+    if (child == 0) {
+        char *lazy = map_anon(LAZY_LEN);
+        memset(lazy, 0x5a, LAZY_LEN);
+        if (madvise(lazy, LAZY_LEN, MADV_FREE) != 0)
+            _exit(2);
+        // 不断分配匿名内存，直到发生回收
+        while (pgsteal() <= before) {
+            char *chunk = map_anon(CHUNK);
+            if (chunk == MAP_FAILED)
+                _exit(3);
+            memset(chunk, 1, CHUNK);
+        }
+        // 被回收的页面重新缺页后读出 0
+        _exit(lazy[0] == 0 ? 0 : 4);
+    }
+    int status = 0;
+    check(waitpid(child, &status, 0) == child, "waitpid");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 0, "lazily freed pages reclaimed before OOM");
+    check(pgsteal() > before, "pgsteal_direct increased");
+
+    // 读入大文件填满页缓存，之后分配超出空闲内存的匿名内存，逐出缓存后分配成功
+    char big[] = "reclaim_big_file";
+    file = open(big, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(file >= 0, "create large file");
+    char *page = malloc(PAGE);
+    for (size_t i = 0; i < FILE_LEN / PAGE; i++) {
+        memset(page, file_byte(i), PAGE);
+        if (write(file, page, PAGE) != PAGE) {
+            check(0, "write large file");
+            break;
+        }
+    }
+    free(page);
+    check(read_file(file), "read large file");
+    long cached = vmstat("nr_file_pages");
+    check(cached >= FILE_LEN / PAGE, "unmapped file pages stay in the page cache");
+    before = pgsteal();
+    child = fork();
+    if (child == 0) {
+        while (vmstat("nr_file_pages") >= cached) {
+            char *chunk = map_anon(CHUNK);
+            if (chunk == MAP_FAILED)
+                _exit(3);
+            memset(chunk, 1, CHUNK);
+        }
+        _exit(0);
+    }
+    check(waitpid(child, &status, 0) == child, "waitpid");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 0, "page cache evicted before OOM");
+    check(pgsteal() > before, "pgsteal_direct counts evicted pages");
+    check(read_file(file), "evicted pages are read again from the file");
+    close(file);
+    unlink(big);
+
+    puts(failed ? "reclaim test failed" : "reclaim test passed");
+    return failed;
+}
@@ -0,0 +1,91 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static char buf[4096];
+
+// 读出整个文件，返回读到的长度
+static size_t read_file(const char *path)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return 0;
+    size_t total = 0;
+    ssize_t ret;
+    while ((ret = read(fd, buf + total, sizeof(buf) - 1 - total)) > 0)
+        total += ret;
+    close(fd);
+    buf[total] = 0;
+    return total;
+}
+
+// sched 中 field 一项以纳秒为单位的值，找不到时返回 -1
+static long long sched_field_ns(const char *field)
+{
+    if (read_file("/proc/self/sched") == 0)
+        return -1;
+    char key[64];
+    snprintf(key, sizeof(key), "\n%s ", field);
+    char *line = strstr(buf, key);
+    if (!line)
+        return -1;
+    long long ms, frac;
+    if (sscanf(strchr(line, ':') + 1, "%lld.%lld", &ms, &frac) != 2)
+        return -1;
+    return ms * 1000000 + frac;
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, (ms % 1000) * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+int main(void)
+{
+    unsigned long long exec_ns, wait_ns, count;
+    read_file("/proc/self/schedstat");
+    check(sscanf(buf, "%llu %llu %llu", &exec_ns, &wait_ns, &count) == 3, "schedstat has three fields");
+    check(read_file("/proc/self/sched") > 0 && strstr(buf, "wait_max") != NULL, "sched has wait_max");
+
+    // 与一个占满 CPU 的子进程绑定在同一个 CPU 上，每次睡醒后都需要等待子进程让出 CPU
+    cpu_set_t set;
+    CPU_ZERO(&set);
+    CPU_SET(0, &set);
+    check(sched_setaffinity(0, sizeof(set), &set) == 0, "sched_setaffinity");
+    pid_t hog = fork();
+    if (hog == 0) {
+        for (;;)
+            ;
+    }
+    check(hog > 0, "fork");
+    for (int i = 0; i < 10; i++)
+        sleep_ms(5);
+    kill(hog, SIGKILL);
+    waitpid(hog, NULL, 0);
+
+    unsigned long long wait_after, count_after;
+    read_file("/proc/self/schedstat");
+    check(sscanf(buf, "%*u %llu %llu", &wait_after, &count_after) == 2, "schedstat after wakeups");
+    check(wait_after > wait_ns, "run delay grows while a CPU hog runs");
+    check(count_after >= count + 10, "every wakeup is counted");
+    long long wait_max = sched_field_ns("wait_max");
+    long long wait_sum = sched_field_ns("wait_sum");
+    long long wait_avg = sched_field_ns("wait_avg");
+    check(wait_max > 0, "wakeup latency is nonzero");
+    check(wait_sum >= wait_max, "wait_sum covers wait_max");
+    check(wait_avg > 0 && wait_avg <= wait_max, "wait_avg is between zero and wait_max");
+
+    puts(failed ? "sched_latency test failed" : "sched_latency test passed");
+    return failed;
+}
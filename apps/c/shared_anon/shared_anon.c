@@ -0,0 +1,111 @@
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define REGION_SIZE 8192
+#define PAGE_SIZE 4096
+// 大于 riscv64 平台的物理内存，映射时就分配全部页面会失败
+#define LARGE_SIZE (256UL << 20)
+
+int main(void)
+{
+    int failed = 0;
+    volatile int *shared = mmap(NULL, REGION_SIZE, PROT_READ | PROT_WRITE,
+                                MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    volatile int *private = mmap(NULL, REGION_SIZE, PROT_READ | PROT_WRITE,
+                                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (shared == MAP_FAILED || private == MAP_FAILED) {
+        puts("mmap failed");
+        return 1;
+    }
+    if (shared[0] != 0 || shared[REGION_SIZE / sizeof(int) - 1] != 0) {
+        puts("shared anonymous mapping is not zero-filled");
+        failed = 1;
+    }
+    shared[0] = 1;
+    private[0] = 1;
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        // 子进程能看到父进程 fork 前写入的内容，并写入新的值
+        int ret = shared[0] == 1 ? 0 : 1;
+        shared[0] = 42;
+        shared[REGION_SIZE / sizeof(int) - 1] = 43;
+        private[0] = 42;
+        _exit(ret);
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        puts("child did not inherit the shared mapping");
+        failed = 1;
+    }
+    // 共享映射中子进程的写入对父进程可见，私有映射则不可见
+    if (shared[0] != 42 || shared[REGION_SIZE / sizeof(int) - 1] != 43) {
+        printf("parent sees %d, %d in shared mapping\n", shared[0],
+               shared[REGION_SIZE / sizeof(int) - 1]);
+        failed = 1;
+    }
+    if (private[0] != 1) {
+        puts("write to private mapping leaked to parent");
+        failed = 1;
+    }
+    munmap((void *)shared, REGION_SIZE);
+    munmap((void *)private, REGION_SIZE);
+
+    // 页面在第一次访问时才分配，fork 之后才分配的页面同样在父子进程间共享
+    volatile char *large = mmap(NULL, LARGE_SIZE, PROT_READ | PROT_WRITE,
+                                MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    if (large == MAP_FAILED) {
+        puts("large shared anonymous mapping failed");
+        failed = 1;
+    } else {
+        pid = fork();
+        if (pid == 0) {
+            large[0] = 1;
+            large[LARGE_SIZE - 1] = 2;
+            _exit(0);
+        }
+        waitpid(pid, NULL, 0);
+        if (large[0] != 1 || large[LARGE_SIZE - 1] != 2) {
+            puts("pages allocated by the child are not shared");
+            failed = 1;
+        }
+        munmap((void *)large, LARGE_SIZE);
+    }
+
+    // 解除一部分映射后，其余部分仍然与子进程共享
+    volatile char *pages = mmap(NULL, 4 * PAGE_SIZE, PROT_READ | PROT_WRITE,
+                                MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    if (pages == MAP_FAILED) {
+        puts("mmap failed");
+        return 1;
+    }
+    for (int i = 0; i < 4; i++)
+        pages[i * PAGE_SIZE] = 'a' + i;
+    if (munmap((void *)(pages + PAGE_SIZE), PAGE_SIZE) != 0) {
+        puts("partial munmap of a shared anonymous mapping failed");
+        failed = 1;
+    }
+    pid = fork();
+    if (pid == 0) {
+        int ret = pages[2 * PAGE_SIZE] == 'c' ? 0 : 1;
+        pages[0] = 'x';
+        pages[3 * PAGE_SIZE] = 'y';
+        _exit(ret);
+    }
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        puts("child sees wrong content after partial munmap");
+        failed = 1;
+    }
+    if (pages[0] != 'x' || pages[2 * PAGE_SIZE] != 'c' || pages[3 * PAGE_SIZE] != 'y') {
+        puts("split shared anonymous mapping is no longer shared");
+        failed = 1;
+    }
+    munmap((void *)pages, 4 * PAGE_SIZE);
+
+    puts(failed ? "shared_anon test failed" : "shared_anon test passed");
+    return failed;
+}
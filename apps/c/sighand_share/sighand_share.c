@@ -0,0 +1,91 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <pthread.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile pid_t handled_tid;
+static volatile int handled_by;
+
+static void handler_a(int sig)
+{
+    (void)sig;
+    handled_tid = syscall(SYS_gettid);
+    handled_by = 1;
+}
+
+static void handler_b(int sig)
+{
+    (void)sig;
+    handled_by = 2;
+}
+
+static void *install_handler(void *arg)
+{
+    (void)arg;
+    struct sigaction sa = {0};
+    sa.sa_handler = handler_a;
+    sigaction(SIGUSR1, &sa, NULL);
+    return NULL;
+}
+
+static pid_t receiver_tid;
+
+static void *receive_signal(void *arg)
+{
+    (void)arg;
+    receiver_tid = syscall(SYS_gettid);
+    // 信号发给自身，由本线程递送
+    syscall(SYS_tgkill, getpid(), receiver_tid, SIGUSR1);
+    return NULL;
+}
+
+int main(void)
+{
+    int failed = 0;
+    pthread_t thread;
+
+    // 一个线程安装的处理函数对其他线程可见
+    pthread_create(&thread, NULL, install_handler, NULL);
+    pthread_join(thread, NULL);
+    pthread_create(&thread, NULL, receive_signal, NULL);
+    pthread_join(thread, NULL);
+    if (handled_by != 1 || handled_tid != receiver_tid) {
+        printf("sibling delivery: handled by %d in tid %d, receiver %d\n", handled_by,
+               handled_tid, receiver_tid);
+        failed = 1;
+    }
+
+    // fork 得到的是一份独立的副本
+    pid_t child = fork();
+    if (child == 0) {
+        struct sigaction sa = {0};
+        sa.sa_handler = handler_b;
+        sigaction(SIGUSR1, &sa, NULL);
+        _exit(0);
+    }
+    waitpid(child, NULL, 0);
+    struct sigaction old;
+    sigaction(SIGUSR1, NULL, &old);
+    if (old.sa_handler != handler_a) {
+        puts("child's sigaction changed the parent's handler");
+        failed = 1;
+    }
+
+    // 线程必须共享处理函数，共享处理函数必须共享地址空间
+    if (syscall(SYS_clone, CLONE_THREAD | CLONE_VM, 0, 0, 0, 0) != -1 || errno != EINVAL) {
+        puts("CLONE_THREAD without CLONE_SIGHAND accepted");
+        failed = 1;
+    }
+    if (syscall(SYS_clone, CLONE_SIGHAND, 0, 0, 0, 0) != -1 || errno != EINVAL) {
+        puts("CLONE_SIGHAND without CLONE_VM accepted");
+        failed = 1;
+    }
+
+    puts(failed ? "sighand_share test failed" : "sighand_share test passed");
+    return failed;
+}
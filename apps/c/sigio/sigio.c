@@ -0,0 +1,129 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static volatile sig_atomic_t sigio_count = 0;
+static volatile sig_atomic_t rt_count = 0;
+static volatile int rt_fd = -1;
+static volatile int rt_code = 0;
+
+static void on_sigio(int sig)
+{
+    (void)sig;
+    sigio_count++;
+}
+
+static void on_rt(int sig, siginfo_t *info, void *ucontext)
+{
+    (void)sig;
+    (void)ucontext;
+    rt_count++;
+    rt_fd = info->si_fd;
+    rt_code = info->si_code;
+}
+
+// 等待信号到来，最多约一秒
+static void wait_for(volatile sig_atomic_t *count)
+{
+    for (int i = 0; i < 100 && *count == 0; i++)
+        usleep(10000);
+}
+
+int main(void)
+{
+    int fds[2];
+    char c = 'x';
+
+    // 默认发送不带信息的 SIGIO
+    check(signal(SIGIO, on_sigio) != SIG_ERR, "signal SIGIO");
+    check(pipe(fds) == 0, "pipe");
+    check(fcntl(fds[0], F_GETOWN) == 0, "no owner by default");
+    check(fcntl(fds[0], F_SETOWN, getpid()) == 0, "F_SETOWN");
+    check(fcntl(fds[0], F_GETOWN) == getpid(), "F_GETOWN");
+    check(!(fcntl(fds[0], F_GETFL) & O_ASYNC), "O_ASYNC clear by default");
+    check(fcntl(fds[0], F_SETFL, fcntl(fds[0], F_GETFL) | O_ASYNC) == 0, "set O_ASYNC");
+    check(fcntl(fds[0], F_GETFL) & O_ASYNC, "F_GETFL reports O_ASYNC");
+
+    pid_t child = fork();
+    if (child == 0) {
+        write(fds[1], &c, 1);
+        _exit(0);
+    }
+    int status;
+    check(waitpid(child, &status, 0) == child, "waitpid");
+    wait_for(&sigio_count);
+    check(sigio_count > 0, "SIGIO when the pipe becomes readable");
+    check(read(fds[0], &c, 1) == 1, "read the byte");
+
+    // 清除 O_ASYNC 后不再发送信号
+    check(fcntl(fds[0], F_SETFL, fcntl(fds[0], F_GETFL) & ~O_ASYNC) == 0, "clear O_ASYNC");
+    sigio_count = 0;
+    write(fds[1], &c, 1);
+    usleep(50000);
+    check(sigio_count == 0, "no SIGIO without O_ASYNC");
+    read(fds[0], &c, 1);
+
+    // 找不到属主时返回 ESRCH
+    errno = 0;
+    check(fcntl(fds[0], F_SETOWN, 0x7ffffff0) == -1 && errno == ESRCH, "F_SETOWN to a missing process");
+    close(fds[0]);
+    close(fds[1]);
+
+    // 属主为进程组时组内的所有进程都收到信号
+    check(pipe(fds) == 0, "pipe");
+    check(fcntl(fds[0], F_SETOWN, -getpgrp()) == 0, "F_SETOWN to the process group");
+    check(fcntl(fds[0], F_GETOWN) == -getpgrp(), "F_GETOWN reports the process group");
+    check(fcntl(fds[0], F_SETFL, O_ASYNC) == 0, "set O_ASYNC");
+    int ready[2];
+    check(pipe(ready) == 0, "pipe");
+    sigio_count = 0;
+    child = fork();
+    if (child == 0) {
+        write(ready[1], &c, 1);
+        wait_for(&sigio_count);
+        _exit(sigio_count > 0 ? 0 : 1);
+    }
+    read(ready[0], &c, 1);
+    write(fds[1], &c, 1);
+    wait_for(&sigio_count);
+    check(sigio_count > 0, "SIGIO to the owner group reaches the caller");
+    check(waitpid(child, &status, 0) == child && WIFEXITED(status) && WEXITSTATUS(status) == 0,
+          "SIGIO to the owner group reaches the other member");
+    errno = 0;
+    check(fcntl(fds[0], F_SETOWN, -0x7ffffff0) == -1 && errno == ESRCH, "F_SETOWN to a missing group");
+    close(fds[0]);
+    close(fds[1]);
+    close(ready[0]);
+    close(ready[1]);
+
+    // F_SETSIG 指定的信号带有文件描述符与事件
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = on_rt;
+    sa.sa_flags = SA_SIGINFO;
+    check(sigaction(SIGRTMIN, &sa, NULL) == 0, "sigaction SIGRTMIN");
+    check(pipe(fds) == 0, "pipe");
+    check(fcntl(fds[0], F_SETOWN, getpid()) == 0, "F_SETOWN");
+    check(fcntl(fds[0], F_SETSIG, SIGRTMIN) == 0, "F_SETSIG");
+    check(fcntl(fds[0], F_GETSIG) == SIGRTMIN, "F_GETSIG");
+    errno = 0;
+    check(fcntl(fds[0], F_SETSIG, 1000) == -1 && errno == EINVAL, "F_SETSIG rejects invalid signals");
+    check(fcntl(fds[0], F_SETFL, O_ASYNC) == 0, "set O_ASYNC");
+    write(fds[1], &c, 1);
+    wait_for(&rt_count);
+    check(rt_count > 0, "SIGRTMIN when the pipe becomes readable");
+    check(rt_fd == fds[0], "si_fd is the readable fd");
+    check(rt_code == POLL_IN, "si_code is POLL_IN");
+    close(fds[0]);
+    close(fds[1]);
+
+    puts(failed ? "sigio test failed" : "sigio test passed");
+    return failed;
+}
@@ -0,0 +1,92 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/select.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int handled = 0;
+
+static void handler(int sig)
+{
+    (void)sig;
+    handled++;
+}
+
+// 子进程稍后向父进程发送 SIGUSR1
+static pid_t send_later(void)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(100000);
+        kill(getppid(), SIGUSR1);
+        _exit(0);
+    }
+    return pid;
+}
+
+static int check(const char *name, int ret, pid_t pid)
+{
+    waitpid(pid, NULL, 0);
+    if (ret != -1 || errno != EINTR || handled != 1) {
+        printf("sigmask_wait: %s returned %d, errno %d, handled %d\n", name, ret, errno,
+               handled);
+        return 1;
+    }
+    handled = 0;
+    return 0;
+}
+
+// SIGUSR1 平时被阻塞，只在等待期间通过临时掩码解除阻塞
+// 等待应返回 EINTR，且处理函数恰好执行一次
+int main()
+{
+    int fds[2];
+    sigset_t block, empty;
+    struct sigaction sa = {0};
+    sa.sa_handler = handler;
+    sigaction(SIGUSR1, &sa, NULL);
+    sigemptyset(&block);
+    sigaddset(&block, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &block, NULL);
+    sigemptyset(&empty);
+    if (pipe(fds) < 0) {
+        perror("pipe");
+        return 1;
+    }
+
+    struct pollfd pfd = {.fd = fds[0], .events = POLLIN};
+    pid_t pid = send_later();
+    int ret = ppoll(&pfd, 1, NULL, &empty);
+    if (check("ppoll", ret, pid))
+        return 1;
+
+    fd_set rset;
+    FD_ZERO(&rset);
+    FD_SET(fds[0], &rset);
+    pid = send_later();
+    ret = pselect(fds[0] + 1, &rset, NULL, NULL, NULL, &empty);
+    if (check("pselect", ret, pid))
+        return 1;
+
+    int epfd = epoll_create1(0);
+    struct epoll_event ev = {.events = EPOLLIN, .data.fd = fds[0]};
+    epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &ev);
+    pid = send_later();
+    ret = epoll_pwait(epfd, &ev, 1, -1, &empty);
+    if (check("epoll_pwait", ret, pid))
+        return 1;
+
+    // 等待结束后原掩码应已恢复，SIGUSR1 仍被阻塞
+    sigset_t cur;
+    sigprocmask(SIG_BLOCK, NULL, &cur);
+    if (!sigismember(&cur, SIGUSR1)) {
+        printf("sigmask_wait: mask was not restored\n");
+        return 1;
+    }
+    printf("sigmask_wait: test passed\n");
+    return 0;
+}
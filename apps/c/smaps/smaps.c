@@ -0,0 +1,131 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+
+static char buf[1 << 20];
+
+// 读出整个文件，返回读到的长度
+static size_t read_file(const char *path)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return 0;
+    size_t total = 0;
+    ssize_t ret;
+    while ((ret = read(fd, buf + total, sizeof(buf) - 1 - total)) > 0)
+        total += ret;
+    close(fd);
+    buf[total] = 0;
+    return total;
+}
+
+// smaps 中起始地址为 addr 的映射的 field 一项，以 kB 为单位，找不到时返回 -1
+static long smaps_field(const void *addr, const char *field)
+{
+    if (read_file("/proc/self/smaps") == 0)
+        return -1;
+    char header[32];
+    snprintf(header, sizeof(header), "%08lx-", (unsigned long)addr);
+    char *entry = strstr(buf, header);
+    while (entry && entry != buf && entry[-1] != '\n')
+        entry = strstr(entry + 1, header);
+    if (!entry)
+        return -1;
+    // 下一个映射从下一个 VmFlags 之后开始
+    char *end = strstr(entry, "VmFlags:");
+    char key[32];
+    snprintf(key, sizeof(key), "\n%s:", field);
+    char *line = strstr(entry, key);
+    if (!line || (end && line > end))
+        return -1;
+    return atol(line + strlen(key));
+}
+
+// maps 中是否有起始地址为 addr 且权限为 perms 的映射
+static int maps_has(const void *addr, const char *perms)
+{
+    if (read_file("/proc/self/maps") == 0)
+        return 0;
+    char line[64];
+    snprintf(line, sizeof(line), "%08lx-", (unsigned long)addr);
+    char *entry = strstr(buf, line);
+    return entry && strncmp(strchr(entry, ' ') + 1, perms, 4) == 0;
+}
+
+int main(void)
+{
+    // 私有匿名映射只有被写过的页面计入 Rss。前后各留出一个空洞，避免与相邻的映射合并
+    char *priv = mmap(NULL, 18 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(priv != MAP_FAILED, "mmap private");
+    munmap(priv, PAGE);
+    munmap(priv + 17 * PAGE, PAGE);
+    priv += PAGE;
+    check(maps_has(priv, "rw-p"), "private mapping in maps");
+    check(smaps_field(priv, "Size") == 64, "Size of the private mapping");
+    check(smaps_field(priv, "Rss") == 0, "untouched mapping has no Rss");
+    for (int i = 0; i < 5; i++)
+        priv[i * 2 * PAGE] = 1;
+    check(smaps_field(priv, "Rss") == 20, "Rss counts touched pages");
+    check(smaps_field(priv, "Pss") == 20, "Pss of private pages");
+    check(smaps_field(priv, "Private_Dirty") == 20, "Private_Dirty counts touched pages");
+    check(smaps_field(priv, "Shared_Dirty") == 0, "no shared pages");
+    check(smaps_field(priv, "Anonymous") == 20, "touched pages are anonymous");
+    check(smaps_field(priv, "Swap") == 0, "no swap");
+
+    // 与子进程共享的页面计入 Shared，Pss 按映射数均摊
+    char *shared = mmap(NULL, 8 * PAGE, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    check(shared != MAP_FAILED, "mmap shared");
+    check(maps_has(shared, "rw-s"), "shared mapping in maps");
+    for (int i = 0; i < 3; i++)
+        shared[i * PAGE] = 1;
+    int ready[2], done[2];
+    check(pipe(ready) == 0 && pipe(done) == 0, "pipe");
+    pid_t child = fork();
+    if (child == 0) {
+        char c = 0;
+        for (int i = 0; i < 3; i++)
+            c += shared[i * PAGE];
+        write(ready[1], &c, 1);
+        read(done[0], &c, 1);
+        _exit(0);
+    }
+    char c;
+    check(read(ready[0], &c, 1) == 1 && c == 3, "child maps the shared pages");
+    long shared_dirty = smaps_field(shared, "Shared_Dirty");
+    long rss = smaps_field(shared, "Rss");
+    check(shared_dirty >= 12, "Shared_Dirty counts pages mapped by both processes");
+    check(smaps_field(shared, "Private_Dirty") == 0, "no private pages in the shared mapping");
+    check(smaps_field(shared, "Pss") * 2 == rss, "Pss is half of Rss");
+    write(done[1], &c, 1);
+    int status;
+    check(waitpid(child, &status, 0) == child, "waitpid");
+
+    // statm 以页为单位，常驻页数随写入增加
+    long size, resident, shr, text, lib, data, dt;
+    read_file("/proc/self/statm");
+    check(sscanf(buf, "%ld %ld %ld %ld %ld %ld %ld", &size, &resident, &shr, &text, &lib, &data, &dt) == 7,
+          "statm has seven fields");
+    check(size >= resident && resident >= 5, "statm size and resident");
+    for (int i = 0; i < 16; i++)
+        priv[i * PAGE] = 2;
+    long resident_after;
+    read_file("/proc/self/statm");
+    check(sscanf(buf, "%*s %ld", &resident_after) == 1 && resident_after >= resident + 11,
+          "resident grows with touched pages");
+
+    munmap(priv, 16 * PAGE);
+    munmap(shared, 8 * PAGE);
+    check(smaps_field(priv, "Size") == -1, "unmapped region disappears from smaps");
+
+    puts(failed ? "smaps test failed" : "smaps test passed");
+    return failed;
+}
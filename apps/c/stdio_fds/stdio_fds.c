@@ -0,0 +1,39 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+int main(void)
+{
+    // 进程启动时 0、1、2 已经打开，第一个 open 得到 3
+    for (int fd = 0; fd < 3; fd++)
+        check(fcntl(fd, F_GETFD) != -1, "standard fd is open");
+    int file = open("stdio_fds.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    check(file == 3, "first open returns fd 3");
+    close(file);
+    unlink("stdio_fds.txt");
+
+    // 控制台上没有输入时 stdin 不可读，poll 应当超时而不是立即返回
+    if (isatty(0)) {
+        struct pollfd pfd = {.fd = 0, .events = POLLIN};
+        check(poll(&pfd, 1, 10) == 0 && pfd.revents == 0, "stdin without input is not readable");
+    }
+
+    // fork 出的子进程继承标准输入输出
+    pid_t child = fork();
+    if (child == 0) {
+        int ok = fcntl(0, F_GETFD) != -1 && write(1, "", 0) == 0 && write(2, "", 0) == 0;
+        _exit(ok ? 0 : 1);
+    }
+    int status;
+    check(waitpid(child, &status, 0) == child && WIFEXITED(status) && WEXITSTATUS(status) == 0,
+          "child inherits the standard fds");
+
+    puts(failed ? "stdio_fds test failed" : "stdio_fds test passed");
+    return failed;
+}
@@ -0,0 +1,40 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static void expect(long ret, int err, const char *what)
+{
+    if (ret != -1 || errno != err) {
+        printf("FAIL: %s returned %ld, errno %d (expected %d)\n", what, ret, errno, err);
+        failed = 1;
+    }
+}
+
+int main(void)
+{
+    char buf[16];
+    void *bad = (void *)16;
+
+    // 错误以负的 errno 返回，用户态通过 errno 看到
+    expect(read(1000, buf, sizeof(buf)), EBADF, "read on a bad fd");
+    expect(open("/no/such/file", O_RDONLY), ENOENT, "open a missing file");
+    expect(syscall(SYS_openat, AT_FDCWD, bad, O_RDONLY, 0), EFAULT, "openat with a bad path pointer");
+    expect(write(1, bad, 8), EFAULT, "write from a bad buffer");
+    expect(syscall(SYS_uname, bad), EFAULT, "uname into a bad buffer");
+    expect(writev(1, bad, 1), EFAULT, "writev with a bad iovec array");
+    expect(close(1000), EBADF, "close a bad fd");
+
+    // 读写 0 字节总是成功
+    if (read(0, NULL, 0) != 0 || write(1, NULL, 0) != 0) {
+        puts("FAIL: zero-length read/write");
+        failed = 1;
+    }
+
+    puts(failed ? "syscall_errno test failed" : "syscall_errno test passed");
+    return failed;
+}
@@ -0,0 +1,104 @@
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/time.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+// 读取 /proc/<pid>/stat 中的状态字母，失败时返回 0
+static char proc_state(pid_t pid)
+{
+    char path[64], buf[256];
+    snprintf(path, sizeof(path), "/proc/%d/stat", pid);
+    FILE *fp = fopen(path, "r");
+    if (fp == NULL)
+        return 0;
+    size_t n = fread(buf, 1, sizeof(buf) - 1, fp);
+    fclose(fp);
+    buf[n] = '\0';
+    char *p = strrchr(buf, ')');
+    if (p == NULL || p[1] != ' ')
+        return 0;
+    return p[2];
+}
+
+static long now_ms(void)
+{
+    struct timeval tv;
+    gettimeofday(&tv, NULL);
+    return tv.tv_sec * 1000 + tv.tv_usec / 1000;
+}
+
+static int killed_by_sigkill(pid_t pid)
+{
+    int status = 0;
+    return waitpid(pid, &status, 0) == pid && WIFSIGNALED(status) &&
+           WTERMSIG(status) == SIGKILL;
+}
+
+static void on_usr1(int sig)
+{
+    (void)sig;
+}
+
+static pid_t spawn_pauser(void)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        for (;;)
+            pause();
+    }
+    return pid;
+}
+
+int main(void)
+{
+    // SIGSTOP 之后进程显示为 T，SIGCONT 之后回到可中断的休眠
+    pid_t pid = spawn_pauser();
+    usleep(50000);
+    check(proc_state(pid) == 'S', "pausing task is in interruptible sleep");
+    kill(pid, SIGSTOP);
+    usleep(50000);
+    check(proc_state(pid) == 'T', "SIGSTOP stops the task");
+    kill(pid, SIGCONT);
+    usleep(50000);
+    check(proc_state(pid) == 'S', "SIGCONT resumes the task");
+    kill(pid, SIGKILL);
+    check(killed_by_sigkill(pid), "resumed task is killed by SIGKILL");
+
+    // 停止的任务也能被 SIGKILL 结束
+    pid = spawn_pauser();
+    usleep(50000);
+    kill(pid, SIGSTOP);
+    usleep(50000);
+    check(proc_state(pid) == 'T', "second task is stopped");
+    kill(pid, SIGKILL);
+    check(killed_by_sigkill(pid), "SIGKILL wakes a stopped task");
+
+    // vfork 的父进程在等待子进程时处于 killable 的休眠：
+    // 有处理函数的信号不能唤醒它，SIGKILL 可以
+    pid = fork();
+    if (pid == 0) {
+        signal(SIGUSR1, on_usr1);
+        if (vfork() == 0) {
+            usleep(2000000);
+            _exit(0);
+        }
+        _exit(0);
+    }
+    usleep(100000);
+    check(proc_state(pid) == 'D', "vfork parent is in killable sleep");
+    kill(pid, SIGUSR1);
+    usleep(50000);
+    check(waitpid(pid, NULL, WNOHANG) == 0, "SIGUSR1 does not wake a killable sleep");
+    check(proc_state(pid) == 'D', "vfork parent is still sleeping after SIGUSR1");
+    long start = now_ms();
+    kill(pid, SIGKILL);
+    check(killed_by_sigkill(pid), "SIGKILL wakes a killable sleep");
+    check(now_ms() - start < 1000, "SIGKILL does not wait for the vfork child");
+
+    puts(failed ? "task_state test failed" : "task_state test passed");
+    return failed;
+}
@@ -0,0 +1,34 @@
+#include <errno.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "check.h"
+
+int main(void)
+{
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    check(fd >= 0, "socket");
+
+    // 算法名可以带有结尾的 '\0'
+    check(setsockopt(fd, IPPROTO_TCP, TCP_CONGESTION, "reno", 5) == 0, "set reno");
+    char name[16] = {0};
+    socklen_t len = sizeof(name);
+    check(getsockopt(fd, IPPROTO_TCP, TCP_CONGESTION, name, &len) == 0 && strcmp(name, "reno") == 0,
+          "get reno");
+
+    // 不合法的算法名返回错误，而不是使内核崩溃
+    const char bad[] = {(char)0xff, (char)0xfe, 0};
+    check(setsockopt(fd, IPPROTO_TCP, TCP_CONGESTION, bad, sizeof(bad)) == -1, "invalid name is rejected");
+    memset(name, 0, sizeof(name));
+    len = sizeof(name);
+    check(getsockopt(fd, IPPROTO_TCP, TCP_CONGESTION, name, &len) == 0 && strcmp(name, "reno") == 0,
+          "rejected name keeps the old algorithm");
+    close(fd);
+
+    puts(failed ? "tcp_congestion test failed" : "tcp_congestion test passed");
+    return failed;
+}
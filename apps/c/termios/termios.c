@@ -0,0 +1,59 @@
+#include <signal.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static volatile sig_atomic_t winch = 0;
+
+static void on_winch(int sig)
+{
+    (void)sig;
+    winch = 1;
+}
+
+static long elapsed_ms(struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+int main(void)
+{
+    struct termios saved, raw, now;
+    check(tcgetattr(0, &saved) == 0, "tcgetattr");
+    check((saved.c_lflag & ICANON) && (saved.c_lflag & ECHO), "canonical mode with echo by default");
+
+    // 关闭规范模式与回显，VMIN = 0, VTIME = 1：没有输入时 read 约 100 毫秒后返回 0
+    raw = saved;
+    raw.c_lflag &= ~(ICANON | ECHO);
+    raw.c_cc[VMIN] = 0;
+    raw.c_cc[VTIME] = 1;
+    check(tcsetattr(0, TCSAFLUSH, &raw) == 0, "tcsetattr TCSAFLUSH");
+    check(tcgetattr(0, &now) == 0 && !(now.c_lflag & ICANON) && now.c_cc[VTIME] == 1,
+          "settings are stored");
+    struct timespec start;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    char ch;
+    check(read(0, &ch, 1) == 0, "read times out with no input");
+    long ms = elapsed_ms(&start);
+    check(ms >= 90 && ms < 1000, "VTIME timeout is about 100ms");
+
+    // 修改窗口大小后前台进程组收到 SIGWINCH
+    signal(SIGWINCH, on_winch);
+    tcsetpgrp(0, getpid());
+    struct winsize ws = {.ws_row = 30, .ws_col = 100};
+    check(ioctl(0, TIOCSWINSZ, &ws) == 0, "TIOCSWINSZ");
+    struct winsize got = {0};
+    check(ioctl(0, TIOCGWINSZ, &got) == 0 && got.ws_row == 30 && got.ws_col == 100,
+          "TIOCGWINSZ returns the new size");
+    check(winch, "SIGWINCH delivered");
+
+    tcsetattr(0, TCSANOW, &saved);
+    puts(failed ? "termios test failed" : "termios test passed");
+    return failed;
+}
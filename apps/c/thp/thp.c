@@ -0,0 +1,135 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+#define HUGE (2 << 20)
+#define LEN (8 << 20)
+
+#ifndef MADV_COLLAPSE
+#define MADV_COLLAPSE 25
+#endif
+
+// 读出 /proc/vmstat 中名为 name 的计数，失败时返回 -1
+static long vmstat(const char *name)
+{
+    static char buf[16384];
+    int fd = open("/proc/vmstat", O_RDONLY);
+    if (fd < 0)
+        return -1;
+    size_t total = 0;
+    ssize_t ret;
+    while ((ret = read(fd, buf + total, sizeof(buf) - 1 - total)) > 0)
+        total += ret;
+    close(fd);
+    buf[total] = 0;
+    char key[64];
+    snprintf(key, sizeof(key), "%s ", name);
+    char *line = strstr(buf, key);
+    // 只匹配行首的名字
+    while (line && line != buf && line[-1] != '\n')
+        line = strstr(line + 1, key);
+    return line ? atol(line + strlen(key)) : -1;
+}
+
+// 映射 LEN 字节 2M 对齐的私有匿名内存
+static char *map_aligned(void)
+{
+    char *p = mmap(NULL, LEN + HUGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+        return NULL;
+    char *aligned = (char *)(((uintptr_t)p + HUGE - 1) & ~(uintptr_t)(HUGE - 1));
+    if (aligned > p)
+        munmap(p, aligned - p);
+    munmap(aligned + LEN, p + HUGE - aligned);
+    return aligned;
+}
+
+static void fill(char *p, size_t len)
+{
+    for (size_t i = 0; i < len; i += PAGE)
+        p[i] = (char)(i / PAGE);
+}
+
+static int verify(const char *p, size_t len)
+{
+    for (size_t i = 0; i < len; i += PAGE)
+        if (p[i] != (char)(i / PAGE))
+            return 0;
+    return 1;
+}
+
+int main(void)
+{
+    check(vmstat("thp_fault_alloc") >= 0 && vmstat("thp_collapse_alloc") >= 0, "read THP counters");
+
+    // 被标记的区域在第一次缺页时直接分配大页
+    char *p = map_aligned();
+    check(p != NULL, "mmap");
+    check(madvise(p, LEN, MADV_HUGEPAGE) == 0, "MADV_HUGEPAGE");
+    long fault_before = vmstat("thp_fault_alloc");
+    fill(p, LEN);
+    check(verify(p, LEN), "data in huge pages");
+    check(vmstat("thp_fault_alloc") >= fault_before + LEN / HUGE, "huge pages allocated on fault");
+
+    // 部分 munmap、mprotect 与 MADV_DONTNEED 会拆分大页，其余的页面不受影响
+    check(munmap(p + PAGE, PAGE) == 0, "munmap one page of a huge page");
+    check(p[0] == 0 && p[2 * PAGE] == 2, "pages around the hole are kept");
+    check(mprotect(p + HUGE + PAGE, PAGE, PROT_READ) == 0, "mprotect one page of a huge page");
+    check(p[HUGE + PAGE] == (char)(HUGE / PAGE + 1), "read-only page keeps its data");
+    p[HUGE + 2 * PAGE] = 0x7f;
+    check(p[HUGE + 2 * PAGE] == 0x7f, "neighbouring page is still writable");
+    check(madvise(p + 2 * HUGE + PAGE, PAGE, MADV_DONTNEED) == 0, "MADV_DONTNEED one page");
+    check(p[2 * HUGE + PAGE] == 0 && p[2 * HUGE] == (char)(2 * HUGE / PAGE),
+          "only the discarded page reads zero");
+
+    // 子进程看到同样的数据
+    pid_t child = fork();
+    if (child == 0)
+        _exit(verify(p + 3 * HUGE, HUGE) ? 0 : 1);
+    int status = 0;
+    check(waitpid(child, &status, 0) == child && WIFEXITED(status) && WEXITSTATUS(status) == 0,
+          "child reads huge pages");
+    munmap(p, LEN);
+
+    // 先以小页面填满，再标记时合并为大页
+    char *q = map_aligned();
+    check(q != NULL, "mmap");
+    check(madvise(q, LEN, MADV_NOHUGEPAGE) == 0, "MADV_NOHUGEPAGE");
+    fault_before = vmstat("thp_fault_alloc");
+    fill(q, LEN);
+    check(vmstat("thp_fault_alloc") == fault_before, "no huge pages without the advice");
+    long collapse_before = vmstat("thp_collapse_alloc");
+    check(madvise(q, LEN, MADV_HUGEPAGE) == 0, "MADV_HUGEPAGE on populated memory");
+    check(vmstat("thp_collapse_alloc") >= collapse_before + LEN / HUGE, "populated pages collapsed");
+    check(verify(q, LEN), "data survives the collapse");
+    munmap(q, LEN);
+
+    // MADV_COLLAPSE 不需要 MADV_HUGEPAGE 标记，立即将填满的 2M 范围合并为一个大页
+    char *r = map_aligned();
+    check(r != NULL, "mmap");
+    check(madvise(r, LEN, MADV_NOHUGEPAGE) == 0, "MADV_NOHUGEPAGE");
+    errno = 0;
+    check(madvise(r, HUGE, MADV_COLLAPSE) == -1 && errno == EINVAL, "MADV_COLLAPSE on empty memory: EINVAL");
+    fill(r, HUGE);
+    collapse_before = vmstat("thp_collapse_alloc");
+    check(madvise(r, HUGE, MADV_COLLAPSE) == 0, "MADV_COLLAPSE");
+    check(vmstat("thp_collapse_alloc") == collapse_before + 1, "one huge page after MADV_COLLAPSE");
+    check(verify(r, HUGE), "data survives MADV_COLLAPSE");
+    r[PAGE] = 0x55;
+    check(r[PAGE] == 0x55 && r[0] == 0, "collapsed page is writable");
+    errno = 0;
+    check(madvise(r + PAGE, HUGE, MADV_COLLAPSE) == -1 && errno == EINVAL, "unaligned MADV_COLLAPSE: EINVAL");
+    munmap(r, LEN);
+
+    puts(failed ? "thp test failed" : "thp test passed");
+    return failed;
+}
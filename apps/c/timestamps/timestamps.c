@@ -0,0 +1,111 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define TEST_FILE "timestamps_file"
+#define TEST_LINK "timestamps_link"
+
+static int same_time(struct timespec a, struct timespec b)
+{
+    return a.tv_sec == b.tv_sec && a.tv_nsec == b.tv_nsec;
+}
+
+static int changed(struct timespec now, struct timespec before)
+{
+    return !same_time(now, before);
+}
+
+// 与上一次记录的时间戳比较，检查 atime、mtime、ctime 是否分别发生了变化
+static struct stat last;
+
+static void expect(int atime, int mtime, int ctime, const char *msg)
+{
+    struct stat st;
+    if (stat(TEST_FILE, &st) != 0) {
+        check(0, msg);
+        return;
+    }
+    char buf[128];
+    snprintf(buf, sizeof(buf), "%s: atime", msg);
+    check(changed(st.st_atim, last.st_atim) == atime, buf);
+    snprintf(buf, sizeof(buf), "%s: mtime", msg);
+    check(changed(st.st_mtim, last.st_mtim) == mtime, buf);
+    snprintf(buf, sizeof(buf), "%s: ctime", msg);
+    check(changed(st.st_ctim, last.st_ctim) == ctime, buf);
+    last = st;
+    // 保证下一步操作得到的时间与这一步的不同
+    usleep(20000);
+}
+
+int main(void)
+{
+    char buf[16];
+    unlink(TEST_LINK);
+    unlink(TEST_FILE);
+
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "create");
+    check(stat(TEST_FILE, &last) == 0, "stat after create");
+    check(same_time(last.st_atim, last.st_mtim) && same_time(last.st_mtim, last.st_ctim),
+          "a new file has equal timestamps");
+    usleep(20000);
+
+    check(write(fd, "hello", 5) == 5, "write");
+    expect(0, 1, 1, "write");
+
+    // relatime：修改之后的第一次读取更新 atime，之后的读取不再更新
+    check(pread(fd, buf, sizeof(buf), 0) == 5, "read");
+    expect(1, 0, 0, "first read");
+    check(pread(fd, buf, sizeof(buf), 0) == 5, "read again");
+    expect(0, 0, 0, "second read");
+
+    check(chmod(TEST_FILE, 0600) == 0, "chmod");
+    expect(0, 0, 1, "chmod");
+
+    check(ftruncate(fd, 2) == 0, "ftruncate");
+    expect(0, 1, 1, "ftruncate");
+
+    check(link(TEST_FILE, TEST_LINK) == 0, "link");
+    expect(0, 0, 1, "link");
+
+    struct timespec times[2] = {{1000, 0}, {2000, 0}};
+    check(utimensat(AT_FDCWD, TEST_FILE, times, 0) == 0, "utimensat");
+    expect(1, 1, 1, "utimensat");
+    check(last.st_atim.tv_sec == 1000 && last.st_mtim.tv_sec == 2000, "utimensat sets the times");
+
+    struct timespec omit[2] = {{0, UTIME_OMIT}, {0, UTIME_OMIT}};
+    check(utimensat(AT_FDCWD, TEST_FILE, omit, 0) == 0, "utimensat UTIME_OMIT");
+    expect(0, 0, 0, "utimensat UTIME_OMIT");
+
+    // O_NOATIME 打开的文件读取时不更新 atime，即使 relatime 本会更新
+    int noatime = open(TEST_FILE, O_RDONLY | O_NOATIME);
+    check(noatime >= 0 && read(noatime, buf, sizeof(buf)) == 2, "O_NOATIME read");
+    expect(0, 0, 0, "O_NOATIME read");
+    close(noatime);
+
+    // futimens 修改的是 fd 对应的文件
+    check(futimens(fd, NULL) == 0, "futimens");
+    expect(1, 1, 1, "futimens");
+
+    // 不同的文件描述符与按路径的 stat 看到同一份时间戳
+    int other = open(TEST_FILE, O_RDONLY);
+    struct stat st;
+    check(other >= 0 && fstat(other, &st) == 0, "fstat");
+    check(same_time(st.st_atim, last.st_atim) && same_time(st.st_mtim, last.st_mtim) &&
+              same_time(st.st_ctim, last.st_ctim),
+          "fstat on another fd matches stat");
+    close(other);
+    close(fd);
+
+    unlink(TEST_LINK);
+    unlink(TEST_FILE);
+
+    puts(failed ? "timestamps test failed" : "timestamps test passed");
+    return failed;
+}
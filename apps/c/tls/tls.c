@@ -0,0 +1,71 @@
+#include <pthread.h>
+#include <stdint.h>
+#include <stdio.h>
+
+#include "check.h"
+
+static __thread int tls_value = 42;
+static __thread char tls_buf[64];
+
+// 读取用户态的线程指针
+static uintptr_t thread_pointer(void)
+{
+    uintptr_t tp;
+#if defined(__riscv)
+    __asm__ volatile("mv %0, tp" : "=r"(tp));
+#elif defined(__aarch64__)
+    __asm__ volatile("mrs %0, tpidr_el0" : "=r"(tp));
+#elif defined(__x86_64__)
+    // fs:0 保存着线程控制块自身的地址，即 fs_base
+    __asm__ volatile("mov %%fs:0, %0" : "=r"(tp));
+#else
+#error "unsupported architecture"
+#endif
+    return tp;
+}
+
+struct thread_result {
+    uintptr_t tp;
+    intptr_t offset;
+    int initial;
+    int value;
+};
+
+// 新线程由 clone 的 CLONE_SETTLS 设置线程指针，TLS 变量从初始值开始
+static void *worker(void *arg)
+{
+    struct thread_result *result = arg;
+    result->tp = thread_pointer();
+    result->offset = (intptr_t)((uintptr_t)&tls_value - result->tp);
+    result->initial = tls_value;
+    tls_value = 7;
+    tls_buf[0] = 'w';
+    result->value = tls_value;
+    return NULL;
+}
+
+int main(void)
+{
+    // 初始线程的线程指针由 libc 在启动时设置
+    uintptr_t main_tp = thread_pointer();
+    check(main_tp != 0, "initial thread pointer is set");
+    check(tls_value == 42, "initial tls value");
+    tls_value = 100;
+    tls_buf[0] = 'm';
+    intptr_t main_offset = (intptr_t)((uintptr_t)&tls_value - main_tp);
+
+    struct thread_result result = {0};
+    pthread_t thread;
+    check(pthread_create(&thread, NULL, worker, &result) == 0, "pthread_create");
+    check(pthread_join(thread, NULL) == 0, "pthread_join");
+
+    check(result.tp != 0 && result.tp != main_tp, "new thread has its own thread pointer");
+    check(result.offset == main_offset, "tls variable lies at the same offset from tp");
+    check(result.initial == 42, "new thread sees the initial tls value");
+    check(result.value == 7, "new thread writes its own tls");
+    check(tls_value == 100 && tls_buf[0] == 'm', "main thread tls is untouched");
+    check(thread_pointer() == main_tp, "main thread pointer is unchanged");
+
+    puts(failed ? "tls test failed" : "tls test passed");
+    return failed;
+}
@@ -0,0 +1,38 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+// 将 fd 1 重定向到文件后，写 /dev/tty 仍应当输出到控制台
+int main()
+{
+    const char *msg = "tty: hello from /dev/tty\n";
+    int file = open("./tty_redirect.txt", O_CREAT | O_RDWR, 0644);
+    if (file < 0) {
+        perror("open");
+        return 1;
+    }
+    int saved = dup(1);
+    dup2(file, 1);
+    printf("this line goes to the file\n");
+    fflush(stdout);
+
+    int tty = open("/dev/tty", O_RDWR);
+    if (tty < 0) {
+        dup2(saved, 1);
+        printf("tty: open /dev/tty failed\n");
+        return 1;
+    }
+    int ret = write(tty, msg, strlen(msg));
+    close(tty);
+
+    dup2(saved, 1);
+    close(file);
+    unlink("./tty_redirect.txt");
+    if (ret != (int)strlen(msg)) {
+        printf("tty: write returned %d\n", ret);
+        return 1;
+    }
+    printf("tty: test passed\n");
+    return 0;
+}
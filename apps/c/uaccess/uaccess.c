@@ -0,0 +1,65 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#define TEST_FILE "uaccess_test.txt"
+
+// 期望系统调用以 err 失败
+static int expect_errno(const char *name, long ret, int err)
+{
+    if (ret != -1 || errno != err) {
+        printf("%s: ret %ld, errno %d, expected %d\n", name, ret, errno, err);
+        return 1;
+    }
+    return 0;
+}
+
+// 期望系统调用以 EFAULT 失败
+static int expect_efault(const char *name, long ret)
+{
+    return expect_errno(name, ret, EFAULT);
+}
+
+int main(void)
+{
+    int failed = 0;
+
+    // 非法的用户指针不应导致内核崩溃，而是返回 EFAULT
+    failed |= expect_efault("write to stdout", syscall(SYS_write, 1, (void *)1, 10));
+
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+    lseek(fd, 0, SEEK_SET);
+    failed |= expect_efault("read", syscall(SYS_read, fd, (void *)1, 5));
+    failed |= expect_efault("read kernel address", syscall(SYS_read, fd, (void *)-4096L, 5));
+    failed |= expect_efault("pread64", syscall(SYS_pread64, fd, (void *)1, 5, 0));
+    failed |= expect_efault("pwrite64", syscall(SYS_pwrite64, fd, (void *)1, 5, 0));
+    failed |= expect_efault("writev", syscall(SYS_writev, fd, (void *)1, 1));
+    failed |= expect_efault("uname", syscall(SYS_uname, NULL));
+
+    // 之前的错误不影响正常的读写
+    char buf[8] = {0};
+    if (pread(fd, buf, 5, 0) != 5 || buf[0] != 'h' || buf[4] != 'o') {
+        puts("pread after faults failed");
+        failed = 1;
+    }
+    close(fd);
+    unlink(TEST_FILE);
+
+    // 已关闭的文件描述符返回 EBADF，管道不能定位，返回 ESPIPE
+    failed |= expect_errno("pread64 closed fd", pread(fd, buf, 5, 0), EBADF);
+    failed |= expect_errno("pwrite64 closed fd", pwrite(fd, "x", 1, 0), EBADF);
+    failed |= expect_errno("sendfile closed fd", syscall(SYS_sendfile, 1, fd, NULL, 5), EBADF);
+    int fds[2];
+    pipe(fds);
+    failed |= expect_errno("pread64 pipe", pread(fds[0], buf, 5, 0), ESPIPE);
+    close(fds[0]);
+    close(fds[1]);
+
+    puts(failed ? "uaccess test failed" : "uaccess test passed");
+    return failed;
+}
@@ -0,0 +1,104 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/uio.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#define PAGE 4096
+#define BUF_PAGES 16
+#define BUF_LEN (BUF_PAGES * PAGE)
+#define ROUNDS 2000
+#define RACE_MS 5000
+
+static char payload[] = "payload";
+static char *page;
+static char *buf;
+static atomic_int stop = 0;
+
+// 在内核复制数据的同时反复取消映射、重新映射 buf 的最后一页
+static void *toggle(void *arg)
+{
+    (void)arg;
+    char *last = buf + (BUF_PAGES - 1) * PAGE;
+    while (!atomic_load(&stop)) {
+        munmap(last, PAGE);
+        sched_yield();
+        mmap(last, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1, 0);
+        sched_yield();
+    }
+    return NULL;
+}
+
+static long now_ms(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+int main(void)
+{
+    int fd = open("/dev/null", O_WRONLY);
+    check(fd >= 0, "open /dev/null");
+
+    page = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(page != MAP_FAILED, "mmap");
+    struct iovec *iov = (struct iovec *)page;
+    iov->iov_base = payload;
+    iov->iov_len = sizeof(payload);
+    check(writev(fd, iov, 1) == sizeof(payload), "writev with mapped iovec");
+
+    // 未映射的地址在复制之前就被拒绝
+    munmap(page, PAGE);
+    errno = 0;
+    check(writev(fd, iov, 1) == -1 && errno == EFAULT, "writev with unmapped iovec is EFAULT");
+    struct timespec *ts = (struct timespec *)page;
+    errno = 0;
+    check(nanosleep(ts, NULL) == -1 && errno == EFAULT, "nanosleep with unmapped request is EFAULT");
+
+    // 一次复制多页，复制前面的页面时最后一页被其他线程取消映射，
+    // 此时地址检查已经通过，由修复代码返回 EFAULT（或者只写入出错之前的部分）而不是让内核崩溃
+    int pipefd[2];
+    check(pipe(pipefd) == 0, "pipe");
+    check(fcntl(pipefd[1], F_SETPIPE_SZ, 2 * BUF_LEN) >= BUF_LEN, "F_SETPIPE_SZ");
+    fcntl(pipefd[0], F_SETFL, O_NONBLOCK);
+    buf = mmap(NULL, BUF_LEN, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(buf != MAP_FAILED, "mmap buffer");
+    memset(buf, 'x', BUF_LEN);
+    static char drain[BUF_LEN];
+    pthread_t thread;
+    pthread_create(&thread, NULL, toggle, NULL);
+    int ok = 0, faults = 0, others = 0;
+    long deadline = now_ms() + RACE_MS;
+    for (int i = 0; i < ROUNDS || (faults == 0 && now_ms() < deadline); i++) {
+        errno = 0;
+        ssize_t ret = write(pipefd[1], buf, BUF_LEN);
+        if (ret == BUF_LEN)
+            ok++;
+        else if ((ret == -1 && errno == EFAULT) || (ret > 0 && ret < BUF_LEN))
+            faults++;
+        else
+            others++;
+        while (read(pipefd[0], drain, sizeof(drain)) > 0)
+            ;
+    }
+    atomic_store(&stop, 1);
+    pthread_join(thread, NULL);
+    printf("racing write: %d copied, %d faulted\n", ok, faults);
+    check(others == 0, "racing write either copies the buffer or stops at the fault");
+    check(faults > 0, "the mapping is removed while the buffer is copied");
+    close(pipefd[0]);
+    close(pipefd[1]);
+
+    close(fd);
+    puts(failed ? "uaccess_fault test failed" : "uaccess_fault test passed");
+    return failed;
+}
@@ -0,0 +1,136 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "check.h"
+
+static void on_usr1(int sig)
+{
+    (void)sig;
+}
+
+int main(void)
+{
+    int status = -1;
+
+    // 没有子进程时返回 ECHILD
+    errno = 0;
+    check(waitpid(-1, &status, WNOHANG) == -1 && errno == ECHILD, "wait without children sets ECHILD");
+
+    // 正常退出的子进程，退出码位于状态的 8~15 位
+    pid_t child = fork();
+    if (child == 0) {
+        _exit(42);
+    }
+    check(waitpid(child, &status, 0) == child, "waitpid returns the child pid");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 42, "exit code is reported");
+    check(!WIFSIGNALED(status), "normal exit is not signaled");
+
+    // 已经回收的子进程不能再次等待
+    errno = 0;
+    check(waitpid(child, &status, 0) == -1 && errno == ECHILD, "reaped child can not be waited again");
+
+    // 退出码只保留低 8 位
+    child = fork();
+    if (child == 0) {
+        exit(0x1ff);
+    }
+    check(waitpid(-1, &status, 0) == child, "wait any child");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 0xff, "exit code is truncated to 8 bits");
+
+    // 尚未退出的子进程，WNOHANG 立即返回 0；被信号终止时报告信号编号
+    int pipe_fds[2];
+    check(pipe(pipe_fds) == 0, "pipe");
+    child = fork();
+    if (child == 0) {
+        char c;
+        close(pipe_fds[1]);
+        read(pipe_fds[0], &c, 1);
+        _exit(0);
+    }
+    close(pipe_fds[0]);
+    check(waitpid(child, &status, WNOHANG) == 0, "WNOHANG returns 0 for a running child");
+    check(kill(child, SIGKILL) == 0, "kill child");
+    check(waitpid(child, &status, 0) == child, "wait killed child");
+    check(WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL, "killing signal is reported");
+    check(!WIFEXITED(status), "killed child did not exit normally");
+    close(pipe_fds[1]);
+
+    // 状态指针可以为空，rusage 中带有子进程的资源使用情况
+    child = fork();
+    if (child == 0) {
+        volatile unsigned long sum = 0;
+        for (unsigned long i = 0; i < 1000000; i++) {
+            sum += i;
+        }
+        _exit(0);
+    }
+    struct rusage usage;
+    usage.ru_maxrss = -1;
+    check(wait4(child, NULL, 0, &usage) == child, "wait4 with null status");
+    check(usage.ru_maxrss >= 0, "rusage is filled");
+    check(usage.ru_utime.tv_usec >= 0 && usage.ru_utime.tv_usec < 1000000, "rusage utime is valid");
+
+    // pid 为 0 时等待同组的子进程，小于 -1 时等待进程组 -pid 中的子进程
+    check(pipe(pipe_fds) == 0, "pipe for process groups");
+    pid_t grouped = fork();
+    if (grouped == 0) {
+        char c;
+        setpgid(0, 0);
+        close(pipe_fds[1]);
+        read(pipe_fds[0], &c, 1);
+        _exit(1);
+    }
+    setpgid(grouped, grouped);
+    child = fork();
+    if (child == 0) {
+        char c;
+        close(pipe_fds[1]);
+        read(pipe_fds[0], &c, 1);
+        _exit(2);
+    }
+    close(pipe_fds[0]);
+    check(getpgid(grouped) == grouped, "setpgid moves the child to its own group");
+    check(getpgid(child) == getpgid(0), "child inherits the process group");
+    check(waitpid(0, &status, WNOHANG) == 0, "WNOHANG on own group with a running child");
+    check(waitpid(-grouped, &status, WNOHANG) == 0, "WNOHANG on other group with a running child");
+    close(pipe_fds[1]);
+    check(waitpid(-grouped, &status, 0) == grouped, "wait the child in the given group");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 1, "grouped child exit code");
+    errno = 0;
+    check(waitpid(-grouped, &status, 0) == -1 && errno == ECHILD, "no more children in the group");
+    check(waitpid(0, &status, 0) == child, "wait the child in own group");
+    check(WIFEXITED(status) && WEXITSTATUS(status) == 2, "own group child exit code");
+
+    // 阻塞的等待可以被有处理函数的信号打断
+    struct sigaction sa = {0};
+    sa.sa_handler = on_usr1;
+    sigaction(SIGUSR1, &sa, NULL);
+    pid_t parent = getpid();
+    child = fork();
+    if (child == 0) {
+        usleep(50000);
+        kill(parent, SIGUSR1);
+        for (;;)
+            pause();
+    }
+    errno = 0;
+    check(waitpid(child, &status, 0) == -1 && errno == EINTR, "signal interrupts a blocking wait");
+    kill(child, SIGKILL);
+    check(waitpid(child, &status, 0) == child, "wait child after the interruption");
+
+    // 无效的状态指针返回 EFAULT
+    child = fork();
+    if (child == 0) {
+        _exit(0);
+    }
+    errno = 0;
+    check(wait4(child, (int *)1, 0, NULL) == -1 && errno == EFAULT, "bad status pointer sets EFAULT");
+
+    puts(failed ? "wait4 test failed" : "wait4 test passed");
+    return failed;
+}
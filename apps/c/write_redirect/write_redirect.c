@@ -0,0 +1,92 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define TEST_FILE "write_redirect.txt"
+
+int main(void)
+{
+    int failed = 0;
+    char buf[64] = {0};
+
+    // 写入普通文件的内容留在文件中，而不是输出到控制台
+    int fd = open(TEST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (write(fd, "file\n", 5) != 5) {
+        puts("write to file failed");
+        failed = 1;
+    }
+
+    // 将标准输出重定向到文件后，写 fd 1 的内容进入文件
+    int saved_stdout = dup(1);
+    dup2(fd, 1);
+    if (write(1, "stdout\n", 7) != 7) {
+        failed = 1;
+    }
+    dup2(saved_stdout, 1);
+    close(saved_stdout);
+
+    lseek(fd, 0, SEEK_SET);
+    ssize_t len = read(fd, buf, sizeof(buf) - 1);
+    if (len != 12 || strcmp(buf, "file\nstdout\n") != 0) {
+        printf("file content: %zd bytes \"%s\"\n", len, buf);
+        failed = 1;
+    }
+    close(fd);
+
+    // 以 O_WRONLY 打开后写入跨越多页的内容，重新打开读出的内容相同
+    static char big[3 * 4096 + 100], back[sizeof(big)];
+    for (size_t i = 0; i < sizeof(big); i++)
+        big[i] = (char)(i * 7);
+    fd = open(TEST_FILE, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (write(fd, big, sizeof(big)) != sizeof(big)) {
+        puts("write to an O_WRONLY file failed");
+        failed = 1;
+    }
+    errno = 0;
+    if (read(fd, back, 1) != -1 || errno != EBADF) {
+        puts("read from an O_WRONLY file should fail with EBADF");
+        failed = 1;
+    }
+    close(fd);
+    fd = open(TEST_FILE, O_RDONLY);
+    if (read(fd, back, sizeof(back)) != sizeof(back) || memcmp(big, back, sizeof(big)) != 0) {
+        puts("file content differs from what was written");
+        failed = 1;
+    }
+    errno = 0;
+    if (write(fd, "x", 1) != -1 || errno != EBADF) {
+        puts("write to an O_RDONLY file should fail with EBADF");
+        failed = 1;
+    }
+    close(fd);
+
+    // 缓冲区中途失效时返回失效之前写入的字节数，一开始就失效时返回 EFAULT
+    char *page = mmap(NULL, 2 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    munmap(page + 4096, 4096);
+    memset(page, 'p', 4096);
+    fd = open(TEST_FILE, O_WRONLY | O_TRUNC);
+    if (write(fd, page + 4000, 200) != 96) {
+        puts("write from a partly unmapped buffer should stop at the hole");
+        failed = 1;
+    }
+    errno = 0;
+    if (write(fd, page + 4096, 10) != -1 || errno != EFAULT) {
+        puts("write from an unmapped buffer should fail with EFAULT");
+        failed = 1;
+    }
+    close(fd);
+    munmap(page, 4096);
+    unlink(TEST_FILE);
+
+    // 写入不存在的 fd 返回 EBADF
+    if (write(100, "x", 1) != -1 || errno != EBADF) {
+        puts("write to a closed fd should fail with EBADF");
+        failed = 1;
+    }
+
+    puts(failed ? "write_redirect test failed" : "write_redirect test passed");
+    return failed;
+}
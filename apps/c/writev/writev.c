@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#include "check.h"
+
+#ifndef IOV_MAX
+#define IOV_MAX 1024
+#endif
+
+int main(void)
+{
+    // 不是合法 UTF-8 的字节与 '\0' 也按原样写入
+    char a[] = {'h', 'i', 0x00, (char)0xff};
+    char b[] = {(char)0xc3, (char)0x28, '\n'};
+    struct iovec iov[4] = {
+        {.iov_base = a, .iov_len = sizeof(a)},
+        {.iov_base = NULL, .iov_len = 0},
+        {.iov_base = b, .iov_len = 0},
+        {.iov_base = b, .iov_len = sizeof(b)},
+    };
+
+    int fd = open("writev_test.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    check(fd >= 0, "open");
+    check(writev(fd, iov, 4) == sizeof(a) + sizeof(b), "writev returns the total length");
+    char buf[16] = {0};
+    check(pread(fd, buf, sizeof(buf), 0) == sizeof(a) + sizeof(b), "file length");
+    check(memcmp(buf, a, sizeof(a)) == 0 && memcmp(buf + sizeof(a), b, sizeof(b)) == 0,
+          "file content matches the iovecs");
+
+    // base 为空而 len 不为 0 时在写入任何数据之前返回 EFAULT
+    struct iovec bad[2] = {
+        {.iov_base = a, .iov_len = sizeof(a)},
+        {.iov_base = NULL, .iov_len = 4},
+    };
+    errno = 0;
+    check(writev(fd, bad, 2) == -1 && errno == EFAULT, "null base with non-zero length: EFAULT");
+    check(lseek(fd, 0, SEEK_END) == sizeof(a) + sizeof(b), "nothing written on EFAULT");
+    errno = 0;
+    check(writev(fd, iov, 0) == 0, "writev with no iovecs");
+    close(fd);
+    unlink("writev_test.txt");
+
+    // 管道与 readv
+    int pipefd[2];
+    check(pipe(pipefd) == 0, "pipe");
+    check(writev(pipefd[1], iov, 4) == sizeof(a) + sizeof(b), "writev to a pipe");
+    char ra[4], rb[3];
+    struct iovec riov[2] = {
+        {.iov_base = ra, .iov_len = sizeof(ra)},
+        {.iov_base = rb, .iov_len = sizeof(rb)},
+    };
+    check(readv(pipefd[0], riov, 2) == sizeof(ra) + sizeof(rb), "readv from a pipe");
+    check(memcmp(ra, a, sizeof(a)) == 0 && memcmp(rb, b, sizeof(b)) == 0, "pipe content matches");
+
+    // 管道中的数据不足时 readv 返回已读取的部分，而不是阻塞在下一个 iovec 上
+    check(write(pipefd[1], a, sizeof(a)) == sizeof(a), "write to a pipe");
+    char big[8], rest[8];
+    struct iovec short_iov[2] = {
+        {.iov_base = big, .iov_len = sizeof(big)},
+        {.iov_base = rest, .iov_len = sizeof(rest)},
+    };
+    check(readv(pipefd[0], short_iov, 2) == sizeof(a), "short readv");
+
+    close(pipefd[0]);
+    close(pipefd[1]);
+
+    // iovec 数量超过 IOV_MAX
+    static struct iovec many[IOV_MAX + 1];
+    errno = 0;
+    check(writev(1, many, IOV_MAX + 1) == -1 && errno == EINVAL, "iovcnt above IOV_MAX: EINVAL");
+
+    // 标准输出
+    struct iovec out[2] = {
+        {.iov_base = "writev to ", .iov_len = 10},
+        {.iov_base = "stdout\n", .iov_len = 7},
+    };
+    check(writev(1, out, 2) == 17, "writev to stdout");
+
+    puts(failed ? "writev test failed" : "writev test passed");
+    return failed;
+}
@@ -57,6 +57,7 @@ pub const SDCARD_TESTCASES: &[&str] = &[
     // "busybox echo context switch overhead",
     // "lmbench_all lat_ctx -P 1 -s 32 2 4 8 16 24 32 64 96",
     "busybox sh libctest_testcode.sh",
+    // "busybox sh ctest_testcode.sh",
     // "busybox sh lua_testcode.sh",
     // "libc-bench",
     // "busybox sh ./netperf_testcode.sh",
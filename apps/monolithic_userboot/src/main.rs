@@ -15,8 +15,8 @@ fn main() {
     }
     #[cfg(not(feature = "batch"))]
     {
-        let testcase = "busybox sh";
-        axstarry::run_testcase(testcase);
+        // init 程序由内核命令行的 init= 决定
+        axstarry::run_init();
         axstarry::println(format!("System halted with exit code {}", 0).as_str());
     }
 }
@@ -68,6 +68,9 @@ while [ -n "$1" ]; do
 		libc-static)
 			FILE=libc-static
 			;;
+		ctest)
+			FILE=ctest
+			;;
 		*)
 			display_help
 			;;
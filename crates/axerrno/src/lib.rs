@@ -89,6 +89,8 @@ pub enum AxError {
     Interrupted,
     /// Syscall timed out
     Timeout,
+    /// The file would grow beyond the maximum size it supports.
+    FileTooLarge,
 }
 
 /// A specialized [`Result`] type with [`AxError`] as the error type.
@@ -216,6 +218,7 @@ impl AxError {
             WriteZero => "Write zero",
             Interrupted => "Interrupted",
             Timeout => "Timeout",
+            FileTooLarge => "File too large",
         }
     }
 
@@ -269,6 +272,7 @@ impl From<AxError> for LinuxError {
             WouldBlock => LinuxError::EAGAIN,
             Interrupted => LinuxError::EINTR,
             Timeout => LinuxError::ETIME,
+            FileTooLarge => LinuxError::EFBIG,
         }
     }
 }
@@ -291,13 +295,13 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 24);
-        assert_eq!(max_code, AxError::Timeout.code());
+        assert_eq!(max_code, 25);
+        assert_eq!(max_code, AxError::FileTooLarge.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
         assert_eq!(Ok(AxError::AddrInUse), AxError::try_from(1));
         assert_eq!(Ok(AxError::AlreadyExists), AxError::try_from(2));
-        assert_eq!(Ok(AxError::Timeout), AxError::try_from(max_code));
+        assert_eq!(Ok(AxError::FileTooLarge), AxError::try_from(max_code));
         assert_eq!(Err(max_code + 1), AxError::try_from(max_code + 1));
         assert_eq!(Err(0), AxError::try_from(0));
         assert_eq!(Err(-1), AxError::try_from(-1));
@@ -2,7 +2,7 @@ use alloc::collections::BTreeMap;
 use alloc::sync::{Arc, Weak};
 use alloc::{string::String, vec::Vec};
 
-use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
 use axfs_vfs::{VfsError, VfsResult};
 use spin::RwLock;
 
@@ -16,6 +16,7 @@ pub struct DirNode {
     this: Weak<DirNode>,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<String, VfsNodeRef>>,
+    perm: RwLock<VfsNodePerm>,
 }
 
 impl DirNode {
@@ -24,6 +25,7 @@ impl DirNode {
             this: this.clone(),
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
             children: RwLock::new(BTreeMap::new()),
+            perm: RwLock::new(VfsNodePerm::default_dir()),
         })
     }
 
@@ -79,7 +81,17 @@ impl DirNode {
 
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        Ok(VfsNodeAttr::new(
+            *self.perm.read(),
+            VfsNodeType::Dir,
+            4096,
+            0,
+        ))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        *self.perm.write() = perm;
+        Ok(())
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
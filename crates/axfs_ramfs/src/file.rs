@@ -1,5 +1,7 @@
 use alloc::vec::Vec;
-use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{
+    impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult,
+};
 use spin::RwLock;
 
 /// The file node in the RAM filesystem.
@@ -7,6 +9,7 @@ use spin::RwLock;
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct FileNode {
     content: RwLock<Vec<u8>>,
+    perm: RwLock<VfsNodePerm>,
 }
 
 impl FileNode {
@@ -14,13 +17,25 @@ impl FileNode {
     pub const fn new() -> Self {
         Self {
             content: RwLock::new(Vec::new()),
+            perm: RwLock::new(VfsNodePerm::default_file()),
         }
     }
 }
 
 impl VfsNodeOps for FileNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_file(self.content.read().len() as _, 0))
+        let size = self.content.read().len() as _;
+        Ok(VfsNodeAttr::new(
+            *self.perm.read(),
+            VfsNodeType::File,
+            size,
+            0,
+        ))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        *self.perm.write() = perm;
+        Ok(())
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+use axfs_vfs::{VfsError, VfsNodePerm, VfsNodeType, VfsResult};
 
 use crate::*;
 
@@ -134,3 +134,33 @@ fn test_ramfs() {
     assert_eq!(root.remove("./foo"), Ok(()));
     assert!(ramfs.root_dir_node().get_entries().is_empty());
 }
+
+#[test]
+fn test_ramfs_perm() {
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    root.create("f1", VfsNodeType::File).unwrap();
+    root.create("foo", VfsNodeType::Dir).unwrap();
+
+    let file = root.clone().lookup("f1").unwrap();
+    assert_eq!(file.get_attr().unwrap().perm(), VfsNodePerm::default_file());
+    let exec = VfsNodePerm::from_bits_truncate(0o755);
+    file.set_perm(exec).unwrap();
+    assert_eq!(file.get_attr().unwrap().perm(), exec);
+    assert_eq!(
+        root.clone()
+            .lookup("f1")
+            .unwrap()
+            .get_attr()
+            .unwrap()
+            .perm(),
+        exec
+    );
+
+    let dir = root.lookup("foo").unwrap();
+    assert_eq!(dir.get_attr().unwrap().perm(), VfsNodePerm::default_dir());
+    let perm = VfsNodePerm::from_bits_truncate(0o700);
+    dir.set_perm(perm).unwrap();
+    assert_eq!(dir.get_attr().unwrap().perm(), perm);
+    assert!(dir.get_attr().unwrap().is_dir());
+}
@@ -22,6 +22,7 @@
 //! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
 //! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
 //! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | both |
+//! | [`ino()`](VfsNodeOps::ino) | Get the inode number of the node | both |
 //! | [`read_at()`](VfsNodeOps::read_at) | Read data from the file | file |
 //! | [`write_at()`](VfsNodeOps::write_at) | Write data to the file | file |
 //! | [`fsync()`](VfsNodeOps::fsync) | Synchronize the file data to disk | file |
@@ -100,6 +101,20 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Set the permission of the node.
+    fn set_perm(&self, _perm: VfsNodePerm) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Get the inode number of the node.
+    ///
+    /// The default uses the address of the node, which identifies it as long
+    /// as it stays alive. Filesystems that create a new node object on every
+    /// lookup must override it.
+    fn ino(&self) -> u64 {
+        self as *const Self as *const () as usize as u64
+    }
+
     // file operations:
 
     /// Read data from the file at the given offset.
@@ -11,7 +11,6 @@ const AT_PHNUM: u8 = 5;
 const AT_PAGESZ: u8 = 6;
 #[allow(unused)]
 const AT_BASE: u8 = 7;
-#[allow(unused)]
 const AT_ENTRY: u8 = 9;
 const AT_RANDOM: u8 = 25;
 
@@ -53,6 +52,10 @@ pub fn get_auxv_vector(
     );
     map.insert(AT_PHENT, elf.header.pt2.ph_entry_size() as usize);
     map.insert(AT_PHNUM, elf.header.pt2.ph_count() as usize);
+    map.insert(
+        AT_ENTRY,
+        crate::get_elf_entry(elf, elf_base_addr).as_usize(),
+    );
     map.insert(AT_RANDOM, 0);
     map.insert(AT_PAGESZ, PAGE_SIZE_4K);
     map
@@ -64,6 +67,7 @@ pub fn get_auxv_vector(
 /// * `args` - The arguments of the app
 /// * `envs` - The environment variables of the app
 /// * `auxv` - The auxv vector of the app
+/// * `random` - The 16 random bytes that `AT_RANDOM` points to
 /// * `stack_top` - The top address of the stack
 /// * `stack_size` - The size of the stack.
 ///
@@ -84,13 +88,14 @@ pub fn get_app_stack_region(
     args: Vec<String>,
     envs: &[String],
     auxv: BTreeMap<u8, usize>,
+    random: &[u8; 16],
     stack_top: VirtAddr,
     stack_size: usize,
 ) -> (Vec<u8>, usize) {
     let ustack_top = stack_top;
     let ustack_bottom = ustack_top + stack_size;
     // The stack variable is actually the information carried by the stack
-    let stack = init_stack(args, envs, auxv, ustack_bottom.into());
+    let stack = init_stack(args, envs, auxv, random, ustack_bottom.into());
     let ustack_bottom = stack.get_sp();
     let mut data = [0_u8].repeat(stack_size - stack.get_len());
     data.extend(stack.get_data_front_ref());
@@ -26,6 +26,14 @@ const fn p1_index(vaddr: VirtAddr) -> usize {
     (vaddr.as_usize() >> 12) & (ENTRY_COUNT - 1)
 }
 
+const fn root_index<M: PagingMetaData>(vaddr: VirtAddr) -> usize {
+    if M::LEVELS == 3 {
+        p3_index(vaddr)
+    } else {
+        p4_index(vaddr)
+    }
+}
+
 /// A generic page table struct for 64-bit platform.
 ///
 /// It also tracks all intermediate level tables. They will be deallocated
@@ -110,6 +118,55 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         Ok((paddr, size))
     }
 
+    /// Maps the 2M page starts with `vaddr` to the 2M frame starts with
+    /// `target`, replacing whatever the entry held before.
+    ///
+    /// If the region was mapped with 4K pages, their page table is freed, so
+    /// the caller must hold the frames of these pages itself, and flush the TLB
+    /// of the region afterwards.
+    ///
+    /// Returns [`Err(PagingError::NotAligned)`](PagingError::NotAligned) if
+    /// the addresses are not aligned to 2M.
+    pub fn map_huge_overwrite(
+        &mut self,
+        vaddr: VirtAddr,
+        target: PhysAddr,
+        flags: MappingFlags,
+    ) -> PagingResult {
+        if !vaddr.is_aligned(PageSize::Size2M) || !target.is_aligned(PageSize::Size2M) {
+            return Err(PagingError::NotAligned);
+        }
+        let entry = self.get_entry_mut_or_create(vaddr, PageSize::Size2M)?;
+        let old_table = (entry.is_present() && !entry.is_huge()).then(|| entry.paddr());
+        *entry = GenericPTE::new_page(target, flags, true);
+        if let Some(table) = old_table {
+            self.intrm_tables.retain(|&paddr| paddr != table);
+            IF::dealloc_frame(table);
+        }
+        Ok(())
+    }
+
+    /// Splits the 2M page that contains `vaddr` into 512 4K pages with the
+    /// same target frames and flags.
+    ///
+    /// Returns `false` if `vaddr` is not mapped by a 2M page. You need to
+    /// flush the TLB of the region after a split.
+    pub fn split_huge(&mut self, vaddr: VirtAddr) -> PagingResult<bool> {
+        let (entry, size) = self.get_entry_mut(vaddr)?;
+        if size != PageSize::Size2M || !entry.is_present() {
+            return Ok(false);
+        }
+        let paddr = entry.paddr();
+        let flags = entry.flags();
+        let table_paddr = Self::alloc_table()?;
+        for (i, pte) in self.table_of_mut(table_paddr).iter_mut().enumerate() {
+            *pte = GenericPTE::new_page(paddr + i * PAGE_SIZE_4K, flags, false);
+        }
+        *entry = GenericPTE::new_table(table_paddr);
+        self.intrm_tables.push(table_paddr);
+        Ok(true)
+    }
+
     /// Maps a fault page starts with `vaddr`.
     pub fn map_fault(
         &mut self,
@@ -312,6 +369,44 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         }
         Ok(())
     }
+    /// Copies the root-level entries covering `[vaddr, vaddr + size)` into
+    /// the root table at `root_paddr`, so that both page tables share the same
+    /// lower-level tables.
+    ///
+    /// Only the entries that are still unused in the target are copied, so it
+    /// can be called again to pick up root-level entries populated later.
+    /// The shared tables are still owned by `self`, and will not be
+    /// deallocated when the target page table is dropped.
+    ///
+    /// Returns the number of entries copied.
+    ///
+    /// # Safety
+    ///
+    /// `root_paddr` must be the root table of a page table with the same
+    /// format, and `self` must outlive it.
+    pub unsafe fn share_root_entries(
+        &self,
+        root_paddr: PhysAddr,
+        vaddr: VirtAddr,
+        size: usize,
+    ) -> usize {
+        if size == 0 {
+            return 0;
+        }
+        let first = root_index::<M>(vaddr);
+        let last = root_index::<M>(vaddr + (size - 1));
+        let src = self.table_of(self.root_paddr());
+        let dst = self.table_of_mut(root_paddr);
+        let mut count = 0;
+        for idx in first..=last {
+            if dst[idx].is_unused() && !src[idx].is_unused() {
+                dst[idx] = src[idx];
+                count += 1;
+            }
+        }
+        count
+    }
+
     /// Walk the page table recursively.
     ///
     /// When reaching the leaf page table, call `func` on the current page table
@@ -473,3 +568,124 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> Drop for PageTable64<M, P
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::alloc::{alloc_zeroed, dealloc, Layout};
+
+    use memory_addr::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
+
+    use crate::riscv::Sv39PageTable;
+    use crate::{MappingFlags, PageSize, PagingIf};
+
+    struct TestPagingIf;
+
+    fn frame_layout() -> Layout {
+        Layout::from_size_align(PAGE_SIZE_4K, PAGE_SIZE_4K).unwrap()
+    }
+
+    impl PagingIf for TestPagingIf {
+        fn alloc_frame() -> Option<PhysAddr> {
+            let ptr = unsafe { alloc_zeroed(frame_layout()) };
+            (!ptr.is_null()).then(|| PhysAddr::from(ptr as usize))
+        }
+
+        fn dealloc_frame(paddr: PhysAddr) {
+            unsafe { dealloc(paddr.as_usize() as *mut u8, frame_layout()) }
+        }
+
+        fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
+            VirtAddr::from(paddr.as_usize())
+        }
+    }
+
+    const KERNEL_SPACE_START: usize = 0xffff_ffc0_0000_0000;
+    const KERNEL_SPACE_SIZE: usize = 1 << 38;
+
+    #[test]
+    fn test_share_root_entries() {
+        let flags = MappingFlags::READ | MappingFlags::WRITE;
+        let mut kernel = Sv39PageTable::<TestPagingIf>::try_new().unwrap();
+        let base = VirtAddr::from(0xffff_ffc0_8000_0000);
+        kernel
+            .map(base, 0x8000_0000.into(), PageSize::Size4K, flags)
+            .unwrap();
+
+        let user = Sv39PageTable::<TestPagingIf>::try_new().unwrap();
+        let shared = unsafe {
+            kernel.share_root_entries(
+                user.root_paddr(),
+                KERNEL_SPACE_START.into(),
+                KERNEL_SPACE_SIZE,
+            )
+        };
+        assert_eq!(shared, 1);
+        assert_eq!(user.query(base).unwrap().0, PhysAddr::from(0x8000_0000));
+
+        // 已共享的顶级页表项下新增的映射无需同步即可见
+        kernel
+            .map(
+                base + PAGE_SIZE_4K,
+                0x8000_1000.into(),
+                PageSize::Size4K,
+                flags,
+            )
+            .unwrap();
+        assert!(user.query(base + PAGE_SIZE_4K).is_ok());
+
+        // 新增的顶级页表项需要同步后才可见
+        let far = VirtAddr::from(0xffff_ffd0_0000_0000);
+        kernel
+            .map(far, 0x9000_0000.into(), PageSize::Size4K, flags)
+            .unwrap();
+        assert!(user.query(far).is_err());
+        let shared = unsafe {
+            kernel.share_root_entries(
+                user.root_paddr(),
+                KERNEL_SPACE_START.into(),
+                KERNEL_SPACE_SIZE,
+            )
+        };
+        assert_eq!(shared, 1);
+        assert_eq!(user.query(far).unwrap().0, PhysAddr::from(0x9000_0000));
+
+        // 用户页表释放时不会释放共享的下级页表
+        drop(user);
+        assert!(kernel.query(far).is_ok());
+    }
+
+    #[test]
+    fn test_collapse_to_huge_page() {
+        let flags = MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER;
+        let mut pt = Sv39PageTable::<TestPagingIf>::try_new().unwrap();
+        let base = VirtAddr::from(0x4000_0000);
+        let huge_size = PageSize::Size2M as usize;
+        for offset in (0..huge_size).step_by(PAGE_SIZE_4K) {
+            pt.map(
+                base + offset,
+                PhysAddr::from(0x8000_0000 + offset * 2),
+                PageSize::Size4K,
+                flags,
+            )
+            .unwrap();
+        }
+        let tables = pt.intrm_tables.len();
+        assert_eq!(pt.query(base).unwrap().2, PageSize::Size4K);
+
+        // 合并后整个范围由一个 2M 页表项映射，原来的末级页表被释放
+        pt.map_huge_overwrite(base, 0x8040_0000.into(), flags).unwrap();
+        assert_eq!(pt.intrm_tables.len(), tables - 1);
+        for offset in [0, PAGE_SIZE_4K, huge_size - PAGE_SIZE_4K] {
+            let (paddr, _, size) = pt.query(base + offset).unwrap();
+            assert_eq!(size, PageSize::Size2M);
+            assert_eq!(paddr, PhysAddr::from(0x8040_0000 + offset));
+        }
+
+        // 未对齐的地址不能映射为大页
+        assert!(pt
+            .map_huge_overwrite(base + PAGE_SIZE_4K, 0x8040_0000.into(), flags)
+            .is_err());
+    }
+}
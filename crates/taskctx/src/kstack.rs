@@ -1,26 +1,67 @@
 extern crate alloc;
-use core::{alloc::Layout, ptr::NonNull};
+use core::{
+    alloc::Layout,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use memory_addr::VirtAddr;
+use memory_addr::{VirtAddr, PAGE_SIZE_4K};
 
+/// 写在内核栈最低地址处的标记值，被改写说明栈已经溢出
+const STACK_CANARY: u64 = 0x5a5a_c0de_dead_beef;
+
+/// 任务的内核栈
+///
+/// 栈总是按页对齐，栈底（最低地址）写有 [`STACK_CANARY`]，调度器在每次切换任务时检查它，
+/// 即使没有保护页也能发现大部分栈溢出。开启 `kstack_zero` 时分配的栈会被清零。
 pub(crate) struct TaskStack {
     ptr: NonNull<u8>,
     layout: Layout,
+    /// 栈是否已经被释放
+    freed: AtomicBool,
 }
 
 impl TaskStack {
     pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
+        let layout = Layout::from_size_align(size, PAGE_SIZE_4K).unwrap();
+        let ptr = unsafe {
+            if cfg!(feature = "kstack_zero") {
+                alloc::alloc::alloc_zeroed(layout)
+            } else {
+                alloc::alloc::alloc(layout)
+            }
+        };
+        let stack = Self {
+            ptr: NonNull::new(ptr).unwrap(),
             layout,
-        }
+            freed: AtomicBool::new(false),
+        };
+        unsafe { stack.canary_ptr().write(STACK_CANARY) };
+        stack
     }
 
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
 
+    fn canary_ptr(&self) -> *mut u64 {
+        self.ptr.as_ptr() as *mut u64
+    }
+
+    /// 栈底的标记值是否完好，为 false 说明栈发生过溢出
+    pub fn canary_intact(&self) -> bool {
+        unsafe { self.canary_ptr().read_volatile() == STACK_CANARY }
+    }
+
+    /// 释放栈的内存，重复调用时不会再次释放
+    fn release(&self) {
+        let already_freed = self.freed.swap(true, Ordering::AcqRel);
+        debug_assert!(!already_freed, "kernel stack freed twice");
+        if !already_freed {
+            unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+
     // #[cfg(feature = "monolithic")]
     // /// 获取内核栈第一个压入的trap上下文，防止出现内核trap嵌套
     // pub fn get_first_trap_frame(&self) -> *mut TrapFrame {
@@ -30,6 +71,40 @@ impl TaskStack {
 
 impl Drop for TaskStack {
     fn drop(&mut self) {
-        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaskStack;
+    use memory_addr::PAGE_SIZE_4K;
+
+    #[test]
+    fn test_kstack_aligned() {
+        for pages in 1..4 {
+            let stack = TaskStack::alloc(pages * PAGE_SIZE_4K);
+            assert_eq!(stack.ptr.as_ptr() as usize % PAGE_SIZE_4K, 0);
+            assert_eq!(stack.top().as_usize() % PAGE_SIZE_4K, 0);
+        }
+    }
+
+    #[test]
+    fn test_kstack_canary() {
+        let stack = TaskStack::alloc(PAGE_SIZE_4K);
+        assert!(stack.canary_intact());
+        // 直接改写栈底的标记值，模拟栈溢出越过了栈底
+        unsafe { stack.ptr.as_ptr().write_bytes(0, 16) };
+        assert!(!stack.canary_intact());
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "kernel stack freed twice"))]
+    fn test_kstack_release_once() {
+        let stack = TaskStack::alloc(PAGE_SIZE_4K);
+        stack.release();
+        assert!(stack.freed.load(core::sync::atomic::Ordering::Acquire));
+        // drop 时不会再次释放内存，debug 构建下会触发断言
+        drop(stack);
     }
 }
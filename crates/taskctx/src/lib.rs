@@ -8,6 +8,8 @@
 //!
 //! - `stat`: Task statistics.
 //!
+//! - `sched_stat`: Scheduling statistics, only with the `sched_trace` feature.
+//!
 //! - `preempt_disable_count`: Preemption disable counter. Only when the counter is zero, the
 //! task can be preempted. It can be used to implement preemption protection lock.
 #![no_std]
@@ -25,6 +27,11 @@ mod tls;
 mod stat;
 pub use stat::*;
 
+#[cfg(feature = "sched_trace")]
+mod sched_stat;
+#[cfg(feature = "sched_trace")]
+pub use sched_stat::SchedStat;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "multitask")] {
         mod kstack;
@@ -0,0 +1,120 @@
+//! 任务的调度统计，仅在启用 `sched_trace` 时存在
+//!
+//! 运行队列在任务进入就绪队列、被选中运行以及被切换出去时更新这些计数，
+//! 由此得到任务处于就绪状态却没有运行的总时间，以及自愿与非自愿的上下文切换次数。
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+/// 任务不在就绪队列中时 `ready_since` 的取值
+const NOT_READY: u64 = u64::MAX;
+
+/// 任务尚未运行过时 `last_cpu` 的取值
+const NO_CPU: usize = usize::MAX;
+
+/// 转换为原子变量中保存的纳秒数，超出 u64 时饱和
+fn as_nanos(time: Duration) -> u64 {
+    u64::try_from(time.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// 任务的调度统计
+pub struct SchedStat {
+    /// 最近一次进入就绪队列的时间戳，单位为纳秒
+    ready_since: AtomicU64,
+    /// 处于就绪状态但没有运行的总时间，单位为纳秒
+    wait_ns: AtomicU64,
+    /// 单次就绪等待的最长时间，单位为纳秒
+    max_wait_ns: AtomicU64,
+    /// 从就绪队列中被选中运行的次数
+    run_count: AtomicUsize,
+    /// 自愿切换的次数，即阻塞或退出时让出 CPU
+    nvcsw: AtomicUsize,
+    /// 非自愿切换的次数，即被抢占或仍可运行时主动 yield
+    nivcsw: AtomicUsize,
+    /// 上一次运行所在的 CPU
+    last_cpu: AtomicUsize,
+}
+
+impl Default for SchedStat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchedStat {
+    /// 所有计数均为 0 的统计
+    pub const fn new() -> Self {
+        Self {
+            ready_since: AtomicU64::new(NOT_READY),
+            wait_ns: AtomicU64::new(0),
+            max_wait_ns: AtomicU64::new(0),
+            run_count: AtomicUsize::new(0),
+            nvcsw: AtomicUsize::new(0),
+            nivcsw: AtomicUsize::new(0),
+            last_cpu: AtomicUsize::new(NO_CPU),
+        }
+    }
+
+    /// 任务在 `now` 时进入就绪队列
+    pub fn mark_ready(&self, now: Duration) {
+        self.ready_since.store(as_nanos(now), Ordering::Release);
+    }
+
+    /// 任务在 `now` 时被选中运行，返回这次在就绪队列中等待的时间
+    ///
+    /// 任务不是从就绪队列中选出的（如 idle 任务）时返回 0
+    pub fn mark_running(&self, now: Duration) -> Duration {
+        let since = self.ready_since.swap(NOT_READY, Ordering::AcqRel);
+        if since == NOT_READY {
+            return Duration::ZERO;
+        }
+        let waited = as_nanos(now).saturating_sub(since);
+        self.wait_ns.fetch_add(waited, Ordering::AcqRel);
+        self.max_wait_ns.fetch_max(waited, Ordering::AcqRel);
+        self.run_count.fetch_add(1, Ordering::AcqRel);
+        Duration::from_nanos(waited)
+    }
+
+    /// 任务被切换出去，`voluntary` 表示是否是自愿切换
+    pub fn count_switch(&self, voluntary: bool) {
+        if voluntary {
+            self.nvcsw.fetch_add(1, Ordering::AcqRel);
+        } else {
+            self.nivcsw.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// 记录任务在 `cpu` 上运行，若之前运行在另一个 CPU 上则返回该 CPU
+    pub fn migrate_to(&self, cpu: usize) -> Option<usize> {
+        match self.last_cpu.swap(cpu, Ordering::AcqRel) {
+            NO_CPU => None,
+            last_cpu if last_cpu == cpu => None,
+            last_cpu => Some(last_cpu),
+        }
+    }
+
+    /// 处于就绪状态但没有运行的总时间
+    pub fn wait_time(&self) -> Duration {
+        Duration::from_nanos(self.wait_ns.load(Ordering::Acquire))
+    }
+
+    /// 单次就绪等待的最长时间
+    pub fn max_wait(&self) -> Duration {
+        Duration::from_nanos(self.max_wait_ns.load(Ordering::Acquire))
+    }
+
+    /// 从就绪队列中被选中运行的次数
+    pub fn run_count(&self) -> usize {
+        self.run_count.load(Ordering::Acquire)
+    }
+
+    /// 自愿上下文切换的次数
+    pub fn voluntary_switches(&self) -> usize {
+        self.nvcsw.load(Ordering::Acquire)
+    }
+
+    /// 非自愿上下文切换的次数
+    pub fn involuntary_switches(&self) -> usize {
+        self.nivcsw.load(Ordering::Acquire)
+    }
+}
@@ -1,5 +1,7 @@
 //! 负责任务时间统计的实现
 
+use core::time::Duration;
+
 numeric_enum_macro::numeric_enum! {
     #[repr(i32)]
     #[allow(non_camel_case_types)]
@@ -28,24 +30,24 @@ impl From<usize> for TimerType {
 
 /// 任务时间统计结构
 pub struct TimeStat {
-    /// 用户态经过的时间，单位为纳秒
-    utime_ns: usize,
-    /// 内核态经过的时间，单位为纳秒
-    stime_ns: usize,
+    /// 用户态经过的时间
+    utime: Duration,
+    /// 内核态经过的时间
+    stime: Duration,
     /// 进入用户态时标记当前时间戳，用于统计用户态时间
-    user_timestamp: usize,
+    user_timestamp: Duration,
     /// 进入内核态时标记当前时间戳，用于统计内核态时间
-    kernel_timestamp: usize,
+    kernel_timestamp: Duration,
     /// 计时器类型
     timer_type: TimerType,
     /// 设置下一次触发计时器的区间
-    /// 当 timer_remained_us 归零时，**如果 timer_interval_us 非零**，则将其重置为 timer_interval_us 的值；
+    /// 当 timer_remained 归零时，**如果 timer_interval 非零**，则将其重置为 timer_interval 的值；
     /// 否则，则这个计时器不再触发
-    timer_interval_ns: usize,
+    timer_interval: Duration,
     /// 当前轮次下计数器剩余的时间
     ///
     /// 根据timer_type的种类来进行计算，当归零的时候触发信号，同时进行更新
-    timer_remained_ns: usize,
+    timer_remained: Duration,
 
     /// 是否需要发送计时器信号
     pending_timer_signal: bool,
@@ -60,83 +62,76 @@ impl TimeStat {
     /// 新建一个进程时需要初始化时间
     pub fn new() -> Self {
         Self {
-            utime_ns: 0,
-            stime_ns: 0,
-            user_timestamp: 0,
+            utime: Duration::ZERO,
+            stime: Duration::ZERO,
+            user_timestamp: Duration::ZERO,
             // 创建新任务时一般都在内核内，所以可以认为进入内核的时间就是当前时间
-            kernel_timestamp: 0,
+            kernel_timestamp: Duration::ZERO,
             timer_type: TimerType::NONE,
-            timer_interval_ns: 0,
-            timer_remained_ns: 0,
+            timer_interval: Duration::ZERO,
+            timer_remained: Duration::ZERO,
             pending_timer_signal: false,
         }
     }
 
     /// To get the time statistics
     ///
-    /// The format is (user time, kernel time) in nanoseconds
-    pub fn output(&self) -> (usize, usize) {
-        (self.utime_ns, self.stime_ns)
+    /// The format is (user time, kernel time)
+    pub fn output(&self) -> (Duration, Duration) {
+        (self.utime, self.stime)
     }
 
     /// 复位时间统计器
-    pub fn reset(&mut self, current_timestamp: usize) {
-        self.utime_ns = 0;
-        self.stime_ns = 0;
-        self.user_timestamp = 0;
-        self.kernel_timestamp = current_timestamp;
+    pub fn reset(&mut self, now: Duration) {
+        self.utime = Duration::ZERO;
+        self.stime = Duration::ZERO;
+        self.user_timestamp = Duration::ZERO;
+        self.kernel_timestamp = now;
     }
     /// 从用户态进入内核态，记录当前时间戳，统计用户态时间
-    pub fn switch_into_kernel_mode(&mut self, tid: isize, current_timestamp: usize) {
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.user_timestamp;
-        self.utime_ns += delta;
-        self.kernel_timestamp = now_time_ns;
+    pub fn switch_into_kernel_mode(&mut self, tid: isize, now: Duration) {
+        let delta = now.saturating_sub(self.user_timestamp);
+        self.utime += delta;
+        self.kernel_timestamp = now;
         if self.timer_type != TimerType::NONE {
             self.update_timer(delta, tid);
         };
     }
     /// 从内核态进入用户态，记录当前时间戳，统计内核态时间
-    pub fn switch_into_user_mode(&mut self, tid: isize, current_timestamp: usize) {
-        // 获取当前时间，单位为纳秒
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.stime_ns += delta;
-        self.user_timestamp = now_time_ns;
+    pub fn switch_into_user_mode(&mut self, tid: isize, now: Duration) {
+        let delta = now.saturating_sub(self.kernel_timestamp);
+        self.stime += delta;
+        self.user_timestamp = now;
         if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
             self.update_timer(delta, tid);
         };
     }
     /// 内核态下，当前任务被切换掉，统计内核态时间
-    pub fn swtich_from_old_task(&mut self, tid: isize, current_timestamp: usize) {
-        // 获取当前时间，单位为纳秒
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
-        self.stime_ns += delta;
+    pub fn swtich_from_old_task(&mut self, tid: isize, now: Duration) {
+        let delta = now.saturating_sub(self.kernel_timestamp);
+        self.stime += delta;
         // 需要更新内核态时间戳
-        self.kernel_timestamp = now_time_ns;
+        self.kernel_timestamp = now;
         if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
             self.update_timer(delta, tid);
         };
     }
     /// 内核态下，切换到当前任务，更新内核态时间戳
-    pub fn switch_to_new_task(&mut self, tid: isize, current_timestamp: usize) {
-        // 获取当前时间，单位为纳秒
-        let now_time_ns = current_timestamp;
-        let delta = now_time_ns - self.kernel_timestamp;
+    pub fn switch_to_new_task(&mut self, tid: isize, now: Duration) {
+        let delta = now.saturating_sub(self.kernel_timestamp);
         // 更新时间戳，方便当该任务被切换时统计内核经过的时间
-        self.kernel_timestamp = now_time_ns;
+        self.kernel_timestamp = now;
         // 注意，对于REAL类型的任务，此时也需要统计经过的时间
         if self.timer_type == TimerType::REAL {
             self.update_timer(delta, tid)
         }
     }
 
-    /// 以微秒形式输出计时器信息
+    /// 输出计时器信息
     ///
     /// (计时器周期，当前计时器剩余时间)
-    pub fn output_timer_as_us(&self) -> (usize, usize) {
-        (self.timer_interval_ns / 1000, self.timer_remained_ns / 1000)
+    pub fn output_timer(&self) -> (Duration, Duration) {
+        (self.timer_interval, self.timer_remained)
     }
 
     /// 设定计时器信息
@@ -144,26 +139,26 @@ impl TimeStat {
     /// 若type不为None则返回成功
     pub fn set_timer(
         &mut self,
-        timer_interval_ns: usize,
-        timer_remained_ns: usize,
+        timer_interval: Duration,
+        timer_remained: Duration,
         timer_type: usize,
     ) -> bool {
         self.timer_type = timer_type.into();
-        self.timer_interval_ns = timer_interval_ns;
-        self.timer_remained_ns = timer_remained_ns;
+        self.timer_interval = timer_interval;
+        self.timer_remained = timer_remained;
         self.pending_timer_signal = false;
         self.timer_type != TimerType::NONE
     }
 
     /// 更新计时器，同时判断是否要发出信号
-    pub fn update_timer(&mut self, delta: usize, _tid: isize) {
-        if self.timer_remained_ns == 0 {
+    pub fn update_timer(&mut self, delta: Duration, _tid: isize) {
+        if self.timer_remained.is_zero() {
             // 计时器已经结束了
             return;
         }
-        if self.timer_remained_ns > delta {
+        if self.timer_remained > delta {
             // 此时计时器还没有结束，直接更新其内容
-            self.timer_remained_ns -= delta;
+            self.timer_remained -= delta;
             return;
         }
         // 此时计时器已经结束了，需要准备发出信号
@@ -181,7 +176,7 @@ impl TimeStat {
         if self.pending_timer_signal {
             self.pending_timer_signal = false;
             // 重置计时器
-            self.timer_remained_ns = self.timer_interval_ns;
+            self.timer_remained = self.timer_interval;
             match self.timer_type {
                 TimerType::REAL => Some(14),
                 TimerType::VIRTUAL => Some(26),
@@ -1,6 +1,8 @@
 #[cfg(feature = "tls")]
 use crate::tls::TlsArea;
 
+#[cfg(feature = "sched_trace")]
+use crate::SchedStat;
 use crate::{arch::TaskContext, TaskStack, TimeStat};
 extern crate alloc;
 use alloc::{boxed::Box, string::String};
@@ -10,6 +12,7 @@ use core::{
     cell::UnsafeCell,
     fmt,
     sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    time::Duration,
 };
 use memory_addr::{align_up_4k, VirtAddr};
 
@@ -18,6 +21,19 @@ use memory_addr::{align_up_4k, VirtAddr};
 pub struct TaskId(u64);
 
 static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[cfg(feature = "leak_check")]
+/// 当前尚未被释放的 TaskInner 数量，用于在测试中检查任务引用计数是否泄漏
+static LIVE_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "leak_check")]
+/// 获取当前存活的任务数量
+///
+/// 在 fork/exit 压力测试结束并等待回收完成后，该值应当回到测试开始前的值
+pub fn live_task_count() -> usize {
+    LIVE_TASKS.load(Ordering::Acquire)
+}
+
 impl TaskId {
     /// Create a new task ID.
     pub fn new() -> Self {
@@ -65,6 +81,46 @@ impl From<u8> for TaskState {
     }
 }
 
+/// The kind of sleep when a task is blocked.
+///
+/// It decides whether a signal can abort the sleep.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SleepKind {
+    /// 可以被任意信号唤醒，如管道读、wait4、futex
+    Interruptible = 0,
+    /// 只能被 SIGKILL 唤醒
+    Killable = 1,
+    /// 不会被信号唤醒，用于必须等待完成的短暂等待，如 I/O 完成
+    Uninterruptible = 2,
+}
+
+impl From<u8> for SleepKind {
+    #[inline]
+    fn from(kind: u8) -> Self {
+        match kind {
+            0 => Self::Interruptible,
+            1 => Self::Killable,
+            2 => Self::Uninterruptible,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl SleepKind {
+    /// Whether a signal can abort the sleep.
+    ///
+    /// `is_kill` indicates whether the signal is SIGKILL.
+    #[inline]
+    pub fn wakeable_by(self, is_kill: bool) -> bool {
+        match self {
+            Self::Interruptible => true,
+            Self::Killable => is_kill,
+            Self::Uninterruptible => false,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 #[allow(non_camel_case_types)]
 /// The policy of the scheduler
@@ -145,6 +201,15 @@ pub struct TaskInner {
     /// Task state
     state: AtomicU8,
 
+    /// The kind of sleep when the task is blocked
+    sleep_kind: AtomicU8,
+
+    /// Whether the task was woken up by a signal in its last sleep
+    woken_by_signal: AtomicBool,
+
+    /// Whether the task is stopped by a job control signal
+    stopped: AtomicBool,
+
     #[cfg(feature = "preempt")]
     /// Whether the task needs to be rescheduled
     ///
@@ -195,6 +260,10 @@ pub struct TaskInner {
     #[allow(unused)]
     time: UnsafeCell<TimeStat>,
 
+    #[cfg(feature = "sched_trace")]
+    /// 调度统计
+    sched_stat: SchedStat,
+
     #[cfg(feature = "monolithic")]
     /// TODO: to support the sched_setaffinity
     ///
@@ -249,6 +318,14 @@ impl TaskInner {
         None
     }
 
+    /// 内核栈栈底的标记值是否完好，没有内核栈的任务总是返回 true
+    #[inline]
+    pub fn kernel_stack_intact(&self) -> bool {
+        self.kstack
+            .as_ref()
+            .map_or(true, |kstack| kstack.canary_intact())
+    }
+
     #[cfg(feature = "monolithic")]
     /// Create a new task with the given entry function and stack size.
     pub fn new<F>(
@@ -336,45 +413,45 @@ impl TaskInner {
 impl TaskInner {
     #[inline]
     /// update the time information when the task is switched from user mode to kernel mode
-    pub fn time_stat_from_user_to_kernel(&self, current_tick: usize) {
+    pub fn time_stat_from_user_to_kernel(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).switch_into_kernel_mode(self.id.as_u64() as isize, current_tick);
+            (*time).switch_into_kernel_mode(self.id.as_u64() as isize, now);
         }
     }
 
     #[inline]
     /// update the time information when the task is switched from kernel mode to user mode
-    pub fn time_stat_from_kernel_to_user(&self, current_tick: usize) {
+    pub fn time_stat_from_kernel_to_user(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).switch_into_user_mode(self.id.as_u64() as isize, current_tick);
+            (*time).switch_into_user_mode(self.id.as_u64() as isize, now);
         }
     }
 
     #[inline]
     /// update the time information when the task is switched out
-    pub fn time_stat_when_switch_from(&self, current_tick: usize) {
+    pub fn time_stat_when_switch_from(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).swtich_from_old_task(self.id.as_u64() as isize, current_tick);
+            (*time).swtich_from_old_task(self.id.as_u64() as isize, now);
         }
     }
 
     #[inline]
     /// update the time information when the task is ready to be switched in
-    pub fn time_stat_when_switch_to(&self, current_tick: usize) {
+    pub fn time_stat_when_switch_to(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).switch_to_new_task(self.id.as_u64() as isize, current_tick);
+            (*time).switch_to_new_task(self.id.as_u64() as isize, now);
         }
     }
 
     #[inline]
     /// output the time statistics
     ///
-    /// The format is (user time, kernel time) in nanoseconds
-    pub fn time_stat_output(&self) -> (usize, usize) {
+    /// The format is (user time, kernel time)
+    pub fn time_stat_output(&self) -> (Duration, Duration) {
         let time = self.time.get();
         unsafe { (*time).output() }
     }
@@ -382,10 +459,9 @@ impl TaskInner {
     #[inline]
     /// 输出计时器信息
     /// (计时器周期，当前计时器剩余时间)
-    /// 单位为us
-    pub fn timer_output(&self) -> (usize, usize) {
+    pub fn timer_output(&self) -> (Duration, Duration) {
         let time = self.time.get();
-        unsafe { (*time).output_timer_as_us() }
+        unsafe { (*time).output_timer() }
     }
 
     #[inline]
@@ -394,20 +470,20 @@ impl TaskInner {
     /// 若type不为None则返回成功
     pub fn set_timer(
         &self,
-        timer_interval_ns: usize,
-        timer_remained_ns: usize,
+        timer_interval: Duration,
+        timer_remained: Duration,
         timer_type: usize,
     ) -> bool {
         let time = self.time.get();
-        unsafe { (*time).set_timer(timer_interval_ns, timer_remained_ns, timer_type) }
+        unsafe { (*time).set_timer(timer_interval, timer_remained, timer_type) }
     }
 
     #[inline]
     /// 重置统计时间
-    pub fn time_stat_reset(&self, current_tick: usize) {
+    pub fn time_stat_reset(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).reset(current_tick);
+            (*time).reset(now);
         }
     }
 }
@@ -545,6 +621,8 @@ impl TaskInner {
         name: String,
         #[cfg(feature = "tls")] tls_area: (usize, usize),
     ) -> Self {
+        #[cfg(feature = "leak_check")]
+        LIVE_TASKS.fetch_add(1, Ordering::AcqRel);
         Self {
             id,
             name: UnsafeCell::new(name),
@@ -552,6 +630,9 @@ impl TaskInner {
             is_init: false,
             entry: None,
             state: AtomicU8::new(TaskState::Ready as u8),
+            sleep_kind: AtomicU8::new(SleepKind::Interruptible as u8),
+            woken_by_signal: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
@@ -564,6 +645,9 @@ impl TaskInner {
 
             time: UnsafeCell::new(TimeStat::new()),
 
+            #[cfg(feature = "sched_trace")]
+            sched_stat: SchedStat::new(),
+
             #[cfg(feature = "monolithic")]
             process_id: AtomicU64::new(0),
 
@@ -650,6 +734,60 @@ impl TaskInner {
         matches!(self.state(), TaskState::Blocked)
     }
 
+    #[inline]
+    /// the kind of sleep when the task is blocked
+    pub fn sleep_kind(&self) -> SleepKind {
+        self.sleep_kind.load(Ordering::Acquire).into()
+    }
+
+    #[inline]
+    /// set the kind of the next sleep
+    ///
+    /// It will be reset to `Interruptible` when the task is unblocked.
+    pub fn set_sleep_kind(&self, kind: SleepKind) {
+        self.sleep_kind.store(kind as u8, Ordering::Release)
+    }
+
+    /// Whether the task is blocked and the sleep can be aborted by a signal
+    #[inline]
+    pub fn can_wake_by_signal(&self, is_kill: bool) -> bool {
+        self.is_blocked() && self.sleep_kind().wakeable_by(is_kill)
+    }
+
+    #[inline]
+    /// mark whether the task is woken up by a signal
+    pub fn set_woken_by_signal(&self, value: bool) {
+        self.woken_by_signal.store(value, Ordering::Release)
+    }
+
+    #[inline]
+    /// get and clear the flag whether the task is woken up by a signal
+    ///
+    /// The blocking primitive should translate it to `EINTR`.
+    pub fn take_woken_by_signal(&self) -> bool {
+        self.woken_by_signal.swap(false, Ordering::AcqRel)
+    }
+
+    #[inline]
+    /// Whether the task is stopped by a job control signal such as `SIGSTOP`
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    /// mark whether the task is stopped by a job control signal
+    pub fn set_stopped(&self, value: bool) {
+        self.stopped.store(value, Ordering::Release)
+    }
+
+    /// The state letter shown in the task dump and `/proc/<pid>/stat`
+    ///
+    /// R: running or ready, S: interruptible sleep, D: uninterruptible sleep,
+    /// T: stopped, Z: exited
+    pub fn state_letter(&self) -> char {
+        state_letter(self.state(), self.sleep_kind(), self.is_stopped())
+    }
+
     /// Whether the task has been inited
     #[inline]
     pub const fn is_init(&self) -> bool {
@@ -741,11 +879,18 @@ impl TaskInner {
         self.tls.tls_ptr() as usize
     }
 
+    /// Get the scheduling statistics of the task
+    #[cfg(feature = "sched_trace")]
+    #[inline]
+    pub fn sched_stat(&self) -> &SchedStat {
+        &self.sched_stat
+    }
+
     /// Reset the task time statistics
-    pub fn reset_time_stat(&self, current_timestamp: usize) {
+    pub fn reset_time_stat(&self, now: Duration) {
         let time = self.time.get();
         unsafe {
-            (*time).reset(current_timestamp);
+            (*time).reset(now);
         }
     }
 
@@ -771,5 +916,54 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         log::debug!("task drop: {}", self.id_name());
+        #[cfg(feature = "leak_check")]
+        {
+            let prev = LIVE_TASKS.fetch_sub(1, Ordering::AcqRel);
+            debug_assert!(prev > 0, "task {} dropped twice", self.id_name());
+        }
+    }
+}
+
+fn state_letter(state: TaskState, kind: SleepKind, stopped: bool) -> char {
+    match state {
+        TaskState::Running | TaskState::Ready => 'R',
+        // 停止的任务阻塞在等待 SIGCONT 的队列上
+        TaskState::Blocked if stopped => 'T',
+        TaskState::Blocked => match kind {
+            SleepKind::Interruptible => 'S',
+            SleepKind::Killable | SleepKind::Uninterruptible => 'D',
+        },
+        TaskState::Exited => 'Z',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{state_letter, SleepKind, TaskState};
+
+    #[test]
+    fn test_sleep_kind_wakeable() {
+        assert!(SleepKind::Interruptible.wakeable_by(false));
+        assert!(SleepKind::Interruptible.wakeable_by(true));
+        // killable 的等待只会被 SIGKILL 打断
+        assert!(!SleepKind::Killable.wakeable_by(false));
+        assert!(SleepKind::Killable.wakeable_by(true));
+        // uninterruptible 的等待即使是 SIGKILL 也不会打断
+        assert!(!SleepKind::Uninterruptible.wakeable_by(false));
+        assert!(!SleepKind::Uninterruptible.wakeable_by(true));
+    }
+
+    #[test]
+    fn test_state_letter() {
+        use SleepKind::*;
+        use TaskState::*;
+        assert_eq!(state_letter(Ready, Interruptible, false), 'R');
+        assert_eq!(state_letter(Blocked, Interruptible, false), 'S');
+        assert_eq!(state_letter(Blocked, Killable, false), 'D');
+        assert_eq!(state_letter(Blocked, Uninterruptible, false), 'D');
+        assert_eq!(state_letter(Blocked, Killable, true), 'T');
+        // 被 SIGCONT 唤醒、尚未清除停止标志时按运行态显示
+        assert_eq!(state_letter(Ready, Interruptible, true), 'R');
+        assert_eq!(state_letter(Exited, Interruptible, false), 'Z');
     }
 }
@@ -31,8 +31,7 @@
 
 extern crate alloc;
 
-use alloc::{boxed::Box, collections::BinaryHeap};
-use core::cmp::{Ord, Ordering, PartialOrd};
+use alloc::{boxed::Box, collections::BTreeMap};
 use core::time::Duration;
 
 /// The type of the time value.
@@ -46,44 +45,38 @@ pub trait TimerEvent {
     fn callback(self, now: TimeValue);
 }
 
-struct TimerEventWrapper<E> {
-    deadline: TimeValue,
-    event: E,
-}
-
-/// A list of timed events.
+/// The handle of a timed event returned by [`TimerList::set`].
 ///
-/// It internally uses a min-heap to store the events by deadline, make it
-/// possible to trigger these events sequentially.
-pub struct TimerList<E: TimerEvent> {
-    events: BinaryHeap<TimerEventWrapper<E>>,
-}
-
-impl<E> PartialOrd for TimerEventWrapper<E> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other)) // reverse ordering for Min-heap
-    }
+/// It can be used to cancel that event with [`TimerList::cancel_id`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimerId {
+    deadline: TimeValue,
+    seq: u64,
 }
 
-impl<E> Ord for TimerEventWrapper<E> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.deadline.cmp(&self.deadline) // reverse ordering for Min-heap
+impl TimerId {
+    /// The deadline of the event.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
     }
 }
 
-impl<E> PartialEq for TimerEventWrapper<E> {
-    fn eq(&self, other: &Self) -> bool {
-        self.deadline.eq(&other.deadline)
-    }
+/// A list of timed events.
+///
+/// It internally uses an ordered map keyed by the deadline, make it possible to
+/// trigger these events sequentially. Events with the same deadline are
+/// triggered in the order they were set.
+pub struct TimerList<E: TimerEvent> {
+    events: BTreeMap<TimerId, E>,
+    next_seq: u64,
 }
 
-impl<E> Eq for TimerEventWrapper<E> {}
-
 impl<E: TimerEvent> TimerList<E> {
     /// Creates a new empty timer list.
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
-            events: BinaryHeap::new(),
+            events: BTreeMap::new(),
+            next_seq: 0,
         }
     }
 
@@ -94,8 +87,16 @@ impl<E: TimerEvent> TimerList<E> {
     }
 
     /// Set a timed event that will be triggered at `deadline`.
-    pub fn set(&mut self, deadline: TimeValue, event: E) {
-        self.events.push(TimerEventWrapper { deadline, event });
+    ///
+    /// Returns the handle of the event.
+    pub fn set(&mut self, deadline: TimeValue, event: E) -> TimerId {
+        let id = TimerId {
+            deadline,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        self.events.insert(id, event);
+        id
     }
 
     /// Cancel all events that meet the condition.
@@ -103,14 +104,21 @@ impl<E: TimerEvent> TimerList<E> {
     where
         F: Fn(&E) -> bool,
     {
-        // TODO: performance optimization
-        self.events.retain(|e| !condition(&e.event));
+        self.events.retain(|_, e| !condition(e));
+    }
+
+    /// Cancel the event with the given handle.
+    ///
+    /// Returns the event if it has not been expired yet, or `None` if it has
+    /// already been expired or canceled.
+    pub fn cancel_id(&mut self, id: TimerId) -> Option<E> {
+        self.events.remove(&id)
     }
 
     /// Get the deadline of the most recent event.
     #[inline]
     pub fn next_deadline(&self) -> Option<TimeValue> {
-        self.events.peek().map(|e| e.deadline)
+        self.events.keys().next().map(|id| id.deadline)
     }
 
     /// Try to expire the earliest event that passed the deadline at the given
@@ -118,10 +126,10 @@ impl<E: TimerEvent> TimerList<E> {
     ///
     /// Returns `None` if no event is expired.
     pub fn expire_one(&mut self, now: TimeValue) -> Option<(TimeValue, E)> {
-        if let Some(e) = self.events.peek() {
-            if e.deadline <= now {
-                return self.events.pop().map(|e| (e.deadline, e.event));
-            }
+        let entry = self.events.first_entry()?;
+        if entry.key().deadline <= now {
+            let (id, event) = entry.remove_entry();
+            return Some((id.deadline, event));
         }
         None
     }
@@ -158,6 +166,7 @@ impl TimerEvent for TimerEventFn {
 mod tests {
     use super::{TimeValue, TimerEvent, TimerEventFn, TimerList};
     use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
     use std::time::{Duration, Instant};
 
     #[test]
@@ -231,4 +240,73 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_timer_list_order_and_cancel() {
+        const NUM_TIMERS: usize = 1000;
+
+        struct RecordEvent(usize, Arc<Mutex<Vec<(usize, TimeValue)>>>);
+
+        impl TimerEvent for RecordEvent {
+            fn callback(self, now: TimeValue) {
+                self.1.lock().unwrap().push((self.0, now));
+            }
+        }
+
+        // a fixed LCG, so that the test is reproducible
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            seed >> 33
+        };
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut timer_list = TimerList::new();
+        let mut deadlines = Vec::new();
+        let mut ids = Vec::new();
+        for i in 0..NUM_TIMERS {
+            // a small range, so that many timers share the same deadline
+            let ddl = Duration::from_micros(random() % 500);
+            deadlines.push(ddl);
+            ids.push(timer_list.set(ddl, RecordEvent(i, fired.clone())));
+        }
+
+        let mut canceled = vec![false; NUM_TIMERS];
+        let mut now = Duration::ZERO;
+        while !timer_list.is_empty() {
+            now += Duration::from_micros(7);
+            // cancel some timers just before they expire
+            for (i, id) in ids.iter().enumerate() {
+                if i % 10 == 0 && !canceled[i] && id.deadline() <= now {
+                    canceled[i] = timer_list.cancel_id(*id).is_some();
+                    assert!(canceled[i], "timer {} was expired before canceling", i);
+                }
+            }
+            while let Some((deadline, event)) = timer_list.expire_one(now) {
+                assert!(deadline <= now);
+                event.callback(now);
+            }
+        }
+
+        let fired = fired.lock().unwrap();
+        let mut count = vec![0; NUM_TIMERS];
+        for &(i, _) in fired.iter() {
+            count[i] += 1;
+        }
+        for i in 0..NUM_TIMERS {
+            let expected = if canceled[i] { 0 } else { 1 };
+            assert_eq!(count[i], expected, "timer {} fired {} times", i, count[i]);
+            // an expired or canceled timer can not be canceled again
+            assert!(timer_list.cancel_id(ids[i]).is_none());
+        }
+        assert_eq!(fired.len(), NUM_TIMERS - NUM_TIMERS / 10);
+        // events are triggered by deadline, and in the order they were set on ties
+        for pair in fired.windows(2) {
+            let (a, b) = (pair[0].0, pair[1].0);
+            assert!((deadlines[a], a) < (deadlines[b], b));
+            assert!(pair[0].1 <= pair[1].1);
+        }
+    }
 }
@@ -19,6 +19,7 @@ pub(crate) unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     // init fdt
     axhal::platform::mem::idmap_device(dtb);
     of::init_fdt_ptr(phys_to_virt(dtb.into()).as_usize() as *const u8);
+    axhal::platform::init_cmdline(of::bootargs().unwrap_or_default());
 
     // HugeMap all device memory for allocator
     for m in of::memory_nodes() {
@@ -12,6 +12,9 @@ unsafe fn init_boot_page_table() {
     BOOT_PT_SV39[2] = (0x80000 << 10) | 0xef;
     // 0xffff_ffc0_8000_0000..0xffff_ffc0_c000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[0x102] = (0x80000 << 10) | 0xef;
+    // 0xffff_ffc0_c000_0000..0xffff_ffc1_0000_0000, VRWX_GAD, 1G block
+    // QEMU places the device tree at the end of RAM, which may be above 3G
+    BOOT_PT_SV39[0x103] = (0xc0000 << 10) | 0xef;
 }
 
 unsafe fn init_mmu() {
@@ -10,6 +10,11 @@ unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     axhal::mem::clear_bss();
     axhal::cpu::init_primary(cpu_id);
 
+    // save the kernel command line before the device tree may be overwritten
+    // by the allocator or unmapped by the kernel page table
+    of::init_fdt_ptr(axhal::mem::phys_to_virt(dtb.into()).as_usize() as *const u8);
+    axhal::platform::init_cmdline(of::bootargs().unwrap_or_default());
+
     axtrap::init_interrupt();
     axlog::init();
     axlog::set_max_level(option_env!("AX_LOG").unwrap_or("")); // no effect if set `log-level-*` features
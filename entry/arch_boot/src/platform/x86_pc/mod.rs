@@ -14,11 +14,25 @@ fn current_cpu_id() -> usize {
     }
 }
 
-unsafe extern "C" fn rust_entry(magic: usize, _mbi: usize) {
-    // TODO: handle multiboot info
+/// Returns the kernel command line passed by the multiboot loader.
+unsafe fn multiboot_cmdline(mbi: usize) -> &'static str {
+    const MULTIBOOT_INFO_CMDLINE: u32 = 1 << 2;
+    let info = axhal::mem::phys_to_virt(mbi.into()).as_ptr() as *const u32;
+    if info.read() & MULTIBOOT_INFO_CMDLINE == 0 {
+        return "";
+    }
+    let cmdline = axhal::mem::phys_to_virt((info.add(4).read() as usize).into()).as_ptr();
+    core::ffi::CStr::from_ptr(cmdline as *const _)
+        .to_str()
+        .unwrap_or_default()
+}
+
+unsafe extern "C" fn rust_entry(magic: usize, mbi: usize) {
+    // TODO: handle memory regions in multiboot info
     if magic == self::boot::MULTIBOOT_BOOTLOADER_MAGIC {
         axhal::mem::clear_bss();
         axhal::cpu::init_primary(current_cpu_id());
+        axhal::platform::init_cmdline(multiboot_cmdline(mbi));
         axtrap::init_interrupt();
         axlog::init();
         axlog::set_max_level(option_env!("AX_LOG").unwrap_or("")); // no effect if set `log-level-*` features
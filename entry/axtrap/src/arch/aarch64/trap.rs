@@ -77,6 +77,10 @@ fn handle_el1h_64_sync_exception(tf: &mut TrapFrame) {
         }
         Some(ESR_EL1::EC::Value::DataAbortCurrentEL)
         | Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => {
+            // 访问用户内存的指令出错时跳转到修复代码，由其向调用者返回错误
+            if axhal::arch::fixup_exception(&mut tf.elr) {
+                return;
+            }
             let iss = esr.read(ESR_EL1::ISS);
             panic!(
                 "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}:\n{:#x?}",
@@ -126,7 +130,7 @@ fn handle_el0t_64_sync_exception(tf: &mut TrapFrame) {
                 tf.r[8],
                 [tf.r[0], tf.r[1], tf.r[2], tf.r[3], tf.r[4], tf.r[5]],
             );
-            tf.r[0] = result as usize;
+            tf.r[0] = result;
         }
         Some(ESR_EL1::EC::Value::DataAbortLowerEL) => {
             let far = FAR_EL1.get() as usize;
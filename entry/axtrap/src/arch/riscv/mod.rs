@@ -37,6 +37,34 @@ fn handle_breakpoint(sepc: &mut usize) {
     *sepc += 2
 }
 
+/// 将用户态的 ecall 作为 Linux syscall 交给 `syscall` 处理，返回值写入 a0
+///
+/// 只有来自用户态的 ecall 才能作为 Linux syscall 处理，SPP 表明来自 S 态时 panic
+#[cfg(feature = "monolithic")]
+fn handle_user_ecall(tf: &mut TrapFrame, syscall: impl FnOnce(usize, [usize; 6]) -> usize) {
+    assert!(
+        tf.is_from_user(),
+        "ecall with supervisor SPP treated as user syscall @ {:#x}:\n{:#x?}",
+        tf.sepc,
+        tf
+    );
+    tf.sepc += 4;
+    tf.regs.a0 = syscall(
+        tf.regs.a7,
+        [
+            tf.regs.a0, tf.regs.a1, tf.regs.a2, tf.regs.a3, tf.regs.a4, tf.regs.a5,
+        ],
+    );
+}
+
+/// 内核自身不会执行 ecall，S 态的 ecall 说明内核出现了错误
+fn handle_supervisor_ecall(tf: &TrapFrame) -> ! {
+    panic!(
+        "Unexpected ecall from supervisor mode @ {:#x}, a7 = {}:\n{:#x?}",
+        tf.sepc, tf.regs.a7, tf
+    );
+}
+
 #[no_mangle]
 pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let scause = scause::read();
@@ -47,18 +75,12 @@ pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
         Trap::Interrupt(_) => handle_irq(scause.bits(), from_user),
 
         #[cfg(feature = "monolithic")]
-        Trap::Exception(E::UserEnvCall) => {
+        Trap::Exception(E::UserEnvCall) => handle_user_ecall(tf, |syscall_id, args| {
             axhal::arch::enable_irqs();
-            tf.sepc += 4;
-            let result = handle_syscall(
-                tf.regs.a7,
-                [
-                    tf.regs.a0, tf.regs.a1, tf.regs.a2, tf.regs.a3, tf.regs.a4, tf.regs.a5,
-                ],
-            );
-            tf.regs.a0 = result as usize;
+            let result = handle_syscall(syscall_id, args);
             axhal::arch::disable_irqs();
-        }
+            result
+        }),
 
         #[cfg(feature = "monolithic")]
         Trap::Exception(E::InstructionPageFault) => {
@@ -77,6 +99,10 @@ pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
         Trap::Exception(E::LoadPageFault) => {
             let addr = riscv::register::stval::read();
             if !from_user {
+                // 访问用户内存的指令出错时跳转到修复代码，由其向调用者返回错误
+                if axhal::arch::fixup_exception(&mut tf.sepc) {
+                    return;
+                }
                 unimplemented!(
                     "L page fault from kernel, addr: {:X}, sepc: {:X}",
                     addr,
@@ -90,6 +116,10 @@ pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
         Trap::Exception(E::StorePageFault) => {
             let addr = riscv::register::stval::read();
             if !from_user {
+                // 访问用户内存的指令出错时跳转到修复代码，由其向调用者返回错误
+                if axhal::arch::fixup_exception(&mut tf.sepc) {
+                    return;
+                }
                 unimplemented!(
                     "S page fault from kernel, addr: {:X}, sepc: {:X}",
                     addr,
@@ -100,6 +130,13 @@ pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
             handle_page_fault(addr.into(), MappingFlags::USER | MappingFlags::WRITE);
         }
 
+        // 访问用户内存时的访问错误（而不是缺页）同样交给修复代码
+        #[cfg(feature = "monolithic")]
+        Trap::Exception(E::LoadFault | E::StoreFault)
+            if !from_user && axhal::arch::fixup_exception(&mut tf.sepc) => {}
+
+        Trap::Exception(E::SupervisorEnvCall) => handle_supervisor_ecall(tf),
+
         _ => {
             panic!(
                 "Unhandled trap {:?} @ {:#x}:\n{:#x?}",
@@ -114,6 +151,64 @@ pub fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     {
         if from_user {
             handle_signals();
+            // 返回用户态时不应还有未释放的 UserMemoryGuard
+            debug_assert_eq!(
+                axhal::arch::user_memory_guard_depth(),
+                0,
+                "UserMemoryGuard leaked into user mode"
+            );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axhal::arch::TrapFrame;
+
+    /// sstatus.SPP，置 1 表示 trap 来自 S 态
+    const SSTATUS_SPP: usize = 1 << 8;
+
+    fn ecall_frame(from_user: bool) -> TrapFrame {
+        let mut tf = TrapFrame::default();
+        if !from_user {
+            tf.sstatus |= SSTATUS_SPP;
+        }
+        tf.sepc = 0x1000;
+        tf.regs.a7 = 64;
+        tf.regs.a0 = 1;
+        tf.regs.a1 = 0x2000;
+        tf.regs.a2 = 5;
+        tf
+    }
+
+    #[cfg(feature = "monolithic")]
+    #[test]
+    fn test_user_ecall_dispatch() {
+        let mut tf = ecall_frame(true);
+        let mut called = None;
+        super::handle_user_ecall(&mut tf, |syscall_id, args| {
+            called = Some((syscall_id, args));
+            5
+        });
+        // 系统调用号取自 a7，参数取自 a0-a5，返回值写回 a0 并跳过 ecall 指令
+        assert_eq!(called, Some((64, [1, 0x2000, 5, 0, 0, 0])));
+        assert_eq!(tf.regs.a0, 5);
+        assert_eq!(tf.sepc, 0x1004);
+    }
+
+    #[cfg(feature = "monolithic")]
+    #[test]
+    #[should_panic(expected = "ecall with supervisor SPP")]
+    fn test_user_ecall_from_supervisor() {
+        let mut tf = ecall_frame(false);
+        super::handle_user_ecall(&mut tf, |_, _| {
+            unreachable!("dispatched a supervisor ecall")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected ecall from supervisor mode")]
+    fn test_supervisor_ecall() {
+        super::handle_supervisor_ecall(&ecall_frame(false));
+    }
+}
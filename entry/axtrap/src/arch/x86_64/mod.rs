@@ -53,6 +53,9 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
                     axlog::debug!("error_code: {:?}", tf.error_code);
                     crate::trap::handle_page_fault(unsafe { cr2() }.into(), map_flags);
                 }
+            } else if let Some(fixup) = axhal::arch::search_exception_table(tf.rip as usize) {
+                // 访问用户内存的指令出错时跳转到修复代码，由其向调用者返回错误
+                tf.rip = fixup as u64;
             } else {
                 panic!(
                     "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
@@ -15,6 +15,7 @@ mod page;
 use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
 pub use page::PhysPage;
 use spinlock::SpinNoIrq;
 
@@ -104,24 +105,26 @@ impl GlobalAllocator {
     ///  aligned to it.
     pub fn alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
         // simple two-level allocator: if no heap memory, allocate from the page allocator.
-        let mut balloc = self.balloc.lock();
         loop {
+            let mut balloc = self.balloc.lock();
             if let Ok(ptr) = balloc.alloc(layout) {
                 return Ok(ptr);
-            } else {
-                let old_size = balloc.total_bytes();
-                let expand_size = old_size
-                    .max(layout.size())
-                    .next_power_of_two()
-                    .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
-                debug!(
-                    "expand heap memory: [{:#x}, {:#x})",
-                    heap_ptr,
-                    heap_ptr + expand_size
-                );
-                balloc.add_memory(heap_ptr, expand_size)?;
             }
+            let old_size = balloc.total_bytes();
+            let expand_size = old_size
+                .max(layout.size())
+                .next_power_of_two()
+                .max(PAGE_SIZE);
+            // The OOM handler may block, so do not hold the heap lock while
+            // asking the page allocator for more memory.
+            drop(balloc);
+            let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+            debug!(
+                "expand heap memory: [{:#x}, {:#x})",
+                heap_ptr,
+                heap_ptr + expand_size
+            );
+            self.balloc.lock().add_memory(heap_ptr, expand_size)?;
         }
     }
 
@@ -142,8 +145,23 @@ impl GlobalAllocator {
     ///
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
+    ///
+    /// If there is not enough memory, the handler set by [`set_oom_handler`]
+    /// is called and the allocation is retried as long as it reports that
+    /// memory was freed.
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        loop {
+            let res = self.palloc.lock().alloc_pages(num_pages, align_pow2);
+            if res.is_ok() {
+                return res;
+            }
+            FAILED_PAGE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+            let handler = *OOM_HANDLER.lock();
+            match handler {
+                Some(handler) if handler(num_pages) => continue,
+                _ => return res,
+            }
+        }
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -200,6 +218,29 @@ pub fn global_allocator() -> &'static GlobalAllocator {
     &GLOBAL_ALLOCATOR
 }
 
+static OOM_HANDLER: SpinNoIrq<Option<fn(usize) -> bool>> = SpinNoIrq::new(None);
+
+static FAILED_PAGE_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets a function called when the page allocator runs out of memory, with
+/// the number of pages requested. It should try to free some memory and
+/// return `true` if the allocation is worth retrying.
+///
+/// The handler is called without any allocator lock held, but the caller of
+/// the allocation may hold other locks, so the handler must check whether it
+/// is allowed to block.
+pub fn set_oom_handler(handler: fn(usize) -> bool) {
+    *OOM_HANDLER.lock() = Some(handler);
+}
+
+/// Returns how many times the page allocator has run out of memory.
+///
+/// Comparing two readings tells whether a failed operation was caused by a
+/// shortage of physical pages.
+pub fn failed_page_allocs() -> usize {
+    FAILED_PAGE_ALLOCS.load(Ordering::Relaxed)
+}
+
 /// Initializes the global allocator with the given memory region.
 ///
 /// Note that the memory region bounds are just numbers, and the allocator
@@ -20,3 +20,17 @@ pub const PHYS_MEMORY_END: usize = PHYS_MEMORY_BASE + PHYS_MEMORY_SIZE;
 
 /// user memory
 pub const USER_MEMORY_START: usize = 0x1000;
+
+/// 用户地址空间的大小，用户地址均小于该值
+#[cfg(target_arch = "x86_64")]
+pub const TASK_SIZE: usize = 0x8000_0000_0000;
+/// 用户地址空间的大小，用户地址均小于该值
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+pub const TASK_SIZE: usize = 0x40_0000_0000;
+/// 用户地址空间的大小，用户地址均小于该值
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "riscv32",
+    target_arch = "riscv64"
+)))]
+pub const TASK_SIZE: usize = 0x1_0000_0000_0000;
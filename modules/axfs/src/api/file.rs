@@ -180,6 +180,16 @@ impl File {
         self.inner.get_attr()
     }
 
+    /// Get the inode number of the file.
+    pub fn ino(&self) -> u64 {
+        self.inner.ino()
+    }
+
+    /// Whether the underlying node is of type `T`, e.g. a device node of devfs.
+    pub fn node_is<T: 'static>(&self) -> bool {
+        self.inner.node_is::<T>()
+    }
+
     /// To truncate the file to a specified length.
     pub fn truncate(&mut self, len: usize) -> Result<()> {
         self.inner.truncate(len as u64)
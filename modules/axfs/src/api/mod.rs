@@ -66,6 +66,11 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Changes the permissions found on a file or a directory.
+pub fn set_permissions(path: &str, perm: Permissions) -> io::Result<()> {
+    crate::root::lookup(None, path)?.set_perm(perm)
+}
+
 /// Creates a new, empty directory at the provided path.
 pub fn create_dir(path: &str) -> io::Result<()> {
     DirBuilder::new().create(path)
@@ -108,11 +108,15 @@ bitflags! {
         const EXCLUSIVE = 1 << 7;
         /// 使打开的文件不会成为该进程的控制终端。目前没有终端设置，不处理
         const NOCTTY = 1 << 8;
-        /// 同上，在不同的库中可能会用到这个或者上一个
-        const EXCL = 1 << 9;
+        /// 打开时将已存在的普通文件截断为 0
+        const TRUNC = 1 << 9;
+        /// 每次写入前都将偏移量移到文件末尾
+        const APPEND = 1 << 10;
         /// 非阻塞读写?(虽然不知道为什么但 date.lua 也要)
         /// 在 socket 中使用得较多
         const NON_BLOCK = 1 << 11;
+        /// 文件可读或可写时向属主发送信号，即信号驱动 I/O
+        const ASYNC = 1 << 13;
         /// 要求把 CR-LF 都换成 LF
         const TEXT = 1 << 14;
         /// 和上面不同，要求输入输出都不进行这个翻译
@@ -121,6 +125,8 @@ bitflags! {
         const DSYNC = 1 << 16;
         /// 如果是符号链接，不跟随符号链接去寻找文件，而是针对连接本身
         const NOFOLLOW = 1 << 17;
+        /// 读取时不更新文件的 atime
+        const NOATIME = 1 << 18;
         /// 在 exec 时需关闭
         const CLOEXEC = 1 << 19;
         /// 是否是目录
@@ -158,6 +164,16 @@ impl OpenFlags {
         self.contains(Self::EXCLUSIVE)
     }
 
+    /// 获取是否需要在打开时截断文件
+    pub fn is_truncate(&self) -> bool {
+        self.contains(Self::TRUNC)
+    }
+
+    /// 获取是否以追加模式写入
+    pub fn is_append(&self) -> bool {
+        self.contains(Self::APPEND)
+    }
+
     /// 获取是否是目录
     pub fn is_dir(&self) -> bool {
         self.contains(Self::DIR)
@@ -343,6 +359,9 @@ pub trait FileIO: AsAny + Send + Sync {
     }
 
     /// To control the file descriptor
+    ///
+    /// 各类文件自行处理与自身相关的请求，不认识的请求返回 `Unsupported`，
+    /// 由系统调用层转换为 ENOTTY
     fn ioctl(&self, _request: usize, _arg1: usize) -> AxResult<()> {
         Err(AxError::Unsupported)
     }
@@ -399,12 +418,24 @@ impl From<usize> for AccessMode {
 /// IOCTL系统调用支持
 #[allow(missing_docs)]
 pub const TCGETS: usize = 0x5401;
+/// 立即修改终端设置
+pub const TCSETS: usize = 0x5402;
+/// 等待输出发送完毕后修改终端设置
+pub const TCSETSW: usize = 0x5403;
+/// 等待输出发送完毕并丢弃未读的输入后修改终端设置
+pub const TCSETSF: usize = 0x5404;
 #[allow(missing_docs)]
 pub const TIOCGPGRP: usize = 0x540F;
 #[allow(missing_docs)]
 pub const TIOCSPGRP: usize = 0x5410;
 #[allow(missing_docs)]
 pub const TIOCGWINSZ: usize = 0x5413;
+/// 设置终端窗口大小
+pub const TIOCSWINSZ: usize = 0x5414;
+/// 获取可以立即读取的字节数
+pub const FIONREAD: usize = 0x541B;
+/// 设置或清除非阻塞模式
+pub const FIONBIO: usize = 0x5421;
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 /// the size of the console window
@@ -97,8 +97,9 @@ impl OpenOptions {
         }
         match (self.write, self.append) {
             (true, false) => {}
+            // Like Linux, a read-only open may create the file, but not truncate it.
             (false, false) => {
-                if self.truncate || self.create || self.create_new {
+                if self.truncate {
                     return false;
                 }
             }
@@ -223,6 +224,9 @@ impl File {
 
     /// Sets the cursor of the file to the specified offset. Returns the new
     /// position after the seek.
+    ///
+    /// The new position must fit in an `i64`, so that it can be returned by
+    /// `lseek` as a non-negative `off_t`.
     pub fn seek(&mut self, pos: SeekFrom) -> AxResult<u64> {
         let size = self.get_attr()?.size();
         let new_offset = match pos {
@@ -230,6 +234,7 @@ impl File {
             SeekFrom::Current(off) => self.offset.checked_add_signed(off),
             SeekFrom::End(off) => size.checked_add_signed(off),
         }
+        .filter(|&offset| offset <= i64::MAX as u64)
         .ok_or_else(|| ax_err_type!(InvalidInput))?;
         self.offset = new_offset;
         Ok(new_offset)
@@ -240,6 +245,18 @@ impl File {
         self.node.access(Cap::empty())?.get_attr()
     }
 
+    /// Gets the inode number of the file.
+    pub fn ino(&self) -> u64 {
+        self.node.access(Cap::empty()).map_or(0, |node| node.ino())
+    }
+
+    /// Whether the underlying node is of type `T`, e.g. a device node of devfs.
+    pub fn node_is<T: 'static>(&self) -> bool {
+        self.node
+            .access(Cap::empty())
+            .is_ok_and(|node| node.as_any().is::<T>())
+    }
+
     #[allow(unused)]
     /// whether the file is readable.
     pub fn readable(&self) -> bool {
@@ -356,8 +373,13 @@ impl Directory {
 }
 
 impl Drop for File {
+    /// Flushes the data written through this file before closing it.
     fn drop(&mut self) {
-        unsafe { self.node.access_unchecked().release().ok() };
+        let node = unsafe { self.node.access_unchecked() };
+        if self.node.can_access(Cap::WRITE) {
+            node.fsync().ok();
+        }
+        node.release().ok();
     }
 }
 
@@ -140,6 +140,21 @@ impl VfsNodeOps for FileWrapper {
         Ok(VfsNodeAttr::new(perm, vtype, size, blocks))
     }
 
+    /// lwext4 的接口没有给出 inode 号，且每次 lookup 都会新建 FileWrapper，因此由文件的绝对路径得出
+    fn ino(&self) -> u64 {
+        let file = self.0.lock();
+        let path = file.get_path();
+        super::path_ino(path.to_str().unwrap_or_default())
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        let mut file = self.0.lock();
+        info!("set_perm of {:?}: {:o}", file.get_path(), perm.mode());
+        file.file_mode_set(perm.mode())
+            .map(|_v| ())
+            .map_err(|e| e.try_into().unwrap())
+    }
+
     fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
         info!("create {:?} on Ext4fs: {}", ty, path);
         let fpath = self.path_deal_with(path);
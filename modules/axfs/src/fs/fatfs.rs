@@ -1,4 +1,4 @@
-use alloc::sync::Arc;
+use alloc::{format, string::String, sync::Arc};
 use core::cell::UnsafeCell;
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
@@ -15,8 +15,13 @@ pub struct FatFileSystem {
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// A file in the FAT filesystem, along with its inode number.
+pub struct FileWrapper<'a>(
+    Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>,
+    u64,
+);
+/// A directory in the FAT filesystem, along with its path relative to the root.
+pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>, String);
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -50,15 +55,23 @@ impl FatFileSystem {
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir(), String::new())) }
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    // FAT has no inode numbers, and fatfs creates a new object on every lookup,
+    // so the inode number is derived from the path instead.
+    fn new_file(
+        file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>,
+        path: &str,
+    ) -> Arc<FileWrapper> {
+        Arc::new(FileWrapper(Mutex::new(file), super::path_ino(path)))
     }
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+    fn new_dir(
+        dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>,
+        path: String,
+    ) -> Arc<DirWrapper> {
+        Arc::new(DirWrapper(dir, path))
     }
 }
 
@@ -73,6 +86,10 @@ impl VfsNodeOps for FileWrapper<'static> {
         Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
     }
 
+    fn ino(&self) -> u64 {
+        self.1
+    }
+
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         // let mut file = self.0.lock();
         // file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
@@ -131,6 +148,12 @@ impl VfsNodeOps for FileWrapper<'static> {
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
         file.truncate().map_err(as_vfs_err)
     }
+
+    /// Writes back the directory entry (size and timestamps) that fatfs keeps
+    /// in memory while the file is being written.
+    fn fsync(&self) -> VfsResult {
+        self.0.lock().flush().map_err(as_vfs_err)
+    }
 }
 
 impl VfsNodeOps for DirWrapper<'static> {
@@ -146,10 +169,15 @@ impl VfsNodeOps for DirWrapper<'static> {
         ))
     }
 
+    fn ino(&self) -> u64 {
+        super::path_ino(&self.1)
+    }
+
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
-            .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+        let parent = self.1.rsplit_once('/').map_or("", |(parent, _)| parent);
+        self.0.open_dir("..").map_or(None, |dir| {
+            Some(FatFileSystem::new_dir(dir, String::from(parent)))
+        })
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -162,11 +190,12 @@ impl VfsNodeOps for DirWrapper<'static> {
             return self.lookup(rest);
         }
 
+        let full_path = format!("{}/{}", self.1, path);
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
         if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
+            Ok(FatFileSystem::new_file(file, &full_path))
         } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+            Ok(FatFileSystem::new_dir(dir, full_path))
         } else {
             Err(VfsError::NotFound)
         }
@@ -12,6 +12,19 @@ cfg_if::cfg_if! {
     }
 }
 
+/// Derives an inode number from the path of a node relative to the root of
+/// its filesystem, for filesystems that create a new node on every lookup and
+/// do not expose the on-disk inode number.
+#[cfg(any(feature = "fatfs", feature = "ext4fs"))]
+pub(crate) fn path_ino(path: &str) -> u64 {
+    // FNV-1a
+    path.trim_matches('/')
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
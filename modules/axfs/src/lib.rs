@@ -41,10 +41,68 @@ pub use axfs_ramfs;
 use axdriver::{prelude::*, AxDeviceContainer};
 
 /// Initializes filesystems by block devices.
-pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+pub fn init_filesystems(blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    init_filesystems_with_root(blk_devs, None, None);
+}
+
+/// Initializes filesystems, mounting the block device named by `root` on `/`.
+///
+/// `root` names the disk the way Linux names virtio disks (`/dev/vda` for
+/// block device 0, `/dev/vdb` for block device 1, ...) and defaults to block
+/// device 0. If `rootfstype` is given, it must be the filesystem the kernel is
+/// built with.
+///
+/// # Panics
+///
+/// Panics if the root device does not exist or `rootfstype` is not supported,
+/// as Linux does when it cannot mount the root filesystem.
+pub fn init_filesystems_with_root(
+    mut blk_devs: AxDeviceContainer<AxBlockDevice>,
+    root: Option<&str>,
+    rootfstype: Option<&str>,
+) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
+    let index = match root {
+        Some(root) => root_device_index(root)
+            .unwrap_or_else(|| panic!("VFS: Cannot open root device \"{}\"", root)),
+        None => 0,
+    };
+    if let Some(fstype) = rootfstype {
+        if !self::root::rootfs_type_supported(fstype) {
+            panic!("VFS: Unable to mount root fs of type \"{}\"", fstype);
+        }
+    }
+    // devices before the root device are left unused
+    for _ in 0..index {
+        blk_devs.take_one();
+    }
+    let dev = blk_devs
+        .take_one()
+        .unwrap_or_else(|| panic!("VFS: Cannot open root device {}", index));
+    info!("  use block device {}: {:?}", index, dev.device_name());
     self::root::init_rootfs(self::dev::Disk::new(dev));
 }
+
+/// Parses a virtio disk name such as `/dev/vdb` into the block device index.
+fn root_device_index(root: &str) -> Option<usize> {
+    let name = root.strip_prefix("/dev/").unwrap_or(root);
+    match name.strip_prefix("vd")?.as_bytes() {
+        [c @ b'a'..=b'z'] => Some((c - b'a') as usize),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::root_device_index;
+
+    #[test]
+    fn test_root_device_index() {
+        assert_eq!(root_device_index("/dev/vda"), Some(0));
+        assert_eq!(root_device_index("/dev/vdc"), Some(2));
+        assert_eq!(root_device_index("vdb"), Some(1));
+        assert_eq!(root_device_index("/dev/vda1"), None);
+        assert_eq!(root_device_index("/dev/sda"), None);
+    }
+}
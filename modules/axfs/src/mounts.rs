@@ -56,6 +56,7 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
     let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
     file_over.write_at(0, b"0\n")?;
+    proc_root.create("vmstat", VfsNodeType::File)?;
 
     // Create /proc/self/stat
     proc_root.create("self", VfsNodeType::Dir)?;
@@ -119,6 +119,10 @@ impl VfsNodeOps for RootDirectory {
         self.main_fs.root_dir().get_attr()
     }
 
+    fn ino(&self) -> u64 {
+        self.main_fs.root_dir().ino()
+    }
+
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
         self.lookup_mounted_fs(path, |fs, rest_path| {
             let dir = fs.root_dir();
@@ -157,6 +161,24 @@ impl VfsNodeOps for RootDirectory {
     }
 }
 
+/// Whether the root filesystem can be mounted as `fstype` (the `rootfstype=`
+/// kernel parameter). Only the filesystem the kernel is built with is supported.
+pub(crate) fn rootfs_type_supported(fstype: &str) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "myfs")] {
+            let _ = fstype;
+            true
+        } else if #[cfg(feature = "fatfs")] {
+            matches!(fstype, "vfat" | "fat" | "msdos")
+        } else if #[cfg(feature = "ext4fs")] {
+            fstype == "ext4"
+        } else {
+            let _ = fstype;
+            false
+        }
+    }
+}
+
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
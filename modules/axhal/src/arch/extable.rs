@@ -0,0 +1,80 @@
+//! Kernel accesses to user memory that recover from faults.
+//!
+//! Every instruction that may fault while the kernel touches user memory is
+//! recorded in the `__ex_table` section together with a fixup address, like
+//! Linux's exception table. When such an instruction faults, the trap handler
+//! finds the faulting PC with [`fixup_exception`] and resumes at the fixup
+//! code, so the access reports an error to its caller instead of bringing the
+//! kernel down.
+//!
+//! [`copy_user`] is the only primitive built on it: everything else (clearing,
+//! copying structures and strings) is expressed with it.
+
+/// An entry of the exception table, see the `__ex_table` sections in the
+/// architecture-specific `uaccess.S`.
+#[repr(C)]
+struct ExceptionTableEntry {
+    /// Address of the instruction that may fault.
+    insn: usize,
+    /// Address to resume at when it faults.
+    fixup: usize,
+}
+
+extern "C" {
+    fn __copy_user(dst: *mut u8, src: *const u8, len: usize) -> usize;
+}
+
+#[cfg(target_os = "none")]
+fn exception_table() -> &'static [ExceptionTableEntry] {
+    extern "C" {
+        fn __start_ex_table();
+        fn __stop_ex_table();
+    }
+    let start = __start_ex_table as usize;
+    let end = __stop_ex_table as usize;
+    let len = (end - start) / core::mem::size_of::<ExceptionTableEntry>();
+    unsafe { core::slice::from_raw_parts(start as *const ExceptionTableEntry, len) }
+}
+
+#[cfg(not(target_os = "none"))]
+fn exception_table() -> &'static [ExceptionTableEntry] {
+    &[]
+}
+
+/// Returns the fixup address of the instruction at `pc`, if it is allowed to
+/// fault.
+pub fn search_exception_table(pc: usize) -> Option<usize> {
+    exception_table()
+        .iter()
+        .find(|entry| entry.insn == pc)
+        .map(|entry| entry.fixup)
+}
+
+/// Redirects a kernel-mode fault at `*pc` to its fixup code.
+///
+/// Returns `false` if the faulting instruction has no fixup, in which case the
+/// fault is a kernel bug.
+pub fn fixup_exception(pc: &mut usize) -> bool {
+    match search_exception_table(*pc) {
+        Some(fixup) => {
+            *pc = fixup;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Copies `len` bytes from `src` to `dst`, where either side may be user
+/// memory that faults.
+///
+/// Returns the number of bytes that could not be copied, so `0` means
+/// success. The copy stops at the first faulting byte.
+///
+/// # Safety
+///
+/// The kernel side of the copy must be valid for `len` bytes. On riscv the
+/// caller must also hold a `UserMemoryGuard`, otherwise every access to a
+/// user page faults.
+pub unsafe fn copy_user(dst: *mut u8, src: *const u8, len: usize) -> usize {
+    __copy_user(dst, src, len)
+}
@@ -1,5 +1,9 @@
 //! Architecture-specific types and operations.
 
+mod extable;
+
+pub use self::extable::{copy_user, fixup_exception, search_exception_table};
+
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "x86_64")] {
         mod x86_64;
@@ -40,6 +40,12 @@ pub struct GeneralRegisters {
     pub t6: usize,
 }
 
+/// sstatus 中的 SPP 位，记录 trap 之前所处的特权级，置 1 表示来自 S 态
+const SSTATUS_SPP: usize = 1 << 8;
+
+/// sstatus 中的 SUM 位，置 1 时 S 态可以访问用户页面
+const SSTATUS_SUM: usize = 1 << 18;
+
 /// Saved registers when a trap (interrupt or exception) occurs.
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
@@ -67,8 +73,11 @@ impl TrapFrame {
         let mut trap_frame = TrapFrame::default();
         trap_frame.set_user_sp(user_sp);
         trap_frame.sepc = app_entry;
-        trap_frame.sstatus =
-            unsafe { (*(&sstatus as *const Sstatus as *const usize) & !(1 << 8)) & !(1 << 1) };
+        // 用户态运行时 SUM 保持清零，内核只在 UserMemoryGuard 存活期间访问用户内存
+        trap_frame.sstatus = unsafe {
+            *(&sstatus as *const Sstatus as *const usize) & !SSTATUS_SPP & !SSTATUS_SUM & !(1 << 1)
+        };
+        let _guard = super::UserMemoryGuard::new();
         unsafe {
             // a0为参数个数
             // a1存储的是用户栈底，即argv
@@ -78,6 +87,11 @@ impl TrapFrame {
         trap_frame
     }
 
+    /// 根据保存的 sstatus.SPP 判断该 trap 是否来自用户态
+    pub fn is_from_user(&self) -> bool {
+        self.sstatus & SSTATUS_SPP == 0
+    }
+
     /// 设置返回值
     pub fn set_ret_code(&mut self, ret_value: usize) {
         self.regs.a0 = ret_value;
@@ -207,3 +221,17 @@ pub fn task_context_switch(prev_ctx: &mut TaskContext, next_ctx: &TaskContext) {
         taskctx::context_switch(prev_ctx, next_ctx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{TrapFrame, SSTATUS_SPP};
+
+    #[test]
+    fn test_trap_frame_origin() {
+        let mut tf = TrapFrame::default();
+        assert!(tf.is_from_user());
+        // 构造一个来自 S 态的 trap frame，不应被当作用户态的 syscall
+        tf.sstatus |= SSTATUS_SPP;
+        assert!(!tf.is_from_user());
+    }
+}
@@ -2,6 +2,7 @@
 mod macros;
 
 mod context;
+mod sum;
 
 pub use self::context::{GeneralRegisters, TrapFrame};
 
@@ -9,6 +10,7 @@ pub use self::context::{GeneralRegisters, TrapFrame};
 pub use context::first_into_user;
 
 pub use context::task_context_switch;
+pub use sum::{user_memory_accessible, user_memory_guard_depth, UserMemoryGuard};
 
 use memory_addr::{PhysAddr, VirtAddr};
 use riscv::asm;
@@ -110,3 +112,4 @@ pub unsafe fn write_thread_pointer(tp: usize) {
 include_asm_marcos!();
 
 core::arch::global_asm!(include_str!("signal.S"));
+core::arch::global_asm!(include_str!("uaccess.S"));
@@ -0,0 +1,85 @@
+//! Access to user memory from S-mode.
+//!
+//! S-mode can only touch pages with the `U` bit while `sstatus.SUM` is set.
+//! [`UserMemoryGuard`] sets the bit for its lifetime. Guards may nest: a
+//! per-CPU depth counter tracks them, and only the outermost one puts the bit
+//! back to the state it found it in.
+
+use kernel_guard::{IrqSave, NoPreempt};
+use riscv::register::sstatus;
+
+/// Number of live guards on this CPU.
+#[percpu::def_percpu]
+static SUM_DEPTH: usize = 0;
+
+/// Whether `sstatus.SUM` was set before the outermost guard on this CPU.
+#[percpu::def_percpu]
+static SUM_SAVED: bool = false;
+
+/// Sets `sstatus.SUM`, allowing S-mode to access user pages.
+#[inline]
+pub(crate) fn enable_sum() {
+    unsafe { sstatus::set_sum() }
+}
+
+/// Clears `sstatus.SUM`, forbidding S-mode to access user pages.
+#[inline]
+pub(crate) fn disable_sum() {
+    unsafe { sstatus::clear_sum() }
+}
+
+/// Returns whether S-mode can currently access user pages.
+#[inline]
+pub fn user_memory_accessible() -> bool {
+    sstatus::read().sum()
+}
+
+/// Returns the number of [`UserMemoryGuard`]s alive on the current CPU.
+#[inline]
+pub fn user_memory_guard_depth() -> usize {
+    SUM_DEPTH.read_current()
+}
+
+/// Allows the kernel to access user memory while it is alive.
+///
+/// Preemption is disabled while the guard is held, so the guard is dropped on
+/// the CPU that created it. Code holding a guard therefore must not sleep;
+/// make sure the user pages are mapped before taking it.
+pub struct UserMemoryGuard {
+    _no_preempt: NoPreempt,
+}
+
+impl UserMemoryGuard {
+    /// Sets `sstatus.SUM` until the guard is dropped.
+    pub fn new() -> Self {
+        let no_preempt = NoPreempt::new();
+        let _irq = IrqSave::new();
+        let depth = SUM_DEPTH.read_current();
+        if depth == 0 {
+            SUM_SAVED.write_current(user_memory_accessible());
+            enable_sum();
+        }
+        SUM_DEPTH.write_current(depth + 1);
+        Self {
+            _no_preempt: no_preempt,
+        }
+    }
+}
+
+impl Default for UserMemoryGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for UserMemoryGuard {
+    fn drop(&mut self) {
+        let _irq = IrqSave::new();
+        let depth = SUM_DEPTH.read_current();
+        debug_assert!(depth > 0, "unbalanced UserMemoryGuard");
+        SUM_DEPTH.write_current(depth.saturating_sub(1));
+        if depth == 1 && !SUM_SAVED.read_current() {
+            disable_sum();
+        }
+    }
+}
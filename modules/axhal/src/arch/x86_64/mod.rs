@@ -112,3 +112,4 @@ pub unsafe fn write_thread_pointer(fs_base: usize) {
 }
 
 core::arch::global_asm!(include_str!("signal.S"));
+core::arch::global_asm!(include_str!("uaccess.S"));
@@ -20,6 +20,8 @@
 //! - `fp_simd`: Enable floating-point and SIMD support.
 //! - `paging`: Enable page table manipulation.
 //! - `irq`: Enable interrupt handling support.
+//! - `virtual-clock`: Use a virtual clock that is only advanced explicitly as
+//!    the system clock, instead of the hardware timer.
 //!
 //! [ArceOS]: https://github.com/rcore-os/arceos
 //! [cargo test]: https://doc.rust-lang.org/cargo/guide/tests.html
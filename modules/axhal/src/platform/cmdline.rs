@@ -0,0 +1,174 @@
+//! Kernel command line.
+//!
+//! The boot code copies the command line (`bootargs` of the device tree
+//! `/chosen` node, or the multiboot command line on x86) here before the boot
+//! page table is replaced, so it stays available for the whole lifetime of the
+//! kernel. Parameters are separated by spaces and take the form `key=value` or
+//! a bare `flag`; values can be double-quoted to contain spaces. Everything
+//! after a standalone `--` belongs to init.
+
+use lazy_init::LazyInit;
+
+/// Maximum length of the kernel command line, longer ones are truncated.
+pub const COMMAND_LINE_SIZE: usize = 2048;
+
+static CMDLINE: LazyInit<Cmdline> = LazyInit::new();
+
+static EMPTY_CMDLINE: Cmdline = Cmdline {
+    buf: [0; COMMAND_LINE_SIZE],
+    len: 0,
+};
+
+/// Saves the kernel command line. Must be called only once, by the boot code.
+pub fn init_cmdline(cmdline: &str) {
+    CMDLINE.init_by(Cmdline::new(cmdline));
+}
+
+/// Returns the kernel command line, which is empty if the boot loader did not
+/// provide one.
+pub fn cmdline() -> &'static Cmdline {
+    CMDLINE.try_get().unwrap_or(&EMPTY_CMDLINE)
+}
+
+/// A parsed view of the kernel command line.
+pub struct Cmdline {
+    buf: [u8; COMMAND_LINE_SIZE],
+    len: usize,
+}
+
+impl Cmdline {
+    /// Copies `cmdline`, truncating it to [`COMMAND_LINE_SIZE`] bytes.
+    pub fn new(cmdline: &str) -> Self {
+        let mut len = cmdline.len().min(COMMAND_LINE_SIZE);
+        while !cmdline.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut buf = [0; COMMAND_LINE_SIZE];
+        buf[..len].copy_from_slice(&cmdline.as_bytes()[..len]);
+        Self { buf, len }
+    }
+
+    /// The raw command line.
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+
+    /// Iterates over the kernel parameters (those before `--`) as
+    /// `(key, value)` pairs. Surrounding quotes are removed from the values.
+    pub fn params(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        Words::new(self.as_str())
+            .take_while(|word| *word != "--")
+            .map(|word| match word.split_once('=') {
+                Some((key, value)) => (unquote(key), Some(unquote(value))),
+                None => (unquote(word), None),
+            })
+    }
+
+    /// Returns the value of the parameter `key`. If it is given several times,
+    /// the last one wins.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params()
+            .filter(|(k, _)| *k == key)
+            .filter_map(|(_, value)| value)
+            .last()
+    }
+
+    /// Whether the parameter `key` is present, with or without a value.
+    pub fn contains(&self, key: &str) -> bool {
+        self.params().any(|(k, _)| k == key)
+    }
+
+    /// Iterates over the arguments after `--`, which are passed to init as is.
+    pub fn init_args(&self) -> impl Iterator<Item = &str> {
+        Words::new(self.as_str())
+            .skip_while(|word| *word != "--")
+            .skip(1)
+            .map(unquote)
+    }
+}
+
+/// Splits the command line at spaces outside of double quotes.
+struct Words<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Words<'a> {
+    fn new(s: &'a str) -> Self {
+        Self { rest: s }
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let s = self.rest.trim_start();
+        if s.is_empty() {
+            self.rest = s;
+            return None;
+        }
+        let mut in_quote = false;
+        let end = s
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    in_quote = !in_quote;
+                }
+                c.is_whitespace() && !in_quote
+            })
+            .map_or(s.len(), |(i, _)| i);
+        self.rest = &s[end..];
+        Some(&s[..end])
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.strip_prefix('"').unwrap_or(s);
+    s.strip_suffix('"').unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmdline_params() {
+        let cmdline = Cmdline::new("  root=/dev/vda rootfstype=ext4 quiet loglevel=3 loglevel=7 ");
+        assert_eq!(cmdline.get("root"), Some("/dev/vda"));
+        assert_eq!(cmdline.get("rootfstype"), Some("ext4"));
+        assert_eq!(cmdline.get("loglevel"), Some("7"));
+        assert_eq!(cmdline.get("quiet"), None);
+        assert!(cmdline.contains("quiet"));
+        assert!(!cmdline.contains("init"));
+        assert_eq!(cmdline.params().count(), 5);
+    }
+
+    #[test]
+    fn test_cmdline_quotes() {
+        let cmdline = Cmdline::new("init=/bin/sh msg=\"hello world\" \"a b\"");
+        assert_eq!(cmdline.get("init"), Some("/bin/sh"));
+        assert_eq!(cmdline.get("msg"), Some("hello world"));
+        assert!(cmdline.contains("a b"));
+    }
+
+    #[test]
+    fn test_cmdline_init_args() {
+        let cmdline = Cmdline::new("console=ttyS0 -- -c \"echo hi\" x=1");
+        assert_eq!(cmdline.get("x"), None);
+        let mut args = cmdline.init_args();
+        assert_eq!(args.next(), Some("-c"));
+        assert_eq!(args.next(), Some("echo hi"));
+        assert_eq!(args.next(), Some("x=1"));
+        assert_eq!(args.next(), None);
+        assert_eq!(Cmdline::new("a b").init_args().count(), 0);
+    }
+
+    #[test]
+    fn test_cmdline_truncate() {
+        let long = "é".repeat(COMMAND_LINE_SIZE);
+        let cmdline = Cmdline::new(&long);
+        assert!(cmdline.as_str().len() <= COMMAND_LINE_SIZE);
+        assert!(cmdline.as_str().chars().all(|c| c == 'é'));
+        assert_eq!(Cmdline::new("").params().count(), 0);
+    }
+}
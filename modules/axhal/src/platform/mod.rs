@@ -1,5 +1,8 @@
 //! Platform-specific operations.
 
+mod cmdline;
+pub use self::cmdline::{cmdline, init_cmdline, Cmdline, COMMAND_LINE_SIZE};
+
 cfg_if::cfg_if! {
     if #[cfg(all(target_arch = "aarch64", any(
         platform_family = "aarch64-qemu-virt",
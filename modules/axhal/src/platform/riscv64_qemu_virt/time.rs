@@ -8,10 +8,10 @@ pub fn current_ticks() -> u64 {
     time::read() as u64
 }
 
-/// Converts hardware ticks to nanoseconds.
+/// Converts hardware ticks to nanoseconds, saturating on overflow.
 #[inline]
 pub const fn ticks_to_nanos(ticks: u64) -> u64 {
-    ticks * NANOS_PER_TICK
+    ticks.saturating_mul(NANOS_PER_TICK)
 }
 
 /// Converts nanoseconds to hardware ticks.
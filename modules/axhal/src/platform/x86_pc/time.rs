@@ -14,14 +14,16 @@ pub fn current_ticks() -> u64 {
     unsafe { core::arch::x86_64::_rdtsc() - INIT_TICK }
 }
 
-/// Converts hardware ticks to nanoseconds.
+/// Converts hardware ticks to nanoseconds, saturating on overflow.
 pub fn ticks_to_nanos(ticks: u64) -> u64 {
-    ticks * 1_000 / unsafe { CPU_FREQ_MHZ }
+    let nanos = ticks as u128 * 1_000 / unsafe { CPU_FREQ_MHZ } as u128;
+    u64::try_from(nanos).unwrap_or(u64::MAX)
 }
 
-/// Converts nanoseconds to hardware ticks.
+/// Converts nanoseconds to hardware ticks, saturating on overflow.
 pub fn nanos_to_ticks(nanos: u64) -> u64 {
-    nanos * unsafe { CPU_FREQ_MHZ } / 1_000
+    let ticks = nanos as u128 * unsafe { CPU_FREQ_MHZ } as u128 / 1_000;
+    u64::try_from(ticks).unwrap_or(u64::MAX)
 }
 
 /// Set a one-shot timer.
@@ -10,9 +10,9 @@ pub type TimeValue = Duration;
 
 #[cfg(feature = "irq")]
 pub use crate::platform::irq::TIMER_IRQ_NUM;
-#[cfg(feature = "irq")]
-pub use crate::platform::time::set_oneshot_timer;
-pub use crate::platform::time::{current_ticks, nanos_to_ticks, ticks_to_nanos};
+pub use crate::platform::time::{nanos_to_ticks, ticks_to_nanos};
+
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// Number of milliseconds in a second.
 pub const MILLIS_PER_SEC: u64 = 1_000;
@@ -25,9 +25,113 @@ pub const NANOS_PER_MILLIS: u64 = 1_000_000;
 /// Number of nanoseconds in a microsecond.
 pub const NANOS_PER_MICROS: u64 = 1_000;
 
+/// A source of the system clock.
+///
+/// All time reads in the kernel (the timer tick, sleeps, timers, `clock_gettime`,
+/// etc.) go through the selected clock source, so replacing it changes the time
+/// seen by the whole system.
+pub trait ClockSource: Sync {
+    /// Returns the current clock time in hardware ticks.
+    fn current_ticks(&self) -> u64;
+
+    /// Returns the current clock time in nanoseconds.
+    fn current_nanos(&self) -> u64 {
+        ticks_to_nanos(self.current_ticks())
+    }
+}
+
+/// The clock source backed by the hardware timer (e.g. the `time` CSR on RISC-V).
+///
+/// It is used unless the `virtual-clock` feature is enabled.
+pub struct HardwareClock;
+
+impl ClockSource for HardwareClock {
+    fn current_ticks(&self) -> u64 {
+        crate::platform::time::current_ticks()
+    }
+}
+
+/// A virtual clock that starts from zero and only moves forward when
+/// [`VirtualClock::advance`] is called.
+///
+/// Tests use it to drive timers and sleeps deterministically instead of
+/// depending on the real time of the machine.
+#[cfg(feature = "virtual-clock")]
+pub struct VirtualClock {
+    nanos: AtomicU64,
+}
+
+#[cfg(feature = "virtual-clock")]
+impl VirtualClock {
+    /// Creates a virtual clock at time zero.
+    pub const fn new() -> Self {
+        Self {
+            nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Moves the clock forward by `nanos` nanoseconds, returns the new time.
+    pub fn advance(&self, nanos: u64) -> u64 {
+        self.nanos.fetch_add(nanos, Ordering::AcqRel) + nanos
+    }
+}
+
+#[cfg(feature = "virtual-clock")]
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "virtual-clock")]
+impl ClockSource for VirtualClock {
+    fn current_ticks(&self) -> u64 {
+        nanos_to_ticks(self.current_nanos())
+    }
+
+    fn current_nanos(&self) -> u64 {
+        self.nanos.load(Ordering::Acquire)
+    }
+}
+
+/// The virtual clock used as the system clock when `virtual-clock` is enabled.
+#[cfg(feature = "virtual-clock")]
+pub static VIRTUAL_CLOCK: VirtualClock = VirtualClock::new();
+
+/// Returns the clock source selected at build time.
+pub fn clock_source() -> &'static dyn ClockSource {
+    #[cfg(feature = "virtual-clock")]
+    {
+        &VIRTUAL_CLOCK
+    }
+    #[cfg(not(feature = "virtual-clock"))]
+    {
+        &HardwareClock
+    }
+}
+
+/// Returns the current clock time in ticks of the selected clock source.
+pub fn current_ticks() -> u64 {
+    clock_source().current_ticks()
+}
+
 /// Returns the current clock time in nanoseconds.
 pub fn current_time_nanos() -> u64 {
-    ticks_to_nanos(current_ticks())
+    clock_source().current_nanos()
+}
+
+/// Set a one-shot timer.
+///
+/// A timer interrupt will be triggered at the given deadline of the selected
+/// clock source. With the virtual clock, the deadline is converted to the same
+/// distance from now on the hardware timer, so the periodic tick keeps running
+/// while timer events only expire when the virtual clock reaches them.
+#[cfg(feature = "irq")]
+pub fn set_oneshot_timer(deadline: TimeValue) {
+    #[cfg(feature = "virtual-clock")]
+    let deadline = TimeValue::from_nanos(HardwareClock.current_nanos())
+        .saturating_add(deadline.saturating_sub(current_time()));
+    crate::platform::time::set_oneshot_timer(time_to_nanos(deadline));
 }
 
 /// Returns the current clock time in [`TimeValue`].
@@ -35,6 +139,64 @@ pub fn current_time() -> TimeValue {
     TimeValue::from_nanos(current_time_nanos())
 }
 
+/// Converts a [`TimeValue`] to nanoseconds, saturating at `u64::MAX` (about
+/// 584 years) instead of wrapping.
+pub fn time_to_nanos(time: TimeValue) -> u64 {
+    u64::try_from(time.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Converts nanoseconds to hardware ticks, rounding up.
+///
+/// [`nanos_to_ticks`] truncates, so a deadline or timeout converted with it
+/// may expire up to one tick early. Waits must never end before the requested
+/// time, so they use this one instead.
+pub fn nanos_to_ticks_ceil(nanos: u64) -> u64 {
+    ceil_ticks(nanos, nanos_to_ticks, ticks_to_nanos)
+}
+
+/// Converts a [`TimeValue`] to hardware ticks, rounding up and saturating.
+pub fn time_to_ticks(time: TimeValue) -> u64 {
+    nanos_to_ticks_ceil(time_to_nanos(time))
+}
+
+fn ceil_ticks(nanos: u64, to_ticks: impl Fn(u64) -> u64, to_nanos: impl Fn(u64) -> u64) -> u64 {
+    let ticks = to_ticks(nanos);
+    if to_nanos(ticks) < nanos {
+        ticks.saturating_add(1)
+    } else {
+        ticks
+    }
+}
+
+/// The wall-clock time at which the system clock reads zero, in nanoseconds
+/// since the Unix epoch.
+static EPOCH_OFFSET_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the wall-clock time at which the system clock reads zero, in
+/// nanoseconds since the Unix epoch.
+///
+/// It is usually set once at boot (e.g. from the `epoch=` kernel parameter),
+/// and only affects [`wall_time`], not the monotonic clock.
+pub fn set_epoch_offset_nanos(nanos: u64) {
+    EPOCH_OFFSET_NANOS.store(nanos, Ordering::Release);
+}
+
+/// Returns the wall-clock time at which the system clock reads zero, in
+/// nanoseconds since the Unix epoch.
+pub fn epoch_offset_nanos() -> u64 {
+    EPOCH_OFFSET_NANOS.load(Ordering::Acquire)
+}
+
+/// Returns the current wall-clock time in nanoseconds since the Unix epoch.
+pub fn wall_time_nanos() -> u64 {
+    epoch_offset_nanos() + current_time_nanos()
+}
+
+/// Returns the current wall-clock time since the Unix epoch in [`TimeValue`].
+pub fn wall_time() -> TimeValue {
+    TimeValue::from_nanos(wall_time_nanos())
+}
+
 /// Busy waiting for the given duration.
 pub fn busy_wait(dur: Duration) {
     busy_wait_until(current_time() + dur);
@@ -46,3 +208,44 @@ pub fn busy_wait_until(deadline: TimeValue) {
         core::hint::spin_loop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3 ns tick, so most nanosecond values fall between two ticks.
+    fn to_ticks(nanos: u64) -> u64 {
+        nanos / 3
+    }
+
+    fn to_nanos(ticks: u64) -> u64 {
+        ticks.saturating_mul(3)
+    }
+
+    #[test]
+    fn test_ceil_ticks() {
+        assert_eq!(ceil_ticks(0, to_ticks, to_nanos), 0);
+        assert_eq!(ceil_ticks(1, to_ticks, to_nanos), 1);
+        assert_eq!(ceil_ticks(3, to_ticks, to_nanos), 1);
+        assert_eq!(ceil_ticks(4, to_ticks, to_nanos), 2);
+        // the rounded up wait is never shorter than requested
+        for nanos in 0..100 {
+            assert!(to_nanos(ceil_ticks(nanos, to_ticks, to_nanos)) >= nanos);
+        }
+        assert_eq!(ceil_ticks(u64::MAX, to_ticks, to_nanos), u64::MAX / 3 + 1);
+        assert_eq!(ceil_ticks(u64::MAX, |n| n, |t| t), u64::MAX);
+    }
+
+    #[test]
+    fn test_time_to_nanos() {
+        assert_eq!(time_to_nanos(TimeValue::from_nanos(1)), 1);
+        assert_eq!(time_to_nanos(TimeValue::new(2, 5)), 2 * NANOS_PER_SEC + 5);
+        assert_eq!(time_to_nanos(TimeValue::from_nanos(u64::MAX)), u64::MAX);
+        // past the 2^64 ns horizon the result saturates instead of wrapping
+        assert_eq!(
+            time_to_nanos(TimeValue::from_nanos(u64::MAX) + TimeValue::from_nanos(1)),
+            u64::MAX
+        );
+        assert_eq!(time_to_nanos(TimeValue::MAX), u64::MAX);
+    }
+}
@@ -260,3 +260,46 @@ pub fn set_max_level(level: &str) {
         .unwrap_or(LevelFilter::Off);
     log::set_max_level(lf);
 }
+
+/// Set the maximum log level from the `loglevel=` kernel parameter.
+///
+/// Numeric levels follow the console log level of Linux, where only messages
+/// more important than `level` are printed: `loglevel=4` prints errors,
+/// `loglevel=5` adds warnings, `loglevel=7` adds info and `loglevel=8` adds
+/// debug messages. The level names accepted by [`set_max_level`] work as well.
+/// Invalid values are ignored.
+pub fn set_console_loglevel(level: &str) {
+    if let Some(lf) = console_loglevel_filter(level) {
+        log::set_max_level(lf);
+    }
+}
+
+fn console_loglevel_filter(level: &str) -> Option<LevelFilter> {
+    match level.parse::<u32>() {
+        Ok(0) => Some(LevelFilter::Off),
+        Ok(1..=4) => Some(LevelFilter::Error),
+        Ok(5 | 6) => Some(LevelFilter::Warn),
+        Ok(7) => Some(LevelFilter::Info),
+        Ok(8) => Some(LevelFilter::Debug),
+        Ok(_) => Some(LevelFilter::Trace),
+        Err(_) => LevelFilter::from_str(level).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::console_loglevel_filter;
+    use log::LevelFilter;
+
+    #[test]
+    fn test_console_loglevel() {
+        assert_eq!(console_loglevel_filter("0"), Some(LevelFilter::Off));
+        assert_eq!(console_loglevel_filter("4"), Some(LevelFilter::Error));
+        assert_eq!(console_loglevel_filter("5"), Some(LevelFilter::Warn));
+        assert_eq!(console_loglevel_filter("7"), Some(LevelFilter::Info));
+        assert_eq!(console_loglevel_filter("8"), Some(LevelFilter::Debug));
+        assert_eq!(console_loglevel_filter("15"), Some(LevelFilter::Trace));
+        assert_eq!(console_loglevel_filter("debug"), Some(LevelFilter::Debug));
+        assert_eq!(console_loglevel_filter("loud"), None);
+    }
+}
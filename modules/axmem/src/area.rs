@@ -1,13 +1,17 @@
-use alloc::vec::Vec;
+use alloc::{string::String, sync::Arc, vec::Vec};
 use axalloc::PhysPage;
-use axerrno::AxResult;
+use axerrno::{AxError, AxResult};
 use axhal::{
     mem::{virt_to_phys, VirtAddr, PAGE_SIZE_4K},
-    paging::{MappingFlags, PageSize, PageTable},
+    paging::{MappingFlags, PageSize, PageTable, PagingError},
 };
 use axio::{Seek, SeekFrom};
-use core::ptr::copy_nonoverlapping;
+use core::{ops::Range, ptr::copy_nonoverlapping};
 
+use crate::page_cache::{self, MappedPage};
+use crate::shared::SharedAnon;
+use crate::thp::{self, PAGES_PER_HUGE_PAGE};
+use crate::usage::MappingUsage;
 use crate::MemBackend;
 
 /// A continuous virtual area in user memory.
@@ -16,13 +20,17 @@ use crate::MemBackend;
 /// `Clone` trait won't implemented.
 pub struct MapArea {
     /// phys pages of this area
-    pub pages: Vec<Option<PhysPage>>,
+    pub pages: Vec<Option<MappedPage>>,
     /// start virtual address
     pub vaddr: VirtAddr,
     /// mapping flags of this area
     pub flags: MappingFlags,
     /// whether the area is backed by a file
     pub backend: Option<MemBackend>,
+    /// 是否被 MADV_HUGEPAGE 标记，可以使用透明大页
+    pub hugepage: bool,
+    /// 共享匿名映射的共享对象，私有映射与文件映射为 None
+    pub shared_anon: Option<SharedAnon>,
 }
 
 impl MapArea {
@@ -33,22 +41,46 @@ impl MapArea {
         flags: MappingFlags,
         backend: Option<MemBackend>,
         page_table: &mut PageTable,
-    ) -> Self {
+    ) -> AxResult<Self> {
         let mut pages = Vec::with_capacity(num_pages);
         for _ in 0..num_pages {
             pages.push(None);
         }
 
-        page_table
-            .map_fault_region(start, num_pages * PAGE_SIZE_4K, flags)
-            .unwrap();
+        for page_index in 0..num_pages {
+            let vaddr = start + page_index * PAGE_SIZE_4K;
+            if let Err(err) = page_table.map_fault(vaddr, PageSize::Size4K, flags) {
+                // 例如页表页分配失败，撤销已经建立的部分映射
+                for mapped in 0..page_index {
+                    let _ = page_table.unmap(start + mapped * PAGE_SIZE_4K);
+                }
+                return Err(match err {
+                    PagingError::NoMemory => AxError::NoMemory,
+                    _ => AxError::InvalidInput,
+                });
+            }
+        }
 
-        Self {
+        Ok(Self {
             pages,
             vaddr: start,
             flags,
             backend,
-        }
+            hugepage: false,
+            shared_anon: None,
+        })
+    }
+
+    /// 创建共享匿名映射的区域，页面在缺页时从新建的共享对象中获取
+    pub fn new_shared_anon(
+        start: VirtAddr,
+        num_pages: usize,
+        flags: MappingFlags,
+        page_table: &mut PageTable,
+    ) -> AxResult<Self> {
+        let mut area = Self::new_lazy(start, num_pages, flags, None, page_table)?;
+        area.shared_anon = Some(SharedAnon::new(num_pages));
+        Ok(area)
     }
 
     /// Allocated an area and map it in page table.
@@ -60,7 +92,11 @@ impl MapArea {
         backend: Option<MemBackend>,
         page_table: &mut PageTable,
     ) -> AxResult<Self> {
-        let pages = PhysPage::alloc_contiguous(num_pages, PAGE_SIZE_4K, data)?;
+        let pages: Vec<Option<MappedPage>> =
+            PhysPage::alloc_contiguous(num_pages, PAGE_SIZE_4K, data)?
+                .into_iter()
+                .map(|page| page.map(MappedPage::from))
+                .collect();
         debug!(
             "start: {:X?}, size: {:X},  page start: {:X?} flags: {:?}",
             start,
@@ -82,6 +118,8 @@ impl MapArea {
             vaddr: start,
             flags,
             backend,
+            hugepage: false,
+            shared_anon: None,
         })
     }
 
@@ -91,13 +129,15 @@ impl MapArea {
         self.pages.clear();
     }
 
-    /// 如果处理失败，返回false，此时直接退出当前程序
+    /// 如果地址或权限不合法，返回 `BadAddress`，此时直接退出当前程序
+    ///
+    /// 如果物理页帧耗尽，返回 `NoMemory`，由调用者决定是否回收内存后重试
     pub fn handle_page_fault(
         &mut self,
         addr: VirtAddr,
         flags: MappingFlags,
         page_table: &mut PageTable,
-    ) -> bool {
+    ) -> AxResult<()> {
         trace!(
             "handling {:?} page fault in area [{:?}, {:?})",
             addr,
@@ -108,37 +148,107 @@ impl MapArea {
             self.vaddr <= addr && addr < self.end_va(),
             "Try to handle page fault address out of bound"
         );
-        if !self.flags.contains(flags) {
+        // PROT_NONE 的区域不分配页面，任何访问都不合法
+        if !self
+            .flags
+            .intersects(MappingFlags::READ | MappingFlags::WRITE | MappingFlags::EXECUTE)
+            || !self.flags.contains(flags)
+        {
             error!(
                 "Try to access {:?} memory addr: {:?} with {:?} flag",
                 self.flags, addr, flags
             );
-            return false;
+            return Err(AxError::BadAddress);
         }
 
         let page_index = (usize::from(addr) - usize::from(self.vaddr)) / PAGE_SIZE_4K;
         if page_index >= self.pages.len() {
             error!("Phys page index out of bound");
-            return false;
+            return Err(AxError::BadAddress);
         }
-        if self.pages[page_index].is_some() {
+        if let Some(page) = &self.pages[page_index] {
+            // 写入被 MADV_FREE 标记的页面，取消标记后即可继续使用
+            if page.is_lazy_free() && flags.contains(MappingFlags::WRITE) {
+                self.cancel_lazy_free(page_index, page_table);
+                return Ok(());
+            }
+            // 写入共享的只读文件页面，需要复制出私有页面
+            if page.is_shared() && flags.contains(MappingFlags::WRITE) {
+                return self.break_cow(addr, page_index, page_table);
+            }
             error!("Page fault in page already loaded");
-            return false;
+            return Err(AxError::BadAddress);
+        }
+
+        // 共享匿名映射的页面可能已经由其他共享者分配
+        if let Some(shared_anon) = &self.shared_anon {
+            let page = match shared_anon.get_or_alloc(page_index) {
+                Ok(page) => page,
+                Err(_) => {
+                    warn!("Out of phys pages when handling page fault at {:?}", addr);
+                    return Err(AxError::NoMemory);
+                }
+            };
+            page_table
+                .map_overwrite(
+                    addr.align_down_4k(),
+                    virt_to_phys(page.start_vaddr),
+                    PageSize::Size4K,
+                    self.flags,
+                )
+                .expect("Map in page fault handler failed");
+            axhal::arch::flush_tlb(addr.align_down_4k().into());
+            self.pages[page_index] = Some(MappedPage::SharedAnon(page));
+            return Ok(());
+        }
+
+        // 读取或执行私有文件映射时，优先共享页缓存中的干净页面
+        if !flags.contains(MappingFlags::WRITE) {
+            if let Some(backend) = &mut self.backend {
+                if backend.path().is_some() {
+                    let offset = backend.offset() + (page_index * PAGE_SIZE_4K) as u64;
+                    if let Some(page) = page_cache::get_or_load(backend, offset) {
+                        let page = page?;
+                        page_table
+                            .map_overwrite(
+                                addr.align_down_4k(),
+                                virt_to_phys(page.start_vaddr),
+                                PageSize::Size4K,
+                                self.flags - MappingFlags::WRITE,
+                            )
+                            .expect("Map in page fault handler failed");
+                        axhal::arch::flush_tlb(addr.align_down_4k().into());
+                        self.pages[page_index] = Some(MappedPage::Shared(page));
+                        return Ok(());
+                    }
+                }
+            }
         }
 
         debug!("page index {}", page_index);
 
+        if self.try_fault_huge_page(page_index, page_table) {
+            return Ok(());
+        }
+
         // Allocate new page
-        let mut page = PhysPage::alloc().expect("Error allocating new phys page for page fault");
+        let mut page = match PhysPage::alloc() {
+            Ok(page) => page,
+            Err(_) => {
+                warn!("Out of phys pages when handling page fault at {:?}", addr);
+                return Err(AxError::NoMemory);
+            }
+        };
 
         debug!(
             "new phys page virtual (offset) address {:?}",
             page.start_vaddr
         );
 
-        // Read data from backend to fill with 0.
+        // Read data from backend, the part past the end of file is filled with 0.
         match &mut self.backend {
             Some(backend) => {
+                page.fill(0);
                 if backend
                     .read_from_seek(
                         SeekFrom::Current((page_index * PAGE_SIZE_4K) as i64),
@@ -164,10 +274,289 @@ impl MapArea {
             .expect("Map in page fault handler failed");
 
         axhal::arch::flush_tlb(addr.align_down_4k().into());
-        self.pages[page_index] = Some(page);
+        self.pages[page_index] = Some(page.into());
+        self.try_collapse(page_index, page_table);
+        Ok(())
+    }
+
+    /// 页面 `page_index` 所在的 2M 对齐范围，范围不完全位于区域内或区域不能使用透明大页时返回 None
+    fn huge_page_range(&self, page_index: usize) -> Option<Range<usize>> {
+        if !self.hugepage {
+            return None;
+        }
+        self.collapsible_range(page_index)
+    }
+
+    /// 页面 `page_index` 所在的 2M 对齐范围，不考虑区域是否被 MADV_HUGEPAGE 标记
+    ///
+    /// 只有私有匿名的可写区域能够使用大页，范围不完全位于区域内时返回 None
+    fn collapsible_range(&self, page_index: usize) -> Option<Range<usize>> {
+        if self.backend.is_some()
+            || self.shared_anon.is_some()
+            || !self.flags.contains(MappingFlags::WRITE)
+        {
+            return None;
+        }
+        let vaddr = usize::from(self.vaddr) + page_index * PAGE_SIZE_4K;
+        let huge_start = vaddr & !(PageSize::Size2M as usize - 1);
+        let huge_end = huge_start + PageSize::Size2M as usize;
+        if huge_start < usize::from(self.vaddr) || huge_end > usize::from(self.end_va()) {
+            return None;
+        }
+        let first = (huge_start - usize::from(self.vaddr)) / PAGE_SIZE_4K;
+        Some(first..first + PAGES_PER_HUGE_PAGE)
+    }
+
+    /// 将 `range` 中的页面换成 `pages`，并以一个大页映射
+    fn map_huge_page(
+        &mut self,
+        range: Range<usize>,
+        pages: Vec<PhysPage>,
+        page_table: &mut PageTable,
+    ) {
+        let vaddr = self.vaddr + range.start * PAGE_SIZE_4K;
+        page_table
+            .map_huge_overwrite(vaddr, virt_to_phys(pages[0].start_vaddr), self.flags)
+            .expect("Map huge page failed");
+        axhal::arch::flush_tlb(None);
+        for (slot, page) in self.pages[range].iter_mut().zip(pages) {
+            *slot = Some(page.into());
+        }
+    }
+
+    /// 缺页的页面所在的 2M 范围可以使用透明大页且还没有分配任何页面时，直接分配一个大页
+    ///
+    /// 分配不到连续的物理内存时返回 false，由调用者分配小页面
+    fn try_fault_huge_page(&mut self, page_index: usize, page_table: &mut PageTable) -> bool {
+        let Some(range) = self.huge_page_range(page_index) else {
+            return false;
+        };
+        if self.pages[range.clone()].iter().any(|page| page.is_some()) {
+            return false;
+        }
+        let Some(pages) = thp::alloc_huge_pages() else {
+            return false;
+        };
+        self.map_huge_page(range, pages, page_table);
+        thp::record_fault_alloc();
         true
     }
 
+    /// 页面 `page_index` 所在的 2M 范围中的小页面都已填充时，将它们合并为一个大页
+    ///
+    /// 范围中含有共享页面或 MADV_FREE 页面、已经是大页或者分配不到连续的物理内存时不做处理
+    fn try_collapse(&mut self, page_index: usize, page_table: &mut PageTable) {
+        if let Some(range) = self.huge_page_range(page_index) {
+            let _ = self.collapse_range(range, page_table);
+        }
+    }
+
+    /// 将 `range` 中的 512 个小页面复制到一个新分配的大页中，以一个大页映射，并释放原来的页面
+    ///
+    /// 范围中有尚未填充、共享或被 MADV_FREE 标记的页面时返回 `InvalidInput`，分配不到连续的
+    /// 物理内存时返回 `NoMemory`。范围已经是大页时不做处理
+    fn collapse_range(&mut self, range: Range<usize>, page_table: &mut PageTable) -> AxResult<()> {
+        if !self.pages[range.clone()]
+            .iter()
+            .all(|page| matches!(page, Some(MappedPage::Private(_))))
+        {
+            return Err(AxError::InvalidInput);
+        }
+        let vaddr = self.vaddr + range.start * PAGE_SIZE_4K;
+        if matches!(page_table.query(vaddr), Ok((_, _, PageSize::Size2M))) {
+            return Ok(());
+        }
+        let mut pages = thp::alloc_huge_pages().ok_or(AxError::NoMemory)?;
+        for (new_page, old_page) in pages.iter_mut().zip(self.pages[range.clone()].iter()) {
+            unsafe {
+                copy_nonoverlapping(
+                    old_page.as_ref().unwrap().as_ptr(),
+                    new_page.as_mut_ptr(),
+                    PAGE_SIZE_4K,
+                );
+            }
+        }
+        self.map_huge_page(range, pages, page_table);
+        thp::record_collapse();
+        Ok(())
+    }
+
+    /// MADV_COLLAPSE：将从 `start` 开始的 2M 对齐范围合并为一个大页，不要求区域被 MADV_HUGEPAGE 标记
+    ///
+    /// 范围不完全位于区域内、区域不是私有匿名的可写区域或者范围中有尚未填充的页面时返回
+    /// `InvalidInput`，分配不到连续的物理内存时返回 `NoMemory`
+    pub fn collapse(&mut self, start: VirtAddr, page_table: &mut PageTable) -> AxResult<()> {
+        let page_index = (usize::from(start) - usize::from(self.vaddr)) / PAGE_SIZE_4K;
+        match self.collapsible_range(page_index) {
+            Some(range) if range.start == page_index => self.collapse_range(range, page_table),
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+
+    /// 合并区域中所有已经填满的 2M 范围，在区域被 MADV_HUGEPAGE 标记时调用
+    pub fn collapse_all(&mut self, page_table: &mut PageTable) {
+        let mut page_index = 0;
+        while page_index < self.pages.len() {
+            match self.huge_page_range(page_index) {
+                Some(range) => {
+                    self.try_collapse(page_index, page_table);
+                    page_index = range.end;
+                }
+                None => page_index += 1,
+            }
+        }
+    }
+
+    /// 将 [start, end) 中的大页拆分为小页面，之后可以逐页修改这些页面的映射
+    fn split_huge_pages(&self, start: VirtAddr, end: VirtAddr, page_table: &mut PageTable) {
+        let range = self.page_range(start, end);
+        let mut page_index = range.start;
+        while page_index < range.end {
+            let vaddr = self.vaddr + page_index * PAGE_SIZE_4K;
+            if matches!(page_table.query(vaddr), Ok((_, _, PageSize::Size2M))) {
+                let _ = page_table.split_huge(vaddr);
+                let huge_end = (usize::from(vaddr) | (PageSize::Size2M as usize - 1)) + 1;
+                page_index = (huge_end - usize::from(self.vaddr)) / PAGE_SIZE_4K;
+            } else {
+                page_index += 1;
+            }
+        }
+    }
+
+    /// 复制共享页面为私有页面，并以区域原本的权限重新映射
+    ///
+    /// 原共享页面的引用计数随之减少
+    fn break_cow(
+        &mut self,
+        addr: VirtAddr,
+        page_index: usize,
+        page_table: &mut PageTable,
+    ) -> AxResult<()> {
+        let mut new_page = PhysPage::alloc()?;
+        let old_page = self.pages[page_index].as_ref().unwrap();
+        unsafe {
+            copy_nonoverlapping(old_page.as_ptr(), new_page.as_mut_ptr(), PAGE_SIZE_4K);
+        }
+        page_table
+            .map_overwrite(
+                addr.align_down_4k(),
+                virt_to_phys(new_page.start_vaddr),
+                PageSize::Size4K,
+                self.flags,
+            )
+            .expect("Map in page fault handler failed");
+        axhal::arch::flush_tlb(addr.align_down_4k().into());
+        self.pages[page_index] = Some(new_page.into());
+        Ok(())
+    }
+
+    /// 取消页面的 MADV_FREE 标记，并以区域原本的权限重新映射
+    fn cancel_lazy_free(&mut self, page_index: usize, page_table: &mut PageTable) {
+        if let Some(MappedPage::LazyFree(page)) = self.pages[page_index].take() {
+            self.pages[page_index] = Some(MappedPage::Private(page));
+        }
+        let vaddr = self.vaddr + page_index * PAGE_SIZE_4K;
+        let _ = page_table.update(vaddr, None, Some(self.flags));
+        axhal::arch::flush_tlb(vaddr.into());
+    }
+
+    /// Whether the page at `addr` is mapped read-only in a writable area, so that a write fault
+    /// has to be handled before writing to it (a shared clean page or a MADV_FREE page).
+    pub fn is_write_protected_page(&self, addr: VirtAddr) -> bool {
+        let page_index = (usize::from(addr) - usize::from(self.vaddr)) / PAGE_SIZE_4K;
+        self.flags.contains(MappingFlags::WRITE)
+            && matches!(self.pages.get(page_index), Some(Some(page)) if page.is_write_protected())
+    }
+
+    /// 区域中与 [start, end) 重叠的页面下标
+    fn page_range(&self, start: VirtAddr, end: VirtAddr) -> Range<usize> {
+        let start = start.max(self.vaddr);
+        let end = end.min(self.end_va());
+        if start >= end {
+            return 0..0;
+        }
+        let base = usize::from(self.vaddr);
+        (usize::from(start) - base) / PAGE_SIZE_4K
+            ..(usize::from(end) - base + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K
+    }
+
+    /// 释放一个页面，并将其重新映射为缺页项，之后的访问重新触发缺页
+    fn drop_page(&mut self, page_index: usize, page_table: &mut PageTable) {
+        let vaddr = self.vaddr + page_index * PAGE_SIZE_4K;
+        let _ = page_table.unmap(vaddr);
+        self.pages[page_index] = None;
+        page_table
+            .map_fault(vaddr, PageSize::Size4K, self.flags)
+            .unwrap();
+    }
+
+    /// MADV_DONTNEED：释放 [start, end) 中已经分配的页面
+    ///
+    /// 之后再访问时，私有匿名映射读出 0，文件映射重新从文件读入。共享文件映射的页面在释放前先写回。
+    /// You need to flush TLB after this function.
+    pub fn discard_pages(&mut self, start: VirtAddr, end: VirtAddr, page_table: &mut PageTable) {
+        let shared_file = self.is_shared_file();
+        self.split_huge_pages(start, end, page_table);
+        for page_index in self.page_range(start, end) {
+            if self.pages[page_index].is_none() {
+                continue;
+            }
+            if shared_file {
+                self.sync_page_with_backend(page_index);
+            }
+            self.drop_page(page_index, page_table);
+        }
+    }
+
+    /// MADV_FREE：将 [start, end) 中已经分配的私有页面标记为可回收，并改为只读映射
+    ///
+    /// 只适用于私有匿名映射，文件映射与共享匿名映射返回 `InvalidInput`。You need to flush TLB
+    /// after this function.
+    pub fn lazy_free_pages(
+        &mut self,
+        start: VirtAddr,
+        end: VirtAddr,
+        page_table: &mut PageTable,
+    ) -> AxResult<()> {
+        if self.backend.is_some() || self.shared_anon.is_some() {
+            return Err(AxError::InvalidInput);
+        }
+        self.split_huge_pages(start, end, page_table);
+        for page_index in self.page_range(start, end) {
+            match self.pages[page_index].take() {
+                Some(MappedPage::Private(page)) => {
+                    let _ = page_table.update(
+                        self.vaddr + page_index * PAGE_SIZE_4K,
+                        None,
+                        Some(self.flags - MappingFlags::WRITE),
+                    );
+                    self.pages[page_index] = Some(MappedPage::LazyFree(page));
+                }
+                page => self.pages[page_index] = page,
+            }
+        }
+        Ok(())
+    }
+
+    /// 回收区域中被 MADV_FREE 标记、之后没有再被写入的页面，返回扫描与回收的页数
+    ///
+    /// 其余已分配的页面仍在使用中，不会被回收。You need to flush TLB after this function.
+    pub fn reclaim_lazy_free(&mut self, page_table: &mut PageTable) -> (usize, usize) {
+        let mut scanned = 0;
+        let mut reclaimed = 0;
+        for page_index in 0..self.pages.len() {
+            let Some(page) = &self.pages[page_index] else {
+                continue;
+            };
+            scanned += 1;
+            if page.is_lazy_free() {
+                self.drop_page(page_index, page_table);
+                reclaimed += 1;
+            }
+        }
+        (scanned, reclaimed)
+    }
+
     /// Sync pages in index back to `self.backend` (if there is one).
     ///
     /// # Panics
@@ -175,6 +564,10 @@ impl MapArea {
     /// Panics if index is out of bounds.
     pub fn sync_page_with_backend(&mut self, page_index: usize) {
         if let Some(page) = &self.pages[page_index] {
+            if page.is_shared() {
+                // 共享页面未被修改过，无需写回
+                return;
+            }
             if let Some(backend) = &mut self.backend {
                 if backend.writable() {
                     let _ = backend
@@ -202,6 +595,9 @@ impl MapArea {
         if let Some(backend) = &mut self.backend {
             let _ = backend.seek(SeekFrom::Current(delete_size as i64)).unwrap();
         }
+        if let Some(shared_anon) = &mut self.shared_anon {
+            *shared_anon = shared_anon.with_delta(delete_pages);
+        }
 
         // remove (dealloc) phys pages
         drop(self.pages.drain(0..delete_pages));
@@ -254,6 +650,10 @@ impl MapArea {
 
                 backend
             }),
+            hugepage: self.hugepage,
+            shared_anon: self.shared_anon.as_ref().map(|shared_anon| {
+                shared_anon.with_delta((addr.as_usize() - self.vaddr.as_usize()) / PAGE_SIZE_4K)
+            }),
         }
     }
 
@@ -296,6 +696,10 @@ impl MapArea {
 
                 backend
             }),
+            hugepage: self.hugepage,
+            shared_anon: self.shared_anon.as_ref().map(|shared_anon| {
+                shared_anon.with_delta((start.as_usize() - self.vaddr.as_usize()) / PAGE_SIZE_4K)
+            }),
         };
 
         let right = Self {
@@ -313,6 +717,10 @@ impl MapArea {
 
                 backend
             }),
+            hugepage: self.hugepage,
+            shared_anon: self.shared_anon.as_ref().map(|shared_anon| {
+                shared_anon.with_delta((end.as_usize() - self.vaddr.as_usize()) / PAGE_SIZE_4K)
+            }),
         };
 
         (mid, right)
@@ -358,6 +766,11 @@ impl MapArea {
 
                 backend
             }),
+            hugepage: self.hugepage,
+            shared_anon: self.shared_anon.as_ref().map(|shared_anon| {
+                shared_anon
+                    .with_delta((right_start.as_usize() - self.vaddr.as_usize()) / PAGE_SIZE_4K)
+            }),
         };
 
         // remove pages
@@ -380,6 +793,64 @@ impl MapArea {
         self.vaddr + self.size()
     }
 
+    /// 是否为共享映射，即共享文件映射或共享匿名映射
+    pub fn is_shared(&self) -> bool {
+        self.is_shared_file() || self.shared_anon.is_some()
+    }
+
+    /// 是否为共享文件映射，写入的内容会写回文件
+    ///
+    /// 私有文件映射的后端带有文件路径，以便在页缓存中共享干净页面
+    pub fn is_shared_file(&self) -> bool {
+        self.backend
+            .as_ref()
+            .is_some_and(|backend| backend.path().is_none())
+    }
+
+    /// 逐页统计区域的内存使用情况，见 [`MappingUsage`]
+    pub fn usage(&mut self, page_table: &PageTable) -> MappingUsage {
+        let shared = self.is_shared();
+        let mut usage =
+            MappingUsage::new(self.vaddr.into(), self.end_va().into(), self.flags, shared);
+        let mut file = None;
+        if let Some(backend) = &mut self.backend {
+            usage.offset = backend.offset();
+            usage.path = backend.path().map(String::from);
+            file = backend.ino().map(|ino| (ino, usage.offset));
+        }
+        usage.hugepage = self.hugepage;
+        let writable = self.flags.contains(MappingFlags::WRITE);
+        for (page_index, page) in self.pages.iter().enumerate() {
+            let Some(page) = page else {
+                continue;
+            };
+            let mut map_count = page.map_count();
+            if let (MappedPage::Shared(page), Some((ino, offset))) = (page, file) {
+                // 页缓存自身持有的引用不计入映射计数
+                let key = (ino, offset + (page_index * PAGE_SIZE_4K) as u64);
+                if page_cache::is_cached(key, page) {
+                    map_count -= 1;
+                }
+            }
+            usage.add_page(
+                map_count,
+                writable && matches!(page, MappedPage::Private(_) | MappedPage::SharedAnon(_)),
+            );
+            if page.is_lazy_free() {
+                usage.lazy_free += PAGE_SIZE_4K;
+            }
+            // 私有映射中不与页缓存共享的页面都是匿名页面
+            if !shared && !page.is_shared() {
+                usage.anonymous += PAGE_SIZE_4K;
+                let vaddr = self.vaddr + page_index * PAGE_SIZE_4K;
+                if matches!(page_table.query(vaddr), Ok((_, _, PageSize::Size2M))) {
+                    usage.anon_huge += PAGE_SIZE_4K;
+                }
+            }
+        }
+        usage
+    }
+
     /// return whether all the pages have been allocated.
     pub fn allocated(&self) -> bool {
         self.pages.iter().all(|page| page.is_some())
@@ -393,8 +864,9 @@ impl MapArea {
 
     /// Fill `self` with `byte`.
     pub fn fill(&mut self, byte: u8) {
+        // 共享的干净页面不属于当前区域，不能直接修改
         self.pages.iter_mut().for_each(|page| {
-            if let Some(page) = page {
+            if let Some(page) = page.as_mut().and_then(|page| page.as_private_mut()) {
                 page.fill(byte);
             }
         });
@@ -427,12 +899,29 @@ impl MapArea {
         page_table
             .update_region(self.vaddr, self.size(), flags)
             .unwrap();
+        // 共享页面与 MADV_FREE 页面需要保持只读，以便写入时触发缺页
+        for (idx, page) in self.pages.iter().enumerate() {
+            if matches!(page, Some(page) if page.is_write_protected()) {
+                let _ = page_table.update(
+                    self.vaddr + idx * PAGE_SIZE_4K,
+                    None,
+                    Some(flags - MappingFlags::WRITE),
+                );
+            }
+        }
     }
     /// Allocating new phys pages and clone it self.
     /// This function will modify the page table as well.
     pub fn clone_alloc(&self, page_table: &mut PageTable) -> AxResult<Self> {
         // All the pages have been allocated. Allocate a contiguous area in phys memory.
-        if self.allocated() {
+        // 含有共享页面或是共享匿名映射时需要逐页处理，以继续共享这些页面
+        if self.allocated()
+            && self.shared_anon.is_none()
+            && !self.pages.iter().flatten().any(|page| page.is_shared())
+        {
+            // as_slice 经由当前地址空间的用户地址读取数据
+            #[cfg(target_arch = "riscv64")]
+            let _guard = axhal::arch::UserMemoryGuard::new();
             MapArea::new_alloc(
                 self.vaddr,
                 self.pages.len(),
@@ -441,6 +930,10 @@ impl MapArea {
                 self.backend.clone(),
                 page_table,
             )
+            .map(|mut area| {
+                area.hugepage = self.hugepage;
+                area
+            })
         } else {
             let pages: Vec<_> = self
                 .pages
@@ -449,6 +942,31 @@ impl MapArea {
                 .map(|(idx, slot)| {
                     let vaddr = self.vaddr + (idx * PAGE_SIZE_4K);
                     match slot.as_ref() {
+                        Some(MappedPage::Shared(page)) => {
+                            page_table
+                                .map(
+                                    vaddr,
+                                    virt_to_phys(page.start_vaddr),
+                                    PageSize::Size4K,
+                                    self.flags - MappingFlags::WRITE,
+                                )
+                                .unwrap();
+
+                            Some(MappedPage::Shared(Arc::clone(page)))
+                        }
+                        // 共享匿名映射的页面与父进程共享，不需要复制
+                        Some(MappedPage::SharedAnon(page)) => {
+                            page_table
+                                .map(
+                                    vaddr,
+                                    virt_to_phys(page.start_vaddr),
+                                    PageSize::Size4K,
+                                    self.flags,
+                                )
+                                .unwrap();
+
+                            Some(MappedPage::SharedAnon(Arc::clone(page)))
+                        }
                         Some(page) => {
                             let mut new_page = PhysPage::alloc().unwrap();
                             unsafe {
@@ -468,7 +986,7 @@ impl MapArea {
                                 )
                                 .unwrap();
 
-                            Some(new_page)
+                            Some(new_page.into())
                         }
                         None => {
                             page_table
@@ -484,6 +1002,8 @@ impl MapArea {
                 vaddr: self.vaddr,
                 flags: self.flags,
                 backend: self.backend.clone(),
+                hugepage: self.hugepage,
+                shared_anon: self.shared_anon.clone(),
             })
         }
     }
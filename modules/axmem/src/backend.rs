@@ -1,11 +1,15 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String};
 use axfs::api::{File, FileExt};
 use axio::{Read, Seek, SeekFrom};
 
+use crate::page_cache::invalidate_page_cache;
+
 /// File backend for Lazy load `MapArea`. `file` should be a file holding a offset value. Normally,
 /// `MemBackend` won't share a file with other things, so we use a `Box` here.
 pub struct MemBackend {
     file: Box<dyn FileExt>,
+    /// 文件路径，用于在页缓存中共享干净页面。为 None 时不参与共享
+    path: Option<String>,
 }
 
 impl MemBackend {
@@ -13,7 +17,30 @@ impl MemBackend {
     pub fn new(mut file: Box<dyn FileExt>, offset: u64) -> Self {
         let _ = file.seek(SeekFrom::Start(offset)).unwrap();
 
-        Self { file }
+        Self { file, path: None }
+    }
+
+    /// Set the path of the backing file, so that clean pages can be shared through the page cache.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// the path of the backing file if it can be shared
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// the inode number of the backing file, used as the key of the page cache
+    pub fn ino(&self) -> Option<u64> {
+        self.file.as_any().downcast_ref::<File>().map(File::ino)
+    }
+
+    /// the offset in the file where the mapping starts
+    pub fn offset(&mut self) -> u64 {
+        self.file
+            .seek(SeekFrom::Current(0))
+            .expect("Error get current pos in file")
     }
 
     /// clone a new `MemBackend` with a delta offset of the file of the original `MemBackend`.
@@ -31,8 +58,14 @@ impl MemBackend {
     }
 
     /// write to the file of the `MemBackend` with a pos offset.
+    ///
+    /// Cached pages of the file are dropped, as they no longer match its content.
     pub fn write_to_seek(&mut self, pos: SeekFrom, buf: &[u8]) -> Result<usize, axio::Error> {
-        self.file.write_to_seek(pos, buf)
+        let written = self.file.write_to_seek(pos, buf)?;
+        if let Some(ino) = self.ino() {
+            invalidate_page_cache(ino);
+        }
+        Ok(written)
     }
 
     /// whether the file of the `MemBackend` is readable.
@@ -57,6 +90,7 @@ impl Clone for MemBackend {
 
         Self {
             file: Box::new(file),
+            path: self.path.clone(),
         }
     }
 }
@@ -0,0 +1,108 @@
+//! 内核地址空间
+//!
+//! 所有用户页表共享内核页表中覆盖内核地址空间的顶级页表项，而不是各自复制一份内核映射。
+//! 这样在已共享的顶级页表项之下新增的内核映射会立即对所有地址空间可见；
+//! 而当内核映射用到了一个新的顶级页表项时，则由 [`map_kernel_region`] 将其同步到所有已存在的用户页表中。
+use alloc::collections::BTreeSet;
+use axerrno::{AxError, AxResult};
+use axhal::{
+    mem::{PhysAddr, VirtAddr},
+    paging::{MappingFlags, PageTable},
+};
+use spinlock::SpinNoIrq;
+
+/// 内核地址空间的起始地址与大小，该范围内的顶级页表项由所有地址空间共享
+#[cfg(target_arch = "x86_64")]
+const KERNEL_SPACE: (usize, usize) = (0xffff_8000_0000_0000, 0x8000_0000_0000);
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const KERNEL_SPACE: (usize, usize) = (0xffff_ffc0_0000_0000, 0x40_0000_0000);
+/// aarch64 下内核地址空间由 TTBR1 单独翻译，用户页表中无需包含内核映射
+#[cfg(target_arch = "aarch64")]
+const KERNEL_SPACE: (usize, usize) = (0xffff_0000_0000_0000, 0);
+
+/// 内核页表
+static KERNEL_PAGE_TABLE: SpinNoIrq<Option<PageTable>> = SpinNoIrq::new(None);
+
+/// 所有共享了内核地址空间的用户页表的根页表物理地址
+static USER_PAGE_TABLES: SpinNoIrq<BTreeSet<usize>> = SpinNoIrq::new(BTreeSet::new());
+
+/// 设置内核页表，应当在创建任何用户地址空间之前调用
+pub fn init_kernel_page_table(page_table: PageTable) {
+    let mut kernel_page_table = KERNEL_PAGE_TABLE.lock();
+    assert!(
+        kernel_page_table.is_none(),
+        "kernel page table is already initialized"
+    );
+    *kernel_page_table = Some(page_table);
+}
+
+/// 内核页表的根页表物理地址
+pub fn kernel_page_table_root() -> PhysAddr {
+    KERNEL_PAGE_TABLE
+        .lock()
+        .as_ref()
+        .expect("kernel page table is not initialized")
+        .root_paddr()
+}
+
+/// 判断地址是否位于内核地址空间中
+pub fn is_kernel_addr(addr: VirtAddr) -> bool {
+    addr.as_usize() >= KERNEL_SPACE.0
+}
+
+/// 让 `page_table` 共享内核页表的顶级页表项，并登记以便之后同步新增的顶级页表项
+///
+/// 页表释放前需要调用 [`unshare_kernel_space`] 取消登记
+pub(crate) fn share_kernel_space(page_table: &PageTable) {
+    let kernel_page_table = KERNEL_PAGE_TABLE.lock();
+    let kernel_page_table = kernel_page_table
+        .as_ref()
+        .expect("kernel page table is not initialized");
+    // 持有内核页表锁期间完成登记，避免与 map_kernel_region 交错而漏掉新的顶级页表项
+    unsafe {
+        kernel_page_table.share_root_entries(
+            page_table.root_paddr(),
+            KERNEL_SPACE.0.into(),
+            KERNEL_SPACE.1,
+        );
+    }
+    USER_PAGE_TABLES
+        .lock()
+        .insert(page_table.root_paddr().as_usize());
+}
+
+/// 取消登记，之后不再向该页表同步内核的顶级页表项
+pub(crate) fn unshare_kernel_space(page_table: &PageTable) {
+    let _kernel_page_table = KERNEL_PAGE_TABLE.lock();
+    USER_PAGE_TABLES
+        .lock()
+        .remove(&page_table.root_paddr().as_usize());
+}
+
+/// 在内核地址空间中新增映射，并对所有地址空间生效
+///
+/// 若映射用到了新的顶级页表项，会将其同步到所有已登记的用户页表中
+pub fn map_kernel_region(
+    vaddr: VirtAddr,
+    paddr: PhysAddr,
+    size: usize,
+    flags: MappingFlags,
+) -> AxResult<()> {
+    if !is_kernel_addr(vaddr) {
+        return Err(AxError::InvalidInput);
+    }
+    let mut kernel_page_table = KERNEL_PAGE_TABLE.lock();
+    let kernel_page_table = kernel_page_table
+        .as_mut()
+        .expect("kernel page table is not initialized");
+    kernel_page_table
+        .map_region(vaddr, paddr, size, flags, true)
+        .map_err(|_| AxError::InvalidInput)?;
+    for root in USER_PAGE_TABLES.lock().iter() {
+        unsafe {
+            kernel_page_table.share_root_entries((*root).into(), vaddr, size);
+        }
+    }
+    axhal::arch::flush_tlb(None);
+    Ok(())
+}
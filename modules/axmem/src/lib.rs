@@ -2,10 +2,28 @@
 #![cfg_attr(not(test), no_std)]
 mod area;
 mod backend;
+mod kernel;
+mod overcommit;
+mod page_cache;
+mod reclaim;
 mod shared;
+mod thp;
+mod usage;
 pub use area::MapArea;
 use axerrno::{AxError, AxResult};
 pub use backend::MemBackend;
+pub use kernel::{
+    init_kernel_page_table, is_kernel_addr, kernel_page_table_root, map_kernel_region,
+};
+pub use overcommit::{
+    accountable, commit_limit_pages, committed_pages, free_pages, overcommit_policy,
+    set_overcommit_policy, total_pages, vm_enough_memory, OvercommitPolicy, OVERCOMMIT_RATIO,
+};
+pub use page_cache::{invalidate_page_cache, page_cache_pages, shrink_page_cache, MappedPage};
+pub use reclaim::{reclaim_stats, ReclaimStats};
+pub use shared::IpcPerm;
+pub use thp::{thp_stats, ThpStats};
+pub use usage::MappingUsage;
 
 extern crate alloc;
 use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
@@ -18,7 +36,7 @@ extern crate log;
 
 use axhal::{
     arch::flush_tlb,
-    mem::{memory_regions, phys_to_virt, PhysAddr, VirtAddr, PAGE_SIZE_4K},
+    mem::{virt_to_phys, PhysAddr, VirtAddr, PAGE_SIZE_4K},
     paging::{MappingFlags, PageSize, PageTable},
 };
 
@@ -36,6 +54,9 @@ pub static SHARED_MEMS: SpinNoIrq<BTreeMap<i32, Arc<SharedMem>>> = SpinNoIrq::ne
 /// The map from key to shmid. It's used to query shmid from key.
 pub static KEY_TO_SHMID: SpinNoIrq<BTreeMap<i32, i32>> = SpinNoIrq::new(BTreeMap::new());
 
+/// 一个地址空间中映射区域数量的上限，与 Linux 中 vm.max_map_count 的默认值相同
+pub const MAX_MAP_COUNT: usize = 65530;
+
 /// PageTable + MemoryArea for a process (task)
 pub struct MemorySet {
     page_table: PageTable,
@@ -43,6 +64,11 @@ pub struct MemorySet {
 
     private_mem: BTreeMap<i32, Arc<SharedMem>>,
     attached_mem: Vec<(VirtAddr, MappingFlags, Arc<SharedMem>)>,
+
+    /// 释放页面之前记录的常驻内存峰值（页数）
+    peak_rss_pages: usize,
+    /// 计入全局 overcommit 提交量的页数
+    committed_pages: usize,
 }
 
 impl MemorySet {
@@ -58,29 +84,25 @@ impl MemorySet {
             owned_mem: BTreeMap::new(),
             private_mem: BTreeMap::new(),
             attached_mem: Vec::new(),
+            peak_rss_pages: 0,
+            committed_pages: 0,
         }
     }
 
     /// Create a new MemorySet with kernel mapped regions.
+    ///
+    /// The kernel mappings are shared with the kernel page table by reference.
     pub fn new_with_kernel_mapped() -> Self {
-        let mut page_table = PageTable::try_new().expect("Error allocating page table.");
-
-        for r in memory_regions() {
-            debug!(
-                "mapping kernel region [0x{:x}, 0x{:x})",
-                usize::from(phys_to_virt(r.paddr)),
-                usize::from(phys_to_virt(r.paddr)) + r.size,
-            );
-            page_table
-                .map_region(phys_to_virt(r.paddr), r.paddr, r.size, r.flags.into(), true)
-                .expect("Error mapping kernel memory");
-        }
+        let page_table = PageTable::try_new().expect("Error allocating page table.");
+        kernel::share_kernel_space(&page_table);
 
         Self {
             page_table,
             owned_mem: BTreeMap::new(),
             private_mem: BTreeMap::new(),
             attached_mem: Vec::new(),
+            peak_rss_pages: 0,
+            committed_pages: 0,
         }
     }
 
@@ -105,7 +127,7 @@ impl MemorySet {
         flags: MappingFlags,
         data: Option<&[u8]>,
         backend: Option<MemBackend>,
-    ) {
+    ) -> AxResult<()> {
         let num_pages = (size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K;
 
         let area = match data {
@@ -116,8 +138,7 @@ impl MemorySet {
                 Some(data),
                 backend,
                 &mut self.page_table,
-            )
-            .unwrap(),
+            )?,
             // None => match backend {
             //     Some(backend) => {
             //         MapArea::new_lazy(vaddr, num_pages, flags, Some(backend), &mut self.page_table)
@@ -127,7 +148,7 @@ impl MemorySet {
             //             .unwrap()
             //     }
             // },
-            None => MapArea::new_lazy(vaddr, num_pages, flags, backend, &mut self.page_table),
+            None => MapArea::new_lazy(vaddr, num_pages, flags, backend, &mut self.page_table)?,
         };
 
         info!(
@@ -141,6 +162,8 @@ impl MemorySet {
 
         // self.owned_mem.insert(area.vaddr.into(), area);
         assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
+        self.sync_commit();
+        Ok(())
     }
 
     /// Make [start, end) unmapped and dealloced. You need to flush TLB after this.
@@ -149,6 +172,8 @@ impl MemorySet {
     pub fn split_for_area(&mut self, start: VirtAddr, size: usize) {
         let end = start + size;
         assert!(end.is_aligned_4k());
+        self.update_peak_rss();
+        self.split_huge_at_bounds(start, end);
 
         // Note: Some areas will have to shrink its left part, so its key in BTree (start vaddr) have to change.
         // We get all the overlapped areas out first.
@@ -215,6 +240,7 @@ impl MemorySet {
                 assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
             }
         }
+        self.sync_commit();
     }
 
     /// Find a free area with given start virtual address and size. Return the start address of the area.
@@ -266,26 +292,46 @@ impl MemorySet {
             backend.is_some()
         );
 
-        let addr = if fixed {
-            self.split_for_area(start, size);
-
-            self.new_region(start, size, flags, None, backend);
-
-            axhal::arch::flush_tlb(None);
+        // 可写的私有映射需要按 overcommit 策略检查并计入提交量
+        let shared = backend
+            .as_ref()
+            .is_some_and(|backend| backend.path().is_none());
+        let charged = if accountable(flags, shared) {
+            size / PAGE_SIZE_4K
+        } else {
+            0
+        };
+        if vm_enough_memory(charged).is_err() {
+            return -1;
+        }
 
-            start.as_usize() as isize
+        let start = if fixed {
+            self.split_for_area(start, size);
+            Some(start)
         } else {
             info!("find free area");
-            let start = self.find_free_area(start, size);
-
-            match start {
-                Some(start) => {
-                    info!("found area [{:?}, {:?})", start, start + size);
-                    self.new_region(start, size, flags, None, backend);
-                    flush_tlb(None);
-                    start.as_usize() as isize
+            self.find_free_area(start, size)
+        };
+        let addr = match start {
+            Some(start) => {
+                info!("found area [{:?}, {:?})", start, start + size);
+                self.committed_pages += charged;
+                match self.new_region(start, size, flags, None, backend) {
+                    Ok(()) => {
+                        flush_tlb(None);
+                        start.as_usize() as isize
+                    }
+                    // 页表页分配失败
+                    Err(_) => {
+                        self.committed_pages -= charged;
+                        overcommit::vm_unacct_memory(charged);
+                        -1
+                    }
                 }
-                None => -1,
+            }
+            None => {
+                overcommit::vm_unacct_memory(charged);
+                -1
             }
         };
 
@@ -294,13 +340,78 @@ impl MemorySet {
         addr
     }
 
+    /// 建立共享匿名映射（MAP_SHARED | MAP_ANONYMOUS）。You need to flush tlb after this.
+    ///
+    /// 映射由一个匿名的共享对象支持，页面在第一次访问时才分配。fork 时子进程与父进程共享
+    /// 同一个对象，而不是写时复制。映射可以被部分解除，所有映射都解除后物理页随之释放。
+    pub fn mmap_shared_anonymous(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        fixed: bool,
+    ) -> AxResult<VirtAddr> {
+        let size = (size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K * PAGE_SIZE_4K;
+        let start = if fixed {
+            self.split_for_area(start, size);
+            self.detach_shared_range(start, size);
+            start
+        } else {
+            self.find_free_area(start, size).ok_or(AxError::NoMemory)?
+        };
+        let area =
+            MapArea::new_shared_anon(start, size / PAGE_SIZE_4K, flags, &mut self.page_table)?;
+        assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
+        Ok(start)
+    }
+
     /// munmap. You need to flush TLB after this.
     pub fn munmap(&mut self, start: VirtAddr, size: usize) {
         // align up to 4k
         let size = (size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K * PAGE_SIZE_4K;
         info!("[munmap] [{:?}, {:?})", start, (start + size).align_up_4k());
 
+        self.writeback_shared_file_range(start, size);
         self.split_for_area(start, size);
+        self.detach_shared_range(start, size);
+    }
+
+    /// 将 [start, start + size) 中共享文件映射的页面写回文件
+    ///
+    /// 私有文件映射的后端带有页缓存路径，其修改不应写回文件，因此跳过
+    fn writeback_shared_file_range(&mut self, start: VirtAddr, size: usize) {
+        let end = start + size;
+        for area in self.owned_mem.values_mut() {
+            let is_shared_file = area
+                .backend
+                .as_ref()
+                .is_some_and(|backend| backend.path().is_none());
+            if !is_shared_file || !area.overlap_with(start, end) {
+                continue;
+            }
+            for page_index in 0..area.pages.len() {
+                let page_vaddr = area.vaddr + page_index * PAGE_SIZE_4K;
+                if page_vaddr >= start && page_vaddr < end && area.pages[page_index].is_some() {
+                    area.sync_page_with_backend(page_index);
+                }
+            }
+        }
+    }
+
+    /// 解除完全位于 [start, start + size) 中的共享内存映射
+    ///
+    /// TODO: 暂不支持只解除共享内存映射的一部分
+    fn detach_shared_range(&mut self, start: VirtAddr, size: usize) {
+        let end = start + size;
+        let page_table = &mut self.page_table;
+        self.attached_mem.retain(|(addr, _, mem)| {
+            let contained = start <= *addr && *addr + mem.size() <= end;
+            if contained {
+                page_table.unmap_region(*addr, mem.size()).unwrap();
+                mem.dec_map_count();
+            }
+            !contained
+        });
     }
 
     /// msync
@@ -322,12 +433,63 @@ impl MemorySet {
         }
     }
 
+    /// MADV_DONTNEED，释放 [start, start + size) 中已经分配的页面。You need to flush TLB after this.
+    pub fn discard_pages(&mut self, start: VirtAddr, size: usize) {
+        let end = start + size;
+        self.update_peak_rss();
+        for area in self.owned_mem.values_mut() {
+            if area.overlap_with(start, end) {
+                area.discard_pages(start, end, &mut self.page_table);
+            }
+        }
+    }
+
+    /// MADV_FREE，将 [start, start + size) 中的私有匿名页面标记为可回收。You need to flush TLB
+    /// after this.
+    ///
+    /// 范围内含有文件映射或共享匿名映射时返回 `InvalidInput`，此时不做任何修改。
+    pub fn lazy_free_pages(&mut self, start: VirtAddr, size: usize) -> AxResult<()> {
+        let end = start + size;
+        if self.owned_mem.values().any(|area| {
+            area.overlap_with(start, end) && (area.backend.is_some() || area.shared_anon.is_some())
+        }) {
+            return Err(AxError::InvalidInput);
+        }
+        for area in self.owned_mem.values_mut() {
+            if area.overlap_with(start, end) {
+                area.lazy_free_pages(start, end, &mut self.page_table)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 回收地址空间中被 MADV_FREE 标记的页面，返回回收的页数
+    pub fn reclaim_lazy_free(&mut self) -> usize {
+        self.update_peak_rss();
+        let mut scanned = 0;
+        let mut reclaimed = 0;
+        for area in self.owned_mem.values_mut() {
+            let (area_scanned, area_reclaimed) = area.reclaim_lazy_free(&mut self.page_table);
+            scanned += area_scanned;
+            reclaimed += area_reclaimed;
+        }
+        reclaim::record_reclaim(scanned, reclaimed);
+        if reclaimed > 0 {
+            flush_tlb(None);
+        }
+        reclaimed
+    }
+
     /// Edit the page table to update flags in given virt address segment. You need to flush TLB
     /// after calling this function.
     ///
     /// NOTE: It's possible that this function will break map areas into two for different mapping
     /// flag settings.
-    pub fn mprotect(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) {
+    ///
+    /// 以下情况返回错误，此时不做任何修改：
+    /// - 范围中有未映射的部分，或分割区域后区域数量超过 [`MAX_MAP_COUNT`]，返回 `NoMemory`
+    /// - 为以只读方式打开的共享文件映射加上写权限，返回 `PermissionDenied`
+    pub fn mprotect(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult<()> {
         info!(
             "[mprotect] addr: [{:?}, {:?}), flags: {:?}",
             start,
@@ -337,6 +499,62 @@ impl MemorySet {
         let end = start + size;
         assert!(end.is_aligned_4k());
 
+        // 共享内存的映射不在 owned_mem 中，但也不算作未映射的部分
+        let mut ranges: Vec<(VirtAddr, VirtAddr)> = self
+            .attached_mem
+            .iter()
+            .map(|(addr, _, mem)| (*addr, *addr + mem.size()))
+            .collect();
+        let mut new_areas = 0;
+        for area in self.owned_mem.values() {
+            if !area.overlap_with(start, end) {
+                continue;
+            }
+            if flags.contains(MappingFlags::WRITE)
+                && area.is_shared_file()
+                && !area
+                    .backend
+                    .as_ref()
+                    .is_some_and(|backend| backend.writable())
+            {
+                return Err(AxError::PermissionDenied);
+            }
+            new_areas += (area.vaddr < start) as usize + (end < area.end_va()) as usize;
+            ranges.push((area.vaddr, area.end_va()));
+        }
+        ranges.sort_unstable_by_key(|(range_start, _)| *range_start);
+        let mut covered = start;
+        for (range_start, range_end) in ranges {
+            if range_end <= covered || range_start >= end {
+                continue;
+            }
+            if range_start > covered {
+                return Err(AxError::NoMemory);
+            }
+            covered = range_end;
+        }
+        if covered < end {
+            return Err(AxError::NoMemory);
+        }
+        if self.owned_mem.len() + new_areas > MAX_MAP_COUNT {
+            return Err(AxError::NoMemory);
+        }
+        // 为私有映射加上写权限时，新增的可写部分需要计入提交量
+        let charged: usize = self
+            .owned_mem
+            .values()
+            .filter(|area| {
+                area.overlap_with(start, end)
+                    && !accountable(area.flags, area.is_shared())
+                    && accountable(flags, area.is_shared())
+            })
+            .map(|area| area.end_va().min(end).as_usize() - area.vaddr.max(start).as_usize())
+            .sum::<usize>()
+            / PAGE_SIZE_4K;
+        vm_enough_memory(charged)?;
+        self.committed_pages += charged;
+        self.split_huge_at_bounds(start, end);
+
         flush_tlb(None);
         //self.manual_alloc_range_for_lazy(start, end - 1).unwrap();
         // NOTE: There will be new areas but all old aree's start address won't change. But we
@@ -383,7 +601,82 @@ impl MemorySet {
 
             assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
         }
+        self.sync_commit();
         axhal::arch::flush_tlb(None);
+        Ok(())
+    }
+
+    /// 拆分跨越 `start` 或 `end` 的大页，之后才能在这两个地址处分割区域或修改映射
+    fn split_huge_at_bounds(&mut self, start: VirtAddr, end: VirtAddr) {
+        for addr in [start, end] {
+            if !addr.is_aligned(PageSize::Size2M) {
+                let _ = self.page_table.split_huge(addr);
+            }
+        }
+    }
+
+    /// MADV_HUGEPAGE 与 MADV_NOHUGEPAGE：设置 [start, start + size) 中的区域能否使用透明大页。
+    /// You need to flush TLB after this.
+    ///
+    /// 只有一部分在范围内的区域先被分割。允许使用时，立即合并其中已经填满的 2M 范围；
+    /// 禁止使用时，已有的大页保持不变。
+    pub fn set_hugepage(&mut self, start: VirtAddr, size: usize, enable: bool) {
+        let end = start + size;
+        self.split_huge_at_bounds(start, end);
+
+        let mut overlapped_area: Vec<MapArea> = Vec::new();
+        let mut prev_area: BTreeMap<usize, MapArea> = BTreeMap::new();
+        for _ in 0..self.owned_mem.len() {
+            let (idx, area) = self.owned_mem.pop_first().unwrap();
+            if area.overlap_with(start, end) {
+                overlapped_area.push(area);
+            } else {
+                prev_area.insert(idx, area);
+            }
+        }
+        self.owned_mem = prev_area;
+
+        for mut area in overlapped_area {
+            // 分割出位于范围内的部分
+            if area.vaddr < start {
+                let right = area.split(start);
+                assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
+                area = right;
+            }
+            if end < area.end_va() {
+                let right = area.split(end);
+                assert!(self.owned_mem.insert(right.vaddr.into(), right).is_none());
+            }
+            area.hugepage = enable;
+            if enable {
+                area.collapse_all(&mut self.page_table);
+            }
+            assert!(self.owned_mem.insert(area.vaddr.into(), area).is_none());
+        }
+    }
+
+    /// MADV_COLLAPSE：将 [start, start + size) 中的每个 2M 范围合并为一个大页。You need to flush
+    /// TLB after this.
+    ///
+    /// `start` 与 `size` 需要 2M 对齐，范围必须是已经全部填充的私有匿名内存，否则返回
+    /// `InvalidInput`；分配不到连续的物理内存时返回 `NoMemory`。出错时之前的范围保持合并后的状态。
+    pub fn collapse(&mut self, start: VirtAddr, size: usize) -> AxResult<()> {
+        let huge_size = PageSize::Size2M as usize;
+        if !start.is_aligned(PageSize::Size2M) || size % huge_size != 0 {
+            return Err(AxError::InvalidInput);
+        }
+        for huge_start in (usize::from(start)..usize::from(start) + size).step_by(huge_size) {
+            let huge_start = VirtAddr::from(huge_start);
+            match self
+                .owned_mem
+                .values_mut()
+                .find(|area| area.contains(huge_start, huge_start + huge_size))
+            {
+                Some(area) => area.collapse(huge_start, &mut self.page_table)?,
+                None => return Err(AxError::InvalidInput),
+            }
+        }
+        Ok(())
     }
 
     /// It will map newly allocated page in the page table. You need to flush TLB after this.
@@ -393,12 +686,7 @@ impl MemorySet {
             .values_mut()
             .find(|area| area.vaddr <= addr && addr < area.end_va())
         {
-            Some(area) => {
-                if !area.handle_page_fault(addr, flags, &mut self.page_table) {
-                    return Err(AxError::BadAddress);
-                }
-                Ok(())
-            }
+            Some(area) => area.handle_page_fault(addr, flags, &mut self.page_table),
             None => {
                 error!("Page fault address {:?} not found in memory set ", addr);
                 Err(AxError::BadAddress)
@@ -408,10 +696,84 @@ impl MemorySet {
 
     /// 将用户分配的页面从页表中直接解映射，内核分配的页面依然保留
     pub fn unmap_user_areas(&mut self) {
+        self.update_peak_rss();
         for (_, area) in self.owned_mem.iter_mut() {
             area.dealloc(&mut self.page_table);
         }
         self.owned_mem.clear();
+        for (addr, _, mem) in self.attached_mem.drain(..) {
+            self.page_table.unmap_region(addr, mem.size()).unwrap();
+            mem.dec_map_count();
+        }
+        self.sync_commit();
+    }
+
+    /// 当前地址空间中实际分配了物理页的页数，即常驻内存大小（RSS）
+    pub fn rss_pages(&self) -> usize {
+        self.owned_mem
+            .values()
+            .map(|area| area.pages.iter().filter(|page| page.is_some()).count())
+            .sum()
+    }
+
+    /// 常驻内存的峰值（页数）
+    ///
+    /// 常驻内存只会在释放页面时减少，因此只在释放之前记录峰值，读取时再与当前的值比较
+    pub fn peak_rss_pages(&self) -> usize {
+        self.peak_rss_pages.max(self.rss_pages())
+    }
+
+    /// 在释放页面之前调用，记录常驻内存的峰值
+    fn update_peak_rss(&mut self) {
+        self.peak_rss_pages = self.peak_rss_pages();
+    }
+
+    /// 地址空间中每个映射区域的内存使用情况，按起始地址排序
+    ///
+    /// 共享内存在挂载时即全部映射，其页面的映射计数为挂载的次数
+    pub fn mapping_usage(&mut self) -> Vec<MappingUsage> {
+        let mut usages: Vec<MappingUsage> = self
+            .owned_mem
+            .values_mut()
+            .map(|area| area.usage(&self.page_table))
+            .collect();
+        for (addr, flags, mem) in &self.attached_mem {
+            let start = addr.as_usize();
+            let mut usage = MappingUsage::new(start, start + mem.size(), *flags, true);
+            for _ in 0..mem.size() / PAGE_SIZE_4K {
+                usage.add_page(mem.map_count(), flags.contains(MappingFlags::WRITE));
+            }
+            usages.push(usage);
+        }
+        usages.sort_by_key(|usage| usage.start);
+        usages
+    }
+
+    /// 地址空间中计入 overcommit 提交量的页数，即所有可写私有映射的大小
+    pub fn committed_pages(&self) -> usize {
+        self.committed_pages
+    }
+
+    /// 可写私有映射的总页数
+    fn accountable_pages(&self) -> usize {
+        self.owned_mem
+            .values()
+            .filter(|area| accountable(area.flags, area.is_shared()))
+            .map(|area| area.pages.len())
+            .sum()
+    }
+
+    /// 修改映射之后调用，将提交量与当前的可写私有映射同步，并更新全局的提交量
+    ///
+    /// 需要检查的新增部分应当在修改之前通过 [`vm_enough_memory`] 计入，这里只处理其余的变化
+    fn sync_commit(&mut self) {
+        let pages = self.accountable_pages();
+        if pages > self.committed_pages {
+            overcommit::vm_acct_memory(pages - self.committed_pages);
+        } else {
+            overcommit::vm_unacct_memory(self.committed_pages - pages);
+        }
+        self.committed_pages = pages;
     }
 
     /// Query the page table to get the physical address, flags and page size of the given virtual
@@ -485,9 +847,29 @@ impl MemorySet {
             .map_region(addr, mem.paddr(), mem.size(), flags, false)
             .unwrap();
 
+        mem.inc_map_count();
         self.attached_mem.push((addr, flags, mem));
     }
 
+    /// 若 `addr` 位于挂载的共享内存或共享匿名映射中，返回其对应的物理地址
+    ///
+    /// 共享匿名映射中尚未分配的页面在此时分配，以便各个共享者得到相同的地址
+    pub fn shared_mem_paddr(&self, addr: VirtAddr) -> Option<PhysAddr> {
+        if let Some(area) = self
+            .owned_mem
+            .values()
+            .find(|area| area.vaddr <= addr && addr < area.end_va())
+        {
+            let page_index = (addr.as_usize() - area.vaddr.as_usize()) / PAGE_SIZE_4K;
+            let page = area.shared_anon.as_ref()?.get_or_alloc(page_index).ok()?;
+            return Some(virt_to_phys(page.start_vaddr) + addr.align_offset_4k());
+        }
+        self.attached_mem
+            .iter()
+            .find(|(start, _, mem)| *start <= addr && addr < *start + mem.size())
+            .map(|(start, _, mem)| mem.paddr() + (addr.as_usize() - start.as_usize()))
+    }
+
     /// Detach a SharedMem from the memory set.
     ///
     /// TODO: implement this
@@ -504,7 +886,10 @@ impl MemorySet {
     /// 若不在内存集中，则返回None。
     ///
     /// 若在内存集中，且已经分配了物理页面，则不做处理。
-    pub fn manual_alloc_for_lazy(&mut self, addr: VirtAddr) -> AxResult<()> {
+    ///
+    /// `access` 为内核随后访问该地址的方式。只有包含 WRITE 时才会复制写时复制共享的页面
+    /// 或取消 MADV_FREE 标记，只读取用户内存时页面保持共享。
+    pub fn manual_alloc_for_lazy(&mut self, addr: VirtAddr, access: MappingFlags) -> AxResult<()> {
         if let Some((_, area)) = self
             .owned_mem
             .iter_mut()
@@ -519,18 +904,31 @@ impl MemorySet {
             let entry = entry.unwrap().0;
             if !entry.is_present() {
                 // 若未分配物理页面，则手动为其分配一个页面，写入到对应页表中
-                if !area.handle_page_fault(addr, entry.flags(), &mut self.page_table) {
-                    return Err(AxError::BadAddress);
-                }
+                area.handle_page_fault(addr, access, &mut self.page_table)?;
+            } else if access.contains(MappingFlags::WRITE) && area.is_write_protected_page(addr) {
+                // 内核将向该地址写入，提前复制共享页面或取消 MADV_FREE 标记
+                area.handle_page_fault(addr, MappingFlags::WRITE, &mut self.page_table)?;
             }
             Ok(())
+        } else if self
+            .attached_mem
+            .iter()
+            .any(|(start, _, mem)| *start <= addr && addr < *start + mem.size())
+        {
+            // 共享内存在映射时已经分配好了物理页面
+            Ok(())
         } else {
             Err(AxError::InvalidInput)
         }
     }
     /// 暴力实现区间强制分配
-    /// 传入区间左闭右闭
-    pub fn manual_alloc_range_for_lazy(&mut self, start: VirtAddr, end: VirtAddr) -> AxResult<()> {
+    /// 传入区间左闭右闭，`access` 的含义与 [`Self::manual_alloc_for_lazy`] 相同
+    pub fn manual_alloc_range_for_lazy(
+        &mut self,
+        start: VirtAddr,
+        end: VirtAddr,
+        access: MappingFlags,
+    ) -> AxResult<()> {
         if start > end {
             return Err(AxError::InvalidInput);
         }
@@ -539,15 +937,19 @@ impl MemorySet {
         for addr in (start..=end).step_by(PAGE_SIZE_4K) {
             // 逐页访问，主打暴力
             debug!("allocating page at {:x}", addr);
-            self.manual_alloc_for_lazy(addr.into())?;
+            self.manual_alloc_for_lazy(addr.into(), access)?;
         }
         Ok(())
     }
     /// 判断某一个类型的某一个对象是否被分配
-    pub fn manual_alloc_type_for_lazy<T: Sized>(&mut self, obj: *const T) -> AxResult<()> {
+    pub fn manual_alloc_type_for_lazy<T: Sized>(
+        &mut self,
+        obj: *const T,
+        access: MappingFlags,
+    ) -> AxResult<()> {
         let start = obj as usize;
         let end = start + core::mem::size_of::<T>() - 1;
-        self.manual_alloc_range_for_lazy(start.into(), end.into())
+        self.manual_alloc_range_for_lazy(start.into(), end.into(), access)
     }
 }
 
@@ -558,29 +960,29 @@ impl MemorySet {
     /// If it occurs error, the new MemorySet will be dropped and return the error.
     pub fn clone_or_err(&self) -> AxResult<Self> {
         let mut page_table = PageTable::try_new().expect("Error allocating page table.");
-
-        for r in memory_regions() {
-            debug!(
-                "mapping kernel region [0x{:x}, 0x{:x})",
-                usize::from(phys_to_virt(r.paddr)),
-                usize::from(phys_to_virt(r.paddr)) + r.size,
-            );
-            page_table
-                .map_region(phys_to_virt(r.paddr), r.paddr, r.size, r.flags.into(), true)
-                .expect("Error mapping kernel memory");
-        }
         let mut owned_mem: BTreeMap<usize, MapArea> = BTreeMap::new();
-        for (vaddr, area) in self.owned_mem.iter() {
-            info!("vaddr: {:X?}, new_area: {:X?}", vaddr, area.vaddr);
-            match area.clone_alloc(&mut page_table) {
-                Ok(new_area) => {
-                    info!("new area: {:X?}", new_area.vaddr);
-                    owned_mem.insert(*vaddr, new_area);
-                    Ok(())
+        let mut committed_pages = 0;
+        let result = self
+            .owned_mem
+            .iter()
+            .try_for_each(|(vaddr, area)| -> AxResult<()> {
+                info!("vaddr: {:X?}, new_area: {:X?}", vaddr, area.vaddr);
+                // 复制出的可写私有映射同样需要计入提交量
+                if accountable(area.flags, area.is_shared()) {
+                    vm_enough_memory(area.pages.len())?;
+                    committed_pages += area.pages.len();
                 }
-                Err(err) => Err(err),
-            }?;
+                let new_area = area.clone_alloc(&mut page_table)?;
+                info!("new area: {:X?}", new_area.vaddr);
+                owned_mem.insert(*vaddr, new_area);
+                Ok(())
+            });
+        if let Err(err) = result {
+            overcommit::vm_unacct_memory(committed_pages);
+            return Err(err);
         }
+        // 放在最后共享内核地址空间，出错提前返回时页表尚未登记
+        kernel::share_kernel_space(&page_table);
 
         let mut new_memory = Self {
             page_table,
@@ -588,6 +990,8 @@ impl MemorySet {
 
             private_mem: self.private_mem.clone(),
             attached_mem: Vec::new(),
+            peak_rss_pages: 0,
+            committed_pages,
         };
 
         for (addr, flags, mem) in &self.attached_mem {
@@ -601,5 +1005,6 @@ impl MemorySet {
 impl Drop for MemorySet {
     fn drop(&mut self) {
         self.unmap_user_areas();
+        kernel::unshare_kernel_space(&self.page_table);
     }
 }
@@ -0,0 +1,109 @@
+//! 匿名内存的 overcommit 策略，对应 /proc/sys/vm/overcommit_memory
+//!
+//! - [`OvercommitPolicy::Guess`]：启发式，只拒绝单次超过全部物理内存的映射
+//! - [`OvercommitPolicy::Always`]：总是允许，物理页不足时由缺页处理触发 OOM killer
+//! - [`OvercommitPolicy::Never`]：所有进程的可写私有映射之和不能超过提交上限，
+//!   上限为物理内存的 overcommit_ratio%
+//!
+//! 所有地址空间的提交量之和记录在一个全局计数中，地址空间在建立、修改与解除映射时更新它，
+//! 检查时不需要遍历所有进程。
+use axerrno::{AxError, AxResult};
+use axhal::paging::MappingFlags;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// overcommit 策略
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OvercommitPolicy {
+    /// 启发式检查，默认策略
+    Guess = 0,
+    /// 不做检查
+    Always = 1,
+    /// 严格按提交上限检查
+    Never = 2,
+}
+
+impl TryFrom<usize> for OvercommitPolicy {
+    type Error = AxError;
+
+    fn try_from(mode: usize) -> AxResult<Self> {
+        match mode {
+            0 => Ok(Self::Guess),
+            1 => Ok(Self::Always),
+            2 => Ok(Self::Never),
+            _ => Err(AxError::InvalidInput),
+        }
+    }
+}
+
+/// Never 策略下提交上限占物理内存的百分比，与 Linux 的默认值相同
+pub const OVERCOMMIT_RATIO: usize = 50;
+
+static OVERCOMMIT_POLICY: AtomicUsize = AtomicUsize::new(OvercommitPolicy::Guess as usize);
+
+/// 所有地址空间已经提交的页数之和
+static COMMITTED_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// 当前的 overcommit 策略
+pub fn overcommit_policy() -> OvercommitPolicy {
+    OvercommitPolicy::try_from(OVERCOMMIT_POLICY.load(Ordering::Acquire))
+        .unwrap_or(OvercommitPolicy::Guess)
+}
+
+/// 设置 overcommit 策略
+pub fn set_overcommit_policy(policy: OvercommitPolicy) {
+    OVERCOMMIT_POLICY.store(policy as usize, Ordering::Release);
+}
+
+/// 物理内存的总页数
+pub fn total_pages() -> usize {
+    let allocator = axalloc::global_allocator();
+    allocator.used_pages() + allocator.available_pages()
+}
+
+/// 页分配器中空闲的物理页数
+pub fn free_pages() -> usize {
+    axalloc::global_allocator().available_pages()
+}
+
+/// Never 策略下的提交上限，以页为单位
+pub fn commit_limit_pages() -> usize {
+    total_pages() * OVERCOMMIT_RATIO / 100
+}
+
+/// 所有地址空间已经提交的页数之和
+pub fn committed_pages() -> usize {
+    COMMITTED_PAGES.load(Ordering::Acquire)
+}
+
+/// 按当前的 overcommit 策略检查能否再提交 `pages` 页
+///
+/// 能提交时将其计入提交量，否则返回 `NoMemory`，此时提交量不变
+pub fn vm_enough_memory(pages: usize) -> AxResult<()> {
+    let committed = COMMITTED_PAGES.fetch_add(pages, Ordering::AcqRel) + pages;
+    let enough = match overcommit_policy() {
+        OvercommitPolicy::Always => true,
+        OvercommitPolicy::Guess => pages <= total_pages(),
+        OvercommitPolicy::Never => committed <= commit_limit_pages(),
+    };
+    if enough {
+        Ok(())
+    } else {
+        COMMITTED_PAGES.fetch_sub(pages, Ordering::AcqRel);
+        Err(AxError::NoMemory)
+    }
+}
+
+/// 不做检查地计入 `pages` 页提交量
+pub(crate) fn vm_acct_memory(pages: usize) {
+    COMMITTED_PAGES.fetch_add(pages, Ordering::AcqRel);
+}
+
+/// 释放 `pages` 页提交量
+pub(crate) fn vm_unacct_memory(pages: usize) {
+    COMMITTED_PAGES.fetch_sub(pages, Ordering::AcqRel);
+}
+
+/// 映射是否需要计入提交量：可写的私有映射（匿名或私有文件映射）写入时需要新的物理页
+pub fn accountable(flags: MappingFlags, shared: bool) -> bool {
+    flags.contains(MappingFlags::WRITE) && !shared
+}
@@ -0,0 +1,252 @@
+//! 文件页缓存
+//!
+//! 多个进程以 MAP_PRIVATE 映射同一个文件（如动态库）时，未被修改的干净页面通过引用计数
+//! 共享同一个物理页帧，并以只读方式映射。发生写缺页时再复制出私有页面，同时减少共享计数。
+//! 解除映射后页面仍留在缓存中，内存紧张时再按 LRU 顺序逐出。
+//!
+//! 文件被写入、截断、删除或重命名时，需要调用 [`invalidate_page_cache`] 丢弃该文件的缓存项，
+//! 之后的缺页会重新从文件读入。已经映射的页面不受影响。
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use axalloc::PhysPage;
+use axerrno::AxResult;
+use axio::SeekFrom;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spinlock::SpinNoIrq;
+
+use crate::{reclaim::record_reclaim, MemBackend};
+
+/// 回收时扫描的缓存项数与要逐出的页数之比的上限
+const SHRINK_SCAN_RATIO: usize = 4;
+
+/// 以 (inode 号, 页对齐的文件偏移) 为键的页缓存
+///
+/// 所有映射都释放某个页面后，缓存仍持有该页面，之后再次映射同一文件时不必重新读入。
+/// 物理内存紧张时由 [`shrink_page_cache`] 按最近最少使用的顺序逐出这些不再被映射的页面。
+struct PageCache {
+    pages: BTreeMap<(u64, u64), CachedPage>,
+    /// 按最近一次访问的先后排列的缓存项，键为访问序号
+    lru: BTreeMap<u64, (u64, u64)>,
+    next_tick: u64,
+}
+
+struct CachedPage {
+    page: Arc<PhysPage>,
+    /// 最近一次访问的序号，即在 [`PageCache::lru`] 中的键
+    tick: u64,
+}
+
+impl PageCache {
+    const fn new() -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    /// 查找 `key` 对应的页面，并将其移到 LRU 的末尾
+    fn get(&mut self, key: &(u64, u64)) -> Option<Arc<PhysPage>> {
+        let tick = self.tick();
+        let cached = self.pages.get_mut(key)?;
+        self.lru.remove(&cached.tick);
+        self.lru.insert(tick, *key);
+        cached.tick = tick;
+        Some(cached.page.clone())
+    }
+
+    fn insert(&mut self, key: (u64, u64), page: Arc<PhysPage>) {
+        let tick = self.tick();
+        self.lru.insert(tick, key);
+        if let Some(old) = self.pages.insert(key, CachedPage { page, tick }) {
+            self.lru.remove(&old.tick);
+        }
+    }
+
+    fn remove(&mut self, key: &(u64, u64)) {
+        if let Some(cached) = self.pages.remove(key) {
+            self.lru.remove(&cached.tick);
+        }
+    }
+
+    /// 从 LRU 的头部开始逐出至多 `nr_pages` 个不再被映射的页面，返回 (扫描数, 逐出数)
+    ///
+    /// 仍被映射的页面不能逐出，将其移到 LRU 的末尾，下次回收时不再重复扫描。
+    /// 每次至多扫描 `nr_pages` 的 [`SHRINK_SCAN_RATIO`] 倍，避免关中断持锁时扫描整个缓存
+    fn shrink(&mut self, nr_pages: usize) -> (usize, usize) {
+        let mut scanned = 0;
+        let mut evicted = 0;
+        while evicted < nr_pages && scanned < nr_pages * SHRINK_SCAN_RATIO {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            scanned += 1;
+            if Arc::strong_count(&self.pages[&key].page) == 1 {
+                self.pages.remove(&key);
+                evicted += 1;
+            } else {
+                let tick = self.tick();
+                self.lru.insert(tick, key);
+                self.pages.get_mut(&key).unwrap().tick = tick;
+            }
+        }
+        (scanned, evicted)
+    }
+}
+
+static PAGE_CACHE: SpinNoIrq<PageCache> = SpinNoIrq::new(PageCache::new());
+
+/// 缓存失效的次数
+///
+/// 读入页面时不持有锁，若读入期间发生了失效，读到的内容可能已经过时，不能放入缓存
+static INVALIDATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// 映射区域中的一个物理页面
+pub enum MappedPage {
+    /// 仅属于当前映射区域的页面
+    Private(PhysPage),
+    /// 与其他映射共享的只读文件页面，写入前需要先复制
+    Shared(Arc<PhysPage>),
+    /// 被 MADV_FREE 标记的私有匿名页面，以只读方式映射
+    ///
+    /// 再次写入时恢复为 [`MappedPage::Private`]，在此之前内存紧张时可以直接回收
+    LazyFree(PhysPage),
+    /// 共享匿名映射中的页面，所有共享者都以可写方式映射，写入对彼此可见
+    ///
+    /// 共享对象本身也持有一份引用，见 [`crate::shared::SharedAnon`]
+    SharedAnon(Arc<PhysPage>),
+}
+
+impl MappedPage {
+    /// whether the page is shared with other mappings
+    pub fn is_shared(&self) -> bool {
+        matches!(self, Self::Shared(_))
+    }
+
+    /// whether the page has been marked by MADV_FREE and can be reclaimed
+    pub fn is_lazy_free(&self) -> bool {
+        matches!(self, Self::LazyFree(_))
+    }
+
+    /// 映射了该页面的区域数。私有页面总是 1，共享页面为引用计数：
+    /// fork 时复制引用，写时复制与解除映射时释放引用
+    ///
+    /// 共享匿名页面的引用计数中不含共享对象本身持有的一份。共享文件页面仍在页缓存中时，
+    /// 引用计数中含有页缓存持有的一份，需要由调用者根据 [`is_cached`] 扣除
+    pub fn map_count(&self) -> usize {
+        match self {
+            Self::Private(_) | Self::LazyFree(_) => 1,
+            Self::Shared(page) => Arc::strong_count(page),
+            Self::SharedAnon(page) => Arc::strong_count(page) - 1,
+        }
+    }
+
+    /// 页面是否以只读方式映射，写入前需要先经过写缺页处理
+    pub fn is_write_protected(&self) -> bool {
+        !matches!(self, Self::Private(_) | Self::SharedAnon(_))
+    }
+
+    /// get the mutable reference of a private page
+    pub fn as_private_mut(&mut self) -> Option<&mut PhysPage> {
+        match self {
+            Self::Private(page) => Some(page),
+            Self::Shared(_) | Self::LazyFree(_) | Self::SharedAnon(_) => None,
+        }
+    }
+}
+
+impl Deref for MappedPage {
+    type Target = PhysPage;
+
+    fn deref(&self) -> &PhysPage {
+        match self {
+            Self::Private(page) | Self::LazyFree(page) => page,
+            Self::Shared(page) | Self::SharedAnon(page) => page,
+        }
+    }
+}
+
+impl From<PhysPage> for MappedPage {
+    fn from(page: PhysPage) -> Self {
+        Self::Private(page)
+    }
+}
+
+/// 获取文件 `offset` 处的共享页面，若不在缓存中则从文件读入
+///
+/// `backend` 不能被缓存时返回 None。读取文件时不持有缓存的锁，
+/// 读入完成后再次检查缓存，其他任务已经读入同一页面时使用已有的页面。
+pub fn get_or_load(backend: &mut MemBackend, offset: u64) -> Option<AxResult<Arc<PhysPage>>> {
+    let key = (backend.ino()?, offset);
+    if let Some(page) = PAGE_CACHE.lock().get(&key) {
+        return Some(Ok(page));
+    }
+    let invalidations = INVALIDATIONS.load(Ordering::Acquire);
+    let mut page = match PhysPage::alloc() {
+        Ok(page) => page,
+        Err(err) => return Some(Err(err)),
+    };
+    // 文件末尾不足一页的部分需要填零
+    page.fill(0);
+    if backend
+        .read_from_seek(SeekFrom::Start(offset), page.as_slice_mut())
+        .is_err()
+    {
+        warn!(
+            "Failed to read page cache of inode {} at {:#x}",
+            key.0, offset
+        );
+        return None;
+    }
+    let page = Arc::new(page);
+    let mut cache = PAGE_CACHE.lock();
+    if let Some(cached) = cache.get(&key) {
+        return Some(Ok(cached));
+    }
+    if INVALIDATIONS.load(Ordering::Acquire) == invalidations {
+        cache.insert(key, page.clone());
+    }
+    Some(Ok(page))
+}
+
+/// 丢弃 inode 号为 `ino` 的文件的所有缓存页面
+pub fn invalidate_page_cache(ino: u64) {
+    let mut cache = PAGE_CACHE.lock();
+    INVALIDATIONS.fetch_add(1, Ordering::AcqRel);
+    let keys: Vec<_> = cache
+        .pages
+        .range((ino, 0)..=(ino, u64::MAX))
+        .map(|(key, _)| *key)
+        .collect();
+    for key in keys {
+        cache.remove(&key);
+    }
+}
+
+/// 物理内存紧张时调用，逐出至多 `nr_pages` 个不再被任何映射使用的缓存页面，返回逐出的页数
+///
+/// 缓存中的页面只由文件读入，写入时总是先复制出私有页面，因此缓存中没有需要写回的脏页
+pub fn shrink_page_cache(nr_pages: usize) -> usize {
+    let (scanned, evicted) = PAGE_CACHE.lock().shrink(nr_pages);
+    record_reclaim(scanned, evicted);
+    evicted
+}
+
+/// 页缓存中 `key` 处的页面是否就是 `page`
+pub(crate) fn is_cached(key: (u64, u64), page: &Arc<PhysPage>) -> bool {
+    PAGE_CACHE
+        .lock()
+        .pages
+        .get(&key)
+        .is_some_and(|cached| Arc::ptr_eq(&cached.page, page))
+}
+
+/// 页缓存当前持有的页数，对应 /proc/vmstat 中的 nr_file_pages
+pub fn page_cache_pages() -> usize {
+    PAGE_CACHE.lock().pages.len()
+}
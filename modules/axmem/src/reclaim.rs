@@ -0,0 +1,35 @@
+//! 物理内存紧张时的页面回收
+//!
+//! 内核没有交换分区，能够直接回收的有两类页面：
+//! - 页缓存中不再被任何映射使用的干净页面，按 LRU 顺序逐出，见 [`crate::shrink_page_cache`]。
+//!   缓存中的页面写入前总是先复制，因此没有需要写回的脏页；仍被映射的页面则一律跳过。
+//! - 被 MADV_FREE 标记、此后没有再被写入的私有匿名页面。
+//!
+//! 缺页处理分配失败时先进行回收，回收不到页面时才交给 OOM killer。
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static PAGES_SCANNED: AtomicUsize = AtomicUsize::new(0);
+static PAGES_RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+
+/// 页面回收的累计统计，对应 /proc/vmstat 中的 pgscan_direct 与 pgsteal_direct
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReclaimStats {
+    /// 回收时扫描过的已分配页面数
+    pub scanned: usize,
+    /// 实际回收的页面数
+    pub reclaimed: usize,
+}
+
+/// 记录一次回收的结果
+pub(crate) fn record_reclaim(scanned: usize, reclaimed: usize) {
+    PAGES_SCANNED.fetch_add(scanned, Ordering::Relaxed);
+    PAGES_RECLAIMED.fetch_add(reclaimed, Ordering::Relaxed);
+}
+
+/// 自启动以来页面回收的累计统计
+pub fn reclaim_stats() -> ReclaimStats {
+    ReclaimStats {
+        scanned: PAGES_SCANNED.load(Ordering::Relaxed),
+        reclaimed: PAGES_RECLAIMED.load(Ordering::Relaxed),
+    }
+}
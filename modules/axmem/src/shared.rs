@@ -1,13 +1,18 @@
-use axalloc::GlobalPage;
+use alloc::{sync::Arc, vec::Vec};
+use axalloc::{GlobalPage, PhysPage};
 use axerrno::AxResult;
 use axhal::{
     mem::{virt_to_phys, PhysAddr, PAGE_SIZE_4K},
     time::current_time,
 };
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spinlock::SpinNoIrq;
 
 #[allow(dead_code)]
 pub struct SharedMem {
     pages: GlobalPage,
+    /// 挂载到各个地址空间中的次数，即其中每个页面的映射计数
+    map_count: AtomicUsize,
     /// The information of the shared memory.
     pub info: SharedMemInfo,
 }
@@ -26,11 +31,14 @@ impl SharedMem {
     ) -> AxResult<Self> {
         let num_pages = (size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K;
 
-        let pages = GlobalPage::alloc_contiguous(num_pages, PAGE_SIZE_4K)?;
+        let mut pages = GlobalPage::alloc_contiguous(num_pages, PAGE_SIZE_4K)?;
+        // 共享内存与匿名映射一样，初始内容应当为 0
+        pages.zero();
         let size = pages.size();
 
         Ok(Self {
             pages,
+            map_count: AtomicUsize::new(0),
             info: SharedMemInfo::new(key, size, pid, uid, gid, mode),
         })
     }
@@ -44,11 +52,26 @@ impl SharedMem {
     pub fn paddr(&self) -> PhysAddr {
         self.pages.start_paddr(virt_to_phys)
     }
+
+    /// 当前映射了这段共享内存的次数
+    pub fn map_count(&self) -> usize {
+        self.map_count.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次挂载
+    pub(crate) fn inc_map_count(&self) {
+        self.map_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次解除挂载
+    pub(crate) fn dec_map_count(&self) {
+        self.map_count.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 #[allow(dead_code)]
 pub struct SharedMemInfo {
-    perm: SharedMemPermInfo,
+    perm: IpcPerm,
     size: usize,
 
     a_time: usize,
@@ -59,14 +82,61 @@ pub struct SharedMemInfo {
     l_pid: u64,
 }
 
+/// System V IPC 对象的权限信息，对应 Linux 的 `struct ipc64_perm`
+///
+/// 共享内存与消息队列共用，IPC_STAT 时直接复制到用户空间
+#[repr(C)]
 #[allow(dead_code)]
-pub struct SharedMemPermInfo {
-    key: i32,
-    uid: u32,
-    gid: u32,
-    cuid: u32,
-    cgid: u32,
-    mode: u16,
+#[derive(Clone, Copy, Default)]
+pub struct IpcPerm {
+    /// 创建时使用的 key
+    pub key: i32,
+    /// 属主的 uid
+    pub uid: u32,
+    /// 属主的 gid
+    pub gid: u32,
+    /// 创建者的 uid
+    pub cuid: u32,
+    /// 创建者的 gid
+    pub cgid: u32,
+    /// 权限位，只保留低 9 位
+    pub mode: u32,
+    seq: u16,
+    __pad2: u16,
+    __unused1: u64,
+    __unused2: u64,
+}
+
+impl IpcPerm {
+    /// 由 `(uid, gid)` 创建的对象的权限信息
+    pub fn new(key: i32, uid: u32, gid: u32, mode: u32) -> Self {
+        Self {
+            key,
+            uid,
+            gid,
+            cuid: uid,
+            cgid: gid,
+            mode: mode & 0o777,
+            ..Default::default()
+        }
+    }
+
+    /// 判断用户 `(uid, gid)` 是否拥有 `access` 中要求的权限（rwx 三位）
+    ///
+    /// 超级用户总是拥有全部权限
+    pub fn permits(&self, uid: u32, gid: u32, access: u32) -> bool {
+        if uid == 0 {
+            return true;
+        }
+        let granted = if uid == self.uid || uid == self.cuid {
+            self.mode >> 6
+        } else if gid == self.gid || gid == self.cgid {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        access & 0o7 & !granted == 0
+    }
 }
 
 impl SharedMemInfo {
@@ -75,14 +145,7 @@ impl SharedMemInfo {
     /// This function should be called by SharedMem::try_new().
     fn new(key: i32, size: usize, pid: u64, uid: u32, gid: u32, mode: u16) -> Self {
         Self {
-            perm: SharedMemPermInfo {
-                key,
-                uid,
-                gid,
-                cuid: uid,
-                cgid: gid,
-                mode,
-            },
+            perm: IpcPerm::new(key, uid, gid, mode as u32),
             size,
             a_time: 0,
             d_time: 0,
@@ -93,3 +156,66 @@ impl SharedMemInfo {
         }
     }
 }
+
+/// 共享匿名映射（MAP_SHARED | MAP_ANONYMOUS）背后的匿名共享对象中的一段
+///
+/// 对象中的页面在第一次缺页时才分配并填零，之后所有映射了该页的区域共享同一个物理页帧，
+/// 写入对所有共享者可见。区域被分割时各部分持有同一个对象与各自的页偏移，
+/// 所有区域都解除映射后对象与其中的页面随之释放。
+#[derive(Clone)]
+pub struct SharedAnon {
+    pages: Arc<SpinNoIrq<Vec<Option<Arc<PhysPage>>>>>,
+    /// 区域的第一页在对象中的下标
+    first_page: usize,
+}
+
+impl SharedAnon {
+    /// 创建一个有 `num_pages` 页、尚未分配任何页面的共享对象
+    pub fn new(num_pages: usize) -> Self {
+        let mut pages = Vec::with_capacity(num_pages);
+        pages.resize_with(num_pages, || None);
+        Self {
+            pages: Arc::new(SpinNoIrq::new(pages)),
+            first_page: 0,
+        }
+    }
+
+    /// 从当前位置向后偏移 `delta` 页，用于分割区域
+    pub fn with_delta(&self, delta: usize) -> Self {
+        Self {
+            pages: Arc::clone(&self.pages),
+            first_page: self.first_page + delta,
+        }
+    }
+
+    /// 获取区域中第 `page_index` 页对应的页面，尚未分配时分配一个填零的页面
+    ///
+    /// 物理页帧耗尽时返回 `NoMemory`
+    pub fn get_or_alloc(&self, page_index: usize) -> AxResult<Arc<PhysPage>> {
+        let mut pages = self.pages.lock();
+        let slot = &mut pages[self.first_page + page_index];
+        if let Some(page) = slot {
+            return Ok(Arc::clone(page));
+        }
+        let mut page = PhysPage::alloc()?;
+        page.fill(0);
+        let page = Arc::new(page);
+        *slot = Some(Arc::clone(&page));
+        Ok(page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpcPerm;
+
+    #[test]
+    fn test_ipc_perm() {
+        let perm = IpcPerm::new(1, 1000, 100, 0o640);
+        assert!(perm.permits(0, 0, 0o6));
+        assert!(perm.permits(1000, 100, 0o6));
+        assert!(perm.permits(1001, 100, 0o4));
+        assert!(!perm.permits(1001, 100, 0o2));
+        assert!(!perm.permits(1001, 101, 0o4));
+    }
+}
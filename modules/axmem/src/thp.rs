@@ -0,0 +1,63 @@
+//! 透明大页
+//!
+//! 被 MADV_HUGEPAGE 标记的私有匿名区域中，完全位于区域内的 2M 对齐范围可以用一个 2M 大页映射：
+//! - 范围内第一次缺页时，若能分配到连续且对齐的 2M 物理内存，直接映射为大页；
+//! - 范围内的 512 个小页面都被填充后，将它们复制到连续的物理内存中，合并为一个大页。
+//!
+//! 页表中的大页在部分 munmap、mprotect 或 madvise 之前会被拆分回小页面，因此区域的页面记录
+//! 始终以 4K 为单位。
+use alloc::vec::Vec;
+use axalloc::PhysPage;
+use axhal::{
+    mem::{virt_to_phys, PAGE_SIZE_4K},
+    paging::PageSize,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 一个大页包含的小页面数
+pub const PAGES_PER_HUGE_PAGE: usize = PageSize::Size2M as usize / PAGE_SIZE_4K;
+
+static FAULT_ALLOC: AtomicUsize = AtomicUsize::new(0);
+static COLLAPSE_ALLOC: AtomicUsize = AtomicUsize::new(0);
+
+/// 透明大页的累计统计，对应 /proc/vmstat 中的 thp_fault_alloc 与 thp_collapse_alloc
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThpStats {
+    /// 缺页时直接分配的大页数
+    pub fault_alloc: usize,
+    /// 由小页面合并而成的大页数
+    pub collapse_alloc: usize,
+}
+
+/// 记录一次缺页时的大页分配
+pub(crate) fn record_fault_alloc() {
+    FAULT_ALLOC.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 记录一次小页面的合并
+pub(crate) fn record_collapse() {
+    COLLAPSE_ALLOC.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 自启动以来透明大页的累计统计
+pub fn thp_stats() -> ThpStats {
+    ThpStats {
+        fault_alloc: FAULT_ALLOC.load(Ordering::Relaxed),
+        collapse_alloc: COLLAPSE_ALLOC.load(Ordering::Relaxed),
+    }
+}
+
+/// 分配物理地址连续且 2M 对齐、已清零的 512 个页面
+///
+/// 物理内存不足或碎片化时返回 None，调用者应退回到小页面
+pub(crate) fn alloc_huge_pages() -> Option<Vec<PhysPage>> {
+    let pages: Vec<PhysPage> =
+        PhysPage::alloc_contiguous(PAGES_PER_HUGE_PAGE, PageSize::Size2M as usize, None)
+            .ok()?
+            .into_iter()
+            .flatten()
+            .collect();
+    virt_to_phys(pages[0].start_vaddr)
+        .is_aligned(PageSize::Size2M)
+        .then_some(pages)
+}
@@ -0,0 +1,106 @@
+//! 映射区域的内存使用统计，用于生成 /proc/<pid>/smaps 与 /proc/<pid>/statm
+//!
+//! 统计在读取时逐页遍历区域得到，每个已映射的页面按映射计数与是否被写过分类：
+//! - 映射计数为 1 的页面计入 Private，大于 1 的计入 Shared，Pss 为页面大小除以映射计数之和；
+//! - 可写区域中的私有页面视为脏页面，页缓存中共享的文件页面与 MADV_FREE 页面视为干净页面。
+//!
+//! 内核没有交换分区，因此不统计 Swap。
+use alloc::string::String;
+use axhal::{mem::PAGE_SIZE_4K, paging::MappingFlags};
+
+/// Pss 累加时保留的小数位数，与 Linux 一致
+const PSS_SHIFT: u32 = 12;
+
+/// 一个映射区域的内存使用情况，大小均以字节为单位
+#[derive(Clone, Debug)]
+pub struct MappingUsage {
+    /// 起始地址
+    pub start: usize,
+    /// 结束地址
+    pub end: usize,
+    /// 映射权限
+    pub flags: MappingFlags,
+    /// 是否为共享映射（MAP_SHARED 或共享内存）
+    pub shared: bool,
+    /// 映射的文件在文件中的偏移
+    pub offset: u64,
+    /// 映射的文件路径，匿名映射或路径未知时为 None
+    pub path: Option<String>,
+    /// 是否被 MADV_HUGEPAGE 标记
+    pub hugepage: bool,
+    /// 已映射的页面大小之和
+    pub rss: usize,
+    /// 按映射计数均摊后的页面大小之和
+    pub pss: usize,
+    /// 脏页面的 Pss
+    pub pss_dirty: usize,
+    /// 映射计数大于 1 的干净页面
+    pub shared_clean: usize,
+    /// 映射计数大于 1 的脏页面
+    pub shared_dirty: usize,
+    /// 映射计数为 1 的干净页面
+    pub private_clean: usize,
+    /// 映射计数为 1 的脏页面
+    pub private_dirty: usize,
+    /// 匿名页面，包括私有文件映射中写时复制出的页面
+    pub anonymous: usize,
+    /// 被 MADV_FREE 标记的页面
+    pub lazy_free: usize,
+    /// 以 2M 大页映射的匿名页面
+    pub anon_huge: usize,
+    /// Pss 的累加值，保留 [`PSS_SHIFT`] 位小数
+    pss_acc: u64,
+    /// 脏页面 Pss 的累加值
+    pss_dirty_acc: u64,
+}
+
+impl MappingUsage {
+    /// 创建 [start, end) 的空统计
+    pub fn new(start: usize, end: usize, flags: MappingFlags, shared: bool) -> Self {
+        Self {
+            start,
+            end,
+            flags,
+            shared,
+            offset: 0,
+            path: None,
+            hugepage: false,
+            rss: 0,
+            pss: 0,
+            pss_dirty: 0,
+            shared_clean: 0,
+            shared_dirty: 0,
+            private_clean: 0,
+            private_dirty: 0,
+            anonymous: 0,
+            lazy_free: 0,
+            anon_huge: 0,
+            pss_acc: 0,
+            pss_dirty_acc: 0,
+        }
+    }
+
+    /// 区域的大小
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// 计入一个映射计数为 `map_count` 的已映射页面
+    pub fn add_page(&mut self, map_count: usize, dirty: bool) {
+        let map_count = map_count.max(1);
+        self.rss += PAGE_SIZE_4K;
+        let pss = ((PAGE_SIZE_4K as u64) << PSS_SHIFT) / map_count as u64;
+        self.pss_acc += pss;
+        self.pss = (self.pss_acc >> PSS_SHIFT) as usize;
+        if dirty {
+            self.pss_dirty_acc += pss;
+            self.pss_dirty = (self.pss_dirty_acc >> PSS_SHIFT) as usize;
+        }
+        match (map_count > 1, dirty) {
+            (true, true) => self.shared_dirty += PAGE_SIZE_4K,
+            (true, false) => self.shared_clean += PAGE_SIZE_4K,
+            (false, true) => self.private_dirty += PAGE_SIZE_4K,
+            (false, false) => self.private_clean += PAGE_SIZE_4K,
+        }
+    }
+}
@@ -142,6 +142,9 @@ impl<'a> SocketSetWrapper<'a> {
         }
         #[cfg(not(feature = "ip"))]
         ETH0.poll(&self.0);
+        if let Some(hook) = POLL_HOOK.get() {
+            hook();
+        }
     }
 
     pub fn remove(&self, handle: SocketHandle) {
@@ -322,6 +325,16 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+static POLL_HOOK: spin::Once<fn()> = spin::Once::new();
+
+/// Sets a function called after each poll of the network stack, when sockets
+/// may have become readable or writable.
+///
+/// Only the first call takes effect.
+pub fn set_poll_hook(hook: fn()) {
+    POLL_HOOK.call_once(|| hook);
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
     #[cfg(not(feature = "ip"))]
@@ -3,7 +3,7 @@ use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
-use axhal::time::current_ticks;
+use axhal::time::{current_time, Duration};
 use axio::{PollState, Read, Write};
 use axsync::Mutex;
 
@@ -330,14 +330,14 @@ impl TcpSocket {
     /// Receives data from the socket, stores it in the given buffer.
     ///
     /// It will return [`Err(Timeout)`](AxError::Timeout) if expired.
-    pub fn recv_timeout(&self, buf: &mut [u8], ticks: u64) -> AxResult<usize> {
+    pub fn recv_timeout(&self, buf: &mut [u8], timeout: Duration) -> AxResult<usize> {
         if self.is_connecting() {
             return Err(AxError::WouldBlock);
         } else if !self.is_connected() {
             return ax_err!(NotConnected, "socket recv() failed");
         }
 
-        let expire_at = current_ticks() + ticks;
+        let deadline = current_time().saturating_add(timeout);
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
@@ -358,7 +358,7 @@ impl TcpSocket {
                     Ok(len)
                 } else {
                     // no more data
-                    if current_ticks() > expire_at {
+                    if current_time() > deadline {
                         Err(AxError::Timeout)
                     } else {
                         Err(AxError::WouldBlock)
@@ -411,6 +411,17 @@ impl TcpSocket {
         }
     }
 
+    /// Returns the number of bytes that can be read immediately.
+    pub fn recv_queue(&self) -> usize {
+        let handle = unsafe { self.handle.get().read() };
+        match handle {
+            Some(handle) if self.get_state() == STATE_CONNECTED => {
+                SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| socket.recv_queue())
+            }
+            _ => 0,
+        }
+    }
+
     /// To set the nagle algorithm enabled or not.
     pub fn set_nagle_enabled(&self, enabled: bool) -> AxResult {
         let handle = unsafe { self.handle.get().read() };
@@ -2,7 +2,7 @@ use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
-use axhal::time::current_ticks;
+use axhal::time::{current_time, Duration};
 use axio::{PollState, Read, Write};
 use axsync::Mutex;
 use spin::RwLock;
@@ -122,12 +122,16 @@ impl UdpSocket {
     /// Receives data from the socket, stores it in the given buffer.
     ///
     /// It will return [`Err(Timeout)`](AxError::Timeout) if expired.
-    pub fn recv_from_timeout(&self, buf: &mut [u8], ticks: u64) -> AxResult<(usize, SocketAddr)> {
-        let expire_at = current_ticks() + ticks;
+    pub fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> AxResult<(usize, SocketAddr)> {
+        let deadline = current_time().saturating_add(timeout);
         self.recv_impl(|socket| match socket.recv_slice(buf) {
             Ok((len, meta)) => Ok((len, into_core_sockaddr(meta.endpoint))),
             Err(_) => {
-                if current_ticks() > expire_at {
+                if current_time() > deadline {
                     Err(AxError::Timeout)
                 } else {
                     Err(AxError::WouldBlock)
@@ -198,6 +202,16 @@ impl UdpSocket {
         Ok(())
     }
 
+    /// Returns the size of the next pending datagram, or 0 if there is none.
+    pub fn recv_queue(&self) -> usize {
+        if self.local_addr.read().is_none() {
+            return 0;
+        }
+        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+            socket.peek().map_or(0, |(data, _)| data.len())
+        })
+    }
+
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
         if self.local_addr.read().is_none() {
@@ -0,0 +1,266 @@
+//! 进程记账
+//!
+//! 开启后，每个进程退出时生成一条 acct_v3 格式的记录并追加到指定的文件中。
+//! 退出路径只把记录放入内核队列，由后台的内核线程通过普通的文件写入路径写入文件，
+//! 避免写文件阻塞进程退出。队列已满或文件无法写入时丢弃记录并计数，
+//! 文件系统已满时自动关闭记账。
+extern crate alloc;
+use alloc::{collections::VecDeque, string::String};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{File, Write};
+use axhal::time::{current_time_nanos, time_to_nanos, NANOS_PER_SEC};
+use axlog::{info, warn};
+use axsync::Mutex;
+use axtask::TaskInner;
+use spinlock::SpinNoIrq;
+
+use crate::process::Process;
+
+/// 记录中时间的单位为 1/AHZ 秒
+const AHZ: u64 = 100;
+
+/// acct_v3 的版本号
+const ACCT_VERSION: u8 = 3;
+
+/// 等待写入的记录数的上限
+const ACCT_QUEUE_LEN: usize = 128;
+
+/// 记账记录，与 Linux 的 `struct acct_v3` 布局一致
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AcctV3 {
+    /// 标志位
+    pub ac_flag: u8,
+    /// 记录格式的版本号，总为 3
+    pub ac_version: u8,
+    /// 控制终端
+    pub ac_tty: u16,
+    /// 退出状态，与 wait 得到的 status 格式相同
+    pub ac_exitcode: u32,
+    /// 用户 id
+    pub ac_uid: u32,
+    /// 组 id
+    pub ac_gid: u32,
+    /// 进程号
+    pub ac_pid: u32,
+    /// 父进程号
+    pub ac_ppid: u32,
+    /// 进程开始运行的时间（秒）
+    pub ac_btime: u32,
+    /// 运行经过的时间（1/AHZ 秒）
+    pub ac_etime: f32,
+    /// 用户态时间（1/AHZ 秒，comp_t 编码）
+    pub ac_utime: u16,
+    /// 内核态时间（1/AHZ 秒，comp_t 编码）
+    pub ac_stime: u16,
+    /// 常驻内存的峰值（KB，comp_t 编码）
+    pub ac_mem: u16,
+    /// 读写的字符数
+    pub ac_io: u16,
+    /// 读写的块数
+    pub ac_rw: u16,
+    /// 次缺页次数
+    pub ac_minflt: u16,
+    /// 主缺页次数
+    pub ac_majflt: u16,
+    /// 换出次数
+    pub ac_swaps: u16,
+    /// 进程名，以 '\0' 结尾
+    pub ac_comm: [u8; 16],
+}
+
+impl AcctV3 {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                core::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+/// 将数值编码为 comp_t：低 13 位为尾数，高 3 位为以 8 为底的指数
+fn encode_comp_t(mut value: u64) -> u16 {
+    const MANT_BITS: u16 = 13;
+    const MAX_MANT: u64 = (1 << MANT_BITS) - 1;
+    let mut exp = 0;
+    let mut round = false;
+    while value > MAX_MANT {
+        round = value & 0b100 != 0;
+        value >>= 3;
+        exp += 1;
+    }
+    // 四舍五入后可能再次溢出
+    if round {
+        value += 1;
+        if value > MAX_MANT {
+            value >>= 3;
+            exp += 1;
+        }
+    }
+    if exp > 7 {
+        return u16::MAX;
+    }
+    ((exp as u16) << MANT_BITS) | value as u16
+}
+
+/// 纳秒转换为 1/AHZ 秒
+fn nanos_to_ahz(nanos: u64) -> u64 {
+    nanos / (NANOS_PER_SEC / AHZ)
+}
+
+/// 是否开启了记账
+static ACCT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 记账文件，为 None 时表示未开启
+static ACCT_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// 等待写入文件的记录
+static ACCT_QUEUE: SpinNoIrq<VecDeque<AcctV3>> = SpinNoIrq::new(VecDeque::new());
+
+/// 被丢弃的记录数
+static ACCT_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// 是否已有负责写入的内核线程在运行
+static WRITER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 开启记账，之后退出的进程的记录会追加到 `path` 中
+///
+/// 若已开启，则先写完已有的记录再切换到新的文件
+pub fn enable(path: &str) -> AxResult<()> {
+    let file = File::options().write(true).append(true).open(path)?;
+    if !file.metadata()?.is_file() {
+        return Err(AxError::PermissionDenied);
+    }
+    let mut acct_file = ACCT_FILE.lock();
+    flush(&mut acct_file);
+    *acct_file = Some(file);
+    ACCT_ENABLED.store(true, Ordering::Release);
+    info!("[acct] enabled, file: {}", path);
+    Ok(())
+}
+
+/// 关闭记账，关闭前会写完已经生成的记录
+pub fn disable() {
+    ACCT_ENABLED.store(false, Ordering::Release);
+    let mut acct_file = ACCT_FILE.lock();
+    flush(&mut acct_file);
+    *acct_file = None;
+    info!("[acct] disabled");
+}
+
+/// 记账是否开启
+pub fn is_enabled() -> bool {
+    ACCT_ENABLED.load(Ordering::Acquire)
+}
+
+/// 因队列已满或文件无法写入而被丢弃的记录数
+pub fn dropped_records() -> usize {
+    ACCT_DROPPED.load(Ordering::Acquire)
+}
+
+/// 将队列中的记录全部写入文件
+fn flush(acct_file: &mut Option<File>) {
+    loop {
+        let Some(record) = ACCT_QUEUE.lock().pop_front() else {
+            break;
+        };
+        let Some(file) = acct_file.as_mut() else {
+            ACCT_DROPPED.fetch_add(1, Ordering::AcqRel);
+            continue;
+        };
+        match file.write_all(record.as_bytes()) {
+            Ok(()) => {}
+            Err(AxError::StorageFull) => {
+                // 文件系统已满，关闭记账
+                warn!("[acct] file system is full, accounting disabled");
+                ACCT_ENABLED.store(false, Ordering::Release);
+                *acct_file = None;
+                ACCT_DROPPED.fetch_add(1, Ordering::AcqRel);
+            }
+            Err(err) => {
+                warn!("[acct] failed to write record: {:?}", err);
+                ACCT_DROPPED.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+/// 启动写入记录的内核线程，队列清空后线程即退出
+fn wake_writer() {
+    if WRITER_RUNNING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    axtask::spawn_raw(
+        || loop {
+            flush(&mut ACCT_FILE.lock());
+            WRITER_RUNNING.store(false, Ordering::Release);
+            // 退出前再次检查，避免遗漏在此期间加入的记录
+            if ACCT_QUEUE.lock().is_empty() || WRITER_RUNNING.swap(true, Ordering::AcqRel) {
+                break;
+            }
+        },
+        String::from("acct"),
+        axconfig::TASK_STACK_SIZE,
+    );
+}
+
+/// 在进程退出时生成记账记录，由进程的主线程在其他线程都退出之后、释放资源之前调用
+pub(crate) fn record_exit(process: &Process, leader: &TaskInner) {
+    if !is_enabled() {
+        return;
+    }
+    let now = current_time_nanos();
+    let start = process.start_time();
+    let (utime, stime) = process.exit_times();
+    let peak_rss_kb = process.peak_rss_pages() * axhal::mem::PAGE_SIZE_4K / 1024;
+    let mut record = AcctV3 {
+        ac_version: ACCT_VERSION,
+        ac_exitcode: process.wait_status() as u32,
+        ac_pid: process.pid() as u32,
+        ac_ppid: process.get_parent() as u32,
+        ac_btime: (start / NANOS_PER_SEC) as u32,
+        ac_etime: nanos_to_ahz(now - start) as f32,
+        ac_utime: encode_comp_t(nanos_to_ahz(time_to_nanos(utime))),
+        ac_stime: encode_comp_t(nanos_to_ahz(time_to_nanos(stime))),
+        ac_mem: encode_comp_t(peak_rss_kb as u64),
+        ..Default::default()
+    };
+    let name = leader.name();
+    let name = name.rsplit('/').next().unwrap_or_default().as_bytes();
+    let len = name.len().min(record.ac_comm.len() - 1);
+    record.ac_comm[..len].copy_from_slice(&name[..len]);
+
+    let mut queue = ACCT_QUEUE.lock();
+    if queue.len() >= ACCT_QUEUE_LEN {
+        drop(queue);
+        ACCT_DROPPED.fetch_add(1, Ordering::AcqRel);
+        return;
+    }
+    queue.push_back(record);
+    drop(queue);
+    wake_writer();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_comp_t, AcctV3};
+
+    #[test]
+    fn test_acct_v3_layout() {
+        assert_eq!(core::mem::size_of::<AcctV3>(), 64);
+    }
+
+    #[test]
+    fn test_encode_comp_t() {
+        assert_eq!(encode_comp_t(0), 0);
+        assert_eq!(encode_comp_t(0x1fff), 0x1fff);
+        // 8192 = 1024 * 8
+        assert_eq!(encode_comp_t(0x2000), (1 << 13) | 1024);
+        // 超出可表示的范围
+        assert_eq!(encode_comp_t(u64::MAX), u16::MAX);
+    }
+}
@@ -8,27 +8,34 @@ use alloc::{
     vec,
     vec::Vec,
 };
-use axconfig::{MAX_USER_HEAP_SIZE, MAX_USER_STACK_SIZE, USER_HEAP_BASE, USER_STACK_TOP};
+use axconfig::{
+    MAX_USER_HEAP_SIZE, MAX_USER_STACK_SIZE, TASK_SIZE, USER_HEAP_BASE, USER_MEMORY_START,
+    USER_STACK_TOP,
+};
 use axerrno::{AxError, AxResult};
+use axhal::arch::TrapFrame;
 use axhal::mem::VirtAddr;
 use axhal::paging::MappingFlags;
-use axhal::time::{current_time_nanos, NANOS_PER_MICROS, NANOS_PER_SEC};
+use axhal::time::{current_time, Duration};
 use axhal::KERNEL_PROCESS_ID;
 use axlog::{debug, info};
 use axmem::MemorySet;
 
 use axsignal::signal_no::SignalNo;
-use axsync::Mutex;
+use axsync::SpinWaitNoIrq;
 use axtask::{current, yield_now, AxTaskRef, CurrentTask, TaskId, TaskState, IDLE_TASK, RUN_QUEUE};
 use elf_parser::{
     get_app_stack_region, get_auxv_vector, get_elf_entry, get_elf_segments, get_relocate_pairs,
 };
 use xmas_elf::program::SegmentData;
 
+use crate::fd_manager::FdManager;
 use crate::flags::WaitStatus;
-use crate::futex::clear_wait;
+use crate::fs_context::FsContext;
+use crate::futex::{clear_wait, futex_key, futex_wake};
 use crate::link::real_path;
-use crate::process::{Process, PID2PC, TID2TASK};
+use crate::process::{Process, FD_LIMIT_ORIGIN, PID2PC, TID2TASK};
+use crate::uaccess::{copy_struct_to_user, UserMemoryGuard};
 
 use crate::signal::{send_signal_to_process, send_signal_to_thread};
 
@@ -37,9 +44,10 @@ pub fn init_kernel_process() {
     let kernel_process = Arc::new(Process::new(
         TaskId::new().as_u64(),
         0,
-        Mutex::new(Arc::new(Mutex::new(MemorySet::new_empty()))),
+        SpinWaitNoIrq::new(Arc::new(SpinWaitNoIrq::new(MemorySet::new_empty()))),
         0,
-        vec![],
+        Arc::new(FdManager::new(vec![], FD_LIMIT_ORIGIN)),
+        Arc::new(FsContext::new()),
     ));
 
     axtask::init_scheduler();
@@ -47,6 +55,7 @@ pub fn init_kernel_process() {
         IDLE_TASK.current_ref_raw().get_unchecked()
     }));
     PID2PC.lock().insert(kernel_process.pid(), kernel_process);
+    crate::oom::init_oom();
 }
 
 /// return the `Arc<Process>` of the current process
@@ -76,25 +85,18 @@ pub fn exit_current_task(exit_code: i32) -> ! {
     );
     // 检查这个任务是否有sig_child信号
 
-    if current_task.get_sig_child() || current_task.is_leader() {
+    // 主线程退出时在进程成为僵尸进程之后再通知父进程，使父进程被唤醒时即可回收该进程
+    if current_task.get_sig_child() && !current_task.is_leader() {
         let parent = process.get_parent();
         if parent != KERNEL_PROCESS_ID {
             // 发送sigchild
             send_signal_to_process(parent as isize, 17).unwrap();
         }
     }
-    // clear_child_tid 的值不为 0，则将这个用户地址处的值写为0
+    // clear_child_tid 的值不为 0，则将这个用户地址处的值写为0，并唤醒在其上等待的线程（如 pthread_join）
     let clear_child_tid = current_task.get_clear_child_tid();
-    if clear_child_tid != 0 {
-        // 先确认是否在用户空间
-        if process
-            .manual_alloc_for_lazy(clear_child_tid.into())
-            .is_ok()
-        {
-            unsafe {
-                *(clear_child_tid as *mut i32) = 0;
-            }
-        }
+    if clear_child_tid != 0 && copy_struct_to_user(clear_child_tid, 0_i32).is_ok() {
+        futex_wake(futex_key(&process, clear_child_tid, false), 1);
     }
     if current_task.is_leader() {
         loop {
@@ -114,11 +116,16 @@ pub fn exit_current_task(exit_code: i32) -> ! {
         }
         TID2TASK.lock().remove(&curr_id);
         process.set_exit_code(exit_code);
+        let (utime, stime) = current_task.time_stat_output();
+        process.add_exit_times(utime, stime);
+
+        process.update_peak_rss();
+        crate::acct::record_exit(&process, &current_task);
 
         process.set_zombie(true);
 
         process.tasks.lock().clear();
-        process.fd_manager.fd_table.lock().clear();
+        process.fd_manager().fd_table.lock().clear();
 
         process.signal_modules.lock().clear();
 
@@ -130,14 +137,22 @@ pub fn exit_current_task(exit_code: i32) -> ! {
             child.set_parent(KERNEL_PROCESS_ID);
             kernel_process.children.lock().push(Arc::clone(child));
         }
-        if let Some(parent_process) = pid2pc.get(&process.get_parent()) {
-            parent_process.set_vfork_block(false);
+        let parent = process.get_parent();
+        if let Some(parent_process) = pid2pc.get(&parent) {
+            parent_process.finish_vfork(process.pid());
+            parent_process.notify_child_exit();
         }
         pid2pc.remove(&process.pid());
         drop(pid2pc);
+        if parent != KERNEL_PROCESS_ID {
+            // 发送sigchild
+            send_signal_to_process(parent as isize, 17).unwrap();
+        }
         drop(process);
     } else {
         TID2TASK.lock().remove(&curr_id);
+        let (utime, stime) = current_task.time_stat_output();
+        process.add_exit_times(utime, stime);
         // 从进程中删除当前线程
         let mut tasks = process.tasks.lock();
         let len = tasks.len();
@@ -155,7 +170,61 @@ pub fn exit_current_task(exit_code: i32) -> ! {
     RUN_QUEUE.lock().exit_current(exit_code);
 }
 
+/// 取出 ELF 文件中 PT_INTERP 段给出的动态链接器路径，静态链接的程序返回 None
+fn elf_interp(elf: &xmas_elf::ElfFile) -> AxResult<Option<String>> {
+    let Some(interp) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
+    else {
+        return Ok(None);
+    };
+    let interp = match interp.get_data(elf) {
+        Ok(SegmentData::Undefined(data)) => data,
+        _ => return Err(AxError::InvalidData),
+    };
+    let interp_path = from_utf8(interp).map_err(|_| AxError::InvalidData)?;
+    // remove trailing '\0'
+    Ok(Some(interp_path.trim_matches(char::from(0)).to_string()))
+}
+
+/// 解析 ELF 文件，只接受可执行文件与位置无关的可执行文件（共享对象）
+fn parse_elf(elf_data: &[u8]) -> AxResult<xmas_elf::ElfFile> {
+    let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| AxError::InvalidData)?;
+    match elf.header.pt2.type_().as_type() {
+        xmas_elf::header::Type::Executable | xmas_elf::header::Type::SharedObject => Ok(elf),
+        _ => Err(AxError::InvalidData),
+    }
+}
+
+/// 检查 `name` 能否由 [`load_app`] 加载
+///
+/// exec 在释放原有的地址空间之前调用，使得程序无法加载时 exec 仍能向调用者返回错误。
+/// 程序或其动态链接器不存在时返回 NotFound，不是合法的 ELF 文件时返回 InvalidData
+pub fn check_app(name: &str) -> AxResult<()> {
+    if name.ends_with(".sh") {
+        return Ok(());
+    }
+    let elf_data = axfs::api::read(name).map_err(|_| AxError::NotFound)?;
+    check_elf(&elf_data)
+}
+
+/// 与 [`check_app`] 相同，但检查的是已经读入内存的程序，对应 [`load_elf`]
+pub fn check_elf(elf_data: &[u8]) -> AxResult<()> {
+    let elf = parse_elf(elf_data)?;
+    if let Some(interp_path) = elf_interp(&elf)? {
+        let interp_data =
+            axfs::api::read(real_path(&interp_path).as_str()).map_err(|_| AxError::NotFound)?;
+        // 动态链接器本身不能再要求另一个动态链接器
+        if elf_interp(&parse_elf(&interp_data)?)?.is_some() {
+            return Err(AxError::InvalidData);
+        }
+    }
+    Ok(())
+}
+
 /// 返回应用程序入口，用户栈底，用户堆底
+///
+/// 动态链接的程序会转而加载其 PT_INTERP 段给出的动态链接器，程序路径作为它的第一个参数
 pub fn load_app(
     name: String,
     mut args: Vec<String>,
@@ -172,20 +241,21 @@ pub fn load_app(
         // exit(0)
         return Err(AxError::NotFound);
     };
-    let elf = xmas_elf::ElfFile::new(&elf_data).expect("Error parsing app ELF file.");
-    debug!("app elf data length: {}", elf_data.len());
-    if let Some(interp) = elf
-        .program_iter()
-        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
-    {
-        let interp = match interp.get_data(&elf) {
-            Ok(SegmentData::Undefined(data)) => data,
-            _ => panic!("Invalid data in Interp Elf Program Header"),
-        };
+    load_elf(&elf_data, args, envs, memory_set)
+}
 
-        let interp_path = from_utf8(interp).expect("Interpreter path isn't valid UTF-8");
-        // remove trailing '\0'
-        let interp_path = interp_path.trim_matches(char::from(0)).to_string();
+/// 从已经读入内存的 ELF 文件加载程序，返回值与 [`load_app`] 相同
+///
+/// 动态链接器仍然从文件系统中读取
+pub fn load_elf(
+    elf_data: &[u8],
+    mut args: Vec<String>,
+    envs: &Vec<String>,
+    memory_set: &mut MemorySet,
+) -> AxResult<(VirtAddr, VirtAddr, VirtAddr)> {
+    let elf = parse_elf(elf_data)?;
+    debug!("app elf data length: {}", elf_data.len());
+    if let Some(interp_path) = elf_interp(&elf)? {
         let real_interp_path = real_path(&interp_path);
         args = [vec![real_interp_path.clone()], args].concat();
         return load_app(real_interp_path, args, envs, memory_set);
@@ -204,15 +274,18 @@ pub fn load_app(
             segment.flags,
             segment.data.as_deref(),
             None,
-        );
+        )?;
     }
 
+    // 重定位直接写入用户地址
+    let user_memory_guard = UserMemoryGuard::new();
     for relocate_pair in relocate_pairs {
         let src: usize = relocate_pair.src.into();
         let dst: usize = relocate_pair.dst.into();
         let count = relocate_pair.count;
         unsafe { copy_nonoverlapping(src.to_ne_bytes().as_ptr(), dst as *mut u8, count) }
     }
+    drop(user_memory_guard);
 
     // Now map the stack and the heap
     let heap_start = VirtAddr::from(USER_HEAP_BASE);
@@ -223,7 +296,7 @@ pub fn load_app(
         MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
         Some(&heap_data),
         None,
-    );
+    )?;
     info!(
         "[new region] user heap: [{:?}, {:?})",
         heap_start,
@@ -235,14 +308,19 @@ pub fn load_app(
     let stack_top = VirtAddr::from(USER_STACK_TOP);
     let stack_size = MAX_USER_STACK_SIZE;
 
-    let (stack_data, stack_bottom) = get_app_stack_region(args, envs, auxv, stack_top, stack_size);
+    // AT_RANDOM 指向的随机字节与 getrandom 取自同一随机数源
+    let mut random = [0u8; 16];
+    axrandom::fill_bytes(&mut random);
+
+    let (stack_data, stack_bottom) =
+        get_app_stack_region(args, envs, auxv, &random, stack_top, stack_size);
     memory_set.new_region(
         stack_top,
         stack_size,
         MappingFlags::USER | MappingFlags::READ | MappingFlags::WRITE,
         Some(&stack_data),
         None,
-    );
+    )?;
     info!(
         "[new region] user stack: [{:?}, {:?})",
         stack_top,
@@ -254,27 +332,20 @@ pub fn load_app(
 /// 当从内核态到用户态时，统计对应进程的时间信息
 pub fn time_stat_from_kernel_to_user() {
     let curr_task = current();
-    curr_task.time_stat_from_kernel_to_user(current_time_nanos() as usize);
+    curr_task.time_stat_from_kernel_to_user(current_time());
 }
 
 #[no_mangle]
 /// 当从用户态到内核态时，统计对应进程的时间信息
 pub fn time_stat_from_user_to_kernel() {
     let curr_task = current();
-    curr_task.time_stat_from_user_to_kernel(current_time_nanos() as usize);
+    curr_task.time_stat_from_user_to_kernel(current_time());
 }
 
 /// 统计时间输出
-/// (用户态秒，用户态微秒，内核态秒，内核态微秒)
-pub fn time_stat_output() -> (usize, usize, usize, usize) {
-    let curr_task = current();
-    let (utime_ns, stime_ns) = curr_task.time_stat_output();
-    (
-        utime_ns / NANOS_PER_SEC as usize,
-        utime_ns / NANOS_PER_MICROS as usize,
-        stime_ns / NANOS_PER_SEC as usize,
-        stime_ns / NANOS_PER_MICROS as usize,
-    )
+/// (用户态时间，内核态时间)
+pub fn time_stat_output() -> (Duration, Duration) {
+    current().time_stat_output()
 }
 
 /// To deal with the page fault
@@ -286,73 +357,53 @@ pub fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
         current_process.memory_set.lock().lock().page_table_token()
     );
 
-    if current_process
-        .memory_set
-        .lock()
-        .lock()
-        .handle_page_fault(addr, flags)
-        .is_ok()
-    {
+    // 物理页帧耗尽时，先回收内存后重试
+    let ans = crate::oom::retry_on_oom("page fault", || {
+        current_process
+            .memory_set
+            .lock()
+            .lock()
+            .handle_page_fault(addr, flags)
+    });
+    if ans.is_ok() {
         axhal::arch::flush_tlb(None);
     } else {
         let _ = send_signal_to_thread(current().id().as_u64() as isize, SignalNo::SIGSEGV as isize);
     }
 }
 
-/// 在当前进程找对应的子进程，并等待子进程结束
-/// 若找到了则返回对应的pid
-/// 否则返回一个状态
-///
-/// # Safety
+/// 在当前进程找对应的子进程，若其已经退出则将其从子进程列表中移除并返回
 ///
-/// 保证传入的 ptr 是有效的
-pub unsafe fn wait_pid(pid: isize, exit_code_ptr: *mut i32) -> Result<u64, WaitStatus> {
-    // 获取当前进程
+/// `pid` 为 -1 时等待任意子进程，为 0 时等待与当前进程同组的子进程，小于 -1 时等待
+/// 进程组 `-pid` 中的子进程。退出状态由 [`Process::wait_status`] 给出。
+/// 找不到对应的子进程时返回 `NotExist`，子进程尚未退出时返回 `Running`
+pub fn wait_pid(pid: isize) -> Result<Arc<Process>, WaitStatus> {
     let curr_process = current_process();
-    let mut exit_task_id: usize = 0;
-    let mut answer_id: u64 = 0;
+    let mut children = curr_process.children.lock();
     let mut answer_status = WaitStatus::NotExist;
-    for (index, child) in curr_process.children.lock().iter().enumerate() {
-        if pid == -1 {
-            // 任意一个进程结束都可以的
-            answer_status = WaitStatus::Running;
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        // 因为没有切换页表，所以可以直接填写
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.pid();
-                break;
-            }
-        } else if child.pid() == pid as u64 {
-            // 找到了对应的进程
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{:?}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                        // 用于WEXITSTATUS设置编码
-                    }
-                }
-                answer_id = child.pid();
-            } else {
-                answer_status = WaitStatus::Running;
-            }
+    for (index, child) in children.iter().enumerate() {
+        let matched = match pid {
+            // 等待指定的子进程
+            1.. => child.pid() == pid as u64,
+            // 等待与当前进程同组的任意子进程
+            0 => child.pgid() == curr_process.pgid(),
+            // 等待任意子进程
+            -1 => true,
+            // 等待进程组 -pid 中的任意子进程
+            _ => child.pgid() == pid.unsigned_abs() as u64,
+        };
+        if !matched {
+            continue;
+        }
+        answer_status = WaitStatus::Running;
+        if let Some(exit_code) = child.get_code_if_exit() {
+            info!("wait pid _{}_ with code _{}_", child.pid(), exit_code);
+            return Ok(children.remove(index));
+        }
+        if pid > 0 {
             break;
         }
     }
-    // 若进程成功结束，需要将其从父进程的children中删除
-    if answer_status == WaitStatus::Exited {
-        curr_process.children.lock().remove(exit_task_id);
-        return Ok(answer_id);
-    }
     Err(answer_status)
 }
 
@@ -377,7 +428,42 @@ pub fn set_child_tid(tid: usize) {
     curr.set_clear_child_tid(tid);
 }
 
+/// 检查 `addr` 能否作为用户态的线程指针
+///
+/// 0 表示清除线程指针，其余地址必须位于用户地址空间内，但不要求已经映射
+pub fn check_user_tls(addr: usize) -> AxResult<()> {
+    if addr != 0 && !(USER_MEMORY_START..TASK_SIZE).contains(&addr) {
+        return Err(AxError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// 设置任务 `task` 在用户态的线程指针，clone 的 CLONE_SETTLS、新进程与 exec 均通过它设置 TLS
+///
+/// `trap_frame` 是该任务下一次返回用户态时恢复的上下文：riscv 与 aarch64 的线程指针保存在其中，
+/// x86_64 的 fs_base 则保存在任务上下文中，`task` 为当前任务时还会立即写入寄存器。
+/// `addr` 不合法时返回 `InvalidInput`，见 [`check_user_tls`]
+pub fn set_user_tls(task: &AxTaskRef, trap_frame: &mut TrapFrame, addr: usize) -> AxResult<()> {
+    check_user_tls(addr)?;
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = task;
+        trap_frame.set_tls(addr);
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        let _ = trap_frame;
+        unsafe {
+            task.set_tls_force(addr);
+            if task.id() == current().id() {
+                axhal::arch::write_thread_pointer(addr);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Get the task reference by tid
 pub fn get_task_ref(tid: u64) -> Option<AxTaskRef> {
-    TID2TASK.lock().get(&tid).cloned()
+    TID2TASK.lock().get(&tid).and_then(|task| task.upgrade())
 }
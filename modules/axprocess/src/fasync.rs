@@ -0,0 +1,268 @@
+//! 信号驱动 I/O
+//!
+//! 设置了 O_ASYNC 的文件在变为可读或可写时向属主发送信号。属主由 fcntl 的 F_SETOWN 指定，
+//! 信号默认为不带信息的 SIGIO；由 F_SETSIG 指定信号后，siginfo 中带有文件描述符与发生的事件。
+//!
+//! - 管道在读写时直接通知对端；
+//! - 套接字与控制台的就绪状态在协议栈处理收发、控制台取出输入时才会变化，它们在启用 O_ASYNC 后
+//!   被登记到对应的 [`ReadyWatch`] 中，在这些路径上检查登记的文件，在文件由不可读变为可读、
+//!   由不可写变为可写时发出通知。
+//!
+//! 属主进程只以弱引用保存，属主进程退出后即使其 pid 被复用，信号也不会发给新的进程。
+//! F_SETOWN 给出负数时属主为进程组，信号发给通知时组内的所有进程。
+extern crate alloc;
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::LinuxError;
+use axfs::api::FileIO;
+use axsignal::{
+    info::{SigInfo, SI_KERNEL},
+    signal_no::{SignalNo, MAX_SIG_NUM},
+};
+use axsync::Mutex;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{signal::send_signal_info_to_process, Process, PID2PC};
+
+/// 通知的事件，值为 siginfo 中的 si_code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum FasyncEvent {
+    /// 有数据可以读取
+    In = 1,
+    /// 可以写入
+    Out = 2,
+}
+
+impl FasyncEvent {
+    /// 事件对应的 poll 事件位，填入 siginfo 的 si_band
+    fn band(self) -> i64 {
+        const POLLIN: i64 = 0x1;
+        const POLLOUT: i64 = 0x4;
+        const POLLRDNORM: i64 = 0x40;
+        const POLLWRNORM: i64 = 0x100;
+        const POLLWRBAND: i64 = 0x200;
+        const POLLMSG: i64 = 0x400;
+        match self {
+            Self::In => POLLIN | POLLRDNORM | POLLMSG,
+            Self::Out => POLLOUT | POLLWRNORM | POLLWRBAND,
+        }
+    }
+}
+
+/// 接收通知的属主
+enum FasyncOwner {
+    /// 没有属主
+    None,
+    /// 属主进程
+    Process(Weak<Process>),
+    /// 属主进程组，值为进程组号
+    Group(u64),
+}
+
+struct FasyncInner {
+    /// 是否设置了 O_ASYNC
+    enabled: bool,
+    /// 设置 O_ASYNC 时使用的文件描述符，填入 siginfo 的 si_fd
+    fd: i32,
+    /// F_SETOWN 给出的属主，负数表示进程组
+    owner_id: i32,
+    /// 属主
+    owner: FasyncOwner,
+    /// F_SETSIG 指定的信号，0 表示 SIGIO
+    signum: usize,
+    /// 上一次检查时观察到的可读与可写状态，只用于登记到 [`ReadyWatch`] 中的文件
+    last_ready: (bool, bool),
+}
+
+/// 一个打开的文件的信号驱动 I/O 状态，由共享该文件的所有文件描述符共用
+pub struct Fasync {
+    inner: Mutex<FasyncInner>,
+}
+
+impl Default for Fasync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fasync {
+    /// 未启用 O_ASYNC、没有属主的状态
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(FasyncInner {
+                enabled: false,
+                fd: -1,
+                owner_id: 0,
+                owner: FasyncOwner::None,
+                signum: 0,
+                last_ready: (false, false),
+            }),
+        }
+    }
+
+    /// 是否设置了 O_ASYNC
+    pub fn is_async(&self) -> bool {
+        self.inner.lock().enabled
+    }
+
+    /// 设置或清除 O_ASYNC，`fd` 为此时使用的文件描述符
+    pub fn set_async(&self, enabled: bool, fd: usize) {
+        let mut inner = self.inner.lock();
+        inner.enabled = enabled;
+        if enabled {
+            inner.fd = fd as i32;
+        }
+    }
+
+    /// F_GETOWN 返回的属主
+    pub fn owner(&self) -> i32 {
+        self.inner.lock().owner_id
+    }
+
+    /// 设置属主，`owner_id` 为 0 时清除属主，为负数时属主为进程组
+    ///
+    /// 找不到对应的进程或进程组时返回 ESRCH
+    pub fn set_owner(&self, owner_id: i32) -> Result<(), LinuxError> {
+        let owner = match owner_id {
+            0 => FasyncOwner::None,
+            id if id > 0 => PID2PC
+                .lock()
+                .get(&(id as u64))
+                .map(|process| FasyncOwner::Process(Arc::downgrade(process)))
+                .ok_or(LinuxError::ESRCH)?,
+            id => {
+                let pgid = id.unsigned_abs() as u64;
+                if group_members(pgid).is_empty() {
+                    return Err(LinuxError::ESRCH);
+                }
+                FasyncOwner::Group(pgid)
+            }
+        };
+        let mut inner = self.inner.lock();
+        inner.owner_id = owner_id;
+        inner.owner = owner;
+        Ok(())
+    }
+
+    /// F_GETSIG 返回的信号，0 表示 SIGIO
+    pub fn signal(&self) -> usize {
+        self.inner.lock().signum
+    }
+
+    /// 设置就绪时发送的信号，0 表示 SIGIO，不合法的信号返回 EINVAL
+    pub fn set_signal(&self, signum: usize) -> Result<(), LinuxError> {
+        if signum > MAX_SIG_NUM {
+            return Err(LinuxError::EINVAL);
+        }
+        self.inner.lock().signum = signum;
+        Ok(())
+    }
+
+    /// 文件发生了 `event`，启用了 O_ASYNC 时向属主发送信号
+    pub fn notify(&self, event: FasyncEvent) {
+        let inner = self.inner.lock();
+        if !inner.enabled {
+            return;
+        }
+        let targets = match &inner.owner {
+            FasyncOwner::None => return,
+            FasyncOwner::Process(owner) => owner.upgrade().into_iter().collect(),
+            FasyncOwner::Group(pgid) => group_members(*pgid),
+        };
+        // 与 Linux 一样，默认的 SIGIO 不带事件信息
+        let info = match inner.signum {
+            0 => SigInfo::new(SignalNo::SIGIO as i32, SI_KERNEL),
+            signum => SigInfo::poll(signum as i32, event as i32, event.band(), inner.fd),
+        };
+        drop(inner);
+        for target in targets {
+            if !target.get_zombie() {
+                send_signal_info_to_process(&target, info.si_signo as usize, Some(info));
+            }
+        }
+    }
+
+    /// 检查时观察到的就绪状态，状态由否变为是时发出通知
+    fn ready_changed(&self, readable: bool, writable: bool) {
+        let (was_readable, was_writable) =
+            core::mem::replace(&mut self.inner.lock().last_ready, (readable, writable));
+        if readable && !was_readable {
+            self.notify(FasyncEvent::In);
+        }
+        if writable && !was_writable {
+            self.notify(FasyncEvent::Out);
+        }
+    }
+}
+
+/// 进程组 `pgid` 中尚未退出的进程
+fn group_members(pgid: u64) -> Vec<Arc<Process>> {
+    PID2PC
+        .lock()
+        .values()
+        .filter(|process| process.pgid() == pgid && !process.get_zombie())
+        .cloned()
+        .collect()
+}
+
+/// 一类不会自行通知的文件的登记表
+///
+/// 这类文件的就绪状态只在某个路径上变化（如协议栈处理收发），该路径在变化后调用 [`ReadyWatch::check`]
+pub struct ReadyWatch {
+    files: Mutex<Vec<(Weak<dyn FileIO>, Arc<Fasync>)>>,
+    /// 是否正在检查，查询就绪状态时可能再次进入该路径
+    checking: AtomicBool,
+}
+
+impl ReadyWatch {
+    /// 空的登记表
+    pub const fn new() -> Self {
+        Self {
+            files: Mutex::new(Vec::new()),
+            checking: AtomicBool::new(false),
+        }
+    }
+
+    /// 登记 `file`，`fasync` 为该文件的信号驱动 I/O 状态
+    ///
+    /// 以登记时的状态为起点，此后状态由否变为是时才发出通知。文件被关闭或清除 O_ASYNC 后自动注销
+    pub fn add(&self, file: &Arc<dyn FileIO>, fasync: &Arc<Fasync>) {
+        fasync.inner.lock().last_ready = (file.ready_to_read(), file.ready_to_write());
+        let mut files = self.files.lock();
+        if !files.iter().any(|(_, other)| Arc::ptr_eq(other, fasync)) {
+            files.push((Arc::downgrade(file), fasync.clone()));
+        }
+    }
+
+    /// 检查登记的文件，就绪状态由否变为是时发出通知
+    ///
+    /// 已经在检查时直接返回
+    pub fn check(&self) {
+        let files: Vec<_> = {
+            let mut files = self.files.lock();
+            if files.is_empty() {
+                return;
+            }
+            files.retain(|(file, fasync)| file.strong_count() > 0 && fasync.is_async());
+            files.clone()
+        };
+        if self.checking.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        for (file, fasync) in files.iter() {
+            if let Some(file) = file.upgrade() {
+                fasync.ready_changed(file.ready_to_read(), file.ready_to_write());
+            }
+        }
+        self.checking.store(false, Ordering::Release);
+    }
+}
+
+impl Default for ReadyWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,36 +1,106 @@
 //! todo 重构fd_table, fd_allocator
 extern crate alloc;
-use core::sync::atomic::{AtomicI32, AtomicU64};
+use core::sync::atomic::AtomicU64;
 
-use alloc::string::String;
-use alloc::sync::Arc;
+use alloc::{collections::BTreeMap, sync::Arc};
+use axerrno::{AxError, AxResult};
 use axfs::api::{FileIO, OpenFlags};
-use axlog::info;
+use axlog::{debug, info};
 
 use alloc::vec::Vec;
-use axsync::Mutex;
+use axsync::{Mutex, SpinWaitNoIrq};
 
 use crate::stdio::{Stdin, Stdout};
+
+/// 进程的文件描述符表
+///
+/// 以 CLONE_FILES 创建的进程与父进程共享同一个表，否则复制一份；同一进程的线程总是共享。
 pub struct FdManager {
     /// 保存文件描述符的数组
-    pub fd_table: Mutex<Vec<Option<Arc<dyn FileIO>>>>,
+    pub fd_table: SpinWaitNoIrq<Vec<Option<Arc<dyn FileIO>>>>,
     /// 保存文件描述符的数组的最大长度
     pub limit: AtomicU64,
-    /// 创建文件时的mode的掩码
-    umask: AtomicI32,
-    pub cwd: Mutex<String>,
+    /// 文件描述符自己的 close_on_exec 位
+    ///
+    /// 没有记录的文件描述符沿用打开文件时给出的 O_CLOEXEC。dup 系列与 F_SETFD 只修改这里，
+    /// 因而不影响共享同一个文件的其他文件描述符。需要同时持有时先取 `fd_table` 的锁
+    fd_cloexec: SpinWaitNoIrq<BTreeMap<usize, bool>>,
 }
 
 impl FdManager {
     pub fn new(fd_table: Vec<Option<Arc<dyn FileIO>>>, limit: usize) -> Self {
         Self {
-            fd_table: Mutex::new(fd_table),
+            fd_table: SpinWaitNoIrq::new(fd_table),
             limit: AtomicU64::new(limit as u64),
-            umask: AtomicI32::new(0o022),
-            cwd: Mutex::new(String::from("/")),
+            fd_cloexec: SpinWaitNoIrq::new(BTreeMap::new()),
+        }
+    }
+
+    /// 复制一份独立的文件描述符表，用于不带 CLONE_FILES 的 clone
+    pub fn deep_copy(&self) -> Self {
+        let fd_table = self.fd_table.lock();
+        Self {
+            fd_table: SpinWaitNoIrq::new(fd_table.clone()),
+            limit: AtomicU64::new(self.get_limit()),
+            fd_cloexec: SpinWaitNoIrq::new(self.fd_cloexec.lock().clone()),
         }
     }
 
+    /// 在 `fd_table` 中分配不小于 `floor` 的最小空闲文件描述符，必要时扩展表
+    ///
+    /// `fd_table` 是已经上锁的 [`FdManager::fd_table`]，超出资源限制时返回 `StorageFull`
+    pub fn alloc_fd_from(
+        &self,
+        fd_table: &mut Vec<Option<Arc<dyn FileIO>>>,
+        floor: usize,
+    ) -> AxResult<usize> {
+        let fd = (floor..fd_table.len())
+            .find(|&fd| fd_table[fd].is_none())
+            .unwrap_or(floor.max(fd_table.len()));
+        if fd >= self.get_limit() as usize {
+            debug!("fd table is full");
+            return Err(AxError::StorageFull);
+        }
+        if fd >= fd_table.len() {
+            fd_table.resize(fd + 1, None);
+        }
+        self.fd_cloexec.lock().remove(&fd);
+        Ok(fd)
+    }
+
+    /// 将 `file` 放到 `fd_table` 的 `fd` 处，并设置该文件描述符的 close_on_exec 位，返回原来的文件
+    ///
+    /// `fd` 不小于资源限制时返回 `InvalidInput`。原来的文件可以在释放表的锁之后再丢弃
+    pub fn insert_at(
+        &self,
+        fd_table: &mut Vec<Option<Arc<dyn FileIO>>>,
+        fd: usize,
+        file: Arc<dyn FileIO>,
+        cloexec: bool,
+    ) -> AxResult<Option<Arc<dyn FileIO>>> {
+        if fd >= self.get_limit() as usize {
+            return Err(AxError::InvalidInput);
+        }
+        if fd >= fd_table.len() {
+            fd_table.resize(fd + 1, None);
+        }
+        self.fd_cloexec.lock().insert(fd, cloexec);
+        Ok(fd_table[fd].replace(file))
+    }
+
+    /// 文件描述符 `fd` 在 exec 时是否关闭，`file` 是 `fd` 对应的文件
+    pub fn is_close_on_exec(&self, fd: usize, file: &Arc<dyn FileIO>) -> bool {
+        match self.fd_cloexec.lock().get(&fd) {
+            Some(&cloexec) => cloexec,
+            None => file.get_status().is_close_on_exec(),
+        }
+    }
+
+    /// 设置文件描述符 `fd` 的 close_on_exec 位（F_SETFD）
+    pub fn set_close_on_exec(&self, fd: usize, cloexec: bool) {
+        self.fd_cloexec.lock().insert(fd, cloexec);
+    }
+
     pub fn get_limit(&self) -> u64 {
         self.limit.load(core::sync::atomic::Ordering::Acquire)
     }
@@ -40,17 +110,14 @@ impl FdManager {
             .store(new_limit, core::sync::atomic::Ordering::Release)
     }
 
-    #[allow(unused)]
-    pub fn get_mask(&self) -> i32 {
-        self.umask.load(core::sync::atomic::Ordering::Acquire)
-    }
-
-    /// 设置新的 mask，返回旧的 mask
-    pub fn set_mask(&self, new_mask: i32) -> i32 {
-        let old_mask = self.umask.load(core::sync::atomic::Ordering::Acquire);
-        self.umask
-            .store(new_mask, core::sync::atomic::Ordering::Release);
-        old_mask
+    /// 从文件描述符表中移除 `fd`，返回被移除的文件
+    ///
+    /// 返回的文件在调用者释放之后才会真正关闭，因此可以在释放文件描述符表的锁之后再丢弃
+    pub fn remove(&self, fd: usize) -> Option<Arc<dyn FileIO>> {
+        let mut fd_table = self.fd_table.lock();
+        let file = fd_table.get_mut(fd)?.take()?;
+        self.fd_cloexec.lock().remove(&fd);
+        Some(file)
     }
 
     /// 在执行 `exec()` 时关闭标记为 `CLOEXEC` 的文件
@@ -58,15 +125,17 @@ impl FdManager {
         let mut fd_table = self.fd_table.lock();
         for (index, fd) in fd_table.iter_mut().enumerate() {
             if let Some(f) = fd {
-                if f.get_status().is_close_on_exec() {
+                if self.is_close_on_exec(index, f) {
                     info!("close fd: {} on exec", index);
                     fd.take();
                 }
             }
         }
+        self.fd_cloexec.lock().retain(|_, cloexec| !*cloexec);
         if fd_table[0].is_none() {
             fd_table[0] = Some(Arc::new(Stdin {
                 flags: Mutex::new(OpenFlags::empty()),
+                fasync: Arc::default(),
             }));
         }
         if fd_table[1].is_none() {
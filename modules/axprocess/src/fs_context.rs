@@ -0,0 +1,161 @@
+//! 进程的文件系统上下文
+//!
+//! 包括根目录、当前工作目录与创建文件时的 umask，路径解析与创建文件时均从这里读取。
+//! 以 CLONE_FS 创建的进程与父进程共享同一个上下文，否则复制一份；同一进程的线程总是共享。
+//! exec 时上下文保持不变。
+extern crate alloc;
+use alloc::{format, string::String};
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use axfs::api::canonicalize;
+use axsync::SpinWaitNoIrq;
+
+/// 进程默认的 umask
+const DEFAULT_UMASK: i32 = 0o022;
+
+/// 进程的文件系统上下文
+pub struct FsContext {
+    /// 根目录在整个文件系统中的绝对路径，以 '/' 结尾
+    root: SpinWaitNoIrq<String>,
+    /// 当前工作目录，是相对于根目录的绝对路径，以 '/' 结尾
+    cwd: SpinWaitNoIrq<String>,
+    /// 创建文件时的 mode 的掩码
+    umask: AtomicI32,
+}
+
+impl Default for FsContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsContext {
+    /// 根目录与工作目录均为 '/' 的上下文
+    pub fn new() -> Self {
+        Self {
+            root: SpinWaitNoIrq::new(String::from("/")),
+            cwd: SpinWaitNoIrq::new(String::from("/")),
+            umask: AtomicI32::new(DEFAULT_UMASK),
+        }
+    }
+
+    /// 复制一份独立的上下文，用于不带 CLONE_FS 的 clone
+    pub fn deep_copy(&self) -> Self {
+        Self {
+            root: SpinWaitNoIrq::new(self.root()),
+            cwd: SpinWaitNoIrq::new(self.cwd()),
+            umask: AtomicI32::new(self.umask()),
+        }
+    }
+
+    /// 根目录在整个文件系统中的绝对路径
+    pub fn root(&self) -> String {
+        self.root.lock().clone()
+    }
+
+    /// 当前工作目录，相对于根目录给出
+    pub fn cwd(&self) -> String {
+        self.cwd.lock().clone()
+    }
+
+    /// getcwd 返回的工作目录：规范的绝对路径，除根目录外不以 '/' 结尾
+    ///
+    /// chdir 时路径已经过规范化与链接解析，因此这里得到的路径不含 "."、".." 或链接
+    pub fn canonical_cwd(&self) -> String {
+        let mut cwd = self.cwd();
+        if cwd.len() > 1 {
+            cwd.pop();
+        }
+        cwd
+    }
+
+    /// 获取当前的 umask
+    pub fn umask(&self) -> i32 {
+        self.umask.load(Ordering::Acquire)
+    }
+
+    /// 设置新的 umask，返回旧的 umask
+    pub fn set_umask(&self, new_mask: i32) -> i32 {
+        self.umask.swap(new_mask & 0o777, Ordering::AcqRel)
+    }
+
+    /// 以 umask 屏蔽创建文件时给出的 mode
+    pub fn apply_umask(&self, mode: u32) -> u32 {
+        mode & !(self.umask() as u32)
+    }
+
+    /// 将相对于当前工作目录或根目录的路径转换为整个文件系统中的绝对路径
+    ///
+    /// 路径中的 ".." 不会越过根目录，`path` 以 '/' 结尾时结果也以 '/' 结尾
+    pub fn resolve(&self, path: &str) -> String {
+        let path_in_root = if path.starts_with('/') {
+            String::from(path)
+        } else {
+            format!("{}{}", self.cwd(), path)
+        };
+        let mut resolved = canonicalize(&path_in_root).unwrap_or(path_in_root);
+        let root = self.root();
+        if root != "/" {
+            resolved = format!("{}{}", root.trim_end_matches('/'), resolved);
+        }
+        if path.ends_with('/') && !resolved.ends_with('/') {
+            resolved.push('/');
+        }
+        resolved
+    }
+
+    /// 切换当前工作目录
+    ///
+    /// `path` 是由 [`FsContext::resolve`] 得到的路径，应当已经确认是一个目录
+    pub fn set_cwd(&self, path: &str) {
+        let root = self.root();
+        let path = strip_root(path, &root).unwrap_or(path);
+        let mut cwd = canonicalize(path).unwrap_or_else(|_| String::from(path));
+        if !cwd.starts_with('/') {
+            cwd.insert(0, '/');
+        }
+        if !cwd.ends_with('/') {
+            cwd.push('/');
+        }
+        *self.cwd.lock() = cwd;
+    }
+
+    /// 切换根目录，之后的工作目录为新的根目录
+    ///
+    /// `path` 是由 [`FsContext::resolve`] 得到的路径，应当已经确认是一个目录
+    pub fn set_root(&self, path: &str) {
+        let mut root = String::from(path);
+        if !root.ends_with('/') {
+            root.push('/');
+        }
+        *self.root.lock() = root;
+        *self.cwd.lock() = String::from("/");
+    }
+}
+
+/// 去掉 `path` 开头的根目录 `root`，得到相对于根目录的路径
+///
+/// 按路径分量比较，`path` 不在 `root` 之下时返回 None
+fn strip_root<'a>(path: &'a str, root: &str) -> Option<&'a str> {
+    let root = root.trim_end_matches('/');
+    let rest = path.strip_prefix(root)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_root;
+
+    #[test]
+    fn test_strip_root() {
+        assert_eq!(strip_root("/root/dir/", "/root/"), Some("/dir/"));
+        assert_eq!(strip_root("/root", "/root/"), Some(""));
+        assert_eq!(strip_root("/dir/", "/"), Some("/dir/"));
+        // 只有前缀相同的兄弟目录不在根目录之下
+        assert_eq!(strip_root("/rootfoo/dir/", "/root/"), None);
+    }
+}
@@ -1,13 +1,58 @@
 //! 实现与futex相关的系统调用
+//!
+//! 等待者按 [`FutexKey`] 挂在全局的等待表中。私有 futex 以及不在共享内存中的 futex
+//! 以地址空间与虚拟地址为键，共享同一地址空间的线程可以互相唤醒；
+//! 不带 FUTEX_PRIVATE_FLAG 且位于共享内存中的 futex 以物理地址为键，
+//! 不同进程映射同一块共享内存时也能互相唤醒。
+//!
+//! 检查 futex 的值与加入等待表在同一把锁下完成，唤醒者修改值之后才会取这把锁，因此不会丢失唤醒。
 use alloc::collections::{BTreeMap, VecDeque};
-use axhal::mem::VirtAddr;
+use alloc::sync::Arc;
+use axerrno::LinuxError;
 use axsync::Mutex;
 use axtask::{AxTaskRef, WaitQueue};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use crate::uaccess::copy_struct_from_user;
+use crate::{current_task, Process};
 
 extern crate alloc;
 
-/// vec中的元素分别是任务指针,对应存储时的futex变量的值
-pub static FUTEX_WAIT_TASK: Mutex<BTreeMap<VirtAddr, VecDeque<(AxTaskRef, u32)>>> =
+/// futex 等待表的键
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FutexKey {
+    /// 地址空间与其中的虚拟地址，地址空间以其 `MemorySet` 的地址标识
+    Private {
+        /// 地址空间的标识
+        mm: usize,
+        /// futex 的虚拟地址
+        addr: usize,
+    },
+    /// 共享内存中 futex 的物理地址
+    Shared {
+        /// futex 的物理地址
+        paddr: usize,
+    },
+}
+
+/// 等待表中的一个等待者
+pub struct FutexWaiter {
+    /// 等待的任务
+    pub task: AxTaskRef,
+    /// 被 FUTEX_WAKE 或 FUTEX_REQUEUE 唤醒时置位
+    woken: Arc<AtomicBool>,
+}
+
+impl FutexWaiter {
+    fn wake(self) {
+        self.woken.store(true, Ordering::Release);
+        WAIT_FOR_FUTEX.notify_task(false, &self.task);
+    }
+}
+
+/// futex 的等待表，每个键对应一个先进先出的等待队列
+pub static FUTEX_WAIT_TASK: Mutex<BTreeMap<FutexKey, VecDeque<FutexWaiter>>> =
     Mutex::new(BTreeMap::new());
 
 /// waiting queue which stores tasks waiting for futex variable
@@ -29,6 +74,133 @@ impl FutexRobustList {
     }
 }
 
+/// 计算 `process` 中地址 `uaddr` 处 futex 的键
+///
+/// `private` 为 true（FUTEX_PRIVATE_FLAG）时不必查找共享内存
+pub fn futex_key(process: &Process, uaddr: usize, private: bool) -> FutexKey {
+    let memory_set = Arc::clone(&process.memory_set.lock());
+    if !private {
+        if let Some(paddr) = memory_set.lock().shared_mem_paddr(uaddr.into()) {
+            return FutexKey::Shared {
+                paddr: paddr.as_usize(),
+            };
+        }
+    }
+    FutexKey::Private {
+        mm: Arc::as_ptr(&memory_set) as usize,
+        addr: uaddr,
+    }
+}
+
+/// 若 `uaddr` 处的值等于 `val`，则在 `key` 上等待，直到被唤醒、超时或被信号打断
+///
+/// 值不相等时返回 EAGAIN，超时返回 ETIMEDOUT，被信号打断返回 EINTR
+pub fn futex_wait(
+    key: FutexKey,
+    uaddr: usize,
+    val: u32,
+    timeout: Option<Duration>,
+) -> Result<(), LinuxError> {
+    let curr = current_task();
+    let woken = Arc::new(AtomicBool::new(false));
+    {
+        let mut futex_wait_task = FUTEX_WAIT_TASK.lock();
+        if copy_struct_from_user::<u32>(uaddr)? != val {
+            return Err(LinuxError::EAGAIN);
+        }
+        futex_wait_task
+            .entry(key)
+            .or_default()
+            .push_back(FutexWaiter {
+                task: curr.as_task_ref().clone(),
+                woken: Arc::clone(&woken),
+            });
+    }
+
+    let condition = || woken.load(Ordering::Acquire) || curr.take_woken_by_signal();
+    let timed_out = match timeout {
+        Some(dur) => WAIT_FOR_FUTEX.wait_timeout_until(dur, condition),
+        None => {
+            WAIT_FOR_FUTEX.wait_until(condition);
+            false
+        }
+    };
+    if woken.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    // 超时或被信号打断，此时可能已被 requeue 到别的键上，需要在整个表中查找自己
+    let mut futex_wait_task = FUTEX_WAIT_TASK.lock();
+    if woken.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    for waiters in futex_wait_task.values_mut() {
+        waiters.retain(|waiter| !Arc::ptr_eq(&waiter.woken, &woken));
+    }
+    futex_wait_task.retain(|_, waiters| !waiters.is_empty());
+    Err(if timed_out {
+        LinuxError::ETIMEDOUT
+    } else {
+        LinuxError::EINTR
+    })
+}
+
+/// 唤醒至多 `nr_wake` 个在 `key` 上等待的任务，返回唤醒的数目
+pub fn futex_wake(key: FutexKey, nr_wake: usize) -> usize {
+    let mut futex_wait_task = FUTEX_WAIT_TASK.lock();
+    let woken = match futex_wait_task.get_mut(&key) {
+        Some(waiters) => wake_waiters(waiters, nr_wake),
+        None => 0,
+    };
+    futex_wait_task.retain(|_, waiters| !waiters.is_empty());
+    woken
+}
+
+/// 唤醒至多 `nr_wake` 个在 `src` 上等待的任务，再将至多 `nr_requeue` 个剩余的等待者移动到 `dst` 上
+///
+/// `expected` 为 `Some((uaddr, val))` 时（FUTEX_CMP_REQUEUE）先确认 `uaddr` 处的值等于 `val`，
+/// 否则返回 EAGAIN。返回唤醒与移动的数目。
+pub fn futex_requeue(
+    src: FutexKey,
+    dst: FutexKey,
+    nr_wake: usize,
+    nr_requeue: usize,
+    expected: Option<(usize, u32)>,
+) -> Result<(usize, usize), LinuxError> {
+    let mut futex_wait_task = FUTEX_WAIT_TASK.lock();
+    if let Some((uaddr, val)) = expected {
+        if copy_struct_from_user::<u32>(uaddr)? != val {
+            return Err(LinuxError::EAGAIN);
+        }
+    }
+    let Some(src_waiters) = futex_wait_task.get_mut(&src) else {
+        return Ok((0, 0));
+    };
+    let woken = wake_waiters(src_waiters, nr_wake);
+    let mut moved = VecDeque::new();
+    if src != dst {
+        let nr_requeue = nr_requeue.min(src_waiters.len());
+        moved = src_waiters.drain(..nr_requeue).collect();
+    }
+    let requeued = moved.len();
+    if requeued > 0 {
+        futex_wait_task.entry(dst).or_default().append(&mut moved);
+    }
+    futex_wait_task.retain(|_, waiters| !waiters.is_empty());
+    Ok((woken, requeued))
+}
+
+fn wake_waiters(waiters: &mut VecDeque<FutexWaiter>, nr_wake: usize) -> usize {
+    let mut woken = 0;
+    while woken < nr_wake {
+        match waiters.pop_front() {
+            Some(waiter) => waiter.wake(),
+            None => break,
+        }
+        woken += 1;
+    }
+    woken
+}
+
 /// 退出的时候清空指针
 ///
 /// 若当前线程是主线程，代表进程退出，此时传入的id是进程id，要清除所有进程下的线程
@@ -39,18 +211,15 @@ pub fn clear_wait(id: u64, leader: bool) {
 
     if leader {
         // 清空所有所属进程为指定进程的线程
-        futex_wait_task.iter_mut().for_each(|(_, tasks)| {
-            // tasks.drain_filter(|task| task.get_process_id() == id);
-            tasks.retain(|(task, _)| task.get_process_id() != id);
+        futex_wait_task.iter_mut().for_each(|(_, waiters)| {
+            waiters.retain(|waiter| waiter.task.get_process_id() != id);
         });
     } else {
-        futex_wait_task.iter_mut().for_each(|(_, tasks)| {
-            // tasks.drain_filter(|task| task.id().as_u64() == id);
-            tasks.retain(|(task, _)| task.id().as_u64() != id)
+        futex_wait_task.iter_mut().for_each(|(_, waiters)| {
+            waiters.retain(|waiter| waiter.task.id().as_u64() != id);
         });
     }
 
     // 如果一个共享变量不会被线程所使用了，那么直接把他移除
-    // info!("clean pre keys: {:?}", futex_wait_task.keys());
-    futex_wait_task.retain(|_, tasks| !tasks.is_empty());
+    futex_wait_task.retain(|_, waiters| !waiters.is_empty());
 }
@@ -0,0 +1,689 @@
+//! 控制台的行规程
+//!
+//! 控制台的输入在这里按照 termios 的设置处理后再交给读者：规范模式下按行编辑、回显，
+//! 非规范模式下按 VMIN/VTIME 决定读操作何时返回；输出时按 OPOST/ONLCR 转换换行。
+//! 输入设备只能轮询，因此读操作在等待时反复从设备中取出字符并让出 CPU，收到信号时返回 EINTR。
+extern crate alloc;
+use alloc::{collections::VecDeque, vec::Vec};
+
+use axerrno::{AxError, AxResult};
+use spinlock::SpinNoIrq;
+
+/// 输入标志：忽略输入中的 '\r'
+pub const IGNCR: u32 = 0o200;
+/// 输入标志：将输入中的 '\r' 转换为 '\n'
+pub const ICRNL: u32 = 0o400;
+/// 输入标志：将输入中的 '\n' 转换为 '\r'
+pub const INLCR: u32 = 0o100;
+/// 输出标志：开启输出处理
+pub const OPOST: u32 = 0o1;
+/// 输出标志：将输出中的 '\n' 转换为 "\r\n"
+pub const ONLCR: u32 = 0o4;
+/// 本地标志：收到 INTR、QUIT 等字符时产生信号
+pub const ISIG: u32 = 0o1;
+/// 本地标志：规范模式
+pub const ICANON: u32 = 0o2;
+/// 本地标志：回显输入的字符
+pub const ECHO: u32 = 0o10;
+/// 本地标志：ERASE 字符擦除前一个字符
+pub const ECHOE: u32 = 0o20;
+/// 本地标志：KILL 字符擦除当前行
+pub const ECHOK: u32 = 0o40;
+/// 本地标志：即使不回显也回显换行
+pub const ECHONL: u32 = 0o100;
+
+/// `c_cc` 中 ERASE 字符的下标
+pub const VERASE: usize = 2;
+/// `c_cc` 中 KILL 字符的下标
+pub const VKILL: usize = 3;
+/// `c_cc` 中 EOF 字符的下标
+pub const VEOF: usize = 4;
+/// `c_cc` 中 TIME 的下标，单位为 0.1 秒
+pub const VTIME: usize = 5;
+/// `c_cc` 中 MIN 的下标
+pub const VMIN: usize = 6;
+
+/// `c_cc` 的长度
+const NCCS: usize = 19;
+
+/// 输入缓冲区的大小，超出的输入被丢弃
+const INPUT_BUF_SIZE: usize = 4096;
+
+/// VTIME 的单位对应的纳秒数
+const NANOS_PER_VTIME: u64 = 100_000_000;
+
+/// 终端的设置，与 Linux 内核中 TCGETS 使用的 `struct termios` 布局一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Termios {
+    /// 输入标志
+    pub c_iflag: u32,
+    /// 输出标志
+    pub c_oflag: u32,
+    /// 控制标志
+    pub c_cflag: u32,
+    /// 本地标志
+    pub c_lflag: u32,
+    /// 行规程编号
+    pub c_line: u8,
+    /// 控制字符
+    pub c_cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// 与 Linux 的默认设置相同：规范模式、回显，输入的 '\r' 转换为 '\n'，输出的 '\n' 转换为 "\r\n"
+    pub const fn new() -> Self {
+        let mut c_cc = [0; NCCS];
+        c_cc[0] = 0x03; // VINTR: ^C
+        c_cc[1] = 0x1c; // VQUIT: ^\
+        c_cc[VERASE] = 0x7f;
+        c_cc[VKILL] = 0x15; // ^U
+        c_cc[VEOF] = 0x04; // ^D
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        Self {
+            c_iflag: ICRNL,
+            c_oflag: OPOST | ONLCR,
+            // B38400 | CS8 | CREAD | HUPCL
+            c_cflag: 0o2277,
+            c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK,
+            c_line: 0,
+            c_cc,
+        }
+    }
+
+    fn iflag(&self, flag: u32) -> bool {
+        self.c_iflag & flag != 0
+    }
+
+    fn oflag(&self, flag: u32) -> bool {
+        self.c_oflag & flag != 0
+    }
+
+    fn lflag(&self, flag: u32) -> bool {
+        self.c_lflag & flag != 0
+    }
+}
+
+impl Default for Termios {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 终端窗口的大小，与 `struct winsize` 布局一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WinSize {
+    /// 行数
+    pub ws_row: u16,
+    /// 列数
+    pub ws_col: u16,
+    /// 宽度（像素）
+    pub ws_xpixel: u16,
+    /// 高度（像素）
+    pub ws_ypixel: u16,
+}
+
+/// 修改 termios 的时机，对应 TCSETS、TCSETSW 与 TCSETSF
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetTermiosMode {
+    /// 立即修改
+    Now,
+    /// 等待已写入的输出全部发送后再修改
+    Drain,
+    /// 等待输出发送完毕，并丢弃尚未读取的输入后再修改
+    Flush,
+}
+
+/// 行规程使用的终端设备
+pub trait TtyDevice {
+    /// 读取一个输入的字符，没有输入时返回 `None`
+    fn getchar(&self) -> Option<u8>;
+    /// 输出字符，返回时字符已经发送完毕
+    fn write_bytes(&self, bytes: &[u8]);
+    /// 当前时间（纳秒）
+    fn now_nanos(&self) -> u64;
+    /// 等待输入时调用，让出 CPU
+    ///
+    /// 有待处理的信号时返回 `Interrupted`，读操作随之返回，交由信号处理流程处理
+    fn wait(&self) -> AxResult<()>;
+    /// 取出了新的输入之后调用
+    fn input_received(&self) {}
+}
+
+/// 行规程的状态
+struct LineDiscipline {
+    termios: Termios,
+    winsize: WinSize,
+    /// 前台进程组
+    fg_pgrp: u32,
+    /// 非规范模式下可以读取的输入
+    input: VecDeque<u8>,
+    /// 规范模式下已经结束、可以读取的行，以 EOF 结束的行不含结尾的字符
+    lines: VecDeque<Vec<u8>>,
+    /// 规范模式下正在编辑的行
+    line: Vec<u8>,
+}
+
+impl LineDiscipline {
+    const fn new() -> Self {
+        Self {
+            termios: Termios::new(),
+            winsize: WinSize {
+                ws_row: 0,
+                ws_col: 0,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            },
+            fg_pgrp: 0,
+            input: VecDeque::new(),
+            lines: VecDeque::new(),
+            line: Vec::new(),
+        }
+    }
+
+    /// 尚未读取的输入的字节数
+    fn buffered(&self) -> usize {
+        self.input.len() + self.lines.iter().map(Vec::len).sum::<usize>() + self.line.len()
+    }
+
+    /// 可以立即读取的字节数
+    fn readable(&self) -> usize {
+        if self.termios.lflag(ICANON) {
+            self.lines.iter().map(Vec::len).sum()
+        } else {
+            self.input.len()
+        }
+    }
+
+    /// 按输出标志处理要输出的字符
+    fn process_output(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        if self.termios.oflag(OPOST) && self.termios.oflag(ONLCR) {
+            for &byte in bytes {
+                if byte == b'\n' {
+                    out.push(b'\r');
+                }
+                out.push(byte);
+            }
+        } else {
+            out.extend_from_slice(bytes);
+        }
+    }
+
+    /// 处理一个输入的字符，需要回显的内容追加到 `echo` 中
+    fn receive(&mut self, mut ch: u8, echo: &mut Vec<u8>) {
+        let termios = self.termios;
+        if ch == b'\r' {
+            if termios.iflag(IGNCR) {
+                return;
+            }
+            if termios.iflag(ICRNL) {
+                ch = b'\n';
+            }
+        } else if ch == b'\n' && termios.iflag(INLCR) {
+            ch = b'\r';
+        }
+        if self.buffered() >= INPUT_BUF_SIZE {
+            return;
+        }
+        let echo_on = termios.lflag(ECHO);
+        if !termios.lflag(ICANON) {
+            self.input.push_back(ch);
+            if echo_on {
+                self.process_output(&[ch], echo);
+            }
+            return;
+        }
+        if ch == termios.c_cc[VERASE] {
+            if self.line.pop().is_some() && echo_on {
+                if termios.lflag(ECHOE) {
+                    echo.extend_from_slice(b"\x08 \x08");
+                } else {
+                    echo.push(ch);
+                }
+            }
+        } else if ch == termios.c_cc[VKILL] {
+            let erased = self.line.len();
+            self.line.clear();
+            if echo_on && termios.lflag(ECHOK) {
+                if termios.lflag(ECHOE) {
+                    for _ in 0..erased {
+                        echo.extend_from_slice(b"\x08 \x08");
+                    }
+                } else {
+                    echo.push(ch);
+                    self.process_output(b"\n", echo);
+                }
+            }
+        } else if ch == termios.c_cc[VEOF] {
+            // EOF 结束当前行但不放入行中，空行被读到时 read 返回 0
+            self.lines.push_back(core::mem::take(&mut self.line));
+        } else if ch == b'\n' {
+            self.line.push(ch);
+            self.lines.push_back(core::mem::take(&mut self.line));
+            if echo_on || termios.lflag(ECHONL) {
+                self.process_output(b"\n", echo);
+            }
+        } else {
+            self.line.push(ch);
+            if echo_on {
+                self.process_output(&[ch], echo);
+            }
+        }
+    }
+
+    /// 规范模式下读取一行，没有完整的行时返回 `None`
+    fn read_line(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let mut line = self.lines.pop_front()?;
+        let len = buf.len().min(line.len());
+        buf[..len].copy_from_slice(&line[..len]);
+        // 缓冲区放不下的部分留到下一次读取
+        if len < line.len() {
+            self.lines.push_front(line.split_off(len));
+        }
+        Some(len)
+    }
+
+    /// 非规范模式下读取已有的输入
+    fn read_raw(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.input.len());
+        for (dst, src) in buf.iter_mut().zip(self.input.drain(..len)) {
+            *dst = src;
+        }
+        len
+    }
+
+    /// 切换 termios，规范模式与非规范模式之间切换时移动已有的输入
+    fn set_termios(&mut self, termios: Termios) {
+        let was_canonical = self.termios.lflag(ICANON);
+        self.termios = termios;
+        match (was_canonical, termios.lflag(ICANON)) {
+            (true, false) => {
+                for line in self.lines.drain(..) {
+                    self.input.extend(line);
+                }
+                self.input.extend(self.line.drain(..));
+            }
+            (false, true) => self.line.extend(self.input.drain(..)),
+            _ => {}
+        }
+    }
+
+    fn flush_input(&mut self) {
+        self.input.clear();
+        self.lines.clear();
+        self.line.clear();
+    }
+}
+
+/// 一个终端：终端设备加上行规程
+pub struct TtyCore<D: TtyDevice> {
+    device: D,
+    ldisc: SpinNoIrq<LineDiscipline>,
+}
+
+impl<D: TtyDevice> TtyCore<D> {
+    /// 以默认的 termios 创建终端
+    pub const fn new(device: D) -> Self {
+        Self {
+            device,
+            ldisc: SpinNoIrq::new(LineDiscipline::new()),
+        }
+    }
+
+    /// 取出设备中的全部输入交给行规程，返回取出的字符数
+    fn pump(&self) -> usize {
+        let mut count = 0;
+        let mut echo = Vec::new();
+        while let Some(ch) = self.device.getchar() {
+            self.ldisc.lock().receive(ch, &mut echo);
+            count += 1;
+        }
+        if !echo.is_empty() {
+            self.device.write_bytes(&echo);
+        }
+        if count > 0 {
+            self.device.input_received();
+        }
+        count
+    }
+
+    /// 读取输入，`non_block` 为 true 时没有输入则返回 `WouldBlock`
+    pub fn read(&self, buf: &mut [u8], non_block: bool) -> AxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let termios = self.termios();
+        if termios.lflag(ICANON) {
+            loop {
+                self.pump();
+                if let Some(len) = self.ldisc.lock().read_line(buf) {
+                    return Ok(len);
+                }
+                if non_block {
+                    return Err(AxError::WouldBlock);
+                }
+                self.device.wait()?;
+            }
+        }
+        if non_block {
+            self.pump();
+            return match self.ldisc.lock().read_raw(buf) {
+                0 => Err(AxError::WouldBlock),
+                len => Ok(len),
+            };
+        }
+        self.read_noncanonical(
+            buf,
+            termios.c_cc[VMIN] as usize,
+            termios.c_cc[VTIME] as u64 * NANOS_PER_VTIME,
+        )
+    }
+
+    /// 非规范模式下阻塞的读取，按 VMIN 与 VTIME 的四种组合决定何时返回
+    ///
+    /// - MIN = 0, TIME = 0：立即返回已有的输入，可能为 0 字节
+    /// - MIN > 0, TIME = 0：直到有 MIN 个字节（不超过 `buf` 的长度）才返回
+    /// - MIN = 0, TIME > 0：有输入即返回，TIME 内没有输入则返回 0
+    /// - MIN > 0, TIME > 0：至少等到一个字节，之后凑满 MIN 个字节或两个字节之间的间隔超过 TIME 时返回
+    fn read_noncanonical(&self, buf: &mut [u8], min: usize, time: u64) -> AxResult<usize> {
+        let min = min.min(buf.len());
+        let start = self.device.now_nanos();
+        // 最近一次收到输入的时间，MIN 与 TIME 均大于 0 时用于计算字节间的间隔
+        let mut last_input = start;
+        loop {
+            if self.pump() > 0 {
+                last_input = self.device.now_nanos();
+            }
+            let available = self.ldisc.lock().input.len();
+            let done = match (min, time) {
+                (0, 0) => true,
+                (_, 0) => available >= min,
+                (0, _) => available > 0 || self.device.now_nanos() - start >= time,
+                _ => {
+                    available >= min
+                        || (available > 0 && self.device.now_nanos() - last_input >= time)
+                }
+            };
+            if done {
+                return Ok(self.ldisc.lock().read_raw(buf));
+            }
+            self.device.wait()?;
+        }
+    }
+
+    /// 写入输出，按输出标志转换后交给设备
+    pub fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let mut out = Vec::with_capacity(buf.len());
+        self.ldisc.lock().process_output(buf, &mut out);
+        self.device.write_bytes(&out);
+        Ok(buf.len())
+    }
+
+    /// 可以立即读取的字节数
+    pub fn readable_bytes(&self) -> usize {
+        self.pump();
+        self.ldisc.lock().readable()
+    }
+
+    /// 当前的 termios
+    pub fn termios(&self) -> Termios {
+        self.ldisc.lock().termios
+    }
+
+    /// 修改 termios
+    ///
+    /// 输出总是同步写入设备，因此 `Drain` 无需等待；`Flush` 还会丢弃尚未读取的输入
+    pub fn set_termios(&self, termios: Termios, mode: SetTermiosMode) {
+        let mut ldisc = self.ldisc.lock();
+        if mode == SetTermiosMode::Flush {
+            ldisc.flush_input();
+        }
+        ldisc.set_termios(termios);
+    }
+
+    /// 当前的窗口大小
+    pub fn winsize(&self) -> WinSize {
+        self.ldisc.lock().winsize
+    }
+
+    /// 修改窗口大小，返回大小是否发生了变化，变化时调用者应向前台进程组发送 SIGWINCH
+    pub fn set_winsize(&self, winsize: WinSize) -> bool {
+        let mut ldisc = self.ldisc.lock();
+        let changed = ldisc.winsize != winsize;
+        ldisc.winsize = winsize;
+        changed
+    }
+
+    /// 前台进程组
+    pub fn fg_pgrp(&self) -> u32 {
+        self.ldisc.lock().fg_pgrp
+    }
+
+    /// 设置前台进程组
+    pub fn set_fg_pgrp(&self, pgrp: u32) {
+        self.ldisc.lock().fg_pgrp = pgrp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    const MILLI: u64 = 1_000_000;
+
+    /// 按预定的时间产生输入的设备，每次等待时钟前进 1 毫秒
+    struct FakeDevice {
+        now: Cell<u64>,
+        /// (到达时间, 字符)
+        script: RefCell<VecDeque<(u64, u8)>>,
+        output: RefCell<Vec<u8>>,
+        /// 信号到达的时间，此后的等待都被打断
+        signal_at: Cell<Option<u64>>,
+    }
+
+    impl FakeDevice {
+        fn new(script: &[(u64, u8)]) -> Self {
+            Self {
+                now: Cell::new(0),
+                script: RefCell::new(script.iter().copied().collect()),
+                output: RefCell::new(Vec::new()),
+                signal_at: Cell::new(None),
+            }
+        }
+    }
+
+    impl TtyDevice for FakeDevice {
+        fn getchar(&self) -> Option<u8> {
+            let mut script = self.script.borrow_mut();
+            match script.front() {
+                Some(&(at, ch)) if at <= self.now.get() => {
+                    script.pop_front();
+                    Some(ch)
+                }
+                _ => None,
+            }
+        }
+
+        fn write_bytes(&self, bytes: &[u8]) {
+            self.output.borrow_mut().extend_from_slice(bytes);
+        }
+
+        fn now_nanos(&self) -> u64 {
+            self.now.get()
+        }
+
+        fn wait(&self) -> AxResult<()> {
+            if self.signal_at.get().is_some_and(|at| at <= self.now.get()) {
+                return Err(AxError::Interrupted);
+            }
+            self.now.set(self.now.get() + MILLI);
+            Ok(())
+        }
+    }
+
+    fn raw_tty(script: &[(u64, u8)], min: u8, time: u8) -> TtyCore<FakeDevice> {
+        let tty = TtyCore::new(FakeDevice::new(script));
+        let mut termios = tty.termios();
+        termios.c_lflag &= !(ICANON | ECHO);
+        termios.c_cc[VMIN] = min;
+        termios.c_cc[VTIME] = time;
+        tty.set_termios(termios, SetTermiosMode::Now);
+        tty
+    }
+
+    #[test]
+    fn test_raw_byte_at_a_time() {
+        let tty = raw_tty(&[(5 * MILLI, b'a'), (20 * MILLI, b'b')], 1, 0);
+        let mut buf = [0; 8];
+        assert_eq!(tty.read(&mut buf, false), Ok(1));
+        assert_eq!(buf[0], b'a');
+        assert_eq!(tty.device.now.get(), 5 * MILLI);
+        assert_eq!(tty.read(&mut buf, false), Ok(1));
+        assert_eq!(buf[0], b'b');
+        assert_eq!(tty.device.now.get(), 20 * MILLI);
+    }
+
+    #[test]
+    fn test_raw_poll() {
+        // MIN = 0, TIME = 0：没有输入时立即返回 0
+        let tty = raw_tty(&[(5 * MILLI, b'a')], 0, 0);
+        let mut buf = [0; 8];
+        assert_eq!(tty.read(&mut buf, false), Ok(0));
+        assert_eq!(tty.device.now.get(), 0);
+    }
+
+    #[test]
+    fn test_raw_read_timeout() {
+        // MIN = 0, TIME = 1：100 毫秒内没有输入则返回 0
+        let tty = raw_tty(&[(250 * MILLI, b'x')], 0, 1);
+        let mut buf = [0; 8];
+        assert_eq!(tty.read(&mut buf, false), Ok(0));
+        assert_eq!(tty.device.now.get(), 100 * MILLI);
+        assert_eq!(tty.read(&mut buf, false), Ok(0));
+        assert_eq!(tty.device.now.get(), 200 * MILLI);
+        // 输入在超时之前到达时立即返回
+        assert_eq!(tty.read(&mut buf, false), Ok(1));
+        assert_eq!(buf[0], b'x');
+        assert_eq!(tty.device.now.get(), 250 * MILLI);
+    }
+
+    #[test]
+    fn test_raw_interbyte_timer() {
+        // MIN = 4, TIME = 1：字节间隔不超过 100 毫秒时继续等待，超过后返回已有的字节
+        let tty = raw_tty(
+            &[
+                (300 * MILLI, b'a'),
+                (350 * MILLI, b'b'),
+                (420 * MILLI, b'c'),
+                (900 * MILLI, b'd'),
+            ],
+            4,
+            1,
+        );
+        let mut buf = [0; 8];
+        // 第一个字节之前不计时
+        assert_eq!(tty.read(&mut buf, false), Ok(3));
+        assert_eq!(&buf[..3], b"abc");
+        assert_eq!(tty.device.now.get(), 520 * MILLI);
+        assert_eq!(tty.read(&mut buf, false), Ok(1));
+        assert_eq!(buf[0], b'd');
+        assert_eq!(tty.device.now.get(), 1000 * MILLI);
+    }
+
+    #[test]
+    fn test_raw_min_bytes() {
+        // MIN = 2, TIME = 0：凑满 2 个字节才返回
+        let tty = raw_tty(
+            &[(MILLI, b'a'), (50 * MILLI, b'b'), (60 * MILLI, b'c')],
+            2,
+            0,
+        );
+        let mut buf = [0; 8];
+        assert_eq!(tty.read(&mut buf, false), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(tty.device.now.get(), 50 * MILLI);
+    }
+
+    #[test]
+    fn test_read_interrupted() {
+        // MIN = 1, TIME = 0：没有输入时一直等待，直到信号到达
+        let tty = raw_tty(&[(50 * MILLI, b'a')], 1, 0);
+        tty.device.signal_at.set(Some(10 * MILLI));
+        let mut buf = [0; 8];
+        assert_eq!(tty.read(&mut buf, false), Err(AxError::Interrupted));
+        assert_eq!(tty.device.now.get(), 10 * MILLI);
+        // 规范模式下等待一整行时同样会被打断
+        let tty = TtyCore::new(FakeDevice::new(&[(0, b'l'), (50 * MILLI, b'\r')]));
+        tty.device.signal_at.set(Some(10 * MILLI));
+        assert_eq!(tty.read(&mut buf, false), Err(AxError::Interrupted));
+        tty.device.signal_at.set(None);
+        assert_eq!(tty.read(&mut buf, false), Ok(2));
+        assert_eq!(&buf[..2], b"l\n");
+    }
+
+    #[test]
+    fn test_canonical_editing() {
+        let tty = TtyCore::new(FakeDevice::new(&[
+            (0, b'l'),
+            (0, b's'),
+            (0, b'x'),
+            (0, 0x7f),
+            (0, b'\r'),
+            (0, b'p'),
+            (0, 0x15),
+            (0, 0x04),
+        ]));
+        let mut buf = [0; 8];
+        // '\r' 被转换为 '\n'，ERASE 擦除了 'x'
+        assert_eq!(tty.read(&mut buf, false), Ok(3));
+        assert_eq!(&buf[..3], b"ls\n");
+        assert_eq!(
+            &tty.device.output.borrow()[..],
+            b"lsx\x08 \x08\r\np\x08 \x08"
+        );
+        // KILL 擦除了 'p'，之后的 EOF 使 read 返回 0
+        assert_eq!(tty.read(&mut buf, false), Ok(0));
+    }
+
+    #[test]
+    fn test_output_translation() {
+        let tty = TtyCore::new(FakeDevice::new(&[]));
+        tty.write(b"a\nb").unwrap();
+        assert_eq!(&tty.device.output.borrow()[..], b"a\r\nb");
+        let mut termios = tty.termios();
+        termios.c_oflag &= !ONLCR;
+        tty.set_termios(termios, SetTermiosMode::Now);
+        tty.write(b"\n").unwrap();
+        assert_eq!(&tty.device.output.borrow()[..], b"a\r\nb\n");
+    }
+
+    #[test]
+    fn test_set_termios_flush() {
+        let tty = raw_tty(&[(0, b'a'), (0, b'b')], 0, 0);
+        assert_eq!(tty.readable_bytes(), 2);
+        let termios = tty.termios();
+        // TCSETSW 保留尚未读取的输入，TCSETSF 丢弃
+        tty.set_termios(termios, SetTermiosMode::Drain);
+        assert_eq!(tty.readable_bytes(), 2);
+        tty.set_termios(termios, SetTermiosMode::Flush);
+        assert_eq!(tty.readable_bytes(), 0);
+    }
+
+    #[test]
+    fn test_set_winsize() {
+        let tty = TtyCore::new(FakeDevice::new(&[]));
+        let winsize = WinSize {
+            ws_row: 24,
+            ws_col: 80,
+            ..Default::default()
+        };
+        assert!(tty.set_winsize(winsize));
+        assert!(!tty.set_winsize(winsize));
+        assert_eq!(tty.winsize(), winsize);
+    }
+}
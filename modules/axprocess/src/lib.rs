@@ -6,11 +6,19 @@ pub use api::*;
 mod process;
 pub use process::{Process, PID2PC, TID2TASK};
 
+pub mod acct;
+pub mod fasync;
 pub mod flags;
 pub mod futex;
 pub mod link;
+pub mod oom;
+pub mod ldisc;
 mod stdio;
+pub use stdio::{console_fasync, Tty, CONSOLE_WATCH};
 
 mod fd_manager;
+mod fs_context;
+pub use fs_context::FsContext;
 
 pub mod signal;
+pub mod uaccess;
@@ -1,15 +1,16 @@
 //! 模拟的链接、挂载模块
 //! fat32本身不支持符号链接和硬链接，两个指向相同文件的目录条目将会被chkdsk报告为交叉链接并修复
 extern crate alloc;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::format;
 use alloc::string::{String, ToString};
-use axerrno::{AxError, AxResult};
+use axerrno::{AxError, AxResult, LinuxError};
 use axfs::api::{canonicalize, path_exists, remove_file, FileIOType};
 use axlog::{debug, info, trace};
 use axsync::Mutex;
 
 use crate::current_process;
+use crate::uaccess::user_path;
 #[allow(unused)]
 /// The file descriptor used to specify the current working directory of a process
 pub const AT_FDCWD: usize = -100isize as usize;
@@ -112,44 +113,104 @@ impl FilePath {
         self.0.ends_with(other.0.as_str())
     }
 }
-#[allow(unused)]
-/// # Safety
+
+/// 用户看到的文件到实际文件的映射
+pub static LINK_PATH_MAP: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+/// 实际文件(而不是用户文件)到链接数的映射
+pub static LINK_COUNT_MAP: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+/// 符号链接到其内容的映射，键是链接在整个文件系统中的绝对路径
 ///
-/// The caller must ensure that the pointer is valid and points to a valid C string.
-/// The string must be null-terminated.
-pub unsafe fn get_str_len(start: *const u8) -> usize {
-    let mut ptr = start as usize;
-    while *(ptr as *const u8) != 0 {
-        ptr += 1;
+/// 与硬链接一样，符号链接只保存在内存中，不写入文件系统
+pub static SYMLINK_MAP: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// 解析一个路径时最多跟随的符号链接数，超过时返回 ELOOP
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// 创建内容为 `target` 的符号链接 `link`，创建时不检查 `target` 是否存在
+///
+/// `link` 已经存在时返回 EEXIST，所在目录不存在时返回 ENOENT
+pub fn create_symlink(target: &str, link: &FilePath) -> Result<(), LinuxError> {
+    let path = link.path().trim_end_matches('/');
+    let mut map = SYMLINK_MAP.lock();
+    if path.is_empty() || path_exists(path) || map.contains_key(path) {
+        return Err(LinuxError::EEXIST);
+    }
+    match link.dir() {
+        Ok(dir) if path_exists(dir) => {}
+        _ => return Err(LinuxError::ENOENT),
     }
-    ptr - start as usize
+    map.insert(path.to_string(), target.to_string());
+    Ok(())
 }
 
-#[allow(unused)]
-/// # Safety
+/// 读取符号链接的内容，`path` 不是符号链接时返回 None
+pub fn read_symlink(path: &str) -> Option<String> {
+    SYMLINK_MAP.lock().get(path).cloned()
+}
+
+/// 删除符号链接本身，返回 `path` 是否是一个符号链接
+pub fn remove_symlink(path: &str) -> bool {
+    SYMLINK_MAP.lock().remove(path).is_some()
+}
+
+/// 逐个路径分量解析 `path` 中的符号链接，返回不含符号链接的绝对路径
 ///
-/// The caller must ensure that the pointer is valid and points to a valid C string.
-pub unsafe fn raw_ptr_to_ref_str(start: *const u8) -> &'static str {
-    let len = unsafe { get_str_len(start) };
-    // 因为这里直接用用户空间提供的虚拟地址来访问，所以一定能连续访问到字符串，不需要考虑物理地址是否连续
-    let slice = unsafe { core::slice::from_raw_parts(start, len) };
-    if let Ok(s) = core::str::from_utf8(slice) {
-        s
-    } else {
-        axlog::error!("not utf8 slice");
-        for c in slice {
-            axlog::error!("{c} ");
+/// `path` 是整个文件系统中的绝对路径。最后一个分量只在 `follow_last` 为真时解析，
+/// 结果保留 `path` 末尾的 '/'。链接的内容是绝对路径时从进程的根目录开始解析，
+/// 否则从链接所在的目录开始解析，跟随的链接过多时返回 ELOOP
+pub fn follow_symlinks(path: &str, follow_last: bool) -> Result<String, LinuxError> {
+    if SYMLINK_MAP.lock().is_empty() {
+        return Ok(path.to_string());
+    }
+    let root = current_process().fs_context.root();
+    let map = SYMLINK_MAP.lock();
+    let mut pending: VecDeque<String> = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .map(String::from)
+        .collect();
+    // 已经解析的部分，总是以 '/' 结尾
+    let mut resolved = String::from("/");
+    let mut follows = 0;
+    while let Some(component) = pending.pop_front() {
+        match component.as_str() {
+            "." => continue,
+            ".." => {
+                if resolved.len() > 1 {
+                    resolved.pop();
+                    resolved.truncate(resolved.rfind('/').map_or(0, |pos| pos + 1));
+                }
+                continue;
+            }
+            _ => {}
         }
-        axlog::error!("");
-        ""
+        let candidate = format!("{}{}", resolved, component);
+        match map.get(&candidate) {
+            Some(target) if !pending.is_empty() || follow_last => {
+                follows += 1;
+                if follows > MAX_SYMLINK_FOLLOWS {
+                    return Err(LinuxError::ELOOP);
+                }
+                if target.starts_with('/') {
+                    resolved = root.clone();
+                }
+                for component in target.rsplit('/').filter(|c| !c.is_empty()) {
+                    pending.push_front(String::from(component));
+                }
+            }
+            _ => {
+                resolved = candidate;
+                resolved.push('/');
+            }
+        }
+    }
+    if resolved.len() > 1 && !path.ends_with('/') {
+        resolved.pop();
     }
+    Ok(resolved)
 }
 
-/// 用户看到的文件到实际文件的映射
-pub static LINK_PATH_MAP: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
-/// 实际文件(而不是用户文件)到链接数的映射
-pub static LINK_COUNT_MAP: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
-
 /// 将用户提供的路径转换成实际的路径
 ///
 /// 如果在链接列表中找不到，则直接返回自己
@@ -298,25 +359,41 @@ pub fn deal_with_path(
     path_addr: Option<*const u8>,
     force_dir: bool,
 ) -> Option<FilePath> {
+    let path = match path_addr {
+        Some(path_addr) => match user_path(path_addr as usize) {
+            Ok(path) => path,
+            Err(err) => {
+                axlog::warn!("invalid path address {:?}: {:?}", path_addr, err);
+                return None;
+            }
+        },
+        None => String::new(),
+    };
+    deal_with_path_str(dir_fd, path, force_dir)
+}
+
+/// The same as [`deal_with_path`], but the path has already been copied from user space
+pub fn deal_with_path_str(dir_fd: usize, path: String, force_dir: bool) -> Option<FilePath> {
+    resolve_path_at(dir_fd, path, force_dir).ok()
+}
+
+/// The same as [`deal_with_path_str`], but reports why the path can't be resolved
+///
+/// Relative paths are resolved against `dir_fd`, or against the current working directory
+/// if it is AT_FDCWD. Absolute paths ignore `dir_fd`.
+///
+/// Returns `EBADF` if `dir_fd` is needed but not open, `ENOTDIR` if it is needed to
+/// resolve a relative path but is not a directory, and `ENOENT` if the path is invalid.
+pub fn resolve_path_at(
+    dir_fd: usize,
+    mut path: String,
+    force_dir: bool,
+) -> Result<FilePath, LinuxError> {
     let process = current_process();
-    let mut path = "".to_string();
-    if let Some(path_addr) = path_addr {
-        if path_addr.is_null() {
-            axlog::warn!("path address is null");
-            return None;
-        }
-        if process
-            .manual_alloc_for_lazy((path_addr as usize).into())
-            .is_ok()
-        {
-            // 直接访问前需要确保已经被分配
-            path = unsafe { raw_ptr_to_ref_str(path_addr) }.to_string().clone();
-        } else {
-            axlog::warn!("path address is invalid");
-            return None;
-        }
-    }
 
+    // 绝对路径以及相对于 AT_FDCWD 的路径需要根据进程的根目录与工作目录解析，
+    // 而从 dir_fd 得到的路径已经是整个文件系统中的路径
+    let mut in_fs_context = path.starts_with('/');
     if path.is_empty() {
         // If pathname is an empty string, in this case, dirfd can refer to any type of file, not just a directory
         // and the behavior of fstatat() is similar to that of fstat()
@@ -324,46 +401,45 @@ pub fn deal_with_path(
         if dir_fd == AT_FDCWD && dir_fd as u32 == AT_FDCWD as u32 {
             // return Some(FilePath::new(".").unwrap());
             path = String::from(".");
+            in_fs_context = true;
         } else {
-            let fd_table = process.fd_manager.fd_table.lock();
-            if dir_fd >= fd_table.len() {
-                axlog::warn!("fd index out of range");
-                return None;
-            }
-            match fd_table[dir_fd].as_ref() {
-                Some(dir) => {
+            let fd_manager = process.fd_manager();
+            let fd_table = fd_manager.fd_table.lock();
+            match fd_table.get(dir_fd) {
+                Some(Some(dir)) => {
                     let dir = dir.clone();
                     path = dir.get_path();
                 }
-                None => {
-                    axlog::warn!("fd not exist");
-                    return None;
+                _ => {
+                    axlog::warn!("fd {} not exist", dir_fd);
+                    return Err(LinuxError::EBADF);
                 }
             }
         }
     } else if !path.starts_with('/') && dir_fd != AT_FDCWD && dir_fd as u32 != AT_FDCWD as u32 {
         // 如果不是绝对路径, 且dir_fd不是AT_FDCWD, 则需要将dir_fd和path拼接起来
-        let fd_table = process.fd_manager.fd_table.lock();
-        if dir_fd >= fd_table.len() {
-            axlog::warn!("fd index out of range");
-            return None;
-        }
-        match fd_table[dir_fd].as_ref() {
-            Some(dir) => {
+        let fd_manager = process.fd_manager();
+        let fd_table = fd_manager.fd_table.lock();
+        match fd_table.get(dir_fd) {
+            Some(Some(dir)) => {
                 if dir.get_type() != FileIOType::DirDesc {
                     axlog::warn!("selected fd {} is not a dir", dir_fd);
-                    return None;
+                    return Err(LinuxError::ENOTDIR);
+                }
+                let mut dir_path = dir.get_path();
+                if !dir_path.ends_with('/') {
+                    dir_path.push('/');
                 }
-                let dir = dir.clone();
-                // 有没有可能dir的尾部一定是一个/号，所以不用手工添加/
-                path = format!("{}{}", dir.get_path(), path);
+                path = format!("{}{}", dir_path, path);
                 axlog::warn!("handled_path: {}", path);
             }
-            None => {
-                axlog::warn!("fd not exist");
-                return None;
+            _ => {
+                axlog::warn!("fd {} not exist", dir_fd);
+                return Err(LinuxError::EBADF);
             }
         }
+    } else if !path.starts_with('/') {
+        in_fs_context = true;
     }
     if force_dir && !path.ends_with('/') {
         path = format!("{}/", path);
@@ -372,11 +448,22 @@ pub fn deal_with_path(
         // 如果path以.或..结尾, 则加上/告诉FilePath::new它是一个目录
         path = format!("{}/", path);
     }
-    match FilePath::new(path.as_str()) {
-        Ok(path) => Some(path),
-        Err(err) => {
+    if in_fs_context {
+        path = process.fs_context.resolve(&path);
+    }
+    let to_file_path = |path: &str| {
+        FilePath::new(path).map_err(|err| {
             axlog::warn!("error when creating FilePath: {:?}", err);
-            None
-        }
+            LinuxError::ENOENT
+        })
+    };
+    let file_path = to_file_path(&path)?;
+    // 逐个分量解析路径中的符号链接，以 '/' 结尾的路径最后一个分量也需要解析
+    let follow_last = file_path.path().ends_with('/');
+    let resolved = follow_symlinks(file_path.path(), follow_last)?;
+    if resolved == file_path.path() {
+        Ok(file_path)
+    } else {
+        to_file_path(&resolved)
     }
 }
@@ -0,0 +1,219 @@
+//! 物理内存耗尽时的 OOM killer
+//!
+//! 当无法分配到物理页帧时，选出常驻内存最大的用户进程并向其发送 SIGKILL，
+//! 等待其释放内存后再重试分配。
+//!
+//! 杀死进程之前先逐出页缓存中不再被映射的页面，并回收被 MADV_FREE 标记的页面，见 [`reclaim_pages`]。
+//!
+//! 缺页、访问用户内存与 mmap 在释放地址空间的锁后通过 [`retry_on_oom`] 重试；
+//! 内核自身的分配（例如扩充内核堆）在分配器中直接调用 [`init_oom`] 注册的处理函数。
+extern crate alloc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axhal::time::current_time_nanos;
+use axhal::KERNEL_PROCESS_ID;
+use axlog::{info, warn};
+use axmem::MemorySet;
+use axsignal::signal_no::SignalNo;
+use axsync::SpinWaitNoIrq;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::process::{Process, PID2PC};
+use crate::signal::send_signal_to_process;
+use crate::{current_process, yield_now_task};
+
+/// 正在分配器中处理 OOM 的任务 id，0 表示没有
+static OOM_OWNER: AtomicU64 = AtomicU64::new(0);
+
+/// oom_score_adj 的最小值，此时进程永远不会被选中
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+
+/// oom_score_adj 的最大值
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// 等待牺牲进程退出的最长时间
+const OOM_WAIT_NANOS: u64 = 100_000_000;
+
+/// 每次回收时最多逐出的页缓存页数
+const RECLAIM_BATCH: usize = 256;
+
+/// 计算进程的 badness，分数越高越优先被杀死
+///
+/// 返回 None 表示该进程不参与选择
+fn oom_badness(process: &Process) -> Option<usize> {
+    let adj = process.get_oom_score_adj();
+    // 内核进程与初始进程（直接由内核进程创建的用户进程）不参与选择
+    if process.pid() == KERNEL_PROCESS_ID
+        || process.get_parent() == KERNEL_PROCESS_ID
+        || process.get_zombie()
+        || adj == OOM_SCORE_ADJ_MIN
+    {
+        return None;
+    }
+    let rss = process.memory_set.lock().lock().rss_pages();
+    if rss == 0 {
+        return None;
+    }
+    // 以 rss 为基准，按 adj 的千分比进行偏置
+    Some(rss * (adj - OOM_SCORE_ADJ_MIN) as usize / 1000)
+}
+
+/// 选出 badness 最高的进程
+fn select_victim() -> Option<(Arc<Process>, usize)> {
+    let processes: Vec<Arc<Process>> = PID2PC.lock().values().cloned().collect();
+    let mut victim: Option<(Arc<Process>, usize)> = None;
+    for process in processes {
+        if let Some(points) = oom_badness(&process) {
+            if victim.as_ref().map_or(true, |(_, best)| points > *best) {
+                victim = Some((process, points));
+            }
+        }
+    }
+    victim
+}
+
+/// 所有存活进程的地址空间，共享同一地址空间的进程只出现一次
+fn live_memory_sets() -> Vec<Arc<SpinWaitNoIrq<MemorySet>>> {
+    let processes: Vec<Arc<Process>> = PID2PC.lock().values().cloned().collect();
+    let mut memory_sets = Vec::new();
+    for process in processes {
+        if process.get_zombie() {
+            continue;
+        }
+        let memory_set = Arc::clone(&process.memory_set.lock());
+        if !memory_sets
+            .iter()
+            .any(|seen: &Arc<_>| Arc::ptr_eq(seen, &memory_set))
+        {
+            memory_sets.push(memory_set);
+        }
+    }
+    memory_sets
+}
+
+/// 物理内存耗尽时先于 OOM killer 调用，逐出至多 [`RECLAIM_BATCH`] 个页缓存中不再被映射的页面，
+/// 并回收所有进程中被 MADV_FREE 标记的页面
+///
+/// 返回回收的页数，为 0 时调用者应当继续调用 [`out_of_memory`]。
+/// 调用时不能持有任何进程地址空间的锁。
+pub fn reclaim_pages() -> usize {
+    let evicted = axmem::shrink_page_cache(RECLAIM_BATCH);
+    let lazy_freed: usize = live_memory_sets()
+        .iter()
+        .map(|memory_set| memory_set.lock().reclaim_lazy_free())
+        .sum();
+    if evicted + lazy_freed > 0 {
+        info!(
+            "[oom] evicted {} page cache pages, reclaimed {} lazily freed pages",
+            evicted, lazy_freed
+        );
+    }
+    evicted + lazy_freed
+}
+
+/// 物理内存耗尽时调用，杀死一个用户进程以回收内存
+///
+/// `trigger` 说明触发 OOM 的原因，仅用于日志。
+///
+/// 若成功选出并杀死了牺牲进程，返回 true，调用者可以重试分配。
+/// 若牺牲进程正是当前进程，则只发送信号并返回 false，交由信号处理流程退出。
+pub fn out_of_memory(trigger: &str) -> bool {
+    let (victim, _) = match select_victim() {
+        Some(ans) => ans,
+        None => {
+            warn!("[oom] {}: no killable process", trigger);
+            return false;
+        }
+    };
+    let rss = victim.memory_set.lock().lock().rss_pages();
+    warn!(
+        "[oom] {}: kill process {} ({}), rss: {} pages",
+        trigger,
+        victim.pid(),
+        victim.get_file_path(),
+        rss
+    );
+    if send_signal_to_process(victim.pid() as isize, SignalNo::SIGKILL as isize).is_err() {
+        return false;
+    }
+    if victim.pid() == current_process().pid() {
+        return false;
+    }
+    // 短暂等待牺牲进程退出
+    let deadline = current_time_nanos() + OOM_WAIT_NANOS;
+    while !victim.get_zombie() && current_time_nanos() < deadline {
+        yield_now_task();
+    }
+    if !victim.get_zombie() {
+        warn!("[oom] process {} did not exit in time", victim.pid());
+        return false;
+    }
+    // 进程已退出但尚未被回收，若地址空间不再共享，则提前释放其用户内存
+    let memory_set = victim.memory_set.lock();
+    if Arc::strong_count(&memory_set) == 1 {
+        memory_set.lock().unmap_user_areas();
+    }
+    info!(
+        "[oom] reclaimed {} pages from process {}",
+        rss,
+        victim.pid()
+    );
+    true
+}
+
+/// 执行可能因物理页帧耗尽而失败的操作，失败时依次回收 MADV_FREE 页面、杀死进程后重试
+///
+/// `trigger` 说明触发 OOM 的原因，仅用于日志。调用时不能持有任何进程地址空间的锁。
+///
+/// 只有操作期间页帧分配器确实分配失败过，才会回收内存并重试，
+/// 因此超出地址空间或 overcommit 限制等其他原因导致的 NoMemory 会直接返回。
+pub fn retry_on_oom<T>(trigger: &str, mut op: impl FnMut() -> AxResult<T>) -> AxResult<T> {
+    let failed = axalloc::failed_page_allocs();
+    let ans = op();
+    if !matches!(ans, Err(AxError::NoMemory)) || axalloc::failed_page_allocs() == failed {
+        return ans;
+    }
+    if reclaim_pages() > 0 {
+        let ans = op();
+        if !matches!(ans, Err(AxError::NoMemory)) {
+            return ans;
+        }
+    }
+    if out_of_memory(trigger) {
+        return op();
+    }
+    ans
+}
+
+/// 页帧分配器的 OOM 处理函数，内核自身的分配失败时调用
+///
+/// 当前任务不能阻塞（例如持有地址空间的锁）或进程表正被占用时直接返回 false，
+/// 由调用者释放锁后通过 [`retry_on_oom`] 处理。
+fn kernel_alloc_oom(num_pages: usize) -> bool {
+    if !axtask::can_block() || PID2PC.is_locked() {
+        return false;
+    }
+    let curr_id = axtask::current().id().as_u64();
+    match OOM_OWNER.compare_exchange(0, curr_id, Ordering::Acquire, Ordering::Acquire) {
+        Ok(_) => {}
+        // 处理 OOM 时又分配失败，不能递归
+        Err(owner) if owner == curr_id => return false,
+        // 其他任务正在处理 OOM，等待其完成后重试
+        Err(_) => {
+            while OOM_OWNER.load(Ordering::Acquire) != 0 {
+                yield_now_task();
+            }
+            return true;
+        }
+    }
+    warn!("[oom] kernel failed to allocate {} pages", num_pages);
+    let freed = reclaim_pages() > 0 || out_of_memory("kernel allocation");
+    OOM_OWNER.store(0, Ordering::Release);
+    freed
+}
+
+/// 向页帧分配器注册 OOM 处理函数
+pub fn init_oom() {
+    axalloc::set_oom_handler(kernel_alloc_oom);
+}
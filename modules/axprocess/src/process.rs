@@ -10,29 +10,38 @@ use axhal::arch::{
     read_trapframe_from_kstack, write_page_table_root0, write_trapframe_to_kstack, TrapFrame,
 };
 use axhal::mem::{phys_to_virt, VirtAddr};
+use axhal::paging::MappingFlags;
 
-use axhal::time::current_time_nanos;
+use axhal::time::{current_time, current_time_nanos, Duration};
 use axhal::KERNEL_PROCESS_ID;
 use axlog::{debug, error};
 use axmem::MemorySet;
-use axsync::Mutex;
-use axtask::{current, new_task, AxTaskRef, TaskId, RUN_QUEUE};
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use axsync::{Mutex, SpinWaitNoIrq};
+use axtask::{
+    current, new_task, vfork_suspend, wake_vfork_process, AxTaskRef, TaskId, WaitQueue,
+    WeakAxTaskRef, RUN_QUEUE,
+};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicUsize, Ordering};
 
 use crate::fd_manager::FdManager;
 use crate::flags::CloneFlags;
+use crate::fs_context::FsContext;
 use crate::futex::FutexRobustList;
+use crate::oom::{retry_on_oom, OOM_SCORE_ADJ_MAX, OOM_SCORE_ADJ_MIN};
 
 use crate::signal::SignalModule;
 use crate::stdio::{Stderr, Stdin, Stdout};
-use crate::{load_app, yield_now_task};
+use crate::uaccess::copy_struct_to_user;
+use crate::{load_app, load_elf, set_user_tls};
 
-/// Map from task id to arc pointer of task
-pub static TID2TASK: Mutex<BTreeMap<u64, AxTaskRef>> = Mutex::new(BTreeMap::new());
+/// Map from task id to weak pointer of task
+///
+/// 任务由所属进程的 `tasks` 持有强引用，这里只保存弱引用，避免注册表延长任务的生命周期
+pub static TID2TASK: Mutex<BTreeMap<u64, WeakAxTaskRef>> = Mutex::new(BTreeMap::new());
 
 /// Map from process id to arc pointer of process
 pub static PID2PC: Mutex<BTreeMap<u64, Arc<Process>>> = Mutex::new(BTreeMap::new());
-const FD_LIMIT_ORIGIN: usize = 1025;
+pub(crate) const FD_LIMIT_ORIGIN: usize = 1025;
 
 extern "C" {
     fn start_signal_trampoline();
@@ -46,14 +55,26 @@ pub struct Process {
     /// 父进程号
     pub parent: AtomicU64,
 
+    /// 进程组号，新进程继承父进程的进程组
+    pgid: AtomicU64,
+
     /// 子进程
     pub children: Mutex<Vec<Arc<Process>>>,
 
+    /// 子进程退出的次数，wait4 据此判断等待期间是否有子进程退出
+    child_exits: AtomicUsize,
+
+    /// 在 wait4 中等待子进程退出的任务
+    child_exit_wq: WaitQueue,
+
     /// 所管理的线程
     pub tasks: Mutex<Vec<AxTaskRef>>,
 
-    /// 文件描述符管理器
-    pub fd_manager: FdManager,
+    /// 文件描述符管理器，以 CLONE_FILES 创建的进程与父进程共享，见 [`Process::fd_manager`]
+    fd_manager: SpinWaitNoIrq<Arc<FdManager>>,
+
+    /// 文件系统上下文，以 CLONE_FS 创建的进程与父进程共享
+    pub fs_context: Arc<FsContext>,
 
     /// 进程状态
     pub is_zombie: AtomicBool,
@@ -61,8 +82,14 @@ pub struct Process {
     /// 退出状态码
     pub exit_code: AtomicI32,
 
+    /// 被信号终止时的信号编号，正常退出时为 0
+    exit_signal: AtomicI32,
+
+    /// 退出时主线程在用户态与内核态的运行时间，由父进程的 wait4 返回
+    exit_times: Mutex<(Duration, Duration)>,
+
     /// 地址空间
-    pub memory_set: Mutex<Arc<Mutex<MemorySet>>>,
+    pub memory_set: SpinWaitNoIrq<Arc<SpinWaitNoIrq<MemorySet>>>,
 
     /// 用户堆基址，任何时候堆顶都不能比这个值小，理论上讲是一个常量
     pub heap_bottom: AtomicU64,
@@ -79,11 +106,23 @@ pub struct Process {
     /// 具体使用交给了用户空间
     pub robust_list: Mutex<BTreeMap<u64, FutexRobustList>>,
 
-    /// 是否被vfork阻塞
-    pub blocked_by_vfork: Mutex<bool>,
+    /// 以 vfork 方式创建、尚未 exec 或退出的子进程号，为 0 时表示未被 vfork 阻塞
+    vfork_child: AtomicU64,
 
     /// 该进程可执行文件所在的路径
     pub file_path: Mutex<String>,
+
+    /// 是否拥有控制终端，目前控制终端即为控制台
+    pub has_ctty: AtomicBool,
+
+    /// OOM 时选择牺牲进程的偏置值，范围为 [-1000, 1000]
+    pub oom_score_adj: AtomicI32,
+
+    /// 进程创建的时间（纳秒）
+    start_time: u64,
+
+    /// 常驻内存的峰值（页数），在释放内存之前更新
+    peak_rss_pages: AtomicUsize,
 }
 
 impl Process {
@@ -102,6 +141,34 @@ impl Process {
         self.parent.store(parent, Ordering::Release)
     }
 
+    /// get the process group id
+    pub fn pgid(&self) -> u64 {
+        self.pgid.load(Ordering::Acquire)
+    }
+
+    /// set the process group id
+    pub fn set_pgid(&self, pgid: u64) {
+        self.pgid.store(pgid, Ordering::Release)
+    }
+
+    /// 子进程退出的次数，与 [`Process::wait_child_exit`] 配合使用
+    pub fn child_exits(&self) -> usize {
+        self.child_exits.load(Ordering::Acquire)
+    }
+
+    /// 子进程成为僵尸进程后调用，唤醒在 wait4 中等待的任务
+    pub(crate) fn notify_child_exit(&self) {
+        self.child_exits.fetch_add(1, Ordering::AcqRel);
+        self.child_exit_wq.notify_all(false);
+    }
+
+    /// 等待子进程退出次数超过 `seen`，期间收到信号时提前返回
+    pub fn wait_child_exit(&self, seen: usize) {
+        let curr = current();
+        self.child_exit_wq
+            .wait_until(|| self.child_exits() != seen || curr.take_woken_by_signal());
+    }
+
     /// get the exit code of the process
     pub fn get_exit_code(&self) -> i32 {
         self.exit_code.load(Ordering::Acquire)
@@ -112,6 +179,40 @@ impl Process {
         self.exit_code.store(exit_code, Ordering::Release)
     }
 
+    /// 记录进程被信号 `signal` 终止，在进程退出之前调用
+    pub fn set_exit_signal(&self, signal: i32) {
+        self.exit_signal.store(signal, Ordering::Release)
+    }
+
+    /// wait 系列系统调用返回的状态，编码与 Linux 的 W* 宏一致
+    ///
+    /// 正常退出时退出码的低 8 位位于状态的 8~15 位，被信号终止时状态的低 7 位为信号编号
+    pub fn wait_status(&self) -> i32 {
+        match self.exit_signal.load(Ordering::Acquire) {
+            0 => (self.get_exit_code() & 0xff) << 8,
+            signal => signal & 0x7f,
+        }
+    }
+
+    /// 进程的文件描述符表
+    ///
+    /// 以 CLONE_FILES 创建的进程共享同一个表，直到其中一个进程执行 exec
+    pub fn fd_manager(&self) -> Arc<FdManager> {
+        Arc::clone(&self.fd_manager.lock())
+    }
+
+    /// 累计一个退出的线程的运行时间
+    pub(crate) fn add_exit_times(&self, utime: Duration, stime: Duration) {
+        let mut exit_times = self.exit_times.lock();
+        exit_times.0 += utime;
+        exit_times.1 += stime;
+    }
+
+    /// 已经退出的线程在用户态与内核态的运行时间之和，主线程退出后即为整个进程的运行时间
+    pub fn exit_times(&self) -> (Duration, Duration) {
+        *self.exit_times.lock()
+    }
+
     /// whether the process is a zombie process
     pub fn get_zombie(&self) -> bool {
         self.is_zombie.load(Ordering::Acquire)
@@ -142,9 +243,21 @@ impl Process {
         self.heap_bottom.store(bottom, Ordering::Release)
     }
 
-    /// set the process as blocked by vfork
-    pub fn set_vfork_block(&self, value: bool) {
-        *self.blocked_by_vfork.lock() = value;
+    /// 记录以 vfork 方式创建的子进程，此后进程被阻塞直到该子进程 exec 或退出
+    pub fn set_vfork_child(&self, child: u64) {
+        self.vfork_child.store(child, Ordering::Release)
+    }
+
+    /// 是否正在等待以 vfork 方式创建的子进程
+    pub fn is_vfork_blocked(&self) -> bool {
+        self.vfork_child.load(Ordering::Acquire) != 0
+    }
+
+    /// 子进程 `child` exec 或退出时调用，若其为 vfork 出的子进程则解除阻塞并返回 `true`
+    pub fn finish_vfork(&self, child: u64) -> bool {
+        self.vfork_child
+            .compare_exchange(child, 0, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
     }
 
     /// set the executable file path of the process
@@ -158,6 +271,47 @@ impl Process {
         (*self.file_path.lock()).clone()
     }
 
+    /// whether the process has a controlling terminal
+    pub fn has_ctty(&self) -> bool {
+        self.has_ctty.load(Ordering::Acquire)
+    }
+
+    /// attach or detach the controlling terminal of the process
+    pub fn set_ctty(&self, value: bool) {
+        self.has_ctty.store(value, Ordering::Release)
+    }
+
+    /// get the oom_score_adj of the process
+    pub fn get_oom_score_adj(&self) -> i32 {
+        self.oom_score_adj.load(Ordering::Acquire)
+    }
+
+    /// set the oom_score_adj of the process, the value will be clamped to [-1000, 1000]
+    pub fn set_oom_score_adj(&self, value: i32) {
+        self.oom_score_adj.store(
+            value.clamp(OOM_SCORE_ADJ_MIN, OOM_SCORE_ADJ_MAX),
+            Ordering::Release,
+        )
+    }
+
+    /// 进程创建的时间（纳秒）
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    /// 常驻内存的峰值（页数）
+    pub fn peak_rss_pages(&self) -> usize {
+        self.peak_rss_pages.load(Ordering::Acquire)
+    }
+
+    /// 以地址空间记录的峰值更新进程的常驻内存峰值
+    ///
+    /// 地址空间在释放页面之前会自行记录峰值，进程只需在地址空间被释放或替换之前调用
+    pub fn update_peak_rss(&self) {
+        let peak = self.memory_set.lock().lock().peak_rss_pages();
+        self.peak_rss_pages.fetch_max(peak, Ordering::AcqRel);
+    }
+
     /// 若进程运行完成，则获取其返回码
     /// 若正在运行（可能上锁或没有上锁），则返回None
     pub fn get_code_if_exit(&self) -> Option<i32> {
@@ -173,26 +327,37 @@ impl Process {
     pub fn new(
         pid: u64,
         parent: u64,
-        memory_set: Mutex<Arc<Mutex<MemorySet>>>,
+        memory_set: SpinWaitNoIrq<Arc<SpinWaitNoIrq<MemorySet>>>,
         heap_bottom: u64,
-        fd_table: Vec<Option<Arc<dyn FileIO>>>,
+        fd_manager: Arc<FdManager>,
+        fs_context: Arc<FsContext>,
     ) -> Self {
         Self {
             pid,
             parent: AtomicU64::new(parent),
+            pgid: AtomicU64::new(pid),
             children: Mutex::new(Vec::new()),
+            child_exits: AtomicUsize::new(0),
+            child_exit_wq: WaitQueue::new(),
             tasks: Mutex::new(Vec::new()),
             is_zombie: AtomicBool::new(false),
             exit_code: AtomicI32::new(0),
+            exit_signal: AtomicI32::new(0),
+            exit_times: Mutex::new((Duration::ZERO, Duration::ZERO)),
             memory_set,
             heap_bottom: AtomicU64::new(heap_bottom),
             heap_top: AtomicU64::new(heap_bottom),
-            fd_manager: FdManager::new(fd_table, FD_LIMIT_ORIGIN),
+            fd_manager: SpinWaitNoIrq::new(fd_manager),
+            fs_context,
 
             signal_modules: Mutex::new(BTreeMap::new()),
             robust_list: Mutex::new(BTreeMap::new()),
-            blocked_by_vfork: Mutex::new(false),
+            vfork_child: AtomicU64::new(0),
             file_path: Mutex::new(String::new()),
+            has_ctty: AtomicBool::new(false),
+            oom_score_adj: AtomicI32::new(0),
+            start_time: current_time_nanos(),
+            peak_rss_pages: AtomicUsize::new(0),
         }
     }
     /// 根据给定参数创建一个新的进程，作为应用程序初始进程
@@ -202,7 +367,6 @@ impl Process {
 
         {
             use axhal::mem::virt_to_phys;
-            use axhal::paging::MappingFlags;
             // 生成信号跳板
             let signal_trampoline_vaddr: VirtAddr = (axconfig::SIGNAL_TRAMPOLINE).into();
             let signal_trampoline_paddr = virt_to_phys((start_signal_trampoline as usize).into());
@@ -219,8 +383,6 @@ impl Process {
         if page_table_token != 0 {
             unsafe {
                 write_page_table_root0(page_table_token.into());
-                #[cfg(target_arch = "riscv64")]
-                riscv::register::sstatus::set_sum();
             };
         }
 
@@ -234,23 +396,31 @@ impl Process {
         let new_process = Arc::new(Self::new(
             TaskId::new().as_u64(),
             KERNEL_PROCESS_ID,
-            Mutex::new(Arc::new(Mutex::new(memory_set))),
+            SpinWaitNoIrq::new(Arc::new(SpinWaitNoIrq::new(memory_set))),
             heap_bottom.as_usize() as u64,
-            vec![
-                // 标准输入
-                Some(Arc::new(Stdin {
-                    flags: Mutex::new(OpenFlags::empty()),
-                })),
-                // 标准输出
-                Some(Arc::new(Stdout {
-                    flags: Mutex::new(OpenFlags::empty()),
-                })),
-                // 标准错误
-                Some(Arc::new(Stderr {
-                    flags: Mutex::new(OpenFlags::empty()),
-                })),
-            ],
+            Arc::new(FdManager::new(
+                vec![
+                    // 标准输入
+                    Some(Arc::new(Stdin {
+                        flags: Mutex::new(OpenFlags::empty()),
+                        fasync: Arc::default(),
+                    })),
+                    // 标准输出
+                    Some(Arc::new(Stdout {
+                        flags: Mutex::new(OpenFlags::empty()),
+                    })),
+                    // 标准错误
+                    Some(Arc::new(Stderr {
+                        flags: Mutex::new(OpenFlags::empty()),
+                    })),
+                ],
+                FD_LIMIT_ORIGIN,
+            )),
+            Arc::new(FsContext::new()),
         ));
+        // 初始进程以控制台作为控制终端
+        new_process.set_ctty(true);
+        new_process.set_file_path(axfs::api::canonicalize(&path).unwrap_or_else(|_| path.clone()));
         let new_task = new_task(
             || {},
             path,
@@ -261,10 +431,12 @@ impl Process {
         );
         TID2TASK
             .lock()
-            .insert(new_task.id().as_u64(), Arc::clone(&new_task));
+            .insert(new_task.id().as_u64(), Arc::downgrade(&new_task));
         new_task.set_leader(true);
-        let new_trap_frame =
+        let mut new_trap_frame =
             TrapFrame::app_init_context(entry.as_usize(), user_stack_bottom.as_usize());
+        // 新进程从空的线程指针开始，由 libc 根据 auxv 中的信息建立 TLS
+        set_user_tls(&new_task, &mut new_trap_frame, 0).unwrap();
         // // 需要将完整内容写入到内核栈上，first_into_user并不会复制到内核栈上
         write_trapframe_to_kstack(new_task.get_kernel_stack_top().unwrap(), &new_trap_frame);
         new_process.tasks.lock().push(Arc::clone(&new_task));
@@ -299,15 +471,28 @@ impl Process {
     /// args为传入的参数
     /// 任务的统计时间会被重置
     pub fn exec(&self, name: String, args: Vec<String>, envs: &Vec<String>) -> AxResult<()> {
+        self.exec_image(name, None, args, envs)
+    }
+
+    /// 与 [`Process::exec`] 相同，但 `elf_data` 不为空时直接从中加载程序，
+    /// 此时 `name` 只用作任务名与默认的参数，用于执行 memfd 等不在文件系统中的文件
+    pub fn exec_image(
+        &self,
+        name: String,
+        elf_data: Option<&[u8]>,
+        args: Vec<String>,
+        envs: &Vec<String>,
+    ) -> AxResult<()> {
         // 首先要处理原先进程的资源
         // 处理分配的页帧
         // 之后加入额外的东西之后再处理其他的包括信号等因素
         // 不是直接删除原有地址空间，否则构建成本较高。
 
+        self.update_peak_rss();
         if Arc::strong_count(&self.memory_set.lock()) == 1 {
             self.memory_set.lock().lock().unmap_user_areas();
         } else {
-            let memory_set = Arc::new(Mutex::new(MemorySet::clone_or_err(
+            let memory_set = Arc::new(SpinWaitNoIrq::new(MemorySet::clone_or_err(
                 &self.memory_set.lock().lock(),
             )?));
             *self.memory_set.lock() = memory_set;
@@ -325,22 +510,24 @@ impl Process {
         // 清空用户堆，重置堆顶
         axhal::arch::flush_tlb(None);
 
-        // 关闭 `CLOEXEC` 的文件
-        // inner.fd_manager.close_on_exec();
+        // 以 CLONE_FILES 共享的文件描述符表先复制一份，再关闭 `CLOEXEC` 的文件，
+        // 不影响共享该表的其他进程
+        let mut fd_manager = self.fd_manager.lock();
+        if Arc::strong_count(&fd_manager) > 1 {
+            *fd_manager = Arc::new(fd_manager.deep_copy());
+        }
+        fd_manager.close_on_exec();
+        drop(fd_manager);
         let current_task = current();
         // 再考虑手动结束其他所有的task
         let mut tasks = self.tasks.lock();
         for _ in 0..tasks.len() {
             let task = tasks.pop().unwrap();
             if task.id() == current_task.id() {
-                // FIXME: This will reset tls forcefully
-                #[cfg(target_arch = "x86_64")]
-                unsafe {
-                    task.set_tls_force(0);
-                    axhal::arch::write_thread_pointer(0);
-                }
                 tasks.push(task);
             } else {
+                let (utime, stime) = task.time_stat_output();
+                self.add_exit_times(utime, stime);
                 TID2TASK.lock().remove(&task.id().as_u64());
                 RUN_QUEUE.lock().remove_task(&task);
             }
@@ -348,18 +535,26 @@ impl Process {
         // 当前任务被设置为主线程
         current_task.set_leader(true);
         // 重置统计时间
-        current_task.reset_time_stat(current_time_nanos() as usize);
+        current_task.reset_time_stat(current_time());
         current_task.set_name(name.split('/').last().unwrap());
         assert!(tasks.len() == 1);
         drop(tasks);
+        // vfork 出的子进程 exec 之后不再使用父进程的地址空间，父进程可以继续运行
+        if let Some(parent) = PID2PC.lock().get(&self.get_parent()) {
+            if parent.finish_vfork(self.pid) {
+                wake_vfork_process(current_task.as_task_ref());
+            }
+        }
         let args = if args.is_empty() {
             vec![name.clone()]
         } else {
             args
         };
-        let (entry, user_stack_bottom, heap_bottom) = if let Ok(ans) =
-            load_app(name.clone(), args, envs, &mut self.memory_set.lock().lock())
-        {
+        let loaded = match elf_data {
+            Some(elf_data) => load_elf(elf_data, args, envs, &mut self.memory_set.lock().lock()),
+            None => load_app(name.clone(), args, envs, &mut self.memory_set.lock().lock()),
+        };
+        let (entry, user_stack_bottom, heap_bottom) = if let Ok(ans) = loaded {
             ans
         } else {
             error!("Failed to load app {}", name);
@@ -388,7 +583,6 @@ impl Process {
 
         {
             use axhal::mem::virt_to_phys;
-            use axhal::paging::MappingFlags;
             // 重置信号处理模块
             // 此时只会留下一个线程
             self.signal_modules.lock().clear();
@@ -415,8 +609,10 @@ impl Process {
         }
 
         // user_stack_top = user_stack_top / PAGE_SIZE_4K * PAGE_SIZE_4K;
-        let new_trap_frame =
+        let mut new_trap_frame =
             TrapFrame::app_init_context(entry.as_usize(), user_stack_bottom.as_usize());
+        // 旧映像的 TLS 已经失效，与新进程一样从空的线程指针开始
+        set_user_tls(current_task.as_task_ref(), &mut new_trap_frame, 0).unwrap();
         write_trapframe_to_kstack(
             current_task.get_kernel_stack_top().unwrap(),
             &new_trap_frame,
@@ -441,15 +637,14 @@ impl Process {
         // }
         // 是否共享虚拟地址空间
         let new_memory_set = if flags.contains(CloneFlags::CLONE_VM) {
-            Mutex::new(Arc::clone(&self.memory_set.lock()))
+            SpinWaitNoIrq::new(Arc::clone(&self.memory_set.lock()))
         } else {
-            let memory_set = Arc::new(Mutex::new(MemorySet::clone_or_err(
+            let memory_set = Arc::new(SpinWaitNoIrq::new(MemorySet::clone_or_err(
                 &self.memory_set.lock().lock(),
             )?));
 
             {
                 use axhal::mem::virt_to_phys;
-                use axhal::paging::MappingFlags;
                 // 生成信号跳板
                 let signal_trampoline_vaddr: VirtAddr = (axconfig::SIGNAL_TRAMPOLINE).into();
                 let signal_trampoline_paddr =
@@ -463,7 +658,7 @@ impl Process {
                         | MappingFlags::WRITE,
                 )?;
             }
-            Mutex::new(memory_set)
+            SpinWaitNoIrq::new(memory_set)
         };
 
         // 在生成新的进程前，需要决定其所属进程是谁
@@ -500,7 +695,7 @@ impl Process {
         debug!("new task:{}", new_task.id().as_u64());
         TID2TASK
             .lock()
-            .insert(new_task.id().as_u64(), Arc::clone(&new_task));
+            .insert(new_task.id().as_u64(), Arc::downgrade(&new_task));
 
         let new_handler = if flags.contains(CloneFlags::CLONE_SIGHAND) {
             // let curr_id = current().id().as_u64();
@@ -523,12 +718,8 @@ impl Process {
             // info!("curr_id: {:X}", (&curr_id as *const _ as usize));
         };
         // 检查是否在父任务中写入当前新任务的tid
-        if flags.contains(CloneFlags::CLONE_PARENT_SETTID)
-            & self.manual_alloc_for_lazy(ptid.into()).is_ok()
-        {
-            unsafe {
-                *(ptid as *mut i32) = new_task.id().as_u64() as i32;
-            }
+        if flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+            let _ = copy_struct_to_user(ptid, new_task.id().as_u64() as i32);
         }
         // 若包含CLONE_CHILD_SETTID或者CLONE_CHILD_CLEARTID
         // 则需要把线程号写入到子线程地址空间中tid对应的地址中
@@ -546,23 +737,20 @@ impl Process {
             if flags.contains(CloneFlags::CLONE_VM) {
                 // 此时地址空间不会发生改变
                 // 在当前地址空间下进行分配
-                if self.manual_alloc_for_lazy(ctid.into()).is_ok() {
-                    // 正常分配了地址
-                    unsafe {
-                        *(ctid as *mut i32) = if flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
-                            new_task.id().as_u64() as i32
-                        } else {
-                            0
-                        }
-                    }
+                let tid = if flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
+                    new_task.id().as_u64() as i32
                 } else {
-                    return Err(AxError::BadAddress);
-                }
+                    0
+                };
+                copy_struct_to_user(ctid, tid).map_err(|_| AxError::BadAddress)?;
             } else {
                 let memory_set_wrapper = self.memory_set.lock();
                 let mut vm = memory_set_wrapper.lock();
-                // 否则需要在新的地址空间中进行分配
-                if vm.manual_alloc_for_lazy(ctid.into()).is_ok() {
+                // 否则需要在新的地址空间中进行分配，随后直接写入物理页面，因此先复制共享的页面
+                if vm
+                    .manual_alloc_for_lazy(ctid.into(), MappingFlags::WRITE)
+                    .is_ok()
+                {
                     // 此时token没有发生改变，所以不能直接解引用访问，需要手动查页表
                     if let Ok((phyaddr, _, _)) = vm.query(ctid.into()) {
                         let vaddr: usize = phys_to_virt(phyaddr).into();
@@ -612,13 +800,31 @@ impl Process {
         } else {
             // 若创建的是进程，那么需要新建进程
             // 由于地址空间是复制的，所以堆底的地址也一定相同
+            // 带有 CLONE_FS 时与父进程共享文件系统上下文，否则复制一份
+            let fs_context = if flags.contains(CloneFlags::CLONE_FS) {
+                Arc::clone(&self.fs_context)
+            } else {
+                Arc::new(self.fs_context.deep_copy())
+            };
+            // 带有 CLONE_FILES 时与父进程共享文件描述符表，否则复制一份
+            let fd_manager = if flags.contains(CloneFlags::CLONE_FILES) {
+                self.fd_manager()
+            } else {
+                Arc::new(self.fd_manager().deep_copy())
+            };
             let new_process = Arc::new(Process::new(
                 process_id,
                 parent_id,
                 new_memory_set,
                 self.get_heap_bottom(),
-                self.fd_manager.fd_table.lock().clone(),
+                fd_manager,
+                fs_context,
             ));
+            // 子进程继承父进程的控制终端、可执行文件路径与 oom_score_adj
+            new_process.set_ctty(self.has_ctty());
+            new_process.set_file_path(self.get_file_path());
+            new_process.set_oom_score_adj(self.get_oom_score_adj());
+            new_process.set_pgid(self.pgid());
             // 记录该进程，防止被回收
             PID2PC.lock().insert(process_id, Arc::clone(&new_process));
             new_process.tasks.lock().push(Arc::clone(&new_task));
@@ -648,12 +854,7 @@ impl Process {
         // 新开的进程/线程返回值为0
         trap_frame.set_ret_code(0);
         if flags.contains(CloneFlags::CLONE_SETTLS) {
-            #[cfg(not(target_arch = "x86_64"))]
-            trap_frame.set_tls(tls);
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                new_task.set_tls_force(tls);
-            }
+            set_user_tls(&new_task, &mut trap_frame, tls)?;
         }
 
         // 设置用户栈
@@ -668,11 +869,15 @@ impl Process {
             // );
         }
         write_trapframe_to_kstack(new_task.get_kernel_stack_top().unwrap(), &trap_frame);
-        RUN_QUEUE.lock().add_task(new_task);
-        // 判断是否为VFORK
-        if flags.contains(CloneFlags::CLONE_VFORK) {
-            self.set_vfork_block(true);
-            yield_now_task();
+        // 判断是否为VFORK，需要在子任务运行前记录，否则子进程可能先于记录完成 exec
+        let vfork = flags.contains(CloneFlags::CLONE_VFORK);
+        if vfork {
+            self.set_vfork_child(return_id);
+        }
+        RUN_QUEUE.lock().add_task(Arc::clone(&new_task));
+        if vfork {
+            // 等待期间只有 SIGKILL 能打断，被打断后返回用户态前即处理该信号
+            vfork_suspend(&new_task, || !self.is_vfork_blocked());
         }
         Ok(return_id)
     }
@@ -681,24 +886,46 @@ impl Process {
 /// 与地址空间相关的进程方法
 impl Process {
     /// alloc physical memory for lazy allocation manually
-    pub fn manual_alloc_for_lazy(&self, addr: VirtAddr) -> AxResult<()> {
-        self.memory_set.lock().lock().manual_alloc_for_lazy(addr)
+    ///
+    /// 物理页帧耗尽时与缺页处理一样回收内存后重试
+    ///
+    /// `access` 为随后访问的方式，只读访问不会复制写时复制共享的页面
+    pub fn manual_alloc_for_lazy(&self, addr: VirtAddr, access: MappingFlags) -> AxResult<()> {
+        retry_on_oom("user access", || {
+            self.memory_set
+                .lock()
+                .lock()
+                .manual_alloc_for_lazy(addr, access)
+        })
     }
 
     /// alloc range physical memory for lazy allocation manually
-    pub fn manual_alloc_range_for_lazy(&self, start: VirtAddr, end: VirtAddr) -> AxResult<()> {
-        self.memory_set
-            .lock()
-            .lock()
-            .manual_alloc_range_for_lazy(start, end)
+    pub fn manual_alloc_range_for_lazy(
+        &self,
+        start: VirtAddr,
+        end: VirtAddr,
+        access: MappingFlags,
+    ) -> AxResult<()> {
+        retry_on_oom("user access", || {
+            self.memory_set
+                .lock()
+                .lock()
+                .manual_alloc_range_for_lazy(start, end, access)
+        })
     }
 
     /// alloc physical memory with the given type size for lazy allocation manually
-    pub fn manual_alloc_type_for_lazy<T: Sized>(&self, obj: *const T) -> AxResult<()> {
-        self.memory_set
-            .lock()
-            .lock()
-            .manual_alloc_type_for_lazy(obj)
+    pub fn manual_alloc_type_for_lazy<T: Sized>(
+        &self,
+        obj: *const T,
+        access: MappingFlags,
+    ) -> AxResult<()> {
+        retry_on_oom("user access", || {
+            self.memory_set
+                .lock()
+                .lock()
+                .manual_alloc_type_for_lazy(obj, access)
+        })
     }
 }
 
@@ -706,22 +933,12 @@ impl Process {
 impl Process {
     /// 为进程分配一个文件描述符
     pub fn alloc_fd(&self, fd_table: &mut Vec<Option<Arc<dyn FileIO>>>) -> AxResult<usize> {
-        for (i, fd) in fd_table.iter().enumerate() {
-            if fd.is_none() {
-                return Ok(i);
-            }
-        }
-        if fd_table.len() >= self.fd_manager.get_limit() as usize {
-            debug!("fd table is full");
-            return Err(AxError::StorageFull);
-        }
-        fd_table.push(None);
-        Ok(fd_table.len() - 1)
+        self.fd_manager().alloc_fd_from(fd_table, 0)
     }
 
     /// 获取当前进程的工作目录
     pub fn get_cwd(&self) -> String {
-        self.fd_manager.cwd.lock().clone()
+        self.fs_context.cwd()
     }
 }
 
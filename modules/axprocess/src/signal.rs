@@ -1,6 +1,6 @@
 //! 负责处理进程中与信号相关的内容
 extern crate alloc;
-use alloc::sync::Arc;
+use alloc::{collections::BTreeMap, sync::Arc};
 use axerrno::{AxError, AxResult};
 use axhal::{
     arch::{read_trapframe_from_kstack, write_trapframe_to_kstack, TrapFrame},
@@ -10,13 +10,16 @@ use axhal::{
 use axlog::{info, warn};
 use axsignal::{
     action::{SigActionFlags, SignalDefault, SIG_IGN},
-    info::SigInfo,
+    info::{SigInfo, SI_TKILL},
     signal_no::SignalNo,
     ucontext::SignalUserContext,
     SignalHandler, SignalSet,
 };
 use axsync::Mutex;
-use axtask::{TaskState, RUN_QUEUE};
+use axtask::{SleepKind, WaitQueue, RUN_QUEUE};
+
+/// 被 SIGSTOP 等信号停止的任务在此等待 SIGCONT
+static STOPPED_TASKS: WaitQueue = WaitQueue::new();
 
 /// 信号处理模块，进程间不共享
 pub struct SignalModule {
@@ -25,9 +28,20 @@ pub struct SignalModule {
     /// 保存的trap上下文
     pub last_trap_frame_for_signal: Option<TrapFrame>,
     /// 信号处理函数集
+    ///
+    /// 以 CLONE_SIGHAND 创建的任务与创建者共享同一个处理函数集，否则各自持有一份副本
     pub signal_handler: Arc<Mutex<SignalHandler>>,
     /// 未决信号集
     pub signal_set: SignalSet,
+    /// ppoll 等系统调用临时替换掩码时保存的原掩码
+    ///
+    /// 若等待期间有信号在临时掩码下变为可递送，则需要先按临时掩码处理该信号，
+    /// 之后再恢复原掩码
+    pub saved_mask: Option<usize>,
+    /// 随信号一同发送的信息，信号递送时取出；没有记录的信号使用默认的信息
+    ///
+    /// 未决信号以位图表示，同一信号多次发送时只保留最后一次的信息
+    pub sig_infos: BTreeMap<usize, SigInfo>,
 }
 
 impl SignalModule {
@@ -43,6 +57,8 @@ impl SignalModule {
             last_trap_frame_for_signal,
             signal_handler,
             signal_set,
+            saved_mask: None,
+            sig_infos: BTreeMap::new(),
         }
     }
 }
@@ -52,6 +68,8 @@ const USER_SIGNAL_PROTECT: usize = 512;
 use crate::{
     current_process, current_task, exit_current_task,
     process::{PID2PC, TID2TASK},
+    uaccess::{copy_struct_from_user, copy_struct_to_user},
+    Process,
 };
 
 /// 将保存的trap上下文填入内核栈中
@@ -67,24 +85,24 @@ pub fn load_trap_for_signal() -> bool {
     let mut signal_modules = current_process.signal_modules.lock();
     let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
     if let Some(old_trap_frame) = signal_module.last_trap_frame_for_signal.take() {
-        unsafe {
-            // let now_trap_frame: *mut TrapFrame = current_task.get_first_trap_frame();
-            let mut now_trap_frame =
-                read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
-            // 考虑当时调用信号处理函数时，sp对应的地址上的内容即是SignalUserContext
-            // 此时认为一定通过sig_return调用这个函数
-            // 所以此时sp的位置应该是SignalUserContext的位置
-            let sp = now_trap_frame.get_sp();
-            now_trap_frame = old_trap_frame;
-            if signal_module.sig_info {
-                let pc = (*(sp as *const SignalUserContext)).get_pc();
-                now_trap_frame.set_pc(pc);
+        // let now_trap_frame: *mut TrapFrame = current_task.get_first_trap_frame();
+        let mut now_trap_frame =
+            read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
+        // 考虑当时调用信号处理函数时，sp对应的地址上的内容即是SignalUserContext
+        // 此时认为一定通过sig_return调用这个函数
+        // 所以此时sp的位置应该是SignalUserContext的位置
+        let sp = now_trap_frame.get_sp();
+        now_trap_frame = old_trap_frame;
+        if signal_module.sig_info {
+            // 用户栈上的 ucontext 不可读时保留进入处理函数之前的 pc
+            if let Ok(ucontext) = copy_struct_from_user::<SignalUserContext>(sp) {
+                now_trap_frame.set_pc(ucontext.get_pc());
             }
-            write_trapframe_to_kstack(
-                current_task.get_kernel_stack_top().unwrap(),
-                &now_trap_frame,
-            );
         }
+        write_trapframe_to_kstack(
+            current_task.get_kernel_stack_top().unwrap(),
+            &now_trap_frame,
+        );
         true
     } else {
         false
@@ -96,6 +114,7 @@ fn terminate_process(signal: SignalNo) {
     let current_task = current_task();
     warn!("Terminate process: {}", current_task.get_process_id());
     if current_task.is_leader() {
+        current_process().set_exit_signal(signal as i32);
         exit_current_task(signal as i32);
     } else {
         // 此时应当关闭当前进程
@@ -133,7 +152,12 @@ pub fn handle_signals() {
 
     let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
     let signal_set = &mut signal_module.signal_set;
-    let sig_num = if let Some(sig_num) = signal_set.get_one_signal() {
+    let sig_num = signal_set.get_one_signal();
+    // 临时掩码下可递送的信号已经取出，此时可以恢复原掩码
+    if let Some(saved_mask) = signal_module.saved_mask.take() {
+        signal_set.mask = saved_mask;
+    }
+    let sig_num = if let Some(sig_num) = sig_num {
         sig_num
     } else {
         return;
@@ -146,6 +170,10 @@ pub fn handle_signals() {
     );
     let signal = SignalNo::from(sig_num);
     let mask = signal_set.mask;
+    let info = signal_module
+        .sig_infos
+        .remove(&sig_num)
+        .unwrap_or_else(|| SigInfo::new(sig_num as i32, SI_TKILL));
     // 存在未被处理的信号
     if signal_module.last_trap_frame_for_signal.is_some() {
         // 之前的trap frame还未被处理
@@ -181,10 +209,17 @@ pub fn handle_signals() {
                 terminate_process(signal);
             }
             SignalDefault::Stop => {
-                unimplemented!();
+                load_trap_for_signal();
+                // 停止直到收到 SIGCONT，期间 SIGKILL 也能唤醒，随后在下一次处理信号时结束进程
+                current_task.set_stopped(true);
+                STOPPED_TASKS.wait_until_as(SleepKind::Killable, || {
+                    !current_task.is_stopped() || current_task.take_woken_by_signal()
+                });
+                current_task.set_stopped(false);
             }
             SignalDefault::Cont => {
-                unimplemented!();
+                // 继续运行已在发送时完成，这里与忽略相同
+                load_trap_for_signal();
             }
             SignalDefault::Core => {
                 terminate_process(signal);
@@ -231,41 +266,35 @@ pub fn handle_signals() {
     if action.sa_flags.contains(SigActionFlags::SA_SIGINFO) {
         // current_task.set_siginfo(true);
         signal_module.sig_info = true;
-        let sp_base = (((sp - core::mem::size_of::<SigInfo>()) & !0xf)
-            - core::mem::size_of::<SignalUserContext>())
-            & !0xf;
-
-        // TODO: 统一为访问用户空间的操作封装函数
-        process
-            .manual_alloc_range_for_lazy(sp_base.into(), sp.into())
-            .expect("Failed to alloc memory for signal user stack");
-
         // 注意16字节对齐
         sp = (sp - core::mem::size_of::<SigInfo>()) & !0xf;
-        let info = SigInfo {
-            si_signo: sig_num as i32,
-            ..Default::default()
-        };
-        unsafe {
-            *(sp as *mut SigInfo) = info;
-        }
-        trap_frame.set_arg1(sp);
-
+        let siginfo_sp = sp;
         // 接下来存储ucontext
         sp = (sp - core::mem::size_of::<SignalUserContext>()) & !0xf;
-
         let ucontext = SignalUserContext::init(old_pc, mask);
-        unsafe {
-            *(sp as *mut SignalUserContext) = ucontext;
+        if copy_struct_to_user(siginfo_sp, info).is_err()
+            || copy_struct_to_user(sp, ucontext).is_err()
+        {
+            // 用户栈无法写入信号帧，按 Linux 的做法以 SIGSEGV 终止进程
+            drop(signal_handler);
+            drop(signal_modules);
+            terminate_process(SignalNo::SIGSEGV);
+            return;
         }
+        trap_frame.set_arg1(siginfo_sp);
         trap_frame.set_arg2(sp);
     }
 
     #[cfg(target_arch = "x86_64")]
-    unsafe {
+    {
         // set return rip
         sp -= core::mem::size_of::<usize>();
-        *(sp as *mut usize) = restorer;
+        if copy_struct_to_user(sp, restorer).is_err() {
+            drop(signal_handler);
+            drop(signal_modules);
+            terminate_process(SignalNo::SIGSEGV);
+            return;
+        }
     }
 
     trap_frame.set_user_sp(sp);
@@ -275,6 +304,34 @@ pub fn handle_signals() {
     drop(signal_modules);
 }
 
+/// 以临时信号掩码执行一次可被信号打断的等待，供 ppoll、pselect6 与 epoll_pwait 使用
+///
+/// 若 `mask` 为 `None`，则不修改掩码。等待结束后，若有信号在临时掩码下变为可递送，
+/// 则暂不恢复原掩码，而是记录在 `saved_mask` 中，待返回用户态处理完该信号后再恢复，
+/// 以免该信号被原掩码重新阻塞而丢失
+pub fn wait_with_sigmask<T>(mask: Option<usize>, wait: impl FnOnce() -> T) -> T {
+    let mask = match mask {
+        Some(mask) => mask,
+        None => return wait(),
+    };
+    let process = current_process();
+    let tid = current_task().id().as_u64();
+    let old_mask = {
+        let mut signal_modules = process.signal_modules.lock();
+        let signal_module = signal_modules.get_mut(&tid).unwrap();
+        core::mem::replace(&mut signal_module.signal_set.mask, mask)
+    };
+    let ans = wait();
+    let mut signal_modules = process.signal_modules.lock();
+    let signal_module = signal_modules.get_mut(&tid).unwrap();
+    if signal_module.signal_set.find_signal().is_some() {
+        signal_module.saved_mask = Some(old_mask);
+    } else {
+        signal_module.signal_set.mask = old_mask;
+    }
+    ans
+}
+
 /// 从信号处理函数返回
 ///
 /// 返回的值与原先syscall应当返回的值相同，即返回原先保存的trap上下文的a0的值
@@ -295,11 +352,22 @@ pub fn signal_return() -> isize {
 ///
 /// 默认发送到该进程下的主线程
 pub fn send_signal_to_process(pid: isize, signum: isize) -> AxResult<()> {
-    let mut pid2pc = PID2PC.lock();
-    if !pid2pc.contains_key(&(pid as u64)) {
-        return Err(axerrno::AxError::NotFound);
+    let process = PID2PC
+        .lock()
+        .get(&(pid as u64))
+        .cloned()
+        .ok_or(AxError::NotFound)?;
+    send_signal_info_to_process(&process, signum as usize, None);
+    Ok(())
+}
+
+/// 向进程发送信号，`info` 为随信号一同递送的信息
+///
+/// 与 [`send_signal_to_process`] 一样发送到进程的主线程
+pub fn send_signal_info_to_process(process: &Process, signum: usize, info: Option<SigInfo>) {
+    if signum == SignalNo::SIGCONT as usize {
+        continue_process(process);
     }
-    let process = pid2pc.get_mut(&(pid as u64)).unwrap();
     let mut now_id: Option<u64> = None;
     for task in process.tasks.lock().iter_mut() {
         if task.is_leader() {
@@ -310,22 +378,40 @@ pub fn send_signal_to_process(pid: isize, signum: isize) -> AxResult<()> {
     if now_id.is_some() {
         let mut signal_modules = process.signal_modules.lock();
         let signal_module = signal_modules.get_mut(&now_id.unwrap()).unwrap();
-        signal_module.signal_set.try_add_signal(signum as usize);
-        let tid2task = TID2TASK.lock();
-        let main_task = Arc::clone(tid2task.get(&now_id.unwrap()).unwrap());
-        // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
-        if main_task.state() == TaskState::Blocked {
+        signal_module.signal_set.try_add_signal(signum);
+        match info {
+            Some(info) => signal_module.sig_infos.insert(signum, info),
+            None => signal_module.sig_infos.remove(&signum),
+        };
+        // 主线程可能正在退出，此时已经不在 TID2TASK 中，信号已经记录，无需唤醒
+        let Some(main_task) = TID2TASK
+            .lock()
+            .get(&now_id.unwrap())
+            .and_then(|task| task.upgrade())
+        else {
+            return;
+        };
+        // 如果这个时候对应的线程处于可被信号打断的休眠状态，则唤醒之，进入信号处理阶段
+        if main_task.can_wake_by_signal(signum == SignalNo::SIGKILL as usize) {
+            main_task.set_woken_by_signal(true);
             RUN_QUEUE.lock().unblock_task(main_task, false);
         }
     }
-    Ok(())
+}
+
+/// SIGCONT 让进程中所有被停止的任务继续运行，即使该信号被阻塞或忽略
+fn continue_process(process: &Process) {
+    for task in process.tasks.lock().iter() {
+        task.set_stopped(false);
+    }
+    STOPPED_TASKS.notify_all(false);
 }
 
 /// 发送信号到指定的线程
 pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
     let tid2task = TID2TASK.lock();
-    let task = if let Some(task) = tid2task.get(&(tid as u64)) {
-        Arc::clone(task)
+    let task = if let Some(task) = tid2task.get(&(tid as u64)).and_then(|task| task.upgrade()) {
+        task
     } else {
         return Err(AxError::NotFound);
     };
@@ -338,14 +424,19 @@ pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
         return Err(AxError::NotFound);
     };
     drop(pid2pc);
+    if signum == SignalNo::SIGCONT as isize {
+        continue_process(&process);
+    }
     let mut signal_modules = process.signal_modules.lock();
     if !signal_modules.contains_key(&(tid as u64)) {
         return Err(axerrno::AxError::NotFound);
     }
     let signal_module = signal_modules.get_mut(&(tid as u64)).unwrap();
     signal_module.signal_set.try_add_signal(signum as usize);
-    // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
-    if task.state() == TaskState::Blocked {
+    signal_module.sig_infos.remove(&(signum as usize));
+    // 如果这个时候对应的线程处于可被信号打断的休眠状态，则唤醒之，进入信号处理阶段
+    if task.can_wake_by_signal(signum == SignalNo::SIGKILL as isize) {
+        task.set_woken_by_signal(true);
         RUN_QUEUE.lock().unblock_task(task, false);
     }
     Ok(())
@@ -1,16 +1,26 @@
+extern crate alloc;
+use alloc::{string::String, sync::Arc};
 use axerrno::{AxError, AxResult};
 use axfs::api::port::{
-    ConsoleWinSize, FileExt, FileIO, FileIOType, OpenFlags, TCGETS, TIOCGPGRP, TIOCGWINSZ,
-    TIOCSPGRP,
+    FileExt, FileIO, FileIOType, OpenFlags, FIONREAD, TCGETS, TCSETS, TCSETSF, TCSETSW, TIOCGPGRP,
+    TIOCGWINSZ, TIOCSPGRP, TIOCSWINSZ,
 };
 use axhal::console::{getchar, write_bytes};
+use axhal::time::current_time_nanos;
 use axio::{Read, Seek, SeekFrom, Write};
-use axlog::warn;
+use axsignal::signal_no::SignalNo;
 use axsync::Mutex;
 use axtask::yield_now;
+
+use crate::fasync::{Fasync, ReadyWatch};
+use crate::ldisc::{SetTermiosMode, Termios, TtyCore, TtyDevice, WinSize};
+use crate::signal::send_signal_to_process;
+use crate::uaccess::{copy_struct_from_user, copy_struct_to_user};
 /// stdin file for getting chars from console
 pub struct Stdin {
     pub flags: Mutex<OpenFlags>,
+    /// 信号驱动 I/O 的状态
+    pub fasync: Arc<Fasync>,
 }
 
 /// stdout file for putting chars to console
@@ -23,34 +33,124 @@ pub struct Stderr {
     pub flags: Mutex<OpenFlags>,
 }
 
-fn stdin_read(buf: &mut [u8]) -> AxResult<usize> {
-    let ch: u8;
-    loop {
-        match getchar() {
-            Some(c) => {
-                ch = c;
-                break;
-            }
-            None => {
-                yield_now();
-                continue;
-            }
+/// `/dev/tty`, the controlling terminal of the process
+///
+/// Unlike fd 0/1/2, it can't be redirected and always refers to the console for now.
+pub struct Tty {
+    pub flags: Mutex<OpenFlags>,
+    /// 信号驱动 I/O 的状态
+    pub fasync: Arc<Fasync>,
+}
+
+/// 启用了 O_ASYNC 的控制台文件，控制台取出新的输入后检查
+pub static CONSOLE_WATCH: ReadyWatch = ReadyWatch::new();
+
+/// 从控制台读取输入的文件的信号驱动 I/O 状态，`file` 不是这类文件时返回 None
+pub fn console_fasync(file: &dyn FileIO) -> Option<&Arc<Fasync>> {
+    if let Some(stdin) = file.as_any().downcast_ref::<Stdin>() {
+        Some(&stdin.fasync)
+    } else {
+        file.as_any().downcast_ref::<Tty>().map(|tty| &tty.fasync)
+    }
+}
+
+/// 控制台设备
+struct Console;
+
+impl TtyDevice for Console {
+    fn getchar(&self) -> Option<u8> {
+        getchar()
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) {
+        write_bytes(bytes);
+    }
+
+    fn now_nanos(&self) -> u64 {
+        current_time_nanos()
+    }
+
+    fn wait(&self) -> AxResult<()> {
+        if crate::current_process().have_signals().is_some() {
+            return Err(AxError::Interrupted);
         }
+        yield_now();
+        Ok(())
     }
-    unsafe {
-        buf.as_mut_ptr().write_volatile(ch);
+
+    fn input_received(&self) {
+        CONSOLE_WATCH.check();
     }
-    Ok(1)
+}
+
+/// 控制台终端，标准输入输出与 /dev/tty 都经过它的行规程
+static CONSOLE: TtyCore<Console> = TtyCore::new(Console);
+
+/// 从控制台读取输入，非阻塞模式下若没有输入则返回 `WouldBlock`
+fn stdin_read(buf: &mut [u8], non_block: bool) -> AxResult<usize> {
+    CONSOLE.read(buf, non_block)
 }
 
 fn stdout_write(buf: &[u8]) -> AxResult<usize> {
-    write_bytes(buf);
-    Ok(buf.len())
+    CONSOLE.write(buf)
+}
+
+/// 控制台文件只允许修改 `NON_BLOCK` 与 `CLOEXEC` 标志
+fn set_console_status(status: &Mutex<OpenFlags>, flags: OpenFlags) -> bool {
+    let mut status = status.lock();
+    status.set(OpenFlags::NON_BLOCK, flags.contains(OpenFlags::NON_BLOCK));
+    if flags.contains(OpenFlags::CLOEXEC) {
+        status.insert(OpenFlags::CLOEXEC);
+    }
+    true
+}
+
+fn console_ioctl(request: usize, data: usize) -> AxResult<()> {
+    let to_ax = |_| AxError::BadAddress;
+    match request {
+        TCGETS => copy_struct_to_user(data, CONSOLE.termios()).map_err(to_ax),
+        TCSETS | TCSETSW | TCSETSF => {
+            let termios: Termios = copy_struct_from_user(data).map_err(to_ax)?;
+            let mode = match request {
+                TCSETS => SetTermiosMode::Now,
+                TCSETSW => SetTermiosMode::Drain,
+                _ => SetTermiosMode::Flush,
+            };
+            CONSOLE.set_termios(termios, mode);
+            Ok(())
+        }
+        TIOCGWINSZ => copy_struct_to_user(data, CONSOLE.winsize()).map_err(to_ax),
+        TIOCSWINSZ => {
+            let winsize: WinSize = copy_struct_from_user(data).map_err(to_ax)?;
+            if CONSOLE.set_winsize(winsize) {
+                notify_winsize_change();
+            }
+            Ok(())
+        }
+        TIOCGPGRP => copy_struct_to_user(data, CONSOLE.fg_pgrp()).map_err(to_ax),
+        TIOCSPGRP => {
+            let pgrp: u32 = copy_struct_from_user(data).map_err(to_ax)?;
+            CONSOLE.set_fg_pgrp(pgrp);
+            Ok(())
+        }
+        FIONREAD => copy_struct_to_user(data, CONSOLE.readable_bytes() as u32).map_err(to_ax),
+        _ => Err(AxError::Unsupported),
+    }
+}
+
+/// 窗口大小变化后向前台进程组发送 SIGWINCH
+///
+/// 进程组尚未实现，前台进程组即为 TIOCSPGRP 设置的进程；未设置或该进程已退出时与 Linux 一样不发送信号
+fn notify_winsize_change() {
+    let pgrp = CONSOLE.fg_pgrp() as isize;
+    if pgrp != 0 {
+        let _ = send_signal_to_process(pgrp, SignalNo::SIGWINCH as isize);
+    }
 }
 
 impl Read for Stdin {
     fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        stdin_read(buf)
+        stdin_read(buf, self.flags.lock().contains(OpenFlags::NON_BLOCK))
     }
 }
 
@@ -83,15 +183,16 @@ impl FileExt for Stdin {
 
 impl FileIO for Stdin {
     fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
-        stdin_read(buf)
+        stdin_read(buf, self.flags.lock().contains(OpenFlags::NON_BLOCK))
     }
 
     fn get_type(&self) -> FileIOType {
         FileIOType::Stdin
     }
 
+    /// 行规程中有可以立即读取的输入时才可读，poll 与 select 据此等待输入到来
     fn ready_to_read(&self) -> bool {
-        true
+        CONSOLE.readable_bytes() > 0
     }
 
     fn ready_to_write(&self) -> bool {
@@ -111,38 +212,11 @@ impl FileIO for Stdin {
     }
 
     fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
-        match request {
-            TIOCGWINSZ => {
-                let winsize = data as *mut ConsoleWinSize;
-                unsafe {
-                    *winsize = ConsoleWinSize::default();
-                }
-                Ok(())
-            }
-            TCGETS | TIOCSPGRP => {
-                warn!("stdin TCGETS | TIOCSPGRP, pretend to be tty.");
-                // pretend to be tty
-                Ok(())
-            }
-
-            TIOCGPGRP => {
-                warn!("stdin TIOCGPGRP, pretend to be have a tty process group.");
-                unsafe {
-                    *(data as *mut u32) = 0;
-                }
-                Ok(())
-            }
-            _ => Err(AxError::Unsupported),
-        }
+        console_ioctl(request, data)
     }
 
     fn set_status(&self, flags: OpenFlags) -> bool {
-        if flags.contains(OpenFlags::CLOEXEC) {
-            *self.flags.lock() = OpenFlags::CLOEXEC;
-            true
-        } else {
-            false
-        }
+        set_console_status(&self.flags, flags)
     }
 
     fn get_status(&self) -> OpenFlags {
@@ -168,8 +242,7 @@ impl Read for Stdout {
 
 impl Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
-        write_bytes(buf);
-        Ok(buf.len())
+        stdout_write(buf)
     }
     fn flush(&mut self) -> AxResult {
         // stdout is always flushed
@@ -220,6 +293,10 @@ impl FileIO for Stdout {
         FileIOType::Stdout
     }
 
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        console_ioctl(request, data)
+    }
+
     fn ready_to_read(&self) -> bool {
         false
     }
@@ -229,12 +306,7 @@ impl FileIO for Stdout {
     }
 
     fn set_status(&self, flags: OpenFlags) -> bool {
-        if flags.contains(OpenFlags::CLOEXEC) {
-            *self.flags.lock() = flags;
-            true
-        } else {
-            false
-        }
+        set_console_status(&self.flags, flags)
     }
 
     fn get_status(&self) -> OpenFlags {
@@ -260,8 +332,7 @@ impl Read for Stderr {
 
 impl Write for Stderr {
     fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
-        write_bytes(buf);
-        Ok(buf.len())
+        stdout_write(buf)
     }
 
     /// Stderr is always flushed
@@ -290,8 +361,7 @@ impl FileExt for Stderr {
 
 impl FileIO for Stderr {
     fn write(&self, buf: &[u8]) -> AxResult<usize> {
-        write_bytes(buf);
-        Ok(buf.len())
+        stdout_write(buf)
     }
 
     /// Stderr is always flushed
@@ -315,6 +385,10 @@ impl FileIO for Stderr {
         FileIOType::Stderr
     }
 
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        console_ioctl(request, data)
+    }
+
     fn ready_to_read(&self) -> bool {
         false
     }
@@ -323,3 +397,68 @@ impl FileIO for Stderr {
         true
     }
 }
+
+impl FileIO for Tty {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        stdin_read(buf, self.flags.lock().contains(OpenFlags::NON_BLOCK))
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        stdout_write(buf)
+    }
+
+    /// Tty is always flushed
+    fn flush(&self) -> axio::Result {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        String::from("/dev/tty")
+    }
+
+    fn ready_to_read(&self) -> bool {
+        CONSOLE.readable_bytes() > 0
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        console_ioctl(request, data)
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        set_console_status(&self.flags, flags)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            // 设置close_on_exec位置
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+}
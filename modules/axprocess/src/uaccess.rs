@@ -0,0 +1,203 @@
+//! 访问用户地址空间中的数据
+//!
+//! 系统调用读写用户给出的指针时应当经过这里，而不是直接解引用。
+//! 地址范围会先与 [`TASK_SIZE`] 比较，再逐页确认已经映射（延迟分配与写时复制的页面在此时处理），
+//! 因此之后的访问不会在内核中触发缺页；地址不合法或页面的权限不允许这次访问时返回 EFAULT。
+//! 复制本身经过带有修复代码的 [`copy_user`]，检查之后映射被其他线程修改导致访问出错时同样返回 EFAULT。
+//! 复制数据时持有 [`UserMemoryGuard`]，在 riscv 上由它置位 S 态访问用户页面所需的 SUM 位。
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use axconfig::{TASK_SIZE, USER_MEMORY_START};
+use axerrno::LinuxError;
+use axhal::{arch::copy_user, mem::PAGE_SIZE_4K, paging::MappingFlags};
+
+use crate::current_process;
+
+/// 路径的最大长度，包括结尾的 '\0'
+pub const PATH_MAX: usize = 4096;
+
+/// execve 的单个参数或环境变量的最大长度，包括结尾的 '\0'
+pub const MAX_ARG_STRLEN: usize = 32 * PAGE_SIZE_4K;
+
+#[cfg(target_arch = "riscv64")]
+pub use axhal::arch::UserMemoryGuard;
+
+/// 允许内核访问用户内存的守卫，只有 riscv 需要切换 SUM 位，其余架构上不做任何事
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Default)]
+pub struct UserMemoryGuard;
+
+#[cfg(not(target_arch = "riscv64"))]
+impl UserMemoryGuard {
+    /// 创建守卫
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// 检查 `[uaddr, uaddr + len)` 是否是当前进程可以读取的用户地址，并确保其均已映射
+///
+/// 只读取用户内存，写时复制共享的页面与被 MADV_FREE 标记的页面保持原样。`len` 为 0 时总是合法
+pub fn check_user_range(uaddr: usize, len: usize) -> Result<(), LinuxError> {
+    check_user_access(uaddr, len, MappingFlags::READ)
+}
+
+/// 与 [`check_user_range`] 相同，并且要求范围内的页面均可写
+///
+/// 内核随后会写入这些页面，因此会先复制写时复制共享的页面并取消 MADV_FREE 标记
+pub fn check_user_writable(uaddr: usize, len: usize) -> Result<(), LinuxError> {
+    check_user_access(uaddr, len, MappingFlags::READ | MappingFlags::WRITE)
+}
+
+/// 以 `access` 方式检查并映射 `[uaddr, uaddr + len)`
+fn check_user_access(uaddr: usize, len: usize, access: MappingFlags) -> Result<(), LinuxError> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = uaddr.checked_add(len).ok_or(LinuxError::EFAULT)?;
+    if uaddr < USER_MEMORY_START || end > TASK_SIZE {
+        return Err(LinuxError::EFAULT);
+    }
+    current_process()
+        .manual_alloc_range_for_lazy(uaddr.into(), (end - 1).into(), access)
+        .map_err(|_| LinuxError::EFAULT)?;
+    check_page_flags(uaddr, end, MappingFlags::USER | access)
+}
+
+/// 确认 `[start, end)` 中的每一页在页表中的权限都包含 `required`
+fn check_page_flags(start: usize, end: usize, required: MappingFlags) -> Result<(), LinuxError> {
+    let memory_set = current_process().memory_set.lock().clone();
+    let memory_set = memory_set.lock();
+    for page in (start & !(PAGE_SIZE_4K - 1)..end).step_by(PAGE_SIZE_4K) {
+        match memory_set.query(page.into()) {
+            Ok((_, flags, _)) if flags.contains(required) => {}
+            _ => return Err(LinuxError::EFAULT),
+        }
+    }
+    Ok(())
+}
+
+/// 经由 [`copy_user`] 复制 `len` 字节，访问出错时返回 EFAULT
+///
+/// # Safety
+///
+/// 内核一侧的地址必须可以访问 `len` 字节
+unsafe fn copy_bytes(dst: *mut u8, src: *const u8, len: usize) -> Result<(), LinuxError> {
+    let _guard = UserMemoryGuard::new();
+    match copy_user(dst, src, len) {
+        0 => Ok(()),
+        _ => Err(LinuxError::EFAULT),
+    }
+}
+
+/// 从用户地址 `uaddr` 复制 `dst.len()` 字节到 `dst`
+pub fn copy_from_user(dst: &mut [u8], uaddr: usize) -> Result<(), LinuxError> {
+    check_user_range(uaddr, dst.len())?;
+    unsafe { copy_bytes(dst.as_mut_ptr(), uaddr as *const u8, dst.len()) }
+}
+
+/// 将 `src` 复制到用户地址 `uaddr`
+pub fn copy_to_user(uaddr: usize, src: &[u8]) -> Result<(), LinuxError> {
+    check_user_writable(uaddr, src.len())?;
+    unsafe { copy_bytes(uaddr as *mut u8, src.as_ptr(), src.len()) }
+}
+
+/// 将用户地址 `uaddr` 处的 `len` 字节清零
+pub fn clear_user(uaddr: usize, len: usize) -> Result<(), LinuxError> {
+    const ZEROS: [u8; 256] = [0; 256];
+    check_user_writable(uaddr, len)?;
+    let mut done = 0;
+    while done < len {
+        let chunk_len = (len - done).min(ZEROS.len());
+        unsafe { copy_bytes((uaddr + done) as *mut u8, ZEROS.as_ptr(), chunk_len)? };
+        done += chunk_len;
+    }
+    Ok(())
+}
+
+/// 从用户地址 `uaddr` 读取一个 `T`，不要求地址按 `T` 对齐
+pub fn copy_struct_from_user<T: Copy>(uaddr: usize) -> Result<T, LinuxError> {
+    let size = core::mem::size_of::<T>();
+    check_user_range(uaddr, size)?;
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    unsafe {
+        copy_bytes(value.as_mut_ptr() as *mut u8, uaddr as *const u8, size)?;
+        Ok(value.assume_init())
+    }
+}
+
+/// 将 `value` 写入用户地址 `uaddr`，不要求地址按 `T` 对齐
+///
+/// `value` 的所有权转移到用户内存中，不会在内核中被 drop
+pub fn copy_struct_to_user<T>(uaddr: usize, value: T) -> Result<(), LinuxError> {
+    let size = core::mem::size_of::<T>();
+    check_user_writable(uaddr, size)?;
+    let value = core::mem::ManuallyDrop::new(value);
+    unsafe { copy_bytes(uaddr as *mut u8, &*value as *const T as *const u8, size) }
+}
+
+/// 从用户地址 `uaddr` 读取以 '\0' 结尾的字符串，最多读取 `max_len` 字节（包括 '\0'）
+///
+/// 返回不含 '\0' 的原始字节，不要求是合法的 UTF-8。
+/// 逐页检查地址，因此不会越过 '\0' 去访问之后的页面。
+/// 读到不合法的地址时返回 EFAULT，`max_len` 字节内没有 '\0' 时返回 ENAMETOOLONG
+pub fn strncpy_from_user(uaddr: usize, max_len: usize) -> Result<Vec<u8>, LinuxError> {
+    let mut bytes = Vec::new();
+    let mut addr = uaddr;
+    while bytes.len() < max_len {
+        if addr >= TASK_SIZE {
+            return Err(LinuxError::EFAULT);
+        }
+        let page_end = (addr & !(PAGE_SIZE_4K - 1)) + PAGE_SIZE_4K;
+        let chunk_len = (page_end - addr).min(max_len - bytes.len());
+        let start = bytes.len();
+        bytes.resize(start + chunk_len, 0);
+        copy_from_user(&mut bytes[start..], addr)?;
+        if let Some(pos) = bytes[start..].iter().position(|&byte| byte == 0) {
+            bytes.truncate(start + pos);
+            return Ok(bytes);
+        }
+        addr += chunk_len;
+    }
+    Err(LinuxError::ENAMETOOLONG)
+}
+
+/// 从用户地址 `uaddr` 读取路径，长度限制为 [`PATH_MAX`]
+///
+/// 文件系统中的路径以 `String` 表示，因此不是合法 UTF-8 的路径返回 EINVAL
+pub fn user_path(uaddr: usize) -> Result<String, LinuxError> {
+    String::from_utf8(strncpy_from_user(uaddr, PATH_MAX)?).map_err(|_| LinuxError::EINVAL)
+}
+
+/// 读取用户地址 `uaddr` 处以空指针结尾的字符串指针数组，如 execve 的 argv 与 envp
+///
+/// `uaddr` 为 0 时视为空数组。与 Linux 一样，每个字符串占用其长度加上结尾的 '\0' 与指针本身的大小，
+/// 总大小超过 `max_size` 或单个字符串超过 [`MAX_ARG_STRLEN`] 时返回 E2BIG。
+/// 不是合法 UTF-8 的字节被替换为 U+FFFD
+pub fn user_string_array(uaddr: usize, max_size: usize) -> Result<Vec<String>, LinuxError> {
+    let mut strings = Vec::new();
+    if uaddr == 0 {
+        return Ok(strings);
+    }
+    let mut size = 0;
+    let mut ptr_addr = uaddr;
+    loop {
+        let str_addr: usize = copy_struct_from_user(ptr_addr)?;
+        if str_addr == 0 {
+            return Ok(strings);
+        }
+        let bytes = strncpy_from_user(str_addr, MAX_ARG_STRLEN).map_err(|err| match err {
+            LinuxError::ENAMETOOLONG => LinuxError::E2BIG,
+            err => err,
+        })?;
+        size += bytes.len() + 1 + core::mem::size_of::<usize>();
+        if size > max_size {
+            return Err(LinuxError::E2BIG);
+        }
+        strings.push(String::from_utf8_lossy(&bytes).into_owned());
+        ptr_addr = ptr_addr
+            .checked_add(core::mem::size_of::<usize>())
+            .ok_or(LinuxError::EFAULT)?;
+    }
+}
@@ -0,0 +1,185 @@
+//! 内核随机数源
+//!
+//! 启动时由 CPU 的随机数指令（若有）与时钟抖动向熵池中加入熵，累计的熵足够之后才使用熵池作为
+//! 种子初始化 ChaCha20 随机数生成器。在此之前，getrandom 等调用者需要等待初始化完成，避免得到
+//! 可预测的结果。时钟计数本身是可预测的，只混入熵池而不计入熵。
+//!
+//! 内核中所有需要随机数的地方都应当使用同一个生成器：getrandom 系统调用，以及加载程序时
+//! 放在用户栈上、由 auxv 中 AT_RANDOM 指向的 16 字节（libc 以此生成栈保护的 canary）。
+#![cfg_attr(not(test), no_std)]
+mod source;
+
+use axhal::time::current_time_nanos;
+use axtask::WaitQueue;
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+use spinlock::SpinNoIrq;
+
+/// 初始化随机数生成器所需的最少熵（比特）
+const SEED_BITS: usize = 128;
+
+/// 熵池，累计足够的熵之后初始化随机数生成器
+struct EntropyPool {
+    pool: [u8; 32],
+    /// 下一个混入的字节在熵池中的位置
+    pos: usize,
+    /// 已记入的熵（比特）
+    bits: usize,
+    rng: Option<ChaCha20Rng>,
+}
+
+impl EntropyPool {
+    const fn new() -> Self {
+        Self {
+            pool: [0; 32],
+            pos: 0,
+            bits: 0,
+            rng: None,
+        }
+    }
+
+    fn is_seeded(&self) -> bool {
+        self.rng.is_some()
+    }
+
+    /// 向熵池中混入数据，并记入 `entropy_bits` 比特的熵
+    ///
+    /// 若记入后熵已足够且尚未初始化，则初始化随机数生成器，返回值表示是否在此次完成初始化
+    fn add_entropy(&mut self, data: &[u8], entropy_bits: usize) -> bool {
+        for &byte in data {
+            let pos = self.pos % self.pool.len();
+            self.pool[pos] = self.pool[pos].rotate_left(3) ^ byte;
+            self.pos = self.pos.wrapping_add(1);
+        }
+        self.bits = self.bits.saturating_add(entropy_bits);
+        if self.rng.is_none() && self.bits >= SEED_BITS {
+            self.rng = Some(ChaCha20Rng::from_seed(self.pool));
+            return true;
+        }
+        false
+    }
+
+    /// 若已完成初始化，则用随机数填充 `buf`
+    fn try_fill(&mut self, buf: &mut [u8]) -> bool {
+        match self.rng.as_mut() {
+            Some(rng) => {
+                rng.fill_bytes(buf);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+static POOL: SpinNoIrq<EntropyPool> = SpinNoIrq::new(EntropyPool::new());
+
+/// 等待随机数生成器初始化的任务
+static SEED_WAIT: WaitQueue = WaitQueue::new();
+
+/// 向熵池中加入熵，若因此完成了初始化则唤醒等待的任务
+pub fn add_entropy(data: &[u8], entropy_bits: usize) {
+    if POOL.lock().add_entropy(data, entropy_bits) {
+        SEED_WAIT.notify_all(true);
+    }
+}
+
+/// 为熵池提供种子，返回时随机数生成器已经完成初始化
+///
+/// 优先使用 CPU 的随机数指令，不可用或熵仍不足时收集时钟抖动，直到熵足够为止
+pub fn init() {
+    add_entropy(&(current_time_nanos() as u64).to_ne_bytes(), 0);
+    for _ in 0..SEED_BITS / 64 {
+        if let Some(value) = source::hardware_u64() {
+            add_entropy(&value.to_ne_bytes(), 64);
+        }
+    }
+    let mut jitter = source::Jitter::new();
+    while !is_seeded() {
+        let (sample, entropy_bits) = jitter.sample();
+        add_entropy(&sample.to_ne_bytes(), entropy_bits);
+    }
+}
+
+/// 随机数生成器是否已经完成初始化
+pub fn is_seeded() -> bool {
+    POOL.lock().is_seeded()
+}
+
+/// 等待随机数生成器完成初始化
+pub fn wait_for_seed() {
+    SEED_WAIT.wait_until(is_seeded);
+}
+
+/// 用随机数填充 `buf`，若随机数生成器尚未初始化则返回 false
+pub fn try_fill_bytes(buf: &mut [u8]) -> bool {
+    POOL.lock().try_fill(buf)
+}
+
+/// 用随机数填充 `buf`，若随机数生成器尚未初始化则先等待其初始化
+pub fn fill_bytes(buf: &mut [u8]) {
+    fill_or_wait(&POOL, buf, wait_for_seed);
+}
+
+/// 从 `pool` 取随机数填充 `buf`，尚未初始化时调用 `wait` 等待后重试
+fn fill_or_wait(pool: &SpinNoIrq<EntropyPool>, buf: &mut [u8], mut wait: impl FnMut()) {
+    while !pool.lock().try_fill(buf) {
+        wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_or_wait, EntropyPool};
+    use spinlock::SpinNoIrq;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_unseeded_pool() {
+        let mut pool = EntropyPool::new();
+        let mut buf = [0u8; 16];
+        // 熵不足时不能产生随机数
+        assert!(!pool.add_entropy(&[1, 2, 3, 4], 64));
+        assert!(!pool.try_fill(&mut buf));
+        // 熵足够之后完成初始化，此后可以产生随机数
+        assert!(pool.add_entropy(&[5, 6, 7, 8], 64));
+        assert!(pool.is_seeded());
+        assert!(pool.try_fill(&mut buf));
+        // 已经初始化之后不会重复初始化
+        assert!(!pool.add_entropy(&[9], 64));
+    }
+
+    #[test]
+    fn test_successive_fills_differ() {
+        let mut pool = EntropyPool::new();
+        pool.add_entropy(&[1, 2, 3, 4, 5, 6, 7, 8], 128);
+        // AT_RANDOM 与随后的 getrandom 取自同一生成器，前后两次得到的内容不同
+        let mut at_random = [0u8; 16];
+        let mut getrandom = [0u8; 16];
+        assert!(pool.try_fill(&mut at_random));
+        assert!(pool.try_fill(&mut getrandom));
+        assert_ne!(at_random, getrandom);
+    }
+
+    #[test]
+    fn test_fill_blocks_until_seeded() {
+        static POOL: SpinNoIrq<EntropyPool> = SpinNoIrq::new(EntropyPool::new());
+        static DONE: AtomicBool = AtomicBool::new(false);
+
+        let reader = std::thread::spawn(|| {
+            let mut buf = [0u8; 16];
+            fill_or_wait(&POOL, &mut buf, std::thread::yield_now);
+            DONE.store(true, Ordering::SeqCst);
+        });
+        // 熵不足时读者一直等待
+        POOL.lock().add_entropy(&[1, 2, 3, 4], 64);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!DONE.load(Ordering::SeqCst));
+        // 完成初始化之后读者得到随机数并返回
+        POOL.lock().add_entropy(&[5, 6, 7, 8], 64);
+        reader.join().unwrap();
+        assert!(DONE.load(Ordering::SeqCst));
+    }
+}
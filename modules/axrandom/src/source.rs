@@ -0,0 +1,100 @@
+//! 熵的来源：CPU 的随机数指令与时钟抖动
+use axhal::time::{ClockSource, HardwareClock};
+
+/// 从 CPU 的随机数指令读取 64 位随机数，CPU 不支持或暂时无法给出时返回 None
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn hardware_u64() -> Option<u64> {
+    use core::arch::x86_64::{__cpuid, _rdrand64_step};
+
+    #[target_feature(enable = "rdrand")]
+    unsafe fn rdrand(value: &mut u64) -> i32 {
+        _rdrand64_step(value)
+    }
+
+    // CPUID.01H:ECX.RDRAND[bit 30]
+    if unsafe { __cpuid(1) }.ecx & (1 << 30) == 0 {
+        return None;
+    }
+    let mut value = 0;
+    // RDRAND 可能暂时失败，按 Intel 的建议最多重试 10 次
+    (0..10)
+        .any(|_| unsafe { rdrand(&mut value) } == 1)
+        .then_some(value)
+}
+
+/// 从 CPU 的随机数指令读取 64 位随机数，CPU 不支持或暂时无法给出时返回 None
+#[cfg(target_arch = "aarch64")]
+pub(crate) fn hardware_u64() -> Option<u64> {
+    use core::arch::asm;
+
+    let isar0: u64;
+    unsafe { asm!("mrs {}, ID_AA64ISAR0_EL1", out(reg) isar0) };
+    // ID_AA64ISAR0_EL1.RNDR[63:60] 为 0 时不支持 RNDR
+    if isar0 >> 60 == 0 {
+        return None;
+    }
+    for _ in 0..10 {
+        let value: u64;
+        let nzcv: u64;
+        // RNDR，无法给出随机数时置位 Z 标志
+        unsafe {
+            asm!(
+                "mrs {value}, s3_3_c2_c4_0",
+                "mrs {nzcv}, nzcv",
+                value = out(reg) value,
+                nzcv = out(reg) nzcv,
+            )
+        };
+        if nzcv & (1 << 30) == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// riscv 的 Zkr 扩展并不普遍，访问不存在的 seed CSR 会触发非法指令异常，因此不使用
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub(crate) fn hardware_u64() -> Option<u64> {
+    None
+}
+
+/// 时钟抖动
+///
+/// 反复测量一段访存计算的耗时，耗时受缓存、流水线与中断的影响而变化。
+/// 只有耗时与前两次测量的变化都不为 0 时才计入 1 比特的熵，时钟精度不足时逐渐加长计算。
+/// 总是读取硬件时钟，启用 `virtual-clock` 时系统时钟不会自行前进
+pub(crate) struct Jitter {
+    work: [u8; 64],
+    rounds: usize,
+    last_delta: u64,
+    last_diff: u64,
+}
+
+impl Jitter {
+    pub(crate) const fn new() -> Self {
+        Self {
+            work: [0; 64],
+            rounds: 0,
+            last_delta: 0,
+            last_diff: 0,
+        }
+    }
+
+    /// 进行一次测量，返回混入熵池的数据与其中计入的熵（比特）
+    pub(crate) fn sample(&mut self) -> (u64, usize) {
+        let iterations = 64 + self.rounds.min(1 << 16);
+        self.rounds += 1;
+        let start = HardwareClock.current_ticks();
+        for i in 0..iterations {
+            let j = (i * 7 + self.work[i % 64] as usize) % 64;
+            self.work[i % 64] = self.work[j].wrapping_add(i as u8).rotate_left(1);
+        }
+        let end = HardwareClock.current_ticks();
+        let delta = end.wrapping_sub(start);
+        let diff = delta.abs_diff(self.last_delta);
+        let entropy_bits = usize::from(diff != 0 && diff.abs_diff(self.last_diff) != 0);
+        self.last_delta = delta;
+        self.last_diff = diff;
+        (delta ^ end.rotate_left(32), entropy_bits)
+    }
+}
@@ -120,8 +120,20 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
         option_env!("AX_LOG").unwrap_or(""),
     );
 
+    let cmdline = axhal::platform::cmdline();
+    if let Some(level) = cmdline.get("loglevel") {
+        axlog::set_console_loglevel(level);
+    }
+
     info!("Logging is enabled.");
     info!("Primary CPU {} started, dtb = {:#x}.", cpu_id, dtb);
+    info!("Kernel command line: {}", cmdline.as_str());
+    if let Some(console) = cmdline.get("console") {
+        check_console(console);
+    }
+    if let Some(epoch) = cmdline.get("epoch") {
+        set_epoch(epoch);
+    }
     info!("Platform name {}.", axhal::platform_name());
 
     info!("Found physcial memory regions:");
@@ -159,7 +171,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        axfs::init_filesystems_with_root(
+            all_devices.block,
+            cmdline.get("root"),
+            cmdline.get("rootfstype"),
+        );
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
@@ -180,10 +196,51 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) {
         init_tls();
     }
 
+    #[cfg(feature = "monolithic")]
+    {
+        info!("Initialize random number generator...");
+        axrandom::init();
+    }
+
     info!("Primary CPU {} init OK.", cpu_id);
     INITED_CPUS.fetch_add(1, Ordering::Relaxed);
 }
 
+/// The name of the serial console on each architecture. `console=` may use
+/// either the Linux name of the UART or `ttyS0`.
+const SERIAL_CONSOLES: &[&str] = &["ttyS0", "ttyAMA0", "hvc0"];
+
+/// Checks the device selected by `console=name[,options]`.
+///
+/// The serial port is the only console device, so other devices are reported
+/// and output stays on the serial port.
+fn check_console(console: &str) {
+    let name = console.split(',').next().unwrap_or_default();
+    if SERIAL_CONSOLES.contains(&name) {
+        info!("Console: {}", name);
+    } else {
+        warn!("Console {} is not available, using ttyS0.", name);
+    }
+}
+
+/// Sets the wall clock from `epoch=secs`, the current time in seconds since
+/// the Unix epoch.
+///
+/// Without it, the wall clock starts from the Unix epoch at boot.
+fn set_epoch(epoch: &str) {
+    match epoch.parse::<u64>() {
+        Ok(secs) => {
+            let now = axhal::time::current_time_nanos();
+            let offset = secs
+                .saturating_mul(axhal::time::NANOS_PER_SEC)
+                .saturating_sub(now);
+            axhal::time::set_epoch_offset_nanos(offset);
+            info!("Wall clock set to {} seconds since the epoch.", secs);
+        }
+        Err(_) => warn!("Invalid epoch {}, ignored.", epoch),
+    }
+}
+
 /// exit the main task
 pub fn exit_main() {
     #[cfg(feature = "multitask")]
@@ -198,10 +255,20 @@ pub fn exit_main() {
 cfg_if::cfg_if! {
     if #[cfg(feature = "paging")] {
         use axhal::paging::PageTable;
+        #[cfg(not(feature = "monolithic"))]
         use lazy_init::LazyInit;
         /// The kernel page table.
+        #[cfg(not(feature = "monolithic"))]
         pub static KERNEL_PAGE_TABLE: LazyInit<PageTable> = LazyInit::new();
 
+        /// The root physical address of the kernel page table.
+        pub fn kernel_page_table_root() -> axhal::mem::PhysAddr {
+            #[cfg(feature = "monolithic")]
+            return axmem::kernel_page_table_root();
+            #[cfg(not(feature = "monolithic"))]
+            return KERNEL_PAGE_TABLE.root_paddr();
+        }
+
         fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
             use axhal::mem::{memory_regions, phys_to_virt};
             if axhal::cpu::this_cpu_is_bsp() {
@@ -235,37 +302,52 @@ cfg_if::cfg_if! {
                         true,
                     ).unwrap();
                 }
+                // 用户地址空间需要共享内核页表，因此交由 axmem 管理
+                #[cfg(feature = "monolithic")]
+                axmem::init_kernel_page_table(kernel_page_table);
+                #[cfg(not(feature = "monolithic"))]
                 KERNEL_PAGE_TABLE.init_by(kernel_page_table);
             }
 
-            unsafe { axhal::arch::write_page_table_root(KERNEL_PAGE_TABLE.root_paddr()) };
+            unsafe { axhal::arch::write_page_table_root(kernel_page_table_root()) };
             Ok(())
         }
     }
 }
 #[cfg(feature = "irq")]
 fn init_interrupt() {
-    use axhal::time::TIMER_IRQ_NUM;
+    use axhal::time::{TimeValue, TIMER_IRQ_NUM};
 
     // Setup timer interrupt handler
-    const PERIODIC_INTERVAL_NANOS: u64 =
-        axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+    const PERIODIC_INTERVAL: TimeValue =
+        TimeValue::from_nanos(axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64);
 
+    /// The next periodic tick of this CPU, in nanoseconds
     #[percpu::def_percpu]
     static NEXT_DEADLINE: u64 = 0;
 
     fn update_timer() {
-        let now_ns = axhal::time::current_time_nanos();
+        let now = axhal::time::current_time();
         // Safety: we have disabled preemption in IRQ handler.
-        let mut deadline = unsafe { NEXT_DEADLINE.read_current_raw() };
-        if now_ns >= deadline {
-            deadline = now_ns + PERIODIC_INTERVAL_NANOS;
+        let mut deadline = TimeValue::from_nanos(unsafe { NEXT_DEADLINE.read_current_raw() });
+        if now >= deadline {
+            deadline = now + PERIODIC_INTERVAL;
         }
-        unsafe { NEXT_DEADLINE.write_current_raw(deadline + PERIODIC_INTERVAL_NANOS) };
+        let next = axhal::time::time_to_nanos(deadline + PERIODIC_INTERVAL);
+        unsafe { NEXT_DEADLINE.write_current_raw(next) };
+        // The task manager may arm the timer earlier for its timed events
+        #[cfg(feature = "multitask")]
+        axtask::set_tick_deadline(deadline);
+        #[cfg(not(feature = "multitask"))]
         axhal::time::set_oneshot_timer(deadline);
     }
 
     axhal::irq::register_handler(TIMER_IRQ_NUM, || {
+        // An interrupt before the periodic tick only expires timed events
+        #[cfg(feature = "multitask")]
+        if axtask::on_timer_event() {
+            return;
+        }
         update_timer();
         #[cfg(feature = "multitask")]
         axtask::on_timer_tick();
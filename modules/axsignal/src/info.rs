@@ -2,9 +2,19 @@
 //!
 //! 错误信息：详细定义见 `https://man7.org/linux/man-pages/man2/rt_sigaction.2.html`
 
+/// 由 tkill 等发出的信号的 si_code
+pub const SI_TKILL: i32 = -6;
+/// 内核发出的信号的 si_code
+pub const SI_KERNEL: i32 = 0x80;
+
 /// The information of the signal
 ///
 /// When the `SigAction` specifies that it needs information, it will return it to the user
+///
+/// 布局与 64 位 Linux 的 `siginfo_t` 一致，共 128 字节。目前只填写 SIGIO 等 I/O 就绪信号
+/// 使用的 si_band 与 si_fd，其余联合体成员均为 0
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
 pub struct SigInfo {
     /// The signal number
     pub si_signo: i32,
@@ -12,6 +22,14 @@ pub struct SigInfo {
     pub si_errno: i32,
     /// The code of the signal
     pub si_code: i32,
+    /// 联合体按 8 字节对齐
+    _pad: i32,
+    /// I/O 就绪信号中发生的事件，即 poll 的事件位
+    pub si_band: i64,
+    /// I/O 就绪信号对应的文件描述符
+    pub si_fd: i32,
+    /// 联合体中其余的部分
+    _rest: [i32; 25],
 }
 
 impl Default for SigInfo {
@@ -19,7 +37,31 @@ impl Default for SigInfo {
         Self {
             si_signo: 0,
             si_errno: 0,
-            si_code: -6, // SI_TKILL
+            si_code: SI_TKILL,
+            _pad: 0,
+            si_band: 0,
+            si_fd: 0,
+            _rest: [0; 25],
+        }
+    }
+}
+
+impl SigInfo {
+    /// 以给定的信号编号与 si_code 创建信息，其余成员为 0
+    pub fn new(si_signo: i32, si_code: i32) -> Self {
+        Self {
+            si_signo,
+            si_code,
+            ..Default::default()
+        }
+    }
+
+    /// I/O 就绪信号的信息，`si_code` 为 POLL_IN 等事件码，`band` 为对应的 poll 事件位
+    pub fn poll(si_signo: i32, si_code: i32, band: i64, fd: i32) -> Self {
+        Self {
+            si_band: band,
+            si_fd: fd,
+            ..Self::new(si_signo, si_code)
         }
     }
 }
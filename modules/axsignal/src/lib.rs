@@ -53,12 +53,8 @@ impl SignalHandler {
             .filter(|&action| action.sa_handler != action::SIG_DFL)
     }
     /// 设置信号处理函数
-    ///
-    /// # Safety
-    ///
-    /// 传入的action必须是合法的指针
-    pub unsafe fn set_action(&mut self, sig_num: usize, action: *const SigAction) {
-        self.handlers[sig_num - 1] = Some(unsafe { *action });
+    pub fn set_action(&mut self, sig_num: usize, action: SigAction) {
+        self.handlers[sig_num - 1] = Some(action);
     }
 }
 
@@ -23,7 +23,7 @@ impl Default for SignalStack {
 }
 
 #[repr(C)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 /// The `mcontext` struct for the signal action
 pub struct MContext {
     fault_address: usize,
@@ -61,7 +61,7 @@ impl MContext {
 }
 
 #[repr(C)]
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 /// The user context saved for the signal action, which can be accessed by the signal handler
 pub struct SignalUserContext {
     flags: usize,
@@ -0,0 +1,182 @@
+//! A lock that spins briefly and then sleeps.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axtask::{SleepKind, WaitQueue};
+use kernel_guard::{BaseGuard, NoPreemptIrqSave};
+
+/// How many times to try the lock before going to sleep.
+const SPIN_LIMIT: usize = 128;
+
+/// An adaptive lock that disables kernel preemption and local IRQs while it
+/// is held, like [`SpinNoIrq`](crate::spin::SpinNoIrq).
+///
+/// The holder can not be descheduled, so the lock is only contended by tasks
+/// on other CPUs. A contending task first spins for a short while, since most
+/// critical sections are short. If the lock is still not acquired (e.g. the
+/// holder is running a long critical section), the task blocks on a wait
+/// queue instead of burning CPU time, and is woken up when the lock is
+/// released. Local IRQs are only disabled while the lock is actually held,
+/// never while sleeping.
+///
+/// A task that can not block, e.g. one that already holds another
+/// IRQ-disabling lock, keeps spinning like a [`SpinNoIrq`](crate::spin::SpinNoIrq).
+pub struct SpinWaitNoIrq<T: ?Sized> {
+    locked: AtomicBool,
+    wq: WaitQueue,
+    /// Number of failed attempts to take the lock by spinning.
+    spins: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides mutable data access for [`SpinWaitNoIrq`].
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct SpinWaitNoIrqGuard<'a, T: ?Sized + 'a> {
+    lock: &'a SpinWaitNoIrq<T>,
+    irq_state: <NoPreemptIrqSave as BaseGuard>::State,
+    data: *mut T,
+}
+
+unsafe impl<T: ?Sized + Send> Sync for SpinWaitNoIrq<T> {}
+unsafe impl<T: ?Sized + Send> Send for SpinWaitNoIrq<T> {}
+
+impl<T> SpinWaitNoIrq<T> {
+    /// Creates a new [`SpinWaitNoIrq`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+            spins: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`SpinWaitNoIrq`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> SpinWaitNoIrq<T> {
+    /// Returns `true` if the lock is currently held.
+    ///
+    /// The result may be out of date the instant it is returned, so it should
+    /// only be used as a heuristic.
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many times tasks failed to take the lock while spinning,
+    /// which shows how much CPU time was spent on contention.
+    pub fn spin_count(&self) -> usize {
+        self.spins.load(Ordering::Relaxed)
+    }
+
+    /// Locks the [`SpinWaitNoIrq`] and returns a guard that permits access to
+    /// the inner data.
+    pub fn lock(&self) -> SpinWaitNoIrqGuard<T> {
+        loop {
+            for _ in 0..SPIN_LIMIT {
+                if let Some(guard) = self.try_lock() {
+                    return guard;
+                }
+                self.spins.fetch_add(1, Ordering::Relaxed);
+                core::hint::spin_loop();
+            }
+            // Give up spinning and sleep until the lock looks unlocked, the
+            // holder releases it soon so a signal must not abort the sleep
+            if axtask::can_block() {
+                self.wq
+                    .wait_until_as(SleepKind::Uninterruptible, || !self.is_locked());
+            }
+        }
+    }
+
+    /// Try to lock this [`SpinWaitNoIrq`], returning a lock guard if
+    /// successful.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<SpinWaitNoIrqGuard<T>> {
+        let irq_state = NoPreemptIrqSave::acquire();
+        if self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            Some(SpinWaitNoIrqGuard {
+                lock: self,
+                irq_state,
+                data: self.data.get(),
+            })
+        } else {
+            NoPreemptIrqSave::release(irq_state);
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the lock mutably, no actual locking needs to
+    /// take place.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: ?Sized + Default> Default for SpinWaitNoIrq<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for SpinWaitNoIrq<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "SpinWaitNoIrq {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "SpinWaitNoIrq {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for SpinWaitNoIrqGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for SpinWaitNoIrqGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for SpinWaitNoIrqGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for SpinWaitNoIrqGuard<'a, T> {
+    /// The dropping of the guard will release the lock and wake up a sleeping
+    /// waiter, if any.
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+        NoPreemptIrqSave::release(self.irq_state);
+        self.lock.wq.notify_one(true);
+    }
+}
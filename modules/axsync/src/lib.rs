@@ -3,6 +3,7 @@
 //! Currently supported primitives:
 //!
 //! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`SpinWaitNoIrq`]: An IRQ-disabling lock that spins briefly and then sleeps.
 //! - mod [`spin`](spinlock): spin-locks.
 //!
 //! # Cargo Features
@@ -16,9 +17,15 @@
 
 pub use spinlock as spin;
 
+#[cfg(feature = "multitask")]
+mod adaptive;
 #[cfg(feature = "multitask")]
 mod mutex;
 
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::adaptive::{SpinWaitNoIrq, SpinWaitNoIrqGuard};
+
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
 pub use self::mutex::{Mutex, MutexGuard};
@@ -5,7 +5,7 @@ use core::fmt;
 use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicU64, Ordering};
 
-use axtask::{current, WaitQueue};
+use axtask::{current, SleepKind, WaitQueue};
 
 /// A mutual exclusion primitive useful for protecting shared data, similar to
 /// [`std::sync::Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html).
@@ -87,8 +87,10 @@ impl<T: ?Sized> Mutex<T> {
                         "{} tried to acquire mutex it already owns.",
                         current().id_name()
                     );
-                    // Wait until the lock looks unlocked before retrying
-                    self.wq.wait_until(|| !self.is_locked());
+                    // Wait until the lock looks unlocked before retrying,
+                    // signals do not abort the wait
+                    self.wq
+                        .wait_until_as(SleepKind::Uninterruptible, || !self.is_locked());
                 }
             }
         }
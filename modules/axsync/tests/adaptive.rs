@@ -0,0 +1,70 @@
+use axsync::SpinWaitNoIrq;
+use axtask as thread;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// How long the lock is held by a task on "another CPU".
+const HOLD_TIME: Duration = Duration::from_millis(50);
+const NUM_WAITERS: usize = 4;
+
+#[test]
+fn less_spinning_than_spinlock() {
+    thread::init_scheduler();
+
+    // A pure spinlock: waiters keep polling the lock word until it is free.
+    // It is held by an OS thread, which stands for a task on another CPU.
+    static SPIN_LOCKED: AtomicBool = AtomicBool::new(true);
+    static SPIN_POLLS: AtomicUsize = AtomicUsize::new(0);
+    static SPIN_DONE: AtomicUsize = AtomicUsize::new(0);
+
+    for _ in 0..NUM_WAITERS {
+        thread::spawn(|| {
+            while SPIN_LOCKED
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                SPIN_POLLS.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now();
+            }
+            SPIN_LOCKED.store(false, Ordering::Release);
+            SPIN_DONE.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    let holder = std::thread::spawn(|| {
+        std::thread::sleep(HOLD_TIME);
+        SPIN_LOCKED.store(false, Ordering::Release);
+    });
+    while SPIN_DONE.load(Ordering::Relaxed) < NUM_WAITERS {
+        thread::yield_now();
+    }
+    holder.join().unwrap();
+
+    // The adaptive lock under the same contention
+    static LOCK: SpinWaitNoIrq<usize> = SpinWaitNoIrq::new(0);
+    static LOCKED: AtomicBool = AtomicBool::new(false);
+    static ADAPTIVE_DONE: AtomicUsize = AtomicUsize::new(0);
+
+    let holder = std::thread::spawn(|| {
+        let guard = LOCK.lock();
+        LOCKED.store(true, Ordering::Release);
+        std::thread::sleep(HOLD_TIME);
+        drop(guard);
+    });
+    while !LOCKED.load(Ordering::Acquire) {
+        std::hint::spin_loop();
+    }
+    for _ in 0..NUM_WAITERS {
+        thread::spawn(|| {
+            *LOCK.lock() += 1;
+            ADAPTIVE_DONE.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    while ADAPTIVE_DONE.load(Ordering::Relaxed) < NUM_WAITERS {
+        thread::yield_now();
+    }
+    holder.join().unwrap();
+
+    assert_eq!(*LOCK.lock(), NUM_WAITERS);
+    // Waiters went to sleep instead of polling for the whole hold time
+    assert!(LOCK.spin_count() < SPIN_POLLS.load(Ordering::Relaxed));
+}
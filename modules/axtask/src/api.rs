@@ -1,6 +1,9 @@
 //! Task APIs for multi-task configuration.
 
-use alloc::{string::String, sync::Arc};
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+};
 #[cfg(feature = "monolithic")]
 use axhal::KERNEL_PROCESS_ID;
 use taskctx::TaskState;
@@ -16,6 +19,10 @@ pub use crate::wait_queue::WaitQueue;
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
 
+/// The weak reference type of a task, used by registries that should not
+/// keep the task alive.
+pub type WeakAxTaskRef = Weak<AxTask>;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "sched_rr")] {
         const MAX_TIME_SLICE: usize = 5;
@@ -46,6 +53,16 @@ pub fn current() -> CurrentTask {
     CurrentTask::get()
 }
 
+/// Whether the current task may block, i.e. local IRQs are enabled and it
+/// does not hold a lock that disables kernel preemption.
+pub fn can_block() -> bool {
+    #[cfg(feature = "preempt")]
+    if !current().can_preempt(0) {
+        return false;
+    }
+    axhal::arch::irqs_enabled()
+}
+
 /// Initializes the task scheduler (for the primary CPU).
 pub fn init_scheduler() {
     info!("Initialize scheduling...");
@@ -72,6 +89,31 @@ pub fn on_timer_tick() {
     RUN_QUEUE.lock().scheduler_timer_tick();
 }
 
+/// Sets the deadline of the next periodic tick.
+///
+/// The hardware timer is programmed to the earlier one of the tick and the
+/// earliest timed event.
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn set_tick_deadline(deadline: axhal::time::TimeValue) {
+    crate::timers::set_tick_deadline(deadline);
+}
+
+/// Handles a timer interrupt armed for a timed event before the next periodic
+/// tick: expires the due events and re-arms the hardware timer.
+///
+/// Returns `false` if the interrupt is a periodic tick, which should be handled
+/// by [`on_timer_tick`] instead.
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn on_timer_event() -> bool {
+    if !crate::timers::is_event_interrupt() {
+        return false;
+    }
+    crate::timers::on_event_interrupt();
+    true
+}
+
 #[cfg(feature = "preempt")]
 /// Checks if the current task should be preempted.
 pub fn current_check_preempt_pending() {
@@ -147,12 +189,25 @@ pub fn sleep(dur: core::time::Duration) {
 
 /// Current task is going to sleep, it will be woken up at the given deadline.
 ///
-/// If the feature `irq` is not enabled, it uses busy-wait instead.
+/// If the feature `irq` is not enabled, it uses busy-wait instead. With the
+/// virtual clock, it yields while waiting so that other tasks can advance the
+/// clock.
 pub fn sleep_until(deadline: axhal::time::TimeValue) {
     #[cfg(feature = "irq")]
-    RUN_QUEUE.lock().sleep_until(deadline);
-    #[cfg(not(feature = "irq"))]
+    {
+        RUN_QUEUE.lock().sleep_until(deadline);
+        let curr = crate::current();
+        if crate::schedule::in_timer_list(curr.as_task_ref()) {
+            // woken up before the deadline (e.g. by a signal)
+            crate::timers::cancel_alarm(curr.as_task_ref());
+        }
+    }
+    #[cfg(all(not(feature = "irq"), not(feature = "virtual-clock")))]
     axhal::time::busy_wait_until(deadline);
+    #[cfg(all(not(feature = "irq"), feature = "virtual-clock"))]
+    while axhal::time::current_time() < deadline {
+        yield_now();
+    }
 }
 
 /// Current task is going to sleep, it will be woken up when the given task exits.
@@ -165,14 +220,21 @@ pub fn join(task: &AxTaskRef) -> Option<i32> {
 }
 
 #[cfg(feature = "monolithic")]
-/// Current task is going to sleep. It will be woken up when the given task does exec syscall or exit.
-pub fn vfork_suspend(task: &AxTaskRef) {
-    get_wait_for_exit_queue(task).map(|wait_queue| {
-        wait_queue.wait_until(|| {
-            // If the given task does the exec syscall, it will be the leader of the new process.
-            task.is_leader() || task.state() == TaskState::Exited
+/// Current task is going to sleep. It will be woken up when the given task exits,
+/// or `done` returns true after [`wake_vfork_process`] is called on the task
+/// (e.g. the task does exec syscall).
+///
+/// The sleep is killable: only `SIGKILL` can abort it.
+pub fn vfork_suspend<F>(task: &AxTaskRef, done: F)
+where
+    F: Fn() -> bool,
+{
+    let curr = current();
+    if let Some(wait_queue) = get_wait_for_exit_queue(task) {
+        wait_queue.wait_until_as(crate::SleepKind::Killable, || {
+            done() || task.state() == TaskState::Exited || curr.take_woken_by_signal()
         });
-    });
+    }
 }
 
 #[cfg(feature = "monolithic")]
@@ -20,6 +20,8 @@
 //!   the `multitask` and `preempt` features if it is enabled.
 //! - `sched_cfs`: Use the [Completely Fair Scheduler][3]. It also enables the
 //!   the `multitask` and `preempt` features if it is enabled.
+//! - `sched_trace`: Record scheduler events and per-task scheduling
+//!   statistics, see [`sched_trace`]. Nothing is recorded without it.
 //!
 //! [1]: scheduler::FifoScheduler
 //! [2]: scheduler::RRScheduler
@@ -43,10 +45,23 @@ cfg_if::cfg_if! {
         mod api;
         mod wait_queue;
 
-        pub use taskctx::{SchedPolicy, SchedStatus,TaskState};
+        pub use taskctx::{SchedPolicy, SchedStatus, SleepKind, TaskState};
+        #[cfg(feature = "leak_check")]
+        pub use taskctx::live_task_count;
 
         #[cfg(feature = "irq")]
         mod timers;
+        #[cfg(feature = "irq")]
+        pub use timers::{cancel_timer, set_timer, TimerHandle};
+
+        #[cfg(feature = "virtual-clock")]
+        pub mod time_test;
+
+        #[cfg(feature = "sched_trace")]
+        pub mod sched_trace;
+
+        #[cfg(test)]
+        mod tests;
 
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
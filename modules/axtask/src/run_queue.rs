@@ -6,7 +6,7 @@ use axhal::KERNEL_PROCESS_ID;
 use lazy_init::LazyInit;
 use scheduler::BaseScheduler;
 use spinlock::SpinNoIrq;
-use taskctx::TaskState;
+use taskctx::{SleepKind, TaskState};
 
 use crate::schedule::notify_wait_for_exit;
 use crate::task::{new_init_task, new_task, CurrentTask};
@@ -52,6 +52,8 @@ impl AxRunQueue {
     pub fn add_task(&mut self, task: AxTaskRef) {
         debug!("task spawn: {}", task.id_name());
         assert!(task.is_ready());
+        #[cfg(feature = "sched_trace")]
+        crate::sched_trace::on_enqueue(&task);
         self.scheduler.add_task(task);
     }
 
@@ -114,6 +116,8 @@ impl AxRunQueue {
             // curr.notify_exit(exit_code, self);
             notify_wait_for_exit(curr.as_task_ref(), self);
             EXITED_TASKS.lock().push_back(curr.clone());
+            // 至少由当前 CPU 与 EXITED_TASKS 各持有一份引用，保证切换完成前不会被回收
+            debug_assert!(Arc::strong_count(curr.as_task_ref()) >= 2);
             WAIT_FOR_EXIT.notify_one_locked(false, self);
             self.resched(false);
         }
@@ -148,6 +152,7 @@ impl AxRunQueue {
         #[cfg(feature = "preempt")]
         assert!(curr.can_preempt(1));
 
+        curr.set_woken_by_signal(false);
         curr.set_state(TaskState::Blocked);
         wait_queue_push(curr.clone());
         self.resched(false);
@@ -157,6 +162,9 @@ impl AxRunQueue {
         debug!("task unblock: {}", task.id_name());
         if task.is_blocked() {
             task.set_state(TaskState::Ready);
+            task.set_sleep_kind(SleepKind::Interruptible);
+            #[cfg(feature = "sched_trace")]
+            crate::sched_trace::on_wakeup(&task);
             self.scheduler.add_task(task); // TODO: priority
             if resched {
                 #[cfg(feature = "preempt")]
@@ -186,54 +194,66 @@ impl AxRunQueue {
     /// slice, otherwise reset it.
     fn resched(&mut self, preempt: bool) {
         let prev = crate::current();
+        #[cfg(feature = "sched_trace")]
+        let reason = crate::sched_trace::SwitchReason::new(prev.state(), preempt);
         if prev.is_running() {
             prev.set_state(TaskState::Ready);
             if !prev.is_idle() {
+                #[cfg(feature = "sched_trace")]
+                crate::sched_trace::on_enqueue(prev.as_task_ref());
                 self.scheduler.put_prev_task(prev.clone(), preempt);
             }
         }
-        #[cfg(feature = "monolithic")]
-        {
-            use alloc::collections::BTreeSet;
-            use axhal::cpu::this_cpu_id;
-            let mut task_set = BTreeSet::new();
-            let next = loop {
-                let task = self.scheduler.pick_next_task();
-                if task.is_none() {
-                    break unsafe {
-                        // Safety: IRQs must be disabled at this time.
-                        IDLE_TASK.current_ref_raw().get_unchecked().clone()
-                    };
-                }
-                let task = task.unwrap();
-                // 原先队列有任务，但是全部不满足CPU适配集，则还是返回IDLE
-                if task_set.contains(&task.id().as_u64()) {
-                    break unsafe {
-                        // Safety: IRQs must be disabled at this time.
-                        IDLE_TASK.current_ref_raw().get_unchecked().clone()
-                    };
-                }
-                let mask = task.get_cpu_set();
-                let curr_cpu = this_cpu_id();
-                // 如果当前进程没有被 vfork 阻塞，弹出任务
-                if mask & (1 << curr_cpu) != 0 {
-                    break task;
-                }
-                task_set.insert(task.id().as_u64());
-                self.scheduler.put_prev_task(task, false);
-            };
-            self.switch_to(prev, next);
-        }
-        #[cfg(not(feature = "monolithic"))]
-        {
-            let next = self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
-                // Safety: IRQs must be disabled at this time.
-                IDLE_TASK.current_ref_raw().get_unchecked().clone()
-            });
-            self.switch_to(prev, next);
+        let next = self.pick_next_task();
+        #[cfg(feature = "sched_trace")]
+        crate::sched_trace::on_switch(prev.as_task_ref(), &next, reason);
+        self.switch_to(prev, next);
+    }
+
+    /// Picks the next task to run on this CPU, or the idle task if there is
+    /// none.
+    #[cfg(feature = "monolithic")]
+    fn pick_next_task(&mut self) -> AxTaskRef {
+        use alloc::collections::BTreeSet;
+        use axhal::cpu::this_cpu_id;
+        let mut task_set = BTreeSet::new();
+        loop {
+            let task = self.scheduler.pick_next_task();
+            if task.is_none() {
+                break unsafe {
+                    // Safety: IRQs must be disabled at this time.
+                    IDLE_TASK.current_ref_raw().get_unchecked().clone()
+                };
+            }
+            let task = task.unwrap();
+            // 原先队列有任务，但是全部不满足CPU适配集，则还是返回IDLE
+            if task_set.contains(&task.id().as_u64()) {
+                break unsafe {
+                    // Safety: IRQs must be disabled at this time.
+                    IDLE_TASK.current_ref_raw().get_unchecked().clone()
+                };
+            }
+            let mask = task.get_cpu_set();
+            let curr_cpu = this_cpu_id();
+            // 如果当前进程没有被 vfork 阻塞，弹出任务
+            if mask & (1 << curr_cpu) != 0 {
+                break task;
+            }
+            task_set.insert(task.id().as_u64());
+            self.scheduler.put_prev_task(task, false);
         }
     }
 
+    /// Picks the next task to run on this CPU, or the idle task if there is
+    /// none.
+    #[cfg(not(feature = "monolithic"))]
+    fn pick_next_task(&mut self) -> AxTaskRef {
+        self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
+            // Safety: IRQs must be disabled at this time.
+            IDLE_TASK.current_ref_raw().get_unchecked().clone()
+        })
+    }
+
     fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef) {
         trace!(
             "context switch: {} -> {}",
@@ -246,21 +266,24 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        assert!(
+            prev_task.kernel_stack_intact(),
+            "kernel stack overflow detected in {}",
+            prev_task.id_name()
+        );
         // 当任务进行切换时，更新两个任务的时间统计信息
         #[cfg(feature = "monolithic")]
         {
-            let current_timestamp = axhal::time::current_time_nanos() as usize;
-            next_task.time_stat_when_switch_to(current_timestamp);
-            prev_task.time_stat_when_switch_from(current_timestamp);
+            let now = axhal::time::current_time();
+            next_task.time_stat_when_switch_to(now);
+            prev_task.time_stat_when_switch_from(now);
         }
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
             let next_ctx_ptr = next_task.ctx_mut_ptr();
 
-            // The strong reference count of `prev_task` will be decremented by 1,
-            // but won't be dropped until `gc_entry()` is called.
-            assert!(Arc::strong_count(prev_task.as_task_ref()) > 1);
-            assert!(Arc::strong_count(&next_task) >= 1);
+            // The strong reference count of `prev_task` will be decremented by 1
+            // in `set_current`, but won't be dropped until `gc_entry()` is called.
             #[cfg(feature = "monolithic")]
             {
                 let page_table_token = *next_task.page_table_token.get();
@@ -0,0 +1,219 @@
+//! Scheduler tracing.
+//!
+//! Only available with the `sched_trace` feature. Every CPU keeps a ring of
+//! its latest scheduler events (wakeups, context switches and migrations),
+//! each stamped with the monotonic time. The run queue also keeps per-task
+//! statistics in [`SchedStat`]: the time spent runnable but waiting for a
+//! CPU, and the voluntary and involuntary context switch counts.
+//!
+//! Following Linux, a switch is voluntary when the previous task stops being
+//! runnable (it blocks or exits); being preempted or yielding while still
+//! runnable is involuntary.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use axhal::cpu::this_cpu_id;
+use axhal::time::current_time;
+use spinlock::SpinNoIrq;
+use taskctx::TaskState;
+
+pub use taskctx::SchedStat;
+
+use crate::AxTaskRef;
+
+/// Number of events kept per CPU, older events are overwritten.
+pub const SCHED_EVENT_RING_SIZE: usize = 256;
+
+/// Why the previous task gave up the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// Its time slice ran out.
+    Preempt,
+    /// It yielded while still runnable.
+    Yield,
+    /// It blocked, e.g. on a wait queue or in a sleep.
+    Block,
+    /// It exited.
+    Exit,
+}
+
+impl SwitchReason {
+    /// Derives the reason from the state of the previous task before it is
+    /// put back to the run queue.
+    pub(crate) fn new(prev_state: TaskState, preempt: bool) -> Self {
+        match prev_state {
+            TaskState::Running if preempt => Self::Preempt,
+            TaskState::Running => Self::Yield,
+            TaskState::Exited => Self::Exit,
+            _ => Self::Block,
+        }
+    }
+
+    /// Whether the switch counts as voluntary.
+    pub fn is_voluntary(self) -> bool {
+        matches!(self, Self::Block | Self::Exit)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Preempt => "preempt",
+            Self::Yield => "yield",
+            Self::Block => "block",
+            Self::Exit => "exit",
+        }
+    }
+}
+
+/// What happened in a [`SchedEvent`]. Tasks are identified by their ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedEventKind {
+    /// A blocked task was made ready.
+    Wakeup {
+        /// The woken task.
+        task: u64,
+    },
+    /// The CPU switched from `prev` to `next`.
+    Switch {
+        /// The task that gave up the CPU.
+        prev: u64,
+        /// The task that got the CPU.
+        next: u64,
+        /// Why `prev` gave up the CPU.
+        reason: SwitchReason,
+    },
+    /// A task runs on a different CPU than last time.
+    Migrate {
+        /// The migrated task.
+        task: u64,
+        /// The CPU the task ran on before.
+        from_cpu: usize,
+    },
+}
+
+/// A scheduler event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedEvent {
+    /// The monotonic time when the event happened.
+    pub time: Duration,
+    /// The CPU on which the event happened.
+    pub cpu: usize,
+    /// What happened.
+    pub kind: SchedEventKind,
+}
+
+impl fmt::Display for SchedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>5}.{:06}] cpu{} ",
+            self.time.as_secs(),
+            self.time.subsec_micros(),
+            self.cpu
+        )?;
+        match self.kind {
+            SchedEventKind::Wakeup { task } => write!(f, "wakeup  task {}", task),
+            SchedEventKind::Switch { prev, next, reason } => {
+                write!(f, "switch  {} -> {} ({})", prev, next, reason.as_str())
+            }
+            SchedEventKind::Migrate { task, from_cpu } => {
+                write!(f, "migrate task {} from cpu{}", task, from_cpu)
+            }
+        }
+    }
+}
+
+struct EventRing {
+    events: [Option<SchedEvent>; SCHED_EVENT_RING_SIZE],
+    /// Where the next event is written.
+    next: usize,
+}
+
+impl EventRing {
+    const fn new() -> Self {
+        Self {
+            events: [None; SCHED_EVENT_RING_SIZE],
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, event: SchedEvent) {
+        self.events[self.next] = Some(event);
+        self.next = (self.next + 1) % SCHED_EVENT_RING_SIZE;
+    }
+
+    /// Iterates over the events from the oldest to the newest.
+    fn iter(&self) -> impl Iterator<Item = &SchedEvent> {
+        let (newer, older) = self.events.split_at(self.next);
+        older.iter().chain(newer).flatten()
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_RING: SpinNoIrq<EventRing> = SpinNoIrq::new(EventRing::new());
+
+static EVENT_RINGS: [SpinNoIrq<EventRing>; axconfig::SMP] = [EMPTY_RING; axconfig::SMP];
+
+fn record(kind: SchedEventKind) {
+    let cpu = this_cpu_id();
+    EVENT_RINGS[cpu].lock().push(SchedEvent {
+        time: current_time(),
+        cpu,
+        kind,
+    });
+}
+
+/// `task` is put into the run queue.
+pub(crate) fn on_enqueue(task: &AxTaskRef) {
+    task.sched_stat().mark_ready(current_time());
+}
+
+/// The blocked `task` is made ready.
+pub(crate) fn on_wakeup(task: &AxTaskRef) {
+    record(SchedEventKind::Wakeup {
+        task: task.id().as_u64(),
+    });
+    on_enqueue(task);
+}
+
+/// `next` is picked to run after `prev`, which may be the same task.
+pub(crate) fn on_switch(prev: &AxTaskRef, next: &AxTaskRef, reason: SwitchReason) {
+    next.sched_stat().mark_running(current_time());
+    if alloc::sync::Arc::ptr_eq(prev, next) {
+        return;
+    }
+    prev.sched_stat().count_switch(reason.is_voluntary());
+    record(SchedEventKind::Switch {
+        prev: prev.id().as_u64(),
+        next: next.id().as_u64(),
+        reason,
+    });
+    if let Some(from_cpu) = next.sched_stat().migrate_to(this_cpu_id()) {
+        record(SchedEventKind::Migrate {
+            task: next.id().as_u64(),
+            from_cpu,
+        });
+    }
+}
+
+/// Returns the latest `n` scheduler events of all CPUs, from the oldest to
+/// the newest.
+pub fn sched_events(n: usize) -> Vec<SchedEvent> {
+    let mut events: Vec<SchedEvent> = EVENT_RINGS
+        .iter()
+        .flat_map(|ring| ring.lock().iter().copied().collect::<Vec<_>>())
+        .collect();
+    events.sort_by_key(|event| event.time);
+    let skip = events.len().saturating_sub(n);
+    events.split_off(skip)
+}
+
+/// Renders the latest `n` scheduler events of all CPUs, one per line.
+pub fn dump_sched_events(n: usize) -> String {
+    let mut out = String::new();
+    for event in sched_events(n) {
+        let _ = writeln!(out, "{}", event);
+    }
+    out
+}
@@ -44,7 +44,7 @@ pub fn new_task<F>(
 where
     F: FnOnce() + Send + 'static,
 {
-    use axhal::time::current_time_nanos;
+    use axhal::time::current_time;
 
     use crate::schedule::add_wait_for_exit_queue;
 
@@ -73,7 +73,7 @@ where
     // 设置 CPU 亲和集
     task.set_cpu_set((1 << axconfig::SMP) - 1, 1, axconfig::SMP);
 
-    task.reset_time_stat(current_time_nanos() as usize);
+    task.reset_time_stat(current_time());
 
     let axtask = Arc::new(AxTask::new(task));
     add_wait_for_exit_queue(&axtask);
@@ -128,12 +128,20 @@ pub(crate) fn new_init_task(name: String) -> AxTaskRef {
     axtask
 }
 /// A wrapper of [`AxTaskRef`] as the current task.
+///
+/// The per-CPU current task pointer owns exactly one strong reference of the
+/// task, which is taken in [`CurrentTask::init_current`] or
+/// [`CurrentTask::set_current`] and released when the task is switched out.
+/// A [`CurrentTask`] only borrows that reference, so it is wrapped in
+/// [`ManuallyDrop`] and never changes the reference count by itself.
 pub struct CurrentTask(ManuallyDrop<AxTaskRef>);
 
 impl CurrentTask {
     pub(crate) fn try_get() -> Option<Self> {
         let ptr: *const super::AxTask = taskctx::current_task_ptr();
         if !ptr.is_null() {
+            // SAFETY: the pointer is produced by `Arc::into_raw` in
+            // `install_current`, and its reference is still held by the CPU.
             Some(Self(unsafe { ManuallyDrop::new(AxTaskRef::from_raw(ptr)) }))
         } else {
             None
@@ -157,18 +165,33 @@ impl CurrentTask {
         Arc::ptr_eq(&self.0, other)
     }
 
+    /// Hands one strong reference of `task` over to the per-CPU pointer.
+    unsafe fn install_current(task: AxTaskRef) {
+        let ptr = Arc::into_raw(task);
+        taskctx::set_current_task_ptr(ptr);
+    }
+
     pub(crate) unsafe fn init_current(init_task: AxTaskRef) {
         #[cfg(feature = "tls")]
         axhal::arch::write_thread_pointer(init_task.get_tls_ptr());
-        let ptr = Arc::into_raw(init_task);
-        taskctx::set_current_task_ptr(ptr);
+        Self::install_current(init_task);
     }
 
     pub(crate) unsafe fn set_current(prev: Self, next: AxTaskRef) {
         let Self(arc) = prev;
+        // The reference held by the CPU is going to be released, but `prev`
+        // is still running on its own kernel stack. Someone else (the run
+        // queue, a wait queue or `EXITED_TASKS`) must keep it alive until
+        // the switch is completed, otherwise the stack would be freed under
+        // our feet.
+        assert!(
+            Arc::strong_count(&arc) > 1,
+            "task {} would be dropped while still running",
+            arc.id_name()
+        );
+        assert!(Arc::strong_count(&next) >= 1);
         ManuallyDrop::into_inner(arc); // `call Arc::drop()` to decrease prev task reference count.
-        let ptr = Arc::into_raw(next);
-        taskctx::set_current_task_ptr(ptr);
+        Self::install_current(next);
     }
 }
 
@@ -198,7 +221,7 @@ extern "C" fn task_entry() -> ! {
                     let kernel_sp = task.get_kernel_stack_top().unwrap();
                     // 切换页表已经在switch实现了
                     // 记得更新时间
-                    task.time_stat_from_kernel_to_user(axhal::time::current_time_nanos() as usize);
+                    task.time_stat_from_kernel_to_user(axhal::time::current_time());
                     axhal::arch::first_into_user(kernel_sp);
                 }
             }
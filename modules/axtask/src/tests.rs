@@ -1,4 +1,5 @@
 use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
 use std::sync::{Mutex, Once};
 
 use crate::{self as axtask, current, WaitQueue};
@@ -128,3 +129,182 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_sleep_virtual_clock() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 3;
+    const SLEEP_NANOS: u64 = 1_000_000_000;
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+    let start = axtask::time_test::now_nanos();
+    let mut tasks = Vec::with_capacity(NUM_TASKS);
+    for i in 0..NUM_TASKS {
+        // task i sleeps for (i + 1) seconds
+        tasks.push(axtask::spawn(move || {
+            axtask::sleep(std::time::Duration::from_nanos(
+                SLEEP_NANOS * (i as u64 + 1),
+            ));
+            WOKEN.fetch_add(1, Ordering::Relaxed);
+        }));
+    }
+    // let all tasks compute their deadlines before the clock moves
+    axtask::yield_now();
+
+    for i in 0..NUM_TASKS {
+        // no task wakes up before the clock reaches its deadline
+        axtask::time_test::advance(SLEEP_NANOS - 1);
+        axtask::yield_now();
+        assert_eq!(WOKEN.load(Ordering::Relaxed), i);
+
+        axtask::time_test::advance(1);
+        while WOKEN.load(Ordering::Relaxed) < i + 1 {
+            axtask::yield_now();
+        }
+        assert_eq!(WOKEN.load(Ordering::Relaxed), i + 1);
+    }
+    assert_eq!(
+        axtask::time_test::now_nanos() - start,
+        SLEEP_NANOS * NUM_TASKS as u64
+    );
+
+    for task in tasks {
+        task.join();
+    }
+}
+
+#[cfg(feature = "leak_check")]
+#[test]
+fn test_task_no_leak() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_ROUNDS: usize = 20;
+    const NUM_TASKS: usize = 10;
+    let base = axtask::live_task_count();
+
+    for _ in 0..NUM_ROUNDS {
+        let tasks: Vec<_> = (0..NUM_TASKS)
+            .map(|i| {
+                axtask::spawn(move || {
+                    axtask::yield_now();
+                    axtask::exit(i as _);
+                })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.join(), Some(i as _));
+        }
+    }
+
+    // 等待 gc 任务回收所有已退出的任务
+    while axtask::live_task_count() > base {
+        axtask::yield_now();
+    }
+    assert_eq!(axtask::live_task_count(), base);
+}
+
+#[cfg(feature = "sched_trace")]
+#[test]
+fn test_sched_trace_starved_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const STARVE_NANOS: u64 = 1_000_000_000;
+    const STARVE: Duration = Duration::from_nanos(STARVE_NANOS);
+    static RAN: AtomicUsize = AtomicUsize::new(0);
+
+    // the task is ready at once, but the main task keeps the CPU while the
+    // clock moves on
+    let starved = axtask::spawn(|| {
+        RAN.fetch_add(1, Ordering::Relaxed);
+        axtask::yield_now();
+    });
+    axtask::time_test::advance(STARVE_NANOS);
+    assert_eq!(RAN.load(Ordering::Relaxed), 0);
+    let voluntary = current().sched_stat().voluntary_switches();
+    starved.join();
+    assert_eq!(RAN.load(Ordering::Relaxed), 1);
+
+    let stat = starved.sched_stat();
+    assert!(stat.wait_time() >= STARVE);
+    assert!(stat.max_wait() >= STARVE);
+    assert!(stat.run_count() >= 1 && stat.max_wait() <= stat.wait_time());
+    // yielding while runnable is involuntary, exiting is voluntary
+    assert!(stat.involuntary_switches() >= 1);
+    assert!(stat.voluntary_switches() >= 1);
+    // the main task blocked in `join`
+    assert!(current().sched_stat().voluntary_switches() > voluntary);
+
+    // a task that gets the CPU while the clock stands still never waits
+    let prompt = axtask::spawn(|| {});
+    prompt.join();
+    assert_eq!(prompt.sched_stat().wait_time(), Duration::ZERO);
+    assert!(prompt.sched_stat().run_count() >= 1);
+
+    let starved_id = starved.id().as_u64();
+    let events = axtask::sched_trace::sched_events(usize::MAX);
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        axtask::sched_trace::SchedEventKind::Switch {
+            prev,
+            reason: axtask::sched_trace::SwitchReason::Yield,
+            ..
+        } if prev == starved_id
+    )));
+    let dump = axtask::sched_trace::dump_sched_events(8);
+    assert_eq!(dump.lines().count(), 8.min(events.len()));
+    assert!(dump.lines().all(|line| line.contains("cpu0")));
+}
+
+/// set in the child process spawned by `test_kstack_overflow_detected`
+const KSTACK_OVERFLOW_ENV: &str = "AXTASK_KSTACK_OVERFLOW_CHILD";
+
+/// Runs in a child process: a task overflows its kernel stack and then yields,
+/// so the canary check in `switch_to` panics and the process dies.
+#[test]
+#[ignore]
+fn kstack_overflow_child() {
+    if std::env::var_os(KSTACK_OVERFLOW_ENV).is_none() {
+        return;
+    }
+    INIT.call_once(axtask::init_scheduler);
+
+    const STACK_SIZE: usize = 0x4000;
+    axtask::spawn_raw(
+        || {
+            // clobber the lowest bytes of the stack, as a runaway recursion would
+            let base = current().get_kernel_stack_top().unwrap() - STACK_SIZE;
+            unsafe { (base as *mut u8).write_bytes(0, 16) };
+            axtask::yield_now();
+        },
+        "overflow".into(),
+        STACK_SIZE,
+    );
+    for _ in 0..10 {
+        axtask::yield_now();
+    }
+}
+
+#[test]
+fn test_kstack_overflow_detected() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--ignored",
+            "--exact",
+            "tests::kstack_overflow_child",
+            "--nocapture",
+        ])
+        .env(KSTACK_OVERFLOW_ENV, "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("kernel stack overflow detected in"),
+        "{}",
+        stderr
+    );
+}
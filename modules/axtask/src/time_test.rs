@@ -0,0 +1,19 @@
+//! Test API for driving the virtual clock.
+//!
+//! Only available with the `virtual-clock` feature, where the system clock is
+//! [`axhal::time::VIRTUAL_CLOCK`] and does not move on its own.
+
+/// Moves the virtual clock forward by `nanos` nanoseconds.
+///
+/// Timer events that become due are fired synchronously before returning, so
+/// tasks sleeping until a deadline that has been reached are already woken up.
+pub fn advance(nanos: u64) {
+    axhal::time::VIRTUAL_CLOCK.advance(nanos);
+    #[cfg(feature = "irq")]
+    crate::timers::check_events();
+}
+
+/// Returns the current time of the virtual clock in nanoseconds.
+pub fn now_nanos() -> u64 {
+    axhal::time::current_time_nanos()
+}
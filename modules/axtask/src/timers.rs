@@ -1,8 +1,17 @@
-use alloc::sync::Arc;
-use axhal::time::current_time;
+//! The timer queue shared by all timed events: task sleeps, wait timeouts and
+//! the callbacks armed by [`set_timer`].
+//!
+//! The hardware timer is always programmed to the earlier one of the next
+//! periodic tick and the earliest event, so an event does not have to wait for
+//! the next tick to expire. Interrupts that come before the tick only expire
+//! events, see [`is_event_interrupt`].
+
+use alloc::{boxed::Box, sync::Arc};
+use axhal::time::{current_time, time_to_nanos};
+use core::sync::atomic::{AtomicU64, Ordering};
 use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
-use timer_list::{TimeValue, TimerEvent, TimerList};
+use timer_list::{TimeValue, TimerEvent, TimerId, TimerList};
 
 use crate::{
     schedule::{add_to_timer_list, remove_from_timer_list},
@@ -10,31 +19,117 @@ use crate::{
 };
 
 // TODO: per-CPU
-static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
+static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<AxTimerEvent>>> = LazyInit::new();
+
+/// The deadline of the next periodic tick, in nanoseconds.
+static TICK_DEADLINE: AtomicU64 = AtomicU64::new(0);
 
-struct TaskWakeupEvent(AxTaskRef);
+/// The deadline programmed in the hardware timer, in nanoseconds.
+static ARMED_DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+enum AxTimerEvent {
+    /// Wakes up a sleeping task, or a task waiting with a timeout.
+    TaskWakeup(AxTaskRef),
+    /// A callback armed by [`set_timer`].
+    Callback(Box<dyn FnOnce(TimeValue) + Send>),
+}
 
-impl TimerEvent for TaskWakeupEvent {
-    fn callback(self, _now: TimeValue) {
-        let mut rq = RUN_QUEUE.lock();
-        // self.0.set_in_timer_list(false);
-        remove_from_timer_list(&self.0);
-        rq.unblock_task(self.0, true);
+impl TimerEvent for AxTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::TaskWakeup(task) => {
+                let mut rq = RUN_QUEUE.lock();
+                // task.set_in_timer_list(false);
+                remove_from_timer_list(&task);
+                rq.unblock_task(task, true);
+            }
+            Self::Callback(f) => f(now),
+        }
     }
 }
 
+/// The handle of a timer armed by [`set_timer`].
+#[derive(Debug)]
+pub struct TimerHandle(TimerId);
+
+impl TimerHandle {
+    /// The deadline of the timer.
+    pub fn deadline(&self) -> TimeValue {
+        self.0.deadline()
+    }
+}
+
+/// Programs the hardware timer to the earlier one of the next tick and the
+/// earliest event.
+fn arm(timers: &TimerList<AxTimerEvent>) {
+    let tick = TICK_DEADLINE.load(Ordering::Acquire);
+    // With the virtual clock, events only expire when the clock is advanced,
+    // so the hardware timer just keeps the periodic tick.
+    #[cfg(not(feature = "virtual-clock"))]
+    let deadline = timers
+        .next_deadline()
+        .map_or(tick, |deadline| tick.min(time_to_nanos(deadline)));
+    #[cfg(feature = "virtual-clock")]
+    let deadline = {
+        let _ = timers;
+        tick
+    };
+    ARMED_DEADLINE.store(deadline, Ordering::Release);
+    axhal::time::set_oneshot_timer(TimeValue::from_nanos(deadline));
+}
+
+/// Inserts an event, and re-arms the hardware timer if the event is now the
+/// earliest one.
+fn insert(
+    timers: &mut TimerList<AxTimerEvent>,
+    deadline: TimeValue,
+    event: AxTimerEvent,
+) -> TimerId {
+    let id = timers.set(deadline, event);
+    if time_to_nanos(deadline) < ARMED_DEADLINE.load(Ordering::Acquire) {
+        arm(timers);
+    }
+    id
+}
+
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     // task.set_in_timer_list(true);
     add_to_timer_list(&task);
-    timers.set(deadline, TaskWakeupEvent(task));
+    insert(&mut timers, deadline, AxTimerEvent::TaskWakeup(task));
 }
 
 pub fn cancel_alarm(task: &AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     // task.set_in_timer_list(false);
     remove_from_timer_list(task);
-    timers.cancel(|t| Arc::ptr_eq(&t.0, task));
+    timers.cancel(|t| matches!(t, AxTimerEvent::TaskWakeup(t) if Arc::ptr_eq(t, task)));
+}
+
+/// Arms a timer that calls `f` with the current time at `deadline`.
+///
+/// The callback runs in the timer interrupt handler with IRQs disabled, so it
+/// must be short and must not block. Heavier work should be handed over to a
+/// task, e.g. by waking it up.
+pub fn set_timer<F>(deadline: TimeValue, f: F) -> TimerHandle
+where
+    F: FnOnce(TimeValue) + Send + 'static,
+{
+    let mut timers = TIMER_LIST.lock();
+    TimerHandle(insert(
+        &mut timers,
+        deadline,
+        AxTimerEvent::Callback(Box::new(f)),
+    ))
+}
+
+/// Cancels a timer armed by [`set_timer`].
+///
+/// Returns `true` if the timer is canceled before expiring, in which case its
+/// callback will never be called. Returns `false` if the timer has already
+/// expired: its callback has been called or is running on another CPU.
+pub fn cancel_timer(handle: TimerHandle) -> bool {
+    TIMER_LIST.lock().cancel_id(handle.0).is_some()
 }
 
 pub fn check_events() {
@@ -49,6 +144,25 @@ pub fn check_events() {
     }
 }
 
+/// Sets the deadline of the next periodic tick, and programs the hardware timer.
+pub fn set_tick_deadline(deadline: TimeValue) {
+    let timers = TIMER_LIST.lock();
+    TICK_DEADLINE.store(time_to_nanos(deadline), Ordering::Release);
+    arm(&timers);
+}
+
+/// Whether the current timer interrupt is armed for an event before the next
+/// periodic tick.
+pub fn is_event_interrupt() -> bool {
+    ARMED_DEADLINE.load(Ordering::Acquire) < TICK_DEADLINE.load(Ordering::Acquire)
+}
+
+/// Expires the events of an event interrupt, and re-arms the hardware timer.
+pub fn on_event_interrupt() {
+    check_events();
+    arm(&TIMER_LIST.lock());
+}
+
 pub fn init() {
     TIMER_LIST.init_by(SpinNoIrq::new(TimerList::new()));
 }
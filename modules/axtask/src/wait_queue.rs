@@ -1,6 +1,7 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use spinlock::SpinRaw;
+use taskctx::SleepKind;
 
 use crate::{
     schedule::{add_to_wait_queue, in_wait_queue, remove_from_wait_queue},
@@ -86,18 +87,34 @@ impl WaitQueue {
     where
         F: Fn() -> bool,
     {
+        self.wait_until_as(SleepKind::Interruptible, condition)
+    }
+
+    /// Same as [`WaitQueue::wait_until`], but the task sleeps as the given
+    /// `kind`, which decides whether a signal can wake it up.
+    ///
+    /// A task woken up by a signal is marked by `set_woken_by_signal`, the
+    /// `condition` should check `take_woken_by_signal` if the sleep can be
+    /// aborted.
+    pub fn wait_until_as<F>(&self, kind: SleepKind, condition: F)
+    where
+        F: Fn() -> bool,
+    {
+        let curr = crate::current();
         loop {
             let mut rq = RUN_QUEUE.lock();
             if condition() {
                 break;
             }
+            // `unblock_task` resets the kind, so set it again for every sleep
+            curr.set_sleep_kind(kind);
             rq.block_current(|task| {
                 // task.set_in_wait_queue(true);
                 add_to_wait_queue(&task);
                 self.queue.lock().push_back(task);
             });
         }
-        self.cancel_events(crate::current());
+        self.cancel_events(curr);
     }
 
     /// Blocks the current task and put it into the wait queue, until other tasks
@@ -63,6 +63,10 @@ ifeq ($(GRAPHIC), n)
   qemu_args-y += -nographic
 endif
 
+ifneq ($(CMDLINE),)
+  qemu_args-y += -append "$(CMDLINE)"
+endif
+
 ifeq ($(QEMU_LOG), y)
   qemu_args-y += -D qemu.log -d in_asm,int,mmu,pcall,cpu_reset,guest_errors
 endif
@@ -0,0 +1,66 @@
+#!/bin/bash
+# 以不同的内核命令行启动 monolithic_userboot，检查选中的 init 与日志级别
+
+ROOT=$(realpath $(dirname $0))/../../
+APP=$ROOT/apps/monolithic_userboot
+TIMEOUT=60s
+EXIT_STATUS=0
+
+RED_C="\x1b[31;1m"
+GREEN_C="\x1b[32;1m"
+CYAN_C="\x1b[36;1m"
+BLOD_C="\x1b[1m"
+END_C="\x1b[0m"
+
+if [ -z "$ARCH" ]; then
+    ARCH=x86_64
+fi
+
+ARGS="ARCH=$ARCH LOG=info ACCEL=n"
+
+# test_one <cmdline> <pattern>...
+# 输出中应当包含所有的 pattern，以 '!' 开头的 pattern 则不应出现
+function test_one() {
+    local cmdline=$1
+    shift
+    local actual="$APP/actual.out"
+    echo -ne "    run with \"${BLOD_C}$cmdline${END_C}\": "
+    timeout --foreground $TIMEOUT make -C "$ROOT" A="$APP" $ARGS CMDLINE="$cmdline" justrun > "$actual" 2>&1
+    for pattern in "$@"; do
+        if [[ $pattern == !* ]]; then
+            grep -q -- "${pattern:1}" "$actual" || continue
+        else
+            grep -q -- "$pattern" "$actual" && continue
+        fi
+        echo -e "${RED_C}failed!${END_C} (pattern \"$pattern\")"
+        cat "$actual"
+        EXIT_STATUS=1
+        return
+    done
+    echo -e "${GREEN_C}passed!${END_C}"
+}
+
+sh $ROOT/build_img.sh $ARCH
+make -C "$ROOT" A="$APP" $ARGS > /dev/null 2>&1 || { echo "build failed"; exit 1; }
+
+echo -e "${CYAN_C}Testing${END_C} kernel command line:"
+# 默认的 init 链最终落到 /bin/sh
+test_one "-- -c exit" \
+    "Kernel command line: -- -c exit" \
+    "Run /bin/sh as init process" \
+    "Init /bin/sh exited with code 0"
+# init= 指定 init 程序，未知参数传给 init
+test_one "init=/bin/busybox cmdline_env=ok -- env" \
+    "Run /bin/busybox as init process" \
+    "cmdline_env=ok" \
+    "!Run /bin/sh as init process"
+# loglevel=4 只输出错误日志
+test_one "init=/bin/busybox loglevel=4 -- true" \
+    "Run /bin/busybox as init process" \
+    "!Kernel command line"
+# 指定的 init 无法运行时内核 panic
+test_one "init=/no/such/init" \
+    "Requested init /no/such/init failed"
+
+echo -e "test script exited with: $EXIT_STATUS"
+exit $EXIT_STATUS
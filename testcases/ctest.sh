@@ -0,0 +1,25 @@
+#!/bin/sh
+# 将 apps/c/testcase_list 中的 C 测例静态编译到 testcases/ctest 目录，
+# 之后在根目录执行 ./build_img.sh -m <arch> -file ctest 即可生成镜像。
+# 需要对应架构的 musl 交叉编译器，可通过 CC 指定，默认为 <arch>-linux-musl-gcc
+ARCH=${ARCH:-x86_64}
+CC=${CC:-$ARCH-linux-musl-gcc}
+ROOT=$(realpath $(dirname $0))/..
+OUT=$ROOT/testcases/ctest
+
+# 测例通过 busybox sh 运行，从该架构默认的测例目录中复制 busybox
+case $ARCH in
+	riscv64) BUSYBOX=$ROOT/testcases/sdcard/busybox ;;
+	x86_64) BUSYBOX=$ROOT/testcases/testsuits-x86_64-linux-musl/busybox ;;
+	aarch64) BUSYBOX=$ROOT/testcases/aarch64/busybox ;;
+	*) echo "Unknown architecture: $ARCH"; exit 1 ;;
+esac
+
+rm -rf $OUT
+mkdir -p $OUT
+for name in $(grep -v '^#' $ROOT/apps/c/testcase_list); do
+	echo "Building $name"
+	$CC -static -O2 -Wall -I $ROOT/apps/c/include -o $OUT/$name $ROOT/apps/c/$name/$name.c -lpthread || exit 1
+done
+cp $ROOT/apps/c/testcase_list $ROOT/apps/c/ctest_testcode.sh $OUT/
+cp $BUSYBOX $OUT/busybox || exit 1
@@ -1,14 +1,25 @@
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use linux_syscall_api::read_file;
+
+/// Kernel parameters handled by the kernel itself, which are not passed to init
+const KERNEL_PARAMS: &[&str] = &["init", "root", "rootfstype", "console", "loglevel"];
+
+/// Init programs tried in order when `init=` is not given
+const DEFAULT_INIT: &[&str] = &["/init", "/bin/sh"];
+
+/// Environment variables every process started by the kernel gets, as Linux gives them to init
+const DEFAULT_ENVS: &[&str] = &["HOME=/", "TERM=linux", "PATH=/usr/sbin:/usr/bin:/sbin:/bin"];
+
 /// To get the environment variables of the application
 ///
-/// # TODO
-/// Now the environment variables are hard coded, we need to read the file "/etc/environment" to get the environment variables
+/// Starts from [`DEFAULT_ENVS`] and the variables the testcases rely on, then applies the
+/// `KEY=value` lines of "/etc/environment" if it exists. Blank lines and lines starting with
+/// `#` are skipped.
 pub fn get_envs() -> Vec<String> {
     // Const string for environment variables
-    let mut envs:Vec<String> = vec![
+    let mut envs: Vec<String> = vec![
         "SHLVL=1".into(),
         "PWD=/".into(),
         "GCC_EXEC_PREFIX=/riscv64-linux-musl-native/bin/../lib/gcc/".into(),
@@ -19,23 +30,88 @@ pub fn get_envs() -> Vec<String> {
         "LD_LIBRARY_PATH=/lib/".into(),
         "LD_DEBUG=files".into(),
     ];
-    // read the file "/etc/environment"
-    // if exist, then append the content to envs
-    // else set the environment variable to default value
+    for env in DEFAULT_ENVS {
+        set_env(&mut envs, env);
+    }
     if let Some(environment_vars) = read_file("/etc/environment") {
-        envs.push(environment_vars);
-    } else {
-        envs.push("PATH=/usr/sbin:/usr/bin:/sbin:/bin".into());
+        for line in environment_vars.lines().map(str::trim) {
+            if line.contains('=') && !line.starts_with('#') {
+                set_env(&mut envs, line);
+            }
+        }
     }
     envs
 }
+
+/// Sets the `KEY=value` entry `env` in `envs`, replacing the earlier value of `KEY` if any
+///
+/// libc's getenv returns the first match, so a later setting must not just be appended.
+fn set_env(envs: &mut Vec<String>, env: &str) {
+    let key = env.split_once('=').map_or(env, |(key, _)| key);
+    match envs.iter_mut().find(|old| {
+        old.split_once('=')
+            .is_some_and(|(old_key, _)| old_key == key)
+    }) {
+        Some(old) => *old = env.to_string(),
+        None => envs.push(env.to_string()),
+    }
+}
+
 /// To run a testcase with the given name, which will be used in initproc
 ///
-/// The environment variables are hard coded, we need to read the file "/etc/environment" to get the environment variables
+/// The environment variables come from [`get_envs`]
 pub fn run_testcase(testcase: &str) {
     linux_syscall_api::run_testcase(testcase, get_envs());
 }
 
+/// To run the init process chosen by the kernel command line, and wait for it to exit
+///
+/// The init program is given by `init=`, otherwise the programs in [`DEFAULT_INIT`] are tried in order.
+/// As Linux does, unknown `key=value` parameters are passed to init as environment variables,
+/// overriding the ones from [`get_envs`], other unknown parameters and everything after `--` are passed as arguments.
+///
+/// # Panics
+///
+/// Panics if no init program can be run.
+pub fn run_init() {
+    let cmdline = axhal::platform::cmdline();
+    let mut args = Vec::new();
+    let mut envs = get_envs();
+    for (key, value) in cmdline.params() {
+        // parameters containing '.' belong to kernel modules
+        if KERNEL_PARAMS.contains(&key) || key.contains('.') {
+            continue;
+        }
+        match value {
+            Some(value) => set_env(&mut envs, &alloc::format!("{}={}", key, value)),
+            None => args.push(key.to_string()),
+        }
+    }
+    args.extend(cmdline.init_args().map(String::from));
+
+    let requested = cmdline.get("init");
+    let candidates = match requested {
+        Some(init) => vec![init],
+        None => DEFAULT_INIT.to_vec(),
+    };
+    for init in candidates {
+        let mut init_args = vec![init.to_string()];
+        init_args.extend(args.iter().cloned());
+        axlog::ax_println!("Run {} as init process", init);
+        match linux_syscall_api::run_process(init_args, envs.clone()) {
+            Ok(exit_code) => {
+                axlog::ax_println!("Init {} exited with code {}", init, exit_code);
+                return;
+            }
+            Err(err) => axlog::warn!("Failed to execute {}: {:?}", init, err),
+        }
+    }
+    match requested {
+        Some(init) => panic!("Requested init {} failed", init),
+        None => panic!("No working init found. Try passing init= option to kernel."),
+    }
+}
+
 /// To print a string to the console
 pub fn println(s: &str) {
     axlog::ax_println!("{}", s);
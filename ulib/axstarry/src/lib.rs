@@ -6,7 +6,7 @@ extern crate arch_boot;
 mod file;
 pub use file::fs_init;
 mod api;
-pub use api::{println, run_testcase};
+pub use api::{println, run_init, run_testcase};
 #[cfg(feature = "ext4fs")]
 #[allow(unused_imports)]
 use axlibc::ax_open;